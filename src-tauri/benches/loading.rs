@@ -0,0 +1,73 @@
+//! Benchmarks for loading run files and aggregating stats
+//!
+//! Run with `cargo bench --features test-utils`. All data comes from
+//! `generate_runs` with a fixed seed, written out through the `test-utils`
+//! fixture builders, so results are comparable between machines' runs of the
+//! same commit. Save a baseline before a change with
+//! `-- --save-baseline before` and compare after it with `-- --baseline before`.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sts_stat_viewer_lib::sts::{
+    self, calculate_character_stats, fixtures, generate_runs, RunFileBuilder, RunsDirFixture,
+};
+
+const SEED: u64 = 42;
+
+/// Directory sizes for the load benchmarks
+const DIR_SIZES: [usize; 2] = [1_000, 10_000];
+
+/// Runs aggregated by the stats benchmark
+const STATS_RUNS: usize = 10_000;
+
+fn parse_single_file(c: &mut Criterion) {
+    // A full-length victory, the largest kind of run file
+    let run = generate_runs(SEED, 100)
+        .into_iter()
+        .max_by_key(|r| (r.victory, r.floor_reached))
+        .expect("generated runs");
+    let character = run.character.known().expect("base game character");
+    let fixture = RunsDirFixture::new();
+    let path = fixture.add(&RunFileBuilder::from_run(&run));
+
+    c.bench_function("parse_run_file", |b| {
+        b.iter(|| fixtures::parse_run(black_box(&path), character))
+    });
+}
+
+fn load_runs_dir(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_all_runs");
+    group.sample_size(10);
+    for count in DIR_SIZES {
+        let fixture = RunsDirFixture::generated(SEED, count);
+        group.throughput(Throughput::Elements(count as u64));
+
+        // Every file parsed, as on startup
+        group.bench_with_input(BenchmarkId::new("cold", count), &fixture, |b, fixture| {
+            b.iter(|| fixture.load())
+        });
+
+        // Nothing changed since the last load, as on most API requests
+        sts::set_custom_runs_path(Some(fixture.path().to_path_buf()));
+        assert_eq!(sts::load_all_runs().len(), count);
+        group.bench_function(BenchmarkId::new("cached", count), |b| {
+            b.iter(sts::load_all_runs)
+        });
+        sts::set_custom_runs_path(None);
+    }
+    group.finish();
+}
+
+fn character_stats(c: &mut Criterion) {
+    let runs = generate_runs(SEED, STATS_RUNS);
+    let mut group = c.benchmark_group("calculate_character_stats");
+    group.throughput(Throughput::Elements(STATS_RUNS as u64));
+    group.bench_function(BenchmarkId::from_parameter(STATS_RUNS), |b| {
+        b.iter(|| calculate_character_stats(black_box(&runs)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_single_file, load_runs_dir, character_stats);
+criterion_main!(benches);
@@ -0,0 +1,333 @@
+//! Batch endpoint running several queries in one request
+//!
+//! Every sub-query of a batch is answered from the same set of loaded runs,
+//! so a dashboard asking for stats, a summary and a page of runs gets numbers
+//! that agree with each other even when the runs directory changes between
+//! requests. Each sub-query succeeds or fails on its own; only an invalid
+//! batch or a missing runs directory fails the whole request.
+
+use std::collections::BTreeMap;
+
+use axum::{http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::sts_handlers::{
+    load_runs, AcceptLanguage, CharacterFilterQuery, RunsQuery, StatsListQuery, SummaryQuery,
+};
+use super::types::ApiError;
+use crate::sts::{
+    personal_bests, CharacterStats, DatasetSummary, Locale, PersonalBests, ProgressReport,
+    RunMetrics,
+};
+
+/// Most sub-queries a single batch may hold
+pub const MAX_BATCH_QUERIES: usize = 16;
+
+/// Request body for the batch endpoint
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchRequest {
+    /// Sub-queries to run, by a name of the caller's choosing
+    pub queries: BTreeMap<String, BatchQuery>,
+}
+
+/// One sub-query of a batch, with the parameters of its endpoint
+///
+/// `type` selects the query; the other fields are the query parameters of
+/// the matching GET endpoint.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchQuery {
+    /// Character stats, as `GET /api/stats`
+    Stats(StatsListQuery),
+    /// A page of runs, as `GET /api/runs` (without `recursive`)
+    Runs(RunsQuery),
+    /// Dataset summary, as `GET /api/summary`
+    Summary(SummaryQuery),
+    /// Personal records, as `GET /api/personal-bests`
+    PersonalBests(CharacterFilterQuery),
+    /// Ascension progress, as `GET /api/progress`
+    Progress,
+}
+
+/// A page of runs returned by a `runs` sub-query
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RunsPage {
+    /// Runs on this page
+    pub runs: Vec<RunMetrics>,
+    /// Runs matching the filters before pagination
+    pub total: usize,
+    /// Offset of the first returned run
+    pub offset: usize,
+    /// Limit that was applied
+    pub limit: usize,
+    /// Whether more runs exist after this page
+    pub truncated: bool,
+}
+
+/// Data answered by a successful sub-query
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum BatchData {
+    Stats(Vec<CharacterStats>),
+    Runs(RunsPage),
+    Summary(DatasetSummary),
+    PersonalBests(Box<PersonalBests>),
+    Progress(ProgressReport),
+}
+
+/// Outcome of one sub-query
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BatchResult {
+    /// Status the matching GET endpoint would have answered with
+    pub status: u16,
+    /// The sub-query's data, when it succeeded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<BatchData>,
+    /// Why the sub-query failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ApiError>,
+}
+
+impl From<Result<BatchData, (StatusCode, Json<ApiError>)>> for BatchResult {
+    fn from(result: Result<BatchData, (StatusCode, Json<ApiError>)>) -> Self {
+        match result {
+            Ok(data) => Self {
+                status: StatusCode::OK.as_u16(),
+                data: Some(data),
+                error: None,
+            },
+            Err((status, Json(error))) => Self {
+                status: status.as_u16(),
+                data: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// Response from the batch endpoint
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BatchResponse {
+    /// Runs loaded for the batch, hidden runs included
+    pub total_runs: usize,
+    /// Result of each sub-query, under the name it was given
+    pub results: BTreeMap<String, BatchResult>,
+}
+
+fn invalid_batch(details: impl Into<String>) -> (StatusCode, Json<ApiError>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ApiError::with_details(
+            "Invalid batch",
+            "INVALID_QUERY",
+            details,
+        )),
+    )
+}
+
+impl BatchQuery {
+    /// Answer the query from `runs`, with display names in `locale`
+    fn run(
+        &self,
+        runs: &[RunMetrics],
+        locale: Locale,
+    ) -> Result<BatchData, (StatusCode, Json<ApiError>)> {
+        let snapshot = || Ok(runs);
+        match self {
+            Self::Stats(query) => {
+                let (_, mut stats) = query.character_stats(snapshot)?;
+                for entry in &mut stats {
+                    entry.localize(locale);
+                }
+                Ok(BatchData::Stats(stats))
+            }
+            Self::Runs(query) => {
+                if query.recursive.is_some() {
+                    return Err(invalid_batch(
+                        "recursive is not supported in a batch; the recursive_scan setting applies",
+                    ));
+                }
+                let (runs, page) = query.page(snapshot)?;
+                Ok(BatchData::Runs(RunsPage {
+                    runs,
+                    total: page.total,
+                    offset: page.offset,
+                    limit: page.limit,
+                    truncated: page.truncated(),
+                }))
+            }
+            Self::Summary(query) => {
+                let (_, summary) = query.summary(snapshot)?;
+                Ok(BatchData::Summary(summary))
+            }
+            Self::PersonalBests(query) => {
+                let bests = personal_bests(&query.select(runs)?);
+                Ok(BatchData::PersonalBests(Box::new(bests)))
+            }
+            Self::Progress => Ok(BatchData::Progress(ProgressReport::from_runs(runs))),
+        }
+    }
+}
+
+/// Run several queries against one consistent set of runs
+///
+/// `queries` maps names of your choosing to sub-queries. Each sub-query has a
+/// `type` (`stats`, `runs`, `summary`, `personal_bests` or `progress`) and
+/// takes the query parameters of the matching GET endpoint as fields, e.g.
+/// `{"type": "stats", "include_hidden": true}`. All sub-queries see the same
+/// runs, loaded once for the batch, so their numbers agree.
+///
+/// Results come back under the same names, each with the `status` the GET
+/// endpoint would have answered with and either `data` or `error`. A batch
+/// holds at most 16 sub-queries. Display names follow `Accept-Language`, as
+/// on the GET endpoints.
+#[utoipa::path(
+    post,
+    path = "/api/batch",
+    tag = "sts",
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "Result of each sub-query", body = BatchResponse),
+        (status = 400, description = "Malformed batch, no sub-queries, or more than 16", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn post_batch(
+    AcceptLanguage(locale): AcceptLanguage,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Json<BatchResponse>, (StatusCode, Json<ApiError>)> {
+    let request: BatchRequest =
+        serde_json::from_value(body).map_err(|e| invalid_batch(e.to_string()))?;
+    if request.queries.is_empty() {
+        return Err(invalid_batch("A batch needs at least one query"));
+    }
+    if request.queries.len() > MAX_BATCH_QUERIES {
+        return Err(invalid_batch(format!(
+            "A batch holds at most {} queries, got {}",
+            MAX_BATCH_QUERIES,
+            request.queries.len()
+        )));
+    }
+
+    let runs = load_runs(None)?;
+    let results = request
+        .queries
+        .iter()
+        .map(|(name, query)| (name.clone(), query.run(&runs, locale).into()))
+        .collect();
+    Ok(Json(BatchResponse {
+        total_runs: runs.len(),
+        results,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::serve;
+    use crate::sts::TestRunsDir;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    async fn post(port: u16, body: Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("http://127.0.0.1:{}/api/batch", port))
+            .header(reqwest::header::ACCEPT_LANGUAGE, "fr")
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_batch_answers_every_part_from_one_snapshot() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener));
+
+        let response = post(
+            port,
+            json!({
+                "queries": {
+                    "stats": {"type": "stats", "include_hidden": true},
+                    "summary": {"type": "summary"},
+                    "runs": {"type": "runs", "include_hidden": true, "limit": 2},
+                }
+            }),
+        )
+        .await;
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        let results = &body["results"];
+        for name in ["stats", "summary", "runs"] {
+            assert_eq!(results[name]["status"], 200, "{name}");
+            assert!(results[name]["error"].is_null(), "{name}");
+        }
+
+        let total = body["total_runs"].as_u64().unwrap();
+        assert!(total > 2);
+        let stats_total: u64 = results["stats"]["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["total_runs"].as_u64().unwrap())
+            .sum();
+        assert_eq!(stats_total, total);
+        let names: Vec<&str> = results["stats"]["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["display_name"].as_str().unwrap())
+            .collect();
+        assert!(names.contains(&"Le Cuirassé"), "{names:?}");
+        assert_eq!(results["summary"]["data"]["total_runs"], total);
+        assert_eq!(results["runs"]["data"]["total"], total);
+        assert_eq!(results["runs"]["data"]["runs"].as_array().unwrap().len(), 2);
+        assert_eq!(results["runs"]["data"]["truncated"], true);
+    }
+
+    #[tokio::test]
+    async fn test_batch_reports_failed_parts_separately() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let body = json!({
+            "queries": {
+                "bad": {"type": "stats", "sort_by": "nonsense"},
+                "progress": {"type": "progress"},
+            }
+        });
+        let Json(response) = post_batch(AcceptLanguage(Locale::En), Json(body))
+            .await
+            .unwrap();
+        assert_eq!(response.results["bad"].status, 400);
+        assert_eq!(
+            response.results["bad"].error.as_ref().unwrap().code,
+            "INVALID_QUERY"
+        );
+        assert_eq!(response.results["progress"].status, 200);
+    }
+
+    #[tokio::test]
+    async fn test_batch_size_is_limited() {
+        let too_many: serde_json::Map<String, Value> = (0..=MAX_BATCH_QUERIES)
+            .map(|i| (format!("q{i}"), json!({"type": "progress"})))
+            .collect();
+        for queries in [json!({}), Value::Object(too_many)] {
+            let (status, Json(error)) = post_batch(
+                AcceptLanguage(Locale::En),
+                Json(json!({ "queries": queries })),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(error.code, "INVALID_QUERY");
+        }
+
+        let unknown = json!({"queries": {"x": {"type": "everything"}}});
+        let (status, _) = post_batch(AcceptLanguage(Locale::En), Json(unknown))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
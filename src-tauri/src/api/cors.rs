@@ -0,0 +1,88 @@
+//! Allowed CORS origins
+//!
+//! The API has no authentication, so by default only the desktop webview
+//! (and the Vite dev server) may read responses from a browser context. Any
+//! other page open in the user's browser gets no `Access-Control-Allow-Origin`
+//! header and can't see the data. More origins, or `*` for any origin, can be
+//! allowed through the `cors_origins` config setting.
+
+use axum::http::{HeaderName, HeaderValue};
+use parking_lot::RwLock;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use super::{pagination, request_id, sts_handlers, timing};
+
+/// Origins allowed when nothing else is configured: the Tauri webview on
+/// macOS/Linux, the Tauri webview on Windows, and the Vite dev server
+pub const DEFAULT_ALLOWED_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "https://tauri.localhost",
+    "http://localhost:1420",
+];
+
+/// Configured value that allows every origin
+pub const ANY_ORIGIN: &str = "*";
+
+static ALLOWED_ORIGINS: RwLock<Option<Vec<String>>> = RwLock::new(None);
+
+/// Set the allowed origins; routers built afterwards use them
+pub fn set_allowed_origins(origins: Vec<String>) {
+    if origins.iter().any(|o| o == ANY_ORIGIN) {
+        tracing::warn!("CORS allows any origin: every web page can read the API");
+    }
+    *ALLOWED_ORIGINS.write() = Some(origins);
+}
+
+/// Currently allowed origins
+pub fn allowed_origins() -> Vec<String> {
+    ALLOWED_ORIGINS
+        .read()
+        .clone()
+        .unwrap_or_else(default_origins)
+}
+
+/// [`DEFAULT_ALLOWED_ORIGINS`] as owned strings
+pub fn default_origins() -> Vec<String> {
+    DEFAULT_ALLOWED_ORIGINS
+        .iter()
+        .map(|o| o.to_string())
+        .collect()
+}
+
+/// Origin policy for a list of configured origins
+fn allow_origin(origins: &[String]) -> AllowOrigin {
+    if origins.iter().any(|o| o == ANY_ORIGIN) {
+        return Any.into();
+    }
+    let values: Vec<HeaderValue> = origins
+        .iter()
+        .filter_map(|origin| {
+            let value = HeaderValue::from_str(origin.trim_end_matches('/'));
+            if value.is_err() {
+                tracing::warn!("Ignoring invalid CORS origin {:?}", origin);
+            }
+            value.ok()
+        })
+        .collect();
+    AllowOrigin::list(values)
+}
+
+/// CORS layer for the API router
+pub fn cors_layer() -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(allow_origin(&allowed_origins()))
+        .allow_methods(Any)
+        .allow_headers(Any)
+        .expose_headers(
+            pagination::PAGINATION_HEADERS
+                .into_iter()
+                .chain(sts_handlers::TIME_WINDOW_HEADERS)
+                .chain([
+                    request_id::REQUEST_ID_HEADER,
+                    timing::RESPONSE_TIME_HEADER,
+                    "retry-after",
+                ])
+                .map(HeaderName::from_static)
+                .collect::<Vec<_>>(),
+        )
+}
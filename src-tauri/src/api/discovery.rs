@@ -0,0 +1,148 @@
+//! LAN discovery
+//!
+//! In LAN mode the server advertises itself over mDNS/DNS-SD as
+//! `_sts-stat-viewer._tcp.local.` so phones and other devices can find it
+//! without typing the desktop's IP. The TXT record carries the app version and
+//! the API base path. The advertisement is withdrawn when the server stops or
+//! the app exits.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use mdns_sd::{DaemonStatus, ServiceDaemon, ServiceInfo};
+use parking_lot::Mutex;
+
+/// DNS-SD service type the server is advertised under
+pub const SERVICE_TYPE: &str = "_sts-stat-viewer._tcp.local.";
+
+/// Instance name shown by discovery browsers
+const INSTANCE_NAME: &str = "STS Stat Viewer";
+
+/// How long to wait for the mDNS daemon to confirm a goodbye or shutdown
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The advertisement currently on the network, if any
+static ACTIVE: Mutex<Option<Announcer>> = Mutex::new(None);
+
+/// A running mDNS advertisement
+///
+/// Dropping it withdraws the service and stops the daemon thread.
+pub struct Announcer {
+    daemon: Option<ServiceDaemon>,
+    fullname: String,
+}
+
+impl Announcer {
+    /// Advertise the server on `port` on every non-loopback interface
+    pub fn start(port: u16) -> Result<Self, mdns_sd::Error> {
+        let daemon = ServiceDaemon::new()?;
+        let host = format!("sts-stat-viewer-{}.local.", port);
+        let properties = [("version", env!("CARGO_PKG_VERSION")), ("path", "/api")];
+        let info = ServiceInfo::new(
+            SERVICE_TYPE,
+            INSTANCE_NAME,
+            &host,
+            "",
+            port,
+            &properties[..],
+        )?
+        .enable_addr_auto();
+        let fullname = info.get_fullname().to_string();
+        if let Err(e) = daemon.register(info) {
+            let _ = daemon.shutdown();
+            return Err(e);
+        }
+        tracing::info!("📡 Advertising {} on port {} via mDNS", fullname, port);
+        Ok(Self {
+            daemon: Some(daemon),
+            fullname,
+        })
+    }
+
+    /// Full DNS-SD name of the advertised service
+    pub fn fullname(&self) -> &str {
+        &self.fullname
+    }
+
+    /// Withdraw the service and stop the daemon thread
+    ///
+    /// Returns whether the daemon confirmed it shut down.
+    pub fn stop(mut self) -> bool {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> bool {
+        let Some(daemon) = self.daemon.take() else {
+            return true;
+        };
+        // Send the goodbye packets before the daemon exits
+        if let Ok(status) = daemon.unregister(&self.fullname) {
+            let _ = status.recv_timeout(STOP_TIMEOUT);
+        }
+        let stopped = daemon
+            .shutdown()
+            .ok()
+            .and_then(|status| status.recv_timeout(STOP_TIMEOUT).ok())
+            .is_some_and(|status| matches!(status, DaemonStatus::Shutdown));
+        if stopped {
+            tracing::info!("Stopped advertising {}", self.fullname);
+        } else {
+            tracing::warn!("mDNS daemon did not confirm shutdown");
+        }
+        stopped
+    }
+}
+
+impl Drop for Announcer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Whether a server bound to `addr` is reachable from other machines
+pub fn should_announce(addr: SocketAddr) -> bool {
+    !addr.ip().is_loopback()
+}
+
+/// Start advertising the server, replacing any earlier advertisement
+///
+/// Failures are logged; the server works without discovery.
+pub fn announce(port: u16) {
+    withdraw();
+    match Announcer::start(port) {
+        Ok(announcer) => *ACTIVE.lock() = Some(announcer),
+        Err(e) => tracing::warn!("Could not start mDNS advertisement: {}", e),
+    }
+}
+
+/// Withdraw the current advertisement, if any
+pub fn withdraw() {
+    let announcer = ACTIVE.lock().take();
+    if let Some(announcer) = announcer {
+        announcer.stop();
+    }
+}
+
+/// Whether the server is currently advertised over mDNS
+pub fn is_announced() -> bool {
+    ACTIVE.lock().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_announce_only_reachable_binds() {
+        assert!(!should_announce("127.0.0.1:3030".parse().unwrap()));
+        assert!(should_announce("0.0.0.0:3030".parse().unwrap()));
+        assert!(should_announce("192.168.1.20:3030".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_announcer_starts_and_stops() {
+        let announcer = Announcer::start(crate::api::DEFAULT_PORT).unwrap();
+        assert!(announcer.fullname().ends_with(SERVICE_TYPE));
+        assert!(announcer.stop());
+    }
+}
@@ -5,7 +5,14 @@
 use axum::{extract::Path, http::StatusCode, Json};
 use chrono::Utc;
 
-use super::types::{ApiError, GreetRequest, GreetResponse, HealthResponse, HealthStatus};
+use super::discovery;
+use super::types::{
+    ApiError, DiscoveryResponse, GreetRequest, GreetResponse, HealthResponse, HealthStatus,
+    Profiles, Settings,
+};
+use crate::recent_errors::{self, RecentError};
+use crate::status::{app_status, server_state, AppStatus, ServerState};
+use crate::{config, refresh, sts};
 
 /// Health check endpoint
 ///
@@ -23,6 +30,157 @@ pub async fn health_check() -> Json<HealthResponse> {
         status: HealthStatus::Healthy,
         timestamp: Utc::now(),
         version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        runs_path: Some(crate::sts::get_runs_path_info()),
+        demo: crate::sts::demo_mode(),
+    })
+}
+
+/// Application status endpoint
+///
+/// Reports the state of the server, runs path resolution, the last run load,
+/// the watcher and the config file.
+#[utoipa::path(
+    get,
+    path = "/api/status",
+    tag = "health",
+    responses(
+        (status = 200, description = "Subsystem status report", body = AppStatus)
+    )
+)]
+pub async fn get_status() -> Json<AppStatus> {
+    Json(app_status())
+}
+
+/// Discovery endpoint
+///
+/// Lists the URLs the server is reachable at, including its LAN address in
+/// LAN mode, and whether it is advertised over mDNS.
+#[utoipa::path(
+    get,
+    path = "/api/discovery",
+    tag = "health",
+    responses(
+        (status = 200, description = "Server addresses", body = DiscoveryResponse)
+    )
+)]
+pub async fn get_discovery() -> Json<DiscoveryResponse> {
+    let urls = match server_state() {
+        ServerState::Running { urls, .. } => urls,
+        _ => Vec::new(),
+    };
+    Json(DiscoveryResponse {
+        urls,
+        mdns: discovery::is_announced(),
+        service_type: discovery::SERVICE_TYPE.to_string(),
+    })
+}
+
+/// Recent errors endpoint
+///
+/// Lists the last warnings and errors that were logged (files that failed to
+/// load, failed requests, panics), newest first, with the fields of the
+/// request or file they concern. At most 100 are kept.
+#[utoipa::path(
+    get,
+    path = "/api/errors",
+    tag = "health",
+    responses(
+        (status = 200, description = "Recent warnings and errors, newest first", body = Vec<RecentError>)
+    )
+)]
+pub async fn get_recent_errors() -> Json<Vec<RecentError>> {
+    Json(recent_errors::recent_errors())
+}
+
+/// Clear recent errors endpoint
+#[utoipa::path(
+    delete,
+    path = "/api/errors",
+    tag = "health",
+    responses(
+        (status = 204, description = "Recorded warnings and errors cleared")
+    )
+)]
+pub async fn clear_recent_errors() -> StatusCode {
+    recent_errors::clear_recent_errors();
+    StatusCode::NO_CONTENT
+}
+
+/// Get the settings that can be changed while the app is running
+#[utoipa::path(
+    get,
+    path = "/api/settings",
+    tag = "settings",
+    responses(
+        (status = 200, description = "Current settings", body = Settings)
+    )
+)]
+pub async fn get_settings() -> Json<Settings> {
+    Json(Settings {
+        auto_refresh_secs: config::current().auto_refresh_secs,
+    })
+}
+
+/// Change settings, saving them to the config file
+///
+/// Setting `auto_refresh_secs` starts rescanning the runs directory on that
+/// interval, and `null` stops it.
+#[utoipa::path(
+    put,
+    path = "/api/settings",
+    tag = "settings",
+    request_body = Settings,
+    responses(
+        (status = 200, description = "Settings now in effect", body = Settings),
+        (status = 400, description = "Auto-refresh interval below 30 seconds", body = ApiError)
+    )
+)]
+pub async fn put_settings(
+    Json(settings): Json<Settings>,
+) -> Result<Json<Settings>, (StatusCode, Json<ApiError>)> {
+    // Saving the config and restarting the poller block on file IO and locks
+    let secs = settings.auto_refresh_secs;
+    tokio::task::spawn_blocking(move || refresh::set_auto_refresh_secs(secs))
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::with_details(
+                    "Failed to apply settings",
+                    "INTERNAL_ERROR",
+                    e.to_string(),
+                )),
+            )
+        })?
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::with_details(
+                    "Invalid settings",
+                    "VALIDATION_ERROR",
+                    e,
+                )),
+            )
+        })?;
+    Ok(Json(settings))
+}
+
+/// List the profiles and which one is active
+///
+/// Data endpoints accept `profile` to use another profile's runs for one
+/// request.
+#[utoipa::path(
+    get,
+    path = "/api/profiles",
+    tag = "settings",
+    responses(
+        (status = 200, description = "Profiles, with the active one", body = Profiles)
+    )
+)]
+pub async fn get_profiles() -> Json<Profiles> {
+    Json(Profiles {
+        active: sts::active_profile(),
+        names: sts::profile_names(),
     })
 }
 
@@ -121,6 +279,23 @@ mod tests {
         assert!(response.version.is_some());
     }
 
+    #[tokio::test]
+    async fn test_get_status() {
+        let response = get_status().await;
+        assert_eq!(response.version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[tokio::test]
+    async fn test_put_settings_rejects_short_interval() {
+        let settings = Settings {
+            auto_refresh_secs: Some(10),
+        };
+        let (status, error) = put_settings(Json(settings)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "VALIDATION_ERROR");
+        assert!(error.details.as_deref().unwrap().contains("30"));
+    }
+
     #[tokio::test]
     async fn test_greet_valid_name() {
         let request = GreetRequest {
@@ -2,109 +2,519 @@
 //!
 //! Contains types, handlers, and server configuration for the REST API.
 
+pub mod batch;
+pub mod cors;
+pub mod discovery;
 pub mod handlers;
+pub mod pagination;
+pub mod profiles;
+pub mod request_id;
+pub mod schema_handlers;
+pub mod streaming;
 pub mod sts_handlers;
+pub mod supervisor;
+pub mod timing;
 pub mod types;
+pub mod typescript;
+pub mod warmup;
 
-use axum::{routing::get, Router};
-use tower_http::cors::{Any, CorsLayer};
+use std::any::Any as PanicPayload;
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::Path;
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{routing::get, Json, Router};
+use tokio::net::TcpListener;
+use tower_http::catch_panic::CatchPanicLayer;
+use utoipa::openapi::server::ServerBuilder;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::sts::{CharacterStats, ExportData, RunMetrics};
-use handlers::{greet, greet_by_path, health_check};
+use crate::recent_errors::{ErrorLevel, RecentError};
+use crate::refresh::RunsUpdated;
+use crate::status::{set_server_state, AppStatus, RunsPathStatus, ServerState, WatcherState};
+use crate::sts::{
+    ActBossRelicStats, ActBossStats, ActEliteStats, ActElites, ActQuestionRoomRate,
+    ActQuestionRooms, ActRewards, ActShopEngagement, ActShops, ActSkipRate, AscensionBand,
+    AscensionNormalized, AscensionStats, BehaviorAverages, BossRelicChoice, BossRelicPicks,
+    BossRelicStats, BossStats, BossSwapExclusion, BossSwapRelic, BossSwapStats, CalendarDay,
+    CardRemoval, CharacterId, CharacterProgress, CharacterRelicSources, CharacterShopEngagement,
+    CharacterStats, DatasetSummary, DeckSizeBucket, DeckSizeStats, DuplicateRun, EliteRate,
+    EliteStats, Encounter, EncounterDamage, EncounterTurns, EnemyCount, EventStats, ExportChecksum,
+    ExportData, ExportFilter, ExportVerification, FeatureRow, FeatureSet, FirstRelic,
+    FirstRelicStats, HeartMilestone, KeyStats, LoadIssue, LoadProgress, LoadReport,
+    OutcomeBehavior, PeriodComparison, PeriodDelta, PeriodStats, PersonalBests, PersonalRecord,
+    PlayTimeStats, PotionNameCount, PotionNameStats, PotionPickup, PotionStats, ProgressReport,
+    PurgedCardStats, QuestionRoomRate, RecoveredFile, Recovery, RelicAcquisition, RelicCheckpoint,
+    RelicSourceCounts, RelicSourceStats, RelicTiming, RelicTimingBucket, Ruleset, RunDelta,
+    RunDetail, RunExclusion, RunMetrics, RunSelection, RunsPathResolution, ScoreBreakdown,
+    ScoreComponent, ShopEngagement, ShopStats, SkipRate, SkipRateStats, TimeBucket, UpgradeBucket,
+    UpgradeImpactStats, UpgradeSources, WarmupState, WinRecord,
+};
+use batch::{
+    post_batch, BatchData, BatchQuery, BatchRequest, BatchResponse, BatchResult, RunsPage,
+};
+use handlers::{
+    clear_recent_errors, get_discovery, get_profiles, get_recent_errors, get_settings, get_status,
+    greet, greet_by_path, health_check, put_settings,
+};
+use schema_handlers::{get_schema, list_schemas};
 use sts_handlers::{
-    get_character_runs, get_character_stats, get_characters, get_export, get_runs, get_stats,
+    get_act_boss_stats, get_ascension_stats, get_boss_relic_stats, get_boss_swap_stats,
+    get_calendar, get_calendar_years, get_character_runs, get_character_stats, get_characters,
+    get_deck_size_stats, get_elite_stats, get_enemies, get_event_stats, get_export,
+    get_first_relic, get_ml_features, get_period_comparison, get_personal_bests, get_potion_names,
+    get_potion_stats, get_progress, get_purged_cards, get_relic_sources, get_relic_timing, get_run,
+    get_runs, get_runs_delta, get_shop_stats, get_skip_rate, get_slow_fights, get_stats,
+    get_summary, get_upgrade_impact, get_when_stats, hide_run, import_export, reparse_run,
+    unhide_run, CharacterFilterQuery, RunsQuery, StatsListQuery, SummaryQuery,
+};
+use supervisor::{panic_message, ServerError};
+use types::{
+    ApiError, DiscoveryResponse, GreetRequest, GreetResponse, HealthResponse, HealthStatus,
+    Profiles, RunVisibility, Settings,
 };
-use types::{ApiError, GreetRequest, GreetResponse, HealthResponse, HealthStatus};
 
 /// OpenAPI documentation structure
 #[derive(OpenApi)]
 #[openapi(
     info(
         title = "STS Stat Viewer API",
-        description = "API for Slay the Spire run statistics and analysis",
+        description = "API for Slay the Spire run statistics and analysis
+
+## Filtering runs
+
+`/api/runs`, `/api/stats` and `/api/export` share one set of run filters, and the
+per-statistic endpoints accept `character`:
+
+- `character`: only runs of these characters, as a comma-separated list of names,
+  display names or aliases (`IRONCLAD,silent`)
+- `exclude_character`: leave out runs of these characters, in the same format
+- `exclude`: leave out kinds of run, as a comma-separated list of
+  - `dailies`: Daily Climb runs
+  - `seeded`: runs played on a chosen seed
+  - `endless`: Endless mode runs
+  - `custom`: custom runs
+  - `non_standard`: every run that isn't `standard`
+  - `modded`: runs holding cards, relics or a character the base game doesn't have
+  - `abandoned`: losses without a `killed_by`, i.e. runs given up from the menu
+  - `hidden`: runs hidden from statistics
+
+Unknown names, an empty `character` list, or a character both included and
+excluded are rejected with a 400 `INVALID_QUERY` error.
+
+Each run has one `ruleset`: `daily`, `custom`, `endless`, `seeded` or
+`standard`. A run with several mode flags takes the first of these that applies,
+so a seeded custom run is `custom`. The game mode exclusions match on it, and
+`/api/runs` also accepts `ruleset` to keep only runs of the listed rulesets.
+
+## Profiles
+
+Runs and statistics come from the active profile's runs directory, with that
+profile's hidden runs. Pass `profile` to any run or statistics endpoint to use
+another profile for that request; `GET /api/profiles` lists them. Unknown
+profiles are rejected with a 400 `UNKNOWN_PROFILE` error.
+
+## Startup
+
+The app loads the runs directory in the background when it starts, and
+`GET /api/status` reports its progress in `warmup`. Until the load finishes, run
+and statistics endpoints cover the runs loaded so far, or answer 503
+`WARMING_UP` with a `Retry-After` header when the `warmup_mode` setting is
+`unavailable`.",
         version = "1.0.0",
         contact(name = "API Support")
     ),
     paths(
         handlers::health_check,
+        handlers::get_status,
+        handlers::get_discovery,
+        handlers::get_recent_errors,
+        handlers::clear_recent_errors,
+        handlers::get_settings,
+        handlers::put_settings,
+        handlers::get_profiles,
         handlers::greet,
         handlers::greet_by_path,
         sts_handlers::get_runs,
+        sts_handlers::get_runs_delta,
         sts_handlers::get_character_runs,
         sts_handlers::get_stats,
         sts_handlers::get_character_stats,
+        sts_handlers::get_calendar,
+        sts_handlers::get_calendar_years,
+        sts_handlers::get_boss_swap_stats,
+        sts_handlers::get_boss_relic_stats,
+        sts_handlers::get_shop_stats,
+        sts_handlers::get_purged_cards,
+        sts_handlers::get_slow_fights,
+        sts_handlers::get_act_boss_stats,
+        sts_handlers::get_elite_stats,
+        sts_handlers::get_event_stats,
+        sts_handlers::get_potion_stats,
+        sts_handlers::get_potion_names,
+        sts_handlers::get_when_stats,
+        sts_handlers::get_relic_timing,
+        sts_handlers::get_first_relic,
+        sts_handlers::get_relic_sources,
+        sts_handlers::get_skip_rate,
+        sts_handlers::get_ascension_stats,
+        sts_handlers::get_deck_size_stats,
+        sts_handlers::get_upgrade_impact,
+        sts_handlers::get_period_comparison,
         sts_handlers::get_export,
+        sts_handlers::get_ml_features,
+        sts_handlers::import_export,
         sts_handlers::get_characters,
+        sts_handlers::get_run,
+        sts_handlers::hide_run,
+        sts_handlers::unhide_run,
+        sts_handlers::reparse_run,
+        sts_handlers::get_summary,
+        sts_handlers::get_personal_bests,
+        sts_handlers::get_progress,
+        sts_handlers::get_enemies,
+        batch::post_batch,
+        schema_handlers::list_schemas,
+        schema_handlers::get_schema,
     ),
     components(
         schemas(
-            HealthResponse, HealthStatus, GreetRequest, GreetResponse, ApiError,
-            RunMetrics, CharacterStats, ExportData
+            HealthResponse, HealthStatus, DiscoveryResponse, GreetRequest, GreetResponse, ApiError, RunVisibility, Settings, Profiles, RunsUpdated,
+            RunMetrics, RunDetail, ScoreBreakdown, ScoreComponent, CharacterId, RunDelta, CharacterStats, BehaviorAverages, OutcomeBehavior, KeyStats, EncounterDamage, CalendarDay,
+            BossSwapStats, BossSwapRelic, BossSwapExclusion, WinRecord, BossRelicStats, ActBossRelicStats, BossRelicPicks, BossRelicChoice, ShopStats, ShopEngagement, ActShopEngagement, CharacterShopEngagement, ActShops, PurgedCardStats, CardRemoval, EncounterTurns, ActBossStats, BossStats,
+            EliteStats, ActEliteStats, EliteRate, ActElites, EventStats, QuestionRoomRate, ActQuestionRoomRate, ActQuestionRooms, PotionStats, PotionNameStats, PotionNameCount, PotionPickup,
+            RelicAcquisition, RelicTiming, RelicTimingBucket, RelicCheckpoint, FirstRelic, FirstRelicStats, RelicSourceStats, RelicSourceCounts, CharacterRelicSources,
+            SkipRateStats, SkipRate, ActSkipRate, ActRewards, AscensionStats, AscensionNormalized, AscensionBand, DeckSizeStats, DeckSizeBucket, UpgradeImpactStats, UpgradeBucket, UpgradeSources, PeriodComparison, PeriodStats, PeriodDelta,
+            PersonalBests, PersonalRecord, PlayTimeStats, TimeBucket, ProgressReport, CharacterProgress, HeartMilestone, Encounter, EnemyCount, ExportData, ExportChecksum, ExportVerification, ExportFilter, FeatureSet, FeatureRow, RunSelection, RunExclusion, Ruleset, DatasetSummary, LoadIssue, LoadReport, DuplicateRun,
+            RecoveredFile, Recovery, AppStatus, ServerState, WatcherState, RecentError, ErrorLevel, WarmupState, LoadProgress, RunsPathStatus, RunsPathResolution,
+            BatchRequest, BatchQuery, BatchResponse, BatchResult, BatchData, RunsPage, RunsQuery, StatsListQuery, SummaryQuery, CharacterFilterQuery
         )
     ),
     tags(
         (name = "health", description = "Health check endpoints"),
         (name = "greeting", description = "Greeting endpoints"),
-        (name = "sts", description = "Slay the Spire data endpoints")
+        (name = "settings", description = "Settings that can be changed while the app is running"),
+        (name = "sts", description = "Slay the Spire data endpoints"),
+        (name = "schema", description = "JSON Schemas for the API data types")
     )
 )]
 pub struct ApiDoc;
 
+/// Default port for the API server
+pub const DEFAULT_PORT: u16 = 3030;
+
+/// Server URL advertised when the actual bound address is unknown
+pub const DEFAULT_SERVER_URL: &str = "http://127.0.0.1:3030";
+
+/// Options for running the API server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerOptions {
+    /// Port to listen on (0 picks a free port)
+    pub port: u16,
+    /// Listen on all interfaces so other devices on the network can connect
+    pub lan: bool,
+    /// Advertise the server via mDNS when it is reachable from the network
+    pub mdns: bool,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_PORT,
+            lan: false,
+            mdns: true,
+        }
+    }
+}
+
+/// Best guess at this machine's address on the local network
+///
+/// Connecting a UDP socket only selects a route; no packets are sent.
+fn lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 80)).ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_loopback() && !ip.is_unspecified()).then_some(ip)
+}
+
+/// URLs the server is reachable at for a bound address
+///
+/// A wildcard bind (LAN mode) yields the loopback URL plus the LAN URL.
+pub fn server_urls(addr: SocketAddr) -> Vec<String> {
+    let port = addr.port();
+    if !addr.ip().is_unspecified() {
+        return vec![format!("http://{}", addr)];
+    }
+
+    let mut urls = vec![format!("http://127.0.0.1:{}", port)];
+    if let Some(ip) = lan_ip() {
+        urls.push(format!("http://{}", SocketAddr::new(ip, port)));
+    }
+    urls
+}
+
+/// Build the OpenAPI document with the given server URLs
+pub fn openapi_with_servers(urls: &[String]) -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    let servers = urls
+        .iter()
+        .map(|url| {
+            let description = if url.contains("://127.0.0.1") || url.contains("://[::1]") {
+                "Local server"
+            } else {
+                "LAN server"
+            };
+            ServerBuilder::new()
+                .url(url)
+                .description(Some(description))
+                .build()
+        })
+        .collect();
+    doc.servers = Some(servers);
+    doc
+}
+
+/// Turn a panic in a request handler into a 500 response
+///
+/// The panic itself is logged (with a backtrace) by the panic hook.
+fn handler_panic_response(payload: Box<dyn PanicPayload + Send + 'static>) -> Response {
+    let error = ApiError::with_details(
+        "Internal server error",
+        "INTERNAL_ERROR",
+        panic_message(&*payload),
+    );
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response()
+}
+
+/// Handler that always panics
+#[cfg(test)]
+async fn panicking_handler() -> StatusCode {
+    panic!("injected test panic")
+}
+
 /// Create the API router with all routes and OpenAPI documentation
 pub fn create_router() -> Router {
-    use axum::routing::post;
+    create_router_with_servers(&[DEFAULT_SERVER_URL.to_string()])
+}
 
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+/// Create the API router, advertising the given server URLs in the OpenAPI spec
+pub fn create_router_with_servers(server_urls: &[String]) -> Router {
+    use axum::routing::post;
 
-    Router::new()
+    let router = Router::new()
         // Health and greeting endpoints
         .route("/api/health", get(health_check))
+        .route("/api/status", get(get_status))
+        .route("/api/discovery", get(get_discovery))
+        .route(
+            "/api/errors",
+            get(get_recent_errors).delete(clear_recent_errors),
+        )
+        .route("/api/settings", get(get_settings).put(put_settings))
+        .route("/api/profiles", get(get_profiles))
         .route("/api/greet", post(greet))
         .route("/api/greet/{name}", get(greet_by_path))
         // STS data endpoints
+        .route("/api/characters", get(get_characters))
+        .merge(data_routes())
+        // JSON Schemas
+        .route("/api/schema", get(list_schemas))
+        .route("/api/schema/{type}", get(get_schema))
+        // OpenAPI documentation
+        .merge(
+            SwaggerUi::new("/swagger-ui")
+                .url("/api-docs/openapi.json", openapi_with_servers(server_urls)),
+        );
+
+    // Route that always panics, used to exercise panic recovery
+    #[cfg(test)]
+    let router = router.route("/api/test/panic", get(panicking_handler));
+
+    router
+        .layer(CatchPanicLayer::custom(handler_panic_response))
+        .layer(axum::middleware::from_fn(timing::response_time))
+        .layer(axum::middleware::from_fn(request_id::request_id))
+        .layer(cors::cors_layer())
+}
+
+/// Routes serving run data, which may be held back during the startup warm-up
+fn data_routes() -> Router {
+    use axum::routing::post;
+
+    Router::new()
         .route("/api/runs", get(get_runs))
+        .route("/api/runs/delta", get(get_runs_delta))
         .route("/api/runs/{character}", get(get_character_runs))
         .route("/api/stats", get(get_stats))
+        .route("/api/stats/calendar", get(get_calendar))
+        .route("/api/stats/calendar/years", get(get_calendar_years))
+        .route("/api/stats/boss-swap", get(get_boss_swap_stats))
+        .route("/api/stats/boss-relics", get(get_boss_relic_stats))
+        .route("/api/stats/shops", get(get_shop_stats))
+        .route("/api/stats/purged-cards", get(get_purged_cards))
+        .route("/api/stats/slow-fights", get(get_slow_fights))
+        .route("/api/stats/act-bosses", get(get_act_boss_stats))
+        .route("/api/stats/elites", get(get_elite_stats))
+        .route("/api/stats/events", get(get_event_stats))
+        .route("/api/stats/potions", get(get_potion_stats))
+        .route("/api/stats/potion-names", get(get_potion_names))
+        .route("/api/stats/when", get(get_when_stats))
+        .route("/api/stats/relic-timing", get(get_relic_timing))
+        .route("/api/stats/first-relic", get(get_first_relic))
+        .route("/api/stats/relic-sources", get(get_relic_sources))
+        .route("/api/stats/skip-rate", get(get_skip_rate))
+        .route("/api/stats/by-ascension", get(get_ascension_stats))
+        .route("/api/stats/deck-size", get(get_deck_size_stats))
+        .route("/api/stats/upgrade-impact", get(get_upgrade_impact))
         .route("/api/stats/{character}", get(get_character_stats))
+        .route("/api/compare/periods", get(get_period_comparison))
         .route("/api/export", get(get_export))
-        .route("/api/characters", get(get_characters))
-        // OpenAPI documentation
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .layer(cors)
+        .route("/api/ml/features", get(get_ml_features))
+        .route("/api/import", post(import_export))
+        .route("/api/run/{play_id}", get(get_run))
+        .route("/api/run/{play_id}/hide", post(hide_run).delete(unhide_run))
+        .route("/api/run/{play_id}/reparse", post(reparse_run))
+        .route("/api/summary", get(get_summary))
+        .route("/api/personal-bests", get(get_personal_bests))
+        .route("/api/progress", get(get_progress))
+        .route("/api/enemies", get(get_enemies))
+        .route("/api/batch", post(post_batch))
+        .route_layer(axum::middleware::from_fn(warmup::unavailable_during_warmup))
+        .route_layer(axum::middleware::from_fn(profiles::select_profile))
 }
 
 /// Start the API server on the specified port
-pub async fn start_server(port: u16) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let router = create_router();
-    let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
-
-    println!("🚀 API server running at http://127.0.0.1:{}", port);
-    println!(
-        "📚 Swagger UI available at http://127.0.0.1:{}/swagger-ui/",
-        port
-    );
-    println!(
-        "📄 OpenAPI spec at http://127.0.0.1:{}/api-docs/openapi.json",
-        port
-    );
+pub async fn start_server(port: u16) -> Result<(), ServerError> {
+    start_server_with(ServerOptions {
+        port,
+        ..Default::default()
+    })
+    .await
+}
+
+/// Start the API server with explicit options
+pub async fn start_server_with(options: ServerOptions) -> Result<(), ServerError> {
+    let host = if options.lan {
+        Ipv4Addr::UNSPECIFIED
+    } else {
+        Ipv4Addr::LOCALHOST
+    };
+    let listener = match TcpListener::bind((host, options.port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            let error = format!("Failed to bind port {}: {}", options.port, e);
+            tracing::error!("{}", error);
+            set_server_state(ServerState::Failed { error });
+            return Err(e.into());
+        }
+    };
+    let addr = listener.local_addr()?;
+    if options.mdns && discovery::should_announce(addr) {
+        discovery::announce(addr.port());
+    }
+    let result = serve(listener).await;
+    discovery::withdraw();
+    result
+}
+
+/// Serve the API on an already bound listener
+pub async fn serve(listener: TcpListener) -> Result<(), ServerError> {
+    let addr = listener.local_addr()?;
+    let urls = server_urls(addr);
+    let router = create_router_with_servers(&urls);
 
-    axum::serve(listener, router).await?;
+    for url in &urls {
+        tracing::info!("🚀 API server running at {}", url);
+    }
+    tracing::info!("📚 Swagger UI available at {}/swagger-ui/", urls[0]);
+    tracing::info!("📄 OpenAPI spec at {}/api-docs/openapi.json", urls[0]);
+
+    set_server_state(ServerState::Running {
+        port: addr.port(),
+        urls: urls.clone(),
+    });
+
+    if let Err(e) = axum::serve(listener, router).await {
+        tracing::error!("API server stopped: {}", e);
+        set_server_state(ServerState::Failed {
+            error: e.to_string(),
+        });
+        return Err(e.into());
+    }
     Ok(())
 }
 
 /// Export the OpenAPI spec as JSON string
 pub fn get_openapi_json() -> String {
-    ApiDoc::openapi().to_pretty_json().unwrap()
+    openapi_with_servers(&[DEFAULT_SERVER_URL.to_string()])
+        .to_pretty_json()
+        .unwrap()
 }
 
 /// Export the OpenAPI spec as YAML string
 pub fn get_openapi_yaml() -> String {
-    serde_json::to_string_pretty(&ApiDoc::openapi()).unwrap()
+    openapi_with_servers(&[DEFAULT_SERVER_URL.to_string()])
+        .to_yaml()
+        .unwrap()
+}
+
+/// File format of an exported OpenAPI spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecFormat {
+    Json,
+    Yaml,
+}
+
+impl SpecFormat {
+    /// Parse a format name: `json`, `yaml` or `yml`, in any case
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            _ => Err(format!(
+                "Unknown spec format: {} (expected json or yaml)",
+                format
+            )),
+        }
+    }
+
+    /// Format named by a file's extension, if it names one
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| Self::parse(&ext.to_string_lossy()).ok())
+    }
+}
+
+/// Write the OpenAPI spec to a file, returning its size in bytes
+///
+/// Parent directories are created as needed. An existing file is only
+/// replaced when `overwrite` is set; otherwise this fails with
+/// [`std::io::ErrorKind::AlreadyExists`].
+pub fn write_openapi_spec(
+    out_path: &Path,
+    format: SpecFormat,
+    overwrite: bool,
+) -> std::io::Result<usize> {
+    let spec = match format {
+        SpecFormat::Json => get_openapi_json(),
+        SpecFormat::Yaml => get_openapi_yaml(),
+    };
+    if let Some(parent) = out_path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .create_new(!overwrite)
+        .open(out_path)?;
+    file.write_all(spec.as_bytes())?;
+    Ok(spec.len())
 }
 
 #[cfg(test)]
@@ -121,9 +531,220 @@ mod tests {
         assert!(json.contains("RunMetrics"));
     }
 
+    #[test]
+    fn test_write_openapi_spec() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("nested/spec.json");
+        let bytes = write_openapi_spec(&json_path, SpecFormat::Json, false).unwrap();
+        let written = std::fs::read_to_string(&json_path).unwrap();
+        assert_eq!(written.len(), bytes);
+        let spec: serde_json::Value = serde_json::from_str(&written).unwrap();
+        assert_eq!(spec["info"]["title"], "STS Stat Viewer API");
+
+        let yaml_path = dir.path().join("spec.yaml");
+        write_openapi_spec(&yaml_path, SpecFormat::Yaml, false).unwrap();
+        let yaml: serde_json::Value =
+            serde_norway::from_str(&std::fs::read_to_string(&yaml_path).unwrap()).unwrap();
+        assert_eq!(yaml, spec);
+    }
+
+    #[test]
+    fn test_write_openapi_spec_keeps_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spec.json");
+        std::fs::write(&path, "keep me").unwrap();
+
+        let err = write_openapi_spec(&path, SpecFormat::Json, false).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+
+        let bytes = write_openapi_spec(&path, SpecFormat::Json, true).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), bytes as u64);
+    }
+
+    #[test]
+    fn test_spec_format() {
+        assert_eq!(SpecFormat::parse("JSON"), Ok(SpecFormat::Json));
+        assert_eq!(SpecFormat::parse("yml"), Ok(SpecFormat::Yaml));
+        assert!(SpecFormat::parse("toml").is_err());
+        assert_eq!(
+            SpecFormat::from_path(Path::new("out/spec.yaml")),
+            Some(SpecFormat::Yaml)
+        );
+        assert_eq!(SpecFormat::from_path(Path::new("spec")), None);
+    }
+
     #[test]
     fn test_router_creation() {
         let _router = create_router();
         // Router creation should not panic
     }
+
+    #[test]
+    fn test_default_spec_server() {
+        let json = get_openapi_json();
+        assert!(json.contains(DEFAULT_SERVER_URL));
+    }
+
+    #[test]
+    fn test_server_urls() {
+        let addr: SocketAddr = "127.0.0.1:4040".parse().unwrap();
+        assert_eq!(server_urls(addr), vec!["http://127.0.0.1:4040".to_string()]);
+
+        let addr: SocketAddr = "0.0.0.0:4040".parse().unwrap();
+        let urls = server_urls(addr);
+        assert_eq!(urls[0], "http://127.0.0.1:4040");
+        assert!(urls.iter().all(|u| u.ends_with(":4040")));
+    }
+
+    #[tokio::test]
+    async fn test_served_spec_uses_bound_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener));
+
+        let url = format!("http://127.0.0.1:{}/api-docs/openapi.json", port);
+        let spec: serde_json::Value = reqwest::get(&url).await.unwrap().json().await.unwrap();
+        assert_eq!(
+            spec["servers"][0]["url"],
+            format!("http://127.0.0.1:{}", port)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handler_panic_returns_500_and_server_survives() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener));
+
+        let base = format!("http://127.0.0.1:{}", port);
+        let response = reqwest::get(format!("{}/api/test/panic", base))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.status(),
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR
+        );
+        let error: ApiError = response.json().await.unwrap();
+        assert_eq!(error.code, "INTERNAL_ERROR");
+        assert_eq!(error.details.as_deref(), Some("injected test panic"));
+
+        let health = reqwest::get(format!("{}/api/health", base)).await.unwrap();
+        assert!(health.status().is_success());
+    }
+
+    #[tokio::test]
+    async fn test_request_id_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener));
+        let client = reqwest::Client::new();
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/api/health", port))
+            .header(request_id::REQUEST_ID_HEADER, "support-1234")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()[request_id::REQUEST_ID_HEADER],
+            "support-1234"
+        );
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/api/stats/hermit", port))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        let id = response.headers()[request_id::REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let error: ApiError = response.json().await.unwrap();
+        assert_eq!(error.request_id, Some(id));
+    }
+
+    #[tokio::test]
+    async fn test_response_time_header() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener));
+
+        let response = reqwest::get(format!("http://127.0.0.1:{}/api/health", port))
+            .await
+            .unwrap();
+        let millis: f64 = response.headers()[timing::RESPONSE_TIME_HEADER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(millis >= 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_character_path_is_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener));
+
+        for path in ["/api/stats/hermit", "/api/runs/hermit"] {
+            let response = reqwest::get(format!("http://127.0.0.1:{}{}", port, path))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND, "{path}");
+            let error: ApiError = response.json().await.unwrap();
+            assert_eq!(error.code, "NOT_FOUND");
+            assert!(error.details.unwrap().contains("THE_SILENT"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_discovery_lists_server_urls() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener));
+
+        let response: DiscoveryResponse =
+            reqwest::get(format!("http://127.0.0.1:{}/api/discovery", port))
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+        assert!(!response.urls.is_empty());
+        assert!(!response.mdns);
+        assert_eq!(response.service_type, discovery::SERVICE_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_cors_allows_only_app_origins_by_default() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener));
+        let client = reqwest::Client::new();
+        let url = format!("http://127.0.0.1:{}/api/health", port);
+
+        let response = client
+            .get(&url)
+            .header("origin", "https://evil.example")
+            .send()
+            .await
+            .unwrap();
+        assert!(response
+            .headers()
+            .get("access-control-allow-origin")
+            .is_none());
+
+        let response = client
+            .get(&url)
+            .header("origin", "tauri://localhost")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers()["access-control-allow-origin"],
+            "tauri://localhost"
+        );
+    }
 }
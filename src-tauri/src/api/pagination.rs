@@ -0,0 +1,146 @@
+//! Pagination for list endpoints
+//!
+//! List endpoints return at most `limit` items starting at `offset`. When no
+//! limit is given the configured default applies, and limits above the
+//! configured maximum are rejected. The response body stays a plain JSON
+//! array; pagination metadata is sent in response headers so truncation is
+//! visible without changing the body layout.
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::Json;
+
+use super::types::ApiError;
+use crate::config::ListLimits;
+
+/// Total number of items matching the request, before pagination
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+/// Offset of the first returned item
+pub const OFFSET_HEADER: &str = "x-offset";
+/// Limit that was applied
+pub const LIMIT_HEADER: &str = "x-limit";
+/// `true` when more items exist after the returned page
+pub const TRUNCATED_HEADER: &str = "x-truncated";
+
+/// Headers carrying pagination metadata, exposed to browser clients via CORS
+pub const PAGINATION_HEADERS: [&str; 4] = [
+    TOTAL_COUNT_HEADER,
+    OFFSET_HEADER,
+    LIMIT_HEADER,
+    TRUNCATED_HEADER,
+];
+
+/// Which page of a list was returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageInfo {
+    /// Items matching the request before pagination
+    pub total: usize,
+    /// Offset of the first returned item
+    pub offset: usize,
+    /// Limit that was applied
+    pub limit: usize,
+    /// Number of items returned
+    pub returned: usize,
+}
+
+impl PageInfo {
+    /// Whether items exist after the returned page
+    pub fn truncated(&self) -> bool {
+        self.offset + self.returned < self.total
+    }
+
+    /// Pagination metadata as response headers
+    pub fn headers(&self) -> HeaderMap {
+        let values = [
+            (TOTAL_COUNT_HEADER, self.total.to_string()),
+            (OFFSET_HEADER, self.offset.to_string()),
+            (LIMIT_HEADER, self.limit.to_string()),
+            (TRUNCATED_HEADER, self.truncated().to_string()),
+        ];
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in values {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.insert(HeaderName::from_static(name), value);
+            }
+        }
+        headers
+    }
+}
+
+/// Apply `limit` and `offset` to a list
+///
+/// Returns a 400 `LIMIT_EXCEEDED` error when `limit` is above the maximum.
+pub fn paginate<T>(
+    mut items: Vec<T>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    limits: &ListLimits,
+) -> Result<(Vec<T>, PageInfo), (StatusCode, Json<ApiError>)> {
+    let limit = limit.unwrap_or(limits.default_limit.min(limits.max_limit));
+    if limit > limits.max_limit {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::with_details(
+                "Limit too large",
+                "LIMIT_EXCEEDED",
+                format!("limit must be at most {}", limits.max_limit),
+            )),
+        ));
+    }
+
+    let total = items.len();
+    let offset = offset.unwrap_or(0);
+    items.drain(..offset.min(total));
+    items.truncate(limit);
+
+    let page = PageInfo {
+        total,
+        offset,
+        limit,
+        returned: items.len(),
+    };
+    Ok((items, page))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: ListLimits = ListLimits {
+        default_limit: 10,
+        max_limit: 20,
+    };
+
+    #[test]
+    fn test_default_limit_truncates() {
+        let (items, page) = paginate((0..25).collect(), None, None, &LIMITS).unwrap();
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+        assert_eq!(page.total, 25);
+        assert!(page.truncated());
+
+        let headers = page.headers();
+        assert_eq!(headers[TOTAL_COUNT_HEADER], "25");
+        assert_eq!(headers[LIMIT_HEADER], "10");
+        assert_eq!(headers[TRUNCATED_HEADER], "true");
+    }
+
+    #[test]
+    fn test_offset_and_last_page() {
+        let (items, page) = paginate((0..25).collect(), Some(20), Some(20), &LIMITS).unwrap();
+        assert_eq!(items, vec![20, 21, 22, 23, 24]);
+        assert!(!page.truncated());
+
+        let (items, page) =
+            paginate((0..5).collect::<Vec<i32>>(), None, Some(50), &LIMITS).unwrap();
+        assert!(items.is_empty());
+        assert!(!page.truncated());
+    }
+
+    #[test]
+    fn test_limit_above_maximum_is_rejected() {
+        let (status, error) =
+            paginate((0..5).collect::<Vec<i32>>(), Some(21), None, &LIMITS).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "LIMIT_EXCEEDED");
+    }
+}
@@ -0,0 +1,97 @@
+//! `?profile=` on data requests
+//!
+//! Run and statistics endpoints work on the active profile's runs unless the
+//! request names another profile with `profile`. The choice only applies to
+//! that request; the active profile is changed from the desktop app.
+
+use axum::extract::{Query, Request};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+
+use super::types::ApiError;
+use crate::sts;
+
+#[derive(Deserialize)]
+struct ProfileQuery {
+    profile: Option<String>,
+}
+
+/// Middleware for data routes running the request against the profile it
+/// names, answering 400 for profiles that don't exist
+pub async fn select_profile(request: Request, next: Next) -> Response {
+    let profile = Query::<ProfileQuery>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(query)| query.profile);
+    let Some(profile) = profile else {
+        return next.run(request).await;
+    };
+    if !sts::profile_exists(&profile) {
+        let error = ApiError::with_details(
+            "Unknown profile",
+            "UNKNOWN_PROFILE",
+            format!(
+                "No profile named {:?}; profiles: {}",
+                profile,
+                sts::profile_names().join(", ")
+            ),
+        );
+        return (StatusCode::BAD_REQUEST, Json(error)).into_response();
+    }
+    sts::with_profile(profile, next.run(request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::{RunFileBuilder, RunsDirFixture, TestRunsDir};
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn test_requests_can_name_a_profile() {
+        let runs_dir = TestRunsDir::empty();
+        runs_dir.add(&RunFileBuilder::new("mine"));
+        let partner = RunsDirFixture::new();
+        partner.add(&RunFileBuilder::new("theirs"));
+        sts::set_profile_runs_paths(BTreeMap::from([(
+            "partner".to_string(),
+            Some(partner.path().to_path_buf()),
+        )]));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(crate::api::serve(listener));
+        let status = |path: &str| {
+            let url = format!("http://127.0.0.1:{}{}", port, path);
+            async move { reqwest::get(url).await.unwrap().status() }
+        };
+
+        assert_eq!(status("/api/run/mine").await, StatusCode::OK);
+        assert_eq!(status("/api/run/theirs").await, StatusCode::NOT_FOUND);
+        assert_eq!(
+            status("/api/run/theirs?profile=partner").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status("/api/run/mine?profile=partner").await,
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status("/api/run/mine?profile=default").await,
+            StatusCode::OK
+        );
+        let response = reqwest::get(format!(
+            "http://127.0.0.1:{}/api/run/mine?profile=nobody",
+            port
+        ))
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let error: ApiError = response.json().await.unwrap();
+        assert_eq!(error.code, "UNKNOWN_PROFILE");
+
+        sts::set_profile_runs_paths(BTreeMap::new());
+    }
+}
@@ -0,0 +1,215 @@
+//! Request IDs
+//!
+//! Every request gets a short ID (or keeps the one sent in `X-Request-Id`).
+//! The ID is recorded on the request's tracing span, returned in the
+//! `X-Request-Id` response header, and copied into the `request_id` field of
+//! JSON error bodies, so an error shown in the UI can be matched to its log
+//! lines. Long error messages and details are cut short on the way, and an
+//! error body too large to read is replaced by a short `ApiError`, so error
+//! responses always keep their JSON shape.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::{header, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::Instrument;
+
+use super::types::ApiError;
+
+/// Header carrying the request ID, in both directions
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest incoming request ID that is accepted as is
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// Largest error body that is read to include the request ID
+const MAX_ERROR_BODY_BYTES: usize = 1024 * 1024;
+
+/// Error messages and details are cut to this many characters
+const MAX_ERROR_TEXT_CHARS: usize = 16 * 1024;
+
+/// Request ID of the current request, available to handlers as an extension
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// A new random 8-character hex ID
+fn generate_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+/// The caller's ID, if it is short and printable enough to log and echo back
+fn incoming_id(request: &Request) -> Option<String> {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)?
+        .to_str()
+        .ok()?
+        .trim();
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|b| b.is_ascii_graphic());
+    valid.then(|| id.to_string())
+}
+
+/// Middleware assigning each request an ID
+pub async fn request_id(mut request: Request, next: Next) -> Response {
+    let id = incoming_id(&request).unwrap_or_else(generate_id);
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = tracing::info_span!(
+        "request",
+        id = %id,
+        method = %request.method(),
+        path = %request.uri().path()
+    );
+    let mut response = next.run(request).instrument(span.clone()).await;
+
+    if response.status().is_server_error() {
+        span.in_scope(|| tracing::error!("Request failed with {}", response.status()));
+    }
+    if response.status().is_client_error() || response.status().is_server_error() {
+        response = tag_error_body(response, &id).await;
+    }
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Add the request ID to an `ApiError` body; other bodies are left alone
+async fn tag_error_body(response: Response, id: &str) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_ERROR_BODY_BYTES).await else {
+        tracing::warn!("Error body too large to tag with request ID {}", id);
+        let error = ApiError {
+            request_id: Some(id.to_string()),
+            ..ApiError::with_details(
+                parts.status.canonical_reason().unwrap_or("Request failed"),
+                "RESPONSE_TOO_LARGE",
+                "The error was too large to return; see the server log for this request ID",
+            )
+        };
+        parts.headers.remove(header::CONTENT_LENGTH);
+        let body = serde_json::to_vec(&error).unwrap_or_default();
+        return Response::from_parts(parts, Body::from(body));
+    };
+    let tagged = serde_json::from_slice::<ApiError>(&bytes)
+        .ok()
+        .and_then(|mut error| {
+            truncate_text(&mut error.error);
+            if let Some(details) = &mut error.details {
+                truncate_text(details);
+            }
+            error.request_id = Some(id.to_string());
+            serde_json::to_vec(&error).ok()
+        });
+    match tagged {
+        Some(body) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(body))
+        }
+        None => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
+/// Cut a message to [`MAX_ERROR_TEXT_CHARS`], marking that it was cut
+fn truncate_text(text: &mut String) {
+    if let Some((end, _)) = text.char_indices().nth(MAX_ERROR_TEXT_CHARS) {
+        text.truncate(end);
+        text.push('…');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use axum::Json;
+
+    async fn tagged(error: ApiError) -> (StatusCode, ApiError) {
+        let response = (StatusCode::BAD_REQUEST, Json(error)).into_response();
+        let response = tag_error_body(response, "abc").await;
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_long_error_details_are_truncated() {
+        let details = "é".repeat(MAX_ERROR_TEXT_CHARS + 10);
+        let (status, error) = tagged(ApiError::with_details(
+            "Invalid",
+            "VALIDATION_ERROR",
+            details,
+        ))
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            (error.error.as_str(), error.code.as_str()),
+            ("Invalid", "VALIDATION_ERROR")
+        );
+        let details = error.details.unwrap();
+        assert_eq!(details.chars().count(), MAX_ERROR_TEXT_CHARS + 1);
+        assert!(details.ends_with('…'));
+        assert_eq!(error.request_id.as_deref(), Some("abc"));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_error_body_keeps_its_shape() {
+        let details = "x".repeat(MAX_ERROR_BODY_BYTES);
+        let (status, error) = tagged(ApiError::with_details(
+            "Invalid",
+            "VALIDATION_ERROR",
+            details,
+        ))
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error, "Bad Request");
+        assert_eq!(error.code, "RESPONSE_TOO_LARGE");
+        assert_eq!(error.request_id.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_generated_ids_are_short_and_distinct() {
+        let a = generate_id();
+        let b = generate_id();
+        assert_eq!(a.len(), 8);
+        assert!(a.bytes().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_incoming_id_validation() {
+        let request = |value: &str| {
+            Request::builder()
+                .header(REQUEST_ID_HEADER, value)
+                .body(Body::empty())
+                .unwrap()
+        };
+        assert_eq!(
+            incoming_id(&request("abc-123")),
+            Some("abc-123".to_string())
+        );
+        assert_eq!(incoming_id(&request("  ")), None);
+        assert_eq!(incoming_id(&request("has space")), None);
+        assert_eq!(incoming_id(&request(&"x".repeat(65))), None);
+    }
+}
@@ -0,0 +1,173 @@
+//! JSON Schema handlers
+//!
+//! Serve individual component schemas from the OpenAPI document as
+//! self-contained JSON Schemas: every `#/components/schemas/...` reference is
+//! rewritten to point into a local `$defs` section.
+
+use axum::{extract::Path, http::StatusCode, Json};
+use serde_json::{Map, Value};
+use utoipa::OpenApi;
+
+use super::types::ApiError;
+use super::ApiDoc;
+
+/// Prefix of component references in the OpenAPI document
+const COMPONENT_REF_PREFIX: &str = "#/components/schemas/";
+
+/// JSON Schema dialect the returned schemas declare
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// All component schemas from the OpenAPI document as JSON values
+pub(crate) fn component_schemas() -> Map<String, Value> {
+    ApiDoc::openapi()
+        .components
+        .and_then(|c| serde_json::to_value(c.schemas).ok())
+        .and_then(|v| match v {
+            Value::Object(map) => Some(map),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Names of all registered component schemas, sorted
+pub fn schema_names() -> Vec<String> {
+    component_schemas().keys().cloned().collect()
+}
+
+/// Rewrite component references in place, collecting the referenced names
+fn rewrite_refs(value: &mut Value, root: &str, found: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(reference)) = map.get_mut("$ref") {
+                if let Some(name) = reference.strip_prefix(COMPONENT_REF_PREFIX) {
+                    let name = name.to_string();
+                    *reference = if name == root {
+                        "#".to_string()
+                    } else {
+                        format!("#/$defs/{}", name)
+                    };
+                    if name != root {
+                        found.push(name);
+                    }
+                }
+            }
+            for child in map.values_mut() {
+                rewrite_refs(child, root, found);
+            }
+        }
+        Value::Array(items) => {
+            for child in items {
+                rewrite_refs(child, root, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Get a self-contained JSON Schema for a registered component
+pub fn component_schema(name: &str) -> Option<Value> {
+    let components = component_schemas();
+    let mut schema = components.get(name)?.clone();
+
+    let mut pending = Vec::new();
+    rewrite_refs(&mut schema, name, &mut pending);
+
+    let mut defs = Map::new();
+    while let Some(dep) = pending.pop() {
+        if defs.contains_key(&dep) {
+            continue;
+        }
+        let Some(dep_schema) = components.get(&dep) else {
+            continue;
+        };
+        let mut dep_schema = dep_schema.clone();
+        rewrite_refs(&mut dep_schema, name, &mut pending);
+        defs.insert(dep, dep_schema);
+    }
+
+    if let Value::Object(map) = &mut schema {
+        map.insert("$schema".to_string(), Value::from(JSON_SCHEMA_DIALECT));
+        map.insert("title".to_string(), Value::from(name));
+        if !defs.is_empty() {
+            map.insert("$defs".to_string(), Value::Object(defs));
+        }
+    }
+
+    Some(schema)
+}
+
+/// List the names of all available schemas
+#[utoipa::path(
+    get,
+    path = "/api/schema",
+    tag = "schema",
+    responses(
+        (status = 200, description = "Registered schema names", body = Vec<String>)
+    )
+)]
+pub async fn list_schemas() -> Json<Vec<String>> {
+    Json(schema_names())
+}
+
+/// Get the JSON Schema for a single data type
+#[utoipa::path(
+    get,
+    path = "/api/schema/{type}",
+    tag = "schema",
+    params(
+        ("type" = String, Path, description = "Schema name, e.g. RunMetrics")
+    ),
+    responses(
+        (status = 200, description = "Self-contained JSON Schema", body = serde_json::Value),
+        (status = 404, description = "Unknown schema name", body = ApiError)
+    )
+)]
+pub async fn get_schema(
+    Path(name): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<ApiError>)> {
+    component_schema(&name).map(Json).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ApiError::with_details(
+                "Schema not found",
+                "NOT_FOUND",
+                format!("Available schemas: {}", schema_names().join(", ")),
+            )),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_names() {
+        let names = schema_names();
+        assert!(names.contains(&"RunMetrics".to_string()));
+        assert!(names.contains(&"ExportData".to_string()));
+    }
+
+    #[test]
+    fn test_schema_is_self_contained() {
+        let schema = component_schema("ExportData").unwrap();
+        let json = schema.to_string();
+        assert!(!json.contains(COMPONENT_REF_PREFIX));
+        assert_eq!(schema["title"], "ExportData");
+        assert!(schema["$defs"]["RunMetrics"].is_object());
+        assert!(schema["$defs"]["CharacterStats"].is_object());
+    }
+
+    #[test]
+    fn test_nested_enum_is_inlined() {
+        let schema = component_schema("HealthResponse").unwrap();
+        assert!(schema["$defs"]["HealthStatus"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_schema_lists_names() {
+        let (status, error) = get_schema(Path("Nope".to_string())).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(error.details.as_deref().unwrap().contains("RunMetrics"));
+    }
+}
@@ -0,0 +1,253 @@
+//! Streaming JSON responses
+//!
+//! Large responses (every run, the full export) are serialized a batch of
+//! items at a time while the body is sent, instead of building the whole
+//! document in one buffer first. The output is byte-identical to serializing
+//! the value with `serde_json::to_vec`, which is what `Json` does.
+//!
+//! Only the serialized bytes are bounded: the items themselves are handed
+//! over as a `Vec` and stay in memory until they are written. For the export
+//! that is every exported run, which its character stats are computed from
+//! anyway.
+
+use axum::body::{Body, Bytes};
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+/// Number of array items serialized into each body chunk
+pub const ITEMS_PER_CHUNK: usize = 256;
+
+/// Serialize a JSON array in chunks, wrapped in fixed prefix and suffix bytes
+///
+/// Each chunk holds at most [`ITEMS_PER_CHUNK`] items; the prefix goes in the
+/// first chunk and the suffix in the last.
+pub fn array_chunks<T>(
+    prefix: Vec<u8>,
+    items: Vec<T>,
+    suffix: Vec<u8>,
+) -> impl Iterator<Item = Result<Bytes, serde_json::Error>> + Send
+where
+    T: Serialize + Send,
+{
+    let mut items = items.into_iter().peekable();
+    let mut prefix = Some(prefix);
+    let mut suffix = Some(suffix);
+    let mut first = true;
+
+    std::iter::from_fn(move || {
+        let mut buf = match prefix.take() {
+            Some(mut prefix) => {
+                prefix.push(b'[');
+                prefix
+            }
+            None if suffix.is_none() => return None,
+            None => Vec::new(),
+        };
+
+        for item in items.by_ref().take(ITEMS_PER_CHUNK) {
+            if !first {
+                buf.push(b',');
+            }
+            first = false;
+            if let Err(e) = serde_json::to_writer(&mut buf, &item) {
+                suffix = None;
+                return Some(Err(e));
+            }
+        }
+
+        if items.peek().is_none() {
+            if let Some(suffix) = suffix.take() {
+                buf.push(b']');
+                buf.extend(suffix);
+            }
+        }
+        Some(Ok(Bytes::from(buf)))
+    })
+}
+
+/// Split an object serialized with an empty array field around that field
+///
+/// Returns the bytes before and after the empty array, so the real items can
+/// be streamed in between. Fails if the field isn't present as an empty array.
+pub fn split_around_array<S: Serialize>(
+    shell: &S,
+    field: &str,
+) -> Result<(Vec<u8>, Vec<u8>), serde_json::Error> {
+    let bytes = serde_json::to_vec(shell)?;
+    let marker = format!("{}:[]", serde_json::to_string(field)?);
+    let start = bytes
+        .windows(marker.len())
+        .position(|w| w == marker.as_bytes())
+        .ok_or_else(|| {
+            serde::ser::Error::custom(format!("field {} is not an empty array", field))
+        })?;
+    let split = start + marker.len() - 2;
+    Ok((bytes[..split].to_vec(), bytes[split + 2..].to_vec()))
+}
+
+/// Serialize items as newline-delimited JSON, one item per line
+///
+/// Each chunk holds at most [`ITEMS_PER_CHUNK`] lines.
+pub fn ndjson_chunks<T>(
+    items: Vec<T>,
+) -> impl Iterator<Item = Result<Bytes, serde_json::Error>> + Send
+where
+    T: Serialize + Send,
+{
+    let mut items = items.into_iter().peekable();
+    std::iter::from_fn(move || {
+        items.peek()?;
+        let mut buf = Vec::new();
+        for item in items.by_ref().take(ITEMS_PER_CHUNK) {
+            if let Err(e) = serde_json::to_writer(&mut buf, &item) {
+                return Some(Err(e));
+            }
+            buf.push(b'\n');
+        }
+        Some(Ok(Bytes::from(buf)))
+    })
+}
+
+/// Build a streaming `application/json` response from body chunks
+pub fn json_stream_response<I>(chunks: I) -> Response
+where
+    I: Iterator<Item = Result<Bytes, serde_json::Error>> + Send + 'static,
+{
+    let body = Body::from_stream(futures_util::stream::iter(chunks));
+    ([(CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+/// Build a streaming `application/x-ndjson` response from body chunks
+pub fn ndjson_stream_response<I>(chunks: I) -> Response
+where
+    I: Iterator<Item = Result<Bytes, serde_json::Error>> + Send + 'static,
+{
+    let body = Body::from_stream(futures_util::stream::iter(chunks));
+    ([(CONTENT_TYPE, "application/x-ndjson")], body).into_response()
+}
+
+/// Stream a JSON array
+pub fn json_array_response<T>(items: Vec<T>) -> Response
+where
+    T: Serialize + Send + 'static,
+{
+    json_stream_response(array_chunks(Vec::new(), items, Vec::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::{ExportData, RunMetrics};
+
+    fn synthetic_run(i: usize) -> RunMetrics {
+        serde_json::from_value(serde_json::json!({
+            "play_id": format!("run-{}", i),
+            "character": "IRONCLAD",
+            "floor_reached": (i % 57) as i32,
+            "victory": i.is_multiple_of(5),
+            "score": i as i32,
+            "ascension_level": (i % 21) as i32,
+            "deck_size": 30,
+            "attack_count": 12,
+            "skill_count": 12,
+            "power_count": 6,
+            "upgraded_cards": 8,
+            "cards_removed": 2,
+            "relic_count": 2,
+            "relics": ["Burning Blood", "Vajra"],
+            "master_deck": ["Strike_R", "Defend_R", "Bash+1"],
+            "elites_killed": 3,
+            "bosses_killed": 1,
+            "campfires_rested": 2,
+            "campfires_upgraded": 3,
+            "shops_visited": 2,
+            "cards_purchased": 1,
+            "potions_used": 4,
+            "total_damage_taken": 150,
+            "max_hp_at_end": 80,
+            "killed_by": null,
+            "hidden": false
+        }))
+        .unwrap()
+    }
+
+    fn collect(chunks: impl Iterator<Item = Result<Bytes, serde_json::Error>>) -> Vec<Bytes> {
+        chunks.map(|c| c.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_array_chunks_match_serde() {
+        for count in [0, 1, ITEMS_PER_CHUNK, ITEMS_PER_CHUNK + 1] {
+            let runs: Vec<RunMetrics> = (0..count).map(synthetic_run).collect();
+            let expected = serde_json::to_vec(&runs).unwrap();
+            let streamed = collect(array_chunks(Vec::new(), runs, Vec::new())).concat();
+            assert_eq!(streamed, expected, "mismatch for {} items", count);
+        }
+    }
+
+    #[test]
+    fn test_export_stream_matches_serde() {
+        let runs: Vec<RunMetrics> = (0..3).map(synthetic_run).collect();
+        let export = ExportData {
+            schema_version: crate::sts::EXPORT_SCHEMA_VERSION,
+            runs: runs.clone(),
+            character_stats: crate::sts::calculate_character_stats(&runs),
+            ascension_stats: crate::sts::ascension_stats(&runs, &Default::default()),
+            export_timestamp: 1_700_000_000,
+            export_datetime: chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            export_timezone_offset_minutes: -300,
+            app_version: Some("0.1.0".to_string()),
+            runs_path: Some("~/runs".to_string()),
+            filters: Default::default(),
+            checksum: None,
+        };
+        let expected = serde_json::to_vec(&export).unwrap();
+
+        let shell = ExportData {
+            runs: Vec::new(),
+            ..export
+        };
+        let (prefix, suffix) = split_around_array(&shell, "runs").unwrap();
+        let streamed = collect(array_chunks(prefix, runs, suffix)).concat();
+        assert_eq!(streamed, expected);
+    }
+
+    #[test]
+    fn test_large_array_is_streamed_in_bounded_chunks() {
+        let count = 5000;
+        let runs: Vec<RunMetrics> = (0..count).map(synthetic_run).collect();
+        let one_run = serde_json::to_vec(&runs[0]).unwrap().len();
+
+        let chunks = collect(array_chunks(Vec::new(), runs, Vec::new()));
+        assert_eq!(chunks.len(), count.div_ceil(ITEMS_PER_CHUNK));
+        // No chunk holds much more than one batch of runs
+        let limit = (one_run + 16) * ITEMS_PER_CHUNK;
+        assert!(chunks.iter().all(|c| c.len() <= limit));
+
+        let parsed: Vec<RunMetrics> = serde_json::from_slice(&chunks.concat()).unwrap();
+        assert_eq!(parsed.len(), count);
+        assert_eq!(parsed[count - 1].play_id, format!("run-{}", count - 1));
+    }
+
+    #[test]
+    fn test_ndjson_chunks() {
+        let runs: Vec<RunMetrics> = (0..ITEMS_PER_CHUNK + 1).map(synthetic_run).collect();
+        let chunks = collect(ndjson_chunks(runs));
+        assert_eq!(chunks.len(), 2);
+
+        let body = chunks.concat();
+        let lines: Vec<&[u8]> = body.split(|&b| b == b'\n').collect();
+        // The body ends with a newline, leaving an empty last piece
+        assert_eq!(lines.len(), ITEMS_PER_CHUNK + 2);
+        let last: RunMetrics = serde_json::from_slice(lines[ITEMS_PER_CHUNK]).unwrap();
+        assert_eq!(last.play_id, format!("run-{}", ITEMS_PER_CHUNK));
+        assert!(collect(ndjson_chunks(Vec::<RunMetrics>::new())).is_empty());
+    }
+
+    #[test]
+    fn test_split_around_missing_field() {
+        let value = serde_json::json!({ "runs": [1] });
+        assert!(split_around_array(&value, "runs").is_err());
+    }
+}
@@ -3,28 +3,437 @@
 //! These handlers serve Slay the Spire run data to the frontend.
 
 use axum::{
-    extract::{Path, Query},
-    http::StatusCode,
+    extract::{FromRequestParts, Path, Query},
+    http::{header, request::Parts, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
+use chrono::NaiveDate;
 use serde::Deserialize;
+use utoipa::ToSchema;
 
 use crate::sts::{
-    calculate_character_stats, get_export_data, load_all_runs, Character, CharacterStats,
-    ExportData, RunMetrics,
+    act_boss_stats, ascension_normalized, ascension_stats, boss_relic_stats, boss_swap_stats,
+    cached_runs_delta, calculate_character_stats_with, calendar, calendar_years, csv,
+    dataset_summary, enemy_catalog, export::import_export_value, feature_set, find_run,
+    first_relic_stats, get_export_data_with, parse_deck_size_bounds, personal_bests,
+    purged_card_stats, relic_source_stats, relic_timing, runs_delta, scan_options, set_run_hidden,
+    slow_fights, sort_character_stats, try_load_all_runs_with, ActBossStats, AscensionStats,
+    BossRelicStats, BossSwapStats, CalendarDay, Character, CharacterStats, DatasetSummary,
+    DeckSizeStats, DeltaCursor, EliteStats, EncounterTurns, EnemyCount, EventStats, ExportData,
+    ExportFilter, ExportOptions, FeatureSet, FirstRelicStats, ImportError, Locale,
+    PeriodComparison, PersonalBests, PlayTimeStats, PotionNameStats, PotionStats, ProgressReport,
+    PurgedCardStats, RelicSourceStats, RelicTiming, ReparseError, Ruleset, RunDelta, RunDetail,
+    RunExclusion, RunFilter, RunMetrics, RunSelection, RunsPathNotFound, ShopStats, SkipRateStats,
+    StatsOptions, StatsSortKey, TimeBucketing, TimeWindow, UpgradeImpactStats,
+    DEFAULT_DECK_SIZE_BOUNDS, DEFAULT_MIN_BUCKET_RUNS,
 };
 
-use super::types::ApiError;
+use super::pagination::{paginate, PageInfo};
+use super::streaming::{
+    array_chunks, json_array_response, json_stream_response, ndjson_chunks, ndjson_stream_response,
+    split_around_array,
+};
+use super::types::{ApiError, RunVisibility};
+use crate::config;
+use crate::status::{watcher_state, WatcherState};
 
 /// Query parameters for runs endpoint
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize, ToSchema)]
 pub struct RunsQuery {
-    /// Filter by character
+    /// Only consider runs of these characters (comma-separated)
     pub character: Option<String>,
+    /// Leave out runs of these characters (comma-separated)
+    pub exclude_character: Option<String>,
+    /// Kinds of run to leave out (comma-separated, see [`RunExclusion`])
+    pub exclude: Option<String>,
+    /// Only runs played under these rulesets (comma-separated, see [`Ruleset`])
+    pub ruleset: Option<String>,
     /// Filter by victory only
     pub victories_only: Option<bool>,
     /// Minimum ascension level
     pub min_ascension: Option<i32>,
+    /// Maximum ascension level
+    pub max_ascension: Option<i32>,
+    /// Only runs that ended at or after this time (unix seconds)
+    pub since: Option<i64>,
+    /// Only runs that ended before this time (unix seconds)
+    pub until: Option<i64>,
+    /// Only runs that ended holding all of these relics (comma-separated)
+    pub relic: Option<String>,
+    /// Only runs whose final deck holds all of these cards (comma-separated)
+    pub card: Option<String>,
+    /// Only runs that ended in a fight against this encounter
+    pub killed_by: Option<String>,
+    /// Include runs hidden from statistics
+    pub include_hidden: Option<bool>,
+    /// Search subfolders of character directories (defaults to the config)
+    pub recursive: Option<bool>,
+    /// Maximum number of runs to return
+    pub limit: Option<usize>,
+    /// Number of runs to skip
+    pub offset: Option<usize>,
+}
+
+/// Non-empty names from a comma-separated query parameter
+fn name_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl TryFrom<&RunsQuery> for RunFilter {
+    type Error = (StatusCode, Json<ApiError>);
+
+    fn try_from(query: &RunsQuery) -> Result<Self, Self::Error> {
+        let mut selection = run_selection(
+            query.character.as_deref(),
+            query.exclude_character.as_deref(),
+            query.exclude.as_deref(),
+        )?;
+        selection.rulesets = resolve_rulesets(query.ruleset.as_deref())?;
+        let filter = RunFilter {
+            include_hidden: query.include_hidden.unwrap_or(false),
+            selection,
+            window: time_window(query.since, query.until)?,
+            min_ascension: query.min_ascension,
+            max_ascension: query.max_ascension,
+            victory: query.victories_only.filter(|&only| only),
+            relics: name_list(query.relic.as_deref()),
+            cards: name_list(query.card.as_deref()),
+            killed_by: query
+                .killed_by
+                .as_deref()
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(str::to_string),
+        };
+        filter.validate().map_err(|details| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::with_details(
+                    "Invalid run filter",
+                    "INVALID_QUERY",
+                    details,
+                )),
+            )
+        })?;
+        Ok(filter)
+    }
+}
+
+/// Query parameters for the character runs endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct CharacterRunsQuery {
+    /// Include runs hidden from statistics
+    pub include_hidden: Option<bool>,
+    /// Search subfolders of character directories (defaults to the config)
+    pub recursive: Option<bool>,
+    /// Maximum number of runs to return
+    pub limit: Option<usize>,
+    /// Number of runs to skip
+    pub offset: Option<usize>,
+}
+
+/// Query parameters for stats endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct StatsQuery {
+    /// Include runs hidden from statistics
+    pub include_hidden: Option<bool>,
+    /// Only runs that ended at or after this time (unix seconds)
+    pub since: Option<i64>,
+    /// Only runs that ended before this time (unix seconds)
+    pub until: Option<i64>,
+}
+
+impl StatsQuery {
+    fn options(&self) -> Result<StatsOptions, (StatusCode, Json<ApiError>)> {
+        Ok(StatsOptions {
+            include_hidden: self.include_hidden.unwrap_or(false),
+            window: time_window(self.since, self.until)?,
+            ..Default::default()
+        })
+    }
+}
+
+/// Response header echoing the applied `since` bound (unix seconds)
+pub const STATS_SINCE_HEADER: &str = "x-stats-since";
+/// Response header echoing the applied `until` bound (unix seconds)
+pub const STATS_UNTIL_HEADER: &str = "x-stats-until";
+
+/// Headers echoing the time window of stats, exposed to browser clients via
+/// CORS
+pub const TIME_WINDOW_HEADERS: [&str; 2] = [STATS_SINCE_HEADER, STATS_UNTIL_HEADER];
+
+/// Time window from `since` and `until` query parameters
+///
+/// Returns a 400 `INVALID_QUERY` error when the window ends before it starts.
+fn time_window(
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<TimeWindow, (StatusCode, Json<ApiError>)> {
+    let window = TimeWindow { since, until };
+    window.validate().map_err(|details| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::with_details(
+                "Invalid time window",
+                "INVALID_QUERY",
+                details,
+            )),
+        )
+    })?;
+    Ok(window)
+}
+
+/// The bounds of a time window as response headers; unset bounds are left out
+fn time_window_headers(window: &TimeWindow) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, bound) in [
+        (STATS_SINCE_HEADER, window.since),
+        (STATS_UNTIL_HEADER, window.until),
+    ] {
+        if let Some(bound) = bound {
+            headers.insert(HeaderName::from_static(name), HeaderValue::from(bound));
+        }
+    }
+    headers
+}
+
+/// 503 error for when no runs directory is configured or detected
+fn runs_path_not_found(e: RunsPathNotFound) -> (StatusCode, Json<ApiError>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ApiError::with_details(
+            "Runs directory not found",
+            "RUNS_PATH_NOT_FOUND",
+            e.to_string(),
+        )),
+    )
+}
+
+/// Accepted character names, for error details
+fn valid_characters_hint() -> String {
+    let valid: Vec<&str> = Character::all().iter().map(|c| c.dir_name()).collect();
+    let aliases: Vec<&str> = Character::all()
+        .iter()
+        .flat_map(|c| c.aliases().iter().copied())
+        .collect();
+    format!(
+        "Valid characters: {} (display names and the aliases {} are also accepted)",
+        valid.join(", "),
+        aliases.join(", ")
+    )
+}
+
+/// 404 error for a character name that doesn't resolve, listing accepted names
+fn character_not_found() -> (StatusCode, Json<ApiError>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiError::with_details(
+            "Character not found",
+            "NOT_FOUND",
+            valid_characters_hint(),
+        )),
+    )
+}
+
+/// Resolve a character name from a path or query parameter
+fn resolve_character(name: &str) -> Result<Character, (StatusCode, Json<ApiError>)> {
+    name.parse().map_err(|_| character_not_found())
+}
+
+/// `{character}` path segment, rejected with a 404 unless it names a character
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharacterPath(pub Character);
+
+impl<S: Send + Sync> FromRequestParts<S> for CharacterPath {
+    type Rejection = (StatusCode, Json<ApiError>);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(name) = Path::<String>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| character_not_found())?;
+        resolve_character(&name).map(CharacterPath)
+    }
+}
+
+/// Locale picked from the request's `Accept-Language` header (English without
+/// one), used for display names in responses
+#[derive(Debug, Clone, Copy)]
+pub struct AcceptLanguage(pub Locale);
+
+impl<S: Send + Sync> FromRequestParts<S> for AcceptLanguage {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let locale = parts
+            .headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map_or_else(Locale::default, Locale::negotiate);
+        Ok(AcceptLanguage(locale))
+    }
+}
+
+/// 400 error for a query parameter that lists values that don't resolve
+fn invalid_list(message: &str, failed: String, hint: String) -> (StatusCode, Json<ApiError>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ApiError::with_details(
+            message,
+            "INVALID_QUERY",
+            format!("{}. {}", failed, hint),
+        )),
+    )
+}
+
+/// Resolve a comma-separated list of character names
+///
+/// Each name is resolved like a `{character}` path segment. An empty list,
+/// or one with names that don't resolve, is rejected with a 400 naming what
+/// failed; `param` names the query parameter in the message.
+fn resolve_characters(
+    param: &str,
+    value: Option<&str>,
+) -> Result<Vec<Character>, (StatusCode, Json<ApiError>)> {
+    let Some(value) = value else {
+        return Ok(Vec::new());
+    };
+    let mut characters = Vec::new();
+    let mut unknown = Vec::new();
+    for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        match name.parse::<Character>() {
+            Ok(character) if !characters.contains(&character) => characters.push(character),
+            Ok(_) => {}
+            Err(e) => unknown.push(e.0),
+        }
+    }
+    if !unknown.is_empty() || characters.is_empty() {
+        let failed = if unknown.is_empty() {
+            "No character names given".to_string()
+        } else {
+            format!("Unknown characters: {}", unknown.join(", "))
+        };
+        return Err(invalid_list(
+            &format!("Invalid {} filter", param),
+            failed,
+            valid_characters_hint(),
+        ));
+    }
+    Ok(characters)
+}
+
+/// Resolve an `exclude` query parameter, a comma-separated list of
+/// [`RunExclusion`] names
+fn resolve_exclusions(
+    value: Option<&str>,
+) -> Result<Vec<RunExclusion>, (StatusCode, Json<ApiError>)> {
+    let mut exclude = Vec::new();
+    let mut unknown = Vec::new();
+    for name in value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+    {
+        match RunExclusion::from_name(name) {
+            Some(exclusion) if !exclude.contains(&exclusion) => exclude.push(exclusion),
+            Some(_) => {}
+            None => unknown.push(name),
+        }
+    }
+    if !unknown.is_empty() {
+        let valid: Vec<&str> = RunExclusion::ALL.iter().map(|e| e.name()).collect();
+        return Err(invalid_list(
+            "Invalid exclude filter",
+            format!("Unknown values: {}", unknown.join(", ")),
+            format!("Valid values: {}", valid.join(", ")),
+        ));
+    }
+    Ok(exclude)
+}
+
+/// Resolve a `ruleset` query parameter, a comma-separated list of
+/// [`Ruleset`] names
+fn resolve_rulesets(value: Option<&str>) -> Result<Vec<Ruleset>, (StatusCode, Json<ApiError>)> {
+    let mut rulesets = Vec::new();
+    let mut unknown = Vec::new();
+    for name in value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+    {
+        match Ruleset::from_name(name) {
+            Some(ruleset) if !rulesets.contains(&ruleset) => rulesets.push(ruleset),
+            Some(_) => {}
+            None => unknown.push(name),
+        }
+    }
+    if !unknown.is_empty() {
+        let valid: Vec<&str> = Ruleset::ALL.iter().map(|r| r.name()).collect();
+        return Err(invalid_list(
+            "Invalid ruleset filter",
+            format!("Unknown values: {}", unknown.join(", ")),
+            format!("Valid values: {}", valid.join(", ")),
+        ));
+    }
+    Ok(rulesets)
+}
+
+/// Run selection from the `character`, `exclude_character` and `exclude`
+/// query parameters
+///
+/// A character both included and excluded is rejected with a 400.
+fn run_selection(
+    character: Option<&str>,
+    exclude_character: Option<&str>,
+    exclude: Option<&str>,
+) -> Result<RunSelection, (StatusCode, Json<ApiError>)> {
+    let selection = RunSelection {
+        characters: resolve_characters("character", character)?,
+        exclude_characters: resolve_characters("exclude_character", exclude_character)?,
+        exclude: resolve_exclusions(exclude)?,
+        ..Default::default()
+    };
+    selection.validate().map_err(|details| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::with_details(
+                "Conflicting character filters",
+                "INVALID_QUERY",
+                details,
+            )),
+        )
+    })?;
+    Ok(selection)
+}
+
+/// Resolve an optional `character` query filter
+///
+/// A run matches if it belongs to any of the listed characters.
+fn resolve_character_filter(
+    value: Option<&str>,
+) -> Result<RunSelection, (StatusCode, Json<ApiError>)> {
+    run_selection(value, None, None)
+}
+
+/// Load all runs, failing with 503 when no runs directory is resolved
+///
+/// `recursive` overrides the configured scan depth for this request.
+pub(super) fn load_runs(
+    recursive: Option<bool>,
+) -> Result<Vec<RunMetrics>, (StatusCode, Json<ApiError>)> {
+    let mut options = scan_options();
+    if let Some(recursive) = recursive {
+        options.recursive = recursive;
+    }
+    try_load_all_runs_with(options).map_err(runs_path_not_found)
 }
 
 /// Get all runs with optional filtering
@@ -33,32 +442,125 @@ pub struct RunsQuery {
     path = "/api/runs",
     tag = "sts",
     params(
-        ("character" = Option<String>, Query, description = "Filter by character name"),
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("exclude_character" = Option<String>, Query, description = "Leave out runs of these characters (comma-separated)"),
+        ("exclude" = Option<String>, Query, description = "Kinds of run to leave out: a comma-separated list of dailies, seeded, endless, custom, non_standard, modded, abandoned and hidden"),
+        ("ruleset" = Option<String>, Query, description = "Only runs played under these rulesets: a comma-separated list of standard, seeded, daily, endless and custom"),
         ("victories_only" = Option<bool>, Query, description = "Only return victories"),
-        ("min_ascension" = Option<i32>, Query, description = "Minimum ascension level")
+        ("min_ascension" = Option<i32>, Query, description = "Minimum ascension level"),
+        ("max_ascension" = Option<i32>, Query, description = "Maximum ascension level"),
+        ("since" = Option<i64>, Query, description = "Only runs that ended at or after this time (unix seconds)"),
+        ("until" = Option<i64>, Query, description = "Only runs that ended before this time (unix seconds)"),
+        ("relic" = Option<String>, Query, description = "Only runs that ended holding all of these relics: a comma-separated list, ignoring case"),
+        ("card" = Option<String>, Query, description = "Only runs whose final deck holds all of these cards, upgraded or not: a comma-separated list, ignoring case"),
+        ("killed_by" = Option<String>, Query, description = "Only runs that ended in a fight against this encounter, ignoring case"),
+        ("include_hidden" = Option<bool>, Query, description = "Include runs hidden from statistics"),
+        ("recursive" = Option<bool>, Query, description = "Also search subfolders of character directories (defaults to the recursive_scan setting)"),
+        ("limit" = Option<usize>, Query, description = "Maximum number of runs to return (default 1000, at most 5000 unless configured otherwise)"),
+        ("offset" = Option<usize>, Query, description = "Number of runs to skip")
     ),
     responses(
-        (status = 200, description = "List of runs", body = Vec<RunMetrics>),
-        (status = 500, description = "Server error", body = ApiError)
+        (status = 200, description = "List of runs", body = Vec<RunMetrics>, headers(
+            ("x-total-count" = usize, description = "Runs matching the filters before pagination"),
+            ("x-offset" = usize, description = "Offset of the first returned run"),
+            ("x-limit" = usize, description = "Limit that was applied"),
+            ("x-truncated" = bool, description = "Whether more runs exist after this page")
+        )),
+        (status = 400, description = "Limit above the maximum, or an invalid or conflicting run filter", body = ApiError),
+        (status = 500, description = "Server error", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
     )
 )]
-pub async fn get_runs(Query(params): Query<RunsQuery>) -> Json<Vec<RunMetrics>> {
-    let mut runs = load_all_runs();
+pub async fn get_runs(
+    Query(params): Query<RunsQuery>,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    let (runs, page) = params.page(|| load_runs(params.recursive))?;
+    Ok((page.headers(), json_array_response(runs)).into_response())
+}
 
-    // Apply filters
-    if let Some(ref char) = params.character {
-        runs.retain(|r| r.character.eq_ignore_ascii_case(char));
-    }
+impl RunsQuery {
+    /// The requested page of the runs `load` returns
+    ///
+    /// The filters are checked before `load` is called.
+    pub(super) fn page<R: AsRef<[RunMetrics]>>(
+        &self,
+        load: impl FnOnce() -> Result<R, (StatusCode, Json<ApiError>)>,
+    ) -> Result<(Vec<RunMetrics>, PageInfo), (StatusCode, Json<ApiError>)> {
+        let filter = RunFilter::try_from(self)?;
+        let runs = load()?;
+        let runs = filter.apply(runs.as_ref());
 
-    if params.victories_only.unwrap_or(false) {
-        runs.retain(|r| r.victory);
+        let limits = config::current().list_limits;
+        let (runs, page) = paginate(runs, self.limit, self.offset, &limits)?;
+        let runs = runs
+            .into_iter()
+            .map(|r| r.clone().without_source())
+            .collect();
+        Ok((runs, page))
     }
+}
 
-    if let Some(min_asc) = params.min_ascension {
-        runs.retain(|r| r.ascension_level >= min_asc);
-    }
+/// Query parameters for the runs delta endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct DeltaQuery {
+    /// Cursor from a previous delta response, or an RFC 3339 timestamp
+    pub since: Option<String>,
+}
+
+/// Get runs added, changed or removed since a cursor
+///
+/// Without `since` (or with a cursor from before a restart, or too old for
+/// the removals still kept) every run is returned and `reset` is set. Hidden
+/// runs are included with their flag set. While auto-refresh is on, the
+/// answer comes from the last scan rather than a new one.
+#[utoipa::path(
+    get,
+    path = "/api/runs/delta",
+    tag = "sts",
+    params(
+        ("since" = Option<String>, Query, description = "Cursor from a previous response, or an RFC 3339 timestamp")
+    ),
+    responses(
+        (status = 200, description = "Runs changed since the cursor", body = RunDelta),
+        (status = 400, description = "Invalid cursor", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_runs_delta(
+    Query(params): Query<DeltaQuery>,
+) -> Result<Json<RunDelta>, (StatusCode, Json<ApiError>)> {
+    let since = params
+        .since
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .map(DeltaCursor::parse)
+        .transpose()
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::with_details(
+                    "Invalid cursor",
+                    "INVALID_CURSOR",
+                    e,
+                )),
+            )
+        })?;
 
-    Json(runs)
+    // A watcher or the poller keeps the cache current, so the runs directory
+    // only needs a rescan when neither runs
+    let delta = match watcher_state() {
+        WatcherState::Running { .. } | WatcherState::Polling { .. } => {
+            cached_runs_delta(since.as_ref())
+        }
+        WatcherState::Disabled | WatcherState::Failed { .. } => runs_delta(since.as_ref()),
+    };
+    let mut delta = delta.map_err(runs_path_not_found)?;
+    delta.runs = delta
+        .runs
+        .into_iter()
+        .map(RunMetrics::without_source)
+        .collect();
+    Ok(Json(delta))
 }
 
 /// Get runs for a specific character
@@ -67,129 +569,2738 @@ pub async fn get_runs(Query(params): Query<RunsQuery>) -> Json<Vec<RunMetrics>>
     path = "/api/runs/{character}",
     tag = "sts",
     params(
-        ("character" = String, Path, description = "Character name (IRONCLAD, THE_SILENT, DEFECT, WATCHER)")
+        ("character" = String, Path, description = "Character name (IRONCLAD, THE_SILENT, DEFECT, WATCHER, or a display name or alias such as silent)"),
+        ("include_hidden" = Option<bool>, Query, description = "Include runs hidden from statistics"),
+        ("recursive" = Option<bool>, Query, description = "Also search subfolders of character directories (defaults to the recursive_scan setting)"),
+        ("limit" = Option<usize>, Query, description = "Maximum number of runs to return (default 1000, at most 5000 unless configured otherwise)"),
+        ("offset" = Option<usize>, Query, description = "Number of runs to skip")
     ),
     responses(
-        (status = 200, description = "Character runs", body = Vec<RunMetrics>),
-        (status = 404, description = "Character not found", body = ApiError)
+        (status = 200, description = "Character runs", body = Vec<RunMetrics>, headers(
+            ("x-total-count" = usize, description = "Runs matching the filters before pagination"),
+            ("x-offset" = usize, description = "Offset of the first returned run"),
+            ("x-limit" = usize, description = "Limit that was applied"),
+            ("x-truncated" = bool, description = "Whether more runs exist after this page")
+        )),
+        (status = 400, description = "Limit above the maximum", body = ApiError),
+        (status = 404, description = "Character not found", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
     )
 )]
 pub async fn get_character_runs(
-    Path(character): Path<String>,
-) -> Result<Json<Vec<RunMetrics>>, (StatusCode, Json<ApiError>)> {
-    // Validate character name
-    let valid_chars: Vec<&str> = Character::all().iter().map(|c| c.dir_name()).collect();
+    CharacterPath(character): CharacterPath,
+    Query(params): Query<CharacterRunsQuery>,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    let filter = RunFilter {
+        include_hidden: params.include_hidden.unwrap_or(false),
+        selection: RunSelection {
+            characters: vec![character],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut runs = load_runs(params.recursive)?;
+    filter.retain(&mut runs);
 
-    if !valid_chars
-        .iter()
-        .any(|c| c.eq_ignore_ascii_case(&character))
-    {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(ApiError::with_details(
-                "Character not found",
-                "NOT_FOUND",
-                format!("Valid characters: {}", valid_chars.join(", ")),
+    let limits = config::current().list_limits;
+    let (runs, page) = paginate(runs, params.limit, params.offset, &limits)?;
+    let runs: Vec<RunMetrics> = runs.into_iter().map(RunMetrics::without_source).collect();
+    Ok((page.headers(), json_array_response(runs)).into_response())
+}
+
+/// Query parameters for the all-characters stats endpoint
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct StatsListQuery {
+    /// Include runs hidden from statistics
+    pub include_hidden: Option<bool>,
+    /// Field to sort by (defaults to character order)
+    pub sort_by: Option<String>,
+    /// `asc` or `desc` (the default)
+    pub order: Option<String>,
+    /// Leave out characters with fewer runs than this
+    pub min_runs: Option<i32>,
+    /// Leave out runs of these characters (comma-separated)
+    pub exclude_character: Option<String>,
+    /// Kinds of run to leave out (comma-separated, see [`RunExclusion`])
+    pub exclude: Option<String>,
+    /// `ascension` to add a win rate normalized across ascension bands
+    pub normalize: Option<String>,
+    /// Only runs that ended at or after this time (unix seconds)
+    pub since: Option<i64>,
+    /// Only runs that ended before this time (unix seconds)
+    pub until: Option<i64>,
+    /// Also give the behavioral averages of won and lost runs separately
+    pub split_by_outcome: Option<bool>,
+}
+
+impl StatsListQuery {
+    /// Runs to count, the same options the desktop `get_stats` command takes
+    fn options(&self) -> Result<StatsOptions, (StatusCode, Json<ApiError>)> {
+        Ok(StatsOptions {
+            include_hidden: self.include_hidden.unwrap_or(false),
+            selection: run_selection(
+                None,
+                self.exclude_character.as_deref(),
+                self.exclude.as_deref(),
+            )?,
+            window: time_window(self.since, self.until)?,
+            split_by_outcome: self.split_by_outcome.unwrap_or(false),
+        })
+    }
+
+    /// Whether `normalize=ascension` was requested
+    fn normalize_ascension(&self) -> Result<bool, (StatusCode, Json<ApiError>)> {
+        match self.normalize.as_deref().map(str::trim) {
+            None => Ok(false),
+            Some(mode) if mode.eq_ignore_ascii_case("ascension") => Ok(true),
+            Some(_) => Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::with_details(
+                    "Unknown normalization",
+                    "INVALID_QUERY",
+                    "Valid values: ascension",
+                )),
             )),
-        ));
+        }
     }
 
-    let runs: Vec<RunMetrics> = load_all_runs()
-        .into_iter()
-        .filter(|r| r.character.eq_ignore_ascii_case(&character))
-        .collect();
+    /// The requested sort, if any
+    fn sort(&self) -> Result<Option<StatsSort>, (StatusCode, Json<ApiError>)> {
+        let invalid = |message: &str, valid: &[&str]| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::with_details(
+                    message,
+                    "INVALID_QUERY",
+                    format!("Valid values: {}", valid.join(", ")),
+                )),
+            )
+        };
+        let descending = match self.order.as_deref().map(|o| o.trim().to_ascii_lowercase()) {
+            None => true,
+            Some(order) if order == "desc" => true,
+            Some(order) if order == "asc" => false,
+            Some(_) => return Err(invalid("Unknown sort order", &["asc", "desc"])),
+        };
+        self.sort_by
+            .as_deref()
+            .map(|name| {
+                StatsSortKey::from_name(name)
+                    .map(|key| StatsSort { key, descending })
+                    .ok_or_else(|| invalid("Unknown sort field", StatsSortKey::NAMES))
+            })
+            .transpose()
+    }
+}
 
-    Ok(Json(runs))
+/// Sort requested through [`StatsListQuery`]
+struct StatsSort {
+    key: StatsSortKey,
+    descending: bool,
 }
 
 /// Get aggregated stats for all characters
+///
+/// Characters come in character list order unless `sort_by` is given;
+/// characters with equal values keep that order.
+///
+/// With `normalize=ascension`, each character also gets
+/// `ascension_normalized`: runs are grouped into ascension bands (A0-4, A5-9,
+/// A10-14, A15-19 and A20+), a win rate is computed within each band that has
+/// runs, and those band rates are averaged with equal weights. This compares
+/// characters played at different ascensions more fairly than the raw
+/// `win_rate`, but it is not a prediction of any one ascension's win rate,
+/// and a band with few runs moves it as much as a band with many. Bands with
+/// fewer than 5 runs are listed with `low_confidence` set.
+///
+/// `since` and `until` limit the stats to runs that ended in a time window,
+/// together with the other filters; the window applied is echoed in the
+/// `x-stats-since` and `x-stats-until` response headers.
 #[utoipa::path(
     get,
     path = "/api/stats",
     tag = "sts",
+    params(
+        ("include_hidden" = Option<bool>, Query, description = "Include runs hidden from statistics"),
+        ("sort_by" = Option<String>, Query, description = "Sort by win_rate, total_runs, avg_floor, avg_score or avg_score_per_floor"),
+        ("order" = Option<String>, Query, description = "Sort order: desc (default) or asc"),
+        ("min_runs" = Option<i32>, Query, description = "Leave out characters with fewer runs than this"),
+        ("exclude_character" = Option<String>, Query, description = "Leave out runs of these characters (comma-separated)"),
+        ("exclude" = Option<String>, Query, description = "Kinds of run to leave out: a comma-separated list of dailies, seeded, endless, custom, non_standard, modded, abandoned and hidden"),
+        ("normalize" = Option<String>, Query, description = "ascension: add a win rate averaged over ascension bands with equal weights"),
+        ("since" = Option<i64>, Query, description = "Only count runs that ended at or after this time (unix seconds)"),
+        ("until" = Option<i64>, Query, description = "Only count runs that ended before this time (unix seconds)"),
+        ("split_by_outcome" = Option<bool>, Query, description = "Add by_outcome to each character: the elite, campfire and shop averages of won and lost runs"),
+        ("accept-language" = Option<String>, Header, description = "Language of display_name: en (default), de, fr, es or zh")
+    ),
     responses(
-        (status = 200, description = "Character statistics", body = Vec<CharacterStats>)
+        (status = 200, description = "Character statistics", body = Vec<CharacterStats>, headers(
+            ("x-stats-since" = Option<i64>, description = "The since bound applied, if any"),
+            ("x-stats-until" = Option<i64>, description = "The until bound applied, if any")
+        )),
+        (status = 400, description = "Unknown sort field or order, an invalid run filter, or a time window ending before it starts", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
     )
 )]
-pub async fn get_stats() -> Json<Vec<CharacterStats>> {
-    let runs = load_all_runs();
-    let stats = calculate_character_stats(&runs);
-    Json(stats)
+pub async fn get_stats(
+    AcceptLanguage(locale): AcceptLanguage,
+    Query(params): Query<StatsListQuery>,
+) -> Result<(HeaderMap, Json<Vec<CharacterStats>>), (StatusCode, Json<ApiError>)> {
+    let (window, mut stats) = params.character_stats(|| load_runs(None))?;
+    for entry in &mut stats {
+        entry.localize(locale);
+    }
+    Ok((time_window_headers(&window), Json(stats)))
 }
 
-/// Get stats for a specific character
+impl StatsListQuery {
+    /// Character stats for the runs `load` returns, with the window applied
+    ///
+    /// The query is checked before `load` is called.
+    pub(super) fn character_stats<R: AsRef<[RunMetrics]>>(
+        &self,
+        load: impl FnOnce() -> Result<R, (StatusCode, Json<ApiError>)>,
+    ) -> Result<(TimeWindow, Vec<CharacterStats>), (StatusCode, Json<ApiError>)> {
+        let sort = self.sort()?;
+        let normalize = self.normalize_ascension()?;
+        let options = self.options()?;
+        let runs = load()?;
+        let runs = runs.as_ref();
+        let mut stats = calculate_character_stats_with(runs, &options);
+        if normalize {
+            let by_ascension = ascension_stats(runs, &options);
+            for entry in &mut stats {
+                let levels: Vec<AscensionStats> = by_ascension
+                    .iter()
+                    .filter(|a| a.character == entry.character)
+                    .cloned()
+                    .collect();
+                entry.ascension_normalized = Some(ascension_normalized(&levels));
+            }
+        }
+        if let Some(min_runs) = self.min_runs {
+            stats.retain(|s| s.total_runs >= min_runs);
+        }
+        if let Some(sort) = sort {
+            sort_character_stats(&mut stats, sort.key, sort.descending);
+        }
+        Ok((options.window, stats))
+    }
+}
+
+/// Query parameters for the calendar endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct CalendarQuery {
+    /// Year to return (defaults to the current year)
+    pub year: Option<i32>,
+    /// Offset of the viewer's timezone from UTC, in minutes east
+    pub tz_offset_minutes: Option<i32>,
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Include runs hidden from statistics
+    pub include_hidden: Option<bool>,
+}
+
+/// Bucketing for a `tz_offset_minutes` parameter (UTC if missing)
+///
+/// Offsets outside UTC-14:00..=UTC+14:00 are rejected with a 400.
+fn time_bucketing(
+    tz_offset_minutes: Option<i32>,
+) -> Result<TimeBucketing, (StatusCode, Json<ApiError>)> {
+    TimeBucketing::new(tz_offset_minutes.unwrap_or(0)).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::with_details(
+                "Invalid timezone offset",
+                "INVALID_QUERY",
+                e.to_string(),
+            )),
+        )
+    })
+}
+
+impl CalendarQuery {
+    /// Bucketing for the timezone offset, rejecting values outside
+    /// UTC-14:00..=UTC+14:00
+    fn bucketing(&self) -> Result<TimeBucketing, (StatusCode, Json<ApiError>)> {
+        time_bucketing(self.tz_offset_minutes)
+    }
+
+    /// Load the runs selected by the character and hidden filters
+    fn runs(&self) -> Result<Vec<RunMetrics>, (StatusCode, Json<ApiError>)> {
+        let filter = RunFilter {
+            include_hidden: self.include_hidden.unwrap_or(false),
+            selection: resolve_character_filter(self.character.as_deref())?,
+            ..Default::default()
+        };
+        let mut runs = load_runs(None)?;
+        filter.retain(&mut runs);
+        Ok(runs)
+    }
+}
+
+/// Get daily run activity for one year
+///
+/// Days are local to `tz_offset_minutes` (e.g. `-300` for UTC-5). Every day of
+/// the year is returned, including days without runs.
 #[utoipa::path(
     get,
-    path = "/api/stats/{character}",
+    path = "/api/stats/calendar",
     tag = "sts",
     params(
-        ("character" = String, Path, description = "Character name")
+        ("year" = Option<i32>, Query, description = "Year to return (defaults to the current year)"),
+        ("tz_offset_minutes" = Option<i32>, Query, description = "Timezone offset from UTC in minutes east, e.g. -300 for UTC-5"),
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("include_hidden" = Option<bool>, Query, description = "Include runs hidden from statistics")
     ),
     responses(
-        (status = 200, description = "Character statistics", body = CharacterStats),
-        (status = 404, description = "Character not found", body = ApiError)
+        (status = 200, description = "One entry per day of the year", body = Vec<CalendarDay>),
+        (status = 400, description = "Invalid timezone offset, or an unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
     )
 )]
-pub async fn get_character_stats(
-    Path(character): Path<String>,
-) -> Result<Json<CharacterStats>, (StatusCode, Json<ApiError>)> {
-    let runs = load_all_runs();
-    let stats = calculate_character_stats(&runs);
+pub async fn get_calendar(
+    Query(params): Query<CalendarQuery>,
+) -> Result<Json<Vec<CalendarDay>>, (StatusCode, Json<ApiError>)> {
+    let bucketing = params.bucketing()?;
+    let year = params
+        .year
+        .unwrap_or_else(|| chrono::Datelike::year(&chrono::Utc::now()));
+    Ok(Json(calendar(&params.runs()?, year, bucketing)))
+}
 
-    stats
-        .into_iter()
-        .find(|s| s.character.eq_ignore_ascii_case(&character))
-        .map(Json)
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ApiError::new("Character not found", "NOT_FOUND")),
-            )
-        })
+/// List the years that have runs, for the calendar year picker
+#[utoipa::path(
+    get,
+    path = "/api/stats/calendar/years",
+    tag = "sts",
+    params(
+        ("tz_offset_minutes" = Option<i32>, Query, description = "Timezone offset from UTC in minutes east, e.g. -300 for UTC-5"),
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("include_hidden" = Option<bool>, Query, description = "Include runs hidden from statistics")
+    ),
+    responses(
+        (status = 200, description = "Years with runs, oldest first", body = Vec<i32>),
+        (status = 400, description = "Invalid timezone offset, or an unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_calendar_years(
+    Query(params): Query<CalendarQuery>,
+) -> Result<Json<Vec<i32>>, (StatusCode, Json<ApiError>)> {
+    let bucketing = params.bucketing()?;
+    Ok(Json(calendar_years(&params.runs()?, bucketing)))
 }
 
-/// Get complete export data (all runs + stats)
+/// Query parameters for the weekday and time-of-day stats
+#[derive(Debug, Default, Deserialize)]
+pub struct WhenQuery {
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Offset of the viewer's timezone from UTC, in minutes east
+    pub tz_offset_minutes: Option<i32>,
+    /// Runs a bucket needs to not be flagged low-confidence
+    pub min_runs: Option<i32>,
+}
+
+/// Get win rates by weekday and by four-hour block of the day
+///
+/// Runs are placed by the `local_time` in their file, or by their UTC
+/// timestamp shifted by `tz_offset_minutes` for files without one. Buckets
+/// with fewer than `min_runs` runs are returned with `low_confidence` set.
+/// Hidden runs are ignored.
 #[utoipa::path(
     get,
-    path = "/api/export",
+    path = "/api/stats/when",
     tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("tz_offset_minutes" = Option<i32>, Query, description = "Timezone offset from UTC in minutes east, for runs without local_time"),
+        ("min_runs" = Option<i32>, Query, description = "Runs a bucket needs to not be flagged low-confidence (default 10)")
+    ),
     responses(
-        (status = 200, description = "Complete export data", body = ExportData)
+        (status = 200, description = "Results by weekday and time of day", body = PlayTimeStats),
+        (status = 400, description = "Invalid timezone offset, or an unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
     )
 )]
-pub async fn get_export() -> Json<ExportData> {
-    Json(get_export_data())
+pub async fn get_when_stats(
+    Query(params): Query<WhenQuery>,
+) -> Result<Json<PlayTimeStats>, (StatusCode, Json<ApiError>)> {
+    let bucketing = time_bucketing(params.tz_offset_minutes)?;
+    let runs = CharacterFilterQuery {
+        character: params.character,
+    }
+    .load_runs()?;
+    let min_runs = params.min_runs.unwrap_or(DEFAULT_MIN_BUCKET_RUNS);
+    Ok(Json(PlayTimeStats::from_runs(&runs, bucketing, min_runs)))
 }
 
-/// Get available characters
+/// Query parameters for stats endpoints that only filter by character
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct CharacterFilterQuery {
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+}
+
+impl CharacterFilterQuery {
+    /// Load all runs, keeping those for the requested characters
+    fn load_runs(&self) -> Result<Vec<RunMetrics>, (StatusCode, Json<ApiError>)> {
+        let filter = RunFilter::from(resolve_character_filter(self.character.as_deref())?);
+        let mut runs = load_runs(None)?;
+        filter.retain(&mut runs);
+        Ok(runs)
+    }
+
+    /// The runs of the requested characters among `runs`
+    pub(super) fn select(
+        &self,
+        runs: &[RunMetrics],
+    ) -> Result<Vec<RunMetrics>, (StatusCode, Json<ApiError>)> {
+        let filter = RunFilter::from(resolve_character_filter(self.character.as_deref())?);
+        Ok(filter.apply(runs).into_iter().cloned().collect())
+    }
+}
+
+/// Get win rates for Neow boss relic swaps versus other Neow bonuses
+///
+/// Swap runs where the starter relic is still present (usually mods) are
+/// listed under `excluded` instead of being counted.
 #[utoipa::path(
     get,
-    path = "/api/characters",
+    path = "/api/stats/boss-swap",
     tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
     responses(
-        (status = 200, description = "List of characters", body = Vec<String>)
+        (status = 200, description = "Boss relic swap statistics", body = BossSwapStats),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
     )
 )]
-pub async fn get_characters() -> Json<Vec<serde_json::Value>> {
-    let chars: Vec<serde_json::Value> = Character::all()
-        .iter()
-        .map(|c| {
-            serde_json::json!({
-                "id": c.dir_name(),
-                "name": c.display_name()
-            })
-        })
-        .collect();
-    Json(chars)
+pub async fn get_boss_swap_stats(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<BossSwapStats>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(boss_swap_stats(&params.load_runs()?)))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Get boss relic picks and skips per act
+///
+/// A chest left without taking a relic is a skip. The Act 1 chest of a
+/// Neow boss swap run is neither a pick nor a skip, and runs recorded
+/// without boss relic data are left out.
+#[utoipa::path(
+    get,
+    path = "/api/stats/boss-relics",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Boss relic pick and skip statistics", body = BossRelicStats),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_boss_relic_stats(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<BossRelicStats>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(boss_relic_stats(&params.load_runs()?)))
+}
 
-    #[tokio::test]
-    async fn test_get_characters() {
-        let result = get_characters().await;
-        assert_eq!(result.0.len(), 4);
+/// Get aggregate shop usage: purchases by kind, removals and card sources
+///
+/// `empty_handed_rate` is the share of shops left without buying anything;
+/// a shop where only a card removal was bought counts as used. It is split
+/// by won and lost runs overall, per act and per character.
+#[utoipa::path(
+    get,
+    path = "/api/stats/shops",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Shop statistics", body = ShopStats),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_shop_stats(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<ShopStats>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(ShopStats::from_runs(&params.load_runs()?)))
+}
+
+/// Get the cards removed from decks, most removed first
+///
+/// Upgraded copies count as the card. `win_rate_removed` covers runs that
+/// bought a removal of the card at a shop and `win_rate_kept` runs that never
+/// removed it and still had it at the end; runs that only lost the card to an
+/// event are in neither.
+#[utoipa::path(
+    get,
+    path = "/api/stats/purged-cards",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Removal counts and outcomes per card", body = Vec<PurgedCardStats>),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_purged_cards(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<Vec<PurgedCardStats>>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(purged_card_stats(&params.load_runs()?)))
+}
+
+/// Get encounters, wins and deaths against each act's bosses
+///
+/// `reached` and `survived` give, per act, how often a run that got to the
+/// boss went on to beat it. Hidden runs are ignored.
+#[utoipa::path(
+    get,
+    path = "/api/stats/act-bosses",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Boss results for acts 1 to 4", body = Vec<ActBossStats>),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_act_boss_stats(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<Vec<ActBossStats>>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(act_boss_stats(&params.load_runs()?)))
+}
+
+/// Get how often elites were fought where they could have been, by act,
+/// for won and lost runs
+///
+/// Elite opportunities are a heuristic: the map rows walked through where an
+/// elite can spawn. Files without `path_taken` are counted separately.
+#[utoipa::path(
+    get,
+    path = "/api/stats/elites",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Elite fight rates", body = EliteStats),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_elite_stats(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<EliteStats>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(EliteStats::from_runs(&params.load_runs()?)))
+}
+
+/// Get how often unknown (`?`) rooms turned into fights, overall and by act
+#[utoipa::path(
+    get,
+    path = "/api/stats/events",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Unknown room outcomes", body = EventStats),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_event_stats(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<EventStats>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(EventStats::from_runs(&params.load_runs()?)))
+}
+
+/// Get potions obtained, used and wasted, and how often losses ended with
+/// an unused potion
+///
+/// Runs with Sozu and files without potion data are counted separately and
+/// left out of the averages.
+#[utoipa::path(
+    get,
+    path = "/api/stats/potions",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Potion statistics", body = PotionStats),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_potion_stats(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<PotionStats>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(PotionStats::from_runs(&params.load_runs()?)))
+}
+
+/// Get how often each potion was picked up and the win rate of runs that
+/// picked it up
+///
+/// Run files record the floor each potion was used on but not which potion,
+/// so uses are only given as a total (`uses_unattributed`). Fairy in a Bottle
+/// also reports `deaths_prevented`: runs that fell to 30% of max HP or less
+/// after picking it up and went on to a later floor, a heuristic.
+#[utoipa::path(
+    get,
+    path = "/api/stats/potion-names",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Pickups per potion", body = PotionNameStats),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_potion_names(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<PotionNameStats>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(PotionNameStats::from_runs(&params.load_runs()?)))
+}
+
+/// Query parameters for the first relic endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct FirstRelicQuery {
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Runs a relic needs to not be flagged low-confidence
+    pub min_runs: Option<i32>,
+}
+
+/// Get the win rate of runs by the first relic they picked up
+///
+/// The starter relic and Neow rewards don't count. Runs that died before
+/// finding a relic are left out, including from the baseline win rate each
+/// relic is compared with. Relics picked up first in fewer than `min_runs`
+/// runs are flagged `low_confidence`.
+#[utoipa::path(
+    get,
+    path = "/api/stats/first-relic",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("min_runs" = Option<i32>, Query, description = "Runs a relic needs to not be flagged low-confidence (default 10)")
+    ),
+    responses(
+        (status = 200, description = "Win rate by first relic", body = FirstRelicStats),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_first_relic(
+    Query(params): Query<FirstRelicQuery>,
+) -> Result<Json<FirstRelicStats>, (StatusCode, Json<ApiError>)> {
+    let runs = CharacterFilterQuery {
+        character: params.character,
+    }
+    .load_runs()?;
+    let min_runs = params.min_runs.unwrap_or(DEFAULT_MIN_BUCKET_RUNS);
+    Ok(Json(first_relic_stats(&runs, min_runs)))
+}
+
+/// Get where relics came from in wins versus losses, per character
+///
+/// Each relic in a run's `relics_obtained` is classified by the room on its
+/// floor, and relics taken from boss chests count as `boss`. Relics from
+/// unknown rooms that turned into fights, or from rooms that don't normally
+/// give one, are counted as `uncertain`.
+///
+/// Relic stats are spread over several endpoints rather than one, so the
+/// split has its own route instead of a section on an existing one.
+#[utoipa::path(
+    get,
+    path = "/api/stats/relic-sources",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Relic source distribution for wins and losses", body = RelicSourceStats),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_relic_sources(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<RelicSourceStats>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(relic_source_stats(&params.load_runs()?)))
+}
+
+/// Query parameters for the relic timing endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct RelicTimingQuery {
+    /// Relic to report on (case-insensitive)
+    pub relic: Option<String>,
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+}
+
+/// Get the win rate of runs ending with a relic, by the act it was obtained in
+///
+/// Relics from the start of the run (starter relic, Neow) are reported as act 0.
+#[utoipa::path(
+    get,
+    path = "/api/stats/relic-timing",
+    tag = "sts",
+    params(
+        ("relic" = String, Query, description = "Relic name, e.g. Dead Branch"),
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Win rate by acquisition act", body = RelicTiming),
+        (status = 400, description = "Missing relic, or an unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_relic_timing(
+    Query(params): Query<RelicTimingQuery>,
+) -> Result<Json<RelicTiming>, (StatusCode, Json<ApiError>)> {
+    let Some(relic) = params
+        .relic
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+    else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new("relic is required", "INVALID_QUERY")),
+        ));
+    };
+    let filter = CharacterFilterQuery {
+        character: params.character.clone(),
+    };
+    Ok(Json(relic_timing(&filter.load_runs()?, relic)))
+}
+
+/// Get card reward skip rates overall, by outcome and by act
+///
+/// Runs from game versions without card reward data are counted in
+/// `runs_without_data` and left out of the rates.
+#[utoipa::path(
+    get,
+    path = "/api/stats/skip-rate",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Card reward skip rates", body = SkipRateStats),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_skip_rate(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<SkipRateStats>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(SkipRateStats::from_runs(&params.load_runs()?)))
+}
+
+/// Query parameters for the by-ascension endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct AscensionStatsQuery {
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Include runs hidden from statistics
+    pub include_hidden: Option<bool>,
+}
+
+/// Get runs, wins, win rate and average floor per character and ascension level
+///
+/// Uses the same numbers as the `ascension_stats` section of exports.
+#[utoipa::path(
+    get,
+    path = "/api/stats/by-ascension",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("include_hidden" = Option<bool>, Query, description = "Include runs hidden from statistics")
+    ),
+    responses(
+        (status = 200, description = "One entry per character and ascension level with runs", body = Vec<AscensionStats>),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_ascension_stats(
+    Query(params): Query<AscensionStatsQuery>,
+) -> Result<Json<Vec<AscensionStats>>, (StatusCode, Json<ApiError>)> {
+    let options = StatsOptions {
+        include_hidden: params.include_hidden.unwrap_or(false),
+        selection: resolve_character_filter(params.character.as_deref())?,
+        ..Default::default()
+    };
+    Ok(Json(ascension_stats(&load_runs(None)?, &options)))
+}
+
+/// Query parameters for the deck size endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct DeckSizeQuery {
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Leave out runs of these characters (comma-separated)
+    pub exclude_character: Option<String>,
+    /// Kinds of run to leave out (comma-separated, see [`RunExclusion`])
+    pub exclude: Option<String>,
+    /// Upper bounds of the deck size ranges (comma-separated)
+    pub bounds: Option<String>,
+}
+
+/// Get runs, win rate and average floor by final deck size
+///
+/// Ranges end at each bound, plus one range above the last; the default
+/// bounds `20,25,30,35` give `<=20`, `21-25`, `26-30`, `31-35` and `36+`.
+#[utoipa::path(
+    get,
+    path = "/api/stats/deck-size",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("exclude_character" = Option<String>, Query, description = "Leave out runs of these characters (comma-separated)"),
+        ("exclude" = Option<String>, Query, description = "Kinds of run to leave out: a comma-separated list of dailies, seeded, endless, custom, non_standard, modded, abandoned and hidden"),
+        ("bounds" = Option<String>, Query, description = "Increasing upper bounds of the deck size ranges, comma-separated (default 20,25,30,35)")
+    ),
+    responses(
+        (status = 200, description = "Results per deck size range", body = DeckSizeStats),
+        (status = 400, description = "Invalid bounds or run filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_deck_size_stats(
+    Query(params): Query<DeckSizeQuery>,
+) -> Result<Json<DeckSizeStats>, (StatusCode, Json<ApiError>)> {
+    let bounds = match params.bounds.as_deref() {
+        Some(value) => parse_deck_size_bounds(value).map_err(|details| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::with_details(
+                    "Invalid deck size bounds",
+                    "INVALID_QUERY",
+                    details,
+                )),
+            )
+        })?,
+        None => DEFAULT_DECK_SIZE_BOUNDS.to_vec(),
+    };
+    let selection = run_selection(
+        params.character.as_deref(),
+        params.exclude_character.as_deref(),
+        params.exclude.as_deref(),
+    )?;
+    let mut runs = load_runs(None)?;
+    RunFilter::from(selection).retain(&mut runs);
+    Ok(Json(DeckSizeStats::from_runs(&runs, &bounds)))
+}
+
+/// Query parameters for the upgrade impact endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct UpgradeImpactQuery {
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Leave out runs of these characters (comma-separated)
+    pub exclude_character: Option<String>,
+    /// Kinds of run to leave out (comma-separated, see [`RunExclusion`])
+    pub exclude: Option<String>,
+    /// Runs a bucket needs to not be flagged low-confidence
+    pub min_runs: Option<i32>,
+}
+
+/// Get win rate and average floor by upgraded cards in the final deck
+///
+/// Runs are bucketed by upgrade count and by upgraded share of the deck.
+/// Upgrades are split into smithed and other for runs that record campfire
+/// choices. Buckets with fewer than `min_runs` runs are returned with
+/// `low_confidence` set.
+#[utoipa::path(
+    get,
+    path = "/api/stats/upgrade-impact",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("exclude_character" = Option<String>, Query, description = "Leave out runs of these characters (comma-separated)"),
+        ("exclude" = Option<String>, Query, description = "Kinds of run to leave out: a comma-separated list of dailies, seeded, endless, custom, non_standard, modded, abandoned and hidden"),
+        ("min_runs" = Option<i32>, Query, description = "Runs a bucket needs to not be flagged low-confidence (default 10)")
+    ),
+    responses(
+        (status = 200, description = "Results by upgrade count, upgraded share and upgrade source", body = UpgradeImpactStats),
+        (status = 400, description = "Invalid run filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_upgrade_impact(
+    Query(params): Query<UpgradeImpactQuery>,
+) -> Result<Json<UpgradeImpactStats>, (StatusCode, Json<ApiError>)> {
+    let selection = run_selection(
+        params.character.as_deref(),
+        params.exclude_character.as_deref(),
+        params.exclude.as_deref(),
+    )?;
+    let mut runs = load_runs(None)?;
+    RunFilter::from(selection).retain(&mut runs);
+    let min_runs = params.min_runs.unwrap_or(DEFAULT_MIN_BUCKET_RUNS);
+    Ok(Json(UpgradeImpactStats::from_runs(&runs, min_runs)))
+}
+
+/// Query parameters for the period comparison endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct ComparePeriodsQuery {
+    /// First day of the period after the split, as `YYYY-MM-DD`
+    pub split: Option<String>,
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Leave out runs of these characters (comma-separated)
+    pub exclude_character: Option<String>,
+    /// Kinds of run to leave out (comma-separated, see [`RunExclusion`])
+    pub exclude: Option<String>,
+    /// Only compare this many days on each side of the split
+    pub window_days: Option<u32>,
+    /// Offset of the viewer's timezone from UTC, in minutes east
+    pub tz_offset_minutes: Option<i32>,
+    /// Runs each period needs to not be flagged low-confidence
+    pub min_runs: Option<i32>,
+}
+
+/// Compare stats before and after a date
+///
+/// Runs are placed by the local day they ended on, like the calendar. Both
+/// periods are open-ended unless `window_days` is given, and each comes with
+/// full character stats. A period with fewer than `min_runs` runs is flagged
+/// `low_confidence` and named in `warnings`.
+#[utoipa::path(
+    get,
+    path = "/api/compare/periods",
+    tag = "sts",
+    params(
+        ("split" = String, Query, description = "First day of the period after the split, e.g. 2024-06-01"),
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("exclude_character" = Option<String>, Query, description = "Leave out runs of these characters (comma-separated)"),
+        ("exclude" = Option<String>, Query, description = "Kinds of run to leave out: a comma-separated list of dailies, seeded, endless, custom, non_standard, modded, abandoned and hidden"),
+        ("window_days" = Option<u32>, Query, description = "Only compare this many days on each side of the split"),
+        ("tz_offset_minutes" = Option<i32>, Query, description = "Timezone offset from UTC in minutes east, for runs without local_time"),
+        ("min_runs" = Option<i32>, Query, description = "Runs each period needs to not be flagged low-confidence (default 10)")
+    ),
+    responses(
+        (status = 200, description = "Stats before and after the split, with the differences", body = PeriodComparison),
+        (status = 400, description = "Missing or invalid split date, zero window_days, an invalid timezone offset or run filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_period_comparison(
+    Query(params): Query<ComparePeriodsQuery>,
+) -> Result<Json<PeriodComparison>, (StatusCode, Json<ApiError>)> {
+    let invalid = |message: &str, details: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::with_details(message, "INVALID_QUERY", details)),
+        )
+    };
+    let Some(split) = params
+        .split
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new("split is required", "INVALID_QUERY")),
+        ));
+    };
+    let split = NaiveDate::parse_from_str(split, "%Y-%m-%d")
+        .map_err(|e| invalid("Invalid split date", &format!("{}: {}", split, e)))?;
+    if params.window_days == Some(0) {
+        return Err(invalid("Invalid window", "window_days must be at least 1"));
+    }
+    let bucketing = time_bucketing(params.tz_offset_minutes)?;
+    let selection = run_selection(
+        params.character.as_deref(),
+        params.exclude_character.as_deref(),
+        params.exclude.as_deref(),
+    )?;
+    let mut runs = load_runs(None)?;
+    RunFilter::from(selection).retain(&mut runs);
+    let min_runs = params.min_runs.unwrap_or(DEFAULT_MIN_BUCKET_RUNS);
+    Ok(Json(PeriodComparison::from_runs(
+        &runs,
+        split,
+        params.window_days,
+        bucketing,
+        min_runs,
+    )))
+}
+
+/// Get stats for a specific character
+///
+/// `since` and `until` work as for `/api/stats`.
+#[utoipa::path(
+    get,
+    path = "/api/stats/{character}",
+    tag = "sts",
+    params(
+        ("character" = String, Path, description = "Character name, display name or alias"),
+        ("include_hidden" = Option<bool>, Query, description = "Include runs hidden from statistics"),
+        ("since" = Option<i64>, Query, description = "Only count runs that ended at or after this time (unix seconds)"),
+        ("until" = Option<i64>, Query, description = "Only count runs that ended before this time (unix seconds)"),
+        ("accept-language" = Option<String>, Header, description = "Language of display_name: en (default), de, fr, es or zh")
+    ),
+    responses(
+        (status = 200, description = "Character statistics", body = CharacterStats, headers(
+            ("x-stats-since" = Option<i64>, description = "The since bound applied, if any"),
+            ("x-stats-until" = Option<i64>, description = "The until bound applied, if any")
+        )),
+        (status = 400, description = "Time window ending before it starts", body = ApiError),
+        (status = 404, description = "Character not found", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_character_stats(
+    CharacterPath(character): CharacterPath,
+    AcceptLanguage(locale): AcceptLanguage,
+    Query(params): Query<StatsQuery>,
+) -> Result<(HeaderMap, Json<CharacterStats>), (StatusCode, Json<ApiError>)> {
+    let options = params.options()?;
+    let runs = load_runs(None)?;
+    let stats = calculate_character_stats_with(&runs, &options);
+
+    stats
+        .into_iter()
+        .find(|s| s.character == character.dir_name())
+        .map(|mut stats| {
+            stats.localize(locale);
+            (time_window_headers(&options.window), Json(stats))
+        })
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiError::new("Character not found", "NOT_FOUND")),
+            )
+        })
+}
+
+/// Query parameters for the export endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportQuery {
+    /// Shorten the runs path in the export metadata
+    pub redact_path: Option<bool>,
+    /// Output format: `json` (default), `csv` or `ndjson`
+    pub format: Option<String>,
+    /// Table to write in CSV exports: `runs` (default) or `stats`
+    pub part: Option<String>,
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Leave out runs of these characters (comma-separated)
+    pub exclude_character: Option<String>,
+    /// Kinds of run to leave out (comma-separated, see [`RunExclusion`])
+    pub exclude: Option<String>,
+    /// Only runs that ended at or after this time (unix seconds)
+    pub since: Option<i64>,
+    /// Only runs that ended before this time (unix seconds)
+    pub until: Option<i64>,
+    /// Lowest ascension level included
+    pub min_ascension: Option<i32>,
+    /// Highest ascension level included
+    pub max_ascension: Option<i32>,
+    /// Leave out Daily Climb runs (same as `exclude=dailies`)
+    pub exclude_daily: Option<bool>,
+    /// Leave out runs played on a chosen seed (same as `exclude=seeded`)
+    pub exclude_seeded: Option<bool>,
+}
+
+impl ExportQuery {
+    /// The run filter described by the query
+    fn filter(&self) -> Result<ExportFilter, (StatusCode, Json<ApiError>)> {
+        let mut selection = run_selection(
+            self.character.as_deref(),
+            self.exclude_character.as_deref(),
+            self.exclude.as_deref(),
+        )?;
+        for (flag, exclusion) in [
+            (self.exclude_daily, RunExclusion::Dailies),
+            (self.exclude_seeded, RunExclusion::Seeded),
+        ] {
+            if flag.unwrap_or(false) && !selection.exclude.contains(&exclusion) {
+                selection.exclude.push(exclusion);
+            }
+        }
+        let filter = ExportFilter {
+            selection,
+            since: self.since,
+            until: self.until,
+            min_ascension: self.min_ascension,
+            max_ascension: self.max_ascension,
+        };
+        filter.validate().map_err(|details| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::with_details(
+                    "Invalid export filter",
+                    "INVALID_QUERY",
+                    details,
+                )),
+            )
+        })?;
+        Ok(filter)
+    }
+}
+
+/// Serialization formats of the export endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// The full export document
+    Json,
+    /// Runs as CSV rows
+    Csv,
+    /// Character stats as CSV rows
+    CsvStats,
+    /// One run per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// Accepted values of the `format` parameter
+    pub const FORMATS: &[&str] = &["json", "csv", "ndjson"];
+    /// Accepted values of the `part` parameter
+    pub const CSV_PARTS: &[&str] = &["runs", "stats"];
+
+    /// Pick the format from the `format` and `part` query parameters
+    pub fn from_query(
+        format: Option<&str>,
+        part: Option<&str>,
+    ) -> Result<Self, (StatusCode, Json<ApiError>)> {
+        let invalid = |message: &str, valid: &[&str]| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::with_details(
+                    message,
+                    "INVALID_QUERY",
+                    format!("Valid values: {}", valid.join(", ")),
+                )),
+            )
+        };
+        let format = format.map(|f| f.trim().to_ascii_lowercase());
+        match format.as_deref() {
+            None | Some("json") => Ok(Self::Json),
+            Some("ndjson") => Ok(Self::Ndjson),
+            Some("csv") => match part.map(|p| p.trim().to_ascii_lowercase()).as_deref() {
+                None | Some("runs") => Ok(Self::Csv),
+                Some("stats") => Ok(Self::CsvStats),
+                Some(_) => Err(invalid("Unknown export part", Self::CSV_PARTS)),
+            },
+            Some(_) => Err(invalid("Unknown export format", Self::FORMATS)),
+        }
+    }
+
+    /// `Content-Type` of responses in this format
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv | Self::CsvStats => "text/csv; charset=utf-8",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    /// File name suggested in the `Content-Disposition` header
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Json => "sts-export.json",
+            Self::Csv => "sts-runs.csv",
+            Self::CsvStats => "sts-character-stats.csv",
+            Self::Ndjson => "sts-runs.ndjson",
+        }
+    }
+}
+
+/// Get complete export data (all runs + stats)
+///
+/// Besides the runs and stats, the export records when it was written (as
+/// both an epoch timestamp and a UTC datetime with the host's timezone
+/// offset), the app version, and the runs directory it was read from.
+///
+/// `format=csv` returns the runs as flat CSV rows (or the character stats with
+/// `part=stats`), and `format=ndjson` streams one run per line.
+///
+/// The filter parameters narrow the exported runs; the character stats are
+/// computed over the same subset and the filter is recorded in `filters`.
+#[utoipa::path(
+    get,
+    path = "/api/export",
+    tag = "sts",
+    params(
+        ("redact_path" = Option<bool>, Query, description = "Replace the home folder in the runs path and each run's source_path with ~ (or keep only the last name outside it)"),
+        ("format" = Option<String>, Query, description = "Output format: json (default), csv or ndjson"),
+        ("part" = Option<String>, Query, description = "CSV only: runs (default) or stats"),
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("since" = Option<i64>, Query, description = "Only runs that ended at or after this time (unix seconds)"),
+        ("until" = Option<i64>, Query, description = "Only runs that ended before this time (unix seconds)"),
+        ("min_ascension" = Option<i32>, Query, description = "Lowest ascension level included"),
+        ("max_ascension" = Option<i32>, Query, description = "Highest ascension level included"),
+        ("exclude_character" = Option<String>, Query, description = "Leave out runs of these characters (comma-separated)"),
+        ("exclude" = Option<String>, Query, description = "Kinds of run to leave out: a comma-separated list of dailies, seeded, endless, custom, non_standard, modded, abandoned and hidden"),
+        ("exclude_daily" = Option<bool>, Query, deprecated, description = "Leave out Daily Climb runs (use exclude=dailies)"),
+        ("exclude_seeded" = Option<bool>, Query, deprecated, description = "Leave out runs played on a chosen seed (use exclude=seeded)")
+    ),
+    responses(
+        (status = 200, description = "Export in the requested format", content(
+            (ExportData = "application/json"),
+            (String = "text/csv"),
+            (RunMetrics = "application/x-ndjson")
+        )),
+        (status = 400, description = "Unknown format or part, an inverted filter range, or an invalid or conflicting run filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_export(Query(params): Query<ExportQuery>) -> Response {
+    let format = match ExportFormat::from_query(params.format.as_deref(), params.part.as_deref()) {
+        Ok(format) => format,
+        Err(e) => return e.into_response(),
+    };
+    let filter = match params.filter() {
+        Ok(filter) => filter,
+        Err(e) => return e.into_response(),
+    };
+    let options = ExportOptions {
+        redact_path: params.redact_path.unwrap_or(false),
+        filter,
+        ..Default::default()
+    };
+    let mut export = match get_export_data_with(&options) {
+        Ok(export) => export,
+        Err(e) => return runs_path_not_found(e).into_response(),
+    };
+    let runs = std::mem::take(&mut export.runs);
+
+    let body = match format {
+        ExportFormat::Csv => csv::runs_csv(&runs).into_response(),
+        ExportFormat::CsvStats => csv::character_stats_csv(&export.character_stats).into_response(),
+        ExportFormat::Ndjson => ndjson_stream_response(ndjson_chunks(runs)),
+        // Everything around the runs array is written as is, and the runs
+        // (already loaded for the stats) are serialized in batches in between
+        ExportFormat::Json => match split_around_array(&export, "runs") {
+            Ok((prefix, suffix)) => json_stream_response(array_chunks(prefix, runs, suffix)),
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiError::with_details(
+                        "Failed to serialize export",
+                        "INTERNAL_ERROR",
+                        e.to_string(),
+                    )),
+                )
+                    .into_response()
+            }
+        },
+    };
+    let disposition = format!("attachment; filename=\"{}\"", format.file_name());
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Query parameters for the feature vector endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct FeaturesQuery {
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Output format: `json` (default) or `csv`
+    pub format: Option<String>,
+}
+
+/// Accepted values of the feature vector endpoint's `format` parameter
+const FEATURE_FORMATS: &[&str] = &["json", "csv"];
+
+/// Get one numeric feature vector per run, labelled with its outcome
+///
+/// `columns` names the features in the order they appear in each row. The
+/// order is stable: new features are only appended. `format=csv` returns a
+/// table with `play_id`, `character`, the feature columns and `victory`
+/// (1 or 0), ready for `pandas.read_csv`. Hidden runs are left out.
+#[utoipa::path(
+    get,
+    path = "/api/ml/features",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("format" = Option<String>, Query, description = "Output format: json (default) or csv")
+    ),
+    responses(
+        (status = 200, description = "Feature vectors in the requested format", content(
+            (FeatureSet = "application/json"),
+            (String = "text/csv")
+        )),
+        (status = 400, description = "Unknown format or character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_ml_features(Query(params): Query<FeaturesQuery>) -> Response {
+    let format = params.format.map(|f| f.trim().to_ascii_lowercase());
+    let csv = match format.as_deref() {
+        None | Some("json") => false,
+        Some("csv") => true,
+        Some(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::with_details(
+                    "Unknown features format",
+                    "INVALID_QUERY",
+                    format!("Valid values: {}", FEATURE_FORMATS.join(", ")),
+                )),
+            )
+                .into_response()
+        }
+    };
+    let runs = match (CharacterFilterQuery {
+        character: params.character,
+    })
+    .load_runs()
+    {
+        Ok(runs) => runs,
+        Err(e) => return e.into_response(),
+    };
+    let set = feature_set(&runs);
+    if !csv {
+        return Json(set).into_response();
+    }
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"sts-features.csv\"",
+            ),
+        ],
+        csv::features_csv(&set),
+    )
+        .into_response()
+}
+
+/// Import an export document, upgrading older schema versions
+///
+/// Returns the document converted to the current export layout. When the
+/// export has a `checksum`, its runs are checked against it first: fewer runs
+/// than it covers is a `TRUNCATED_EXPORT` error, and changed runs are a
+/// `CHECKSUM_MISMATCH` error.
+#[utoipa::path(
+    post,
+    path = "/api/import",
+    tag = "sts",
+    request_body = ExportData,
+    responses(
+        (status = 200, description = "Upgraded export data", body = ExportData),
+        (status = 400, description = "Invalid, truncated or modified export document", body = ApiError),
+        (status = 422, description = "Export written by a newer app version", body = ApiError)
+    )
+)]
+pub async fn import_export(
+    Json(doc): Json<serde_json::Value>,
+) -> Result<Json<ExportData>, (StatusCode, Json<ApiError>)> {
+    import_export_value(doc).map(Json).map_err(|e| {
+        let (status, code) = match e {
+            ImportError::Invalid(_) => (StatusCode::BAD_REQUEST, "INVALID_EXPORT"),
+            ImportError::Truncated(_) => (StatusCode::BAD_REQUEST, "TRUNCATED_EXPORT"),
+            ImportError::ChecksumMismatch { .. } => (StatusCode::BAD_REQUEST, "CHECKSUM_MISMATCH"),
+            ImportError::UnsupportedVersion { .. } => {
+                (StatusCode::UNPROCESSABLE_ENTITY, "UNSUPPORTED_VERSION")
+            }
+        };
+        (
+            status,
+            Json(ApiError::with_details("Import failed", code, e.to_string())),
+        )
+    })
+}
+
+/// Get available characters
+///
+/// `id` is the character's directory name; `name` is its display name in the
+/// language picked from `Accept-Language`.
+#[utoipa::path(
+    get,
+    path = "/api/characters",
+    tag = "sts",
+    params(
+        ("accept-language" = Option<String>, Header, description = "Language of the names: en (default), de, fr, es or zh")
+    ),
+    responses(
+        (status = 200, description = "List of characters", body = Vec<String>)
+    )
+)]
+pub async fn get_characters(
+    AcceptLanguage(locale): AcceptLanguage,
+) -> Json<Vec<serde_json::Value>> {
+    let chars: Vec<serde_json::Value> = Character::all()
+        .iter()
+        .map(|c| {
+            serde_json::json!({
+                "id": c.dir_name(),
+                "name": c.localized_name(locale)
+            })
+        })
+        .collect();
+    Json(chars)
+}
+
+/// Get a single run by play_id (hidden runs included)
+///
+/// The run comes with `score_breakdown`, an estimate of where its score came
+/// from, and the `source_path` and `file_modified_at` of its file, which run
+/// lists leave out.
+#[utoipa::path(
+    get,
+    path = "/api/run/{play_id}",
+    tag = "sts",
+    params(
+        ("play_id" = String, Path, description = "Run play_id")
+    ),
+    responses(
+        (status = 200, description = "Run details", body = RunDetail),
+        (status = 404, description = "Run not found", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_run(
+    Path(play_id): Path<String>,
+) -> Result<Json<RunDetail>, (StatusCode, Json<ApiError>)> {
+    find_run(&play_id)
+        .map_err(runs_path_not_found)?
+        .map(|run| Json(RunDetail::from(run)))
+        .ok_or_else(|| run_not_found(&play_id))
+}
+
+/// Hide a run from statistics
+#[utoipa::path(
+    post,
+    path = "/api/run/{play_id}/hide",
+    tag = "sts",
+    params(
+        ("play_id" = String, Path, description = "Run play_id")
+    ),
+    responses(
+        (status = 200, description = "Run hidden", body = RunVisibility),
+        (status = 404, description = "Run not found", body = ApiError),
+        (status = 500, description = "Failed to persist hidden runs", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn hide_run(
+    Path(play_id): Path<String>,
+) -> Result<Json<RunVisibility>, (StatusCode, Json<ApiError>)> {
+    update_run_visibility(play_id, true)
+}
+
+/// Unhide a previously hidden run
+#[utoipa::path(
+    delete,
+    path = "/api/run/{play_id}/hide",
+    tag = "sts",
+    params(
+        ("play_id" = String, Path, description = "Run play_id")
+    ),
+    responses(
+        (status = 200, description = "Run visible again", body = RunVisibility),
+        (status = 404, description = "Run not found", body = ApiError),
+        (status = 500, description = "Failed to persist hidden runs", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn unhide_run(
+    Path(play_id): Path<String>,
+) -> Result<Json<RunVisibility>, (StatusCode, Json<ApiError>)> {
+    update_run_visibility(play_id, false)
+}
+
+/// Re-parse the file behind a run
+///
+/// For run files edited or restored by hand: the file is read again even if
+/// its modification time is unchanged, without reloading any other run. A
+/// file that is gone or no longer loads takes the run out of the cache.
+#[utoipa::path(
+    post,
+    path = "/api/run/{play_id}/reparse",
+    tag = "sts",
+    params(
+        ("play_id" = String, Path, description = "Run play_id")
+    ),
+    responses(
+        (status = 200, description = "The run as parsed from its file now", body = RunMetrics),
+        (status = 404, description = "Run not found", body = ApiError),
+        (status = 410, description = "The run's file was removed", body = ApiError),
+        (status = 422, description = "The run's file no longer loads; details give the path, line and column", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn reparse_run(
+    Path(play_id): Path<String>,
+) -> Result<Json<RunMetrics>, (StatusCode, Json<ApiError>)> {
+    crate::sts::reparse_run(&play_id)
+        .map(Json)
+        .map_err(|e| match e {
+            ReparseError::NoRunsPath => runs_path_not_found(RunsPathNotFound),
+            ReparseError::NotFound => run_not_found(&play_id),
+            ReparseError::FileRemoved(_) => (
+                StatusCode::GONE,
+                Json(ApiError::with_details(
+                    "Run file removed",
+                    "FILE_REMOVED",
+                    e.to_string(),
+                )),
+            ),
+            ReparseError::Invalid(_) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ApiError::with_details(
+                    "Run file no longer loads",
+                    "PARSE_ERROR",
+                    e.to_string(),
+                )),
+            ),
+        })
+}
+
+fn run_not_found(play_id: &str) -> (StatusCode, Json<ApiError>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ApiError::with_details(
+            "Run not found",
+            "NOT_FOUND",
+            format!("No run with play_id {}", play_id),
+        )),
+    )
+}
+
+fn update_run_visibility(
+    play_id: String,
+    hidden: bool,
+) -> Result<Json<RunVisibility>, (StatusCode, Json<ApiError>)> {
+    if find_run(&play_id).map_err(runs_path_not_found)?.is_none() {
+        return Err(run_not_found(&play_id));
+    }
+
+    set_run_hidden(&play_id, hidden).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::with_details(
+                "Failed to save hidden runs",
+                "STORAGE_ERROR",
+                e.to_string(),
+            )),
+        )
+    })?;
+
+    Ok(Json(RunVisibility { play_id, hidden }))
+}
+
+/// Query parameters for the summary endpoint
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct SummaryQuery {
+    /// Only runs that ended at or after this time (unix seconds)
+    pub since: Option<i64>,
+    /// Only runs that ended before this time (unix seconds)
+    pub until: Option<i64>,
+}
+
+/// Get a summary of the loaded dataset
+///
+/// `since` and `until` work as for `/api/stats`.
+#[utoipa::path(
+    get,
+    path = "/api/summary",
+    tag = "sts",
+    params(
+        ("since" = Option<i64>, Query, description = "Only include runs that ended at or after this time (unix seconds)"),
+        ("until" = Option<i64>, Query, description = "Only include runs that ended before this time (unix seconds)")
+    ),
+    responses(
+        (status = 200, description = "Dataset summary", body = DatasetSummary, headers(
+            ("x-stats-since" = Option<i64>, description = "The since bound applied, if any"),
+            ("x-stats-until" = Option<i64>, description = "The until bound applied, if any")
+        )),
+        (status = 400, description = "Time window ending before it starts", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_summary(
+    Query(params): Query<SummaryQuery>,
+) -> Result<(HeaderMap, Json<DatasetSummary>), (StatusCode, Json<ApiError>)> {
+    let (window, summary) = params.summary(|| load_runs(None))?;
+    Ok((time_window_headers(&window), Json(summary)))
+}
+
+impl SummaryQuery {
+    /// Summary of the runs `load` returns that fall in the window
+    ///
+    /// The window is checked before `load` is called.
+    pub(super) fn summary<R: AsRef<[RunMetrics]>>(
+        &self,
+        load: impl FnOnce() -> Result<R, (StatusCode, Json<ApiError>)>,
+    ) -> Result<(TimeWindow, DatasetSummary), (StatusCode, Json<ApiError>)> {
+        let window = time_window(self.since, self.until)?;
+        // Hidden runs are counted in the summary
+        let filter = RunFilter {
+            include_hidden: true,
+            window,
+            ..Default::default()
+        };
+        let runs = load()?;
+        let runs: Vec<RunMetrics> = filter.apply(runs.as_ref()).into_iter().cloned().collect();
+        Ok((window, dataset_summary(&runs)))
+    }
+}
+
+/// Get personal records, each with the run that set it
+///
+/// Hidden runs are ignored. Categories without a qualifying run (e.g. fastest
+/// victory with no wins) are left out of the response.
+#[utoipa::path(
+    get,
+    path = "/api/personal-bests",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases")
+    ),
+    responses(
+        (status = 200, description = "Personal records", body = PersonalBests),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_personal_bests(
+    Query(params): Query<CharacterFilterQuery>,
+) -> Result<Json<PersonalBests>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(personal_bests(&params.load_runs()?)))
+}
+
+/// Get ascension ladder progress for each character
+///
+/// The target level is one above the highest win, capped at 20. Hidden runs
+/// are ignored.
+#[utoipa::path(
+    get,
+    path = "/api/progress",
+    tag = "sts",
+    responses(
+        (status = 200, description = "Ascension progress per character", body = ProgressReport),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_progress() -> Result<Json<ProgressReport>, (StatusCode, Json<ApiError>)> {
+    Ok(Json(ProgressReport::from_runs(&load_runs(None)?)))
+}
+
+/// Query parameters for the enemy catalog
+#[derive(Debug, Default, Deserialize)]
+pub struct EnemiesQuery {
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Only count fights and deaths in this act (1-4)
+    pub act: Option<i32>,
+    /// Merge group sizes, e.g. "2 Louse" and "3 Louse" into "Louse"
+    pub normalize: Option<bool>,
+}
+
+/// List the encounters in the run data with fight and death counts
+///
+/// Hidden runs are ignored. Entries are sorted by fight count, most fought
+/// first.
+#[utoipa::path(
+    get,
+    path = "/api/enemies",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("act" = Option<i32>, Query, description = "Only count fights and deaths in this act (1-4)"),
+        ("normalize" = Option<bool>, Query, description = "Merge encounters that differ only by group size")
+    ),
+    responses(
+        (status = 200, description = "Encounters with fight and death counts", body = Vec<EnemyCount>),
+        (status = 400, description = "Act out of range, or an unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_enemies(
+    Query(params): Query<EnemiesQuery>,
+) -> Result<Json<Vec<EnemyCount>>, (StatusCode, Json<ApiError>)> {
+    if params.act.is_some_and(|act| !(1..=4).contains(&act)) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::with_details(
+                "Invalid act",
+                "INVALID_QUERY",
+                "act must be between 1 and 4",
+            )),
+        ));
+    }
+    let runs = CharacterFilterQuery {
+        character: params.character,
+    }
+    .load_runs()?;
+    Ok(Json(enemy_catalog(
+        &runs,
+        params.act,
+        params.normalize.unwrap_or(false),
+    )))
+}
+
+/// Query parameters for the slow fights endpoint
+#[derive(Debug, Default, Deserialize)]
+pub struct SlowFightsQuery {
+    /// Only consider runs of these characters (comma-separated)
+    pub character: Option<String>,
+    /// Merge group sizes, e.g. "2 Louse" and "3 Louse" into "Louse"
+    pub normalize: Option<bool>,
+    /// Leave out encounters with fewer fights than this
+    pub min_fights: Option<i32>,
+}
+
+/// Get the encounters whose fights last the most turns, slowest first
+///
+/// Only fights whose `damage_taken` entry records its turns count. Each
+/// encounter lists its average and longest fight, with the run the longest
+/// was in. Hidden runs are ignored.
+#[utoipa::path(
+    get,
+    path = "/api/stats/slow-fights",
+    tag = "sts",
+    params(
+        ("character" = Option<String>, Query, description = "Only consider runs of these characters: a comma-separated list of names, display names or aliases"),
+        ("normalize" = Option<bool>, Query, description = "Merge group sizes, e.g. \"2 Louse\" and \"3 Louse\" into \"Louse\""),
+        ("min_fights" = Option<i32>, Query, description = "Leave out encounters with fewer fights than this (default 1)")
+    ),
+    responses(
+        (status = 200, description = "Fight length per encounter, slowest first", body = Vec<EncounterTurns>),
+        (status = 400, description = "Unknown or empty character filter", body = ApiError),
+        (status = 503, description = "No runs directory configured or detected", body = ApiError)
+    )
+)]
+pub async fn get_slow_fights(
+    Query(params): Query<SlowFightsQuery>,
+) -> Result<Json<Vec<EncounterTurns>>, (StatusCode, Json<ApiError>)> {
+    let runs = CharacterFilterQuery {
+        character: params.character,
+    }
+    .load_runs()?;
+    Ok(Json(slow_fights(
+        &runs,
+        params.normalize.unwrap_or(false),
+        params.min_fights.unwrap_or(1),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::{RunFileBuilder, TestRunsDir, FEATURE_COLUMNS, MAX_TZ_OFFSET_MINUTES};
+
+    #[tokio::test]
+    async fn test_get_characters() {
+        let result = get_characters(AcceptLanguage(Locale::En)).await;
+        assert_eq!(result.0.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_display_names_follow_accept_language() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(crate::api::serve(listener));
+
+        let get = |path: &str, language: &str| {
+            reqwest::Client::new()
+                .get(format!("http://127.0.0.1:{}{}", port, path))
+                .header(header::ACCEPT_LANGUAGE, language)
+                .send()
+        };
+        let characters: serde_json::Value = get("/api/characters", "de-DE,en;q=0.5")
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(characters[0]["id"], "IRONCLAD");
+        assert_eq!(characters[0]["name"], "Der Eisenpanzer");
+
+        let stats: CharacterStats = get("/api/stats/silent", "zh-CN")
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(stats.character, "THE_SILENT");
+        assert_eq!(stats.display_name, "静默猎手");
+
+        let stats: Vec<CharacterStats> =
+            get("/api/stats", "ja").await.unwrap().json().await.unwrap();
+        assert!(stats.iter().any(|s| s.display_name == "Silent"));
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_newer_version() {
+        let doc = serde_json::json!({
+            "schema_version": crate::sts::EXPORT_SCHEMA_VERSION + 1,
+            "runs": [],
+            "character_stats": [],
+            "export_timestamp": 0
+        });
+        let (status, error) = import_export(Json(doc)).await.unwrap_err();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error.code, "UNSUPPORTED_VERSION");
+    }
+
+    #[tokio::test]
+    async fn test_delta_rejects_invalid_cursor() {
+        let query = DeltaQuery {
+            since: Some("not-a-cursor".to_string()),
+        };
+        let (status, error) = get_runs_delta(Query(query)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "INVALID_CURSOR");
+    }
+
+    #[tokio::test]
+    async fn test_relic_timing_requires_relic() {
+        let query = RelicTimingQuery {
+            relic: Some("  ".to_string()),
+            ..Default::default()
+        };
+        let (status, error) = get_relic_timing(Query(query)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "INVALID_QUERY");
+    }
+
+    #[tokio::test]
+    async fn test_purged_cards_by_character() {
+        let runs_dir = TestRunsDir::empty();
+        for (id, character) in [("a", Character::Ironclad), ("b", Character::Defect)] {
+            runs_dir.add(
+                &RunFileBuilder::new(id)
+                    .character(character)
+                    .path(&["M", "$"])
+                    .set("items_purged", vec!["Strike_R+1"])
+                    .set("items_purged_floors", vec![2]),
+            );
+        }
+
+        let query = CharacterFilterQuery {
+            character: Some("ironclad".to_string()),
+        };
+        let Json(stats) = get_purged_cards(Query(query)).await.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].card, "Strike_R");
+        assert_eq!((stats[0].removals, stats[0].shop_removals), (1, 1));
+        assert_eq!(stats[0].avg_floor, Some(2.0));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_character_lists_aliases() {
+        let query = CharacterFilterQuery {
+            character: Some("ironclad, Hermit,Vagabond".to_string()),
+        };
+        let (status, error) = get_shop_stats(Query(query)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "INVALID_QUERY");
+        let details = error.0.details.unwrap();
+        assert!(details.contains("Hermit, Vagabond"), "{details}");
+        assert!(details.contains("THE_SILENT"), "{details}");
+        assert!(details.contains("silent"), "{details}");
+    }
+
+    #[tokio::test]
+    async fn test_empty_character_filter_is_rejected() {
+        for value in ["", " , "] {
+            let query = RunsQuery {
+                character: Some(value.to_string()),
+                ..Default::default()
+            };
+            let (status, error) = get_runs(Query(query)).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(error.0.details.unwrap().starts_with("No character names"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_run_filters_are_rejected() {
+        let conflict = RunsQuery {
+            character: Some("watcher,ironclad".to_string()),
+            exclude_character: Some("Watcher".to_string()),
+            ..Default::default()
+        };
+        let unknown_kind = RunsQuery {
+            exclude: Some("dailies,ghosts".to_string()),
+            ..Default::default()
+        };
+        let unknown_ruleset = RunsQuery {
+            ruleset: Some("standard,hard".to_string()),
+            ..Default::default()
+        };
+        for (query, detail) in [
+            (conflict, "WATCHER"),
+            (unknown_kind, "ghosts"),
+            (unknown_ruleset, "hard"),
+        ] {
+            let (status, error) = get_runs(Query(query)).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(error.code, "INVALID_QUERY");
+            let details = error.0.details.unwrap();
+            assert!(details.contains(detail), "{details}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stats_exclude_characters_and_kinds() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let query = StatsListQuery {
+            exclude_character: Some("watcher".to_string()),
+            exclude: Some("abandoned, hidden".to_string()),
+            ..Default::default()
+        };
+        let (_, Json(stats)) = get_stats(AcceptLanguage(Locale::En), Query(query))
+            .await
+            .unwrap();
+        assert!(stats.iter().all(|s| s.character != "WATCHER"));
+
+        let query = ExportQuery {
+            exclude: Some("seeded".to_string()),
+            exclude_daily: Some(true),
+            exclude_seeded: Some(true),
+            ..Default::default()
+        };
+        let filter = query.filter().unwrap();
+        assert_eq!(
+            filter.selection.exclude,
+            [RunExclusion::Seeded, RunExclusion::Dailies]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filter_by_ruleset() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let count = |ruleset: &str| {
+            let query = RunsQuery {
+                ruleset: Some(ruleset.to_string()),
+                ..Default::default()
+            };
+            async move {
+                let response = get_runs(Query(query)).await.unwrap();
+                response.headers()["x-total-count"]
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            }
+        };
+        assert_eq!(count("standard").await, "4");
+        assert_eq!(count("Daily, custom").await, "0");
+    }
+
+    #[tokio::test]
+    async fn test_filter_by_several_characters() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let query = RunsQuery {
+            character: Some("silent, IRONCLAD,ic".to_string()),
+            ..Default::default()
+        };
+        let response = get_runs(Query(query)).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let runs: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        let mut characters: Vec<&str> = runs
+            .iter()
+            .map(|r| r["character"].as_str().unwrap())
+            .collect();
+        characters.sort();
+        assert_eq!(characters, ["IRONCLAD", "THE_SILENT"]);
+
+        let query = ExportQuery {
+            character: Some("watcher,defect".to_string()),
+            ..Default::default()
+        };
+        let filter = query.filter().unwrap();
+        assert_eq!(
+            filter.selection.characters,
+            [Character::Watcher, Character::Defect]
+        );
+        let response = get_export(Query(query)).await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let export: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(export["runs"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            export["filters"]["characters"],
+            serde_json::json!(["WATCHER", "DEFECT"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_character_runs_accepts_alias() {
+        let _dir = TestRunsDir::empty();
+        let response = get_character_runs(
+            CharacterPath(Character::TheSilent),
+            Query(CharacterRunsQuery::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_enemies_rejects_invalid_act() {
+        let query = EnemiesQuery {
+            act: Some(5),
+            ..Default::default()
+        };
+        let (status, error) = get_enemies(Query(query)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "INVALID_QUERY");
+    }
+
+    #[tokio::test]
+    async fn test_time_grouped_endpoints_reject_out_of_range_offset() {
+        let calendar_query = || CalendarQuery {
+            tz_offset_minutes: Some(MAX_TZ_OFFSET_MINUTES + 1),
+            ..Default::default()
+        };
+        let (status, error) = get_calendar(Query(calendar_query())).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "INVALID_QUERY");
+        let (status, _) = get_calendar_years(Query(calendar_query()))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        for offset in [-MAX_TZ_OFFSET_MINUTES - 1, i32::MIN, i32::MAX] {
+            let query = WhenQuery {
+                tz_offset_minutes: Some(offset),
+                ..Default::default()
+            };
+            let (status, error) = get_when_stats(Query(query)).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{offset}");
+            assert_eq!(error.code, "INVALID_QUERY");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_calendar_for_empty_dir_is_all_zero() {
+        let _runs_dir = TestRunsDir::empty();
+        let query = CalendarQuery {
+            year: Some(2024),
+            tz_offset_minutes: Some(-300),
+            ..Default::default()
+        };
+        let Json(days) = get_calendar(Query(query)).await.unwrap();
+        assert_eq!(days.len(), 366);
+        assert!(days.iter().all(|d| d.runs == 0));
+    }
+
+    #[tokio::test]
+    async fn test_runs_limit_above_maximum() {
+        let _runs_dir = TestRunsDir::empty();
+        let query = RunsQuery {
+            limit: Some(config::MAX_LIST_LIMIT + 1),
+            ..Default::default()
+        };
+        let (status, error) = get_runs(Query(query)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "LIMIT_EXCEEDED");
+    }
+
+    #[tokio::test]
+    async fn test_character_runs_report_pagination() {
+        let _runs_dir = TestRunsDir::empty();
+        let query = CharacterRunsQuery {
+            limit: Some(1),
+            ..Default::default()
+        };
+        let response = get_character_runs(CharacterPath(Character::Ironclad), Query(query))
+            .await
+            .unwrap();
+        let headers = response.headers();
+        assert_eq!(headers[crate::api::pagination::LIMIT_HEADER], "1");
+        assert!(headers.contains_key(crate::api::pagination::TOTAL_COUNT_HEADER));
+    }
+
+    #[tokio::test]
+    async fn test_reparse_run() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let play_id = crate::sts::load_all_runs()[0].play_id.clone();
+        let Json(run) = reparse_run(Path(play_id.clone())).await.unwrap();
+        assert_eq!(run.play_id, play_id);
+
+        let (status, error) = reparse_run(Path("no-such-run".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error.code, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_source_file_only_on_run_detail() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let runs_path = crate::sts::get_runs_path().unwrap();
+        let response = get_runs(Query(RunsQuery::default())).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let runs: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert!(runs
+            .iter()
+            .all(|r| r.get("source_path").is_none() && r.get("file_modified_at").is_none()));
+
+        let play_id = runs[0]["play_id"].as_str().unwrap().to_string();
+        let Json(detail) = get_run(Path(play_id.clone())).await.unwrap();
+        let source = detail.run.source_path.unwrap();
+        assert!(std::path::Path::new(&source).starts_with(&runs_path));
+        assert!(detail.run.file_modified_at.is_some());
+
+        let options = ExportOptions {
+            redact_path: true,
+            ..Default::default()
+        };
+        let export = get_export_data_with(&options).unwrap();
+        let run = export.runs.iter().find(|r| r.play_id == play_id).unwrap();
+        let redacted = run.source_path.as_deref().unwrap();
+        assert!(!redacted.contains(&*runs_path.to_string_lossy()));
+        assert!(redacted.ends_with(".run"));
+    }
+
+    #[tokio::test]
+    async fn test_hide_unknown_run() {
+        let _runs_dir = TestRunsDir::empty();
+        let result = hide_run(Path("no-such-run".to_string())).await;
+        let (status, error) = result.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(error.code, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_empty_runs_dir_returns_empty_list() {
+        let _runs_dir = TestRunsDir::empty();
+        let response = get_runs(Query(RunsQuery::default())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"[]");
+    }
+
+    #[tokio::test]
+    async fn test_export_formats() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let cases = [
+            (
+                Some("json"),
+                None,
+                "application/json",
+                "sts-export.json",
+                "{",
+            ),
+            (
+                Some("csv"),
+                None,
+                "text/csv",
+                "sts-runs.csv",
+                "play_id,character,",
+            ),
+            (
+                Some("csv"),
+                Some("stats"),
+                "text/csv",
+                "sts-character-stats.csv",
+                "character,display_name,",
+            ),
+            (
+                Some("ndjson"),
+                None,
+                "application/x-ndjson",
+                "sts-runs.ndjson",
+                "{\"play_id\"",
+            ),
+            (
+                None,
+                None,
+                "application/json",
+                "sts-export.json",
+                "{\"schema_version\"",
+            ),
+        ];
+        for (format, part, content_type, file_name, start) in cases {
+            let query = ExportQuery {
+                format: format.map(str::to_string),
+                part: part.map(str::to_string),
+                ..Default::default()
+            };
+            let response = get_export(Query(query)).await;
+            assert_eq!(response.status(), StatusCode::OK, "{format:?}");
+            let headers = response.headers();
+            assert!(headers[header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with(content_type));
+            assert!(headers[header::CONTENT_DISPOSITION]
+                .to_str()
+                .unwrap()
+                .contains(file_name));
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(body.starts_with(start.as_bytes()), "{format:?} {part:?}");
+            if format == Some("ndjson") {
+                assert_eq!(body.iter().filter(|&&b| b == b'\n').count(), 4);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ascension_stats_match_export() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let Json(stats) = get_ascension_stats(Query(AscensionStatsQuery::default()))
+            .await
+            .unwrap();
+        let export = get_export_data_with(&ExportOptions::default()).unwrap();
+        assert_eq!(stats, export.ascension_stats);
+        assert_eq!(stats.len(), 4);
+
+        let query = AscensionStatsQuery {
+            character: Some("ic".to_string()),
+            ..Default::default()
+        };
+        let Json(stats) = get_ascension_stats(Query(query)).await.unwrap();
+        assert!(stats.iter().all(|s| s.character == "IRONCLAD"));
+    }
+
+    #[tokio::test]
+    async fn test_deck_size_stats() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let Json(stats) = get_deck_size_stats(Query(DeckSizeQuery::default()))
+            .await
+            .unwrap();
+        assert_eq!(stats.buckets.len(), DEFAULT_DECK_SIZE_BOUNDS.len() + 1);
+        let bucketed: i32 = stats.buckets.iter().map(|b| b.runs).sum();
+        assert_eq!(bucketed, stats.runs);
+
+        let query = DeckSizeQuery {
+            exclude_character: Some("ironclad,silent,defect,watcher".to_string()),
+            bounds: Some("10".to_string()),
+            ..Default::default()
+        };
+        let Json(stats) = get_deck_size_stats(Query(query)).await.unwrap();
+        assert_eq!(stats.runs, 0);
+        let labels: Vec<&str> = stats.buckets.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(labels, ["<=10", "11+"]);
+
+        let query = DeckSizeQuery {
+            bounds: Some("30,20".to_string()),
+            ..Default::default()
+        };
+        let (status, Json(error)) = get_deck_size_stats(Query(query)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "INVALID_QUERY");
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_impact() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let query = UpgradeImpactQuery {
+            character: Some("ironclad".to_string()),
+            min_runs: Some(1),
+            ..Default::default()
+        };
+        let Json(stats) = get_upgrade_impact(Query(query)).await.unwrap();
+        let bucketed: i32 = stats.by_upgrades.iter().map(|b| b.runs).sum();
+        assert_eq!(bucketed, stats.runs);
+        assert_eq!(stats.min_runs, 1);
+        assert_eq!(stats.by_upgrade_ratio.len(), 4);
+
+        let query = UpgradeImpactQuery {
+            character: Some("ironclad".to_string()),
+            exclude_character: Some("ic".to_string()),
+            ..Default::default()
+        };
+        let (status, _) = get_upgrade_impact(Query(query)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_stats_sorting_and_min_runs() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let query = StatsListQuery {
+            sort_by: Some("win_rate".to_string()),
+            ..Default::default()
+        };
+        let (_, Json(stats)) = get_stats(AcceptLanguage(Locale::En), Query(query))
+            .await
+            .unwrap();
+        // Ironclad and the Silent won; the tie keeps character order
+        let names: Vec<&str> = stats.iter().map(|s| s.character.as_str()).collect();
+        assert_eq!(names, ["IRONCLAD", "THE_SILENT", "DEFECT", "WATCHER"]);
+
+        let query = StatsListQuery {
+            min_runs: Some(2),
+            ..Default::default()
+        };
+        let (_, Json(stats)) = get_stats(AcceptLanguage(Locale::En), Query(query))
+            .await
+            .unwrap();
+        assert!(stats.is_empty());
+
+        let query = StatsListQuery {
+            sort_by: Some("deck_size".to_string()),
+            ..Default::default()
+        };
+        let (status, error) = get_stats(AcceptLanguage(Locale::En), Query(query))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "INVALID_QUERY");
+    }
+
+    #[tokio::test]
+    async fn test_stats_time_window() {
+        let _runs_dir = TestRunsDir::with_samples();
+        // An Ironclad loss from before the window, next to the sample win
+        let dir = crate::sts::get_runs_path().unwrap().join("IRONCLAD");
+        let sample = std::fs::read(dir.join("1700000000.run")).unwrap();
+        let mut run: serde_json::Value = serde_json::from_slice(&sample).unwrap();
+        run["play_id"] = "old-ironclad-loss".into();
+        run["timestamp"] = 1_600_000_000.into();
+        run["victory"] = false.into();
+        run["killed_by"] = "Hexaghost".into();
+        std::fs::write(dir.join("1600000000.run"), run.to_string()).unwrap();
+
+        let (headers, Json(stats)) =
+            get_stats(AcceptLanguage(Locale::En), Query(StatsListQuery::default()))
+                .await
+                .unwrap();
+        assert_eq!(stats[0].win_rate, 0.5);
+        assert!(headers.get(STATS_SINCE_HEADER).is_none());
+
+        let query = StatsListQuery {
+            since: Some(1_650_000_000),
+            exclude: Some("abandoned".to_string()),
+            ..Default::default()
+        };
+        let (headers, Json(stats)) = get_stats(AcceptLanguage(Locale::En), Query(query))
+            .await
+            .unwrap();
+        assert_eq!((stats[0].total_runs, stats[0].win_rate), (1, 1.0));
+        assert!(stats.iter().all(|s| s.character != "DEFECT"));
+        assert_eq!(headers[STATS_SINCE_HEADER], "1650000000");
+        assert!(headers.get(STATS_UNTIL_HEADER).is_none());
+
+        let query = StatsQuery {
+            until: Some(1_650_000_000),
+            ..Default::default()
+        };
+        let (headers, Json(stats)) = get_character_stats(
+            CharacterPath(Character::Ironclad),
+            AcceptLanguage(Locale::En),
+            Query(query),
+        )
+        .await
+        .unwrap();
+        assert_eq!((stats.total_runs, stats.win_rate), (1, 0.0));
+        assert_eq!(headers[STATS_UNTIL_HEADER], "1650000000");
+
+        let query = SummaryQuery {
+            since: Some(1_650_000_000),
+            until: Some(1_700_000_001),
+        };
+        let (headers, Json(summary)) = get_summary(Query(query)).await.unwrap();
+        assert_eq!(summary.total_runs, 2);
+        assert_eq!(headers.len(), 2);
+
+        let query = StatsListQuery {
+            since: Some(1_700_000_000),
+            until: Some(1_600_000_000),
+            ..Default::default()
+        };
+        let (status, Json(error)) = get_stats(AcceptLanguage(Locale::En), Query(query))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "INVALID_QUERY");
+    }
+
+    #[tokio::test]
+    async fn test_period_comparison() {
+        let _runs_dir = TestRunsDir::with_samples();
+        // The Defect and Watcher samples lost before 2023, the Ironclad and
+        // Silent ones won after
+        let query = ComparePeriodsQuery {
+            split: Some("2023-01-01".to_string()),
+            min_runs: Some(2),
+            ..Default::default()
+        };
+        let Json(comparison) = get_period_comparison(Query(query)).await.unwrap();
+        assert_eq!((comparison.before.runs, comparison.before.wins), (2, 0));
+        assert_eq!((comparison.after.runs, comparison.after.wins), (2, 2));
+        assert_eq!(comparison.delta.win_rate, 1.0);
+        assert!(comparison.warnings.is_empty());
+        assert_eq!(comparison.after.characters.len(), 2);
+
+        let query = ComparePeriodsQuery {
+            split: Some("2023-01-01".to_string()),
+            window_days: Some(400),
+            character: Some("watcher,ironclad".to_string()),
+            ..Default::default()
+        };
+        let Json(comparison) = get_period_comparison(Query(query)).await.unwrap();
+        assert_eq!((comparison.before.runs, comparison.after.runs), (1, 1));
+        assert_eq!(comparison.warnings.len(), 2);
+
+        for (split, window_days) in [
+            (None, None),
+            (Some("June 1st"), None),
+            (Some("2024-06-01"), Some(0)),
+        ] {
+            let query = ComparePeriodsQuery {
+                split: split.map(str::to_string),
+                window_days,
+                ..Default::default()
+            };
+            let (status, Json(error)) = get_period_comparison(Query(query)).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(error.code, "INVALID_QUERY");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stats_normalized_by_ascension() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let (_, Json(stats)) =
+            get_stats(AcceptLanguage(Locale::En), Query(StatsListQuery::default()))
+                .await
+                .unwrap();
+        assert!(stats.iter().all(|s| s.ascension_normalized.is_none()));
+        let json = serde_json::to_value(&stats).unwrap();
+        assert!(json[0].get("ascension_normalized").is_none());
+
+        let query = StatsListQuery {
+            normalize: Some("Ascension".to_string()),
+            ..Default::default()
+        };
+        let (_, Json(stats)) = get_stats(AcceptLanguage(Locale::En), Query(query))
+            .await
+            .unwrap();
+        for entry in &stats {
+            let normalized = entry.ascension_normalized.as_ref().unwrap();
+            let band_runs: i32 = normalized.bands.iter().map(|b| b.runs).sum();
+            assert_eq!(band_runs, entry.total_runs);
+            // Sample data has one run per character
+            assert!(normalized.bands.iter().all(|b| b.low_confidence));
+            assert_eq!(normalized.win_rate, Some(entry.win_rate));
+        }
+
+        let query = StatsListQuery {
+            normalize: Some("floor".to_string()),
+            ..Default::default()
+        };
+        let (status, _) = get_stats(AcceptLanguage(Locale::En), Query(query))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_ml_features_formats() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let response = get_ml_features(Query(FeaturesQuery::default())).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let set: FeatureSet = serde_json::from_slice(&body).unwrap();
+        assert_eq!(set.columns, FEATURE_COLUMNS);
+        assert!(set
+            .rows
+            .iter()
+            .all(|r| r.features.len() == FEATURE_COLUMNS.len()));
+
+        let query = FeaturesQuery {
+            format: Some("CSV".to_string()),
+            ..Default::default()
+        };
+        let response = get_ml_features(Query(query)).await;
+        assert!(response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/csv"));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let csv = String::from_utf8(body.to_vec()).unwrap();
+        assert_eq!(csv.lines().count(), set.rows.len() + 1);
+        assert!(csv.starts_with("play_id,character,ascension_level,"));
+
+        let query = FeaturesQuery {
+            format: Some("parquet".to_string()),
+            ..Default::default()
+        };
+        let response = get_ml_features(Query(query)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_export_rejects_unknown_format() {
+        let query = ExportQuery {
+            format: Some("xml".to_string()),
+            ..Default::default()
+        };
+        let response = get_export(Query(query)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let error: ApiError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code, "INVALID_QUERY");
+        assert!(error.details.unwrap().contains("json, csv, ndjson"));
+
+        let (status, _) = ExportFormat::from_query(Some("csv"), Some("relics")).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_missing_runs_path_is_503() {
+        let (status, error) = runs_path_not_found(RunsPathNotFound);
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error.code, "RUNS_PATH_NOT_FOUND");
+        assert!(error.details.as_deref().unwrap().contains("settings"));
+    }
+
+    #[tokio::test]
+    async fn test_filter_runs_by_items_and_encounter() {
+        let runs_dir = TestRunsDir::empty();
+        runs_dir.add(
+            &RunFileBuilder::new("a")
+                .victory(false)
+                .set("killed_by", "Gremlin Nob")
+                .set("relics", vec!["Burning Blood", "Dead Branch"])
+                .set("master_deck", vec!["Bash+1", "Feed"]),
+        );
+        runs_dir.add(
+            &RunFileBuilder::new("b")
+                .victory(false)
+                .set("killed_by", "Lagavulin")
+                .set("relics", vec!["Burning Blood"])
+                .set("master_deck", vec!["Bash"]),
+        );
+        let play_ids = |query: RunsQuery| async move {
+            let response = get_runs(Query(query)).await.unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let runs: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+            let mut ids: Vec<String> = runs
+                .iter()
+                .map(|r| r["play_id"].as_str().unwrap().to_string())
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        let query = RunsQuery {
+            relic: Some("dead branch, Burning Blood".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(play_ids(query).await, ["a"]);
+        let query = RunsQuery {
+            card: Some("bash".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(play_ids(query).await, ["a", "b"]);
+        let query = RunsQuery {
+            card: Some("Feed+1".to_string()),
+            killed_by: Some("lagavulin".to_string()),
+            ..Default::default()
+        };
+        assert!(play_ids(query).await.is_empty());
+
+        let inverted = RunsQuery {
+            min_ascension: Some(10),
+            max_ascension: Some(5),
+            ..Default::default()
+        };
+        let (status, error) = get_runs(Query(inverted)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error.0.details.unwrap().contains("max_ascension"));
+    }
+
+    mod legacy_filters {
+        use super::*;
+        use crate::sts::demo::generate_runs;
+        use proptest::prelude::*;
+
+        /// `GET /api/runs` filtering before [`RunFilter`]
+        fn legacy_runs_query<'a>(query: &RunsQuery, runs: &'a [RunMetrics]) -> Vec<&'a RunMetrics> {
+            let mut selection = run_selection(
+                query.character.as_deref(),
+                query.exclude_character.as_deref(),
+                query.exclude.as_deref(),
+            )
+            .unwrap();
+            selection.rulesets = resolve_rulesets(query.ruleset.as_deref()).unwrap();
+            let include_hidden = query.include_hidden.unwrap_or(false);
+            let victories_only = query.victories_only.unwrap_or(false);
+            let min_ascension = query.min_ascension.unwrap_or(i32::MIN);
+            runs.iter()
+                .filter(|r| include_hidden || !r.hidden)
+                .filter(|r| selection.matches(r))
+                .filter(|r| !victories_only || r.victory)
+                .filter(|r| r.ascension_level >= min_ascension)
+                .collect()
+        }
+
+        /// [`ExportFilter::matches`] before [`RunFilter`]
+        fn legacy_export_filter(filter: &ExportFilter, run: &RunMetrics) -> bool {
+            filter.selection.matches(run)
+                && filter.window().contains(run)
+                && filter
+                    .min_ascension
+                    .is_none_or(|min| run.ascension_level >= min)
+                && filter
+                    .max_ascension
+                    .is_none_or(|max| run.ascension_level <= max)
+        }
+
+        /// Demo runs with some of them hidden, abandoned or played under
+        /// other rulesets
+        fn dataset() -> impl Strategy<Value = Vec<RunMetrics>> {
+            (
+                any::<u64>(),
+                prop::collection::vec((any::<bool>(), any::<bool>(), 0..Ruleset::ALL.len()), 0..40),
+            )
+                .prop_map(|(seed, tweaks)| {
+                    let mut runs = generate_runs(seed, tweaks.len());
+                    for (run, (hidden, abandoned, ruleset)) in runs.iter_mut().zip(tweaks) {
+                        run.hidden = hidden;
+                        if abandoned && !run.victory {
+                            run.killed_by = None;
+                        }
+                        run.ruleset = Ruleset::ALL[ruleset];
+                    }
+                    runs
+                })
+        }
+
+        /// A comma-separated list of some of `names`
+        fn some_of(names: Vec<&'static str>) -> impl Strategy<Value = Option<String>> {
+            prop::option::of(prop::sample::subsequence(names.clone(), 1..=names.len()))
+                .prop_map(|names| names.map(|n| n.join(",")))
+        }
+
+        /// Characters to keep and to leave out, never the same one in both
+        fn characters() -> impl Strategy<Value = (Option<String>, Option<String>)> {
+            let names: Vec<&'static str> = Character::all().iter().map(|c| c.dir_name()).collect();
+            prop::sample::subsequence(names.clone(), 0..=names.len()).prop_map(move |kept| {
+                let left_out: Vec<&str> = names
+                    .iter()
+                    .copied()
+                    .filter(|n| !kept.contains(n))
+                    .take(1)
+                    .collect();
+                let join = |names: &[&str]| (!names.is_empty()).then(|| names.join(","));
+                (join(&kept), join(&left_out))
+            })
+        }
+
+        fn runs_query() -> impl Strategy<Value = RunsQuery> {
+            (
+                characters(),
+                some_of(RunExclusion::ALL.iter().map(|e| e.name()).collect()),
+                some_of(Ruleset::ALL.iter().map(|r| r.name()).collect()),
+                prop::option::of(any::<bool>()),
+                prop::option::of(0..=20i32),
+                prop::option::of(any::<bool>()),
+            )
+                .prop_map(
+                    |(
+                        (character, exclude_character),
+                        exclude,
+                        ruleset,
+                        victories_only,
+                        min_ascension,
+                        include_hidden,
+                    )| RunsQuery {
+                        character,
+                        exclude_character,
+                        exclude,
+                        ruleset,
+                        victories_only,
+                        min_ascension,
+                        include_hidden,
+                        ..Default::default()
+                    },
+                )
+        }
+
+        fn export_query() -> impl Strategy<Value = ExportQuery> {
+            (
+                characters(),
+                some_of(RunExclusion::ALL.iter().map(|e| e.name()).collect()),
+                prop::option::of(0..=20i32),
+                0..=20i32,
+                prop::option::of(0..365i64),
+                1..365i64,
+            )
+                .prop_map(
+                    |((character, exclude_character), exclude, min, span, since, days)| {
+                        // Demo runs end within the year before 2025
+                        let (year_end, day) = (1_735_689_600, 24 * 60 * 60);
+                        let since = since.map(|d| year_end - (365 - d) * day);
+                        ExportQuery {
+                            character,
+                            exclude_character,
+                            exclude,
+                            min_ascension: min,
+                            max_ascension: min.map(|min| min + span),
+                            since,
+                            until: since.map(|since| since + days * day),
+                            ..Default::default()
+                        }
+                    },
+                )
+        }
+
+        proptest! {
+            #![proptest_config(ProptestConfig::with_cases(64))]
+
+            #[test]
+            fn test_runs_query_matches_legacy_filter(runs in dataset(), query in runs_query()) {
+                let filter = RunFilter::try_from(&query).unwrap();
+                let kept: Vec<&str> = filter.apply(&runs).iter().map(|r| r.play_id.as_str()).collect();
+                let legacy: Vec<&str> = legacy_runs_query(&query, &runs)
+                    .iter()
+                    .map(|r| r.play_id.as_str())
+                    .collect();
+                prop_assert_eq!(kept, legacy);
+            }
+
+            #[test]
+            fn test_export_query_matches_legacy_filter(runs in dataset(), query in export_query()) {
+                let export_filter = query.filter().unwrap();
+                let filter = RunFilter::from(&export_filter);
+                for run in &runs {
+                    prop_assert_eq!(filter.matches(run), legacy_export_filter(&export_filter, run));
+                }
+            }
+        }
     }
 }
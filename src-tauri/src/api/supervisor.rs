@@ -0,0 +1,174 @@
+//! API server supervision
+//!
+//! The server runs in its own task so a panic doesn't take the whole API down
+//! silently. When the task panics, the panic is recorded in the app status and
+//! the server is restarted with exponential backoff, up to a fixed number of
+//! attempts.
+
+use std::any::Any;
+use std::future::Future;
+use std::time::Duration;
+
+use super::{start_server_with, ServerOptions};
+use crate::status::{set_server_state, ServerState};
+
+/// Error returned by the API server
+pub type ServerError = Box<dyn std::error::Error + Send + Sync>;
+
+/// When and how often a panicked server is restarted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Maximum number of restarts before giving up
+    pub max_restarts: u32,
+    /// Delay before the first restart; doubled for each further attempt
+    pub base_delay: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 3,
+            base_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Delay before a restart attempt (starting at 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay * 2u32.saturating_pow(attempt.saturating_sub(1))
+    }
+}
+
+/// Extract a readable message from a panic payload
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Run a server future, restarting it when it panics
+///
+/// Errors returned by the server (e.g. a port that can't be bound) are passed
+/// through without a restart, since retrying won't fix them.
+pub async fn supervise<F, Fut>(policy: RestartPolicy, mut start: F) -> Result<(), ServerError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), ServerError>> + Send + 'static,
+{
+    let mut attempt = 0;
+    loop {
+        let panic = match tokio::spawn(start()).await {
+            Ok(result) => return result,
+            Err(e) if e.is_panic() => panic_message(&*e.into_panic()),
+            Err(e) => return Err(e.into()),
+        };
+
+        attempt += 1;
+        if attempt > policy.max_restarts {
+            let error = format!(
+                "API server panicked {} times, giving up: {}",
+                attempt, panic
+            );
+            tracing::error!("{}", error);
+            set_server_state(ServerState::Failed {
+                error: error.clone(),
+            });
+            return Err(error.into());
+        }
+
+        let delay = policy.delay(attempt);
+        tracing::error!(
+            "API server panicked: {}; restarting in {:?} (attempt {}/{})",
+            panic,
+            delay,
+            attempt,
+            policy.max_restarts
+        );
+        set_server_state(ServerState::Restarting {
+            attempt,
+            max_attempts: policy.max_restarts,
+            error: panic,
+        });
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Start the API server under supervision with the default restart policy
+pub async fn start_supervised(options: ServerOptions) -> Result<(), ServerError> {
+    supervise(RestartPolicy::default(), move || start_server_with(options)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    fn fast_policy(max_restarts: u32) -> RestartPolicy {
+        RestartPolicy {
+            max_restarts,
+            base_delay: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn test_restart_delay_backs_off() {
+        let policy = RestartPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(2), Duration::from_millis(1000));
+        assert_eq!(policy.delay(3), Duration::from_millis(2000));
+    }
+
+    #[tokio::test]
+    async fn test_supervise_restarts_after_panic() {
+        let starts = Arc::new(AtomicU32::new(0));
+        let counter = starts.clone();
+        let result = supervise(fast_policy(3), move || {
+            let count = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if count < 2 {
+                    panic!("injected panic");
+                }
+                Ok(())
+            }
+        })
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_supervise_gives_up_after_max_restarts() {
+        let starts = Arc::new(AtomicU32::new(0));
+        let counter = starts.clone();
+        let result = supervise(fast_policy(2), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { panic!("always panics") }
+        })
+        .await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("always panics"));
+        assert_eq!(starts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_supervise_passes_errors_through() {
+        let starts = Arc::new(AtomicU32::new(0));
+        let counter = starts.clone();
+        let result = supervise(fast_policy(3), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), ServerError>("bind failed".into()) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,58 @@
+//! Response timing
+//!
+//! Each response carries how long the request took in an
+//! `X-Response-Time-Ms` header, and requests slower than the configured
+//! threshold are logged with their path and query.
+
+use std::time::{Duration, Instant};
+
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use parking_lot::RwLock;
+
+/// Header carrying the request duration in milliseconds
+pub const RESPONSE_TIME_HEADER: &str = "x-response-time-ms";
+
+/// Default duration above which a request is logged as slow
+pub const DEFAULT_SLOW_REQUEST_MS: u64 = 500;
+
+static SLOW_REQUEST_THRESHOLD: RwLock<Duration> =
+    RwLock::new(Duration::from_millis(DEFAULT_SLOW_REQUEST_MS));
+
+/// Set the duration above which requests are logged as slow
+pub fn set_slow_request_threshold(threshold: Duration) {
+    *SLOW_REQUEST_THRESHOLD.write() = threshold;
+}
+
+/// Duration above which requests are logged as slow
+pub fn slow_request_threshold() -> Duration {
+    *SLOW_REQUEST_THRESHOLD.read()
+}
+
+/// Middleware timing each request
+///
+/// The time covers the handler and everything inside this layer; for
+/// streamed responses it ends when the headers are ready, not when the last
+/// byte is sent.
+pub async fn response_time(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let uri = request.uri().clone();
+    let mut response = next.run(request).await;
+    let elapsed = started.elapsed();
+
+    if elapsed > slow_request_threshold() {
+        tracing::warn!(
+            "Slow request: {} took {} ms (status {})",
+            uri.path_and_query().map_or(uri.path(), |p| p.as_str()),
+            elapsed.as_millis(),
+            response.status().as_u16()
+        );
+    }
+    let millis = format!("{:.1}", elapsed.as_secs_f64() * 1000.0);
+    if let Ok(value) = HeaderValue::from_str(&millis) {
+        response.headers_mut().insert(RESPONSE_TIME_HEADER, value);
+    }
+    response
+}
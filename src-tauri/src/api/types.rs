@@ -6,6 +6,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::sts::RunsPathResolution;
+
 /// Health status of the API
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -16,6 +18,17 @@ pub enum HealthStatus {
     Unhealthy,
 }
 
+/// Response from the discovery endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DiscoveryResponse {
+    /// URLs the server believes it is reachable at, loopback first
+    pub urls: Vec<String>,
+    /// Whether the server is advertised on the local network via mDNS
+    pub mdns: bool,
+    /// DNS-SD service type used for the advertisement
+    pub service_type: String,
+}
+
 /// Response from the health check endpoint
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct HealthResponse {
@@ -26,6 +39,12 @@ pub struct HealthResponse {
     /// API version
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
+    /// How the runs directory was resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runs_path: Option<RunsPathResolution>,
+    /// Whether the API is serving synthetic demo data instead of real runs
+    #[serde(default)]
+    pub demo: bool,
 }
 
 /// Request body for greeting endpoint
@@ -55,6 +74,35 @@ pub struct ApiError {
     /// Additional error details
     #[serde(skip_serializing_if = "Option::is_none")]
     pub details: Option<String>,
+    /// ID of the failed request, matching the `X-Request-Id` header and logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Settings that can be changed while the app is running
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Settings {
+    /// Seconds between rescans of the runs directory, for directories that
+    /// don't report file changes (at least 30; null turns it off)
+    pub auto_refresh_secs: Option<u64>,
+}
+
+/// Profiles runs can be kept apart in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Profiles {
+    /// Profile data requests use when they don't name one
+    pub active: String,
+    /// Names of all profiles, sorted
+    pub names: Vec<String>,
+}
+
+/// Hidden state of a run after a hide/unhide request
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RunVisibility {
+    /// Run play_id
+    pub play_id: String,
+    /// Whether the run is now hidden from statistics
+    pub hidden: bool,
 }
 
 impl ApiError {
@@ -64,6 +112,7 @@ impl ApiError {
             error: error.into(),
             code: code.into(),
             details: None,
+            request_id: None,
         }
     }
 
@@ -77,6 +126,7 @@ impl ApiError {
             error: error.into(),
             code: code.into(),
             details: Some(details.into()),
+            request_id: None,
         }
     }
 }
@@ -91,9 +141,12 @@ mod tests {
             status: HealthStatus::Healthy,
             timestamp: Utc::now(),
             version: Some("1.0.0".to_string()),
+            runs_path: None,
+            demo: true,
         };
 
         let json = serde_json::to_string(&response).unwrap();
+        assert!(json.contains("\"demo\":true"));
         assert!(json.contains("healthy"));
         assert!(json.contains("1.0.0"));
     }
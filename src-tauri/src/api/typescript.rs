@@ -0,0 +1,259 @@
+//! TypeScript type generation
+//!
+//! Converts the OpenAPI component schemas into a `.d.ts` file so the frontend
+//! can use types that match the Rust structs. Only the JSON Schema features our
+//! `ToSchema` derives produce are supported: objects, arrays, string enums,
+//! nullable type lists, `oneOf`/`allOf` and component references.
+
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use super::schema_handlers::component_schemas;
+
+/// Header written at the top of generated files
+const HEADER: &str =
+    "// Generated from the STS Stat Viewer OpenAPI schemas. Do not edit by hand.\n";
+
+/// Write a JSDoc comment for a schema description, if it has one
+fn write_doc(out: &mut String, schema: &Value, indent: &str) {
+    let Some(description) = schema.get("description").and_then(Value::as_str) else {
+        return;
+    };
+    let lines: Vec<&str> = description.lines().collect();
+    if lines.len() == 1 {
+        out.push_str(&format!("{}/** {} */\n", indent, lines[0]));
+    } else {
+        out.push_str(&format!("{}/**\n", indent));
+        for line in lines {
+            out.push_str(&format!("{} * {}\n", indent, line).replace(" * \n", " *\n"));
+        }
+        out.push_str(&format!("{} */\n", indent));
+    }
+}
+
+/// Join member types into a union, deduplicating repeated members
+fn union(members: Vec<String>) -> String {
+    let mut unique: Vec<String> = Vec::new();
+    for member in members {
+        if !unique.contains(&member) {
+            unique.push(member);
+        }
+    }
+    match unique.len() {
+        0 => "unknown".to_string(),
+        _ => unique.join(" | "),
+    }
+}
+
+/// Map a single JSON Schema `type` keyword to TypeScript
+fn primitive_type(ty: &str, schema: &Value) -> String {
+    match ty {
+        "string" => "string".to_string(),
+        "integer" | "number" => "number".to_string(),
+        "boolean" => "boolean".to_string(),
+        "null" => "null".to_string(),
+        "array" => {
+            let item = schema
+                .get("items")
+                .map(ts_type)
+                .unwrap_or_else(|| "unknown".to_string());
+            if item.contains(' ') {
+                format!("({})[]", item)
+            } else {
+                format!("{}[]", item)
+            }
+        }
+        "object" => object_type(schema),
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Render an inline object type
+fn object_type(schema: &Value) -> String {
+    if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+        let required = required_fields(schema);
+        let fields: Vec<String> = properties
+            .iter()
+            .map(|(name, prop)| {
+                let optional = if required.contains(&name.as_str()) {
+                    ""
+                } else {
+                    "?"
+                };
+                format!("{}{}: {}", property_name(name), optional, ts_type(prop))
+            })
+            .collect();
+        return format!("{{ {} }}", fields.join("; "));
+    }
+
+    match schema.get("additionalProperties") {
+        Some(Value::Bool(false)) => "Record<string, never>".to_string(),
+        Some(value @ Value::Object(_)) => format!("Record<string, {}>", ts_type(value)),
+        _ => "Record<string, unknown>".to_string(),
+    }
+}
+
+/// Names of required properties of an object schema
+fn required_fields(schema: &Value) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// Quote property names that aren't valid identifiers
+fn property_name(name: &str) -> String {
+    let valid = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if valid {
+        name.to_string()
+    } else {
+        serde_json::to_string(name).unwrap_or_default()
+    }
+}
+
+/// Convert a schema into a TypeScript type expression
+fn ts_type(schema: &Value) -> String {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference
+            .rsplit('/')
+            .next()
+            .unwrap_or("unknown")
+            .to_string();
+    }
+
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return union(values.iter().map(Value::to_string).collect());
+    }
+
+    for key in ["oneOf", "anyOf"] {
+        if let Some(variants) = schema.get(key).and_then(Value::as_array) {
+            return union(variants.iter().map(ts_type).collect());
+        }
+    }
+
+    if let Some(parts) = schema.get("allOf").and_then(Value::as_array) {
+        let parts: Vec<String> = parts.iter().map(ts_type).collect();
+        return match parts.len() {
+            1 => parts[0].clone(),
+            _ => parts
+                .iter()
+                .map(|p| format!("({})", p))
+                .collect::<Vec<_>>()
+                .join(" & "),
+        };
+    }
+
+    match schema.get("type") {
+        Some(Value::String(ty)) => primitive_type(ty, schema),
+        Some(Value::Array(types)) => union(
+            types
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|ty| primitive_type(ty, schema))
+                .collect(),
+        ),
+        _ if schema.get("properties").is_some() => object_type(schema),
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Render one named component as an interface or type alias
+fn render_component(out: &mut String, name: &str, schema: &Value) {
+    write_doc(out, schema, "");
+
+    let is_object = schema.get("type").and_then(Value::as_str) == Some("object");
+    let properties = schema.get("properties").and_then(Value::as_object);
+
+    match properties {
+        Some(properties) if is_object => {
+            let required = required_fields(schema);
+            out.push_str(&format!("export interface {} {{\n", name));
+            for (field, prop) in properties {
+                write_doc(out, prop, "  ");
+                let optional = if required.contains(&field.as_str()) {
+                    ""
+                } else {
+                    "?"
+                };
+                out.push_str(&format!(
+                    "  {}{}: {};\n",
+                    property_name(field),
+                    optional,
+                    ts_type(prop)
+                ));
+            }
+            out.push_str("}\n");
+        }
+        _ => out.push_str(&format!("export type {} = {};\n", name, ts_type(schema))),
+    }
+}
+
+/// Generate a `.d.ts` document from a map of component schemas
+///
+/// Returns the document and the generated type names in output order.
+pub fn generate_typescript(schemas: &Map<String, Value>) -> (String, Vec<String>) {
+    let mut out = String::from(HEADER);
+    let mut names = Vec::new();
+
+    for (name, schema) in schemas {
+        out.push('\n');
+        render_component(&mut out, name, schema);
+        names.push(name.clone());
+    }
+
+    (out, names)
+}
+
+/// Generate TypeScript types for the API and write them to a file
+///
+/// Parent directories are created as needed. Returns the generated type names.
+pub fn write_typescript_types(out_path: &Path) -> std::io::Result<Vec<String>> {
+    let (content, names) = generate_typescript(&component_schemas());
+    if let Some(parent) = out_path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    std::fs::write(out_path, content)?;
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMAS: &str = include_str!("../../tests/fixtures/ts_schemas.json");
+    const EXPECTED: &str = include_str!("../../tests/fixtures/ts_types.d.ts");
+
+    #[test]
+    fn test_generate_typescript_golden() {
+        let schemas: Map<String, Value> = serde_json::from_str(SCHEMAS).unwrap();
+        let (output, names) = generate_typescript(&schemas);
+        assert_eq!(output, EXPECTED);
+        assert_eq!(names.len(), schemas.len());
+    }
+
+    #[test]
+    fn test_generate_typescript_for_api() {
+        let (output, names) = generate_typescript(&component_schemas());
+        assert!(names.contains(&"RunMetrics".to_string()));
+        assert!(output.contains("export type HealthStatus = \"healthy\" | \"unhealthy\";"));
+        assert!(output.contains("  runs: RunMetrics[];"));
+        assert!(output.contains("  killed_by?: string | null;"));
+    }
+
+    #[test]
+    fn test_write_typescript_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("types").join("api.d.ts");
+        let names = write_typescript_types(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(HEADER));
+        assert!(names.iter().all(|n| content.contains(n.as_str())));
+    }
+}
@@ -0,0 +1,106 @@
+//! Data requests during the startup warm-up
+//!
+//! While [`sts::warm_up`] is loading the runs directory, data endpoints serve
+//! the runs parsed so far by default. With the `warmup_mode` config setting
+//! set to `unavailable` they answer 503 with a `Retry-After` header instead,
+//! so clients don't show statistics over part of the runs.
+
+use axum::extract::Request;
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use super::types::ApiError;
+use crate::sts::{self, WarmupState};
+
+/// Seconds clients are asked to wait before retrying during the warm-up
+pub const RETRY_AFTER_SECS: u64 = 2;
+
+/// What data endpoints serve while the runs directory is still loading
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmupMode {
+    /// The runs parsed so far
+    #[default]
+    Partial,
+    /// 503 with `Retry-After` until the load finishes
+    Unavailable,
+}
+
+static WARMUP_MODE: RwLock<WarmupMode> = RwLock::new(WarmupMode::Partial);
+
+/// Set what data endpoints serve during the warm-up
+pub fn set_warmup_mode(mode: WarmupMode) {
+    *WARMUP_MODE.write() = mode;
+}
+
+/// What data endpoints serve during the warm-up
+pub fn warmup_mode() -> WarmupMode {
+    *WARMUP_MODE.read()
+}
+
+/// The 503 response for a warm-up state, if requests should get one
+fn unavailable_response(mode: WarmupMode, state: WarmupState) -> Option<Response> {
+    let WarmupState::Loading {
+        files_processed,
+        files_total,
+    } = state
+    else {
+        return None;
+    };
+    if mode != WarmupMode::Unavailable {
+        return None;
+    }
+    let error = ApiError::with_details(
+        "Runs are still loading",
+        "WARMING_UP",
+        format!("{} of {} run files loaded", files_processed, files_total),
+    );
+    let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(error)).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+    Some(response)
+}
+
+/// Middleware for data routes answering 503 during the warm-up when
+/// configured to
+pub async fn unavailable_during_warmup(request: Request, next: Next) -> Response {
+    match unavailable_response(warmup_mode(), sts::warmup_state()) {
+        Some(response) => response,
+        None => next.run(request).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unavailable_only_while_loading() {
+        let loading = WarmupState::Loading {
+            files_processed: 120,
+            files_total: 300,
+        };
+        let response = unavailable_response(WarmupMode::Unavailable, loading).unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+
+        assert!(unavailable_response(WarmupMode::Partial, loading).is_none());
+        assert!(unavailable_response(WarmupMode::Unavailable, WarmupState::Complete).is_none());
+        assert!(unavailable_response(WarmupMode::Unavailable, WarmupState::NotStarted).is_none());
+    }
+
+    #[test]
+    fn test_mode_names() {
+        let mode: WarmupMode = serde_json::from_str(r#""unavailable""#).unwrap();
+        assert_eq!(mode, WarmupMode::Unavailable);
+        assert_eq!(
+            serde_json::to_value(WarmupMode::Partial).unwrap(),
+            "partial"
+        );
+    }
+}
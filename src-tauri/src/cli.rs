@@ -0,0 +1,565 @@
+//! Command line interface
+//!
+//! Running the binary without a recognised subcommand starts the desktop app.
+//! The subcommands below cover headless and developer workflows:
+//!
+//! - `serve [--port N] [--lan] [--validate]`: run only the API server
+//!   (headless mode); `--validate` exits instead of serving a broken runs
+//!   directory, see [`validate`]
+//! - `generate-ts-types <out_path>`: write TypeScript types for the API schemas
+//! - `openapi --out <path> [--format json|yaml] [--force]`: write the OpenAPI
+//!   spec without starting the server; the format defaults to the file
+//!   extension
+//! - `verify-export <path>`: check an export file's checksum without importing
+//!   it
+//! - `export-settings <path>`: write the config and hidden runs of every
+//!   profile into a settings bundle, see [`settings_bundle`]
+//! - `import-settings <path> [--dry-run]`: restore a settings bundle, or with
+//!   `--dry-run` only print what would change
+
+use std::path::PathBuf;
+
+use crate::api::{self, ServerOptions, SpecFormat};
+use crate::{config, logging, refresh, settings_bundle, sts, validate};
+
+/// A parsed subcommand
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Run the API server without the desktop UI
+    Serve(ServeArgs),
+    /// Generate TypeScript types into the given file
+    GenerateTsTypes { out_path: PathBuf },
+    /// Write the OpenAPI spec into the given file
+    OpenApi {
+        out_path: PathBuf,
+        format: SpecFormat,
+        /// Replace the file if it already exists
+        force: bool,
+    },
+    /// Write a standalone HTML stats report into the given file
+    GenerateHtmlReport { out_path: PathBuf },
+    /// Check an export file without importing it
+    VerifyExport { path: PathBuf },
+    /// Write a settings bundle into the given file
+    ExportSettings { out_path: PathBuf },
+    /// Restore a settings bundle
+    ImportSettings {
+        path: PathBuf,
+        /// Only report what the import would change
+        dry_run: bool,
+    },
+}
+
+/// Arguments for the `serve` subcommand; unset values come from the config file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServeArgs {
+    /// Port override
+    pub port: Option<u16>,
+    /// Listen on all interfaces
+    pub lan: bool,
+    /// Load and check the runs directory before serving, exiting on failure
+    /// (also enabled by `STS_VIEWER_REQUIRE_RUNS=1`)
+    pub validate: bool,
+}
+
+impl ServeArgs {
+    /// Combine the arguments with the persisted config
+    pub fn options(&self, config: &config::AppConfig) -> ServerOptions {
+        ServerOptions {
+            port: self.port.unwrap_or(config.port),
+            lan: self.lan || config.lan,
+            mdns: config.mdns,
+        }
+    }
+}
+
+/// Parse command line arguments (without the program name)
+///
+/// Returns `Ok(None)` when no subcommand was given so the caller can start the
+/// desktop app; arguments the app doesn't know are left for Tauri to handle.
+pub fn parse_args(args: &[String]) -> Result<Option<Command>, String> {
+    let Some(subcommand) = args.first() else {
+        return Ok(None);
+    };
+
+    match subcommand.as_str() {
+        "serve" => {
+            let mut serve = ServeArgs::default();
+            let mut rest = args[1..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--port" => {
+                        let value = rest.next().ok_or("--port requires a value")?;
+                        let port = value
+                            .parse()
+                            .map_err(|_| format!("Invalid port: {}", value))?;
+                        serve.port = Some(port);
+                    }
+                    "--lan" => serve.lan = true,
+                    "--validate" => serve.validate = true,
+                    other => return Err(format!("Unknown option for serve: {}", other)),
+                }
+            }
+            Ok(Some(Command::Serve(serve)))
+        }
+        "generate-ts-types" => {
+            let out_path = args
+                .get(1)
+                .ok_or("generate-ts-types requires an output path")?;
+            Ok(Some(Command::GenerateTsTypes {
+                out_path: PathBuf::from(out_path),
+            }))
+        }
+        "openapi" => {
+            let mut out_path = None;
+            let mut format = None;
+            let mut force = false;
+            let mut rest = args[1..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--out" => {
+                        let value = rest.next().ok_or("--out requires a path")?;
+                        out_path = Some(PathBuf::from(value));
+                    }
+                    "--format" => {
+                        let value = rest.next().ok_or("--format requires a value")?;
+                        format = Some(SpecFormat::parse(value)?);
+                    }
+                    "--force" => force = true,
+                    other => return Err(format!("Unknown option for openapi: {}", other)),
+                }
+            }
+            let out_path = out_path.ok_or("openapi requires --out <path>")?;
+            let format = format
+                .or_else(|| SpecFormat::from_path(&out_path))
+                .ok_or_else(|| {
+                    format!(
+                        "Can't tell the spec format from {}; pass --format json or yaml",
+                        out_path.display()
+                    )
+                })?;
+            Ok(Some(Command::OpenApi {
+                out_path,
+                format,
+                force,
+            }))
+        }
+        "generate-html-report" => {
+            let out_path = args
+                .get(1)
+                .ok_or("generate-html-report requires an output path")?;
+            Ok(Some(Command::GenerateHtmlReport {
+                out_path: PathBuf::from(out_path),
+            }))
+        }
+        "verify-export" => {
+            let path = args.get(1).ok_or("verify-export requires a file path")?;
+            Ok(Some(Command::VerifyExport {
+                path: PathBuf::from(path),
+            }))
+        }
+        "export-settings" => {
+            let out_path = args
+                .get(1)
+                .ok_or("export-settings requires an output path")?;
+            Ok(Some(Command::ExportSettings {
+                out_path: PathBuf::from(out_path),
+            }))
+        }
+        "import-settings" => {
+            let mut path = None;
+            let mut dry_run = false;
+            for arg in &args[1..] {
+                match arg.as_str() {
+                    "--dry-run" => dry_run = true,
+                    other if other.starts_with("--") => {
+                        return Err(format!("Unknown option for import-settings: {}", other))
+                    }
+                    other => path = Some(PathBuf::from(other)),
+                }
+            }
+            let path = path.ok_or("import-settings requires a file path")?;
+            Ok(Some(Command::ImportSettings { path, dry_run }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Execute a subcommand, returning the process exit code
+pub fn execute(command: Command) -> i32 {
+    match command {
+        Command::Serve(serve) => {
+            logging::init();
+            let config = config::apply();
+            let options = serve.options(&config);
+            if serve.validate || validate::require_runs_env_enabled() {
+                // Loads the runs in the foreground, so there is nothing left
+                // to warm up
+                let code = validate::run_validation(config.max_failed_file_fraction);
+                if code != 0 {
+                    return code;
+                }
+            } else {
+                std::thread::spawn(|| {
+                    if let Err(e) = sts::warm_up(sts::WARMUP_BATCH_FILES, |_| {}) {
+                        tracing::warn!("Startup load skipped: {}", e);
+                    }
+                });
+            }
+            refresh::set_auto_refresh(config.auto_refresh_interval());
+            let rt = tokio::runtime::Runtime::new().expect("failed to create tokio runtime");
+            let result = rt.block_on(async {
+                tokio::select! {
+                    result = api::supervisor::start_supervised(options) => result,
+                    _ = tokio::signal::ctrl_c() => {
+                        tracing::info!("Shutting down");
+                        Ok(())
+                    }
+                }
+            });
+            api::discovery::withdraw();
+            match result {
+                Ok(()) => 0,
+                Err(e) => {
+                    tracing::error!("API server error: {}", e);
+                    1
+                }
+            }
+        }
+        Command::GenerateTsTypes { out_path } => {
+            match api::typescript::write_typescript_types(&out_path) {
+                Ok(names) => {
+                    println!(
+                        "Wrote {} types to {}: {}",
+                        names.len(),
+                        out_path.display(),
+                        names.join(", ")
+                    );
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Failed to write {}: {}", out_path.display(), e);
+                    1
+                }
+            }
+        }
+        Command::OpenApi {
+            out_path,
+            format,
+            force,
+        } => match api::write_openapi_spec(&out_path, format, force) {
+            Ok(bytes) => {
+                println!(
+                    "Wrote OpenAPI spec to {} ({} bytes)",
+                    out_path.display(),
+                    bytes
+                );
+                0
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                eprintln!(
+                    "{} already exists; pass --force to overwrite it",
+                    out_path.display()
+                );
+                1
+            }
+            Err(e) => {
+                eprintln!("Failed to write {}: {}", out_path.display(), e);
+                1
+            }
+        },
+        Command::GenerateHtmlReport { out_path } => {
+            config::apply();
+            let runs = match sts::try_load_all_runs() {
+                Ok(runs) => runs,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    return 1;
+                }
+            };
+            match sts::write_html_report(&runs, &out_path) {
+                Ok(bytes) => {
+                    println!(
+                        "Wrote report of {} runs to {} ({} bytes)",
+                        runs.len(),
+                        out_path.display(),
+                        bytes
+                    );
+                    0
+                }
+                Err(e) => {
+                    eprintln!("Failed to write {}: {}", out_path.display(), e);
+                    1
+                }
+            }
+        }
+        Command::VerifyExport { path } => {
+            let result = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+                .and_then(|json| sts::verify_export_data(&json).map_err(|e| e.to_string()));
+            match result {
+                Ok(verification) if verification.checksum_verified => {
+                    println!(
+                        "{}: {} runs, checksum OK",
+                        path.display(),
+                        verification.runs
+                    );
+                    0
+                }
+                Ok(verification) => {
+                    println!(
+                        "{}: {} runs, no checksum to verify (exported by an older version)",
+                        path.display(),
+                        verification.runs
+                    );
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{}: {}", path.display(), e);
+                    1
+                }
+            }
+        }
+        Command::ExportSettings { out_path } => match settings_bundle::export_settings(&out_path) {
+            Ok(bundle) => {
+                println!(
+                    "Wrote settings of {} profiles to {}",
+                    bundle.config.profile_names().len(),
+                    out_path.display()
+                );
+                0
+            }
+            Err(e) => {
+                eprintln!("Failed to write {}: {}", out_path.display(), e);
+                1
+            }
+        },
+        Command::ImportSettings { path, dry_run } => {
+            match settings_bundle::import_settings(&path, dry_run) {
+                Ok(report) => {
+                    print_import_report(&report);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{}: {}", path.display(), e);
+                    1
+                }
+            }
+        }
+    }
+}
+
+/// Print what a settings import changed, or would change in a dry run
+fn print_import_report(report: &settings_bundle::ImportReport) {
+    let verb = if report.dry_run {
+        "would change"
+    } else {
+        "changed"
+    };
+    if report.changed_settings.is_empty() {
+        println!("Settings: unchanged");
+    } else {
+        println!("Settings {}: {}", verb, report.changed_settings.join(", "));
+    }
+    if !report.restart_required.is_empty() {
+        println!(
+            "Restart the app to apply: {}",
+            report.restart_required.join(", ")
+        );
+    }
+    for (profile, added) in &report.hidden_runs_added {
+        println!("Hidden runs of {}: {} {} added", profile, added, verb);
+    }
+    for missing in &report.missing_paths {
+        println!(
+            "Warning: runs directory of {} doesn't exist here: {}",
+            missing.profile,
+            missing.path.display()
+        );
+    }
+    if report.dry_run {
+        println!("Dry run: nothing was written");
+    }
+}
+
+/// Run the CLI if a subcommand was given
+///
+/// Returns the exit code when a subcommand ran, or `None` to start the app.
+pub fn run(args: &[String]) -> Option<i32> {
+    match parse_args(args) {
+        Ok(Some(command)) => Some(execute(command)),
+        Ok(None) => None,
+        Err(e) => {
+            eprintln!("{}", e);
+            Some(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_no_subcommand_starts_app() {
+        assert_eq!(parse_args(&[]), Ok(None));
+        assert_eq!(parse_args(&args(&["--some-tauri-flag"])), Ok(None));
+    }
+
+    #[test]
+    fn test_parse_serve() {
+        assert_eq!(
+            parse_args(&args(&["serve"])),
+            Ok(Some(Command::Serve(ServeArgs::default())))
+        );
+        assert_eq!(
+            parse_args(&args(&["serve", "--port", "8080", "--lan"])),
+            Ok(Some(Command::Serve(ServeArgs {
+                port: Some(8080),
+                lan: true,
+                validate: false
+            })))
+        );
+        assert_eq!(
+            parse_args(&args(&["serve", "--validate"])),
+            Ok(Some(Command::Serve(ServeArgs {
+                validate: true,
+                ..Default::default()
+            })))
+        );
+        assert!(parse_args(&args(&["serve", "--port", "abc"])).is_err());
+        assert!(parse_args(&args(&["serve", "--port"])).is_err());
+    }
+
+    #[test]
+    fn test_serve_args_override_config() {
+        let config = config::AppConfig {
+            port: 4000,
+            ..Default::default()
+        };
+        let options = ServeArgs::default().options(&config);
+        assert_eq!(options.port, 4000);
+        assert!(!options.lan);
+
+        let serve = ServeArgs {
+            port: Some(5000),
+            lan: true,
+            validate: false,
+        };
+        assert_eq!(
+            serve.options(&config),
+            ServerOptions {
+                port: 5000,
+                lan: true,
+                mdns: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_generate_ts_types() {
+        assert_eq!(
+            parse_args(&args(&["generate-ts-types", "out/api.d.ts"])),
+            Ok(Some(Command::GenerateTsTypes {
+                out_path: PathBuf::from("out/api.d.ts")
+            }))
+        );
+        assert!(parse_args(&args(&["generate-ts-types"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_openapi() {
+        assert_eq!(
+            parse_args(&args(&["openapi", "--out", "docs/spec.yaml"])),
+            Ok(Some(Command::OpenApi {
+                out_path: PathBuf::from("docs/spec.yaml"),
+                format: SpecFormat::Yaml,
+                force: false,
+            }))
+        );
+        assert_eq!(
+            parse_args(&args(&[
+                "openapi", "--out", "spec", "--format", "json", "--force"
+            ])),
+            Ok(Some(Command::OpenApi {
+                out_path: PathBuf::from("spec"),
+                format: SpecFormat::Json,
+                force: true,
+            }))
+        );
+        assert!(parse_args(&args(&["openapi"])).is_err());
+        assert!(parse_args(&args(&["openapi", "--out", "spec"])).is_err());
+        assert!(parse_args(&args(&["openapi", "--out", "spec", "--format", "xml"])).is_err());
+    }
+
+    #[test]
+    fn test_openapi_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("spec.json");
+        let command = |force| Command::OpenApi {
+            out_path: out_path.clone(),
+            format: SpecFormat::Json,
+            force,
+        };
+        assert_eq!(execute(command(false)), 0);
+        let spec: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out_path).unwrap()).unwrap();
+        assert!(spec["paths"]["/api/health"].is_object());
+
+        assert_eq!(execute(command(false)), 1);
+        assert_eq!(execute(command(true)), 0);
+    }
+
+    #[test]
+    fn test_parse_generate_html_report() {
+        assert_eq!(
+            parse_args(&args(&["generate-html-report", "stats.html"])),
+            Ok(Some(Command::GenerateHtmlReport {
+                out_path: PathBuf::from("stats.html")
+            }))
+        );
+        assert!(parse_args(&args(&["generate-html-report"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_verify_export() {
+        assert_eq!(
+            parse_args(&args(&["verify-export", "sts-export.json"])),
+            Ok(Some(Command::VerifyExport {
+                path: PathBuf::from("sts-export.json")
+            }))
+        );
+        assert!(parse_args(&args(&["verify-export"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_settings_bundles() {
+        assert_eq!(
+            parse_args(&args(&["export-settings", "settings.json"])),
+            Ok(Some(Command::ExportSettings {
+                out_path: PathBuf::from("settings.json")
+            }))
+        );
+        assert!(parse_args(&args(&["export-settings"])).is_err());
+        assert_eq!(
+            parse_args(&args(&["import-settings", "--dry-run", "settings.json"])),
+            Ok(Some(Command::ImportSettings {
+                path: PathBuf::from("settings.json"),
+                dry_run: true,
+            }))
+        );
+        assert_eq!(
+            parse_args(&args(&["import-settings", "settings.json"])),
+            Ok(Some(Command::ImportSettings {
+                path: PathBuf::from("settings.json"),
+                dry_run: false,
+            }))
+        );
+        assert!(parse_args(&args(&["import-settings", "--dry-run"])).is_err());
+        assert!(parse_args(&args(&["import-settings", "a.json", "--force"])).is_err());
+    }
+}
@@ -0,0 +1,448 @@
+//! Persisted application configuration
+//!
+//! Settings live in `config.json` inside the app data directory so both the
+//! desktop app and headless mode pick up the same configuration.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::api::warmup::WarmupMode;
+use crate::api::{self, DEFAULT_PORT};
+use crate::{refresh, sts, validate};
+
+/// File name of the config file inside the data directory
+pub const CONFIG_FILE: &str = "config.json";
+
+/// In-memory copy of the config, loaded on first access
+static CONFIG: RwLock<Option<AppConfig>> = RwLock::new(None);
+
+/// Default number of items returned by list endpoints when no limit is given
+pub const DEFAULT_LIST_LIMIT: usize = 1000;
+
+/// Largest limit a caller may request from list endpoints
+pub const MAX_LIST_LIMIT: usize = 5000;
+
+/// Page size limits for API list endpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListLimits {
+    /// Items returned when the caller doesn't pass `limit`
+    pub default_limit: usize,
+    /// Largest `limit` accepted; larger values are rejected
+    pub max_limit: usize,
+}
+
+impl Default for ListLimits {
+    fn default() -> Self {
+        Self {
+            default_limit: DEFAULT_LIST_LIMIT,
+            max_limit: MAX_LIST_LIMIT,
+        }
+    }
+}
+
+/// Settings each profile keeps for itself
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    /// Custom runs directory, overriding auto-detection
+    pub runs_path: Option<PathBuf>,
+    /// Runs counted by the desktop stats and export commands when they are
+    /// given no options
+    pub stats_options: sts::StatsOptions,
+}
+
+/// Why the active profile could not be changed
+#[derive(Debug)]
+pub enum ProfileError {
+    /// The name can't be used for a profile
+    InvalidName(String),
+    /// No profile has this name
+    NotFound(String),
+    /// The config file could not be saved
+    Storage(std::io::Error),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::InvalidName(name) => write!(
+                f,
+                "Invalid profile name {:?}: use 1 to {} letters, digits, '-' or '_'",
+                name,
+                sts::profiles::MAX_PROFILE_NAME_LEN
+            ),
+            ProfileError::NotFound(name) => write!(f, "Profile not found: {}", name),
+            ProfileError::Storage(e) => write!(f, "Failed to save config: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+/// Application settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Custom runs directory, overriding auto-detection
+    pub runs_path: Option<PathBuf>,
+    /// Port for the API server
+    pub port: u16,
+    /// Listen on all interfaces so other devices on the network can connect
+    pub lan: bool,
+    /// Advertise the server via mDNS in LAN mode
+    pub mdns: bool,
+    /// Page size limits for the API list endpoints
+    pub list_limits: ListLimits,
+    /// Search subfolders of each character directory for run files
+    pub recursive_scan: bool,
+    /// Run files larger than this many bytes are skipped
+    pub max_run_file_bytes: u64,
+    /// Serve synthetic demo runs instead of reading the runs directory
+    /// (also enabled by `STS_VIEWER_DEMO=1`)
+    pub demo_mode: bool,
+    /// Requests taking longer than this many milliseconds are logged as slow
+    pub slow_request_ms: u64,
+    /// Origins allowed to read the API from a browser (`"*"` allows any)
+    pub cors_origins: Vec<String>,
+    /// What data endpoints serve while the runs directory is loading at startup
+    pub warmup_mode: WarmupMode,
+    /// Runs counted by the desktop stats and export commands when they are
+    /// given no options
+    pub stats_options: sts::StatsOptions,
+    /// Share of run files that may fail to parse before `serve --validate`
+    /// refuses to start (0.0 to 1.0)
+    pub max_failed_file_fraction: f64,
+    /// Rescan the runs directory every this many seconds, for directories
+    /// that don't report file changes (off when unset)
+    pub auto_refresh_secs: Option<u64>,
+    /// Language of display names returned by the desktop commands (the HTTP
+    /// API follows each request's `Accept-Language` instead)
+    pub locale: sts::Locale,
+    /// How average and highest floor count victories: `raw` as recorded, or
+    /// `normalized` to 51 for Act 3 wins and 57 for heart kills
+    pub floor_semantics: sts::FloorSemantics,
+    /// Show the tray icon with quick stats in the desktop app
+    pub show_tray: bool,
+    /// Profile whose settings are `runs_path` and `stats_options`; configs
+    /// from before profiles existed load as the `default` profile
+    pub active_profile: String,
+    /// Settings of the profiles that aren't active, by name
+    pub profiles: BTreeMap<String, ProfileSettings>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            runs_path: None,
+            port: DEFAULT_PORT,
+            lan: false,
+            mdns: true,
+            list_limits: ListLimits::default(),
+            recursive_scan: false,
+            max_run_file_bytes: sts::DEFAULT_MAX_FILE_BYTES,
+            demo_mode: false,
+            slow_request_ms: api::timing::DEFAULT_SLOW_REQUEST_MS,
+            cors_origins: api::cors::default_origins(),
+            warmup_mode: WarmupMode::default(),
+            stats_options: sts::StatsOptions::default(),
+            max_failed_file_fraction: validate::DEFAULT_MAX_FAILED_FILE_FRACTION,
+            auto_refresh_secs: None,
+            locale: sts::Locale::default(),
+            floor_semantics: sts::FloorSemantics::default(),
+            show_tray: true,
+            active_profile: sts::DEFAULT_PROFILE.to_string(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Load a config file, falling back to defaults if it is missing or invalid
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid config file {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    /// Interval to rescan the runs directory at, if auto-refresh is on
+    ///
+    /// Intervals below [`refresh::MIN_AUTO_REFRESH_SECS`] are raised to it.
+    pub fn auto_refresh_interval(&self) -> Option<Duration> {
+        self.auto_refresh_secs
+            .map(|secs| Duration::from_secs(secs.max(refresh::MIN_AUTO_REFRESH_SECS)))
+    }
+
+    /// Names of all profiles, sorted
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.profiles.keys().cloned().collect();
+        names.push(self.active_profile.clone());
+        names.sort();
+        names
+    }
+
+    /// Make `name` the active profile, keeping the settings of the one that
+    /// was active
+    ///
+    /// With `create`, a missing profile is added with default settings.
+    pub fn switch_profile(&mut self, name: &str, create: bool) -> Result<(), ProfileError> {
+        if !sts::valid_profile_name(name) {
+            return Err(ProfileError::InvalidName(name.to_string()));
+        }
+        if name == self.active_profile {
+            return Ok(());
+        }
+        let next = match self.profiles.remove(name) {
+            Some(next) => next,
+            None if create => ProfileSettings::default(),
+            None => return Err(ProfileError::NotFound(name.to_string())),
+        };
+        let previous = ProfileSettings {
+            runs_path: self.runs_path.take(),
+            stats_options: std::mem::take(&mut self.stats_options),
+        };
+        self.profiles.insert(
+            std::mem::replace(&mut self.active_profile, name.to_string()),
+            previous,
+        );
+        self.runs_path = next.runs_path;
+        self.stats_options = next.stats_options;
+        Ok(())
+    }
+
+    /// Write the config file, creating parent directories as needed
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+    }
+}
+
+/// Location of the config file, if a data directory is available
+pub fn config_path() -> Option<PathBuf> {
+    sts::get_data_dir().map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// Get the current config, loading it from disk on first access
+pub fn current() -> AppConfig {
+    let mut guard = CONFIG.write();
+    guard
+        .get_or_insert_with(|| {
+            config_path()
+                .map(|path| AppConfig::load(&path))
+                .unwrap_or_default()
+        })
+        .clone()
+}
+
+/// Modify the config and persist it
+pub fn update(f: impl FnOnce(&mut AppConfig)) -> std::io::Result<AppConfig> {
+    let mut config = current();
+    f(&mut config);
+    if let Some(path) = config_path() {
+        config.save(&path)?;
+    }
+    *CONFIG.write() = Some(config.clone());
+    Ok(config)
+}
+
+/// Tell [`sts::profiles`] which profile is active and where the runs of the
+/// others are
+fn apply_profiles(config: &AppConfig) {
+    sts::set_active_profile(&config.active_profile);
+    sts::set_profile_runs_paths(
+        config
+            .profiles
+            .iter()
+            .map(|(name, profile)| (name.clone(), profile.runs_path.clone()))
+            .collect(),
+    );
+}
+
+/// Make `name` the active profile and save it, switching the runs directory
+/// and hidden runs to that profile's
+pub fn switch_profile(name: &str, create: bool) -> Result<AppConfig, ProfileError> {
+    let mut switched = current();
+    switched.switch_profile(name, create)?;
+    let config = update(|c| *c = switched).map_err(ProfileError::Storage)?;
+    apply_profiles(&config);
+    sts::set_custom_runs_path(config.runs_path.clone());
+    Ok(config)
+}
+
+/// Re-read the config file, e.g. after it was replaced, and push its
+/// settings into the subsystems that use them
+pub fn reload() -> AppConfig {
+    *CONFIG.write() = None;
+    let config = apply();
+    sts::set_custom_runs_path(config.runs_path.clone());
+    config
+}
+
+/// Load the config and push its settings into the subsystems that use them
+pub fn apply() -> AppConfig {
+    let config = current();
+    apply_profiles(&config);
+    if let Some(path) = &config.runs_path {
+        sts::set_custom_runs_path(Some(path.clone()));
+    }
+    sts::set_scan_options(sts::ScanOptions {
+        recursive: config.recursive_scan,
+        max_file_bytes: config.max_run_file_bytes,
+    });
+    let demo = config.demo_mode || sts::demo::demo_env_enabled();
+    if demo {
+        tracing::warn!("Demo mode is on: serving synthetic runs, not real data");
+    }
+    sts::set_demo_mode(demo);
+    sts::set_floor_semantics(config.floor_semantics);
+    api::timing::set_slow_request_threshold(Duration::from_millis(config.slow_request_ms));
+    api::cors::set_allowed_origins(config.cors_origins.clone());
+    api::warmup::set_warmup_mode(config.warmup_mode);
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app").join(CONFIG_FILE);
+
+        let config = AppConfig {
+            runs_path: Some(PathBuf::from("/games/sts/runs")),
+            port: 4000,
+            lan: true,
+            mdns: false,
+            list_limits: ListLimits {
+                default_limit: 50,
+                max_limit: 100,
+            },
+            recursive_scan: true,
+            max_run_file_bytes: 1024,
+            demo_mode: true,
+            slow_request_ms: 250,
+            cors_origins: vec!["http://192.168.1.20:1420".to_string()],
+            warmup_mode: WarmupMode::Unavailable,
+            stats_options: sts::StatsOptions {
+                include_hidden: true,
+                selection: sts::RunSelection {
+                    exclude: vec![sts::RunExclusion::Dailies],
+                    ..Default::default()
+                },
+                window: sts::TimeWindow {
+                    since: Some(1_700_000_000),
+                    until: None,
+                },
+                split_by_outcome: true,
+            },
+            max_failed_file_fraction: 0.5,
+            auto_refresh_secs: Some(60),
+            locale: sts::Locale::De,
+            floor_semantics: sts::FloorSemantics::Normalized,
+            show_tray: false,
+            active_profile: "partner".to_string(),
+            profiles: BTreeMap::from([(
+                sts::DEFAULT_PROFILE.to_string(),
+                ProfileSettings {
+                    runs_path: Some(PathBuf::from("/games/sts/shared")),
+                    ..Default::default()
+                },
+            )]),
+        };
+        config.save(&path).unwrap();
+        assert_eq!(AppConfig::load(&path), config);
+    }
+
+    #[test]
+    fn test_config_defaults_for_missing_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        std::fs::write(&path, r#"{"lan": true}"#).unwrap();
+
+        let config = AppConfig::load(&path);
+        assert!(config.lan);
+        assert!(config.mdns);
+        assert_eq!(config.port, DEFAULT_PORT);
+        assert!(config.runs_path.is_none());
+        assert_eq!(config.list_limits, ListLimits::default());
+        assert!(!config.recursive_scan);
+        assert_eq!(config.cors_origins, api::cors::default_origins());
+        assert_eq!(config.warmup_mode, WarmupMode::Partial);
+        assert_eq!(config.stats_options, sts::StatsOptions::default());
+        assert_eq!(
+            config.max_failed_file_fraction,
+            validate::DEFAULT_MAX_FAILED_FILE_FRACTION
+        );
+        assert_eq!(config.auto_refresh_interval(), None);
+        assert_eq!(config.locale, sts::Locale::En);
+        assert_eq!(config.floor_semantics, sts::FloorSemantics::Raw);
+        assert!(config.show_tray);
+        assert_eq!(config.active_profile, sts::DEFAULT_PROFILE);
+        assert!(config.profiles.is_empty());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(AppConfig::load(&path), AppConfig::default());
+    }
+
+    #[test]
+    fn test_settings_from_before_profiles_become_the_default_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        std::fs::write(
+            &path,
+            r#"{"runs_path": "/games/sts/runs", "stats_options": {"include_hidden": true}}"#,
+        )
+        .unwrap();
+
+        let mut config = AppConfig::load(&path);
+        assert_eq!(config.profile_names(), ["default"]);
+        config.switch_profile("partner", true).unwrap();
+        assert_eq!(config.runs_path, None);
+        assert!(!config.stats_options.include_hidden);
+        assert_eq!(config.profile_names(), ["default", "partner"]);
+
+        config.switch_profile("default", false).unwrap();
+        assert_eq!(config.runs_path, Some(PathBuf::from("/games/sts/runs")));
+        assert!(config.stats_options.include_hidden);
+        assert_eq!(config.profiles["partner"], ProfileSettings::default());
+    }
+
+    #[test]
+    fn test_switch_profile_errors() {
+        let mut config = AppConfig::default();
+        assert!(matches!(
+            config.switch_profile("missing", false),
+            Err(ProfileError::NotFound(_))
+        ));
+        assert!(matches!(
+            config.switch_profile("../up", true),
+            Err(ProfileError::InvalidName(_))
+        ));
+        assert_eq!(config, AppConfig::default());
+    }
+
+    #[test]
+    fn test_auto_refresh_interval_has_a_minimum() {
+        let config = AppConfig {
+            auto_refresh_secs: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.auto_refresh_interval(),
+            Some(Duration::from_secs(refresh::MIN_AUTO_REFRESH_SECS))
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! `sts-stat-viewer://` links
+//!
+//! A run link such as `sts-stat-viewer://run/5a3c...` opens the desktop app
+//! on that run's detail view. The OS hands links to the app either as a
+//! launch argument (when the app isn't running yet) or through the deep-link
+//! plugin; both end up in [`resolve`], which checks the run exists locally.
+//!
+//! A link that arrives before the UI has loaded is kept as pending until the
+//! UI takes it with [`take_pending`], since an event emitted that early would
+//! be lost.
+
+use parking_lot::Mutex;
+use std::fmt;
+
+use crate::sts;
+
+/// URL scheme registered for the app
+pub const SCHEME: &str = "sts-stat-viewer";
+
+/// Canonical link to a run
+pub fn run_link(play_id: &str) -> String {
+    format!("{}://run/{}", SCHEME, play_id)
+}
+
+/// Why a link could not be opened
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeepLinkError {
+    /// Not an `sts-stat-viewer://` URL
+    WrongScheme,
+    /// An `sts-stat-viewer://` URL the app has no view for
+    UnknownTarget(String),
+    /// The play_id is missing or contains characters play_ids never have
+    InvalidPlayId(String),
+    /// No runs directory is configured or detected
+    NoRunsPath,
+    /// No local run has this play_id
+    NotFound(String),
+}
+
+impl DeepLinkError {
+    /// Error code reported to the UI
+    pub fn code(&self) -> &'static str {
+        match self {
+            DeepLinkError::WrongScheme => "INVALID_LINK",
+            DeepLinkError::UnknownTarget(_) => "INVALID_LINK",
+            DeepLinkError::InvalidPlayId(_) => "INVALID_PLAY_ID",
+            DeepLinkError::NoRunsPath => "RUNS_PATH_NOT_FOUND",
+            DeepLinkError::NotFound(_) => "NOT_FOUND",
+        }
+    }
+}
+
+impl fmt::Display for DeepLinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeepLinkError::WrongScheme => write!(f, "Not an {}:// link", SCHEME),
+            DeepLinkError::UnknownTarget(target) => write!(f, "Unknown link target: {}", target),
+            DeepLinkError::InvalidPlayId(play_id) => write!(f, "Invalid play_id: {:?}", play_id),
+            DeepLinkError::NoRunsPath => write!(f, "No runs directory configured or detected"),
+            DeepLinkError::NotFound(play_id) => write!(f, "Run not found: {}", play_id),
+        }
+    }
+}
+
+impl std::error::Error for DeepLinkError {}
+
+impl From<sts::RunsPathNotFound> for DeepLinkError {
+    fn from(_: sts::RunsPathNotFound) -> Self {
+        DeepLinkError::NoRunsPath
+    }
+}
+
+/// Whether `play_id` can appear in a link as is
+///
+/// play_ids are UUIDs; anything outside URL-safe characters is rejected
+/// rather than decoded.
+fn valid_play_id(play_id: &str) -> bool {
+    !play_id.is_empty()
+        && play_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// The play_id a run link points to
+///
+/// The scheme and target are matched case-insensitively; a trailing slash,
+/// query and fragment are ignored, as chat apps tend to add them.
+pub fn parse_run_link(url: &str) -> Result<String, DeepLinkError> {
+    let url = url.trim();
+    let rest = url
+        .split_once("://")
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(SCHEME))
+        .map(|(_, rest)| rest)
+        .ok_or(DeepLinkError::WrongScheme)?;
+    let path = rest.split(['?', '#']).next().unwrap_or_default();
+    let path = path.strip_suffix('/').unwrap_or(path);
+    let (target, play_id) = path.split_once('/').unwrap_or((path, ""));
+    if !target.eq_ignore_ascii_case("run") {
+        return Err(DeepLinkError::UnknownTarget(target.to_string()));
+    }
+    if !valid_play_id(play_id) {
+        return Err(DeepLinkError::InvalidPlayId(play_id.to_string()));
+    }
+    Ok(play_id.to_string())
+}
+
+/// The first launch argument that is a link to the app, if any
+pub fn find_link_arg(args: &[String]) -> Option<&str> {
+    args.iter().map(String::as_str).find(|arg| {
+        arg.split_once("://")
+            .is_some_and(|(scheme, _)| scheme.eq_ignore_ascii_case(SCHEME))
+    })
+}
+
+/// Load the run a link points to, including hidden runs
+pub fn resolve(url: &str) -> Result<sts::RunDetail, DeepLinkError> {
+    let play_id = parse_run_link(url)?;
+    sts::find_run(&play_id)?
+        .map(sts::RunDetail::from)
+        .ok_or(DeepLinkError::NotFound(play_id))
+}
+
+static PENDING_LINK: Mutex<Option<String>> = Mutex::new(None);
+
+/// Keep a link until the UI is ready for it, replacing an older one
+pub fn set_pending(url: impl Into<String>) {
+    *PENDING_LINK.lock() = Some(url.into());
+}
+
+/// Take the link that arrived before the UI was ready, if any
+pub fn take_pending() -> Option<String> {
+    PENDING_LINK.lock().take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAY_ID: &str = "5a3c9e1b-7f2d-4c8a-9b6e-0d1f2a3b4c5d";
+
+    #[test]
+    fn test_run_links_round_trip() {
+        let link = run_link(PLAY_ID);
+        assert_eq!(link, format!("sts-stat-viewer://run/{}", PLAY_ID));
+        assert_eq!(parse_run_link(&link).unwrap(), PLAY_ID);
+        for pasted in [
+            format!("{}/", link),
+            format!("{}?from=chat", link),
+            format!("  STS-Stat-Viewer://RUN/{}#top\n", PLAY_ID),
+        ] {
+            assert_eq!(parse_run_link(&pasted).unwrap(), PLAY_ID, "{pasted}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_links() {
+        let cases = [
+            ("https://run/abc", DeepLinkError::WrongScheme),
+            ("sts-stat-viewer:run/abc", DeepLinkError::WrongScheme),
+            (
+                "sts-stat-viewer://stats/abc",
+                DeepLinkError::UnknownTarget("stats".to_string()),
+            ),
+            (
+                "sts-stat-viewer://run/",
+                DeepLinkError::InvalidPlayId(String::new()),
+            ),
+            (
+                "sts-stat-viewer://run/a/b",
+                DeepLinkError::InvalidPlayId("a/b".to_string()),
+            ),
+            (
+                "sts-stat-viewer://run/a%20b",
+                DeepLinkError::InvalidPlayId("a%20b".to_string()),
+            ),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(parse_run_link(url).unwrap_err(), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn test_find_link_arg() {
+        let args: Vec<String> = ["--flag", "sts-stat-viewer://run/abc"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(find_link_arg(&args), Some("sts-stat-viewer://run/abc"));
+        assert_eq!(find_link_arg(&args[..1]), None);
+    }
+
+    #[test]
+    fn test_resolve_checks_the_run_exists() {
+        let runs_dir = sts::TestRunsDir::empty();
+        runs_dir.add(&sts::RunFileBuilder::new(PLAY_ID).victory(true));
+
+        let run = resolve(&run_link(PLAY_ID)).unwrap();
+        assert_eq!(run.run.play_id, PLAY_ID);
+        let err = resolve(&run_link("missing")).unwrap_err();
+        assert_eq!(err, DeepLinkError::NotFound("missing".to_string()));
+        assert_eq!(err.code(), "NOT_FOUND");
+    }
+}
@@ -7,12 +7,27 @@
 //! - Frontend with Svelte 5, Observable Plot, and Effect-TS
 
 pub mod api;
+pub mod cli;
+pub mod config;
+pub mod deep_link;
+pub mod logging;
+pub mod recent_errors;
+pub mod refresh;
+pub mod settings_bundle;
+pub mod status;
 pub mod sts;
+pub mod tray;
+pub mod validate;
 
 use serde::Serialize;
 use std::path::PathBuf;
 use std::thread;
-use tauri::Manager;
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_opener::OpenerExt;
 
 /// Tauri command to greet a user (direct IPC)
 #[tauri::command]
@@ -23,7 +38,10 @@ fn greet(name: &str) -> String {
 /// Tauri command to get the API server URL
 #[tauri::command]
 fn get_api_url() -> String {
-    "http://127.0.0.1:3030".to_string()
+    match status::server_state() {
+        status::ServerState::Running { urls, .. } if !urls.is_empty() => urls[0].clone(),
+        _ => api::DEFAULT_SERVER_URL.to_string(),
+    }
 }
 
 /// Tauri command to get the OpenAPI spec as JSON
@@ -32,28 +50,445 @@ fn get_openapi_spec() -> String {
     api::get_openapi_json()
 }
 
+/// Tauri command to write the OpenAPI spec to a file, returning its size in
+/// bytes
+///
+/// `format` is `json` or `yaml`. An existing file is only replaced when
+/// `overwrite` is true.
+#[tauri::command]
+fn export_openapi_spec(
+    path: String,
+    format: String,
+    overwrite: Option<bool>,
+) -> Result<usize, CommandError> {
+    let format =
+        api::SpecFormat::parse(&format).map_err(|e| CommandError::new("INVALID_FORMAT", e))?;
+    api::write_openapi_spec(&PathBuf::from(&path), format, overwrite.unwrap_or(false)).map_err(
+        |e| {
+            if e.kind() == std::io::ErrorKind::AlreadyExists {
+                CommandError::new("FILE_EXISTS", format!("{} already exists", path))
+            } else {
+                CommandError::new("WRITE_ERROR", format!("Failed to write {}: {}", path, e))
+            }
+        },
+    )
+}
+
+/// Tauri command to generate TypeScript types from the OpenAPI schemas
+///
+/// Returns the names of the generated types.
+#[tauri::command]
+fn generate_ts_types(out_path: String) -> Result<Vec<String>, String> {
+    api::typescript::write_typescript_types(&PathBuf::from(&out_path))
+        .map_err(|e| format!("Failed to write {}: {}", out_path, e))
+}
+
+/// Error returned by data commands, with the same codes as the HTTP API
+///
+/// `RUNS_PATH_NOT_FOUND` means no runs directory is set up; the UI shows a
+/// setup prompt instead of an empty dashboard.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandError {
+    /// Machine-readable error code
+    pub code: String,
+    /// Human-readable message
+    pub message: String,
+}
+
+impl CommandError {
+    fn new(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl From<sts::RunsPathNotFound> for CommandError {
+    fn from(e: sts::RunsPathNotFound) -> Self {
+        Self::new("RUNS_PATH_NOT_FOUND", e.to_string())
+    }
+}
+
+impl From<settings_bundle::BundleError> for CommandError {
+    fn from(e: settings_bundle::BundleError) -> Self {
+        let code = match e {
+            settings_bundle::BundleError::NoDataDir | settings_bundle::BundleError::Io(_) => {
+                "STORAGE_ERROR"
+            }
+            settings_bundle::BundleError::Invalid(_) => "INVALID_BUNDLE",
+            settings_bundle::BundleError::UnsupportedVersion { .. } => "UNSUPPORTED_VERSION",
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+impl From<deep_link::DeepLinkError> for CommandError {
+    fn from(e: deep_link::DeepLinkError) -> Self {
+        Self::new(e.code(), e.to_string())
+    }
+}
+
 /// Tauri command to get all runs directly (without HTTP)
+///
+/// Local callers get every run; the API list limits don't apply here.
 #[tauri::command]
-fn get_runs() -> Vec<sts::RunMetrics> {
-    sts::load_all_runs()
+fn get_runs(
+    include_hidden: Option<bool>,
+    recursive: Option<bool>,
+) -> Result<Vec<sts::RunMetrics>, CommandError> {
+    let mut options = sts::scan_options();
+    if let Some(recursive) = recursive {
+        options.recursive = recursive;
+    }
+    let mut runs = sts::try_load_all_runs_with(options)?;
+    sts::RunFilter {
+        include_hidden: include_hidden.unwrap_or(false),
+        ..Default::default()
+    }
+    .retain(&mut runs);
+    Ok(runs
+        .into_iter()
+        .map(sts::RunMetrics::without_source)
+        .collect())
+}
+
+/// Stats options passed to a command, or the defaults saved in the config
+fn stats_options(options: Option<sts::StatsOptions>) -> Result<sts::StatsOptions, CommandError> {
+    let options = options.unwrap_or_else(|| config::current().stats_options);
+    options
+        .validate()
+        .map_err(|e| CommandError::new("INVALID_FILTER", e))?;
+    Ok(options)
 }
 
 /// Tauri command to get character stats directly
+///
+/// `options` picks the runs counted, like the `/api/stats` query flags, so
+/// both give the same numbers for the same options.
+#[tauri::command]
+fn get_stats(options: Option<sts::StatsOptions>) -> Result<Vec<sts::CharacterStats>, CommandError> {
+    let options = stats_options(options)?;
+    let runs = sts::try_load_all_runs()?;
+    let mut stats = sts::calculate_character_stats_with(&runs, &options);
+    let locale = config::current().locale;
+    for entry in &mut stats {
+        entry.localize(locale);
+    }
+    Ok(stats)
+}
+
+/// Tauri command to get a single run, including hidden ones
+#[tauri::command]
+fn get_run(play_id: String) -> Result<sts::RunDetail, CommandError> {
+    sts::find_run(&play_id)?
+        .map(sts::RunDetail::from)
+        .ok_or_else(|| CommandError::new("NOT_FOUND", format!("Run not found: {}", play_id)))
+}
+
+/// Tauri command to get the `sts-stat-viewer://` link that opens a run
+#[tauri::command]
+fn get_run_link(play_id: String) -> Result<String, CommandError> {
+    get_run(play_id.clone())?;
+    let link = deep_link::run_link(&play_id);
+    deep_link::parse_run_link(&link)?;
+    Ok(link)
+}
+
+/// Tauri command to open the run link the app was launched with
+///
+/// The UI calls this once it has loaded, since a link that arrives earlier
+/// can't be delivered as an event. Returns `None` without a pending link.
+#[tauri::command]
+fn take_pending_run_link() -> Result<Option<sts::RunDetail>, CommandError> {
+    deep_link::take_pending()
+        .map(|url| deep_link::resolve(&url))
+        .transpose()
+        .map_err(CommandError::from)
+}
+
+/// Tauri command to hide a run from statistics
+#[tauri::command]
+fn hide_run(play_id: String) -> Result<bool, CommandError> {
+    set_run_hidden(play_id, true)
+}
+
+/// Tauri command to make a hidden run count towards statistics again
+#[tauri::command]
+fn unhide_run(play_id: String) -> Result<bool, CommandError> {
+    set_run_hidden(play_id, false)
+}
+
+fn set_run_hidden(play_id: String, hidden: bool) -> Result<bool, CommandError> {
+    get_run(play_id.clone())?;
+    sts::set_run_hidden(&play_id, hidden)
+        .map(|_| hidden)
+        .map_err(|e| {
+            CommandError::new(
+                "STORAGE_ERROR",
+                format!("Failed to save hidden runs: {}", e),
+            )
+        })
+}
+
+/// Tauri command to re-parse the file behind a run after it was edited by hand
+#[tauri::command]
+fn reparse_run(play_id: String) -> Result<sts::RunMetrics, CommandError> {
+    sts::reparse_run(&play_id).map_err(|e| {
+        let code = match e {
+            sts::ReparseError::NoRunsPath => "RUNS_PATH_NOT_FOUND",
+            sts::ReparseError::NotFound => "NOT_FOUND",
+            sts::ReparseError::FileRemoved(_) => "FILE_REMOVED",
+            sts::ReparseError::Invalid(_) => "PARSE_ERROR",
+        };
+        CommandError::new(code, e.to_string())
+    })
+}
+
+/// Tauri command to get a summary of the loaded dataset
 #[tauri::command]
-fn get_stats() -> Vec<sts::CharacterStats> {
-    let runs = sts::load_all_runs();
-    sts::calculate_character_stats(&runs)
+fn get_dataset_summary() -> Result<sts::DatasetSummary, CommandError> {
+    Ok(sts::dataset_summary(&sts::try_load_all_runs()?))
+}
+
+/// Tauri command to get ascension ladder progress per character
+#[tauri::command]
+fn get_progress() -> Result<sts::ProgressReport, CommandError> {
+    Ok(sts::ProgressReport::from_runs(&sts::try_load_all_runs()?))
+}
+
+/// Tauri command to get personal records, optionally for one character
+#[tauri::command]
+fn get_personal_bests(character: Option<String>) -> Result<sts::PersonalBests, CommandError> {
+    let character = character
+        .map(|name| name.parse::<sts::Character>())
+        .transpose()
+        .map_err(|e| CommandError::new("NOT_FOUND", e.to_string()))?;
+    let mut runs = sts::try_load_all_runs()?;
+    sts::RunFilter::from(sts::RunSelection {
+        characters: character.into_iter().collect(),
+        ..Default::default()
+    })
+    .retain(&mut runs);
+    Ok(sts::personal_bests(&runs))
 }
 
 /// Tauri command to get export data directly
+///
+/// `filter` limits the exported runs, and the stats computed over them.
+/// `options` picks the runs counted as for [`get_stats`].
+#[tauri::command]
+fn get_export_data(
+    redact_path: Option<bool>,
+    filter: Option<sts::ExportFilter>,
+    options: Option<sts::StatsOptions>,
+) -> Result<sts::ExportData, CommandError> {
+    let filter = filter.unwrap_or_default();
+    filter
+        .validate()
+        .map_err(|e| CommandError::new("INVALID_FILTER", e))?;
+    let options = sts::ExportOptions {
+        redact_path: redact_path.unwrap_or(false),
+        filter,
+        stats: stats_options(options)?,
+    };
+    Ok(sts::get_export_data_with(&options)?)
+}
+
+/// Tauri command to copy a summary of the stats to the clipboard
+///
+/// `format` is `text`, `markdown` or `json`. Returns the length of the copied
+/// summary in characters.
+#[tauri::command]
+fn copy_stats_to_clipboard(app: tauri::AppHandle, format: String) -> Result<usize, CommandError> {
+    let format = sts::ReportFormat::from_name(&format).ok_or_else(|| {
+        CommandError::new(
+            "INVALID_FORMAT",
+            format!(
+                "Unknown report format: {} (expected text, markdown or json)",
+                format
+            ),
+        )
+    })?;
+    let report = sts::StatsReport::from_runs(&sts::try_load_all_runs()?).render(format);
+    app.clipboard()
+        .write_text(report.as_str())
+        .map_err(|e| CommandError::new("CLIPBOARD_ERROR", e.to_string()))?;
+    Ok(report.chars().count())
+}
+
+/// Tauri command to write a standalone HTML stats report, returning its size
+/// in bytes
+#[tauri::command]
+fn generate_html_report(out_path: String) -> Result<usize, CommandError> {
+    let runs = sts::try_load_all_runs()?;
+    sts::write_html_report(&runs, &PathBuf::from(&out_path)).map_err(|e| {
+        CommandError::new(
+            "WRITE_ERROR",
+            format!("Failed to write {}: {}", out_path, e),
+        )
+    })
+}
+
+/// Tauri command to read an export file, upgrading older schema versions
+#[tauri::command]
+fn import_export_file(path: String) -> Result<sts::ExportData, String> {
+    let content =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    sts::import_export_data(&content).map_err(|e| e.to_string())
+}
+
+/// Tauri command to write the config and hidden runs of every profile into a
+/// settings bundle
 #[tauri::command]
-fn get_export_data() -> sts::ExportData {
-    sts::get_export_data()
+fn export_settings(path: String) -> Result<settings_bundle::SettingsBundle, CommandError> {
+    settings_bundle::export_settings(std::path::Path::new(&path)).map_err(CommandError::from)
+}
+
+/// Tauri command to restore a settings bundle
+///
+/// With `dry_run`, nothing is written and the report says what would change.
+/// An imported auto-refresh interval takes effect right away; the report's
+/// `restart_required` lists the settings that need a restart.
+///
+/// Runs off the main thread: restarting the poller waits for its scan, which
+/// may be updating the tray.
+#[tauri::command]
+async fn import_settings(
+    app: tauri::AppHandle,
+    path: String,
+    dry_run: Option<bool>,
+) -> Result<settings_bundle::ImportReport, CommandError> {
+    let dry_run = dry_run.unwrap_or(false);
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let report = settings_bundle::import_settings(std::path::Path::new(&path), dry_run)?;
+        if !dry_run
+            && report
+                .changed_settings
+                .iter()
+                .any(|name| name == "auto_refresh_secs")
+        {
+            refresh::set_auto_refresh(config::current().auto_refresh_interval());
+        }
+        Ok::<_, CommandError>(report)
+    })
+    .await
+    .map_err(|e| CommandError::new("INTERNAL_ERROR", e.to_string()))??;
+    if !dry_run {
+        update_tray(&app);
+    }
+    Ok(report)
+}
+
+/// Tauri command to check an export file without importing it
+#[tauri::command]
+fn verify_export(path: String) -> Result<sts::ExportVerification, String> {
+    let content =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    sts::verify_export_data(&content).map_err(|e| e.to_string())
+}
+
+/// Tauri command to get the auto-refresh interval in seconds (None when off)
+#[tauri::command]
+fn get_auto_refresh() -> Option<u64> {
+    config::current().auto_refresh_secs
+}
+
+/// Tauri command to set the auto-refresh interval in seconds, or turn it off
+/// with None
+///
+/// Runs off the main thread: restarting the poller waits for its scan, which
+/// may be updating the tray.
+#[tauri::command]
+async fn set_auto_refresh(interval_secs: Option<u64>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || refresh::set_auto_refresh_secs(interval_secs))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Tauri command to get the language of display names, e.g. `de`
+#[tauri::command]
+fn get_locale() -> String {
+    config::current().locale.code().to_string()
+}
+
+/// Tauri command to set the language of display names, saving it to the
+/// config
+///
+/// Accepts a language tag such as `de` or `fr-CA`; languages without
+/// translations are rejected. Returns the locale now in effect.
+#[tauri::command]
+fn set_locale(locale: String) -> Result<String, String> {
+    let resolved = sts::Locale::from_tag(&locale).ok_or_else(|| {
+        format!(
+            "Unsupported locale: {} (expected en, de, fr, es or zh)",
+            locale
+        )
+    })?;
+    config::update(|c| c.locale = resolved).map_err(|e| format!("Failed to save config: {}", e))?;
+    Ok(resolved.code().to_string())
+}
+
+/// Tauri command to check whether the tray icon is shown
+#[tauri::command]
+fn get_show_tray() -> bool {
+    config::current().show_tray
+}
+
+/// Tauri command to show or hide the tray icon, saving the setting
+#[tauri::command]
+fn set_show_tray(app: tauri::AppHandle, show: bool) -> Result<(), String> {
+    config::update(|c| c.show_tray = show).map_err(|e| format!("Failed to save config: {}", e))?;
+    match app.tray_by_id(TRAY_ID) {
+        Some(tray) => tray.set_visible(show).map_err(|e| e.to_string()),
+        None if show => build_tray(&app).map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Tauri command to get a status report of all subsystems
+#[tauri::command]
+fn get_app_status() -> status::AppStatus {
+    status::app_status()
+}
+
+/// Tauri command to get the recent warnings and errors, newest first
+#[tauri::command]
+fn get_recent_errors() -> Vec<recent_errors::RecentError> {
+    recent_errors::recent_errors()
+}
+
+/// Tauri command to clear the recent warnings and errors
+#[tauri::command]
+fn clear_recent_errors() {
+    recent_errors::clear_recent_errors();
+}
+
+/// Tauri command to get the current log file (or the log directory if no file exists yet)
+#[tauri::command]
+fn get_log_path() -> Option<String> {
+    let dir = logging::log_dir()?;
+    let path = logging::latest_log_file(&dir).unwrap_or(dir);
+    Some(path.to_string_lossy().to_string())
+}
+
+/// Tauri command to open the log directory in the system file manager
+#[tauri::command]
+fn open_logs_folder(app: tauri::AppHandle) -> Result<(), String> {
+    let dir = logging::log_dir().ok_or("No app data directory available")?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open {}: {}", dir.display(), e))
 }
 
 /// Response containing runs path information
 #[derive(Serialize)]
 pub struct RunsPathInfo {
+    /// Resolution state; `custom_missing` carries the configured path that no
+    /// longer exists and the auto-detected path used instead
+    pub resolution: sts::RunsPathResolution,
     /// Currently active path (custom if set and valid, otherwise auto-detected)
     pub current_path: Option<String>,
     /// Whether a custom path is currently set
@@ -67,33 +502,30 @@ pub struct RunsPathInfo {
 /// Tauri command to get runs path info
 #[tauri::command]
 fn get_runs_path_info() -> RunsPathInfo {
-    let (current, is_custom, auto_detected) = sts::get_runs_path_info();
-    let current_path = current.as_ref().map(|p| p.to_string_lossy().to_string());
-    let path_exists = current.as_ref().map(|p| p.exists()).unwrap_or(false);
+    let resolution = sts::get_runs_path_info();
+    let current = resolution.active_path();
 
     RunsPathInfo {
-        current_path,
-        is_custom,
-        auto_detected_path: auto_detected.map(|p| p.to_string_lossy().to_string()),
-        path_exists,
+        current_path: current.as_ref().map(|p| p.to_string_lossy().to_string()),
+        is_custom: resolution.is_custom(),
+        auto_detected_path: sts::auto_detected_runs_path().map(|p| p.to_string_lossy().to_string()),
+        path_exists: current.as_ref().is_some_and(|p| p.exists()),
+        resolution,
     }
 }
 
 /// Tauri command to set a custom runs path
+///
+/// The input is normalized first (quotes, trailing separators, `~`, verbatim
+/// prefixes), so errors name the path that was actually checked.
 #[tauri::command]
 fn set_runs_path(path: String) -> Result<RunsPathInfo, String> {
-    let path_buf = PathBuf::from(&path);
-
-    // Validate the path exists
-    if !path_buf.exists() {
-        return Err(format!("Path does not exist: {}", path));
-    }
+    let path_buf = sts::normalize_runs_path(&path).map_err(|e| e.to_string())?;
 
-    if !path_buf.is_dir() {
-        return Err(format!("Path is not a directory: {}", path));
+    sts::set_custom_runs_path(Some(path_buf.clone()));
+    if let Err(e) = config::update(|c| c.runs_path = Some(path_buf)) {
+        tracing::error!("Failed to save config: {}", e);
     }
-
-    sts::set_custom_runs_path(Some(path_buf));
     Ok(get_runs_path_info())
 }
 
@@ -101,16 +533,264 @@ fn set_runs_path(path: String) -> Result<RunsPathInfo, String> {
 #[tauri::command]
 fn clear_runs_path() -> RunsPathInfo {
     sts::set_custom_runs_path(None);
+    if let Err(e) = config::update(|c| c.runs_path = None) {
+        tracing::error!("Failed to save config: {}", e);
+    }
     get_runs_path_info()
 }
 
+/// Tauri command to list the profiles and which one is active
+#[tauri::command]
+fn get_profiles() -> api::types::Profiles {
+    let config = config::current();
+    api::types::Profiles {
+        names: config.profile_names(),
+        active: config.active_profile,
+    }
+}
+
+/// Tauri command to switch the runs directory, stats options and hidden runs
+/// to another profile's
+///
+/// With `create`, a missing profile is added with default settings.
+#[tauri::command]
+fn set_active_profile(
+    app: tauri::AppHandle,
+    name: String,
+    create: Option<bool>,
+) -> Result<api::types::Profiles, CommandError> {
+    config::switch_profile(&name, create.unwrap_or(false)).map_err(|e| {
+        let code = match e {
+            config::ProfileError::InvalidName(_) => "INVALID_PROFILE",
+            config::ProfileError::NotFound(_) => "NOT_FOUND",
+            config::ProfileError::Storage(_) => "STORAGE_ERROR",
+        };
+        CommandError::new(code, e.to_string())
+    })?;
+    update_tray(&app);
+    Ok(get_profiles())
+}
+
+/// Event emitted to the frontend whenever the API server state changes
+const SERVER_STATE_EVENT: &str = "api-server-state";
+
+/// Event emitted after each batch of run files loaded at startup, with a
+/// [`sts::LoadProgress`]
+const LOAD_PROGRESS_EVENT: &str = "load-progress";
+
+/// Event emitted when the startup load finishes, with a [`sts::DatasetSummary`]
+const LOAD_COMPLETE_EVENT: &str = "load-complete";
+
+/// Event emitted when runs in the runs directory change, with a
+/// [`refresh::RunsUpdated`]
+const RUNS_UPDATED_EVENT: &str = "runs-updated";
+
+/// Event emitted when a run link is opened, with the linked [`sts::RunDetail`]
+const OPEN_RUN_EVENT: &str = "open-run";
+
+/// Event emitted when a run link can't be opened, with a [`CommandError`]
+const DEEP_LINK_ERROR_EVENT: &str = "deep-link-error";
+
+/// Open a run link in the UI, bringing the window to the front
+fn open_run_link(handle: &tauri::AppHandle, url: &str) {
+    match deep_link::resolve(url) {
+        Ok(run) => {
+            let _ = handle.emit(OPEN_RUN_EVENT, run);
+        }
+        Err(e) => {
+            tracing::warn!("Can't open link {}: {}", url, e);
+            let _ = handle.emit(DEEP_LINK_ERROR_EVENT, CommandError::from(e));
+        }
+    }
+    if let Some(window) = handle.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+/// Id of the tray icon
+const TRAY_ID: &str = "main";
+
+/// Menu items of the tray that change after it is built
+struct TrayItems {
+    win_rate: MenuItem<tauri::Wry>,
+    last_run: MenuItem<tauri::Wry>,
+    pause: CheckMenuItem<tauri::Wry>,
+}
+
+/// Show and focus the main window
+fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+/// Build the tray icon with quick stats and controls
+///
+/// The stats start as placeholders and are filled in from a background
+/// thread, so building the tray never scans the runs directory on the main
+/// thread.
+fn build_tray(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let no_accelerator = None::<&str>;
+    let win_rate = MenuItem::with_id(
+        app,
+        "win-rate",
+        tray::LOADING_WIN_RATE,
+        false,
+        no_accelerator,
+    )?;
+    let last_run = MenuItem::with_id(
+        app,
+        "last-run",
+        tray::LOADING_LAST_RUN,
+        false,
+        no_accelerator,
+    )?;
+    let open = MenuItem::with_id(
+        app,
+        tray::OPEN_DASHBOARD,
+        "Open dashboard",
+        true,
+        no_accelerator,
+    )?;
+    let reload = MenuItem::with_id(app, tray::RELOAD_RUNS, "Reload runs", true, no_accelerator)?;
+    let pause = CheckMenuItem::with_id(
+        app,
+        tray::PAUSE_WATCHING,
+        "Pause watching",
+        tray::can_pause_watching(),
+        false,
+        no_accelerator,
+    )?;
+    let quit = MenuItem::with_id(app, tray::QUIT, "Quit", true, no_accelerator)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &win_rate,
+            &last_run,
+            &PredefinedMenuItem::separator(app)?,
+            &open,
+            &reload,
+            &pause,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&menu)
+        .tooltip(tray::LOADING_TOOLTIP)
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| on_tray_menu(app, event.id().as_ref()))
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    app.manage(TrayItems {
+        win_rate,
+        last_run,
+        pause,
+    });
+    // Waits for the warm-up's scan if it is still running, and fills the
+    // stats in if it finished before the tray existed
+    let handle = app.clone();
+    thread::spawn(move || update_tray(&handle));
+    Ok(())
+}
+
+/// Handle a click on a tray menu item
+fn on_tray_menu(app: &tauri::AppHandle, id: &str) {
+    match id {
+        tray::OPEN_DASHBOARD => show_main_window(app),
+        tray::RELOAD_RUNS => {
+            // Changes reach the UI and the tray through the runs-updated
+            // listener; refresh the tray anyway for the first scan
+            let app = app.clone();
+            thread::spawn(move || {
+                if let Err(e) = refresh::refresh_now() {
+                    tracing::warn!("Reload skipped: {}", e);
+                }
+                update_tray(&app);
+            });
+        }
+        tray::PAUSE_WATCHING => {
+            // Off the main thread: stopping the poller waits for its scan,
+            // which may be updating this menu
+            if let Some(items) = app.try_state::<TrayItems>() {
+                let paused = items.pause.is_checked().unwrap_or(false);
+                thread::spawn(move || tray::set_watching_paused(paused));
+            }
+        }
+        tray::QUIT => app.exit(0),
+        _ => {}
+    }
+}
+
+/// Refresh the quick stats of the tray, if it is shown
+///
+/// The summary is computed on the calling thread and the menu updated on the
+/// main thread without waiting for it, so threads the main thread may be
+/// joining (like the auto-refresh poller) can call this.
+fn update_tray(app: &tauri::AppHandle) {
+    if app.try_state::<TrayItems>().is_none() {
+        return;
+    }
+    let summary = tray::TraySummary::current();
+    let can_pause = tray::can_pause_watching();
+    let handle = app.clone();
+    let posted = app.run_on_main_thread(move || {
+        let Some(items) = handle.try_state::<TrayItems>() else {
+            return;
+        };
+        let _ = items.win_rate.set_text(summary.win_rate_label());
+        let _ = items.last_run.set_text(summary.last_run_label());
+        let _ = items.pause.set_enabled(can_pause);
+        if let Some(tray) = handle.tray_by_id(TRAY_ID) {
+            let _ = tray.set_tooltip(Some(summary.tooltip()));
+        }
+    });
+    if let Err(e) = posted {
+        tracing::warn!("Tray update skipped: {}", e);
+    }
+}
+
+/// Load the runs directory in a background thread, reporting progress to the UI
+fn start_warm_up(handle: tauri::AppHandle) {
+    thread::spawn(move || {
+        let result = sts::warm_up(sts::WARMUP_BATCH_FILES, |progress| {
+            let _ = handle.emit(LOAD_PROGRESS_EVENT, progress);
+        });
+        let summary = result.unwrap_or_else(|e| {
+            tracing::warn!("Startup load skipped: {}", e);
+            sts::dataset_summary(&[])
+        });
+        let _ = handle.emit(LOAD_COMPLETE_EVENT, summary);
+        update_tray(&handle);
+    });
+}
+
 /// Start the API server in a background thread
-fn start_api_server() {
-    thread::spawn(|| {
+///
+/// The server is supervised: panics are logged, reported through the app
+/// status and the server is restarted with backoff.
+fn start_api_server(options: api::ServerOptions) {
+    thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async {
-            if let Err(e) = api::start_server(3030).await {
-                eprintln!("API server error: {}", e);
+            if let Err(e) = api::supervisor::start_supervised(options).await {
+                tracing::error!("API server error: {}", e);
             }
         });
     });
@@ -140,25 +820,119 @@ pub fn run() {
         }
     }
 
-    // Start the API server before Tauri
-    start_api_server();
+    logging::init();
+
+    // A run link the app was started with waits until the UI asks for it
+    let launch_args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(url) = deep_link::find_link_arg(&launch_args) {
+        deep_link::set_pending(url);
+    }
+
+    // Load persisted settings, then start the API server before Tauri
+    let config = config::apply();
+    let auto_refresh = config.auto_refresh_interval();
+    let show_tray = config.show_tray;
+    start_api_server(api::ServerOptions {
+        port: config.port,
+        lan: config.lan,
+        mdns: config.mdns,
+    });
 
     tauri::Builder::default()
+        // Must come first: a second launch (e.g. from clicking a run link)
+        // hands its link to this instance instead of starting another app
+        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.set_focus();
+            }
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_process::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .invoke_handler(tauri::generate_handler![
             greet,
             get_api_url,
             get_openapi_spec,
+            export_openapi_spec,
+            generate_ts_types,
             get_runs,
             get_stats,
             get_export_data,
+            copy_stats_to_clipboard,
+            generate_html_report,
+            import_export_file,
+            verify_export,
+            export_settings,
+            import_settings,
+            get_run,
+            get_run_link,
+            take_pending_run_link,
+            hide_run,
+            unhide_run,
+            reparse_run,
+            get_dataset_summary,
+            get_personal_bests,
+            get_progress,
+            get_app_status,
+            get_recent_errors,
+            clear_recent_errors,
+            get_log_path,
+            open_logs_folder,
             get_runs_path_info,
             set_runs_path,
-            clear_runs_path
+            clear_runs_path,
+            get_auto_refresh,
+            set_auto_refresh,
+            get_locale,
+            set_locale,
+            get_show_tray,
+            set_show_tray,
+            get_profiles,
+            set_active_profile
         ])
-        .setup(|app| {
+        .setup(move |app| {
+            // Forward server state changes (including panics and restarts) to the UI
+            let handle = app.handle().clone();
+            status::set_server_state_listener(move |state| {
+                let _ = handle.emit(SERVER_STATE_EVENT, state);
+            });
+
+            // Load runs before the dashboard asks for them, showing progress
+            start_warm_up(app.handle().clone());
+
+            // Tell the UI about changed runs, and rescan the runs directory
+            // if auto-refresh is on
+            let handle = app.handle().clone();
+            refresh::set_runs_updated_listener(move |update| {
+                let _ = handle.emit(RUNS_UPDATED_EVENT, update);
+                update_tray(&handle);
+            });
+            refresh::set_auto_refresh(auto_refresh);
+
+            // Quick stats in the tray, unless turned off in the settings
+            if show_tray {
+                build_tray(app.handle())?;
+            }
+
+            // Open run links; Linux and Windows dev builds have to register
+            // the scheme at runtime, installers register it otherwise
+            #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+            app.deep_link().register_all()?;
+            #[cfg(target_os = "macos")]
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                if let Some(url) = urls.first() {
+                    deep_link::set_pending(url.as_str());
+                }
+            }
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    open_run_link(&handle, url.as_str());
+                }
+            });
+
             // Enable hardware acceleration and performance settings
             #[cfg(target_os = "linux")]
             {
@@ -169,8 +943,13 @@ pub fn run() {
             }
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_, event| {
+            if let tauri::RunEvent::Exit = event {
+                api::discovery::withdraw();
+            }
+        });
 }
 
 #[cfg(test)]
@@ -197,4 +976,136 @@ mod tests {
         assert!(spec.contains("openapi"));
         assert!(spec.contains("3.1"));
     }
+
+    #[test]
+    fn test_export_openapi_spec() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spec.yaml").to_string_lossy().into_owned();
+        let err = export_openapi_spec(path.clone(), "xml".to_string(), None).unwrap_err();
+        assert_eq!(err.code, "INVALID_FORMAT");
+
+        let bytes = export_openapi_spec(path.clone(), "yaml".to_string(), None).unwrap();
+        assert!(bytes > 0);
+        let err = export_openapi_spec(path.clone(), "yaml".to_string(), None).unwrap_err();
+        assert_eq!(err.code, "FILE_EXISTS");
+        assert!(export_openapi_spec(path, "yaml".to_string(), Some(true)).is_ok());
+    }
+
+    #[test]
+    fn test_get_run_link() {
+        let runs_dir = sts::TestRunsDir::empty();
+        runs_dir.add(&sts::RunFileBuilder::new("abc-123"));
+        let link = get_run_link("abc-123".to_string()).unwrap();
+        assert_eq!(link, "sts-stat-viewer://run/abc-123");
+        assert_eq!(
+            get_run_link("missing".to_string()).unwrap_err().code,
+            "NOT_FOUND"
+        );
+
+        deep_link::set_pending(link);
+        let run = take_pending_run_link().unwrap().unwrap();
+        assert_eq!(run.run.play_id, "abc-123");
+        assert!(take_pending_run_link().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stats_commands_match_api_for_each_flag() {
+        use api::sts_handlers::StatsListQuery;
+        use axum::extract::Query;
+        use axum::Json;
+
+        let _runs_dir = sts::TestRunsDir::with_samples();
+        let mut cases = vec![
+            (sts::StatsOptions::default(), StatsListQuery::default()),
+            (
+                sts::StatsOptions {
+                    include_hidden: true,
+                    ..Default::default()
+                },
+                StatsListQuery {
+                    include_hidden: Some(true),
+                    ..Default::default()
+                },
+            ),
+            (
+                sts::StatsOptions {
+                    selection: sts::RunSelection {
+                        exclude_characters: vec![sts::Character::Watcher],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                StatsListQuery {
+                    exclude_character: Some("watcher".to_string()),
+                    ..Default::default()
+                },
+            ),
+            (
+                sts::StatsOptions {
+                    split_by_outcome: true,
+                    ..Default::default()
+                },
+                StatsListQuery {
+                    split_by_outcome: Some(true),
+                    ..Default::default()
+                },
+            ),
+        ];
+        for exclusion in sts::RunExclusion::ALL {
+            cases.push((
+                sts::StatsOptions {
+                    selection: sts::RunSelection {
+                        exclude: vec![exclusion],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                StatsListQuery {
+                    exclude: Some(exclusion.name().to_string()),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        for (options, query) in cases {
+            let ipc = serde_json::to_value(get_stats(Some(options.clone())).unwrap()).unwrap();
+            let (_, Json(http)) = api::sts_handlers::get_stats(
+                api::sts_handlers::AcceptLanguage(sts::Locale::En),
+                Query(query),
+            )
+            .await
+            .unwrap();
+            assert_eq!(ipc, serde_json::to_value(http).unwrap(), "{:?}", options);
+
+            let export = get_export_data(None, None, Some(options.clone())).unwrap();
+            assert_eq!(
+                ipc,
+                serde_json::to_value(export.character_stats).unwrap(),
+                "{:?}",
+                options
+            );
+        }
+    }
+
+    #[test]
+    fn test_stats_commands_default_to_config() {
+        let _runs_dir = sts::TestRunsDir::with_samples();
+        let defaults = config::current().stats_options;
+        assert_eq!(stats_options(None).unwrap(), defaults);
+        assert_eq!(
+            serde_json::to_value(get_stats(None).unwrap()).unwrap(),
+            serde_json::to_value(get_stats(Some(defaults)).unwrap()).unwrap()
+        );
+
+        let conflicting = sts::StatsOptions {
+            selection: sts::RunSelection {
+                characters: vec![sts::Character::Defect],
+                exclude_characters: vec![sts::Character::Defect],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let error = get_stats(Some(conflicting)).unwrap_err();
+        assert_eq!(error.code, "INVALID_FILTER");
+    }
 }
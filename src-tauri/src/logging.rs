@@ -0,0 +1,140 @@
+//! Logging setup
+//!
+//! Log output goes to the console and to a daily rotating file in `logs/`
+//! under the app data directory, so users can attach the file to bug reports.
+//! The level defaults to `info` and can be changed with `RUST_LOG`. Warnings
+//! and errors are also kept in memory, see [`recent_errors`].
+
+use std::backtrace::Backtrace;
+use std::path::{Path, PathBuf};
+use std::sync::{Once, OnceLock};
+
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{InitError, RollingFileAppender, Rotation};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+use crate::{recent_errors, sts};
+
+/// Prefix of log file names (files are named `sts-stat-viewer.YYYY-MM-DD.log`)
+const LOG_FILE_PREFIX: &str = "sts-stat-viewer";
+
+/// Suffix of log file names
+const LOG_FILE_SUFFIX: &str = "log";
+
+/// Number of daily log files kept before the oldest is deleted
+const MAX_LOG_FILES: usize = 14;
+
+/// Keeps the background log writer alive for the lifetime of the process
+static LOG_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// Ensures the panic hook is only installed once
+static PANIC_HOOK: Once = Once::new();
+
+/// Directory log files are written to
+pub fn log_dir() -> Option<PathBuf> {
+    sts::get_data_dir().map(|dir| dir.join("logs"))
+}
+
+/// Create the rotating file appender for a log directory
+fn file_appender(dir: &Path) -> Result<RollingFileAppender, InitError> {
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(dir)
+}
+
+/// Most recently written log file in a directory
+pub fn latest_log_file(dir: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(LOG_FILE_PREFIX)
+        })
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// Log panics (with a backtrace) through the logger before the default hook runs
+///
+/// Without this, panics in background tasks only reach stderr and never make it
+/// into the log file.
+fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            tracing::error!("{}\n{}", info, Backtrace::force_capture());
+            previous(info);
+        }));
+    });
+}
+
+/// Install the global logger (console + rotating file + recent errors) and
+/// panic hook
+///
+/// Falls back to console-only logging if the log directory can't be used.
+/// Calling this more than once has no effect.
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let console = fmt::layer().with_target(false);
+
+    let file_writer = log_dir().and_then(|dir| match file_appender(&dir) {
+        Ok(appender) => Some(appender),
+        Err(e) => {
+            eprintln!("File logging disabled ({}): {}", dir.display(), e);
+            None
+        }
+    });
+
+    let file = file_writer.map(|appender| {
+        let (writer, guard) = tracing_appender::non_blocking(appender);
+        let _ = LOG_GUARD.set(guard);
+        fmt::layer().with_ansi(false).with_writer(writer)
+    });
+
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(console)
+        .with(file)
+        .with(recent_errors::layer())
+        .try_init();
+
+    install_panic_hook();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_file_appender_writes_to_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut appender = file_appender(dir.path()).unwrap();
+        appender.write_all(b"hello\n").unwrap();
+        appender.flush().unwrap();
+
+        let latest = latest_log_file(dir.path()).unwrap();
+        let name = latest.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with(LOG_FILE_PREFIX));
+        assert!(name.ends_with(LOG_FILE_SUFFIX));
+        assert_eq!(std::fs::read_to_string(latest).unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn test_latest_log_file_ignores_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "x").unwrap();
+        assert!(latest_log_file(dir.path()).is_none());
+        assert!(latest_log_file(&dir.path().join("missing")).is_none());
+    }
+}
@@ -0,0 +1,253 @@
+//! Recent warnings and errors
+//!
+//! A tracing layer keeps the last [`MAX_RECENT_ERRORS`] warnings and errors
+//! in memory, so the settings screen can show what went wrong lately without
+//! the user digging through log files. Anything logged at `warn` or `error`
+//! is kept — parse failures, server errors, panics — along with the fields of
+//! the spans it happened in, such as a request's ID and path.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+use utoipa::ToSchema;
+
+/// Number of entries kept; older ones are dropped first
+pub const MAX_RECENT_ERRORS: usize = 100;
+
+/// Longest message kept, in characters (panic backtraces run long)
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+/// Severity of a recorded entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorLevel {
+    Warn,
+    Error,
+}
+
+/// A warning or error that was logged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RecentError {
+    /// When it was logged
+    pub timestamp: DateTime<Utc>,
+    pub level: ErrorLevel,
+    /// Module that logged it
+    pub target: String,
+    pub message: String,
+    /// Fields of the event and its spans, e.g. `id` and `path` for requests
+    pub context: BTreeMap<String, String>,
+}
+
+/// A bounded list of recent entries, oldest first
+pub struct ErrorLog {
+    entries: Mutex<VecDeque<RecentError>>,
+    capacity: usize,
+}
+
+impl ErrorLog {
+    /// An empty log keeping at most `capacity` entries
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+            capacity,
+        }
+    }
+
+    fn push(&self, error: RecentError) {
+        let mut entries = self.entries.lock();
+        while entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(error);
+    }
+
+    /// Recorded entries, newest first
+    pub fn entries(&self) -> Vec<RecentError> {
+        self.entries.lock().iter().rev().cloned().collect()
+    }
+
+    /// Remove every entry, returning how many there were
+    pub fn clear(&self) -> usize {
+        let mut entries = self.entries.lock();
+        let count = entries.len();
+        entries.clear();
+        count
+    }
+}
+
+static RECENT_ERRORS: ErrorLog = ErrorLog::new(MAX_RECENT_ERRORS);
+
+/// Recent warnings and errors, newest first
+pub fn recent_errors() -> Vec<RecentError> {
+    RECENT_ERRORS.entries()
+}
+
+/// Forget the recorded warnings and errors, returning how many there were
+pub fn clear_recent_errors() -> usize {
+    RECENT_ERRORS.clear()
+}
+
+/// Layer recording warnings and errors into the shared log
+pub fn layer() -> RecentErrorsLayer {
+    RecentErrorsLayer {
+        log: &RECENT_ERRORS,
+    }
+}
+
+/// Tracing layer recording `warn` and `error` events into an [`ErrorLog`]
+pub struct RecentErrorsLayer {
+    log: &'static ErrorLog,
+}
+
+/// Formatted fields of a span, kept in its extensions
+#[derive(Debug, Clone, Default)]
+struct SpanFields(BTreeMap<String, String>);
+
+/// Collects an event's message and fields
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{:?}", value));
+    }
+}
+
+impl FieldVisitor {
+    fn insert(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl<S> Layer<S> for RecentErrorsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(visitor.fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+                fields.0.extend(visitor.fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let level = match *metadata.level() {
+            Level::ERROR => ErrorLevel::Error,
+            Level::WARN => ErrorLevel::Warn,
+            _ => return,
+        };
+
+        let mut context = BTreeMap::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    context.extend(fields.0.clone());
+                }
+            }
+        }
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        context.extend(visitor.fields);
+
+        let mut message = visitor.message.unwrap_or_default();
+        if let Some((end, _)) = message.char_indices().nth(MAX_MESSAGE_CHARS) {
+            message.truncate(end);
+            message.push('…');
+        }
+        self.log.push(RecentError {
+            timestamp: Utc::now(),
+            level,
+            target: metadata.target().to_string(),
+            message,
+            context,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn with_log(log: &'static ErrorLog, f: impl FnOnce()) {
+        let subscriber = tracing_subscriber::registry().with(RecentErrorsLayer { log });
+        tracing::subscriber::with_default(subscriber, f);
+    }
+
+    #[test]
+    fn test_records_warnings_and_errors_with_span_context() {
+        static LOG: ErrorLog = ErrorLog::new(10);
+        with_log(&LOG, || {
+            tracing::info!("not recorded");
+            let span = tracing::info_span!("request", id = "abc123", path = "/api/runs");
+            let _guard = span.enter();
+            tracing::warn!(file = "a.run", "Skipping file");
+            tracing::error!("Request failed with 500");
+        });
+
+        let entries = LOG.entries();
+        assert_eq!(entries.len(), 2);
+        let (error, warning) = (&entries[0], &entries[1]);
+        assert_eq!(error.level, ErrorLevel::Error);
+        assert_eq!(error.message, "Request failed with 500");
+        assert_eq!(error.context["id"], "abc123");
+        assert_eq!(warning.level, ErrorLevel::Warn);
+        assert_eq!(warning.context["path"], "/api/runs");
+        assert_eq!(warning.context["file"], "a.run");
+        assert!(warning.target.ends_with("recent_errors::tests"));
+    }
+
+    #[test]
+    fn test_log_is_bounded_and_clearable() {
+        static LOG: ErrorLog = ErrorLog::new(3);
+        with_log(&LOG, || {
+            for i in 0..5 {
+                tracing::warn!("warning {}", i);
+            }
+        });
+
+        let messages: Vec<String> = LOG.entries().into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, ["warning 4", "warning 3", "warning 2"]);
+        assert_eq!(LOG.clear(), 3);
+        assert!(LOG.entries().is_empty());
+    }
+
+    #[test]
+    fn test_long_messages_are_truncated() {
+        static LOG: ErrorLog = ErrorLog::new(1);
+        with_log(&LOG, || tracing::error!("{}", "x".repeat(5000)));
+        let message = &LOG.entries()[0].message;
+        assert_eq!(message.chars().count(), MAX_MESSAGE_CHARS + 1);
+    }
+}
@@ -0,0 +1,222 @@
+//! Background refresh of the runs directory
+//!
+//! File system events don't arrive for every runs directory (network shares
+//! often never deliver them), so the app can instead rescan the directory on
+//! an interval. Only files whose modification time or size changed are
+//! re-parsed.
+//!
+//! However a change is noticed, it is reported through [`check_for_changes`],
+//! which hands a [`RunsUpdated`] to the registered listener (the desktop app
+//! forwards it to the UI as a `runs-updated` event), so consumers don't depend
+//! on the mechanism that found it.
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+use crate::status::{set_watcher_state, WatcherState};
+use crate::{config, sts};
+
+/// Shortest auto-refresh interval that can be configured
+pub const MIN_AUTO_REFRESH_SECS: u64 = 30;
+
+/// Runs that changed in the runs directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RunsUpdated {
+    /// Cursor from before the change; pass it as `since` to
+    /// `/api/runs/delta` to fetch the changed runs
+    pub since: String,
+    /// Cursor after the change
+    pub cursor: String,
+    /// True when the cache was rebuilt and every run should be reloaded
+    pub reset: bool,
+    /// play_ids of runs added or changed
+    pub changed: Vec<String>,
+    /// play_ids of runs whose files were removed
+    pub removed: Vec<String>,
+}
+
+/// Callback invoked whenever runs change
+type RunsUpdatedListener = Box<dyn Fn(&RunsUpdated) + Send + Sync>;
+
+static RUNS_UPDATED_LISTENER: RwLock<Option<RunsUpdatedListener>> = RwLock::new(None);
+
+/// Register a callback for changed runs (e.g. to forward them to the UI)
+pub fn set_runs_updated_listener(listener: impl Fn(&RunsUpdated) + Send + Sync + 'static) {
+    *RUNS_UPDATED_LISTENER.write() = Some(Box::new(listener));
+}
+
+/// Rescan the runs directory, notifying the listener of runs changed since
+/// the cursor `since`
+///
+/// Returns the cursor to check from next time. Without a cursor this only
+/// records where the runs directory currently stands.
+pub fn check_for_changes(since: Option<&str>) -> Result<String, sts::RunsPathNotFound> {
+    let cursor = since.and_then(|since| sts::DeltaCursor::parse(since).ok());
+    let delta = sts::runs_delta(cursor.as_ref())?;
+    let Some(since) = since else {
+        return Ok(delta.cursor);
+    };
+    if delta.reset || !delta.runs.is_empty() || !delta.removed.is_empty() {
+        let update = RunsUpdated {
+            since: since.to_string(),
+            cursor: delta.cursor.clone(),
+            reset: delta.reset,
+            changed: delta.runs.into_iter().map(|r| r.play_id).collect(),
+            removed: delta.removed,
+        };
+        tracing::info!(
+            changed = update.changed.len(),
+            removed = update.removed.len(),
+            "Runs directory changed"
+        );
+        if let Some(listener) = RUNS_UPDATED_LISTENER.read().as_ref() {
+            listener(&update);
+        }
+    }
+    Ok(delta.cursor)
+}
+
+/// Cursor of the last scan by the poller or [`refresh_now`], so a change is
+/// reported once however it is noticed
+static LAST_CURSOR: Mutex<Option<String>> = Mutex::new(None);
+
+/// Rescan the runs directory now, notifying the listener of changes since
+/// the last scan
+///
+/// The first scan only records where the runs directory stands.
+pub fn refresh_now() -> Result<(), sts::RunsPathNotFound> {
+    let mut last = LAST_CURSOR.lock();
+    *last = Some(check_for_changes(last.as_deref())?);
+    Ok(())
+}
+
+/// A running poll thread
+struct Poller {
+    /// Dropping this wakes the thread and makes it exit
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+static POLLER: Mutex<Option<Poller>> = Mutex::new(None);
+
+/// Rescan the runs directory every `interval` until `stop` is dropped
+///
+/// The first scan starts over from where the runs directory stands, since
+/// it may have been switched while no poller ran.
+fn poll(interval: Duration, stop: mpsc::Receiver<()>) {
+    let check = || {
+        if let Err(e) = refresh_now() {
+            tracing::debug!("Auto-refresh skipped: {}", e);
+        }
+    };
+    *LAST_CURSOR.lock() = None;
+    check();
+    while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(interval) {
+        check();
+    }
+}
+
+/// Start polling the runs directory every `interval`, or stop with `None`
+///
+/// A poller that is already running is stopped first; this waits for a scan
+/// in progress to finish.
+pub fn set_auto_refresh(interval: Option<Duration>) {
+    let mut poller = POLLER.lock();
+    if let Some(Poller { stop, thread }) = poller.take() {
+        drop(stop);
+        if thread.join().is_err() {
+            tracing::error!("Auto-refresh thread panicked");
+        }
+    }
+
+    let Some(interval) = interval else {
+        set_watcher_state(WatcherState::Disabled);
+        return;
+    };
+    let (stop, stopped) = mpsc::channel();
+    let thread = std::thread::spawn(move || poll(interval, stopped));
+    *poller = Some(Poller { stop, thread });
+    set_watcher_state(WatcherState::Polling {
+        interval_secs: interval.as_secs(),
+    });
+}
+
+/// Change the auto-refresh interval in seconds, saving it to the config
+///
+/// `None` turns auto-refresh off. Intervals below [`MIN_AUTO_REFRESH_SECS`]
+/// are rejected.
+pub fn set_auto_refresh_secs(secs: Option<u64>) -> Result<(), String> {
+    if let Some(secs) = secs.filter(|secs| *secs < MIN_AUTO_REFRESH_SECS) {
+        return Err(format!(
+            "Auto-refresh interval must be at least {} seconds, got {}",
+            MIN_AUTO_REFRESH_SECS, secs
+        ));
+    }
+    if let Err(e) = config::update(|c| c.auto_refresh_secs = secs) {
+        tracing::error!("Failed to save config: {}", e);
+    }
+    set_auto_refresh(secs.map(Duration::from_secs));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::watcher_state;
+    use crate::sts::{RunFileBuilder, TestRunsDir};
+
+    #[test]
+    fn test_poller_reports_changes_and_stops() {
+        let runs_dir = TestRunsDir::empty();
+        runs_dir.add(&RunFileBuilder::new("first"));
+        let (sender, updates) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        set_runs_updated_listener(move |update| {
+            let _ = sender.lock().send(update.clone());
+        });
+
+        set_auto_refresh(Some(Duration::from_millis(20)));
+        assert_eq!(watcher_state(), WatcherState::Polling { interval_secs: 0 });
+        // Let the poller record where the directory stands before changing it
+        std::thread::sleep(Duration::from_millis(100));
+        runs_dir.add(&RunFileBuilder::new("second"));
+        let update = updates.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.changed, ["second"]);
+        assert!(!update.reset);
+        assert_ne!(update.since, update.cursor);
+
+        set_auto_refresh(None);
+        assert!(POLLER.lock().is_none());
+        assert_eq!(watcher_state(), WatcherState::Disabled);
+        runs_dir.add(&RunFileBuilder::new("third"));
+        assert!(updates.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn test_refresh_now_reports_each_change_once() {
+        let runs_dir = TestRunsDir::empty();
+        let (sender, updates) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        set_runs_updated_listener(move |update| {
+            let _ = sender.lock().send(update.clone());
+        });
+
+        *LAST_CURSOR.lock() = None;
+        refresh_now().unwrap();
+        runs_dir.add(&RunFileBuilder::new("new"));
+        refresh_now().unwrap();
+        refresh_now().unwrap();
+        assert_eq!(updates.try_recv().unwrap().changed, ["new"]);
+        assert!(updates.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_short_intervals_are_rejected() {
+        let err = set_auto_refresh_secs(Some(5)).unwrap_err();
+        assert!(err.contains("at least 30 seconds"));
+    }
+}
@@ -0,0 +1,401 @@
+//! Settings bundles
+//!
+//! A settings bundle is one JSON document holding the config file and the
+//! sidecar stores of every profile (hidden runs), for moving a setup to
+//! another machine. Runs aren't included; they stay in the game's folders
+//! (see [`sts::export`] for exporting runs).
+//!
+//! Importing replaces the config with the bundle's and merges hidden runs
+//! into the local ones, so a run hidden on either machine stays hidden. Runs
+//! directories that don't exist on this machine are imported anyway and
+//! reported, so they can be fixed in the settings afterwards. The server
+//! settings are only read at startup, so a change to them is reported as
+//! needing a restart.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, AppConfig, CONFIG_FILE};
+use crate::sts::{
+    self,
+    hidden::{HiddenRuns, HIDDEN_RUNS_FILE},
+};
+
+/// Value of the `format` field, telling bundles apart from run exports
+pub const BUNDLE_FORMAT: &str = "sts-stat-viewer-settings";
+
+/// Current bundle schema version
+pub const BUNDLE_SCHEMA_VERSION: u32 = 1;
+
+/// Config and sidecar stores of an app data directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub format: String,
+    pub schema_version: u32,
+    /// When the bundle was written (unix seconds)
+    pub exported_at: i64,
+    pub config: AppConfig,
+    /// Hidden play_ids by profile; profiles without hidden runs are left out
+    #[serde(default)]
+    pub hidden_runs: BTreeMap<String, HiddenRuns>,
+}
+
+/// Errors that can occur while writing or reading a settings bundle
+#[derive(Debug)]
+pub enum BundleError {
+    /// No app data directory is available
+    NoDataDir,
+    /// The bundle file could not be read or the settings could not be written
+    Io(std::io::Error),
+    /// The file is not a settings bundle or does not match its layout
+    Invalid(String),
+    /// The bundle was written by a newer version of the app
+    UnsupportedVersion { found: u32, supported: u32 },
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::NoDataDir => write!(f, "No app data directory available"),
+            BundleError::Io(e) => write!(f, "{}", e),
+            BundleError::Invalid(msg) => write!(f, "Invalid settings bundle: {}", msg),
+            BundleError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Settings bundle version {} is newer than this app supports (up to {}); please update the app",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+impl From<std::io::Error> for BundleError {
+    fn from(e: std::io::Error) -> Self {
+        BundleError::Io(e)
+    }
+}
+
+/// A runs directory from the bundle that doesn't exist on this machine
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MissingPath {
+    pub profile: String,
+    pub path: PathBuf,
+}
+
+/// Config settings that only take effect when the app restarts
+const RESTART_SETTINGS: [&str; 3] = ["port", "lan", "mdns"];
+
+/// What an import changed, or would change in a dry run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportReport {
+    /// Nothing was written
+    pub dry_run: bool,
+    /// Top-level config settings whose value differs from the local config
+    pub changed_settings: Vec<String>,
+    /// Changed settings that only take effect after restarting the app
+    pub restart_required: Vec<String>,
+    /// Number of runs newly hidden, by profile
+    pub hidden_runs_added: BTreeMap<String, usize>,
+    /// Runs directories to fix in the settings after importing
+    pub missing_paths: Vec<MissingPath>,
+}
+
+/// Collect the config and sidecar stores of a data directory
+pub fn bundle_from(data_dir: &Path) -> SettingsBundle {
+    let config = AppConfig::load(&data_dir.join(CONFIG_FILE));
+    let hidden_runs = config
+        .profile_names()
+        .into_iter()
+        .map(|profile| {
+            let hidden = HiddenRuns::load(&hidden_runs_path(data_dir, &profile));
+            (profile, hidden)
+        })
+        .filter(|(_, hidden)| !hidden.is_empty())
+        .collect();
+    SettingsBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        schema_version: BUNDLE_SCHEMA_VERSION,
+        exported_at: Utc::now().timestamp(),
+        config,
+        hidden_runs,
+    }
+}
+
+/// Parse a bundle document, checking its format, version and profile names
+pub fn parse_bundle(json: &str) -> Result<SettingsBundle, BundleError> {
+    let doc: Value = serde_json::from_str(json).map_err(|e| BundleError::Invalid(e.to_string()))?;
+    if doc.get("format").and_then(Value::as_str) != Some(BUNDLE_FORMAT) {
+        return Err(BundleError::Invalid(format!(
+            "expected format {:?}",
+            BUNDLE_FORMAT
+        )));
+    }
+    let version = doc
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| BundleError::Invalid("missing schema_version".to_string()))?;
+    if version > BUNDLE_SCHEMA_VERSION as u64 {
+        return Err(BundleError::UnsupportedVersion {
+            found: u32::try_from(version).unwrap_or(u32::MAX),
+            supported: BUNDLE_SCHEMA_VERSION,
+        });
+    }
+    let bundle: SettingsBundle =
+        serde_json::from_value(doc).map_err(|e| BundleError::Invalid(e.to_string()))?;
+
+    // Profile names become folder names, so reject anything that could
+    // point outside the data directory
+    let profiles = bundle.config.profile_names();
+    if let Some(name) = profiles
+        .iter()
+        .chain(bundle.hidden_runs.keys())
+        .find(|name| !sts::valid_profile_name(name))
+    {
+        return Err(BundleError::Invalid(format!(
+            "invalid profile name {:?}",
+            name
+        )));
+    }
+    Ok(bundle)
+}
+
+/// Restore a bundle into a data directory, or only report what would change
+/// when `dry_run` is set
+pub fn import_into(
+    data_dir: &Path,
+    bundle: &SettingsBundle,
+    dry_run: bool,
+) -> std::io::Result<ImportReport> {
+    let config_path = data_dir.join(CONFIG_FILE);
+    let local = AppConfig::load(&config_path);
+    let changed_settings = changed_settings(&local, &bundle.config);
+    let mut report = ImportReport {
+        dry_run,
+        restart_required: changed_settings
+            .iter()
+            .filter(|name| RESTART_SETTINGS.contains(&name.as_str()))
+            .cloned()
+            .collect(),
+        changed_settings,
+        missing_paths: missing_paths(&bundle.config),
+        ..Default::default()
+    };
+
+    for (profile, imported) in &bundle.hidden_runs {
+        let path = hidden_runs_path(data_dir, profile);
+        let mut hidden = HiddenRuns::load(&path);
+        let added = imported.iter().filter(|id| hidden.insert(id)).count();
+        if added == 0 {
+            continue;
+        }
+        report.hidden_runs_added.insert(profile.clone(), added);
+        if !dry_run {
+            hidden.save(&path)?;
+        }
+    }
+    if !dry_run {
+        bundle.config.save(&config_path)?;
+    }
+    Ok(report)
+}
+
+/// Write a bundle of the app's settings to `path`
+pub fn export_settings(path: &Path) -> Result<SettingsBundle, BundleError> {
+    let data_dir = sts::get_data_dir().ok_or(BundleError::NoDataDir)?;
+    let bundle = bundle_from(&data_dir);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(&bundle).map_err(std::io::Error::from)?;
+    std::fs::write(path, json)?;
+    Ok(bundle)
+}
+
+/// Restore the app's settings from a bundle at `path` and apply them
+///
+/// The auto-refresh poller isn't restarted here, since the CLI has none; the
+/// desktop app restarts it when `auto_refresh_secs` changed.
+pub fn import_settings(path: &Path, dry_run: bool) -> Result<ImportReport, BundleError> {
+    let data_dir = sts::get_data_dir().ok_or(BundleError::NoDataDir)?;
+    let bundle = parse_bundle(&std::fs::read_to_string(path)?)?;
+    let report = import_into(&data_dir, &bundle, dry_run)?;
+    if !dry_run {
+        sts::hidden::reload_hidden_runs();
+        config::reload();
+    }
+    Ok(report)
+}
+
+fn hidden_runs_path(data_dir: &Path, profile: &str) -> PathBuf {
+    sts::profiles::profile_dir_in(data_dir, profile).join(HIDDEN_RUNS_FILE)
+}
+
+/// Names of the top-level config fields that differ between two configs
+fn changed_settings(local: &AppConfig, imported: &AppConfig) -> Vec<String> {
+    let (Ok(Value::Object(local)), Ok(Value::Object(imported))) =
+        (serde_json::to_value(local), serde_json::to_value(imported))
+    else {
+        return Vec::new();
+    };
+    imported
+        .iter()
+        .filter(|(key, value)| local.get(*key) != Some(value))
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+/// Custom runs directories of a config that don't exist here
+fn missing_paths(config: &AppConfig) -> Vec<MissingPath> {
+    let active = (&config.active_profile, &config.runs_path);
+    let others = config
+        .profiles
+        .iter()
+        .map(|(name, profile)| (name, &profile.runs_path));
+    std::iter::once(active)
+        .chain(others)
+        .filter_map(|(profile, path)| {
+            let path = path.as_ref().filter(|path| !path.is_dir())?;
+            Some(MissingPath {
+                profile: profile.clone(),
+                path: path.clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProfileSettings;
+
+    /// A data directory with two profiles, one of them pointing at a runs
+    /// directory that only exists on the old machine
+    fn populated_data_dir(runs_dir: &Path) -> tempfile::TempDir {
+        let data_dir = tempfile::tempdir().unwrap();
+        let config = AppConfig {
+            runs_path: Some(runs_dir.to_path_buf()),
+            port: 4321,
+            profiles: BTreeMap::from([(
+                "partner".to_string(),
+                ProfileSettings {
+                    runs_path: Some(PathBuf::from("/old-machine/sts/runs")),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        config.save(&data_dir.path().join(CONFIG_FILE)).unwrap();
+        for (profile, ids) in [("default", ["a", "b"]), ("partner", ["c", "d"])] {
+            let mut hidden = HiddenRuns::default();
+            for id in ids {
+                hidden.insert(id);
+            }
+            hidden
+                .save(&hidden_runs_path(data_dir.path(), profile))
+                .unwrap();
+        }
+        data_dir
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let runs_dir = tempfile::tempdir().unwrap();
+        let source = populated_data_dir(runs_dir.path());
+        let bundle = bundle_from(source.path());
+        assert_eq!(bundle.schema_version, BUNDLE_SCHEMA_VERSION);
+        assert_eq!(
+            bundle.hidden_runs.keys().collect::<Vec<_>>(),
+            ["default", "partner"]
+        );
+        let json = serde_json::to_string_pretty(&bundle).unwrap();
+        let parsed = parse_bundle(&json).unwrap();
+        assert_eq!(parsed, bundle);
+
+        let target = tempfile::tempdir().unwrap();
+        let preview = import_into(target.path(), &parsed, true).unwrap();
+        assert!(preview.dry_run);
+        assert!(preview.changed_settings.contains(&"port".to_string()));
+        assert!(preview.changed_settings.contains(&"profiles".to_string()));
+        assert!(!preview.changed_settings.contains(&"lan".to_string()));
+        assert_eq!(preview.restart_required, ["port"]);
+        assert_eq!(
+            preview.hidden_runs_added,
+            BTreeMap::from([("default".to_string(), 2), ("partner".to_string(), 2)])
+        );
+        assert_eq!(
+            preview.missing_paths,
+            [MissingPath {
+                profile: "partner".to_string(),
+                path: PathBuf::from("/old-machine/sts/runs"),
+            }]
+        );
+        assert!(std::fs::read_dir(target.path()).unwrap().next().is_none());
+
+        let report = import_into(target.path(), &parsed, false).unwrap();
+        assert_eq!(
+            report,
+            ImportReport {
+                dry_run: false,
+                ..preview
+            }
+        );
+        let restored = bundle_from(target.path());
+        assert_eq!(restored.config, bundle.config);
+        assert_eq!(restored.hidden_runs, bundle.hidden_runs);
+
+        let again = import_into(target.path(), &parsed, false).unwrap();
+        assert!(again.changed_settings.is_empty());
+        assert!(again.restart_required.is_empty());
+        assert!(again.hidden_runs_added.is_empty());
+    }
+
+    #[test]
+    fn test_import_merges_hidden_runs() {
+        let runs_dir = tempfile::tempdir().unwrap();
+        let source = populated_data_dir(runs_dir.path());
+        let bundle = bundle_from(source.path());
+
+        let target = tempfile::tempdir().unwrap();
+        let local_path = hidden_runs_path(target.path(), "default");
+        let mut local = HiddenRuns::default();
+        local.insert("a");
+        local.insert("local-only");
+        local.save(&local_path).unwrap();
+
+        let report = import_into(target.path(), &bundle, false).unwrap();
+        assert_eq!(report.hidden_runs_added.get("default"), Some(&1));
+        let merged = HiddenRuns::load(&local_path);
+        assert_eq!(merged.iter().collect::<Vec<_>>(), ["a", "b", "local-only"]);
+    }
+
+    #[test]
+    fn test_rejected_bundles() {
+        let bundle = bundle_from(tempfile::tempdir().unwrap().path());
+        let mut doc = serde_json::to_value(&bundle).unwrap();
+
+        doc["schema_version"] = (BUNDLE_SCHEMA_VERSION + 1).into();
+        let err = parse_bundle(&doc.to_string()).unwrap_err();
+        assert!(matches!(
+            err,
+            BundleError::UnsupportedVersion { found, supported }
+                if found == BUNDLE_SCHEMA_VERSION + 1 && supported == BUNDLE_SCHEMA_VERSION
+        ));
+        doc["schema_version"] = BUNDLE_SCHEMA_VERSION.into();
+
+        doc["hidden_runs"] = serde_json::json!({ "../escape": ["a"] });
+        let err = parse_bundle(&doc.to_string()).unwrap_err();
+        assert!(matches!(err, BundleError::Invalid(msg) if msg.contains("../escape")));
+
+        doc["format"] = "something-else".into();
+        assert!(matches!(
+            parse_bundle(&doc.to_string()),
+            Err(BundleError::Invalid(_))
+        ));
+        assert!(matches!(parse_bundle("{"), Err(BundleError::Invalid(_))));
+    }
+}
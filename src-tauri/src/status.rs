@@ -0,0 +1,192 @@
+//! Application status
+//!
+//! Subsystems record their state here so the app can report it through the
+//! `get_app_status` command and `GET /api/status` instead of only printing to
+//! stderr.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{config, sts};
+
+/// State of the API server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ServerState {
+    /// The server has not bound its port yet
+    Starting,
+    /// The server is accepting connections
+    Running {
+        /// Port the server is bound to
+        port: u16,
+        /// URLs the server is reachable at
+        urls: Vec<String>,
+    },
+    /// The server panicked and is waiting to be restarted
+    Restarting {
+        /// Restart attempt about to be made (starting at 1)
+        attempt: u32,
+        /// Maximum number of restart attempts
+        max_attempts: u32,
+        /// Panic message
+        error: String,
+    },
+    /// The server failed to start or stopped with an error
+    Failed {
+        /// Error message
+        error: String,
+    },
+}
+
+/// State of the runs directory watcher
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum WatcherState {
+    /// No watcher is active; runs are re-read on each request
+    Disabled,
+    /// The watcher is observing the runs directory
+    Running {
+        /// Directory being watched
+        path: String,
+    },
+    /// The runs directory is rescanned on an interval (the `auto_refresh_secs`
+    /// setting)
+    Polling {
+        /// Seconds between scans
+        interval_secs: u64,
+    },
+    /// The watcher could not be started
+    Failed {
+        /// Error message
+        error: String,
+    },
+}
+
+/// Callback invoked whenever the server state changes
+type ServerStateListener = Box<dyn Fn(&ServerState) + Send + Sync>;
+
+static SERVER_STATE: RwLock<ServerState> = RwLock::new(ServerState::Starting);
+static SERVER_STATE_LISTENER: RwLock<Option<ServerStateListener>> = RwLock::new(None);
+static WATCHER_STATE: RwLock<WatcherState> = RwLock::new(WatcherState::Disabled);
+
+/// Record the API server state and notify the listener
+pub fn set_server_state(state: ServerState) {
+    *SERVER_STATE.write() = state.clone();
+    if let Some(listener) = SERVER_STATE_LISTENER.read().as_ref() {
+        listener(&state);
+    }
+}
+
+/// Register a callback for server state changes (e.g. to forward them to the UI)
+pub fn set_server_state_listener(listener: impl Fn(&ServerState) + Send + Sync + 'static) {
+    *SERVER_STATE_LISTENER.write() = Some(Box::new(listener));
+}
+
+/// Get the API server state
+pub fn server_state() -> ServerState {
+    SERVER_STATE.read().clone()
+}
+
+/// Record the watcher state
+pub fn set_watcher_state(state: WatcherState) {
+    *WATCHER_STATE.write() = state;
+}
+
+/// Get the watcher state
+pub fn watcher_state() -> WatcherState {
+    WATCHER_STATE.read().clone()
+}
+
+/// How the runs directory was resolved
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RunsPathStatus {
+    /// Resolution state, including a missing custom path and its fallback
+    pub resolution: sts::RunsPathResolution,
+    /// Directory that runs are loaded from, if one was found
+    pub current_path: Option<String>,
+    /// Whether a custom path is configured
+    pub is_custom: bool,
+    /// Directory found by auto-detection, if any
+    pub auto_detected_path: Option<String>,
+}
+
+/// Structured report of the state of each subsystem
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AppStatus {
+    /// Application version
+    pub version: String,
+    /// API server state
+    pub server: ServerState,
+    /// Runs directory resolution
+    pub runs_path: RunsPathStatus,
+    /// Result of the most recent run load (None until runs have been loaded)
+    pub last_load: Option<sts::LoadReport>,
+    /// Progress of the background load at startup
+    pub warmup: sts::WarmupState,
+    /// Runs directory watcher state
+    pub watcher: WatcherState,
+    /// Location of the config file
+    pub config_path: Option<String>,
+    /// Whether the config file exists
+    pub config_exists: bool,
+}
+
+/// Collect the current status of all subsystems
+pub fn app_status() -> AppStatus {
+    let resolution = sts::get_runs_path_info();
+    let config_path = config::config_path();
+
+    AppStatus {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        server: server_state(),
+        runs_path: RunsPathStatus {
+            current_path: resolution
+                .active_path()
+                .map(|p| p.to_string_lossy().to_string()),
+            is_custom: resolution.is_custom(),
+            auto_detected_path: sts::auto_detected_runs_path()
+                .map(|p| p.to_string_lossy().to_string()),
+            resolution,
+        },
+        last_load: sts::last_load_report(),
+        warmup: sts::warmup_state(),
+        watcher: watcher_state(),
+        config_exists: config_path.as_ref().is_some_and(|p| p.exists()),
+        config_path: config_path.map(|p| p.to_string_lossy().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_state_serialization() {
+        let state = ServerState::Running {
+            port: 3030,
+            urls: vec!["http://127.0.0.1:3030".to_string()],
+        };
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["state"], "running");
+        assert_eq!(json["port"], 3030);
+
+        let state = ServerState::Restarting {
+            attempt: 1,
+            max_attempts: 3,
+            error: "boom".to_string(),
+        };
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["state"], "restarting");
+        assert_eq!(json["attempt"], 1);
+
+        let json = serde_json::to_value(WatcherState::Disabled).unwrap();
+        assert_eq!(json["state"], "disabled");
+    }
+
+    #[test]
+    fn test_app_status_reports_version() {
+        let status = app_status();
+        assert_eq!(status.version, env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,216 @@
+//! Results by ascension level
+//!
+//! Win rates per character and ascension level. The `/api/stats/by-ascension`
+//! endpoint and the export file both use [`ascension_stats`], so the two
+//! always agree.
+//!
+//! [`AscensionNormalized`] groups those levels into bands and averages the
+//! band win rates with equal weights, so characters played at different
+//! ascensions can be compared.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use super::stats_math::wilson_interval;
+use super::{mean, Character, RunFilter, RunMetrics, StatsOptions};
+
+/// Results for one character at one ascension level
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AscensionStats {
+    /// Character directory name, e.g. `THE_SILENT`
+    pub character: String,
+    pub ascension_level: i32,
+    pub runs: i32,
+    pub wins: i32,
+    /// Share of runs won, from 0 to 1
+    pub win_rate: f64,
+    /// Low end of the win rate's [`wilson_interval`]
+    pub win_rate_ci_low: Option<f64>,
+    /// High end of the win rate's [`wilson_interval`]
+    pub win_rate_ci_high: Option<f64>,
+    pub avg_floor: f64,
+}
+
+/// Results per character and ascension level
+///
+/// Entries are ordered by character (in [`Character::all`] order) and then
+/// by ascension level. Only levels with at least one run are listed, and
+/// runs of modded characters are left out, as in the character stats.
+pub fn ascension_stats(runs: &[RunMetrics], options: &StatsOptions) -> Vec<AscensionStats> {
+    let mut groups: BTreeMap<(usize, i32), Vec<&RunMetrics>> = BTreeMap::new();
+    for run in RunFilter::from(options).apply(runs) {
+        let Some(character) = run.character.known() else {
+            continue;
+        };
+        let order = Character::all()
+            .iter()
+            .position(|c| *c == character)
+            .expect("known characters are listed in Character::all");
+        groups
+            .entry((order, run.ascension_level))
+            .or_default()
+            .push(run);
+    }
+
+    groups
+        .into_iter()
+        .map(|((order, ascension_level), runs)| {
+            let total = runs.len() as i32;
+            let wins = runs.iter().filter(|r| r.victory).count() as i32;
+            let (win_rate_ci_low, win_rate_ci_high) = wilson_interval(wins, total).unzip();
+            AscensionStats {
+                character: Character::all()[order].dir_name().to_string(),
+                ascension_level,
+                runs: total,
+                wins,
+                win_rate: wins as f64 / total as f64,
+                win_rate_ci_low,
+                win_rate_ci_high,
+                avg_floor: mean(runs.iter().map(|r| r.stats_floor())),
+            }
+        })
+        .collect()
+}
+
+/// Ascension bands for normalized win rates, as `(min, max)`; the last band
+/// takes every level above its minimum
+pub const ASCENSION_BANDS: [(i32, Option<i32>); 5] = [
+    (0, Some(4)),
+    (5, Some(9)),
+    (10, Some(14)),
+    (15, Some(19)),
+    (20, None),
+];
+
+/// Bands with fewer runs than this are flagged as low-confidence
+pub const MIN_BAND_RUNS: i32 = 5;
+
+/// Results for one character in one ascension band
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AscensionBand {
+    /// Band label, e.g. `A5-9` or `A20+`
+    pub label: String,
+    pub min_ascension: i32,
+    /// Highest level in the band (None for the last band)
+    pub max_ascension: Option<i32>,
+    pub runs: i32,
+    pub wins: i32,
+    /// Share of runs won, from 0 to 1
+    pub win_rate: f64,
+    /// Fewer than [`MIN_BAND_RUNS`] runs, so the win rate says little
+    pub low_confidence: bool,
+}
+
+/// Win rate with every ascension band weighted equally
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AscensionNormalized {
+    /// Mean of the band win rates (None without runs)
+    pub win_rate: Option<f64>,
+    /// Bands with at least one run, lowest first
+    pub bands: Vec<AscensionBand>,
+}
+
+/// Normalize one character's results by ascension band
+///
+/// `stats` are the character's entries from [`ascension_stats`]. Each band's
+/// win rate is computed from its own runs, and the normalized win rate is
+/// the plain mean of those rates, so a band with many runs counts no more
+/// than one with few. Bands without runs are left out of the mean.
+pub fn ascension_normalized(stats: &[AscensionStats]) -> AscensionNormalized {
+    let bands: Vec<AscensionBand> = ASCENSION_BANDS
+        .into_iter()
+        .filter_map(|(min, max)| {
+            let (runs, wins) = stats
+                .iter()
+                .filter(|s| {
+                    s.ascension_level >= min && max.is_none_or(|max| s.ascension_level <= max)
+                })
+                .fold((0, 0), |(runs, wins), s| (runs + s.runs, wins + s.wins));
+            (runs > 0).then(|| AscensionBand {
+                label: match max {
+                    Some(max) => format!("A{}-{}", min, max),
+                    None => format!("A{}+", min),
+                },
+                min_ascension: min,
+                max_ascension: max,
+                runs,
+                wins,
+                win_rate: wins as f64 / runs as f64,
+                low_confidence: runs < MIN_BAND_RUNS,
+            })
+        })
+        .collect();
+    let win_rate = (!bands.is_empty())
+        .then(|| bands.iter().map(|b| b.win_rate).sum::<f64>() / bands.len() as f64);
+    AscensionNormalized { win_rate, bands }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+
+    fn run_at(id: &str, character: Character, ascension: i32, victory: bool) -> RunMetrics {
+        let mut run = sample_run(id, character, victory);
+        run.ascension_level = ascension;
+        run.floor_reached = if victory { 51 } else { 20 };
+        run
+    }
+
+    #[test]
+    fn test_ascension_stats_grouping() {
+        let mut hidden = run_at("e", Character::Ironclad, 20, true);
+        hidden.hidden = true;
+        let runs = vec![
+            run_at("a", Character::Watcher, 0, true),
+            run_at("b", Character::Ironclad, 20, true),
+            run_at("c", Character::Ironclad, 20, false),
+            run_at("d", Character::Ironclad, 5, false),
+            hidden,
+        ];
+
+        let stats = ascension_stats(&runs, &StatsOptions::default());
+        let keys: Vec<(&str, i32)> = stats
+            .iter()
+            .map(|s| (s.character.as_str(), s.ascension_level))
+            .collect();
+        assert_eq!(keys, [("IRONCLAD", 5), ("IRONCLAD", 20), ("WATCHER", 0)]);
+
+        let a20 = &stats[1];
+        assert_eq!((a20.runs, a20.wins), (2, 1));
+        assert_eq!(a20.win_rate, 0.5);
+        let (low, high) = (a20.win_rate_ci_low.unwrap(), a20.win_rate_ci_high.unwrap());
+        assert!(low < 0.5 && high > 0.5);
+        assert_eq!(a20.avg_floor, 35.5);
+
+        let options = StatsOptions {
+            include_hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(ascension_stats(&runs, &options)[1].runs, 3);
+    }
+
+    #[test]
+    fn test_ascension_normalized_weights_bands_equally() {
+        let mut runs = Vec::new();
+        // A0: 9 of 10 won; A20: 1 of 2 won
+        for i in 0..10 {
+            runs.push(run_at(&format!("low{i}"), Character::Defect, i % 3, i < 9));
+        }
+        runs.push(run_at("high1", Character::Defect, 20, true));
+        runs.push(run_at("high2", Character::Defect, 20, false));
+
+        let normalized = ascension_normalized(&ascension_stats(&runs, &StatsOptions::default()));
+        let bands: Vec<(&str, i32, i32, bool)> = normalized
+            .bands
+            .iter()
+            .map(|b| (b.label.as_str(), b.runs, b.wins, b.low_confidence))
+            .collect();
+        assert_eq!(bands, [("A0-4", 10, 9, false), ("A20+", 2, 1, true)]);
+        // Raw win rate is 10/12; each band counts once here
+        assert_eq!(normalized.win_rate, Some((0.9 + 0.5) / 2.0));
+
+        assert_eq!(ascension_normalized(&[]).win_rate, None);
+    }
+}
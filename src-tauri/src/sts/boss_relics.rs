@@ -0,0 +1,252 @@
+//! Boss relic choices and skips
+//!
+//! Each `boss_relics` entry is the chest after an act boss, in act order:
+//! the relic taken is in `picked` and the rest in `not_picked`. Taking
+//! nothing leaves `picked` out, which counts as a skip. Files from before
+//! `boss_relics` existed have no chest data and are left out of skip rates.
+//!
+//! Runs that took Neow's boss relic swap ([`BOSS_RELIC_BONUS`]) can record
+//! the Act 1 chest without a pick, so that chest counts as neither a pick
+//! nor a skip.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use super::neow::{WinRecord, BOSS_RELIC_BONUS};
+use super::RunMetrics;
+
+/// One boss relic chest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct BossRelicChoice {
+    /// Act whose boss dropped the chest
+    pub act: i32,
+    /// Relic taken, if any
+    pub picked: Option<String>,
+    /// Relics left in the chest
+    pub not_picked: Vec<String>,
+    /// Nothing was taken (false for an empty Act 1 pick after a Neow boss
+    /// swap)
+    pub skipped: bool,
+}
+
+/// Chests from `(picked, not_picked)` pairs in act order
+pub fn summarize_chests<'a>(
+    chests: impl IntoIterator<Item = (Option<&'a str>, Vec<String>)>,
+    neow_bonus: Option<&str>,
+) -> Vec<BossRelicChoice> {
+    let swapped = neow_bonus == Some(BOSS_RELIC_BONUS);
+    chests
+        .into_iter()
+        .zip(1..)
+        .map(|((picked, not_picked), act)| {
+            let picked = picked
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(str::to_string);
+            BossRelicChoice {
+                act,
+                skipped: picked.is_none() && !(swapped && act == 1),
+                picked,
+                not_picked,
+            }
+        })
+        .collect()
+}
+
+/// Boss relic chests of one act across runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ActBossRelicStats {
+    pub act: i32,
+    /// Chests where a relic was taken or skipped
+    pub chests: i32,
+    pub skipped: i32,
+    /// Share of chests skipped
+    pub skip_rate: f64,
+    /// Runs that skipped this act's relic
+    pub when_skipped: WinRecord,
+    /// Runs that took this act's relic
+    pub when_picked: WinRecord,
+}
+
+/// How often a boss relic was offered and taken
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BossRelicPicks {
+    pub relic: String,
+    /// Chests it was in
+    pub offered: i32,
+    pub picked: i32,
+    /// Share of chests it was taken from
+    pub pick_rate: f64,
+    /// Runs that took it
+    #[serde(flatten)]
+    pub record: WinRecord,
+}
+
+/// Boss relic picks and skips
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BossRelicStats {
+    /// Runs with boss relic data
+    pub runs: i32,
+    /// Runs that skipped at least one boss relic
+    pub runs_with_skips: i32,
+    /// One entry per act with chests
+    pub acts: Vec<ActBossRelicStats>,
+    /// Relics in the chests, most offered first
+    pub relics: Vec<BossRelicPicks>,
+}
+
+/// Boss relic picks and skips per act, ignoring hidden runs and runs without
+/// boss relic data
+pub fn boss_relic_stats(runs: &[RunMetrics]) -> BossRelicStats {
+    let mut stats = BossRelicStats::default();
+    let mut by_act: BTreeMap<i32, ActBossRelicStats> = BTreeMap::new();
+    let mut by_relic: BTreeMap<&str, (i32, i32, WinRecord)> = BTreeMap::new();
+
+    for run in runs
+        .iter()
+        .filter(|r| !r.hidden && r.boss_relics_skipped.is_some())
+    {
+        stats.runs += 1;
+        stats.runs_with_skips += i32::from(run.boss_relics_skipped > Some(0));
+        for chest in &run.boss_relics {
+            for relic in chest.picked.iter().chain(&chest.not_picked) {
+                by_relic.entry(relic).or_default().0 += 1;
+            }
+            if let Some(picked) = &chest.picked {
+                let (_, picks, record) = by_relic.entry(picked).or_default();
+                *picks += 1;
+                record.add(run);
+            }
+            if chest.picked.is_none() && !chest.skipped {
+                continue;
+            }
+            let act = by_act
+                .entry(chest.act)
+                .or_insert_with(|| ActBossRelicStats {
+                    act: chest.act,
+                    ..Default::default()
+                });
+            act.chests += 1;
+            if chest.skipped {
+                act.skipped += 1;
+                act.when_skipped.add(run);
+            } else {
+                act.when_picked.add(run);
+            }
+            act.skip_rate = act.skipped as f64 / act.chests as f64;
+        }
+    }
+
+    stats.acts = by_act.into_values().collect();
+    stats.relics = by_relic
+        .into_iter()
+        .map(|(relic, (offered, picked, record))| BossRelicPicks {
+            relic: relic.to_string(),
+            offered,
+            picked,
+            pick_rate: picked as f64 / offered as f64,
+            record,
+        })
+        .collect();
+    stats.relics.sort_by_key(|r| std::cmp::Reverse(r.offered));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{Character, RunsDirFixture};
+
+    fn load_fixtures() -> Vec<RunMetrics> {
+        let fixture = RunsDirFixture::new();
+        let fixtures: [(&str, &[u8]); 3] = [
+            (
+                "picked.run",
+                include_bytes!("../../tests/fixtures/runs/boss_relics_picked.run"),
+            ),
+            (
+                "skipped.run",
+                include_bytes!("../../tests/fixtures/runs/boss_relics_skipped.run"),
+            ),
+            (
+                "swap.run",
+                include_bytes!("../../tests/fixtures/runs/boss_relics_swap.run"),
+            ),
+        ];
+        for (name, bytes) in fixtures {
+            fixture.add_raw(Character::Ironclad, name, bytes);
+        }
+        let mut runs = fixture.load().0;
+        runs.sort_by(|a, b| a.play_id.cmp(&b.play_id));
+        runs
+    }
+
+    #[test]
+    fn test_chest_fixtures() {
+        let runs = load_fixtures();
+        let skips: Vec<(&str, Option<i32>)> = runs
+            .iter()
+            .map(|r| (r.play_id.as_str(), r.boss_relics_skipped))
+            .collect();
+        assert_eq!(
+            skips,
+            [
+                ("boss_relics_picked", Some(0)),
+                ("boss_relics_skipped", Some(1)),
+                ("boss_relics_swap", Some(0)),
+            ]
+        );
+        let picked = &runs[0].boss_relics;
+        assert_eq!(picked.len(), 2);
+        assert_eq!(picked[1].act, 2);
+        assert_eq!(picked[1].picked.as_deref(), Some("Runic Dome"));
+        assert_eq!(picked[1].not_picked, ["Pandora's Box", "Astrolabe"]);
+
+        let swap = &runs[2].boss_relics[0];
+        assert_eq!((swap.picked.as_deref(), swap.skipped), (None, false));
+    }
+
+    #[test]
+    fn test_skip_rate_per_act() {
+        let mut runs = load_fixtures();
+        runs.push(sample_run("no-data", Character::Ironclad, true));
+
+        let stats = boss_relic_stats(&runs);
+        assert_eq!((stats.runs, stats.runs_with_skips), (3, 1));
+        let act1 = &stats.acts[0];
+        // The swap run's empty Act 1 chest isn't a choice
+        assert_eq!((act1.act, act1.chests, act1.skipped), (1, 2, 0));
+        let act2 = &stats.acts[1];
+        assert_eq!((act2.act, act2.chests, act2.skipped), (2, 3, 1));
+        assert!((act2.skip_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!((act2.when_skipped.runs, act2.when_skipped.wins), (1, 1));
+        assert_eq!((act2.when_picked.runs, act2.when_picked.wins), (2, 1));
+
+        let dome = stats
+            .relics
+            .iter()
+            .find(|r| r.relic == "Runic Dome")
+            .unwrap();
+        assert_eq!((dome.offered, dome.picked), (2, 1));
+        assert_eq!(dome.pick_rate, 0.5);
+    }
+
+    #[test]
+    fn test_missing_pick_outside_swap_act_is_a_skip() {
+        let chests = summarize_chests(
+            [
+                (None, vec!["Ectoplasm".to_string()]),
+                (Some(" "), Vec::new()),
+            ],
+            Some(BOSS_RELIC_BONUS),
+        );
+        assert!(!chests[0].skipped);
+        assert!(chests[1].skipped);
+        assert!(chests[1].picked.is_none());
+
+        let chests = summarize_chests([(None, Vec::new())], Some("THREE_CARDS"));
+        assert!(chests[0].skipped);
+    }
+}
@@ -0,0 +1,294 @@
+//! Act boss results
+//!
+//! Boss fights are found in `damage_taken` like any other encounter, but the
+//! game has written some boss names differently over the years ("The Champ"
+//! and "Champ", "Automaton" and "Bronze Automaton"), so they are matched
+//! against [`ACT_BOSSES`] rather than compared verbatim.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{mean, Encounter, RunMetrics};
+
+/// A boss and the names run files use for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActBoss {
+    pub act: i32,
+    /// Name reported by the API
+    pub name: &'static str,
+    /// Names accepted in `damage_taken` and `killed_by`, compared ignoring case
+    pub aliases: &'static [&'static str],
+}
+
+/// The nine act bosses and the Corrupt Heart
+pub const ACT_BOSSES: &[ActBoss] = &[
+    ActBoss {
+        act: 1,
+        name: "The Guardian",
+        aliases: &["The Guardian", "Guardian"],
+    },
+    ActBoss {
+        act: 1,
+        name: "Hexaghost",
+        aliases: &["Hexaghost"],
+    },
+    ActBoss {
+        act: 1,
+        name: "Slime Boss",
+        aliases: &["Slime Boss"],
+    },
+    ActBoss {
+        act: 2,
+        name: "The Champ",
+        aliases: &["The Champ", "Champ"],
+    },
+    ActBoss {
+        act: 2,
+        name: "The Collector",
+        aliases: &["The Collector", "Collector"],
+    },
+    ActBoss {
+        act: 2,
+        name: "Bronze Automaton",
+        aliases: &["Bronze Automaton", "Automaton"],
+    },
+    ActBoss {
+        act: 3,
+        name: "Awakened One",
+        aliases: &["Awakened One"],
+    },
+    ActBoss {
+        act: 3,
+        name: "Time Eater",
+        aliases: &["Time Eater"],
+    },
+    ActBoss {
+        act: 3,
+        name: "Donu and Deca",
+        aliases: &["Donu and Deca"],
+    },
+    ActBoss {
+        act: 4,
+        name: "Corrupt Heart",
+        aliases: &["Corrupt Heart", "The Heart", "Heart"],
+    },
+];
+
+/// Look up the boss an encounter or `killed_by` name refers to
+pub fn find_boss(name: &str) -> Option<&'static ActBoss> {
+    let name = name.trim();
+    ACT_BOSSES
+        .iter()
+        .find(|boss| boss.aliases.iter().any(|a| a.eq_ignore_ascii_case(name)))
+}
+
+/// Results against one boss
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BossStats {
+    pub name: String,
+    /// Fights against the boss
+    pub encountered: i32,
+    /// Fights the run survived
+    pub defeated: i32,
+    /// Runs the boss ended
+    pub killed_player: i32,
+    /// Average HP left after a won fight, if any recorded HP per floor
+    pub avg_hp_remaining: Option<f64>,
+}
+
+/// Results against the bosses of one act
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ActBossStats {
+    pub act: i32,
+    /// Runs that fought this act's boss
+    pub reached: i32,
+    /// Of those, runs that won every boss fight of the act
+    pub survived: i32,
+    /// `survived / reached`, or 0 when no run reached the boss
+    pub survival_rate: f64,
+    /// Every boss of the act, including ones never fought
+    pub bosses: Vec<BossStats>,
+}
+
+/// Whether the run ended in this fight
+pub fn fatal(run: &RunMetrics, encounter: &Encounter) -> bool {
+    !run.victory && encounter.floor >= run.floor_reached
+}
+
+/// Boss results per act, for acts 1 to 4
+///
+/// Hidden runs are ignored.
+pub fn act_boss_stats(runs: &[RunMetrics]) -> Vec<ActBossStats> {
+    #[derive(Default)]
+    struct Tally {
+        encountered: i32,
+        defeated: i32,
+        killed_player: i32,
+        hp_remaining: Vec<i32>,
+    }
+    let mut tallies: Vec<Tally> = ACT_BOSSES.iter().map(|_| Tally::default()).collect();
+    let mut reached = [0; 4];
+    let mut survived = [0; 4];
+    let index = |boss: &ActBoss| {
+        ACT_BOSSES
+            .iter()
+            .position(|b| b == boss)
+            .expect("bosses come from ACT_BOSSES")
+    };
+
+    for run in runs.iter().filter(|r| !r.hidden) {
+        // (reached, survived) for each act in this run
+        let mut acts = [(false, true); 4];
+        for encounter in &run.encounters {
+            let Some(boss) = find_boss(&encounter.enemies) else {
+                continue;
+            };
+            let tally = &mut tallies[index(boss)];
+            let act = &mut acts[boss.act as usize - 1];
+            tally.encountered += 1;
+            act.0 = true;
+            if fatal(run, encounter) {
+                act.1 = false;
+            } else {
+                tally.defeated += 1;
+                tally.hp_remaining.extend(encounter.hp_after);
+            }
+        }
+        if let Some(boss) = run
+            .killed_by
+            .as_deref()
+            .filter(|_| !run.victory)
+            .and_then(find_boss)
+        {
+            tallies[index(boss)].killed_player += 1;
+        }
+        for (i, (was_reached, was_survived)) in acts.into_iter().enumerate() {
+            if was_reached {
+                reached[i] += 1;
+                survived[i] += i32::from(was_survived);
+            }
+        }
+    }
+
+    (1..=4)
+        .map(|act| {
+            let i = act as usize - 1;
+            let bosses = ACT_BOSSES
+                .iter()
+                .zip(&tallies)
+                .filter(|(boss, _)| boss.act == act)
+                .map(|(boss, tally)| BossStats {
+                    name: boss.name.to_string(),
+                    encountered: tally.encountered,
+                    defeated: tally.defeated,
+                    killed_player: tally.killed_player,
+                    avg_hp_remaining: (!tally.hp_remaining.is_empty())
+                        .then(|| mean(tally.hp_remaining.iter().copied())),
+                })
+                .collect();
+            ActBossStats {
+                act,
+                reached: reached[i],
+                survived: survived[i],
+                survival_rate: if reached[i] > 0 {
+                    survived[i] as f64 / reached[i] as f64
+                } else {
+                    0.0
+                },
+                bosses,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Character;
+
+    fn fight(enemies: &str, floor: i32, hp_after: Option<i32>) -> Encounter {
+        Encounter {
+            enemies: enemies.to_string(),
+            floor,
+            hp_after,
+            elite: false,
+            turns: None,
+        }
+    }
+
+    #[test]
+    fn test_boss_table() {
+        assert_eq!(ACT_BOSSES.len(), 10);
+        for act in 1..=3 {
+            assert_eq!(ACT_BOSSES.iter().filter(|b| b.act == act).count(), 3);
+        }
+        for boss in ACT_BOSSES {
+            assert!(boss.aliases.contains(&boss.name), "{}", boss.name);
+        }
+    }
+
+    #[test]
+    fn test_find_boss_aliases() {
+        let act_of = |name: &str| find_boss(name).map(|b| (b.name, b.act));
+        assert_eq!(act_of("The Champ"), Some(("The Champ", 2)));
+        assert_eq!(act_of("Champ"), Some(("The Champ", 2)));
+        assert_eq!(act_of("Automaton"), Some(("Bronze Automaton", 2)));
+        assert_eq!(act_of("collector "), Some(("The Collector", 2)));
+        assert_eq!(act_of("The Heart"), Some(("Corrupt Heart", 4)));
+        assert_eq!(act_of("Donu and Deca"), Some(("Donu and Deca", 3)));
+        assert_eq!(act_of("Spheric Guardian"), None);
+        assert_eq!(act_of("Gremlin Nob"), None);
+    }
+
+    #[test]
+    fn test_act_boss_stats() {
+        let mut win = sample_run("a", Character::Ironclad, true);
+        win.floor_reached = 51;
+        win.encounters = vec![
+            fight("Jaw Worm", 2, Some(70)),
+            fight("Hexaghost", 16, Some(40)),
+            fight("Automaton", 33, None),
+            fight("Time Eater", 50, Some(12)),
+        ];
+        let mut loss = sample_run("b", Character::Ironclad, false);
+        loss.floor_reached = 33;
+        loss.killed_by = Some("The Champ".to_string());
+        loss.encounters = vec![
+            fight("Hexaghost", 16, Some(20)),
+            fight("The Champ", 33, None),
+        ];
+        let mut hidden = loss.clone();
+        hidden.hidden = true;
+
+        let stats = act_boss_stats(&[win, loss, hidden]);
+        assert_eq!(stats.len(), 4);
+
+        let act1 = &stats[0];
+        assert_eq!((act1.reached, act1.survived), (2, 2));
+        let hexaghost = act1.bosses.iter().find(|b| b.name == "Hexaghost").unwrap();
+        assert_eq!((hexaghost.encountered, hexaghost.defeated), (2, 2));
+        assert_eq!(hexaghost.avg_hp_remaining, Some(30.0));
+
+        let act2 = &stats[1];
+        assert_eq!((act2.reached, act2.survived), (2, 1));
+        assert_eq!(act2.survival_rate, 0.5);
+        let champ = act2.bosses.iter().find(|b| b.name == "The Champ").unwrap();
+        assert_eq!(
+            (champ.encountered, champ.defeated, champ.killed_player),
+            (1, 0, 1)
+        );
+        assert_eq!(champ.avg_hp_remaining, None);
+        let automaton = &act2.bosses[2];
+        assert_eq!(
+            (automaton.name.as_str(), automaton.defeated),
+            ("Bronze Automaton", 1)
+        );
+        assert_eq!(automaton.avg_hp_remaining, None);
+
+        assert_eq!((stats[2].reached, stats[2].survived), (1, 1));
+        let act4 = &stats[3];
+        assert_eq!((act4.reached, act4.survival_rate), (0, 0.0));
+        assert_eq!(act4.bosses[0].encountered, 0);
+    }
+}
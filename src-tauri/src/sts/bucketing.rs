@@ -0,0 +1,152 @@
+//! Local time of runs for time-grouped stats
+//!
+//! Every endpoint that groups runs by day, week, month, weekday or hour goes
+//! through [`TimeBucketing`], so a run lands in the same local day everywhere.
+//! A run's `local_time` is the player's own clock and is used as is; runs
+//! without one have their UTC `timestamp` shifted by a fixed offset. Offsets
+//! have no daylight saving rules, so a viewer on summer time passes the
+//! summer offset.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime};
+
+use super::RunMetrics;
+
+/// Largest accepted UTC offset, in minutes (UTC+14:00)
+pub const MAX_TZ_OFFSET_MINUTES: i32 = 14 * 60;
+
+/// A UTC offset outside UTC-14:00..=UTC+14:00
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidTzOffset(pub i32);
+
+impl std::fmt::Display for InvalidTzOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "tz_offset_minutes must be between -{0} and {0}, got {1}",
+            MAX_TZ_OFFSET_MINUTES, self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidTzOffset {}
+
+/// Turns runs into local times and day/week/month keys for one UTC offset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeBucketing {
+    tz_offset_minutes: i32,
+}
+
+impl TimeBucketing {
+    /// Bucketing for an offset in minutes east of UTC (e.g. -300 for UTC-5)
+    pub fn new(tz_offset_minutes: i32) -> Result<Self, InvalidTzOffset> {
+        if !(-MAX_TZ_OFFSET_MINUTES..=MAX_TZ_OFFSET_MINUTES).contains(&tz_offset_minutes) {
+            return Err(InvalidTzOffset(tz_offset_minutes));
+        }
+        Ok(Self { tz_offset_minutes })
+    }
+
+    /// Offset in minutes east of UTC
+    pub fn tz_offset_minutes(&self) -> i32 {
+        self.tz_offset_minutes
+    }
+
+    /// Local time the run ended at, if the file records when it ended
+    ///
+    /// Timestamps whose local time would fall outside the dates chrono can
+    /// represent have no local time, so the run gets no bucket.
+    pub fn local_time(&self, run: &RunMetrics) -> Option<NaiveDateTime> {
+        if let Some(local) = run.local_time {
+            return Some(local);
+        }
+        let utc = DateTime::from_timestamp(run.timestamp?, 0)?;
+        utc.naive_utc()
+            .checked_add_signed(Duration::minutes(self.tz_offset_minutes.into()))
+    }
+
+    /// Local date the run ended on
+    pub fn day(&self, run: &RunMetrics) -> Option<NaiveDate> {
+        self.local_time(run).map(|t| t.date())
+    }
+
+    /// ISO week the run ended in, e.g. `2024-W01`
+    pub fn week(&self, run: &RunMetrics) -> Option<String> {
+        let week = self.day(run)?.iso_week();
+        Some(format!("{}-W{:02}", week.year(), week.week()))
+    }
+
+    /// Month the run ended in, e.g. `2024-01`
+    pub fn month(&self, run: &RunMetrics) -> Option<String> {
+        self.day(run).map(|d| d.format("%Y-%m").to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Character;
+
+    /// 2024-01-01T00:30:00Z, a Monday
+    const NEW_YEAR_0030_UTC: i64 = 1_704_069_000;
+
+    fn run_at(timestamp: i64) -> RunMetrics {
+        let mut run = sample_run("a", Character::Ironclad, true);
+        run.timestamp = Some(timestamp);
+        run
+    }
+
+    #[test]
+    fn test_offset_limits() {
+        assert!(TimeBucketing::new(MAX_TZ_OFFSET_MINUTES).is_ok());
+        assert!(TimeBucketing::new(-MAX_TZ_OFFSET_MINUTES).is_ok());
+        assert_eq!(
+            TimeBucketing::new(MAX_TZ_OFFSET_MINUTES + 1),
+            Err(InvalidTzOffset(MAX_TZ_OFFSET_MINUTES + 1))
+        );
+        assert!(TimeBucketing::new(-MAX_TZ_OFFSET_MINUTES - 1).is_err());
+        assert!(TimeBucketing::new(i32::MIN).is_err());
+        assert!(TimeBucketing::new(i32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_keys_across_midnight() {
+        let run = run_at(NEW_YEAR_0030_UTC);
+        let utc = TimeBucketing::default();
+        assert_eq!(utc.day(&run), NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert_eq!(utc.week(&run).as_deref(), Some("2024-W01"));
+        assert_eq!(utc.month(&run).as_deref(), Some("2024-01"));
+
+        // An hour west of UTC it is still New Year's Eve, in the last ISO
+        // week of 2023
+        let west = TimeBucketing::new(-60).unwrap();
+        assert_eq!(west.day(&run), NaiveDate::from_ymd_opt(2023, 12, 31));
+        assert_eq!(west.week(&run).as_deref(), Some("2023-W52"));
+        assert_eq!(west.month(&run).as_deref(), Some("2023-12"));
+
+        // Half-hour and quarter-hour offsets are kept exactly
+        let east = TimeBucketing::new(5 * 60 + 45).unwrap();
+        let local = east.local_time(&run).unwrap();
+        assert_eq!(
+            local.format("%Y-%m-%d %H:%M").to_string(),
+            "2024-01-01 06:15"
+        );
+    }
+
+    #[test]
+    fn test_local_time_ignores_offset() {
+        let mut run = run_at(NEW_YEAR_0030_UTC);
+        run.local_time =
+            NaiveDate::from_ymd_opt(2023, 12, 31).and_then(|d| d.and_hms_opt(19, 30, 0));
+        let east = TimeBucketing::new(600).unwrap();
+        assert_eq!(east.local_time(&run), run.local_time);
+
+        run.local_time = None;
+        run.timestamp = None;
+        assert_eq!(east.day(&run), None);
+
+        // Shifting the last representable time east leaves chrono's range
+        run.timestamp = Some(DateTime::<chrono::Utc>::MAX_UTC.timestamp());
+        assert_eq!(east.local_time(&run), None);
+        assert!(TimeBucketing::default().day(&run).is_some());
+    }
+}
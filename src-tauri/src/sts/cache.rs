@@ -0,0 +1,1276 @@
+//! Run cache
+//!
+//! Keeps parsed runs in memory keyed by file path so a refresh only re-parses
+//! files whose modification time or size changed. Every ingest and removal is
+//! stamped with a sequence number, which lets clients ask for the changes
+//! since their last request instead of re-downloading every run.
+//!
+//! A play_id index points each run at its file, so a single run can be
+//! re-parsed on demand without walking the runs directory. When several files
+//! hold the same play_id (a sync conflict copy, a backup found by a recursive
+//! scan) only the most recently modified one is loaded, and the others are
+//! listed in the load report.
+//!
+//! A refresh can also run in batches ([`RunCache::begin_refresh`] and
+//! [`RunCache::continue_refresh`]), so a long first load can report progress
+//! and let the runs parsed so far be read in between.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use walkdir::WalkDir;
+
+use super::paths::resolve_links;
+use super::recovery::Recovery;
+use super::{
+    parse_run_file, Character, CharacterId, DuplicateRun, LoadIssue, LoadReport, ParseFailure,
+    RecoveredFile, RunMetrics,
+};
+
+/// Identifies the on-disk version of a run file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Result of parsing a run file, kept until the file changes
+#[derive(Debug, Clone)]
+enum CachedFile {
+    Parsed {
+        run: Box<RunMetrics>,
+        recoveries: Vec<Recovery>,
+    },
+    Failed(ParseFailure),
+}
+
+/// Whether a run took its play_id from its file name
+fn named_after_file(recoveries: &[Recovery]) -> bool {
+    recoveries.contains(&Recovery::PlayIdFromFileName)
+}
+
+/// A cached file along with when it was ingested
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    stamp: FileStamp,
+    /// Character folder the file was found in
+    character: CharacterId,
+    file: CachedFile,
+    seq: u64,
+    ingested_at: DateTime<Utc>,
+}
+
+impl CacheEntry {
+    /// Repairs the file needed, if it parsed
+    fn recoveries(&self) -> &[Recovery] {
+        match &self.file {
+            CachedFile::Parsed { recoveries, .. } => recoveries,
+            CachedFile::Failed(_) => &[],
+        }
+    }
+}
+
+/// Removals kept for delta requests; cursors from before the oldest one
+/// dropped get a full reload instead
+const MAX_REMOVALS: usize = 1000;
+
+/// A run that disappeared from disk
+#[derive(Debug, Clone)]
+struct Removal {
+    play_id: String,
+    seq: u64,
+    removed_at: DateTime<Utc>,
+}
+
+/// Position in the cache's change history, as handed out to clients
+///
+/// Serialized as `<epoch>:<seq>`. The epoch identifies the cache instance so
+/// a cursor from before a restart (or a different runs directory) is detected
+/// rather than silently misread. Clients may also pass an RFC 3339 timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeltaCursor {
+    /// A cursor returned by a previous delta call
+    Sequence { epoch: i64, seq: u64 },
+    /// Changes ingested after a point in time
+    Timestamp(DateTime<Utc>),
+}
+
+impl DeltaCursor {
+    /// Parse a cursor or timestamp
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if let Some((epoch, seq)) = value.split_once(':') {
+            if let (Ok(epoch), Ok(seq)) = (epoch.parse(), seq.parse()) {
+                return Ok(Self::Sequence { epoch, seq });
+            }
+        }
+        DateTime::parse_from_rfc3339(value)
+            .map(|t| Self::Timestamp(t.with_timezone(&Utc)))
+            .map_err(|_| format!("Invalid cursor or timestamp: {}", value))
+    }
+}
+
+/// Runs that changed since a cursor
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RunDelta {
+    /// Cursor to pass as `since` on the next request
+    pub cursor: String,
+    /// True when the cursor could not be used and `runs` holds every run;
+    /// clients should replace their list instead of merging
+    pub reset: bool,
+    /// Runs added or changed since the cursor (hidden runs included, flagged)
+    pub runs: Vec<RunMetrics>,
+    /// play_ids of runs whose files were removed since the cursor
+    pub removed: Vec<String>,
+}
+
+/// Files checked so far in a refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct LoadProgress {
+    /// Run files parsed or found unchanged
+    pub files_processed: usize,
+    /// Run files found in the runs directory
+    pub files_total: usize,
+}
+
+/// A refresh that has scanned the runs directory but not checked every file
+#[derive(Debug, Clone)]
+struct PendingRefresh {
+    files: std::vec::IntoIter<(PathBuf, CharacterId)>,
+    progress: LoadProgress,
+    /// Files checked so far; cached files not among them are removed at the end
+    seen: BTreeSet<PathBuf>,
+}
+
+/// Why a run could not be re-parsed
+#[derive(Debug, Clone)]
+pub enum ReparseError {
+    /// No runs directory is configured or detected
+    NoRunsPath,
+    /// No cached run has this play_id
+    NotFound,
+    /// The run's file is gone; the run was dropped from the cache
+    FileRemoved(PathBuf),
+    /// The file no longer loads; the run was dropped from the cache
+    Invalid(LoadIssue),
+}
+
+impl fmt::Display for ReparseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReparseError::NoRunsPath => write!(f, "No runs directory configured or detected"),
+            ReparseError::NotFound => write!(f, "Run not found"),
+            ReparseError::FileRemoved(path) => {
+                write!(f, "Run file no longer exists: {}", path.display())
+            }
+            ReparseError::Invalid(issue) => match (issue.line, issue.column) {
+                (Some(line), Some(column)) => {
+                    write!(f, "{}:{}:{}: {}", issue.path, line, column, issue.message)
+                }
+                _ => write!(f, "{}: {}", issue.path, issue.message),
+            },
+        }
+    }
+}
+
+impl std::error::Error for ReparseError {}
+
+/// Result of walking the runs directory
+#[derive(Debug, Default)]
+struct ScannedFiles {
+    /// Run files with the character they belong to
+    files: Vec<(PathBuf, CharacterId)>,
+    /// Links that could not be followed
+    issues: Vec<LoadIssue>,
+    /// Run files found in subfolders of a character directory
+    nested: usize,
+}
+
+impl ScannedFiles {
+    fn issue(&mut self, path: &Path, message: String) {
+        self.issues.push(skip_issue(path, message));
+    }
+}
+
+/// Log and describe a file that was skipped without being parsed
+fn skip_issue(path: &Path, message: String) -> LoadIssue {
+    tracing::warn!("Skipping {}: {}", path.display(), message);
+    LoadIssue {
+        path: path.to_string_lossy().to_string(),
+        message,
+        line: None,
+        column: None,
+    }
+}
+
+/// Whether a walk entry is a hidden directory (name starts with `.`)
+fn is_hidden_dir(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && entry.file_name().to_string_lossy().starts_with('.')
+}
+
+/// Describe a file that failed to parse
+fn failure_issue(path: &Path, failure: &ParseFailure) -> LoadIssue {
+    LoadIssue {
+        path: path.to_string_lossy().to_string(),
+        message: failure.message.clone(),
+        line: failure.position.map(|(line, _)| line),
+        column: failure.position.map(|(_, column)| column),
+    }
+}
+
+/// Parse a run file into a cache entry's contents
+fn parse_cached(path: &Path, character: &CharacterId) -> CachedFile {
+    match parse_run_file(path, character) {
+        Ok((run, recoveries)) => {
+            if !recoveries.is_empty() {
+                tracing::info!("Repaired {}: {:?}", path.display(), recoveries);
+            }
+            CachedFile::Parsed {
+                run: Box::new(run),
+                recoveries,
+            }
+        }
+        Err(failure) => {
+            tracing::warn!("Skipping {}: {}", path.display(), failure.message);
+            CachedFile::Failed(failure)
+        }
+    }
+}
+
+/// Describe a walk error, preferring the symlink diagnosis when there is one
+fn link_error_message(path: &Path, error: &walkdir::Error) -> String {
+    if error.loop_ancestor().is_some() {
+        return "Symlink cycle".to_string();
+    }
+    match resolve_links(path) {
+        Err(e) => e.to_string(),
+        Ok(_) => error.to_string(),
+    }
+}
+
+/// Folder the game keeps Daily Climb runs in, which isn't a character
+const DAILY_DIR: &str = "DAILY";
+
+/// Deepest folder nesting searched inside a character directory
+pub const MAX_SCAN_DEPTH: usize = 8;
+
+/// Largest run file loaded by default (8 MiB); real runs are well under 1 MiB
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// How the runs directory is searched for run files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanOptions {
+    /// Also look in subfolders of each character directory (up to
+    /// [`MAX_SCAN_DEPTH`] levels), e.g. `IRONCLAD/2023/`
+    pub recursive: bool,
+    /// Files larger than this are skipped without being read
+    pub max_file_bytes: u64,
+}
+
+impl ScanOptions {
+    /// Flat scan with the default size limit, decks kept in memory
+    pub const DEFAULT: Self = Self {
+        recursive: false,
+        max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+    };
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Parsed runs for one runs directory
+#[derive(Debug, Clone)]
+pub struct RunCache {
+    root: PathBuf,
+    options: ScanOptions,
+    epoch: i64,
+    seq: u64,
+    entries: BTreeMap<PathBuf, CacheEntry>,
+    /// File each parsed run is loaded from, see [`RunCache::index_play_ids`]
+    play_ids: BTreeMap<String, PathBuf>,
+    removals: Vec<Removal>,
+    /// Newest removal dropped to keep the log under [`MAX_REMOVALS`]
+    dropped_removal: Option<Removal>,
+    skipped: Vec<LoadIssue>,
+    nested_files: usize,
+    pending: Option<PendingRefresh>,
+    /// Whether a refresh has finished
+    scanned: bool,
+}
+
+impl RunCache {
+    /// Create an empty cache for a runs directory
+    pub fn new(root: &Path) -> Self {
+        Self::with_options(root, ScanOptions::default())
+    }
+
+    /// Create an empty cache for a runs directory with explicit scan options
+    pub fn with_options(root: &Path, options: ScanOptions) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            options,
+            epoch: Utc::now().timestamp_millis(),
+            seq: 0,
+            entries: BTreeMap::new(),
+            play_ids: BTreeMap::new(),
+            removals: Vec::new(),
+            dropped_removal: None,
+            skipped: Vec::new(),
+            nested_files: 0,
+            pending: None,
+            scanned: false,
+        }
+    }
+
+    /// The runs directory this cache reads from
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// How this cache searches the runs directory
+    pub fn options(&self) -> ScanOptions {
+        self.options
+    }
+
+    /// Cursor for the current state of the cache
+    pub fn cursor(&self) -> String {
+        format!("{}:{}", self.epoch, self.seq)
+    }
+
+    /// `.run` files in the runs directory, with the character they belong to
+    ///
+    /// The character comes from the top-level folder, however deep the file
+    /// is nested. Folders other than the base game characters' hold the runs
+    /// of modded characters and are searched after them, by name (see
+    /// [`character_dirs`](Self::character_dirs)). Symlinked directories and run files are followed; a link
+    /// counts as a run file if either its own name or its target ends in
+    /// `.run`. Dangling or looping links are skipped and returned as issues.
+    /// Hidden directories (starting with `.`) are never searched.
+    fn run_files(&self) -> ScannedFiles {
+        let mut scanned = ScannedFiles::default();
+        let max_depth = if self.options.recursive {
+            MAX_SCAN_DEPTH
+        } else {
+            1
+        };
+
+        for (char_dir, character) in self.character_dirs() {
+            let walker = WalkDir::new(&char_dir)
+                .follow_links(true)
+                .min_depth(1)
+                .max_depth(max_depth)
+                .into_iter()
+                .filter_entry(|entry| !is_hidden_dir(entry));
+
+            for entry in walker {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        let path = e.path().unwrap_or(&char_dir).to_path_buf();
+                        scanned.issue(&path, link_error_message(&path, &e));
+                        continue;
+                    }
+                };
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+
+                let path = entry.path();
+                let is_run = |p: &Path| p.extension().is_some_and(|e| e == "run");
+                let target_is_run = entry.path_is_symlink()
+                    && resolve_links(path).is_ok_and(|target| is_run(&target));
+                if is_run(path) || target_is_run {
+                    if entry.depth() > 1 {
+                        scanned.nested += 1;
+                    }
+                    scanned.files.push((path.to_path_buf(), character.clone()));
+                }
+            }
+        }
+        scanned
+    }
+
+    /// Character folders of the runs directory: the base game characters'
+    /// that exist, then any other visible folder as a modded character
+    ///
+    /// A folder named like a base game character in another case is left
+    /// out, since on a case-insensitive file system it is the same folder.
+    fn character_dirs(&self) -> Vec<(PathBuf, CharacterId)> {
+        let mut dirs: Vec<(PathBuf, CharacterId)> = Character::all()
+            .iter()
+            .map(|character| (self.root.join(character.dir_name()), (*character).into()))
+            .filter(|(dir, _)| dir.exists() || dir.is_symlink())
+            .collect();
+        let Ok(entries) = std::fs::read_dir(&self.root) else {
+            return dirs;
+        };
+        let mut modded: Vec<(PathBuf, CharacterId)> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_str()?.to_string();
+                if name.starts_with('.') || name.eq_ignore_ascii_case(DAILY_DIR) {
+                    return None;
+                }
+                match CharacterId::from_dir_name(&name) {
+                    CharacterId::Modded(_) => Some((entry.path(), CharacterId::Modded(name))),
+                    CharacterId::Known(_) => None,
+                }
+            })
+            .collect();
+        modded.sort_by(|a, b| a.0.cmp(&b.0));
+        dirs.extend(modded);
+        dirs
+    }
+
+    /// Bring the cache in line with the files on disk
+    ///
+    /// Only new or modified files are parsed; removed files are recorded so
+    /// delta requests can report them.
+    pub fn refresh(&mut self) -> LoadReport {
+        self.begin_refresh();
+        self.continue_refresh(usize::MAX)
+            .expect("a refresh without a file limit finishes")
+    }
+
+    /// Scan the runs directory and start a refresh in batches
+    ///
+    /// Files are only checked by [`RunCache::continue_refresh`]; until the
+    /// refresh finishes, [`RunCache::runs`] returns the runs parsed so far. A
+    /// refresh already in progress is started over.
+    pub fn begin_refresh(&mut self) -> LoadProgress {
+        let scanned = self.run_files();
+        self.skipped = scanned.issues;
+        self.nested_files = scanned.nested;
+        let progress = LoadProgress {
+            files_processed: 0,
+            files_total: scanned.files.len(),
+        };
+        self.pending = Some(PendingRefresh {
+            files: scanned.files.into_iter(),
+            progress,
+            seen: BTreeSet::new(),
+        });
+        progress
+    }
+
+    /// Check up to `max_files` more files of the refresh in progress
+    ///
+    /// Returns the load report once every file has been checked (at once if
+    /// no refresh is in progress), or `None` while files remain.
+    pub fn continue_refresh(&mut self, max_files: usize) -> Option<LoadReport> {
+        let now = Utc::now();
+        let Some(mut pending) = self.pending.take() else {
+            return Some(self.report(now));
+        };
+
+        for (path, character) in pending.files.by_ref().take(max_files) {
+            pending.progress.files_processed += 1;
+            let Some(stamp) = FileStamp::of(&path) else {
+                continue;
+            };
+            if stamp.len > self.options.max_file_bytes {
+                let message = format!(
+                    "File is {} bytes, over the {} byte limit",
+                    stamp.len, self.options.max_file_bytes
+                );
+                self.skipped.push(skip_issue(&path, message));
+                continue;
+            }
+            pending.seen.insert(path.clone());
+            if self.entries.get(&path).is_some_and(|e| e.stamp == stamp) {
+                continue;
+            }
+
+            let file = parse_cached(&path, &character);
+            self.seq += 1;
+            let entry = CacheEntry {
+                stamp,
+                character,
+                file,
+                seq: self.seq,
+                ingested_at: now,
+            };
+            if let Some(CacheEntry {
+                file: CachedFile::Parsed { run: old, .. },
+                ..
+            }) = self.entries.insert(path, entry)
+            {
+                self.record_removal_if_gone(old.play_id, now);
+            }
+        }
+
+        if pending.progress.files_processed < pending.progress.files_total {
+            self.pending = Some(pending);
+            self.index_play_ids();
+            return None;
+        }
+
+        let gone: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|path| !pending.seen.contains(*path))
+            .cloned()
+            .collect();
+        for path in gone {
+            if let Some(CacheEntry {
+                file: CachedFile::Parsed { run, .. },
+                ..
+            }) = self.entries.remove(&path)
+            {
+                self.record_removal_if_gone(run.play_id, now);
+            }
+        }
+
+        self.index_play_ids();
+        self.scanned = true;
+        Some(self.report(now))
+    }
+
+    /// Whether a refresh has finished, so the cache reflects the runs
+    /// directory as of then
+    pub fn has_scanned(&self) -> bool {
+        self.scanned
+    }
+
+    /// Files checked so far, while a refresh is in progress
+    pub fn progress(&self) -> Option<LoadProgress> {
+        self.pending.as_ref().map(|p| p.progress)
+    }
+
+    /// Pick the file each play_id is loaded from
+    ///
+    /// The most recently modified file wins; files with the same
+    /// modification time go by path order. Runs named after their file (see
+    /// [`Recovery::PlayIdFromFileName`]) are all loaded, and the index points
+    /// at the first of them unless a file with that play_id exists.
+    fn index_play_ids(&mut self) {
+        let mut index: BTreeMap<String, (&PathBuf, &CacheEntry)> = BTreeMap::new();
+        for (path, entry) in &self.entries {
+            let CachedFile::Parsed { run, recoveries } = &entry.file else {
+                continue;
+            };
+            match index.entry(run.play_id.clone()) {
+                Entry::Vacant(slot) => {
+                    slot.insert((path, entry));
+                }
+                Entry::Occupied(mut slot) => {
+                    let replaces = if named_after_file(recoveries) {
+                        false
+                    } else if named_after_file(slot.get().1.recoveries()) {
+                        true
+                    } else {
+                        entry.stamp.modified > slot.get().1.stamp.modified
+                    };
+                    if replaces {
+                        slot.insert((path, entry));
+                    }
+                }
+            }
+        }
+        self.play_ids = index
+            .into_iter()
+            .map(|(play_id, (path, _))| (play_id, path.clone()))
+            .collect();
+    }
+
+    /// The parsed run in an entry, unless another file's copy is loaded instead
+    fn kept_run<'a>(&self, path: &Path, entry: &'a CacheEntry) -> Option<&'a RunMetrics> {
+        match &entry.file {
+            CachedFile::Parsed { run, recoveries }
+                if named_after_file(recoveries)
+                    || self.play_ids.get(&run.play_id).is_some_and(|p| p == path) =>
+            {
+                Some(run)
+            }
+            _ => None,
+        }
+    }
+
+    /// Re-parse the file behind one run
+    ///
+    /// The file is read again even if its modification time and size are
+    /// unchanged, and no other file is touched. A file that is gone or no
+    /// longer loads takes its run out of the cache, recorded as a removal
+    /// for delta requests.
+    pub fn reparse(&mut self, play_id: &str) -> Result<RunMetrics, ReparseError> {
+        let path = self
+            .play_ids
+            .get(play_id)
+            .cloned()
+            .ok_or(ReparseError::NotFound)?;
+        let now = Utc::now();
+        let character = self.entries[&path].character.clone();
+
+        let stamp = FileStamp::of(&path);
+        let result = match stamp {
+            None => {
+                self.entries.remove(&path);
+                Err(ReparseError::FileRemoved(path.clone()))
+            }
+            Some(stamp) if stamp.len > self.options.max_file_bytes => {
+                let message = format!(
+                    "File is {} bytes, over the {} byte limit",
+                    stamp.len, self.options.max_file_bytes
+                );
+                self.entries.remove(&path);
+                let issue = skip_issue(&path, message);
+                self.skipped.push(issue.clone());
+                Err(ReparseError::Invalid(issue))
+            }
+            Some(stamp) => {
+                let file = parse_cached(&path, &character);
+                let result = match &file {
+                    CachedFile::Parsed { run, .. } => Ok(run.as_ref().clone()),
+                    CachedFile::Failed(failure) => {
+                        Err(ReparseError::Invalid(failure_issue(&path, failure)))
+                    }
+                };
+                self.seq += 1;
+                let entry = CacheEntry {
+                    stamp,
+                    character,
+                    file,
+                    seq: self.seq,
+                    ingested_at: now,
+                };
+                self.entries.insert(path.clone(), entry);
+                result
+            }
+        };
+
+        if !matches!(&result, Ok(run) if run.play_id == play_id) {
+            self.record_removal_if_gone(play_id.to_string(), now);
+        }
+        // Another file may still provide the play_id
+        self.index_play_ids();
+        result
+    }
+
+    /// Record a removal unless another file still provides the play_id
+    fn record_removal_if_gone(&mut self, play_id: String, now: DateTime<Utc>) {
+        let still_present = self
+            .entries
+            .values()
+            .any(|e| matches!(&e.file, CachedFile::Parsed { run, .. } if run.play_id == play_id));
+        if !still_present {
+            self.seq += 1;
+            self.removals.push(Removal {
+                play_id,
+                seq: self.seq,
+                removed_at: now,
+            });
+            if self.removals.len() > MAX_REMOVALS {
+                let excess = self.removals.len() - MAX_REMOVALS;
+                self.dropped_removal = self.removals.drain(..excess).next_back();
+            }
+        }
+    }
+
+    /// Load report describing the cached files as of now
+    pub fn load_report(&self) -> LoadReport {
+        self.report(Utc::now())
+    }
+
+    /// Load report describing the cached files
+    ///
+    /// Skipped links and oversized files are listed in `issues` but don't count as parse failures.
+    fn report(&self, now: DateTime<Utc>) -> LoadReport {
+        let failures: Vec<LoadIssue> = self
+            .entries
+            .iter()
+            .filter_map(|(path, entry)| match &entry.file {
+                CachedFile::Failed(failure) => Some(failure_issue(path, failure)),
+                CachedFile::Parsed { .. } => None,
+            })
+            .collect();
+        let recovered = self
+            .entries
+            .iter()
+            .filter_map(|(path, entry)| match &entry.file {
+                CachedFile::Parsed { recoveries, .. } if !recoveries.is_empty() => {
+                    Some(RecoveredFile {
+                        path: path.to_string_lossy().to_string(),
+                        recoveries: recoveries.clone(),
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+
+        let duplicates = self
+            .entries
+            .iter()
+            .filter_map(|(path, entry)| match &entry.file {
+                CachedFile::Parsed { recoveries, .. } if named_after_file(recoveries) => None,
+                CachedFile::Parsed { run, .. } => {
+                    let kept = self.play_ids.get(&run.play_id)?;
+                    (kept != path).then(|| DuplicateRun {
+                        play_id: run.play_id.clone(),
+                        kept_path: kept.to_string_lossy().to_string(),
+                        ignored_path: path.to_string_lossy().to_string(),
+                    })
+                }
+                CachedFile::Failed(_) => None,
+            })
+            .collect();
+
+        let parse_failures = failures.len();
+        let mut issues = failures;
+        issues.extend(self.skipped.iter().cloned());
+
+        LoadReport {
+            runs_path: Some(self.root.to_string_lossy().to_string()),
+            files_scanned: self.entries.len() as i32,
+            runs_loaded: (self.entries.len() - parse_failures) as i32,
+            parse_failures: parse_failures as i32,
+            nested_files: self.nested_files as i32,
+            recovered,
+            duplicates,
+            issues,
+            loaded_at: now.timestamp(),
+        }
+    }
+
+    /// All cached runs, one per play_id
+    pub fn runs(&self) -> Vec<RunMetrics> {
+        self.entries
+            .iter()
+            .filter_map(|(path, entry)| self.kept_run(path, entry).cloned())
+            .collect()
+    }
+
+    /// Runs added, changed or removed after a cursor
+    ///
+    /// `None`, a cursor from another cache instance, or one older than the
+    /// removals still kept (see [`MAX_REMOVALS`]) yields every run with
+    /// `reset` set.
+    pub fn delta(&self, since: Option<&DeltaCursor>) -> RunDelta {
+        let is_after = |seq: u64, time: DateTime<Utc>, since: &DeltaCursor| match since {
+            DeltaCursor::Sequence { seq: cursor, .. } => seq > *cursor,
+            DeltaCursor::Timestamp(cursor) => time > *cursor,
+        };
+        let since = match since {
+            Some(DeltaCursor::Sequence { epoch, .. }) if *epoch != self.epoch => None,
+            Some(cursor)
+                if self
+                    .dropped_removal
+                    .as_ref()
+                    .is_some_and(|r| is_after(r.seq, r.removed_at, cursor)) =>
+            {
+                None
+            }
+            other => other,
+        };
+        let Some(since) = since else {
+            return RunDelta {
+                cursor: self.cursor(),
+                reset: true,
+                runs: self.runs(),
+                removed: Vec::new(),
+            };
+        };
+
+        let runs: Vec<RunMetrics> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| is_after(entry.seq, entry.ingested_at, since))
+            .filter_map(|(path, entry)| self.kept_run(path, entry).cloned())
+            .collect();
+
+        let current = self.runs();
+        let mut removed: Vec<String> = self
+            .removals
+            .iter()
+            .filter(|r| is_after(r.seq, r.removed_at, since))
+            .filter(|r| !current.iter().any(|run| run.play_id == r.play_id))
+            .map(|r| r.play_id.clone())
+            .collect();
+        removed.sort();
+        removed.dedup();
+
+        RunDelta {
+            cursor: self.cursor(),
+            reset: false,
+            runs,
+            removed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::RunFileBuilder;
+
+    fn write_run(root: &Path, name: &str, play_id: &str) {
+        RunFileBuilder::new(play_id)
+            .floor_reached(10)
+            .file_name(name)
+            .write_to(root)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_oversized_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+        let char_dir = dir.path().join("IRONCLAD");
+        let mut big = serde_json::json!({ "play_id": "big" })
+            .to_string()
+            .into_bytes();
+        big.extend(std::iter::repeat_n(b' ', 4096));
+        std::fs::write(char_dir.join("big.run"), big).unwrap();
+
+        let options = ScanOptions {
+            max_file_bytes: 1024,
+            ..ScanOptions::DEFAULT
+        };
+        let mut cache = RunCache::with_options(dir.path(), options);
+        let report = cache.refresh();
+        assert_eq!(report.runs_loaded, 1);
+        assert_eq!(report.parse_failures, 0);
+        assert_eq!(report.issues.len(), 1);
+        assert!(report.issues[0].path.ends_with("big.run"));
+        assert!(report.issues[0]
+            .message
+            .contains("over the 1024 byte limit"));
+    }
+
+    #[test]
+    fn test_report_lists_repaired_and_broken_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let char_dir = dir.path().join("IRONCLAD");
+        std::fs::create_dir_all(&char_dir).unwrap();
+        let fixtures: [(&str, &[u8]); 3] = [
+            (
+                "bom.run",
+                include_bytes!("../../tests/fixtures/runs/bom.run"),
+            ),
+            (
+                "trailing_nul.run",
+                include_bytes!("../../tests/fixtures/runs/trailing_nul.run"),
+            ),
+            (
+                "truncated.run",
+                include_bytes!("../../tests/fixtures/runs/truncated.run"),
+            ),
+        ];
+        for (name, bytes) in fixtures {
+            std::fs::write(char_dir.join(name), bytes).unwrap();
+        }
+
+        let mut cache = RunCache::new(dir.path());
+        let report = cache.refresh();
+        assert_eq!(report.runs_loaded, 2);
+        assert_eq!(report.recovered.len(), 2);
+        assert_eq!(report.parse_failures, 1);
+        let issue = &report.issues[0];
+        assert!(issue.path.ends_with("truncated.run"));
+        assert!(issue.line.is_some() && issue.column.is_some());
+    }
+
+    #[test]
+    fn test_cursor_parsing() {
+        assert_eq!(
+            DeltaCursor::parse("1700000000000:42"),
+            Ok(DeltaCursor::Sequence {
+                epoch: 1_700_000_000_000,
+                seq: 42
+            })
+        );
+        assert!(matches!(
+            DeltaCursor::parse("2024-01-01T00:00:00Z"),
+            Ok(DeltaCursor::Timestamp(_))
+        ));
+        assert!(DeltaCursor::parse("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_refresh_only_reparses_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+        std::fs::write(dir.path().join("IRONCLAD").join("bad.run"), "{").unwrap();
+
+        let mut cache = RunCache::new(dir.path());
+        let report = cache.refresh();
+        assert_eq!(report.files_scanned, 2);
+        assert_eq!(report.runs_loaded, 1);
+        assert_eq!(report.parse_failures, 1);
+
+        let cursor = cache.cursor();
+        let report = cache.refresh();
+        assert_eq!(cache.cursor(), cursor);
+        assert_eq!(report.parse_failures, 1);
+    }
+
+    #[test]
+    fn test_old_cursors_reset_once_removals_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+        let mut cache = RunCache::new(dir.path());
+        cache.refresh();
+        let old = DeltaCursor::parse(&cache.cursor()).unwrap();
+
+        for i in 0..MAX_REMOVALS {
+            cache.record_removal_if_gone(format!("gone-{}", i), Utc::now());
+        }
+        assert!(!cache.delta(Some(&old)).reset);
+
+        let recent = DeltaCursor::parse(&cache.cursor()).unwrap();
+        cache.record_removal_if_gone("one-more".to_string(), Utc::now());
+        assert_eq!(cache.removals.len(), MAX_REMOVALS);
+        assert!(cache.delta(Some(&old)).reset);
+        let delta = cache.delta(Some(&recent));
+        assert!(!delta.reset);
+        assert_eq!(delta.removed, ["one-more"]);
+    }
+
+    #[test]
+    fn test_delta_add_then_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+
+        let mut cache = RunCache::new(dir.path());
+        cache.refresh();
+        let initial = cache.delta(None);
+        assert!(initial.reset);
+        assert_eq!(initial.runs.len(), 1);
+
+        // A new run shows up in the next delta
+        write_run(dir.path(), "b.run", "b");
+        cache.refresh();
+        let cursor = DeltaCursor::parse(&initial.cursor).unwrap();
+        let added = cache.delta(Some(&cursor));
+        assert!(!added.reset);
+        assert_eq!(added.runs.len(), 1);
+        assert_eq!(added.runs[0].play_id, "b");
+        assert!(added.removed.is_empty());
+
+        // Deleting it is reported as a removal
+        std::fs::remove_file(dir.path().join("IRONCLAD").join("b.run")).unwrap();
+        cache.refresh();
+        let cursor = DeltaCursor::parse(&added.cursor).unwrap();
+        let deleted = cache.delta(Some(&cursor));
+        assert!(deleted.runs.is_empty());
+        assert_eq!(deleted.removed, vec!["b".to_string()]);
+
+        // Nothing changed since the latest cursor
+        let cursor = DeltaCursor::parse(&deleted.cursor).unwrap();
+        let empty = cache.delta(Some(&cursor));
+        assert!(empty.runs.is_empty() && empty.removed.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follows_symlinked_dirs_and_files() {
+        use std::os::unix::fs::symlink;
+
+        // Runs directory whose character folder is a link into a "synced" folder
+        let synced = tempfile::tempdir().unwrap();
+        write_run(synced.path(), "a.run", "a");
+        write_run(synced.path(), "b.run", "b");
+        let root = tempfile::tempdir().unwrap();
+        symlink(synced.path().join("IRONCLAD"), root.path().join("IRONCLAD")).unwrap();
+
+        // A linked run file without the extension on the link name, plus a dangling link
+        let silent = root.path().join("THE_SILENT");
+        std::fs::create_dir_all(&silent).unwrap();
+        let other = tempfile::tempdir().unwrap();
+        let target = other.path().join("c.run");
+        std::fs::write(&target, r#"{"play_id": "c"}"#).unwrap();
+        symlink(&target, silent.join("linked")).unwrap();
+        symlink(other.path().join("gone.run"), silent.join("gone.run")).unwrap();
+
+        let mut cache = RunCache::new(root.path());
+        let report = cache.refresh();
+        let mut ids: Vec<String> = cache.runs().into_iter().map(|r| r.play_id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(report.parse_failures, 0);
+        assert_eq!(report.issues.len(), 1);
+        assert!(report.issues[0].path.ends_with("gone.run"));
+        assert!(report.issues[0].message.starts_with("Dangling link"));
+    }
+
+    #[test]
+    fn test_recursive_scan_finds_archived_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+        write_run(&dir.path().join("IRONCLAD").join("2023"), "b.run", "b");
+        write_run(
+            &dir.path().join("IRONCLAD").join("2023").join("old"),
+            "c.run",
+            "c",
+        );
+        write_run(&dir.path().join("IRONCLAD").join(".trash"), "d.run", "d");
+
+        let mut flat = RunCache::new(dir.path());
+        let report = flat.refresh();
+        assert_eq!(report.runs_loaded, 1);
+        assert_eq!(report.nested_files, 0);
+
+        let mut cache = RunCache::with_options(
+            dir.path(),
+            ScanOptions {
+                recursive: true,
+                ..ScanOptions::DEFAULT
+            },
+        );
+        let report = cache.refresh();
+        let mut ids: Vec<String> = cache.runs().into_iter().map(|r| r.play_id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(report.nested_files, 2);
+        assert!(cache
+            .runs()
+            .iter()
+            .all(|r| r.character == Character::Ironclad));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recursive_scan_survives_directory_loops() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+        let char_dir = dir.path().join("IRONCLAD");
+        symlink(&char_dir, char_dir.join("loop")).unwrap();
+
+        let mut cache = RunCache::with_options(
+            dir.path(),
+            ScanOptions {
+                recursive: true,
+                ..ScanOptions::DEFAULT
+            },
+        );
+        let report = cache.refresh();
+        assert_eq!(cache.runs().len(), 1);
+        assert!(report.issues.iter().any(|i| i.message == "Symlink cycle"));
+    }
+
+    /// Overwrite a run file without changing its size or modification time,
+    /// like restoring it from a backup
+    fn restore_run(path: &Path, contents: &str) {
+        let modified = std::fs::metadata(path).unwrap().modified().unwrap();
+        assert_eq!(
+            std::fs::metadata(path).unwrap().len(),
+            contents.len() as u64
+        );
+        std::fs::write(path, contents).unwrap();
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn test_reparse_picks_up_restored_file() {
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+        write_run(dir.path(), "b.run", "b");
+        let mut cache = RunCache::new(dir.path());
+        cache.refresh();
+        let cursor = DeltaCursor::parse(&cache.cursor()).unwrap();
+
+        let path = dir.path().join("IRONCLAD").join("a.run");
+        let edited = RunFileBuilder::new("a").floor_reached(42).to_json();
+        restore_run(&path, &edited.to_string());
+        cache.refresh();
+        assert_eq!(cache.delta(Some(&cursor)).runs.len(), 0);
+
+        let run = cache.reparse("a").unwrap();
+        assert_eq!(run.floor_reached, 42);
+        let delta = cache.delta(Some(&cursor));
+        assert_eq!(delta.runs.len(), 1);
+        assert_eq!(delta.runs[0].play_id, "a");
+        assert!(delta.removed.is_empty());
+        let floors: Vec<i32> = cache.runs().iter().map(|r| r.floor_reached).collect();
+        assert_eq!(floors, [42, 10]);
+    }
+
+    #[test]
+    fn test_reparse_drops_broken_and_removed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+        write_run(dir.path(), "b.run", "b");
+        let mut cache = RunCache::new(dir.path());
+        cache.refresh();
+        let cursor = DeltaCursor::parse(&cache.cursor()).unwrap();
+
+        let char_dir = dir.path().join("IRONCLAD");
+        std::fs::write(char_dir.join("a.run"), "{\n  \"play_id\": ").unwrap();
+        let Err(ReparseError::Invalid(issue)) = cache.reparse("a") else {
+            panic!("expected a parse error");
+        };
+        assert!(issue.path.ends_with("a.run"));
+        assert_eq!(issue.line, Some(2));
+        assert_eq!(cache.load_report().parse_failures, 1);
+
+        std::fs::remove_file(char_dir.join("b.run")).unwrap();
+        assert!(matches!(
+            cache.reparse("b"),
+            Err(ReparseError::FileRemoved(path)) if path.ends_with("b.run")
+        ));
+        assert!(matches!(cache.reparse("b"), Err(ReparseError::NotFound)));
+        assert!(cache.runs().is_empty());
+        let mut removed = cache.delta(Some(&cursor)).removed;
+        removed.sort();
+        assert_eq!(removed, ["a", "b"]);
+    }
+
+    #[test]
+    fn test_reparse_follows_changed_play_id() {
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+        let mut cache = RunCache::new(dir.path());
+        cache.refresh();
+
+        write_run(dir.path(), "a.run", "c");
+        assert_eq!(cache.reparse("a").unwrap().play_id, "c");
+        assert!(matches!(cache.reparse("a"), Err(ReparseError::NotFound)));
+        assert_eq!(cache.reparse("c").unwrap().play_id, "c");
+        assert_eq!(cache.delta(None).runs.len(), 1);
+    }
+
+    #[test]
+    fn test_runs_record_their_source_file() {
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+        let path = dir.path().join("IRONCLAD").join("a.run");
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        let mut cache = RunCache::new(dir.path());
+        cache.refresh();
+        let run = &cache.runs()[0];
+        assert_eq!(
+            run.source_path.as_deref(),
+            Some(path.to_string_lossy().as_ref())
+        );
+        assert_eq!(
+            run.file_modified_at,
+            Some(DateTime::<Utc>::from(modified).timestamp())
+        );
+    }
+
+    #[test]
+    fn test_runs_named_after_files_are_not_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str| {
+            RunFileBuilder::new("unused")
+                .remove("play_id")
+                .file_name(name)
+                .write_to(dir.path())
+                .unwrap()
+        };
+        file("1700000000.run");
+        file("backup/1700000000.run");
+
+        let mut cache = RunCache::with_options(
+            dir.path(),
+            ScanOptions {
+                recursive: true,
+                ..ScanOptions::DEFAULT
+            },
+        );
+        let report = cache.refresh();
+        assert!(report.duplicates.is_empty());
+        assert_eq!(report.recovered.len(), 2);
+        assert_eq!(
+            report.recovered[0].recoveries,
+            [Recovery::PlayIdFromFileName]
+        );
+        let runs = cache.runs();
+        assert_eq!(runs.len(), 2);
+        assert!(runs.iter().all(|r| r.play_id == "1700000000"));
+    }
+
+    #[test]
+    fn test_duplicate_play_ids_keep_newest_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let char_dir = dir.path().join("IRONCLAD");
+        write_run(dir.path(), "a.run", "a");
+        write_run(dir.path(), "b.run", "b");
+        std::fs::create_dir(char_dir.join("backup")).unwrap();
+        std::fs::copy(
+            char_dir.join("a.run"),
+            char_dir.join("backup").join("a.run"),
+        )
+        .unwrap();
+        let older = SystemTime::now() - std::time::Duration::from_secs(3600);
+        let file = std::fs::File::options()
+            .write(true)
+            .open(char_dir.join("a.run"))
+            .unwrap();
+        file.set_modified(older).unwrap();
+
+        let mut cache = RunCache::with_options(
+            dir.path(),
+            ScanOptions {
+                recursive: true,
+                ..ScanOptions::DEFAULT
+            },
+        );
+        let report = cache.refresh();
+        assert_eq!(report.runs_loaded, 3);
+        let kept = char_dir.join("backup").join("a.run");
+        assert_eq!(
+            report.duplicates,
+            [DuplicateRun {
+                play_id: "a".to_string(),
+                kept_path: kept.to_string_lossy().to_string(),
+                ignored_path: char_dir.join("a.run").to_string_lossy().to_string(),
+            }]
+        );
+        let runs = cache.runs();
+        assert_eq!(runs.len(), 2);
+        let a = runs.iter().find(|r| r.play_id == "a").unwrap();
+        assert_eq!(
+            a.source_path.as_deref(),
+            Some(kept.to_string_lossy().as_ref())
+        );
+        assert_eq!(cache.delta(None).runs.len(), 2);
+
+        // The newer copy going away brings back the older one
+        std::fs::remove_file(&kept).unwrap();
+        let report = cache.refresh();
+        assert!(report.duplicates.is_empty());
+        let older_path = char_dir.join("a.run");
+        assert_eq!(
+            cache.reparse("a").unwrap().source_path.as_deref(),
+            Some(older_path.to_string_lossy().as_ref())
+        );
+    }
+
+    #[test]
+    fn test_delta_with_foreign_cursor_resets() {
+        let dir = tempfile::tempdir().unwrap();
+        write_run(dir.path(), "a.run", "a");
+        let mut cache = RunCache::new(dir.path());
+        cache.refresh();
+
+        let stale = DeltaCursor::Sequence { epoch: 1, seq: 0 };
+        let delta = cache.delta(Some(&stale));
+        assert!(delta.reset);
+        assert_eq!(delta.runs.len(), 1);
+    }
+}
@@ -0,0 +1,127 @@
+//! Daily activity calendar
+//!
+//! Runs are bucketed by the local day they ended on, see [`TimeBucketing`],
+//! to drive a contribution-style heatmap.
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use utoipa::ToSchema;
+
+use super::{RunMetrics, TimeBucketing};
+
+/// Activity on one day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CalendarDay {
+    /// Local date (YYYY-MM-DD)
+    pub date: String,
+    /// Runs that ended on this day
+    pub runs: i32,
+    /// Victories among those runs
+    pub wins: i32,
+    /// Total time played in those runs, in seconds
+    pub playtime: i64,
+}
+
+/// One entry per day of `year`, including days without runs
+///
+/// Runs without a timestamp or local time are left out.
+pub fn calendar(runs: &[RunMetrics], year: i32, bucketing: TimeBucketing) -> Vec<CalendarDay> {
+    let mut by_day: HashMap<NaiveDate, (i32, i32, i64)> = HashMap::new();
+    for run in runs {
+        let Some(date) = bucketing.day(run).filter(|d| d.year() == year) else {
+            continue;
+        };
+        let day = by_day.entry(date).or_default();
+        day.0 += 1;
+        day.1 += i32::from(run.victory);
+        day.2 += i64::from(run.playtime);
+    }
+
+    let Some(first) = NaiveDate::from_ymd_opt(year, 1, 1) else {
+        return Vec::new();
+    };
+    first
+        .iter_days()
+        .take_while(|d| d.year() == year)
+        .map(|date| {
+            let (runs, wins, playtime) = by_day.get(&date).copied().unwrap_or_default();
+            CalendarDay {
+                date: date.format("%Y-%m-%d").to_string(),
+                runs,
+                wins,
+                playtime,
+            }
+        })
+        .collect()
+}
+
+/// Years with at least one run, oldest first
+pub fn calendar_years(runs: &[RunMetrics], bucketing: TimeBucketing) -> Vec<i32> {
+    runs.iter()
+        .filter_map(|run| bucketing.day(run))
+        .map(|date| date.year())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Character;
+
+    /// 2024-01-01T03:00:00Z
+    const NEW_YEAR_3AM_UTC: i64 = 1_704_078_000;
+
+    fn tz(offset: i32) -> TimeBucketing {
+        TimeBucketing::new(offset).unwrap()
+    }
+
+    fn run_at(play_id: &str, timestamp: i64, victory: bool) -> RunMetrics {
+        let mut run = sample_run(play_id, Character::Ironclad, victory);
+        run.timestamp = Some(timestamp);
+        run.playtime = 1800;
+        run
+    }
+
+    #[test]
+    fn test_calendar_covers_every_day() {
+        assert_eq!(calendar(&[], 2023, tz(0)).len(), 365);
+        let days = calendar(&[], 2024, tz(0));
+        assert_eq!(days.len(), 366);
+        assert_eq!(days[0].date, "2024-01-01");
+        assert_eq!(days[365].date, "2024-12-31");
+        assert!(days.iter().all(|d| d.runs == 0));
+    }
+
+    #[test]
+    fn test_negative_offset_moves_run_to_new_years_eve() {
+        let runs = vec![
+            run_at("a", NEW_YEAR_3AM_UTC, true),
+            run_at("b", NEW_YEAR_3AM_UTC + 3 * 3600, false),
+        ];
+
+        // UTC-5: the first run ended at 22:00 on Dec 31st, the second at 01:00
+        let days_2023 = calendar(&runs, 2023, tz(-300));
+        let eve = days_2023.last().unwrap();
+        assert_eq!(eve.date, "2023-12-31");
+        assert_eq!((eve.runs, eve.wins, eve.playtime), (1, 1, 1800));
+
+        let days_2024 = calendar(&runs, 2024, tz(-300));
+        assert_eq!((days_2024[0].runs, days_2024[0].wins), (1, 0));
+
+        // In UTC both runs are on New Year's Day
+        assert_eq!(calendar(&runs, 2024, tz(0))[0].runs, 2);
+        assert_eq!(calendar_years(&runs, tz(-300)), vec![2023, 2024]);
+        assert_eq!(calendar_years(&runs, tz(0)), vec![2024]);
+    }
+
+    #[test]
+    fn test_runs_without_timestamp_are_ignored() {
+        let runs = vec![sample_run("a", Character::Ironclad, true)];
+        assert!(calendar_years(&runs, tz(0)).is_empty());
+        assert!(calendar(&runs, 2024, tz(0)).iter().all(|d| d.runs == 0));
+    }
+}
@@ -0,0 +1,252 @@
+//! Stats before and after a date
+//!
+//! Runs are split by the local day they ended on (see [`TimeBucketing`]), so
+//! a run lands on the same side of the split as on the calendar. Each side
+//! can be limited to a number of days next to the split instead of reaching
+//! back to the first run or forward to the latest.
+
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{calculate_character_stats, mean, CharacterStats, RunMetrics, TimeBucketing};
+
+/// Results for the runs on one side of the split
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PeriodStats {
+    /// First day included (None when the period reaches back to the first run)
+    pub first_day: Option<NaiveDate>,
+    /// Last day included (None when the period runs up to the latest run)
+    pub last_day: Option<NaiveDate>,
+    pub runs: i32,
+    pub wins: i32,
+    /// `wins / runs`, or 0 without runs
+    pub win_rate: f64,
+    /// Average floor reached (0 without runs)
+    pub avg_floor: f64,
+    /// Fewer runs than the minimum, so differences say little
+    pub low_confidence: bool,
+    /// Full stats of each character with runs in the period
+    pub characters: Vec<CharacterStats>,
+}
+
+/// How the period after the split differs from the one before
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PeriodDelta {
+    /// Runs after minus runs before
+    pub runs: i32,
+    /// Win rate after minus win rate before
+    pub win_rate: f64,
+    /// Average floor after minus average floor before
+    pub avg_floor: f64,
+}
+
+/// Stats before and after a split date
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PeriodComparison {
+    /// First day of the period after the split
+    pub split: NaiveDate,
+    /// Days on each side of the split (None for open-ended periods)
+    pub window_days: Option<u32>,
+    /// Runs a period needs to not be flagged low-confidence
+    pub min_runs: i32,
+    /// Runs that ended before the split
+    pub before: PeriodStats,
+    /// Runs that ended on or after the split
+    pub after: PeriodStats,
+    /// `after` minus `before`
+    pub delta: PeriodDelta,
+    /// Runs without a recorded end time, left out of both periods
+    pub undated_runs: i32,
+    /// Reasons the comparison may not be meaningful
+    pub warnings: Vec<String>,
+}
+
+fn period_stats(
+    runs: &[RunMetrics],
+    first_day: Option<NaiveDate>,
+    last_day: Option<NaiveDate>,
+    min_runs: i32,
+) -> PeriodStats {
+    let total = runs.len() as i32;
+    let wins = runs.iter().filter(|r| r.victory).count() as i32;
+    PeriodStats {
+        first_day,
+        last_day,
+        runs: total,
+        wins,
+        win_rate: if total > 0 {
+            wins as f64 / total as f64
+        } else {
+            0.0
+        },
+        avg_floor: mean(runs.iter().map(|r| r.stats_floor())),
+        low_confidence: total < min_runs,
+        characters: calculate_character_stats(runs),
+    }
+}
+
+impl PeriodComparison {
+    /// Compare runs before `split` with runs on or after it, ignoring hidden
+    /// runs
+    ///
+    /// With `window_days`, only runs within that many days of the split count
+    /// on each side.
+    pub fn from_runs(
+        runs: &[RunMetrics],
+        split: NaiveDate,
+        window_days: Option<u32>,
+        bucketing: TimeBucketing,
+        min_runs: i32,
+    ) -> Self {
+        let days = window_days.map(|n| Days::new(n.into()));
+        let first_day = days.map(|d| split.checked_sub_days(d).unwrap_or(NaiveDate::MIN));
+        let end = days.map(|d| split.checked_add_days(d).unwrap_or(NaiveDate::MAX));
+
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        let mut undated_runs = 0;
+        for run in runs.iter().filter(|r| !r.hidden) {
+            let Some(day) = bucketing.day(run) else {
+                undated_runs += 1;
+                continue;
+            };
+            if day < split {
+                if first_day.is_none_or(|first| day >= first) {
+                    before.push(run.clone());
+                }
+            } else if end.is_none_or(|end| day < end) {
+                after.push(run.clone());
+            }
+        }
+
+        let before = period_stats(&before, first_day, split.pred_opt(), min_runs);
+        let after = period_stats(
+            &after,
+            Some(split),
+            end.and_then(|e| e.pred_opt()),
+            min_runs,
+        );
+        let mut warnings = Vec::new();
+        for (side, period) in [("before", &before), ("after", &after)] {
+            if period.low_confidence {
+                warnings.push(format!(
+                    "Only {} runs {} the split; at least {} are needed for a meaningful comparison",
+                    period.runs, side, min_runs
+                ));
+            }
+        }
+
+        Self {
+            split,
+            window_days,
+            min_runs,
+            delta: PeriodDelta {
+                runs: after.runs - before.runs,
+                win_rate: after.win_rate - before.win_rate,
+                avg_floor: after.avg_floor - before.avg_floor,
+            },
+            before,
+            after,
+            undated_runs,
+            warnings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Character;
+
+    /// 2024-06-01T12:00:00Z
+    const SPLIT_NOON_UTC: i64 = 1_717_243_200;
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn run_on(id: &str, days_from_split: i64, victory: bool) -> RunMetrics {
+        let mut run = sample_run(id, Character::TheSilent, victory);
+        run.timestamp = Some(SPLIT_NOON_UTC + days_from_split * DAY);
+        run.local_time = None;
+        run
+    }
+
+    fn split() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+    }
+
+    /// One win in four before the split, three in four after
+    fn runs() -> Vec<RunMetrics> {
+        let mut runs = vec![
+            run_on("b1", -40, true),
+            run_on("b2", -20, false),
+            run_on("b3", -3, false),
+            run_on("b4", -1, false),
+            run_on("a1", 0, true),
+            run_on("a2", 2, true),
+            run_on("a3", 10, false),
+            run_on("a4", 40, true),
+        ];
+        let mut undated = sample_run("u", Character::TheSilent, true);
+        undated.timestamp = None;
+        undated.local_time = None;
+        runs.push(undated);
+        let mut hidden = run_on("h", 1, false);
+        hidden.hidden = true;
+        runs.push(hidden);
+        runs
+    }
+
+    #[test]
+    fn test_compares_before_and_after_split() {
+        let comparison =
+            PeriodComparison::from_runs(&runs(), split(), None, TimeBucketing::default(), 4);
+        assert_eq!((comparison.before.runs, comparison.before.wins), (4, 1));
+        assert_eq!((comparison.after.runs, comparison.after.wins), (4, 3));
+        assert_eq!(comparison.delta.win_rate, 0.5);
+        assert_eq!(comparison.delta.runs, 0);
+        assert_eq!(comparison.undated_runs, 1);
+        assert_eq!(
+            comparison.before.last_day,
+            NaiveDate::from_ymd_opt(2024, 5, 31)
+        );
+        assert_eq!(comparison.after.first_day, Some(split()));
+        assert_eq!(
+            (comparison.before.first_day, comparison.after.last_day),
+            (None, None)
+        );
+        assert_eq!(comparison.after.characters.len(), 1);
+        assert_eq!(comparison.after.characters[0].total_runs, 4);
+        assert!(comparison.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_window_days_and_small_sample_warnings() {
+        let comparison =
+            PeriodComparison::from_runs(&runs(), split(), Some(7), TimeBucketing::default(), 4);
+        // b3, b4 before; a1, a2 after
+        assert_eq!((comparison.before.runs, comparison.before.wins), (2, 0));
+        assert_eq!((comparison.after.runs, comparison.after.wins), (2, 2));
+        assert_eq!(comparison.delta.win_rate, 1.0);
+        assert_eq!(
+            comparison.before.first_day,
+            NaiveDate::from_ymd_opt(2024, 5, 25)
+        );
+        assert_eq!(
+            comparison.after.last_day,
+            NaiveDate::from_ymd_opt(2024, 6, 7)
+        );
+        assert!(comparison.before.low_confidence && comparison.after.low_confidence);
+        assert_eq!(comparison.warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_split_follows_local_day() {
+        // 23:30 UTC on May 31st is already June 1st at UTC+1
+        let mut late = run_on("late", 0, true);
+        late.timestamp = Some(SPLIT_NOON_UTC - DAY + 11 * 60 * 60 + 30 * 60);
+        let utc_plus_one = TimeBucketing::new(60).unwrap();
+        let comparison = PeriodComparison::from_runs(&[late], split(), None, utc_plus_one, 1);
+        assert_eq!((comparison.before.runs, comparison.after.runs), (0, 1));
+    }
+}
@@ -0,0 +1,228 @@
+//! CSV export
+//!
+//! Runs are flattened to one row each: scalar fields as columns, the relic
+//! and deck lists joined with `;`. Character stats and run feature vectors
+//! get their own tables.
+//! Fields are quoted per RFC 4180 when they contain a comma, quote or newline.
+
+use super::{CharacterStats, FeatureSet, RunMetrics};
+
+/// Separator used to join list fields into a single column
+pub const LIST_SEPARATOR: &str = ";";
+
+/// Column headers of [`runs_csv`]
+pub const RUN_COLUMNS: &[&str] = &[
+    "play_id",
+    "character",
+    "victory",
+    "floor_reached",
+    "score",
+    "ascension_level",
+    "timestamp",
+    "playtime",
+    "deck_size",
+    "attack_count",
+    "skill_count",
+    "power_count",
+    "upgraded_cards",
+    "cards_removed",
+    "cards_added",
+    "relic_count",
+    "elites_killed",
+    "bosses_killed",
+    "campfires_rested",
+    "campfires_upgraded",
+    "shops_visited",
+    "cards_purchased",
+    "potions_used",
+    "total_damage_taken",
+    "max_hp_at_end",
+    "neow_bonus",
+    "killed_by",
+    "hidden",
+    "relics",
+    "master_deck",
+];
+
+/// Column headers of [`character_stats_csv`]
+pub const STATS_COLUMNS: &[&str] = &[
+    "character",
+    "display_name",
+    "total_runs",
+    "wins",
+    "win_rate",
+    "avg_score",
+    "avg_floor",
+    "max_floor",
+    "avg_deck_size",
+    "avg_relics",
+];
+
+/// Quote a field if it contains a separator, quote or line break
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn push_row<S: AsRef<str>>(out: &mut String, fields: &[S]) {
+    let row: Vec<String> = fields.iter().map(|f| escape(f.as_ref())).collect();
+    out.push_str(&row.join(","));
+    out.push_str("\r\n");
+}
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(T::to_string).unwrap_or_default()
+}
+
+/// One CSV row for a run, matching [`RUN_COLUMNS`]
+fn run_row(run: &RunMetrics) -> Vec<String> {
+    vec![
+        run.play_id.clone(),
+        run.character.to_string(),
+        run.victory.to_string(),
+        run.floor_reached.to_string(),
+        run.score.to_string(),
+        run.ascension_level.to_string(),
+        optional(&run.timestamp),
+        run.playtime.to_string(),
+        run.deck_size.to_string(),
+        run.attack_count.to_string(),
+        run.skill_count.to_string(),
+        run.power_count.to_string(),
+        run.upgraded_cards.to_string(),
+        run.cards_removed.to_string(),
+        run.cards_added.to_string(),
+        run.relic_count.to_string(),
+        run.elites_killed.to_string(),
+        run.bosses_killed.to_string(),
+        run.campfires_rested.to_string(),
+        run.campfires_upgraded.to_string(),
+        run.shops_visited.to_string(),
+        run.cards_purchased.to_string(),
+        run.potions_used.to_string(),
+        run.total_damage_taken.to_string(),
+        run.max_hp_at_end.to_string(),
+        optional(&run.neow_bonus),
+        optional(&run.killed_by),
+        run.hidden.to_string(),
+        run.relics.join(LIST_SEPARATOR),
+        run.master_deck.join(LIST_SEPARATOR),
+    ]
+}
+
+/// Runs as CSV, one row per run after a header row
+pub fn runs_csv(runs: &[RunMetrics]) -> String {
+    let mut out = String::new();
+    push_row(&mut out, RUN_COLUMNS);
+    for run in runs {
+        push_row(&mut out, &run_row(run));
+    }
+    out
+}
+
+/// Character stats as CSV, one row per character after a header row
+///
+/// Rates and averages are written with four decimals.
+pub fn character_stats_csv(stats: &[CharacterStats]) -> String {
+    let mut out = String::new();
+    push_row(&mut out, STATS_COLUMNS);
+    for s in stats {
+        let decimals = |v: f64| format!("{:.4}", v);
+        let row = vec![
+            s.character.clone(),
+            s.display_name.clone(),
+            s.total_runs.to_string(),
+            s.wins.to_string(),
+            decimals(s.win_rate),
+            decimals(s.avg_score),
+            decimals(s.avg_floor),
+            s.max_floor.to_string(),
+            decimals(s.avg_deck_size),
+            decimals(s.avg_relics),
+        ];
+        push_row(&mut out, &row);
+    }
+    out
+}
+
+/// Feature vectors as CSV: `play_id`, `character`, the feature columns and
+/// the `victory` label (1 or 0)
+pub fn features_csv(set: &FeatureSet) -> String {
+    let mut out = String::new();
+    let mut header = vec!["play_id".to_string(), "character".to_string()];
+    header.extend(set.columns.iter().cloned());
+    header.push("victory".to_string());
+    push_row(&mut out, &header);
+    for row in &set.rows {
+        let mut fields = vec![row.play_id.clone(), row.character.to_string()];
+        fields.extend(row.features.iter().map(f64::to_string));
+        fields.push(i32::from(row.victory).to_string());
+        push_row(&mut out, &fields);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{calculate_character_stats, feature_set, Character, FEATURE_COLUMNS};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("Vajra"), "Vajra");
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_runs_csv_rows_match_header() {
+        let mut run = sample_run("1", Character::Ironclad, true);
+        run.killed_by = Some("Gremlin Nob, Sentries".to_string());
+        let csv = runs_csv(&[run]);
+
+        let lines: Vec<&str> = csv.split("\r\n").filter(|l| !l.is_empty()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].split(',').count(), RUN_COLUMNS.len());
+        assert!(lines[0].starts_with("play_id,character,victory"));
+        assert!(lines[1].starts_with("1,IRONCLAD,true,"));
+        assert!(lines[1].contains("\"Gremlin Nob, Sentries\""));
+    }
+
+    #[test]
+    fn test_features_csv() {
+        let mut run = sample_run("1", Character::TheSilent, false);
+        run.campfires_rested = 1;
+        run.campfires_upgraded = 3;
+        let csv = features_csv(&feature_set(&[run]));
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!("play_id,character,{},victory", FEATURE_COLUMNS.join(","))
+        );
+        let fields: Vec<&str> = lines[1].split(',').collect();
+        assert_eq!(fields.len(), FEATURE_COLUMNS.len() + 3);
+        assert_eq!(&fields[..2], ["1", "THE_SILENT"]);
+        assert!(fields.contains(&"0.75"));
+        assert_eq!(fields.last(), Some(&"0"));
+    }
+
+    #[test]
+    fn test_character_stats_csv() {
+        let runs = vec![
+            sample_run("1", Character::Ironclad, true),
+            sample_run("2", Character::Ironclad, false),
+        ];
+        let csv = character_stats_csv(&calculate_character_stats(&runs));
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], STATS_COLUMNS.join(","));
+        assert!(lines
+            .iter()
+            .any(|l| l.starts_with("IRONCLAD,Ironclad,2,1,0.5000,")));
+    }
+}
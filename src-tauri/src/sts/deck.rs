@@ -0,0 +1,207 @@
+//! Starter decks and relics, and how much of the deck survives a run
+//!
+//! A run's master deck is split into cards left over from the starter deck
+//! and cards added along the way. Upgraded starters (`Strike_R+1`) still
+//! count as starters, and duplicates are matched one for one, so a deck that
+//! removed two Strikes has three starter Strikes left.
+
+use std::collections::HashMap;
+
+use super::items::base_card_id;
+use super::Character;
+
+/// Card id of the curse added to the starter deck at Ascension 10+
+pub const ASCENDERS_BANE: &str = "AscendersBane";
+
+/// First ascension level that starts with Ascender's Bane
+pub const ASCENDERS_BANE_ASCENSION: i32 = 10;
+
+/// Card ids and counts in a character's starter deck (before ascension curses)
+pub fn starter_deck(character: Character) -> &'static [(&'static str, usize)] {
+    match character {
+        Character::Ironclad => &[("Strike_R", 5), ("Defend_R", 4), ("Bash", 1)],
+        Character::TheSilent => &[
+            ("Strike_G", 5),
+            ("Defend_G", 5),
+            ("Survivor", 1),
+            ("Neutralize", 1),
+        ],
+        Character::Defect => &[
+            ("Strike_B", 4),
+            ("Defend_B", 4),
+            ("Zap", 1),
+            ("Dualcast", 1),
+        ],
+        Character::Watcher => &[
+            ("Strike_P", 4),
+            ("Defend_P", 4),
+            ("Eruption", 1),
+            ("Vigilance", 1),
+        ],
+    }
+}
+
+/// Relic each character starts with
+pub fn starter_relic(character: Character) -> &'static str {
+    match character {
+        Character::Ironclad => "Burning Blood",
+        Character::TheSilent => "Ring of the Snake",
+        Character::Defect => "Cracked Core",
+        Character::Watcher => "PureWater",
+    }
+}
+
+/// Split a master deck into `(cards_added, starters_remaining)`
+///
+/// Modded characters have no known starter deck, so every card counts as added.
+pub fn starter_breakdown(
+    deck: &[String],
+    character: Option<Character>,
+    ascension_level: i32,
+) -> (i32, i32) {
+    let mut starters: HashMap<&str, usize> = character
+        .map(|c| starter_deck(c).iter().copied().collect())
+        .unwrap_or_default();
+    if ascension_level >= ASCENDERS_BANE_ASCENSION && !starters.is_empty() {
+        starters.insert(ASCENDERS_BANE, 1);
+    }
+
+    let mut remaining = 0;
+    for card in deck {
+        if let Some(count) = starters.get_mut(base_card_id(card)).filter(|n| **n > 0) {
+            *count -= 1;
+            remaining += 1;
+        }
+    }
+    (deck.len() as i32 - remaining, remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Name, master deck, ascension level and expected `(added, remaining)`
+    type Case = (&'static str, Vec<String>, i32, (i32, i32));
+
+    fn deck(cards: &[(&str, usize)]) -> Vec<String> {
+        cards
+            .iter()
+            .flat_map(|(card, n)| std::iter::repeat_n(card.to_string(), *n))
+            .collect()
+    }
+
+    #[test]
+    fn test_ironclad_breakdown() {
+        let cases: [Case; 5] = [
+            (
+                "untouched starter",
+                deck(&[("Strike_R", 5), ("Defend_R", 4), ("Bash", 1)]),
+                0,
+                (0, 10),
+            ),
+            (
+                "upgraded starters still count",
+                deck(&[
+                    ("Strike_R", 3),
+                    ("Strike_R+1", 2),
+                    ("Defend_R", 4),
+                    ("Bash+1", 1),
+                ]),
+                0,
+                (0, 10),
+            ),
+            (
+                "removed strikes and added cards",
+                deck(&[
+                    ("Strike_R", 3),
+                    ("Defend_R", 4),
+                    ("Bash", 1),
+                    ("Inflame", 1),
+                    ("Shrug It Off", 2),
+                ]),
+                0,
+                (3, 8),
+            ),
+            (
+                "extra copies beyond the starter count are added",
+                deck(&[("Strike_R", 6), ("Defend_R", 4), ("Bash", 2)]),
+                0,
+                (2, 10),
+            ),
+            (
+                "ascender's bane is a starter at A10+",
+                deck(&[
+                    ("Strike_R", 5),
+                    ("Defend_R", 4),
+                    ("Bash", 1),
+                    ("AscendersBane", 1),
+                ]),
+                10,
+                (0, 11),
+            ),
+        ];
+        for (name, cards, ascension, expected) in cases {
+            assert_eq!(
+                starter_breakdown(&cards, Some(Character::Ironclad), ascension),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_watcher_breakdown() {
+        let cases: [Case; 4] = [
+            (
+                "untouched starter",
+                deck(&[
+                    ("Strike_P", 4),
+                    ("Defend_P", 4),
+                    ("Eruption", 1),
+                    ("Vigilance", 1),
+                ]),
+                0,
+                (0, 10),
+            ),
+            (
+                "lean deck after removals",
+                deck(&[
+                    ("Strike_P", 1),
+                    ("Eruption+1", 1),
+                    ("Vigilance", 1),
+                    ("Rushdown", 1),
+                    ("Tantrum", 1),
+                ]),
+                5,
+                (2, 3),
+            ),
+            (
+                "ascender's bane below A10 is added",
+                deck(&[("Strike_P", 4), ("Defend_P", 4), ("AscendersBane", 1)]),
+                9,
+                (1, 8),
+            ),
+            (
+                "other characters' strikes are not starters",
+                deck(&[("Strike_R", 2), ("Defend_P", 4)]),
+                0,
+                (2, 4),
+            ),
+        ];
+        for (name, cards, ascension, expected) in cases {
+            assert_eq!(
+                starter_breakdown(&cards, Some(Character::Watcher), ascension),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_character_counts_everything_as_added() {
+        let cards = deck(&[("Strike_R", 5)]);
+        assert_eq!(starter_breakdown(&cards, None, 20), (5, 0));
+    }
+}
@@ -0,0 +1,211 @@
+//! Win rate by final deck size
+//!
+//! Runs are grouped by `deck_size` into ranges split at upper bounds, so the
+//! default bounds `20,25,30,35` give `<=20`, `21-25`, `26-30`, `31-35` and
+//! `36+`.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{mean, RunMetrics};
+
+/// Upper bounds of the default deck size ranges
+pub const DEFAULT_DECK_SIZE_BOUNDS: [i32; 4] = [20, 25, 30, 35];
+
+/// Runs whose deck ended within one size range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeckSizeBucket {
+    /// Range label, e.g. `<=20`, `21-25` or `36+`
+    pub label: String,
+    /// Smallest deck size in the range (None for the first range)
+    pub min_size: Option<i32>,
+    /// Largest deck size in the range (None for the last range)
+    pub max_size: Option<i32>,
+    pub runs: i32,
+    pub wins: i32,
+    /// Share of runs won, from 0 to 1 (0 without runs)
+    pub win_rate: f64,
+    /// Average floor reached (0 without runs)
+    pub avg_floor: f64,
+}
+
+/// Results by final deck size
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DeckSizeStats {
+    /// Runs included
+    pub runs: i32,
+    /// Average final deck size of winning runs
+    pub avg_deck_size_wins: f64,
+    /// Average final deck size of lost runs
+    pub avg_deck_size_losses: f64,
+    /// Every range, smallest decks first, including ranges without runs
+    pub buckets: Vec<DeckSizeBucket>,
+}
+
+/// Parse comma-separated upper bounds, e.g. `20,25,30,35`
+///
+/// Bounds must be non-negative, below `i32::MAX` (the range after the last
+/// bound starts one above it) and strictly increasing.
+pub fn parse_deck_size_bounds(value: &str) -> Result<Vec<i32>, String> {
+    let mut bounds = Vec::new();
+    for part in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let bound: i32 = part
+            .parse()
+            .ok()
+            .filter(|b| (0..i32::MAX).contains(b))
+            .ok_or_else(|| format!("Not a deck size: {}", part))?;
+        if let Some(last) = bounds.last().filter(|last| bound <= **last) {
+            return Err(format!(
+                "Bounds must increase, got {} after {}",
+                bound, last
+            ));
+        }
+        bounds.push(bound);
+    }
+    if bounds.is_empty() {
+        return Err("No bounds given".to_string());
+    }
+    Ok(bounds)
+}
+
+/// Ranges split at `bounds`, as `(min_size, max_size)`
+fn ranges(bounds: &[i32]) -> Vec<(Option<i32>, Option<i32>)> {
+    let mut ranges = Vec::with_capacity(bounds.len() + 1);
+    let mut min = None;
+    for &bound in bounds {
+        ranges.push((min, Some(bound)));
+        min = Some(bound + 1);
+    }
+    ranges.push((min, None));
+    ranges
+}
+
+fn label(range: (Option<i32>, Option<i32>)) -> String {
+    match range {
+        (None, Some(max)) => format!("<={}", max),
+        (Some(min), None) => format!("{}+", min),
+        (Some(min), Some(max)) if min == max => min.to_string(),
+        (Some(min), Some(max)) => format!("{}-{}", min, max),
+        (None, None) => "all".to_string(),
+    }
+}
+
+impl DeckSizeStats {
+    /// Group runs by deck size at `bounds`, ignoring hidden runs
+    ///
+    /// `bounds` are upper bounds in increasing order, see
+    /// [`parse_deck_size_bounds`].
+    pub fn from_runs(runs: &[RunMetrics], bounds: &[i32]) -> Self {
+        let visible: Vec<&RunMetrics> = runs.iter().filter(|r| !r.hidden).collect();
+        let buckets = ranges(bounds)
+            .into_iter()
+            .map(|range| {
+                let in_range: Vec<&&RunMetrics> = visible
+                    .iter()
+                    .filter(|r| {
+                        range.0.is_none_or(|min| r.deck_size >= min)
+                            && range.1.is_none_or(|max| r.deck_size <= max)
+                    })
+                    .collect();
+                let total = in_range.len() as i32;
+                let wins = in_range.iter().filter(|r| r.victory).count() as i32;
+                DeckSizeBucket {
+                    label: label(range),
+                    min_size: range.0,
+                    max_size: range.1,
+                    runs: total,
+                    wins,
+                    win_rate: if total > 0 {
+                        wins as f64 / total as f64
+                    } else {
+                        0.0
+                    },
+                    avg_floor: mean(in_range.iter().map(|r| r.stats_floor())),
+                }
+            })
+            .collect();
+
+        Self {
+            runs: visible.len() as i32,
+            avg_deck_size_wins: mean(visible.iter().filter(|r| r.victory).map(|r| r.deck_size)),
+            avg_deck_size_losses: mean(visible.iter().filter(|r| !r.victory).map(|r| r.deck_size)),
+            buckets,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Character;
+
+    fn run_with_deck(id: &str, deck_size: i32, victory: bool) -> RunMetrics {
+        let mut run = sample_run(id, Character::Ironclad, victory);
+        run.deck_size = deck_size;
+        run
+    }
+
+    #[test]
+    fn test_buckets_by_deck_size() {
+        let mut hidden = run_with_deck("hidden", 18, false);
+        hidden.hidden = true;
+        let runs = vec![
+            run_with_deck("a", 15, true),
+            run_with_deck("b", 20, true),
+            run_with_deck("c", 21, false),
+            run_with_deck("d", 25, true),
+            run_with_deck("e", 40, false),
+            run_with_deck("f", 36, false),
+            hidden,
+        ];
+
+        let stats = DeckSizeStats::from_runs(&runs, &DEFAULT_DECK_SIZE_BOUNDS);
+        let counts: Vec<(&str, i32, i32)> = stats
+            .buckets
+            .iter()
+            .map(|b| (b.label.as_str(), b.runs, b.wins))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("<=20", 2, 2),
+                ("21-25", 2, 1),
+                ("26-30", 0, 0),
+                ("31-35", 0, 0),
+                ("36+", 2, 0)
+            ]
+        );
+        assert_eq!(stats.runs, 6);
+        assert_eq!(stats.buckets[0].win_rate, 1.0);
+        assert_eq!(stats.buckets[1].avg_floor, 35.5);
+        assert_eq!(stats.buckets[2].win_rate, 0.0);
+        assert_eq!(
+            (stats.buckets[4].min_size, stats.buckets[4].max_size),
+            (Some(36), None)
+        );
+        assert_eq!(stats.avg_deck_size_wins, 20.0);
+        assert_eq!(stats.avg_deck_size_losses, 97.0 / 3.0);
+    }
+
+    #[test]
+    fn test_custom_bounds() {
+        let runs = vec![run_with_deck("a", 10, true), run_with_deck("b", 11, false)];
+        let stats = DeckSizeStats::from_runs(&runs, &[10, 11]);
+        let labels: Vec<&str> = stats.buckets.iter().map(|b| b.label.as_str()).collect();
+        assert_eq!(labels, ["<=10", "11", "12+"]);
+        assert_eq!(stats.buckets[1].runs, 1);
+    }
+
+    #[test]
+    fn test_parse_bounds() {
+        assert_eq!(parse_deck_size_bounds(" 20, 25 ,30"), Ok(vec![20, 25, 30]));
+        assert!(parse_deck_size_bounds("").is_err());
+        assert!(parse_deck_size_bounds("25,20").is_err());
+        assert!(parse_deck_size_bounds("20,20").is_err());
+        assert!(parse_deck_size_bounds("twenty").is_err());
+        assert!(parse_deck_size_bounds("-1").is_err());
+        assert!(parse_deck_size_bounds("20,2147483647").is_err());
+        assert_eq!(parse_deck_size_bounds("2147483646"), Ok(vec![i32::MAX - 1]));
+    }
+}
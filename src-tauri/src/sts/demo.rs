@@ -0,0 +1,549 @@
+//! Demo mode with synthetic run data
+//!
+//! With demo mode on (the `demo_mode` setting or `STS_VIEWER_DEMO=1`), run
+//! loading returns a generated dataset instead of reading the runs directory,
+//! so the UI can be worked on without the game installed. The generator is
+//! seeded, so the same seed always gives the same runs; tests that need bulk
+//! data can call [`generate_runs`] directly.
+
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use super::deck::{starter_deck, starter_relic, ASCENDERS_BANE, ASCENDERS_BANE_ASCENSION};
+use super::enemies::{DamageSplit, Encounter};
+use super::items::{POTIONS, RELICS};
+use super::potions::{self, PotionPickup};
+use super::purges::CardRemoval;
+use super::relics::{RelicAcquisition, RelicSource};
+use super::rewards::ActRewards;
+use super::shops::{self, ActShops};
+use super::{
+    act_for_floor, card_type_counts, score_per_floor, Character, Ruleset, RunMetrics,
+    ACT_END_FLOORS,
+};
+
+/// Environment variable that turns demo mode on (`1` or `true`)
+pub const DEMO_ENV_VAR: &str = "STS_VIEWER_DEMO";
+
+/// Seed of the dataset served in demo mode
+pub const DEMO_SEED: u64 = 0x5743_5354_4154;
+
+/// Number of runs served in demo mode
+pub const DEMO_RUN_COUNT: usize = 300;
+
+/// End of the demo dataset's year of runs (2024-12-31T20:00:00Z)
+const DEMO_END_TIMESTAMP: i64 = 1_735_675_200;
+
+const YEAR_SECONDS: i64 = 365 * 24 * 60 * 60;
+
+static DEMO_MODE: RwLock<bool> = RwLock::new(false);
+
+/// The demo dataset, generated on first use since its seed never changes
+static DEMO_RUNS: LazyLock<Vec<RunMetrics>> =
+    LazyLock::new(|| generate_runs(DEMO_SEED, DEMO_RUN_COUNT));
+
+/// Turn demo mode on or off
+pub fn set_demo_mode(enabled: bool) {
+    *DEMO_MODE.write() = enabled;
+}
+
+/// Whether runs are currently served from the synthetic dataset
+pub fn demo_mode() -> bool {
+    *DEMO_MODE.read()
+}
+
+/// Whether [`DEMO_ENV_VAR`] asks for demo mode
+pub fn demo_env_enabled() -> bool {
+    std::env::var(DEMO_ENV_VAR)
+        .is_ok_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
+/// The dataset served in demo mode
+pub fn demo_runs() -> Vec<RunMetrics> {
+    DEMO_RUNS.clone()
+}
+
+/// Small deterministic generator (SplitMix64), so demo data needs no extra
+/// dependencies and never changes between builds
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform integer in `low..=high`
+    fn range(&mut self, low: i32, high: i32) -> i32 {
+        low + (self.next_u64() % (high - low + 1) as u64) as i32
+    }
+
+    /// True with probability `p`
+    fn chance(&mut self, p: f64) -> bool {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64 <= p
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.next_u64() as usize % items.len()]
+    }
+}
+
+/// Cards a character might add to the starter deck
+fn card_pool(character: Character) -> &'static [&'static str] {
+    match character {
+        Character::Ironclad => &[
+            "Anger",
+            "Armaments",
+            "Battle Trance",
+            "Body Slam",
+            "Carnage",
+            "Clothesline",
+            "Demon Form",
+            "Feed",
+            "Flame Barrier",
+            "Headbutt",
+            "Inflame",
+            "Offering",
+            "Pommel Strike",
+            "Shrug It Off",
+            "Shockwave",
+            "Twin Strike",
+            "Uppercut",
+            "Whirlwind",
+        ],
+        Character::TheSilent => &[
+            "Acrobatics",
+            "Adrenaline",
+            "Backflip",
+            "Blade Dance",
+            "Blur",
+            "Catalyst",
+            "Dagger Throw",
+            "Deadly Poison",
+            "Footwork",
+            "Leg Sweep",
+            "Noxious Fumes",
+            "Piercing Wail",
+            "Poisoned Stab",
+            "Predator",
+            "Prepared",
+            "Wraith Form",
+        ],
+        Character::Defect => &[
+            "Ball Lightning",
+            "Biased Cognition",
+            "Cold Snap",
+            "Coolheaded",
+            "Defragment",
+            "Echo Form",
+            "Glacier",
+            "Hologram",
+            "Loop",
+            "Charge Battery",
+            "Skim",
+            "Sweeping Beam",
+            "Compile Driver",
+            "Electrodynamics",
+        ],
+        Character::Watcher => &[
+            "Crescendo",
+            "Cut Through Fate",
+            "Empty Body",
+            "Empty Fist",
+            "Flurry of Blows",
+            "Follow-Up",
+            "Inner Peace",
+            "Mental Fortress",
+            "Ragnarok",
+            "Rushdown",
+            "Talk to the Hand",
+            "Tantrum",
+            "Third Eye",
+            "Wallop",
+            "Wheel Kick",
+        ],
+    }
+}
+
+/// Encounters by act (index 0 is Act 1)
+const ENCOUNTERS: [&[&str]; 4] = [
+    &[
+        "Cultist",
+        "Jaw Worm",
+        "2 Louse",
+        "Small Slimes",
+        "Gremlin Gang",
+        "Looter",
+        "3 Louse",
+        "Gremlin Nob",
+        "Lagavulin",
+        "3 Sentries",
+        "The Guardian",
+        "Hexaghost",
+        "Slime Boss",
+    ],
+    &[
+        "Chosen",
+        "Shell Parasite",
+        "Spheric Guardian",
+        "3 Byrds",
+        "Snecko",
+        "Centurion and Healer",
+        "Cultist and Chosen",
+        "Gremlin Leader",
+        "Slavers",
+        "Book of Stabbing",
+        "The Champ",
+        "Automaton",
+        "Collector",
+    ],
+    &[
+        "3 Darklings",
+        "Orb Walker",
+        "3 Shapes",
+        "Transient",
+        "Jaw Worm Horde",
+        "Spire Growth",
+        "Writhing Mass",
+        "Giant Head",
+        "Nemesis",
+        "Reptomancer",
+        "Awakened One",
+        "Time Eater",
+        "Donu and Deca",
+    ],
+    &["Shield and Spear", "The Heart"],
+];
+
+const NEOW_BONUSES: &[&str] = &[
+    "THREE_CARDS",
+    "ONE_RANDOM_RARE_CARD",
+    "REMOVE_CARD",
+    "UPGRADE_CARD",
+    "RANDOM_COLORLESS",
+    "THREE_SMALL_POTIONS",
+    "HUNDRED_GOLD",
+    "TEN_PERCENT_HP_BONUS",
+    "BOSS_RELIC",
+];
+
+/// Floor a loss ends on, weighted towards the first two acts
+fn death_floor(rng: &mut Rng) -> i32 {
+    match rng.range(0, 99) {
+        0..=37 => rng.range(2, 17),
+        38..=72 => rng.range(18, 34),
+        73..=95 => rng.range(35, 51),
+        _ => rng.range(53, 55),
+    }
+}
+
+/// Generate `count` plausible runs from `seed`
+///
+/// Runs cover all characters, ascensions 0-20 (win rate falling with
+/// ascension), and timestamps spread over one year, oldest first.
+pub fn generate_runs(seed: u64, count: usize) -> Vec<RunMetrics> {
+    let mut rng = Rng(seed);
+    let mut timestamps: Vec<i64> = (0..count)
+        .map(|_| DEMO_END_TIMESTAMP - (rng.next_u64() % YEAR_SECONDS as u64) as i64)
+        .collect();
+    timestamps.sort_unstable();
+
+    timestamps
+        .into_iter()
+        .enumerate()
+        .map(|(i, timestamp)| generate_run(&mut rng, i, timestamp))
+        .collect()
+}
+
+fn generate_run(rng: &mut Rng, index: usize, timestamp: i64) -> RunMetrics {
+    let character = *rng.pick(Character::all());
+    let ascension_level = rng.range(0, 20);
+    let victory = rng.chance(0.45 - ascension_level as f64 * 0.015);
+    let heart = victory && rng.chance(0.3);
+    let floor_reached = match (victory, heart) {
+        (true, true) => 56,
+        (true, false) => 51,
+        (false, _) => death_floor(rng),
+    };
+    let act = act_for_floor(floor_reached);
+
+    // Fights: one every other floor or so, with the final one ending a loss
+    let mut encounters = Vec::new();
+    let mut floor = 1;
+    while floor < floor_reached {
+        let pool = ENCOUNTERS[act_for_floor(floor) as usize - 1];
+        encounters.push(Encounter {
+            enemies: rng.pick(pool).to_string(),
+            floor,
+            hp_after: None,
+            elite: false,
+            turns: Some(2 + floor % 5),
+        });
+        floor += rng.range(1, 3);
+    }
+    let killed_by = (!victory).then(|| {
+        let killer = rng.pick(ENCOUNTERS[act as usize - 1]).to_string();
+        encounters.push(Encounter {
+            enemies: killer.clone(),
+            floor: floor_reached,
+            hp_after: None,
+            elite: false,
+            turns: Some(4 + floor_reached % 5),
+        });
+        killer
+    });
+
+    // Deck: the starter deck, a few removals, and cards picked along the way
+    let mut master_deck: Vec<String> = starter_deck(character)
+        .iter()
+        .flat_map(|(card, n)| std::iter::repeat_n(card.to_string(), *n))
+        .collect();
+    if ascension_level >= ASCENDERS_BANE_ASCENSION {
+        master_deck.push(ASCENDERS_BANE.to_string());
+    }
+    let cards_removed = rng.range(0, (floor_reached / 15).min(3));
+    let starters_remaining = master_deck.len() as i32 - cards_removed;
+    let removed_cards = master_deck.split_off(starters_remaining as usize);
+    let cards_added = rng.range(floor_reached / 6, floor_reached / 3 + 2);
+    let pool = card_pool(character);
+    for _ in 0..cards_added {
+        master_deck.push(rng.pick(pool).to_string());
+    }
+    let upgraded_cards = rng.range(0, (floor_reached / 5).min(master_deck.len() as i32));
+    for card in master_deck.iter_mut().take(upgraded_cards as usize) {
+        card.push_str("+1");
+    }
+    let (attack_count, skill_count, power_count) = card_type_counts(&master_deck);
+
+    // Relics: the starter relic, then roughly one every four floors
+    let mut relics = vec![starter_relic(character).to_string()];
+    for _ in 0..rng.range(floor_reached / 5, floor_reached / 3) {
+        let relic = rng.pick(&RELICS[4..]).to_string();
+        if !relics.contains(&relic) {
+            relics.push(relic);
+        }
+    }
+
+    let bosses_killed = act - 1 + i32::from(victory);
+    let elites_killed = rng.range(floor_reached / 12, floor_reached / 8 + 1);
+    let shops_visited = rng.range(floor_reached / 15, floor_reached / 10 + 1);
+    let cards_from_shops = rng.range(0, shops_visited.min(cards_added));
+    let shop_removals = cards_removed.min(shops_visited);
+    let shops_skipped = (shops_visited - cards_from_shops.max(shop_removals)).max(0);
+    // Shops spread over the acts reached, the last ones left empty-handed
+    let mut shops_by_act: Vec<ActShops> = (1..=act)
+        .map(|act| ActShops {
+            act,
+            ..ActShops::default()
+        })
+        .collect();
+    for i in 0..shops_visited {
+        let entry = &mut shops_by_act[(i % act) as usize];
+        entry.shops_visited += 1;
+        entry.shops_skipped += i32::from(i >= shops_visited - shops_skipped);
+    }
+    shops_by_act.retain(|shops| shops.shops_visited > 0);
+    let cards_purged = removed_cards
+        .into_iter()
+        .enumerate()
+        .map(|(i, card)| CardRemoval {
+            card,
+            floor: None,
+            shop: (i as i32) < shop_removals,
+        })
+        .collect();
+    let rewards_by_act: Vec<ActRewards> = (1..=act)
+        .map(|a| {
+            let offered = encounters
+                .iter()
+                .filter(|e| act_for_floor(e.floor) == a)
+                .count() as i32;
+            ActRewards {
+                act: a,
+                offered,
+                skipped: rng.range(0, offered / 3),
+            }
+        })
+        .collect();
+    let has_keys = floor_reached > 51;
+    let potions_used = rng.range(0, floor_reached / 5);
+    let potions_obtained = potions_used + rng.range(0, 2);
+    let damage_per_fight = rng.range(4, 12);
+    let total_damage_taken = encounters.len() as i32 * damage_per_fight;
+    let mut damage_split = DamageSplit::default();
+    let mut damage_by_act = vec![0i32; ACT_END_FLOORS.len() + 1];
+    for encounter in &encounters {
+        damage_split.add(Some(&encounter.enemies), damage_per_fight);
+        damage_by_act[act_for_floor(encounter.floor) as usize - 1] += damage_per_fight;
+    }
+    // Relic sources: one boss relic per boss killed, the rest from other rooms
+    let mut relic_sources: HashMap<String, i32> = HashMap::new();
+    for i in 1..relics.len() as i32 {
+        let source = if i <= bosses_killed {
+            RelicSource::Boss
+        } else {
+            *rng.pick(&[
+                RelicSource::Elite,
+                RelicSource::Chest,
+                RelicSource::Shop,
+                RelicSource::Event,
+            ])
+        };
+        *relic_sources
+            .entry(source.as_str().to_string())
+            .or_default() += 1;
+    }
+    let score = floor_reached * 5
+        + elites_killed * 25
+        + bosses_killed * 50
+        + if victory { 250 } else { 0 }
+        + rng.range(0, 150);
+
+    RunMetrics {
+        play_id: format!("demo-{:04}", index + 1),
+        character: character.into(),
+        floor_reached,
+        victory,
+        score,
+        score_per_floor: score_per_floor(score, floor_reached),
+        ascension_level,
+        deck_size: master_deck.len() as i32,
+        attack_count,
+        skill_count,
+        power_count,
+        upgraded_cards,
+        cards_removed,
+        cards_purged,
+        cards_added,
+        starters_remaining,
+        neow_bonus: Some(rng.pick(NEOW_BONUSES).to_string()),
+        relic_count: relics.len() as i32,
+        relic_acquisitions: Vec::new(),
+        // Demo relics after the starter are in pickup order
+        first_relic: relics.get(1).map(|relic| RelicAcquisition {
+            relic: relic.clone(),
+            floor: None,
+        }),
+        relic_sources,
+        relics_after_act1: None,
+        relics_after_act2: None,
+        relics_after_act3: None,
+        boss_relics: Vec::new(),
+        boss_relics_skipped: None,
+        relics,
+        master_deck,
+        elites_killed,
+        elites_by_act: Vec::new(),
+        bosses_killed,
+        campfires_rested: rng.range(0, act + 1),
+        campfires_upgraded: rng.range(act, act * 3),
+        shops_visited,
+        // Shops where neither a card nor a removal was bought
+        shops_skipped,
+        shops_by_act,
+        cards_purchased: cards_from_shops,
+        cards_from_shops,
+        relics_from_shops: 0,
+        potions_from_shops: 0,
+        shop_removals,
+        gold_spent_on_removals: shops::removal_gold(shop_removals),
+        cards_from_rewards: cards_added - cards_from_shops,
+        rewards_offered: Some(rewards_by_act.iter().map(|a| a.offered).sum()),
+        rewards_skipped: Some(rewards_by_act.iter().map(|a| a.skipped).sum()),
+        singing_bowl_picks: 0,
+        rewards_by_act,
+        potions_used,
+        potions_obtained_count: Some(potions_obtained),
+        potions_wasted: Some(potions::wasted(potions_obtained, potions_used)),
+        potions_obtained: (0..potions_obtained as usize)
+            .map(|i| PotionPickup {
+                name: POTIONS[(index + i * 7) % POTIONS.len()].to_string(),
+                floor: None,
+            })
+            .collect(),
+        fairy_saved_run: false,
+        total_damage_taken,
+        damage_from_normals: damage_split.normals,
+        damage_from_elites: damage_split.elites,
+        damage_from_bosses: damage_split.bosses,
+        damage_from_other: damage_split.other,
+        damage_by_act,
+        max_hp_at_end: if victory {
+            rng.range(70, 95)
+        } else {
+            rng.range(60, 90)
+        },
+        max_damage_survived: rng.range(5, 40).min(total_damage_taken),
+        encounters,
+        question_rooms: 0,
+        question_rooms_fought: 0,
+        question_rooms_by_act: Vec::new(),
+        killed_by,
+        has_ruby_key: Some(has_keys),
+        has_emerald_key: Some(has_keys),
+        has_sapphire_key: Some(has_keys),
+        timestamp: Some(timestamp),
+        local_time: None,
+        playtime: floor_reached * rng.range(45, 90),
+        is_daily: false,
+        seeded: false,
+        is_endless: false,
+        is_trial: false,
+        ruleset: Ruleset::Standard,
+        is_modded: false,
+        modded_content: Vec::new(),
+        source_path: None,
+        file_modified_at: None,
+        hidden: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generation_is_deterministic() {
+        // As values, since `relic_sources` keys serialize in any order
+        let json = |seed| serde_json::to_value(generate_runs(seed, 50)).unwrap();
+        assert_eq!(json(7), json(7));
+        assert_ne!(json(7), json(8));
+    }
+
+    #[test]
+    fn test_demo_runs_are_plausible() {
+        let runs = demo_runs();
+        assert_eq!(runs.len(), DEMO_RUN_COUNT);
+        for character in Character::all() {
+            assert!(runs.iter().any(|r| r.character == *character));
+        }
+
+        let wins = runs.iter().filter(|r| r.victory).count();
+        assert!(wins > 20 && wins < DEMO_RUN_COUNT / 2, "{wins} wins");
+
+        let first = runs[0].timestamp.unwrap();
+        let last = runs[runs.len() - 1].timestamp.unwrap();
+        assert!(first <= last && last <= DEMO_END_TIMESTAMP);
+        assert!(last - first > YEAR_SECONDS / 2);
+
+        for run in &runs {
+            assert_eq!(run.deck_size, run.starters_remaining + run.cards_added);
+            assert_eq!(run.killed_by.is_none(), run.victory);
+            assert!(run.floor_reached >= 2 && run.floor_reached <= 56);
+            assert_eq!(
+                run.damage_by_act.iter().sum::<i32>(),
+                run.total_damage_taken
+            );
+            // Every relic but the starter has a source
+            assert_eq!(run.relic_sources.values().sum::<i32>(), run.relic_count - 1);
+            let shops =
+                |count: fn(&ActShops) -> i32| run.shops_by_act.iter().map(count).sum::<i32>();
+            assert_eq!(shops(|a| a.shops_visited), run.shops_visited);
+            assert_eq!(shops(|a| a.shops_skipped), run.shops_skipped);
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Elites fought versus elites routed around
+//!
+//! Run files don't store the map, so how many elites a run could have fought
+//! is a heuristic. Elites can only appear from the sixth row of an act's map
+//! onwards, and rows 9 (treasure) and 15 (rest before the boss) never hold
+//! one, leaving eight rows per act. Each of those rows the run walked through
+//! counts as one elite opportunity, so a run that fought three elites in a
+//! full act has a fight rate of 3/8.
+//!
+//! Rows are read from `path_taken` rather than `path_per_floor`, as the two
+//! lists line up differently:
+//!
+//! - `path_per_floor` has one entry per floor, with `null` for floors that
+//!   aren't map nodes (the treasure floor after each boss). Its floor numbers
+//!   run on across acts.
+//! - `path_taken` has one entry per map node visited and nothing for those
+//!   floors. Each act ends with a `BOSS` entry, so the position since the last
+//!   `BOSS` is the map row.
+//!
+//! Both use `E` for elite rooms, burning elites included. The Act 4 elite is
+//! always on the path and isn't counted.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use super::RunMetrics;
+
+/// `path_taken` symbol of an elite room
+pub const ELITE_ROOM: &str = "E";
+
+/// `path_taken` symbol ending each act
+pub const BOSS_ROOM: &str = "BOSS";
+
+/// Acts with a generated map
+const MAPPED_ACTS: i32 = 3;
+
+/// Whether elites can appear on a map row (1-based)
+pub fn elite_row(row: usize) -> bool {
+    (6..=14).contains(&row) && row != 9
+}
+
+/// Elites fought and elite opportunities in one act of a run
+///
+/// `elite_opportunity` is a heuristic, see the module docs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ActElites {
+    pub act: i32,
+    pub elites_fought: i32,
+    /// Map rows walked through where an elite could have been (heuristic)
+    pub elite_opportunity: i32,
+}
+
+/// Elites fought and opportunities per act from a run's `path_taken`
+///
+/// Acts the run didn't enter are left out.
+pub fn summarize_elites(path_taken: &[Option<String>]) -> Vec<ActElites> {
+    let mut acts: Vec<ActElites> = Vec::new();
+    let mut act = 1;
+    let mut row = 0;
+    for symbol in path_taken {
+        if act > MAPPED_ACTS {
+            break;
+        }
+        if symbol.as_deref() == Some(BOSS_ROOM) {
+            act += 1;
+            row = 0;
+            continue;
+        }
+        row += 1;
+        if row == 1 {
+            acts.push(ActElites {
+                act,
+                ..ActElites::default()
+            });
+        }
+        let entry = acts.last_mut().expect("pushed on the first row");
+        entry.elites_fought += i32::from(symbol.as_deref() == Some(ELITE_ROOM));
+        entry.elite_opportunity += i32::from(elite_row(row));
+    }
+    acts
+}
+
+/// Elite fight rate for a group of runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EliteRate {
+    /// Runs that entered the act
+    pub runs: i32,
+    pub elites_fought: i32,
+    /// Heuristic elite opportunities, see [`ActElites`]
+    pub elite_opportunity: i32,
+    /// `elites_fought / elite_opportunity`, or 0 without opportunities
+    pub fight_rate: f64,
+}
+
+impl EliteRate {
+    fn add(&mut self, act: &ActElites) {
+        self.runs += 1;
+        self.elites_fought += act.elites_fought;
+        self.elite_opportunity += act.elite_opportunity;
+        self.fight_rate = if self.elite_opportunity > 0 {
+            self.elites_fought as f64 / self.elite_opportunity as f64
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Elite fight rate in one act, for won and lost runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ActEliteStats {
+    pub act: i32,
+    pub wins: EliteRate,
+    pub losses: EliteRate,
+}
+
+/// Elite fight rates across runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EliteStats {
+    /// Runs with `path_taken` data
+    pub runs: i32,
+    /// Runs left out because the file has no `path_taken`
+    pub runs_without_data: i32,
+    /// Acts 1 to 3, earliest first
+    pub by_act: Vec<ActEliteStats>,
+}
+
+impl EliteStats {
+    /// Aggregate elite fight rates, ignoring hidden runs
+    pub fn from_runs(runs: &[RunMetrics]) -> Self {
+        let mut stats = Self::default();
+        let mut by_act: BTreeMap<i32, ActEliteStats> = BTreeMap::new();
+        for run in runs.iter().filter(|r| !r.hidden) {
+            if run.elites_by_act.is_empty() {
+                stats.runs_without_data += 1;
+                continue;
+            }
+            stats.runs += 1;
+            for act in &run.elites_by_act {
+                let entry = by_act.entry(act.act).or_insert_with(|| ActEliteStats {
+                    act: act.act,
+                    ..ActEliteStats::default()
+                });
+                if run.victory {
+                    entry.wins.add(act);
+                } else {
+                    entry.losses.add(act);
+                }
+            }
+        }
+        stats.by_act = by_act.into_values().collect();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{Character, RunFileBuilder, RunsDirFixture};
+
+    fn path(symbols: &[&str]) -> Vec<Option<String>> {
+        symbols.iter().map(|s| Some(s.to_string())).collect()
+    }
+
+    /// A full act: elites on rows 6 and 11, rows 9 and 15 fixed
+    const ACT: [&str; 15] = [
+        "M", "?", "M", "$", "M", "E", "M", "R", "T", "M", "E", "?", "M", "R", "R",
+    ];
+
+    #[test]
+    fn test_elite_rows() {
+        let rows: Vec<usize> = (1..=15).filter(|r| elite_row(*r)).collect();
+        assert_eq!(rows, [6, 7, 8, 10, 11, 12, 13, 14]);
+    }
+
+    #[test]
+    fn test_summarize_full_and_partial_acts() {
+        let mut symbols: Vec<&str> = ACT.to_vec();
+        symbols.push(BOSS_ROOM);
+        symbols.extend(["M", "M", "?", "M", "M", "E", "M"]);
+        let acts = summarize_elites(&path(&symbols));
+        assert_eq!(
+            acts,
+            vec![
+                ActElites {
+                    act: 1,
+                    elites_fought: 2,
+                    elite_opportunity: 8,
+                },
+                // Died on row 7 of Act 2: rows 6 and 7 were elite rows
+                ActElites {
+                    act: 2,
+                    elites_fought: 1,
+                    elite_opportunity: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_act4_is_ignored() {
+        let mut symbols = Vec::new();
+        for _ in 0..3 {
+            symbols.extend(ACT);
+            symbols.push(BOSS_ROOM);
+        }
+        symbols.extend(["R", "$", "E", BOSS_ROOM]);
+        let acts = summarize_elites(&path(&symbols));
+        assert_eq!(acts.len(), 3);
+        assert!(acts.iter().all(|a| a.elites_fought == 2));
+    }
+
+    #[test]
+    fn test_path_taken_rows_differ_from_path_per_floor() {
+        // Floor 17 is the Act 1 treasure floor: `null` in path_per_floor, absent
+        // from path_taken. The Act 2 elite is floor 23 but row 6 of its act.
+        let mut per_floor: Vec<Option<&str>> = ACT.iter().map(|s| Some(*s)).collect();
+        per_floor.push(Some(BOSS_ROOM));
+        per_floor.push(None);
+        per_floor.extend(["M", "M", "M", "M", "M", "E"].map(Some));
+        let mut taken: Vec<&str> = ACT.to_vec();
+        taken.push(BOSS_ROOM);
+        taken.extend(["M", "M", "M", "M", "M", "E"]);
+
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("elites")
+                .character(Character::Defect)
+                .set("path_per_floor", per_floor.clone())
+                .path_taken(&taken),
+        );
+        fixture.add(&RunFileBuilder::new("old").character(Character::Defect));
+
+        let runs = fixture.load_sorted();
+        let (elites, old) = (&runs[0], &runs[1]);
+        assert_eq!(per_floor[22], Some(ELITE_ROOM));
+        assert_eq!(elites.elites_killed, 3);
+        assert_eq!(elites.elites_by_act[1].elites_fought, 1);
+        assert_eq!(elites.elites_by_act[1].elite_opportunity, 1);
+        assert!(old.elites_by_act.is_empty());
+    }
+
+    #[test]
+    fn test_elite_stats_split_by_result() {
+        let act = |act, elites_fought, elite_opportunity| ActElites {
+            act,
+            elites_fought,
+            elite_opportunity,
+        };
+        let mut win = sample_run("w", Character::Watcher, true);
+        win.elites_by_act = vec![act(1, 3, 8), act(2, 2, 8)];
+        let mut loss = sample_run("l", Character::Watcher, false);
+        loss.elites_by_act = vec![act(1, 1, 8)];
+        let old = sample_run("o", Character::Watcher, false);
+        let mut hidden = win.clone();
+        hidden.hidden = true;
+
+        let stats = EliteStats::from_runs(&[win, loss, old, hidden]);
+        assert_eq!((stats.runs, stats.runs_without_data), (2, 1));
+        assert_eq!(stats.by_act.len(), 2);
+        assert_eq!(stats.by_act[0].wins.fight_rate, 0.375);
+        assert_eq!(stats.by_act[0].losses.fight_rate, 0.125);
+        assert_eq!(stats.by_act[1].losses.runs, 0);
+        assert_eq!(stats.by_act[1].losses.fight_rate, 0.0);
+    }
+}
@@ -0,0 +1,540 @@
+//! Enemy encounter catalog
+//!
+//! Fights come from `damage_taken`, one entry per combat, and deaths from
+//! `killed_by`. Group encounters are named after their size ("2 Louse",
+//! "3 Louse"); normalizing drops the count so those merge into one entry.
+//!
+//! Encounters are classified as hallway fights, elites or bosses by name:
+//! [`NORMAL_ENCOUNTERS`] and [`ELITE_ENCOUNTERS`] list the vanilla encounter
+//! pools, and bosses are matched against [`ACT_BOSSES`](super::bosses::ACT_BOSSES).
+//! Fights started by events and modded enemies are in none of them.
+//!
+//! Each `damage_taken` entry also records how many turns the fight lasted,
+//! which [`slow_fights`] averages per encounter.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use super::{act_for_floor, bosses, mean, RunMetrics};
+
+/// Hallway fights of the vanilla encounter pools, acts 1 to 3
+pub const NORMAL_ENCOUNTERS: &[&str] = &[
+    // Act 1
+    "Cultist",
+    "Jaw Worm",
+    "2 Louse",
+    "Small Slimes",
+    "Blue Slaver",
+    "Gremlin Gang",
+    "Looter",
+    "Large Slime",
+    "Lots of Slimes",
+    "Exordium Thugs",
+    "Exordium Wildlife",
+    "Red Slaver",
+    "3 Louse",
+    "2 Fungi Beasts",
+    // Act 2
+    "Spheric Guardian",
+    "Chosen",
+    "Shell Parasite",
+    "3 Byrds",
+    "2 Thieves",
+    "Chosen and Byrds",
+    "Sentry and Sphere",
+    "Snake Plant",
+    "Snecko",
+    "Centurion and Healer",
+    "Cultist and Chosen",
+    "3 Cultists",
+    "Shelled Parasite and Fungi",
+    // Act 3
+    "3 Darklings",
+    "Orb Walker",
+    "3 Shapes",
+    "Transient",
+    "4 Shapes",
+    "Maw",
+    "Sphere and 2 Shapes",
+    "Jaw Worm Horde",
+    "Spire Growth",
+    "Writhing Mass",
+];
+
+/// Elite encounters of the vanilla encounter pools, the Act 4 elite included
+pub const ELITE_ENCOUNTERS: &[&str] = &[
+    // Act 1
+    "Gremlin Nob",
+    "Lagavulin",
+    "3 Sentries",
+    // Act 2
+    "Gremlin Leader",
+    "Slavers",
+    "Book of Stabbing",
+    // Act 3
+    "Giant Head",
+    "Nemesis",
+    "Reptomancer",
+    // Act 4
+    "Shield and Spear",
+];
+
+/// What kind of fight an encounter was
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncounterKind {
+    Normal,
+    Elite,
+    Boss,
+    /// Event fights, modded enemies and unrecognized names
+    Other,
+}
+
+/// Classify an encounter by its `damage_taken` name, ignoring case
+pub fn classify_encounter(name: &str) -> EncounterKind {
+    let name = name.trim();
+    let listed = |table: &[&str]| table.iter().any(|e| e.eq_ignore_ascii_case(name));
+    if bosses::find_boss(name).is_some() {
+        EncounterKind::Boss
+    } else if listed(ELITE_ENCOUNTERS) {
+        EncounterKind::Elite
+    } else if listed(NORMAL_ENCOUNTERS) {
+        EncounterKind::Normal
+    } else {
+        EncounterKind::Other
+    }
+}
+
+/// Damage taken by kind of fight
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DamageSplit {
+    pub normals: i32,
+    pub elites: i32,
+    pub bosses: i32,
+    pub other: i32,
+}
+
+impl DamageSplit {
+    /// Add `damage` taken in the encounter `name` (None for fights the file
+    /// doesn't name)
+    pub fn add(&mut self, name: Option<&str>, damage: i32) {
+        let bucket = match name.map_or(EncounterKind::Other, classify_encounter) {
+            EncounterKind::Normal => &mut self.normals,
+            EncounterKind::Elite => &mut self.elites,
+            EncounterKind::Boss => &mut self.bosses,
+            EncounterKind::Other => &mut self.other,
+        };
+        *bucket = bucket.saturating_add(damage);
+    }
+}
+
+/// Average damage taken per run by kind of fight
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EncounterDamage {
+    /// Hallway fights
+    pub normals: f64,
+    pub elites: f64,
+    /// Act bosses and the Corrupt Heart
+    pub bosses: f64,
+    /// Event fights and encounters not in the vanilla tables
+    pub other: f64,
+}
+
+impl EncounterDamage {
+    /// Average the damage split of `runs`
+    pub fn average(runs: &[&RunMetrics]) -> Self {
+        let avg = |field: fn(&RunMetrics) -> i32| mean(runs.iter().map(|r| field(r)));
+        Self {
+            normals: avg(|r| r.damage_from_normals),
+            elites: avg(|r| r.damage_from_elites),
+            bosses: avg(|r| r.damage_from_bosses),
+            other: avg(|r| r.damage_from_other),
+        }
+    }
+}
+
+/// A fight recorded in a run's `damage_taken` list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Encounter {
+    /// Encounter name as written by the game (e.g. "Jaw Worm", "3 Louse")
+    pub enemies: String,
+    pub floor: i32,
+    /// HP left after the fight, when the file records HP per floor
+    #[serde(default)]
+    pub hp_after: Option<i32>,
+    /// Fought in an elite (`E`) room
+    #[serde(default)]
+    pub elite: bool,
+    /// Turns the fight lasted, when the file records them
+    #[serde(default)]
+    pub turns: Option<i32>,
+}
+
+/// How often an encounter was fought and how often it ended the run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct EnemyCount {
+    pub name: String,
+    pub fights: i32,
+    pub deaths: i32,
+}
+
+/// Drop a leading group size, so "3 Louse" becomes "Louse"
+pub fn normalize_encounter(name: &str) -> &str {
+    let name = name.trim();
+    match name.split_once(' ') {
+        Some((count, rest)) if count.parse::<u32>().is_ok() && !rest.is_empty() => rest.trim(),
+        _ => name,
+    }
+}
+
+/// Encounters seen in `runs`, most fought first
+///
+/// With `act`, only fights on floors in that act (and deaths on such floors)
+/// are counted. Hidden runs are ignored.
+pub fn enemy_catalog(runs: &[RunMetrics], act: Option<i32>, normalize: bool) -> Vec<EnemyCount> {
+    let mut counts: HashMap<String, (i32, i32)> = HashMap::new();
+    let key = |name: &str| {
+        if normalize {
+            normalize_encounter(name).to_string()
+        } else {
+            name.trim().to_string()
+        }
+    };
+    let in_act = |floor: i32| act.is_none_or(|a| act_for_floor(floor) == a);
+
+    for run in runs.iter().filter(|r| !r.hidden) {
+        for encounter in run.encounters.iter().filter(|e| in_act(e.floor)) {
+            counts.entry(key(&encounter.enemies)).or_default().0 += 1;
+        }
+        if let Some(killer) = run.killed_by.as_deref().filter(|_| !run.victory) {
+            if in_act(run.floor_reached) {
+                counts.entry(key(killer)).or_default().1 += 1;
+            }
+        }
+    }
+
+    let mut catalog: Vec<EnemyCount> = counts
+        .into_iter()
+        .map(|(name, (fights, deaths))| EnemyCount {
+            name,
+            fights,
+            deaths,
+        })
+        .collect();
+    catalog.sort_by(|a, b| b.fights.cmp(&a.fights).then_with(|| a.name.cmp(&b.name)));
+    catalog
+}
+
+/// How long fights against an encounter take
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EncounterTurns {
+    pub name: String,
+    /// Fights against the encounter that record their turns
+    pub fights: i32,
+    pub avg_turns: f64,
+    pub max_turns: i32,
+    /// Run with the longest of these fights (the earliest one on ties)
+    pub longest_play_id: String,
+}
+
+/// Encounters by average fight length, slowest first
+///
+/// Only fights that record their turns count, and encounters with fewer than
+/// `min_fights` such fights are left out. `normalize` merges group sizes as
+/// in [`enemy_catalog`]. Hidden runs are ignored.
+pub fn slow_fights(runs: &[RunMetrics], normalize: bool, min_fights: i32) -> Vec<EncounterTurns> {
+    struct Tally<'a> {
+        turns: Vec<i32>,
+        longest: (i32, &'a str),
+    }
+
+    let mut tallies: HashMap<String, Tally> = HashMap::new();
+    for run in runs.iter().filter(|r| !r.hidden) {
+        for encounter in &run.encounters {
+            let Some(turns) = encounter.turns else {
+                continue;
+            };
+            let name = if normalize {
+                normalize_encounter(&encounter.enemies)
+            } else {
+                encounter.enemies.trim()
+            };
+            let tally = tallies.entry(name.to_string()).or_insert(Tally {
+                turns: Vec::new(),
+                longest: (turns, &run.play_id),
+            });
+            tally.turns.push(turns);
+            if turns > tally.longest.0 {
+                tally.longest = (turns, &run.play_id);
+            }
+        }
+    }
+
+    let mut slowest: Vec<EncounterTurns> = tallies
+        .into_iter()
+        .filter(|(_, tally)| tally.turns.len() as i32 >= min_fights)
+        .map(|(name, tally)| EncounterTurns {
+            name,
+            fights: tally.turns.len() as i32,
+            avg_turns: mean(tally.turns.iter().copied()),
+            max_turns: tally.longest.0,
+            longest_play_id: tally.longest.1.to_string(),
+        })
+        .collect();
+    slowest.sort_by(|a, b| {
+        b.avg_turns
+            .total_cmp(&a.avg_turns)
+            .then_with(|| b.fights.cmp(&a.fights))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    slowest
+}
+
+/// The fight of a run that took the most turns (the earliest one on ties)
+pub fn longest_fight(run: &RunMetrics) -> Option<&Encounter> {
+    run.encounters
+        .iter()
+        .filter(|e| e.turns.is_some())
+        .rev()
+        .max_by_key(|e| e.turns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::bosses::ACT_BOSSES;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{calculate_character_stats, Character, RunFileBuilder, RunsDirFixture};
+
+    fn run_with(id: &str, fights: &[(&str, i32)], killed_by: Option<&str>) -> RunMetrics {
+        let mut run = sample_run(id, Character::Ironclad, killed_by.is_none());
+        run.encounters = fights
+            .iter()
+            .map(|(enemies, floor)| Encounter {
+                enemies: enemies.to_string(),
+                floor: *floor,
+                hp_after: None,
+                elite: false,
+                turns: None,
+            })
+            .collect();
+        run.killed_by = killed_by.map(str::to_string);
+        run.floor_reached = fights.last().map_or(1, |(_, floor)| *floor);
+        run
+    }
+
+    fn summary(catalog: &[EnemyCount]) -> Vec<(&str, i32, i32)> {
+        catalog
+            .iter()
+            .map(|e| (e.name.as_str(), e.fights, e.deaths))
+            .collect()
+    }
+
+    #[test]
+    fn test_normalize_encounter() {
+        assert_eq!(normalize_encounter("3 Louse"), "Louse");
+        assert_eq!(normalize_encounter("2 Fungi Beasts"), "Fungi Beasts");
+        assert_eq!(normalize_encounter("Jaw Worm"), "Jaw Worm");
+        assert_eq!(normalize_encounter("3"), "3");
+    }
+
+    #[test]
+    fn test_every_vanilla_elite_is_classified() {
+        let elites = [
+            "Gremlin Nob",
+            "Lagavulin",
+            "3 Sentries",
+            "Gremlin Leader",
+            "Slavers",
+            "Book of Stabbing",
+            "Giant Head",
+            "Nemesis",
+            "Reptomancer",
+            "Shield and Spear",
+        ];
+        assert_eq!(ELITE_ENCOUNTERS.len(), elites.len());
+        for elite in elites {
+            assert_eq!(classify_encounter(elite), EncounterKind::Elite, "{elite}");
+        }
+        assert_eq!(classify_encounter(" gremlin nob"), EncounterKind::Elite);
+    }
+
+    #[test]
+    fn test_classify_encounters() {
+        for boss in ACT_BOSSES.iter().flat_map(|b| b.aliases) {
+            assert_eq!(classify_encounter(boss), EncounterKind::Boss, "{boss}");
+        }
+        for normal in NORMAL_ENCOUNTERS {
+            assert_eq!(
+                classify_encounter(normal),
+                EncounterKind::Normal,
+                "{normal}"
+            );
+        }
+        // Event fights and modded enemies
+        for other in [
+            "Colosseum Nobs",
+            "Masked Bandits",
+            "Mind Bloom Boss Battle",
+            "Hermit Slime",
+        ] {
+            assert_eq!(classify_encounter(other), EncounterKind::Other, "{other}");
+        }
+    }
+
+    #[test]
+    fn test_damage_split() {
+        let mut split = DamageSplit::default();
+        split.add(Some("Jaw Worm"), 5);
+        split.add(Some("Lagavulin"), 20);
+        split.add(Some("2 Louse"), 3);
+        split.add(Some("Hexaghost"), 30);
+        split.add(Some("Masked Bandits"), 9);
+        split.add(None, 1);
+        assert_eq!(
+            split,
+            DamageSplit {
+                normals: 8,
+                elites: 20,
+                bosses: 30,
+                other: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn test_damage_split_from_run_files() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("win")
+                .victory(true)
+                .fight(1, "Cultist", 7)
+                .fight(3, "Small Slimes", 3)
+                .fight(8, "3 Sentries", 20)
+                .fight(13, "Masked Bandits", 6)
+                .fight(16, "Hexaghost", 30),
+        );
+        fixture.add(&RunFileBuilder::new("loss").fight(2, "Jaw Worm", 12).fight(
+            6,
+            "Gremlin Nob",
+            40,
+        ));
+
+        let runs = fixture.load_sorted();
+        let (loss, win) = (&runs[0], &runs[1]);
+        assert_eq!(win.damage_from_normals, 10);
+        assert_eq!(win.damage_from_elites, 20);
+        assert_eq!(win.damage_from_bosses, 30);
+        assert_eq!(win.damage_from_other, 6);
+        assert_eq!(win.total_damage_taken, 66);
+        assert_eq!(
+            (loss.damage_from_normals, loss.damage_from_elites),
+            (12, 40)
+        );
+
+        let stats = &calculate_character_stats(&runs)[0];
+        assert_eq!(
+            stats.avg_damage_wins,
+            EncounterDamage {
+                normals: 10.0,
+                elites: 20.0,
+                bosses: 30.0,
+                other: 6.0,
+            }
+        );
+        assert_eq!(stats.avg_damage_losses.elites, 40.0);
+        assert_eq!(stats.avg_damage_losses.bosses, 0.0);
+    }
+
+    #[test]
+    fn test_catalog_counts_fights_and_deaths() {
+        let runs = vec![
+            run_with(
+                "a",
+                &[("2 Louse", 2), ("Jaw Worm", 3), ("3 Louse", 5)],
+                None,
+            ),
+            run_with(
+                "b",
+                &[("Jaw Worm", 2), ("Gremlin Nob", 8), ("Chosen", 20)],
+                Some("Chosen"),
+            ),
+        ];
+
+        let distinct = enemy_catalog(&runs, None, false);
+        assert_eq!(
+            summary(&distinct),
+            vec![
+                ("Jaw Worm", 2, 0),
+                ("2 Louse", 1, 0),
+                ("3 Louse", 1, 0),
+                ("Chosen", 1, 1),
+                ("Gremlin Nob", 1, 0),
+            ]
+        );
+
+        let merged = enemy_catalog(&runs, None, true);
+        assert_eq!(merged[0].name, "Jaw Worm");
+        assert_eq!(
+            merged[1],
+            EnemyCount {
+                name: "Louse".to_string(),
+                fights: 2,
+                deaths: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_catalog_filters_by_act() {
+        let runs = vec![run_with(
+            "a",
+            &[("Cultist", 1), ("Chosen", 20), ("Byrd", 22)],
+            Some("Byrd"),
+        )];
+        assert_eq!(
+            summary(&enemy_catalog(&runs, Some(2), false)),
+            vec![("Byrd", 1, 1), ("Chosen", 1, 0)]
+        );
+        assert_eq!(
+            summary(&enemy_catalog(&runs, Some(1), false)),
+            vec![("Cultist", 1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_slow_fights_from_run_files() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("a")
+                .fight_with_turns(1, "2 Louse", 4, 3)
+                .fight_with_turns(5, "Lagavulin", 18, 9)
+                .fight(6, "Cultist", 5),
+        );
+        fixture.add(
+            &RunFileBuilder::new("b")
+                .fight_with_turns(2, "3 Louse", 6, 5)
+                .fight_with_turns(8, "Lagavulin", 25, 9)
+                .fight_with_turns(9, "Jaw Worm", 3, 0),
+        );
+        let runs = fixture.load_sorted();
+        // A zero turn count isn't a real fight length
+        assert!(longest_fight(&runs[1]).is_some_and(|fight| fight.enemies == "Lagavulin"));
+
+        let slowest = slow_fights(&runs, true, 1);
+        let summary: Vec<_> = slowest
+            .iter()
+            .map(|e| (e.name.as_str(), e.fights, e.avg_turns, e.max_turns))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("Lagavulin", 2, 9.0, 9), ("Louse", 2, 4.0, 5)]
+        );
+        // Ties keep the earliest run
+        assert_eq!(slowest[0].longest_play_id, "a");
+        assert_eq!(slowest[1].longest_play_id, "b");
+
+        assert_eq!(slow_fights(&runs, false, 2).len(), 1);
+        assert_eq!(slow_fights(&runs, false, 1).len(), 3);
+    }
+}
@@ -0,0 +1,205 @@
+//! Unknown (`?`) rooms
+//!
+//! `path_per_floor` keeps `?` for an unknown room whatever it turned into.
+//! A `?` room that became a fight shows up as a `damage_taken` entry on the
+//! same floor; `path_per_floor[i]` is floor `i + 1`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use utoipa::ToSchema;
+
+use super::{act_for_floor, RunMetrics};
+
+/// `path_per_floor` symbol of an unknown room
+pub const QUESTION_ROOM: &str = "?";
+
+/// Unknown rooms entered and fought in one act of a run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ActQuestionRooms {
+    pub act: i32,
+    pub rooms: i32,
+    /// Rooms that turned into a fight
+    pub fought: i32,
+}
+
+/// Unknown room counts for one run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuestionRoomSummary {
+    pub rooms: i32,
+    pub fought: i32,
+    pub by_act: Vec<ActQuestionRooms>,
+}
+
+/// Count unknown rooms and the ones among them that had a fight
+///
+/// `fight_floors` are the floors of the run's `damage_taken` entries.
+pub fn summarize_question_rooms(
+    path_per_floor: &[Option<String>],
+    fight_floors: impl IntoIterator<Item = i32>,
+) -> QuestionRoomSummary {
+    let fight_floors: HashSet<i32> = fight_floors.into_iter().collect();
+    let mut summary = QuestionRoomSummary::default();
+    let mut by_act: BTreeMap<i32, ActQuestionRooms> = BTreeMap::new();
+    for (index, symbol) in path_per_floor.iter().enumerate() {
+        if symbol.as_deref() != Some(QUESTION_ROOM) {
+            continue;
+        }
+        let floor = index as i32 + 1;
+        let fought = fight_floors.contains(&floor);
+        summary.rooms += 1;
+        summary.fought += i32::from(fought);
+
+        let act = act_for_floor(floor);
+        let entry = by_act.entry(act).or_insert_with(|| ActQuestionRooms {
+            act,
+            ..ActQuestionRooms::default()
+        });
+        entry.rooms += 1;
+        entry.fought += i32::from(fought);
+    }
+    summary.by_act = by_act.into_values().collect();
+    summary
+}
+
+/// How often a group of unknown rooms turned into fights
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct QuestionRoomRate {
+    pub rooms: i32,
+    pub fought: i32,
+    /// `fought / rooms`, or 0 without any rooms
+    pub fight_rate: f64,
+}
+
+impl QuestionRoomRate {
+    fn add(&mut self, rooms: i32, fought: i32) {
+        self.rooms += rooms;
+        self.fought += fought;
+        self.fight_rate = if self.rooms > 0 {
+            self.fought as f64 / self.rooms as f64
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Unknown room fight rate for one act
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ActQuestionRoomRate {
+    pub act: i32,
+    #[serde(flatten)]
+    pub rate: QuestionRoomRate,
+}
+
+/// Unknown room outcomes across runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EventStats {
+    /// Runs included
+    pub runs: i32,
+    /// All unknown rooms
+    pub question_rooms: QuestionRoomRate,
+    /// Unknown rooms by act, earliest first
+    pub by_act: Vec<ActQuestionRoomRate>,
+}
+
+impl EventStats {
+    /// Aggregate unknown room outcomes, ignoring hidden runs
+    pub fn from_runs(runs: &[RunMetrics]) -> Self {
+        let mut stats = Self::default();
+        let mut by_act: BTreeMap<i32, QuestionRoomRate> = BTreeMap::new();
+        for run in runs.iter().filter(|r| !r.hidden) {
+            stats.runs += 1;
+            stats
+                .question_rooms
+                .add(run.question_rooms, run.question_rooms_fought);
+            for act in &run.question_rooms_by_act {
+                by_act
+                    .entry(act.act)
+                    .or_default()
+                    .add(act.rooms, act.fought);
+            }
+        }
+        stats.by_act = by_act
+            .into_iter()
+            .map(|(act, rate)| ActQuestionRoomRate { act, rate })
+            .collect();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::{Character, RunFileBuilder, RunsDirFixture};
+
+    fn path(symbols: &[&str]) -> Vec<Option<String>> {
+        symbols.iter().map(|s| Some(s.to_string())).collect()
+    }
+
+    #[test]
+    fn test_fight_on_the_question_room_floor() {
+        // Floors 2, 3 and 5 are unknown rooms; there are fights on 1, 3 and 4
+        let summary = summarize_question_rooms(&path(&["M", "?", "?", "M", "?"]), [1, 3, 4]);
+        assert_eq!((summary.rooms, summary.fought), (3, 1));
+    }
+
+    #[test]
+    fn test_fight_on_neighbouring_floor_does_not_count() {
+        // A fight on the floor before or after a `?` room is a different room
+        let before = summarize_question_rooms(&path(&["M", "?"]), [1]);
+        assert_eq!((before.rooms, before.fought), (1, 0));
+        let after = summarize_question_rooms(&path(&["?", "M"]), [2]);
+        assert_eq!((after.rooms, after.fought), (1, 0));
+        let first_floor = summarize_question_rooms(&path(&["?"]), [1]);
+        assert_eq!(first_floor.fought, 1);
+    }
+
+    #[test]
+    fn test_question_rooms_split_by_act() {
+        // Index 16 is floor 17 (act 1), index 17 is floor 18 (act 2)
+        let mut symbols = vec![Some("M".to_string()); 18];
+        symbols[16] = Some(QUESTION_ROOM.to_string());
+        symbols[17] = Some(QUESTION_ROOM.to_string());
+        symbols.push(None);
+        let summary = summarize_question_rooms(&symbols, [18]);
+        assert_eq!(
+            summary.by_act,
+            vec![
+                ActQuestionRooms {
+                    act: 1,
+                    rooms: 1,
+                    fought: 0,
+                },
+                ActQuestionRooms {
+                    act: 2,
+                    rooms: 1,
+                    fought: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_stats_from_parsed_run() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("events")
+                .character(Character::Watcher)
+                .path(&["M", "?", "?", "$", "?"])
+                .fight(1, "Jaw Worm", 4)
+                .fight(3, "Cultist", 6),
+        );
+
+        let (runs, _) = fixture.load();
+        assert_eq!(runs[0].question_rooms, 3);
+        assert_eq!(runs[0].question_rooms_fought, 1);
+
+        let mut hidden = runs[0].clone();
+        hidden.hidden = true;
+        let stats = EventStats::from_runs(&[runs[0].clone(), hidden]);
+        assert_eq!(stats.runs, 1);
+        assert_eq!(stats.question_rooms.rooms, 3);
+        assert!((stats.question_rooms.fight_rate - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.by_act.len(), 1);
+        assert_eq!(stats.by_act[0].act, 1);
+    }
+}
@@ -0,0 +1,757 @@
+//! Export file versioning and import
+//!
+//! Export files carry a `schema_version`. Files written by older versions of
+//! the app are upgraded step by step through the migration functions below
+//! before being deserialized into the current [`ExportData`] layout.
+//!
+//! Exports also carry an [`ExportChecksum`] over their runs, which import
+//! checks first so a cut-off or edited file is rejected instead of imported.
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use utoipa::ToSchema;
+
+use super::modded::modded_content;
+use super::{
+    ascension_stats, score_per_floor, CharacterId, ExportData, Ruleset, RunExclusion, RunFilter,
+    RunMetrics, RunSelection, StatsOptions, TimeWindow,
+};
+
+/// Current export schema version
+///
+/// Bump this whenever `ExportData` or `RunMetrics` change in a way older
+/// files can't be deserialized from, and add a matching migration.
+pub const EXPORT_SCHEMA_VERSION: u32 = 8;
+
+/// Version assumed for files without a `schema_version` field
+const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+/// A migration upgrades a raw export document by exactly one version
+type Migration = fn(&mut Value) -> Result<(), ImportError>;
+
+/// Migrations indexed by source version: `MIGRATIONS[0]` upgrades v1 to v2
+const MIGRATIONS: &[Migration] = &[
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+];
+
+/// First version whose files carry `ascension_stats`
+const ASCENSION_STATS_VERSION: u32 = 4;
+
+/// Errors that can occur while importing an export file
+#[derive(Debug)]
+pub enum ImportError {
+    /// The file is not valid JSON or does not match the expected layout
+    Invalid(String),
+    /// The file was written by a newer version of the app
+    UnsupportedVersion { found: u32, supported: u32 },
+    /// The file ends early or holds fewer runs than it was written with
+    Truncated(String),
+    /// The runs don't match the checksum they were exported with
+    ChecksumMismatch { expected: String, found: String },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Invalid(msg) => write!(f, "Invalid export file: {}", msg),
+            ImportError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "Export schema version {} is newer than this app supports (up to {}); please update the app",
+                found, supported
+            ),
+            ImportError::Truncated(msg) => write!(
+                f,
+                "Export file is incomplete ({}); it was probably cut off while copying or uploading",
+                msg
+            ),
+            ImportError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "Export file was modified after it was exported: runs checksum is {} but the file says {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Read the schema version of a raw export document
+fn schema_version(doc: &Value) -> Result<u32, ImportError> {
+    match doc.get("schema_version") {
+        None => Ok(LEGACY_SCHEMA_VERSION),
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| ImportError::Invalid("schema_version must be an integer".to_string())),
+    }
+}
+
+/// Parse an export document, upgrading older schema versions to the current one
+pub fn import_export_data(json: &str) -> Result<ExportData, ImportError> {
+    let doc: Value = serde_json::from_str(json).map_err(|e| {
+        if e.is_eof() {
+            ImportError::Truncated(e.to_string())
+        } else {
+            ImportError::Invalid(e.to_string())
+        }
+    })?;
+    import_export_value(doc)
+}
+
+/// Check an export document without keeping the imported data
+///
+/// Runs every check import does, including the checksum.
+pub fn verify_export_data(json: &str) -> Result<ExportVerification, ImportError> {
+    let data = import_export_data(json)?;
+    Ok(ExportVerification {
+        schema_version: data.schema_version,
+        runs: data.runs.len(),
+        checksum_verified: data.checksum.is_some(),
+    })
+}
+
+/// Upgrade and deserialize an already parsed export document
+pub fn import_export_value(mut doc: Value) -> Result<ExportData, ImportError> {
+    if !doc.is_object() {
+        return Err(ImportError::Invalid("expected a JSON object".to_string()));
+    }
+
+    let mut version = schema_version(&doc)?;
+    if version > EXPORT_SCHEMA_VERSION {
+        return Err(ImportError::UnsupportedVersion {
+            found: version,
+            supported: EXPORT_SCHEMA_VERSION,
+        });
+    }
+    if version < LEGACY_SCHEMA_VERSION {
+        return Err(ImportError::Invalid(format!(
+            "unknown schema_version {}",
+            version
+        )));
+    }
+    verify_checksum(&doc)?;
+
+    let source_version = version;
+    while version < EXPORT_SCHEMA_VERSION {
+        MIGRATIONS[(version - LEGACY_SCHEMA_VERSION) as usize](&mut doc)?;
+        version += 1;
+        doc["schema_version"] = Value::from(version);
+    }
+
+    let mut data: ExportData =
+        serde_json::from_value(doc).map_err(|e| ImportError::Invalid(e.to_string()))?;
+    if source_version < ASCENSION_STATS_VERSION {
+        // Like their character stats, older files' breakdown covers every run
+        // in the file
+        let options = StatsOptions {
+            include_hidden: true,
+            ..Default::default()
+        };
+        data.ascension_stats = ascension_stats(&data.runs, &options);
+    }
+    if data.checksum.is_some() {
+        // The checksum covered the runs as written; migrations and newly
+        // defaulted fields change them, so it has to cover the upgraded runs
+        data.checksum = serde_json::to_value(&data.runs)
+            .ok()
+            .map(|runs| ExportChecksum::of_runs(&runs));
+    }
+    Ok(data)
+}
+
+/// Checksum algorithm written into exports
+pub const CHECKSUM_ALGORITHM: &str = "sha256";
+
+/// Integrity check over the runs of an export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ExportChecksum {
+    /// Hash algorithm, see [`CHECKSUM_ALGORITHM`]
+    pub algorithm: String,
+    /// Number of runs the digest covers
+    pub runs: usize,
+    /// Lowercase hex digest of the canonical `runs` array
+    pub digest: String,
+}
+
+impl ExportChecksum {
+    /// Checksum of a runs array as it appears in the export
+    pub fn of_runs(runs: &Value) -> Self {
+        let mut hasher = Sha256::new();
+        write_canonical(runs, &mut hasher);
+        Self {
+            algorithm: CHECKSUM_ALGORITHM.to_string(),
+            runs: runs.as_array().map_or(0, Vec::len),
+            digest: format!("{:x}", hasher.finalize()),
+        }
+    }
+}
+
+/// Result of checking an export file without importing it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ExportVerification {
+    /// Schema version after upgrading
+    pub schema_version: u32,
+    /// Runs in the file
+    pub runs: usize,
+    /// Whether the file had a checksum to verify; exports written before
+    /// checksums were added don't
+    pub checksum_verified: bool,
+}
+
+/// Feed the canonical form of a JSON value to a hasher
+///
+/// Object keys are sorted by their UTF-8 bytes and no whitespace is written;
+/// strings and numbers use serde_json's formatting. This doesn't depend on
+/// the order fields were written in, so files re-saved by another tool or
+/// platform still verify.
+fn write_canonical(value: &Value, hasher: &mut Sha256) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            hasher.update(b"{");
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    hasher.update(b",");
+                }
+                hasher.update(Value::from(key.as_str()).to_string());
+                hasher.update(b":");
+                write_canonical(value, hasher);
+            }
+            hasher.update(b"}");
+        }
+        Value::Array(items) => {
+            hasher.update(b"[");
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    hasher.update(b",");
+                }
+                write_canonical(item, hasher);
+            }
+            hasher.update(b"]");
+        }
+        other => hasher.update(other.to_string()),
+    }
+}
+
+/// Check the runs of a raw export document against its checksum, if it has one
+///
+/// Done before any migration, as the checksum covers the runs as written.
+fn verify_checksum(doc: &Value) -> Result<(), ImportError> {
+    let expected: ExportChecksum = match doc.get("checksum") {
+        None | Some(Value::Null) => return Ok(()),
+        Some(checksum) => serde_json::from_value(checksum.clone())
+            .map_err(|e| ImportError::Invalid(format!("invalid checksum: {}", e)))?,
+    };
+    if expected.algorithm != CHECKSUM_ALGORITHM {
+        return Err(ImportError::Invalid(format!(
+            "unsupported checksum algorithm {}",
+            expected.algorithm
+        )));
+    }
+
+    let runs = doc.get("runs").unwrap_or(&Value::Null);
+    let found = ExportChecksum::of_runs(runs);
+    if found.runs < expected.runs {
+        return Err(ImportError::Truncated(format!(
+            "{} of {} runs",
+            found.runs, expected.runs
+        )));
+    }
+    if found.digest != expected.digest {
+        return Err(ImportError::ChecksumMismatch {
+            expected: expected.digest,
+            found: found.digest,
+        });
+    }
+    Ok(())
+}
+
+/// Apply a function to every run object in the document
+fn for_each_run(
+    doc: &mut Value,
+    mut f: impl FnMut(&mut serde_json::Map<String, Value>),
+) -> Result<(), ImportError> {
+    let runs = doc
+        .get_mut("runs")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| ImportError::Invalid("missing runs array".to_string()))?;
+
+    for run in runs {
+        let run = run
+            .as_object_mut()
+            .ok_or_else(|| ImportError::Invalid("run entries must be objects".to_string()))?;
+        f(run);
+    }
+    Ok(())
+}
+
+/// v1 -> v2: runs gained the `hidden` flag
+fn migrate_v1_to_v2(doc: &mut Value) -> Result<(), ImportError> {
+    for_each_run(doc, |run| {
+        run.entry("hidden").or_insert(Value::Bool(false));
+    })
+}
+
+/// v2 -> v3: exports gained a UTC datetime, the exporter's timezone offset,
+/// the app version and the runs path
+///
+/// Older exports only have the epoch timestamp, so the datetime is derived
+/// from it and the offset is taken as UTC.
+fn migrate_v2_to_v3(doc: &mut Value) -> Result<(), ImportError> {
+    let timestamp = doc
+        .get("export_timestamp")
+        .and_then(Value::as_i64)
+        .ok_or_else(|| ImportError::Invalid("export_timestamp must be an integer".to_string()))?;
+    let datetime = DateTime::from_timestamp(timestamp, 0)
+        .ok_or_else(|| ImportError::Invalid(format!("timestamp {} out of range", timestamp)))?;
+
+    let doc = doc.as_object_mut().expect("checked by import_export_value");
+    doc.entry("export_datetime")
+        .or_insert_with(|| Value::from(datetime.to_rfc3339()));
+    doc.entry("export_timezone_offset_minutes")
+        .or_insert(Value::from(0));
+    doc.entry("app_version").or_insert(Value::Null);
+    doc.entry("runs_path").or_insert(Value::Null);
+    Ok(())
+}
+
+/// v3 -> v4: exports gained per-ascension results
+///
+/// The section is left empty here and rebuilt from the runs once the
+/// document has been deserialized.
+fn migrate_v3_to_v4(doc: &mut Value) -> Result<(), ImportError> {
+    let doc = doc.as_object_mut().expect("checked by import_export_value");
+    doc.entry("ascension_stats")
+        .or_insert(Value::Array(Vec::new()));
+    Ok(())
+}
+
+/// v4 -> v5: filters gained exclusion lists
+///
+/// The `exclude_daily` and `exclude_seeded` flags become entries of the new
+/// `exclude` list.
+fn migrate_v4_to_v5(doc: &mut Value) -> Result<(), ImportError> {
+    let Some(filters) = doc.get_mut("filters") else {
+        return Ok(());
+    };
+    let filters = filters
+        .as_object_mut()
+        .ok_or_else(|| ImportError::Invalid("filters must be an object".to_string()))?;
+    let mut exclude = Vec::new();
+    for (flag, exclusion) in [
+        ("exclude_daily", RunExclusion::Dailies),
+        ("exclude_seeded", RunExclusion::Seeded),
+    ] {
+        if filters.remove(flag).and_then(|v| v.as_bool()) == Some(true) {
+            exclude.push(Value::from(exclusion.name()));
+        }
+    }
+    filters.insert("exclude".to_string(), Value::Array(exclude));
+    Ok(())
+}
+
+/// v5 -> v6: runs gained a ruleset
+///
+/// It is derived from the mode flags stored on each run, the same way run
+/// files are classified.
+fn migrate_v5_to_v6(doc: &mut Value) -> Result<(), ImportError> {
+    for_each_run(doc, |run| {
+        let flag = |name: &str| run.get(name).and_then(Value::as_bool).unwrap_or(false);
+        let ruleset = Ruleset::from_flags(
+            flag("is_daily"),
+            flag("is_trial"),
+            flag("is_endless"),
+            flag("seeded"),
+        );
+        run.insert("ruleset".to_string(), Value::from(ruleset.name()));
+    })
+}
+
+/// v6 -> v7: runs gained modded content detection
+///
+/// Runs are checked against the base game database from the character,
+/// deck and relics stored in the file.
+fn migrate_v6_to_v7(doc: &mut Value) -> Result<(), ImportError> {
+    for_each_run(doc, |run| {
+        let names = |key: &str| -> Vec<String> {
+            run.get(key)
+                .and_then(Value::as_array)
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|v| v.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let character = run
+            .get("character")
+            .and_then(Value::as_str)
+            .map(CharacterId::from_dir_name);
+        let found = character.map_or_else(Vec::new, |character| {
+            modded_content(&character, &names("master_deck"), &names("relics"))
+        });
+        run.insert("is_modded".to_string(), Value::Bool(!found.is_empty()));
+        run.insert("modded_content".to_string(), Value::from(found));
+    })
+}
+
+/// v7 -> v8: runs gained score per floor
+fn migrate_v7_to_v8(doc: &mut Value) -> Result<(), ImportError> {
+    for_each_run(doc, |run| {
+        let number = |name: &str| {
+            run.get(name)
+                .and_then(Value::as_i64)
+                .and_then(|v| i32::try_from(v).ok())
+                .unwrap_or(0)
+        };
+        let pace = score_per_floor(number("score"), number("floor_reached"));
+        run.insert("score_per_floor".to_string(), Value::from(pace));
+    })
+}
+
+/// Shorten a runs path for sharing
+///
+/// Paths inside the home folder become `~/...`; anything else is cut down to
+/// its last folder name.
+pub fn redact_path(path: &Path, home: Option<&Path>) -> String {
+    if let Some(relative) = home.and_then(|home| path.strip_prefix(home).ok()) {
+        return Path::new("~").join(relative).display().to_string();
+    }
+    path.file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
+
+/// Which runs an export covers
+///
+/// The character stats in the export are computed over the same runs. An
+/// empty filter exports everything that isn't hidden.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct ExportFilter {
+    /// Characters and kinds of run to keep
+    #[serde(flatten)]
+    pub selection: RunSelection,
+    /// Only runs that ended at or after this time (unix seconds)
+    pub since: Option<i64>,
+    /// Only runs that ended before this time (unix seconds)
+    pub until: Option<i64>,
+    /// Lowest ascension level included
+    pub min_ascension: Option<i32>,
+    /// Highest ascension level included
+    pub max_ascension: Option<i32>,
+}
+
+impl ExportFilter {
+    /// Reject ranges whose lower bound is above the upper bound, and
+    /// characters both included and excluded
+    pub fn validate(&self) -> Result<(), String> {
+        RunFilter::from(self).validate()
+    }
+
+    /// The date bounds of the filter
+    pub fn window(&self) -> TimeWindow {
+        TimeWindow {
+            since: self.since,
+            until: self.until,
+        }
+    }
+
+    /// Whether a run is part of the export
+    ///
+    /// Runs without a timestamp are left out when a date bound is set.
+    pub fn matches(&self, run: &RunMetrics) -> bool {
+        RunFilter::from(self).matches(run)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Character;
+
+    const EXPORT_V1: &str = include_str!("../../tests/fixtures/export_v1.json");
+    const EXPORT_V2: &str = include_str!("../../tests/fixtures/export_v2.json");
+    const EXPORT_V3: &str = include_str!("../../tests/fixtures/export_v3.json");
+    const EXPORT_V4: &str = include_str!("../../tests/fixtures/export_v4.json");
+    const EXPORT_V5: &str = include_str!("../../tests/fixtures/export_v5.json");
+    const EXPORT_V6: &str = include_str!("../../tests/fixtures/export_v6.json");
+    const EXPORT_V7: &str = include_str!("../../tests/fixtures/export_v7.json");
+    const EXPORT_V8: &str = include_str!("../../tests/fixtures/export_v8.json");
+
+    #[test]
+    fn test_import_v1_export() {
+        let data = import_export_data(EXPORT_V1).unwrap();
+        assert_eq!(data.schema_version, EXPORT_SCHEMA_VERSION);
+        assert_eq!(data.runs.len(), 2);
+        assert!(data.runs.iter().all(|r| !r.hidden));
+        assert_eq!(data.character_stats.len(), 1);
+    }
+
+    #[test]
+    fn test_import_v2_export() {
+        let data = import_export_data(EXPORT_V2).unwrap();
+        assert_eq!(data.schema_version, EXPORT_SCHEMA_VERSION);
+        assert!(data.runs[0].hidden);
+        assert_eq!(data.export_datetime.timestamp(), data.export_timestamp);
+        assert_eq!(data.export_timezone_offset_minutes, 0);
+        assert_eq!(data.app_version, None);
+        assert_eq!(data.runs_path, None);
+    }
+
+    #[test]
+    fn test_import_v3_export() {
+        let data = import_export_data(EXPORT_V3).unwrap();
+        assert_eq!(data.schema_version, EXPORT_SCHEMA_VERSION);
+        assert_eq!(data.ascension_stats.len(), 1);
+        let a5 = &data.ascension_stats[0];
+        assert_eq!((a5.ascension_level, a5.runs, a5.wins), (5, 2, 1));
+        assert_eq!(a5.avg_floor, data.character_stats[0].avg_floor);
+    }
+
+    #[test]
+    fn test_import_v4_export() {
+        let data = import_export_data(EXPORT_V4).unwrap();
+        assert_eq!(data.schema_version, EXPORT_SCHEMA_VERSION);
+        assert_eq!(data.filters, ExportFilter::default());
+
+        let mut doc: Value = serde_json::from_str(EXPORT_V4).unwrap();
+        doc["filters"]["character"] = Value::from("IRONCLAD");
+        doc["filters"]["exclude_daily"] = Value::Bool(true);
+        let data = import_export_value(doc).unwrap();
+        assert_eq!(data.filters.selection.characters, [Character::Ironclad]);
+        assert_eq!(data.filters.selection.exclude, [RunExclusion::Dailies]);
+    }
+
+    #[test]
+    fn test_import_v5_export() {
+        let data = import_export_data(EXPORT_V5).unwrap();
+        assert_eq!(data.schema_version, EXPORT_SCHEMA_VERSION);
+        assert!(data.runs.iter().all(|r| r.ruleset == Ruleset::Standard));
+
+        let mut doc: Value = serde_json::from_str(EXPORT_V5).unwrap();
+        doc["runs"][0]["seeded"] = Value::Bool(true);
+        doc["runs"][1]["seeded"] = Value::Bool(true);
+        doc["runs"][1]["is_daily"] = Value::Bool(true);
+        let data = import_export_value(doc).unwrap();
+        assert_eq!(data.runs[0].ruleset, Ruleset::Seeded);
+        assert_eq!(data.runs[1].ruleset, Ruleset::Daily);
+    }
+
+    #[test]
+    fn test_import_v6_export() {
+        let data = import_export_data(EXPORT_V6).unwrap();
+        assert_eq!(data.schema_version, EXPORT_SCHEMA_VERSION);
+        assert!(data.runs.iter().all(|r| !r.is_modded));
+
+        let mut doc: Value = serde_json::from_str(EXPORT_V6).unwrap();
+        doc["runs"][0]["master_deck"][1] = Value::from("Reap and Sow+1");
+        doc["runs"][1]["character"] = Value::from("HERMIT");
+        let data = import_export_value(doc).unwrap();
+        assert!(data.runs[0].is_modded);
+        assert_eq!(data.runs[0].modded_content, ["Reap and Sow+1"]);
+        assert_eq!(data.runs[1].modded_content, ["HERMIT"]);
+    }
+
+    #[test]
+    fn test_import_v7_export() {
+        let data = import_export_data(EXPORT_V7).unwrap();
+        assert_eq!(data.schema_version, EXPORT_SCHEMA_VERSION);
+        // 1200 points over 51 floors, 400 over 22
+        assert!((data.runs[0].score_per_floor - 1200.0 / 51.0).abs() < 1e-9);
+        assert!((data.runs[1].score_per_floor - 400.0 / 22.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_import_current_export() {
+        let data = import_export_data(EXPORT_V8).unwrap();
+        assert_eq!(data.schema_version, 8);
+        assert!(data.runs[0].hidden);
+        assert_eq!(data.runs[1].ruleset, Ruleset::Custom);
+        assert!(data.runs[1].seeded);
+        assert_eq!(data.runs[1].modded_content, ["Reap and Sow"]);
+        assert_eq!(data.ascension_stats[0].win_rate, 0.5);
+        assert_eq!(data.export_timezone_offset_minutes, 120);
+        assert_eq!(data.app_version.as_deref(), Some("0.1.0"));
+        assert_eq!(
+            data.runs_path.as_deref(),
+            Some("~/.steam/SlayTheSpire/runs")
+        );
+    }
+
+    #[test]
+    fn test_checksum_verifies_on_import() {
+        let mut data = import_export_data(EXPORT_V8).unwrap();
+        let runs = serde_json::to_value(&data.runs).unwrap();
+        data.checksum = Some(ExportChecksum::of_runs(&runs));
+        let json = serde_json::to_string(&data).unwrap();
+
+        let verification = verify_export_data(&json).unwrap();
+        assert!(verification.checksum_verified);
+        assert_eq!(verification.runs, data.runs.len());
+        assert!(!verify_export_data(EXPORT_V8).unwrap().checksum_verified);
+
+        // Key order and whitespace don't matter
+        let doc: Value = serde_json::from_str(&json).unwrap();
+        let pretty = serde_json::to_string_pretty(&doc).unwrap();
+        assert!(import_export_data(&pretty).is_ok());
+
+        let mut tampered = doc.clone();
+        tampered["runs"][0]["floor_reached"] = Value::from(57);
+        assert!(matches!(
+            import_export_value(tampered),
+            Err(ImportError::ChecksumMismatch { .. })
+        ));
+
+        let mut shortened = doc;
+        shortened["runs"].as_array_mut().unwrap().pop();
+        let err = import_export_value(shortened).unwrap_err();
+        assert!(matches!(err, ImportError::Truncated(_)));
+        assert!(err.to_string().contains("cut off"));
+
+        assert!(matches!(
+            import_export_data(&json[..json.len() / 2]),
+            Err(ImportError::Truncated(_))
+        ));
+    }
+
+    #[test]
+    fn test_redact_path() {
+        let home = Path::new("/home/player");
+        assert_eq!(
+            redact_path(Path::new("/home/player/games/runs"), Some(home)),
+            Path::new("~").join("games/runs").display().to_string()
+        );
+        assert_eq!(redact_path(Path::new("/mnt/sts/runs"), Some(home)), "runs");
+        assert_eq!(redact_path(Path::new("/mnt/sts/runs"), None), "runs");
+    }
+
+    #[test]
+    fn test_current_export_round_trip() {
+        let data = import_export_data(EXPORT_V8).unwrap();
+        let json = serde_json::to_string(&data).unwrap();
+        let again = import_export_data(&json).unwrap();
+        assert_eq!(again.runs.len(), data.runs.len());
+        assert_eq!(again.ascension_stats, data.ascension_stats);
+        assert_eq!(again.export_timestamp, data.export_timestamp);
+        assert_eq!(again.filters, data.filters);
+        assert_eq!(again.filters.selection.exclude, [RunExclusion::Seeded]);
+    }
+
+    #[test]
+    fn test_migrated_export_round_trip() {
+        let data = import_export_data(EXPORT_V2).unwrap();
+        let json = serde_json::to_string(&data).unwrap();
+        let again = import_export_data(&json).unwrap();
+        assert!(!again.ascension_stats.is_empty());
+        assert_eq!(again.ascension_stats, data.ascension_stats);
+    }
+
+    #[test]
+    fn test_migrated_export_with_checksum_reimports() {
+        let mut doc: Value = serde_json::from_str(EXPORT_V1).unwrap();
+        let checksum = ExportChecksum::of_runs(&doc["runs"]);
+        doc["checksum"] = serde_json::to_value(checksum).unwrap();
+
+        let data = import_export_value(doc).unwrap();
+        assert_eq!(data.schema_version, EXPORT_SCHEMA_VERSION);
+        let json = serde_json::to_string(&data).unwrap();
+        let verification = verify_export_data(&json).unwrap();
+        assert!(verification.checksum_verified);
+        let again = import_export_data(&json).unwrap();
+        assert_eq!(again.checksum, data.checksum);
+        assert_eq!(again.runs.len(), data.runs.len());
+    }
+
+    #[test]
+    fn test_reject_newer_version() {
+        let mut doc: Value = serde_json::from_str(EXPORT_V8).unwrap();
+        doc["schema_version"] = Value::from(EXPORT_SCHEMA_VERSION + 1);
+        let err = import_export_value(doc).unwrap_err();
+        assert!(matches!(err, ImportError::UnsupportedVersion { .. }));
+        assert!(err.to_string().contains("update the app"));
+    }
+
+    #[test]
+    fn test_reject_invalid_documents() {
+        assert!(matches!(
+            import_export_data("[]"),
+            Err(ImportError::Invalid(_))
+        ));
+        assert!(matches!(
+            import_export_data("{\"schema_version\": \"two\"}"),
+            Err(ImportError::Invalid(_))
+        ));
+        assert!(matches!(
+            import_export_data("{\"character_stats\": []}"),
+            Err(ImportError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_filter_matches() {
+        let mut run = sample_run("a", Character::Ironclad, true);
+        run.ascension_level = 20;
+        run.timestamp = Some(1_700_000_000);
+        assert!(ExportFilter::default().matches(&run));
+
+        let season = ExportFilter {
+            selection: RunSelection {
+                characters: vec![Character::Ironclad],
+                exclude: vec![RunExclusion::Dailies],
+                ..Default::default()
+            },
+            since: Some(1_690_000_000),
+            until: Some(1_710_000_000),
+            min_ascension: Some(20),
+            ..Default::default()
+        };
+        assert!(season.matches(&run));
+
+        run.ruleset = Ruleset::Daily;
+        assert!(!season.matches(&run));
+        run.ruleset = Ruleset::Standard;
+        run.timestamp = None;
+        assert!(!season.matches(&run));
+        run.timestamp = Some(1_710_000_000);
+        assert!(!season.matches(&run));
+    }
+
+    #[test]
+    fn test_export_filter_validation() {
+        let inverted = ExportFilter {
+            min_ascension: Some(15),
+            max_ascension: Some(10),
+            ..Default::default()
+        };
+        assert!(inverted.validate().is_err());
+        let empty_window = ExportFilter {
+            since: Some(100),
+            until: Some(100),
+            ..Default::default()
+        };
+        assert!(empty_window.validate().is_err());
+        let conflict = ExportFilter {
+            selection: RunSelection {
+                characters: vec![Character::Watcher],
+                exclude_characters: vec![Character::Watcher],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(conflict.validate().is_err());
+        assert!(ExportFilter::default().validate().is_ok());
+    }
+}
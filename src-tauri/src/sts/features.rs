@@ -0,0 +1,252 @@
+//! Run feature vectors for outcome models
+//!
+//! Each run becomes a flat row of numbers in the order of
+//! [`FEATURE_COLUMNS`], with the victory label kept apart. The column order is
+//! part of the API: new features are only ever appended, so a model trained
+//! on an older export keeps reading the same columns. Score and floor reached
+//! are left out, as they give the outcome away.
+//!
+//! Most features are read at the end of the run, so they still grow with how
+//! far it got: `deck_size`, `relic_count`, `elites_killed`, `max_hp_at_end`
+//! and the damage of acts 2 to 4 (`damage_act4` is 0 for every run that
+//! didn't reach Act 4) tell long runs from short ones. A model meant to
+//! predict the outcome early should stick to `ascension_level`,
+//! `act1_elites` and `damage_act1`.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{act_for_floor, CharacterId, RunMetrics};
+
+/// Names of the values in [`run_features`], in order
+pub const FEATURE_COLUMNS: &[&str] = &[
+    "ascension_level",
+    "deck_size",
+    "attack_count",
+    "skill_count",
+    "power_count",
+    "upgraded_cards",
+    "cards_removed",
+    "cards_added",
+    "starters_remaining",
+    "relic_count",
+    "act1_elites",
+    "elites_killed",
+    "campfires_rested",
+    "campfires_upgraded",
+    "campfire_rest_ratio",
+    "campfire_upgrade_ratio",
+    "shops_visited",
+    "cards_purchased",
+    "potions_used",
+    "damage_act1",
+    "damage_act2",
+    "damage_act3",
+    "damage_act4",
+    "max_hp_at_end",
+    "question_rooms",
+    "question_rooms_fought",
+];
+
+/// Share of `part` in `total`, or 0 when there is nothing to share
+fn ratio(part: i32, total: i32) -> f64 {
+    if total > 0 {
+        part as f64 / total as f64
+    } else {
+        0.0
+    }
+}
+
+/// Feature values of a run, matching [`FEATURE_COLUMNS`]
+///
+/// Campfire ratios are shares of the rests and upgrades taken, so they are
+/// 0 for runs that did neither. Damage per act is 0 for acts the run didn't
+/// reach and for exports written before it was recorded.
+pub fn run_features(run: &RunMetrics) -> Vec<f64> {
+    let act1_elites = run
+        .encounters
+        .iter()
+        .filter(|e| e.elite && act_for_floor(e.floor) == 1)
+        .count() as i32;
+    let campfires = run.campfires_rested.saturating_add(run.campfires_upgraded);
+    let damage = |act: usize| run.damage_by_act.get(act - 1).copied().unwrap_or(0);
+    let counts = [
+        run.ascension_level,
+        run.deck_size,
+        run.attack_count,
+        run.skill_count,
+        run.power_count,
+        run.upgraded_cards,
+        run.cards_removed,
+        run.cards_added,
+        run.starters_remaining,
+        run.relic_count,
+        act1_elites,
+        run.elites_killed,
+        run.campfires_rested,
+        run.campfires_upgraded,
+    ];
+    let mut features: Vec<f64> = counts.into_iter().map(f64::from).collect();
+    features.push(ratio(run.campfires_rested, campfires));
+    features.push(ratio(run.campfires_upgraded, campfires));
+    features.extend(
+        [
+            run.shops_visited,
+            run.cards_purchased,
+            run.potions_used,
+            damage(1),
+            damage(2),
+            damage(3),
+            damage(4),
+            run.max_hp_at_end,
+            run.question_rooms,
+            run.question_rooms_fought,
+        ]
+        .map(f64::from),
+    );
+    features
+}
+
+/// Feature values and outcome of one run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FeatureRow {
+    pub play_id: String,
+    pub character: CharacterId,
+    /// Values in the order of `columns`
+    pub features: Vec<f64>,
+    /// Label: whether the run was won
+    pub victory: bool,
+}
+
+/// Feature vectors of a set of runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FeatureSet {
+    /// Feature names, in the order of each row's `features`
+    pub columns: Vec<String>,
+    pub rows: Vec<FeatureRow>,
+}
+
+/// Feature vectors of all runs, ignoring hidden runs
+pub fn feature_set(runs: &[RunMetrics]) -> FeatureSet {
+    FeatureSet {
+        columns: FEATURE_COLUMNS.iter().map(|c| c.to_string()).collect(),
+        rows: runs
+            .iter()
+            .filter(|r| !r.hidden)
+            .map(|run| FeatureRow {
+                play_id: run.play_id.clone(),
+                character: run.character.clone(),
+                features: run_features(run),
+                victory: run.victory,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{Character, Encounter, RunFileBuilder, RunsDirFixture};
+
+    fn feature(features: &[f64], column: &str) -> f64 {
+        let index = FEATURE_COLUMNS.iter().position(|c| *c == column).unwrap();
+        features[index]
+    }
+
+    #[test]
+    fn test_column_order_is_stable() {
+        // Models depend on this order: append new columns, never reorder
+        assert_eq!(
+            FEATURE_COLUMNS,
+            [
+                "ascension_level",
+                "deck_size",
+                "attack_count",
+                "skill_count",
+                "power_count",
+                "upgraded_cards",
+                "cards_removed",
+                "cards_added",
+                "starters_remaining",
+                "relic_count",
+                "act1_elites",
+                "elites_killed",
+                "campfires_rested",
+                "campfires_upgraded",
+                "campfire_rest_ratio",
+                "campfire_upgrade_ratio",
+                "shops_visited",
+                "cards_purchased",
+                "potions_used",
+                "damage_act1",
+                "damage_act2",
+                "damage_act3",
+                "damage_act4",
+                "max_hp_at_end",
+                "question_rooms",
+                "question_rooms_fought",
+            ]
+        );
+        let run = sample_run("1", Character::Ironclad, true);
+        assert_eq!(run_features(&run).len(), FEATURE_COLUMNS.len());
+    }
+
+    #[test]
+    fn test_run_features() {
+        let mut run = sample_run("1", Character::Defect, false);
+        run.ascension_level = 15;
+        run.campfires_rested = 3;
+        run.campfires_upgraded = 1;
+        run.encounters = [(6, true), (12, false), (14, true), (25, true)]
+            .map(|(floor, elite)| Encounter {
+                enemies: "Lagavulin".to_string(),
+                floor,
+                hp_after: None,
+                elite,
+                turns: None,
+            })
+            .to_vec();
+        run.damage_by_act = vec![40, 25, 0, 0];
+
+        let features = run_features(&run);
+        assert_eq!(feature(&features, "ascension_level"), 15.0);
+        assert_eq!(feature(&features, "act1_elites"), 2.0);
+        assert_eq!(feature(&features, "campfire_rest_ratio"), 0.75);
+        assert_eq!(feature(&features, "campfire_upgrade_ratio"), 0.25);
+        assert_eq!(feature(&features, "damage_act2"), 25.0);
+
+        // No campfires and no per-act damage recorded
+        let features = run_features(&sample_run("2", Character::Defect, true));
+        assert_eq!(feature(&features, "campfire_rest_ratio"), 0.0);
+        assert_eq!(feature(&features, "damage_act1"), 0.0);
+    }
+
+    #[test]
+    fn test_damage_by_act_from_run_file() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("acts")
+                .fight(3, "Jaw Worm", 8)
+                .fight(10, "Gremlin Nob", 20)
+                .fight(20, "Chosen", 12)
+                .fight(55, "Spire Shield", 30),
+        );
+
+        let run = &fixture.load().0[0];
+        assert_eq!(run.damage_by_act, [28, 12, 0, 30]);
+    }
+
+    #[test]
+    fn test_feature_set_leaves_out_hidden_runs() {
+        let mut hidden = sample_run("hidden", Character::Ironclad, true);
+        hidden.hidden = true;
+        let runs = [sample_run("shown", Character::Ironclad, true), hidden];
+
+        let set = feature_set(&runs);
+        assert_eq!(set.columns, FEATURE_COLUMNS);
+        assert_eq!(set.rows.len(), 1);
+        assert_eq!(set.rows[0].play_id, "shown");
+        assert!(set.rows[0].victory);
+    }
+}
@@ -0,0 +1,458 @@
+//! Run selection shared by the runs, stats and export endpoints
+//!
+//! A [`RunSelection`] keeps runs of some characters, drops runs of others and
+//! drops whole kinds of run ([`RunExclusion`]). Game modes are matched on the
+//! run's [`Ruleset`] rather than its raw flags, so a custom daily counts as a
+//! daily and nothing else.
+//!
+//! A [`RunFilter`] adds every other condition runs can be picked by. HTTP
+//! queries, the Tauri commands' [`StatsOptions`] and [`ExportFilter`]s are
+//! converted into one before any run is looked at, so the same parameters
+//! select the same runs everywhere.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::ToSchema;
+
+use super::items::base_card_id;
+use super::{Character, ExportFilter, Ruleset, RunMetrics, StatsOptions};
+
+/// A kind of run that can be left out with `exclude`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunExclusion {
+    /// Daily Climb runs
+    Dailies,
+    /// Runs played on a seed the player chose
+    Seeded,
+    /// Endless mode runs
+    Endless,
+    /// Custom runs
+    Custom,
+    /// Every run that isn't [`Ruleset::Standard`]
+    NonStandard,
+    /// Runs with content that isn't from the base game, see
+    /// [`modded`](super::modded)
+    Modded,
+    /// Runs given up from the menu, see [`abandoned`]
+    Abandoned,
+    /// Runs hidden from statistics
+    Hidden,
+}
+
+impl RunExclusion {
+    /// Every exclusion, in documentation order
+    pub const ALL: [RunExclusion; 8] = [
+        RunExclusion::Dailies,
+        RunExclusion::Seeded,
+        RunExclusion::Endless,
+        RunExclusion::Custom,
+        RunExclusion::NonStandard,
+        RunExclusion::Modded,
+        RunExclusion::Abandoned,
+        RunExclusion::Hidden,
+    ];
+
+    /// Name used in the `exclude` parameter
+    pub fn name(self) -> &'static str {
+        match self {
+            RunExclusion::Dailies => "dailies",
+            RunExclusion::Seeded => "seeded",
+            RunExclusion::Endless => "endless",
+            RunExclusion::Custom => "custom",
+            RunExclusion::NonStandard => "non_standard",
+            RunExclusion::Modded => "modded",
+            RunExclusion::Abandoned => "abandoned",
+            RunExclusion::Hidden => "hidden",
+        }
+    }
+
+    /// Parse an `exclude` value, ignoring case and surrounding whitespace
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Self::ALL
+            .into_iter()
+            .find(|e| e.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Whether a run is of this kind
+    pub fn applies_to(self, run: &RunMetrics) -> bool {
+        match self {
+            RunExclusion::Dailies => run.ruleset == Ruleset::Daily,
+            RunExclusion::Seeded => run.ruleset == Ruleset::Seeded,
+            RunExclusion::Endless => run.ruleset == Ruleset::Endless,
+            RunExclusion::Custom => run.ruleset == Ruleset::Custom,
+            RunExclusion::NonStandard => run.ruleset != Ruleset::Standard,
+            RunExclusion::Modded => run.is_modded,
+            RunExclusion::Abandoned => abandoned(run),
+            RunExclusion::Hidden => run.hidden,
+        }
+    }
+}
+
+/// Whether a run was abandoned rather than lost
+///
+/// Run files don't say so directly. A lost run names what killed it in
+/// `killed_by`, so a loss without one was given up from the menu.
+pub fn abandoned(run: &RunMetrics) -> bool {
+    !run.victory && run.killed_by.is_none()
+}
+
+/// Characters and kinds of run to keep
+///
+/// The default keeps every run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct RunSelection {
+    /// Only runs of these characters (all characters if empty)
+    #[serde(alias = "character", deserialize_with = "one_or_more_characters")]
+    pub characters: Vec<Character>,
+    /// Leave out runs of these characters
+    pub exclude_characters: Vec<Character>,
+    /// Only runs played under these rulesets (all rulesets if empty)
+    pub rulesets: Vec<Ruleset>,
+    /// Leave out these kinds of run
+    pub exclude: Vec<RunExclusion>,
+}
+
+/// Read a filter's characters from a list, a single name or `null`
+///
+/// Filters used to hold one optional `character`, which older exports and
+/// callers still send.
+fn one_or_more_characters<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Character>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMore {
+        One(Character),
+        More(Vec<Character>),
+    }
+    Ok(match Option::<OneOrMore>::deserialize(deserializer)? {
+        None => Vec::new(),
+        Some(OneOrMore::One(character)) => vec![character],
+        Some(OneOrMore::More(characters)) => characters,
+    })
+}
+
+impl RunSelection {
+    /// Reject characters that are both included and excluded
+    pub fn validate(&self) -> Result<(), String> {
+        let conflicts: Vec<&str> = self
+            .characters
+            .iter()
+            .filter(|c| self.exclude_characters.contains(c))
+            .map(|c| c.dir_name())
+            .collect();
+        if conflicts.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Characters both included and excluded: {}",
+                conflicts.join(", ")
+            ))
+        }
+    }
+
+    /// Whether a run is kept
+    pub fn matches(&self, run: &RunMetrics) -> bool {
+        (self.characters.is_empty() || self.characters.iter().any(|c| run.character == *c))
+            && !self.exclude_characters.iter().any(|c| run.character == *c)
+            && (self.rulesets.is_empty() || self.rulesets.contains(&run.ruleset))
+            && !self.exclude.iter().any(|e| e.applies_to(run))
+    }
+}
+
+/// Range of times runs ended in
+///
+/// `since` is inclusive and `until` exclusive, both in unix seconds. Runs
+/// without a timestamp are left out once either bound is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct TimeWindow {
+    /// Only runs that ended at or after this time (unix seconds)
+    pub since: Option<i64>,
+    /// Only runs that ended before this time (unix seconds)
+    pub until: Option<i64>,
+}
+
+impl TimeWindow {
+    /// Reject windows that end before they start
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.since, self.until) {
+            (Some(since), Some(until)) if since >= until => {
+                Err(format!("since ({}) is not before until ({})", since, until))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether neither bound is set
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Whether a run ended inside the window
+    pub fn contains(&self, run: &RunMetrics) -> bool {
+        if self.is_unbounded() {
+            return true;
+        }
+        run.timestamp.is_some_and(|t| {
+            self.since.is_none_or(|since| t >= since) && self.until.is_none_or(|until| t < until)
+        })
+    }
+}
+
+/// Every condition runs can be picked by
+///
+/// The default keeps every run that isn't hidden. Names of relics, cards and
+/// encounters are compared ignoring case, and cards match upgraded or not.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunFilter {
+    /// Keep hidden runs
+    pub include_hidden: bool,
+    /// Characters and kinds of run to keep
+    pub selection: RunSelection,
+    /// When the runs ended
+    pub window: TimeWindow,
+    /// Lowest ascension level kept
+    pub min_ascension: Option<i32>,
+    /// Highest ascension level kept
+    pub max_ascension: Option<i32>,
+    /// Only victories (`true`) or only losses (`false`)
+    pub victory: Option<bool>,
+    /// Only runs that ended holding all of these relics
+    pub relics: Vec<String>,
+    /// Only runs whose final deck holds all of these cards
+    pub cards: Vec<String>,
+    /// Only runs that ended in a fight against this encounter
+    pub killed_by: Option<String>,
+}
+
+impl RunFilter {
+    /// Reject conflicting character filters, ascension ranges whose lower
+    /// bound is above the upper bound and windows ending before they start
+    pub fn validate(&self) -> Result<(), String> {
+        self.selection.validate()?;
+        if let (Some(min), Some(max)) = (self.min_ascension, self.max_ascension) {
+            if min > max {
+                return Err(format!(
+                    "min_ascension ({}) is above max_ascension ({})",
+                    min, max
+                ));
+            }
+        }
+        self.window.validate()
+    }
+
+    /// Whether a run is kept
+    pub fn matches(&self, run: &RunMetrics) -> bool {
+        (self.include_hidden || !run.hidden)
+            && self.selection.matches(run)
+            && self.window.contains(run)
+            && self
+                .min_ascension
+                .is_none_or(|min| run.ascension_level >= min)
+            && self
+                .max_ascension
+                .is_none_or(|max| run.ascension_level <= max)
+            && self.victory.is_none_or(|victory| run.victory == victory)
+            && self.killed_by.as_deref().is_none_or(|encounter| {
+                run.killed_by
+                    .as_deref()
+                    .is_some_and(|k| k.eq_ignore_ascii_case(encounter))
+            })
+            && self
+                .relics
+                .iter()
+                .all(|relic| run.relics.iter().any(|r| r.eq_ignore_ascii_case(relic)))
+            && self.holds_cards(run)
+    }
+
+    /// Whether a run's final deck holds every card of the filter
+    fn holds_cards(&self, run: &RunMetrics) -> bool {
+        if self.cards.is_empty() {
+            return true;
+        }
+        self.cards.iter().all(|card| {
+            let card = base_card_id(card);
+            run.master_deck
+                .iter()
+                .any(|c| base_card_id(c).eq_ignore_ascii_case(card))
+        })
+    }
+
+    /// The runs the filter keeps, in their original order
+    pub fn apply<'a>(&self, runs: &'a [RunMetrics]) -> Vec<&'a RunMetrics> {
+        runs.iter().filter(|r| self.matches(r)).collect()
+    }
+
+    /// Drop the runs the filter doesn't keep
+    pub fn retain(&self, runs: &mut Vec<RunMetrics>) {
+        runs.retain(|r| self.matches(r));
+    }
+}
+
+/// A selection alone keeps hidden runs; leave them out with
+/// [`RunExclusion::Hidden`]
+impl From<RunSelection> for RunFilter {
+    fn from(selection: RunSelection) -> Self {
+        Self {
+            include_hidden: true,
+            selection,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&StatsOptions> for RunFilter {
+    fn from(options: &StatsOptions) -> Self {
+        Self {
+            include_hidden: options.include_hidden,
+            selection: options.selection.clone(),
+            window: options.window,
+            ..Default::default()
+        }
+    }
+}
+
+/// Hidden runs are left to the export's [`StatsOptions`]
+impl From<&ExportFilter> for RunFilter {
+    fn from(filter: &ExportFilter) -> Self {
+        Self {
+            include_hidden: true,
+            selection: filter.selection.clone(),
+            window: filter.window(),
+            min_ascension: filter.min_ascension,
+            max_ascension: filter.max_ascension,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{RunFileBuilder, RunsDirFixture};
+
+    #[test]
+    fn test_exclusion_names() {
+        for exclusion in RunExclusion::ALL {
+            assert_eq!(RunExclusion::from_name(exclusion.name()), Some(exclusion));
+            let json = serde_json::to_value(exclusion).unwrap();
+            assert_eq!(json, exclusion.name());
+        }
+        assert_eq!(
+            RunExclusion::from_name(" Dailies "),
+            Some(RunExclusion::Dailies)
+        );
+        assert_eq!(RunExclusion::from_name("daily"), None);
+    }
+
+    #[test]
+    fn test_selection_matches() {
+        let ironclad = sample_run("a", Character::Ironclad, true);
+        let mut daily = sample_run("b", Character::TheSilent, true);
+        daily.is_daily = true;
+        daily.ruleset = Ruleset::Daily;
+        let mut abandoned = sample_run("c", Character::Watcher, false);
+        abandoned.killed_by = None;
+        let mut died = abandoned.clone();
+        died.play_id = "e".to_string();
+        died.killed_by = Some("Gremlin Nob".to_string());
+        let mut endless = sample_run("d", Character::Defect, true);
+        endless.is_endless = true;
+        endless.ruleset = Ruleset::Endless;
+        let mut custom = sample_run("f", Character::Ironclad, true);
+        custom.is_trial = true;
+        custom.seeded = true;
+        custom.ruleset = Ruleset::Custom;
+        custom.is_modded = true;
+        let runs = vec![ironclad, daily, abandoned, died, endless, custom];
+        let kept = |selection: &RunSelection| {
+            RunFilter::from(selection.clone())
+                .apply(&runs)
+                .iter()
+                .map(|r| r.play_id.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(kept(&RunSelection::default()).len(), 6);
+        let no_watcher = RunSelection {
+            exclude_characters: vec![Character::Watcher],
+            ..Default::default()
+        };
+        assert_eq!(kept(&no_watcher), ["a", "b", "d", "f"]);
+        let ordinary = RunSelection {
+            exclude: vec![
+                RunExclusion::Dailies,
+                RunExclusion::Endless,
+                RunExclusion::Abandoned,
+            ],
+            ..Default::default()
+        };
+        assert_eq!(kept(&ordinary), ["a", "e", "f"]);
+        // A seeded custom run is custom, not seeded
+        let not_seeded = RunSelection {
+            exclude: vec![RunExclusion::Seeded],
+            ..Default::default()
+        };
+        assert_eq!(kept(&not_seeded).len(), 6);
+        let standard = RunSelection {
+            exclude: vec![RunExclusion::NonStandard],
+            ..Default::default()
+        };
+        assert_eq!(kept(&standard), ["a", "c", "e"]);
+        let modes = RunSelection {
+            rulesets: vec![Ruleset::Daily, Ruleset::Custom],
+            ..Default::default()
+        };
+        assert_eq!(kept(&modes), ["b", "f"]);
+        let vanilla = RunSelection {
+            exclude: vec![RunExclusion::Modded],
+            ..Default::default()
+        };
+        assert_eq!(kept(&vanilla), ["a", "b", "c", "e", "d"]);
+    }
+
+    #[test]
+    fn test_conflicting_characters() {
+        let selection = RunSelection {
+            characters: vec![Character::Ironclad, Character::Watcher],
+            exclude_characters: vec![Character::Watcher],
+            ..Default::default()
+        };
+        let err = selection.validate().unwrap_err();
+        assert!(err.contains("WATCHER"), "{err}");
+        assert!(!err.contains("IRONCLAD"), "{err}");
+        assert!(RunSelection::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_reads_single_character() {
+        let selection: RunSelection = serde_json::from_str(r#"{"character": "IRONCLAD"}"#).unwrap();
+        assert_eq!(selection.characters, [Character::Ironclad]);
+        let selection: RunSelection = serde_json::from_str(r#"{"character": null}"#).unwrap();
+        assert!(selection.characters.is_empty());
+        let selection: RunSelection =
+            serde_json::from_str(r#"{"characters": ["WATCHER", "DEFECT"], "exclude": ["seeded"]}"#)
+                .unwrap();
+        assert_eq!(
+            selection.characters,
+            [Character::Watcher, Character::Defect]
+        );
+        assert_eq!(selection.exclude, [RunExclusion::Seeded]);
+    }
+
+    #[test]
+    fn test_parse_is_endless() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(&RunFileBuilder::new("a").set("is_endless", true));
+        fixture.add(&RunFileBuilder::new("b"));
+
+        let runs = fixture.load_sorted();
+        assert!(runs[0].is_endless);
+        assert_eq!(runs[0].ruleset, Ruleset::Endless);
+        assert!(!runs[1].is_endless);
+    }
+}
@@ -0,0 +1,398 @@
+//! Run file fixtures for tests and tooling (feature `test-utils`)
+//!
+//! [`RunFileBuilder`] writes raw run-file JSON the way the game does, with
+//! only the fields a test cares about set, and [`RunsDirFixture`] lays the
+//! files out in a temporary runs directory with one folder per character, as
+//! [`load_runs_from`] and [`load_all_runs`](super::load_all_runs) expect.
+//!
+//! ```ignore
+//! let fixture = RunsDirFixture::new();
+//! fixture.add(
+//!     &RunFileBuilder::new("win")
+//!         .character(Character::Watcher)
+//!         .victory(true)
+//!         .floor_reached(56)
+//!         .deck(&["Strike_P", "Eruption+1", "Tantrum"]),
+//! );
+//! let (runs, report) = fixture.load();
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+use serde_json::{json, Map, Value};
+
+use super::deck::{starter_deck, starter_relic};
+use super::{generate_runs, load_runs_from, Character, LoadReport, RunMetrics};
+
+/// Builds the JSON of one run file
+///
+/// A new builder holds the play id, floor 1, a loss without `killed_by`,
+/// Ascension 0 and the character's starter deck and relic. Everything else
+/// is left out of the file until a setter adds it, so tests of missing data
+/// keep working.
+#[derive(Debug, Clone)]
+pub struct RunFileBuilder {
+    character: Character,
+    file_name: Option<String>,
+    fields: Map<String, Value>,
+}
+
+impl RunFileBuilder {
+    /// An Ironclad run with the given play id
+    pub fn new(play_id: &str) -> Self {
+        let mut builder = Self {
+            character: Character::Ironclad,
+            file_name: None,
+            fields: Map::new(),
+        };
+        builder.fields.insert("play_id".into(), play_id.into());
+        builder
+            .floor_reached(1)
+            .victory(false)
+            .ascension(0)
+            .character(Character::Ironclad)
+    }
+
+    /// A run file for a run's metrics, e.g. one from [`generate_runs`]
+    ///
+    /// Carries the outcome, deck, relics and fights; campfires go on the
+    /// first floors without a fight. Counts the file doesn't record, like
+    /// elites or shops, are left out.
+    pub fn from_run(run: &RunMetrics) -> Self {
+        let deck: Vec<&str> = run.master_deck.iter().map(String::as_str).collect();
+        let relics: Vec<&str> = run.relics.iter().map(String::as_str).collect();
+        let mut builder = Self::new(&run.play_id)
+            .character(run.character.known().unwrap_or(Character::Ironclad))
+            .victory(run.victory)
+            .floor_reached(run.floor_reached)
+            .ascension(run.ascension_level)
+            .score(run.score)
+            .deck(&deck)
+            .relics(&relics)
+            .set("playtime", run.playtime);
+        if let Some(timestamp) = run.timestamp {
+            builder = builder.timestamp(timestamp);
+        }
+        if let Some(enemy) = &run.killed_by {
+            builder = builder.killed_by(enemy);
+        }
+
+        let mut path = vec![None; run.floor_reached.max(0) as usize];
+        for encounter in &run.encounters {
+            builder = builder.fight(encounter.floor, &encounter.enemies, 0);
+            if let Some(room) = path.get_mut((encounter.floor - 1).max(0) as usize) {
+                *room = Some(if encounter.elite { "E" } else { "M" });
+            }
+        }
+        let rests = std::iter::repeat_n("REST", run.campfires_rested.max(0) as usize);
+        let smiths = std::iter::repeat_n("SMITH", run.campfires_upgraded.max(0) as usize);
+        let mut free_floors = (0..path.len())
+            .filter(|i| path[*i].is_none())
+            .collect::<Vec<_>>();
+        free_floors.reverse();
+        for key in rests.chain(smiths) {
+            let Some(i) = free_floors.pop() else { break };
+            path[i] = Some("R");
+            builder = builder.campfire(i as i32 + 1, key);
+        }
+        builder.set("path_per_floor", path)
+    }
+
+    /// Character folder the file goes into; resets the deck and relics to
+    /// the character's starters
+    pub fn character(mut self, character: Character) -> Self {
+        self.character = character;
+        let deck: Vec<&str> = starter_deck(character)
+            .iter()
+            .flat_map(|(card, count)| std::iter::repeat_n(*card, *count))
+            .collect();
+        self.deck(&deck).relics(&[starter_relic(character)])
+    }
+
+    /// Name of the file, `<play_id>.run` by default
+    pub fn file_name(mut self, name: &str) -> Self {
+        self.file_name = Some(name.to_string());
+        self
+    }
+
+    /// Set any field of the run file, for data without a dedicated setter
+    pub fn set(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.fields.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Leave a field out of the file
+    pub fn remove(mut self, key: &str) -> Self {
+        self.fields.remove(key);
+        self
+    }
+
+    fn push(mut self, key: &str, value: Value) -> Self {
+        let entry = self
+            .fields
+            .entry(key.to_string())
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if let Value::Array(items) = entry {
+            items.push(value);
+        }
+        self
+    }
+
+    /// Whether the run was won
+    pub fn victory(self, victory: bool) -> Self {
+        self.set("victory", victory)
+    }
+
+    /// Floor the run ended on
+    pub fn floor_reached(self, floor: i32) -> Self {
+        self.set("floor_reached", floor)
+    }
+
+    /// Ascension level (`ascension_level`)
+    pub fn ascension(self, level: i32) -> Self {
+        self.set("ascension_level", level)
+    }
+
+    /// Final score
+    pub fn score(self, score: i32) -> Self {
+        self.set("score", score)
+    }
+
+    /// What ended a lost run
+    pub fn killed_by(self, enemy: &str) -> Self {
+        self.set("killed_by", enemy)
+    }
+
+    /// Final deck (`master_deck`), with upgrades as `Bash+1`
+    pub fn deck(self, cards: &[&str]) -> Self {
+        self.set("master_deck", cards.to_vec())
+    }
+
+    /// Relics held at the end of the run
+    pub fn relics(self, relics: &[&str]) -> Self {
+        self.set("relics", relics.to_vec())
+    }
+
+    /// Room symbol of each floor (`path_per_floor`), e.g. `M`, `E`, `?`, `$`
+    pub fn path(self, rooms: &[&str]) -> Self {
+        self.set("path_per_floor", rooms.to_vec())
+    }
+
+    /// Rooms picked on the map (`path_taken`), which skips treasure floors
+    pub fn path_taken(self, rooms: &[&str]) -> Self {
+        self.set("path_taken", rooms.to_vec())
+    }
+
+    /// Add a campfire choice such as `REST`, `SMITH` or `RECALL`
+    pub fn campfire(self, floor: i32, key: &str) -> Self {
+        self.push("campfire_choices", json!({ "floor": floor, "key": key }))
+    }
+
+    /// Add a fight to `damage_taken`
+    pub fn fight(self, floor: i32, enemies: &str, damage: i32) -> Self {
+        self.push(
+            "damage_taken",
+            json!({ "floor": floor, "enemies": enemies, "damage": damage }),
+        )
+    }
+
+    /// A fight that lasted `turns` turns
+    pub fn fight_with_turns(self, floor: i32, enemies: &str, damage: i32, turns: i32) -> Self {
+        self.push(
+            "damage_taken",
+            json!({ "floor": floor, "enemies": enemies, "damage": damage, "turns": turns }),
+        )
+    }
+
+    /// Current HP at the end of each floor
+    pub fn hp_per_floor(self, hp: &[i32]) -> Self {
+        self.set("current_hp_per_floor", hp.to_vec())
+    }
+
+    /// When the run ended (unix seconds)
+    pub fn timestamp(self, timestamp: i64) -> Self {
+        self.set("timestamp", timestamp)
+    }
+
+    /// When the run ended on the player's clock
+    pub fn local_time(self, time: NaiveDateTime) -> Self {
+        self.set("local_time", time.format("%Y%m%d%H%M%S").to_string())
+    }
+
+    /// The run file's JSON
+    pub fn to_json(&self) -> Value {
+        Value::Object(self.fields.clone())
+    }
+
+    /// Path of the file below a runs directory
+    pub fn relative_path(&self) -> PathBuf {
+        let file_name = self.file_name.clone().unwrap_or_else(|| {
+            let play_id = self.fields["play_id"].as_str().unwrap_or("run");
+            format!("{}.run", play_id)
+        });
+        Path::new(self.character.dir_name()).join(file_name)
+    }
+
+    /// Write the file into a runs directory, creating the character folder
+    pub fn write_to(&self, runs_path: &Path) -> std::io::Result<PathBuf> {
+        let path = runs_path.join(self.relative_path());
+        write_file(&path, self.to_json().to_string().as_bytes())?;
+        Ok(path)
+    }
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)
+}
+
+/// A temporary runs directory, deleted when dropped
+pub struct RunsDirFixture {
+    dir: tempfile::TempDir,
+}
+
+impl RunsDirFixture {
+    /// An empty runs directory
+    pub fn new() -> Self {
+        Self {
+            dir: tempfile::tempdir().expect("failed to create temp runs dir"),
+        }
+    }
+
+    /// Root of the runs directory, holding one folder per character
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Write a run file into its character folder
+    pub fn add(&self, run: &RunFileBuilder) -> PathBuf {
+        run.write_to(self.path())
+            .expect("failed to write run fixture")
+    }
+
+    /// Write a file with exact contents into a character folder, e.g. a
+    /// damaged run file
+    pub fn add_raw(
+        &self,
+        character: Character,
+        file_name: &str,
+        contents: impl AsRef<[u8]>,
+    ) -> PathBuf {
+        let path = self.path().join(character.dir_name()).join(file_name);
+        write_file(&path, contents.as_ref()).expect("failed to write run fixture");
+        path
+    }
+
+    /// A runs directory holding [`generate_runs`]`(seed, count)` as run
+    /// files (see [`RunFileBuilder::from_run`])
+    pub fn generated(seed: u64, count: usize) -> Self {
+        let fixture = Self::new();
+        for run in generate_runs(seed, count) {
+            fixture.add(&RunFileBuilder::from_run(&run));
+        }
+        fixture
+    }
+
+    /// Load the directory without touching the global runs path
+    pub fn load(&self) -> (Vec<RunMetrics>, LoadReport) {
+        load_runs_from(self.path())
+    }
+
+    /// Load the directory's runs, sorted by play id
+    pub fn load_sorted(&self) -> Vec<RunMetrics> {
+        let (mut runs, _) = self.load();
+        runs.sort_by(|a, b| a.play_id.cmp(&b.play_id));
+        runs
+    }
+}
+
+/// Parse one run file the way the loader does, without a cache
+pub fn parse_run(path: &Path, character: Character) -> Option<RunMetrics> {
+    super::parse_run_file(path, &character.into())
+        .ok()
+        .map(|(run, _)| run)
+}
+
+impl Default for RunsDirFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_parse_like_a_fresh_loss() {
+        let fixture = RunsDirFixture::new();
+        let path = fixture.add(&RunFileBuilder::new("a").character(Character::Defect));
+        assert!(path.ends_with("DEFECT/a.run"));
+
+        let (runs, report) = fixture.load();
+        assert_eq!(report.parse_failures, 0);
+        let run = &runs[0];
+        assert_eq!(run.character, Character::Defect);
+        assert_eq!((run.floor_reached, run.victory), (1, false));
+        assert_eq!(run.master_deck.len(), 10);
+        assert_eq!(run.relics, ["Cracked Core"]);
+        assert_eq!(run.starters_remaining, 10);
+        assert!(run.timestamp.is_none() && run.rewards_offered.is_none());
+    }
+
+    #[test]
+    fn test_setters_reach_run_metrics() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("b")
+                .character(Character::Watcher)
+                .victory(true)
+                .floor_reached(51)
+                .ascension(20)
+                .deck(&["Strike_P", "Eruption+1", "Tantrum"])
+                .relics(&["PureWater", "Kunai"])
+                .path(&["M", "?", "R", "E"])
+                .campfire(3, "SMITH")
+                .fight(1, "Cultist", 7)
+                .fight(4, "Lagavulin", 20)
+                .timestamp(1_700_000_000)
+                .file_name("custom.run"),
+        );
+        fixture.add_raw(Character::Watcher, "broken.run", "{");
+
+        let (runs, report) = fixture.load();
+        assert_eq!((report.files_scanned, report.parse_failures), (2, 1));
+        let run = &runs[0];
+        assert!(run.victory);
+        assert_eq!((run.floor_reached, run.ascension_level), (51, 20));
+        assert_eq!((run.upgraded_cards, run.relic_count), (1, 2));
+        assert_eq!(run.campfires_upgraded, 1);
+        assert_eq!(run.total_damage_taken, 27);
+        assert_eq!(run.question_rooms, 1);
+        assert_eq!(run.timestamp, Some(1_700_000_000));
+    }
+
+    #[test]
+    fn test_generated_runs_round_trip() {
+        let generated = generate_runs(3, 20);
+        let fixture = RunsDirFixture::generated(3, 20);
+        let runs = fixture.load_sorted();
+        assert_eq!(runs.len(), generated.len());
+        for run in runs {
+            let original = generated.iter().find(|r| r.play_id == run.play_id).unwrap();
+            assert_eq!(run.character, original.character);
+            assert_eq!(
+                (run.victory, run.floor_reached),
+                (original.victory, original.floor_reached)
+            );
+            assert_eq!(run.master_deck, original.master_deck);
+            assert_eq!(run.relics, original.relics);
+            assert_eq!(run.encounters.len(), original.encounters.len());
+            assert_eq!(run.campfires_rested, original.campfires_rested);
+            assert_eq!(run.is_modded, original.is_modded);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Floor numbering of victories
+//!
+//! `floor_reached` of a won run depends on the game version: an Act 3
+//! victory is recorded on floor 51 or 52 depending on whether the victory
+//! screen counts as a floor, and a heart kill on 56 or 57. With
+//! [`FloorSemantics::Normalized`] the floor aggregates (average and highest
+//! floor) count every Act 3 victory as floor 51 and every heart kill as
+//! floor 57, so runs from different versions line up. Losses are never
+//! changed, and `floor_reached` itself always keeps the recorded value.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use super::keys::HEART_FLOOR;
+use super::{RunMetrics, ACT_END_FLOORS};
+
+/// Floor an Act 3 victory counts as when normalized
+pub const ACT3_VICTORY_FLOOR: i32 = 51;
+
+/// Floor a heart kill counts as when normalized
+pub const HEART_VICTORY_FLOOR: i32 = 57;
+
+/// Floor of the Act 3 boss; victories recorded below it are left alone
+const ACT3_BOSS_FLOOR: i32 = ACT_END_FLOORS[2] - 1;
+
+/// How floor aggregates count the floor of won runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FloorSemantics {
+    /// The floor the run file records
+    #[default]
+    Raw,
+    /// Act 3 victories as floor 51 and heart kills as floor 57
+    Normalized,
+}
+
+static FLOOR_SEMANTICS: RwLock<FloorSemantics> = RwLock::new(FloorSemantics::Raw);
+
+/// Set how floor aggregates count victories
+pub fn set_floor_semantics(semantics: FloorSemantics) {
+    *FLOOR_SEMANTICS.write() = semantics;
+}
+
+/// Get how floor aggregates count victories
+pub fn floor_semantics() -> FloorSemantics {
+    *FLOOR_SEMANTICS.read()
+}
+
+/// The floor a run counts as under `semantics`
+///
+/// Victories recorded before the Act 3 boss (which only modded or edited
+/// files have) pass through, as do all losses.
+pub fn normalize_floor(floor_reached: i32, victory: bool, semantics: FloorSemantics) -> i32 {
+    match semantics {
+        FloorSemantics::Raw => floor_reached,
+        FloorSemantics::Normalized if !victory || floor_reached < ACT3_BOSS_FLOOR => floor_reached,
+        FloorSemantics::Normalized if floor_reached > HEART_FLOOR => HEART_VICTORY_FLOOR,
+        FloorSemantics::Normalized => ACT3_VICTORY_FLOOR,
+    }
+}
+
+impl RunMetrics {
+    /// The floor this run counts as in floor aggregates, under the
+    /// configured [`FloorSemantics`]
+    pub fn stats_floor(&self) -> i32 {
+        normalize_floor(self.floor_reached, self.victory, floor_semantics())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FloorSemantics::{Normalized, Raw};
+
+    #[test]
+    fn test_victories_are_normalized() {
+        for (floor, expected) in [(50, 51), (51, 51), (52, 51), (56, 57), (57, 57)] {
+            assert_eq!(
+                normalize_floor(floor, true, Normalized),
+                expected,
+                "{floor}"
+            );
+            assert_eq!(normalize_floor(floor, true, Raw), floor, "{floor}");
+        }
+    }
+
+    #[test]
+    fn test_deaths_pass_through() {
+        for floor in [1, 23, 51, 55, 56] {
+            assert_eq!(normalize_floor(floor, false, Normalized), floor);
+        }
+        assert_eq!(normalize_floor(33, true, Normalized), 33);
+    }
+}
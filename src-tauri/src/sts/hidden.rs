@@ -0,0 +1,163 @@
+//! Hidden runs
+//!
+//! Runs can be hidden from statistics without touching the run file itself.
+//! The hidden play_ids are persisted as a JSON array in the data folder of
+//! each profile (see [`super::profiles`]).
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use super::profiles::{current_profile, profile_data_dir};
+
+/// File name of the persisted hidden-run set inside a profile's data folder
+pub const HIDDEN_RUNS_FILE: &str = "hidden_runs.json";
+
+/// In-memory copies of the persisted sets by profile, loaded on first access
+static HIDDEN_RUNS: RwLock<BTreeMap<String, HiddenRuns>> = RwLock::new(BTreeMap::new());
+
+/// A set of hidden play_ids backed by a JSON file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HiddenRuns {
+    ids: BTreeSet<String>,
+}
+
+impl HiddenRuns {
+    /// Load the set from a file, treating a missing or unreadable file as empty
+    pub fn load(path: &Path) -> Self {
+        let ids = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<BTreeSet<String>>(&content).ok())
+            .unwrap_or_default();
+        Self { ids }
+    }
+
+    /// Write the set to a file, creating parent directories as needed
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&self.ids)?;
+        std::fs::write(path, json)
+    }
+
+    /// Whether a play_id is hidden
+    pub fn contains(&self, play_id: &str) -> bool {
+        self.ids.contains(play_id)
+    }
+
+    /// Hide a play_id, returning false if it was already hidden
+    pub fn insert(&mut self, play_id: &str) -> bool {
+        self.ids.insert(play_id.to_string())
+    }
+
+    /// Unhide a play_id, returning false if it was not hidden
+    pub fn remove(&mut self, play_id: &str) -> bool {
+        self.ids.remove(play_id)
+    }
+
+    /// Number of hidden play_ids
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Whether no runs are hidden
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// The hidden play_ids, sorted
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.ids.iter().map(String::as_str)
+    }
+}
+
+/// Path of a profile's hidden-run file, if a data directory is available
+fn hidden_runs_file(profile: &str) -> Option<PathBuf> {
+    profile_data_dir(profile).map(|dir| dir.join(HIDDEN_RUNS_FILE))
+}
+
+/// Run a closure against the hidden-run set of the current profile and the
+/// path it is saved to
+fn with_hidden_runs<T>(f: impl FnOnce(&mut HiddenRuns, Option<PathBuf>) -> T) -> T {
+    let profile = current_profile();
+    let path = hidden_runs_file(&profile);
+    let mut guard = HIDDEN_RUNS.write();
+    let hidden = guard
+        .entry(profile)
+        .or_insert_with(|| path.as_deref().map(HiddenRuns::load).unwrap_or_default());
+    f(hidden, path)
+}
+
+/// Get a snapshot of the currently hidden play_ids
+pub fn hidden_runs() -> HiddenRuns {
+    with_hidden_runs(|hidden, _| hidden.clone())
+}
+
+/// Set or clear the hidden flag for a run and persist the change
+///
+/// Returns whether the set changed.
+pub fn set_run_hidden(play_id: &str, hidden: bool) -> std::io::Result<bool> {
+    with_hidden_runs(|set, path| {
+        let changed = if hidden {
+            set.insert(play_id)
+        } else {
+            set.remove(play_id)
+        };
+
+        if changed {
+            if let Some(path) = path {
+                set.save(&path)?;
+            }
+        }
+        Ok(changed)
+    })
+}
+
+/// Drop the in-memory copies so the next access re-reads the files
+pub fn reload_hidden_runs() {
+    HIDDEN_RUNS.write().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hidden_runs_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join(HIDDEN_RUNS_FILE);
+
+        let mut hidden = HiddenRuns::default();
+        assert!(hidden.insert("abc"));
+        assert!(!hidden.insert("abc"));
+        assert!(hidden.insert("def"));
+        hidden.save(&path).unwrap();
+
+        let loaded = HiddenRuns::load(&path);
+        assert_eq!(loaded, hidden);
+        assert!(loaded.contains("abc"));
+        assert_eq!(loaded.len(), 2);
+    }
+
+    #[test]
+    fn test_hidden_runs_missing_or_invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(HiddenRuns::load(&dir.path().join("missing.json")).is_empty());
+
+        let path = dir.path().join(HIDDEN_RUNS_FILE);
+        std::fs::write(&path, "not json").unwrap();
+        assert!(HiddenRuns::load(&path).is_empty());
+    }
+
+    #[test]
+    fn test_hidden_runs_remove() {
+        let mut hidden = HiddenRuns::default();
+        hidden.insert("abc");
+        assert!(hidden.remove("abc"));
+        assert!(!hidden.remove("abc"));
+        assert!(!hidden.contains("abc"));
+    }
+}
@@ -0,0 +1,386 @@
+//! Standalone HTML report
+//!
+//! A single HTML file with inline CSS and SVG, for sharing stats with people
+//! who don't run the app. It makes no external requests. Every section is
+//! built from the same aggregates the API serves ([`CharacterStats`],
+//! [`CalendarDay`], [`EnemyCount`], [`PersonalBests`]), so its numbers match
+//! the app.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use super::report::percent;
+use super::{
+    calculate_character_stats, calendar, calendar_years, enemy_catalog, personal_bests,
+    CalendarDay, CharacterStats, EnemyCount, PersonalBests, PersonalRecord, RunMetrics,
+    TimeBucketing,
+};
+
+/// Encounters listed under deaths
+pub const MAX_DEATH_ROWS: usize = 10;
+
+/// A highlight's label, record and how to show its value
+type Highlight<'a> = (&'static str, &'a Option<PersonalRecord>, fn(i64) -> String);
+
+const SPARKLINE_WIDTH: f64 = 240.0;
+const SPARKLINE_HEIGHT: f64 = 40.0;
+
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; max-width: 960px; margin: 2rem auto; padding: 0 1rem; color: #1f2328; background: #fafafa; }
+h1 { margin-bottom: 0.25rem; }
+h2 { margin-top: 2rem; border-bottom: 1px solid #d0d7de; padding-bottom: 0.25rem; }
+table { border-collapse: collapse; width: 100%; }
+th, td { padding: 0.35rem 0.6rem; border-bottom: 1px solid #e5e7eb; }
+th { text-align: left; background: #f0f2f4; }
+td.num, th.num { text-align: right; font-variant-numeric: tabular-nums; }
+.muted { color: #6e7781; }
+svg.sparkline { display: block; }
+svg.sparkline polyline { fill: none; stroke: #c0392b; stroke-width: 2; }
+dl { display: grid; grid-template-columns: max-content 1fr; gap: 0.35rem 1rem; }
+dt { font-weight: 600; }
+dd { margin: 0; }
+";
+
+/// Runs and wins in one calendar month
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonthWinRate {
+    /// Month (YYYY-MM)
+    pub month: String,
+    pub runs: i32,
+    pub wins: i32,
+}
+
+impl MonthWinRate {
+    fn win_rate(&self) -> f64 {
+        if self.runs > 0 {
+            self.wins as f64 / self.runs as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Sections of the report
+#[derive(Debug, Clone)]
+pub struct HtmlReport {
+    pub total_runs: usize,
+    pub characters: Vec<CharacterStats>,
+    /// Months with at least one run, oldest first
+    pub months: Vec<MonthWinRate>,
+    /// Encounters that ended runs, deadliest first
+    pub deaths: Vec<EnemyCount>,
+    pub highlights: PersonalBests,
+}
+
+/// Months with runs, from the activity calendar of every year played
+fn monthly_win_rates(days: impl IntoIterator<Item = CalendarDay>) -> Vec<MonthWinRate> {
+    let mut months: BTreeMap<String, (i32, i32)> = BTreeMap::new();
+    for day in days.into_iter().filter(|d| d.runs > 0) {
+        let month = months.entry(day.date[..7].to_string()).or_default();
+        month.0 += day.runs;
+        month.1 += day.wins;
+    }
+    months
+        .into_iter()
+        .map(|(month, (runs, wins))| MonthWinRate { month, runs, wins })
+        .collect()
+}
+
+impl HtmlReport {
+    /// Build every section from runs, ignoring hidden ones
+    ///
+    /// Months are UTC months, as runs don't record the player's time zone.
+    pub fn from_runs(runs: &[RunMetrics]) -> Self {
+        let visible: Vec<RunMetrics> = runs.iter().filter(|r| !r.hidden).cloned().collect();
+        let bucketing = TimeBucketing::default();
+        let days = calendar_years(&visible, bucketing)
+            .into_iter()
+            .flat_map(|year| calendar(&visible, year, bucketing));
+
+        let mut deaths: Vec<EnemyCount> = enemy_catalog(&visible, None, true)
+            .into_iter()
+            .filter(|e| e.deaths > 0)
+            .collect();
+        deaths.sort_by(|a, b| b.deaths.cmp(&a.deaths).then_with(|| a.name.cmp(&b.name)));
+        deaths.truncate(MAX_DEATH_ROWS);
+
+        Self {
+            total_runs: visible.len(),
+            characters: calculate_character_stats(&visible),
+            months: monthly_win_rates(days),
+            deaths,
+            highlights: personal_bests(&visible),
+        }
+    }
+
+    /// Render the report as a complete HTML document
+    pub fn render(&self) -> String {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>Slay the Spire stats</title>\n");
+        let _ = write!(html, "<style>\n{}</style>\n", STYLE);
+        html.push_str("</head>\n<body>\n<h1>Slay the Spire stats</h1>\n");
+        let wins: i32 = self.characters.iter().map(|c| c.wins).sum();
+        let _ = writeln!(
+            html,
+            "<p class=\"muted\">{} runs, {} wins</p>",
+            self.total_runs, wins
+        );
+
+        self.render_characters(&mut html);
+        self.render_win_rate(&mut html);
+        self.render_deaths(&mut html);
+        self.render_highlights(&mut html);
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn render_characters(&self, html: &mut String) {
+        html.push_str("<h2>Characters</h2>\n");
+        if self.characters.is_empty() {
+            html.push_str("<p class=\"muted\">No runs yet.</p>\n");
+            return;
+        }
+        html.push_str(
+            "<table>\n<tr><th>Character</th><th class=\"num\">Runs</th><th class=\"num\">Wins</th>\
+             <th class=\"num\">Win rate</th><th class=\"num\">Heart kills</th>\
+             <th class=\"num\">Avg floor</th><th class=\"num\">Best ascension</th></tr>\n",
+        );
+        for stats in &self.characters {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>\
+                 <td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.1}</td>\
+                 <td class=\"num\">{}</td></tr>",
+                escape(&stats.display_name),
+                stats.total_runs,
+                stats.wins,
+                percent(stats.win_rate),
+                stats.heart_kills,
+                stats.avg_floor,
+                stats
+                    .highest_ascension_win
+                    .map_or_else(|| "-".to_string(), |a| format!("A{}", a)),
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    fn render_win_rate(&self, html: &mut String) {
+        html.push_str("<h2>Win rate over time</h2>\n");
+        let (Some(first), Some(last)) = (self.months.first(), self.months.last()) else {
+            html.push_str("<p class=\"muted\">No dated runs.</p>\n");
+            return;
+        };
+        let _ = writeln!(
+            html,
+            "<svg class=\"sparkline\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+             role=\"img\" aria-label=\"Monthly win rate\"><polyline points=\"{}\"/></svg>",
+            sparkline_points(&self.months),
+            w = SPARKLINE_WIDTH,
+            h = SPARKLINE_HEIGHT,
+        );
+        let _ = writeln!(
+            html,
+            "<p class=\"muted\">Monthly win rate, {} to {}: {} to {}</p>",
+            first.month,
+            last.month,
+            percent(first.win_rate()),
+            percent(last.win_rate())
+        );
+    }
+
+    fn render_deaths(&self, html: &mut String) {
+        html.push_str("<h2>Deaths by enemy</h2>\n");
+        if self.deaths.is_empty() {
+            html.push_str("<p class=\"muted\">No deaths recorded.</p>\n");
+            return;
+        }
+        html.push_str(
+            "<table>\n<tr><th>Enemy</th><th class=\"num\">Deaths</th><th class=\"num\">Fights</th></tr>\n",
+        );
+        for enemy in &self.deaths {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                escape(&enemy.name),
+                enemy.deaths,
+                enemy.fights
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    fn render_highlights(&self, html: &mut String) {
+        html.push_str("<h2>Highlights</h2>\n");
+        let bests = &self.highlights;
+        let records: [Highlight; 7] = [
+            ("Highest score", &bests.highest_score, |v| v.to_string()),
+            (
+                "Highest ascension won",
+                &bests.highest_winning_ascension,
+                |v| format!("A{}", v),
+            ),
+            ("Deepest floor", &bests.deepest_floor, |v| {
+                format!("Floor {}", v)
+            }),
+            ("Fastest victory", &bests.fastest_victory, format_duration),
+            ("Most elites killed", &bests.most_elites_killed, |v| {
+                v.to_string()
+            }),
+            (
+                "Most damage survived in one fight",
+                &bests.largest_damage_survived,
+                |v| v.to_string(),
+            ),
+            ("Longest fight", &bests.longest_fight, |v| {
+                format!("{} turns", v)
+            }),
+        ];
+        if records.iter().all(|(_, record, _)| record.is_none()) {
+            html.push_str("<p class=\"muted\">No records yet.</p>\n");
+            return;
+        }
+        html.push_str("<dl>\n");
+        for (label, record, format) in records {
+            let Some(record) = record else {
+                continue;
+            };
+            let character = record
+                .character
+                .known()
+                .map_or(record.character.as_str(), |c| c.display_name());
+            let _ = writeln!(
+                html,
+                "<dt>{}</dt><dd>{} <span class=\"muted\">({})</span></dd>",
+                label,
+                format(record.value),
+                escape(character)
+            );
+        }
+        html.push_str("</dl>\n");
+    }
+}
+
+/// Polyline points for the monthly win rates, left to right
+fn sparkline_points(months: &[MonthWinRate]) -> String {
+    let step = if months.len() > 1 {
+        SPARKLINE_WIDTH / (months.len() - 1) as f64
+    } else {
+        0.0
+    };
+    months
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let y = SPARKLINE_HEIGHT - m.win_rate() * SPARKLINE_HEIGHT;
+            format!("{:.1},{:.1}", i as f64 * step, y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Seconds as `12m 34s`, or `1h 02m` from an hour on
+fn format_duration(seconds: i64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds % 3600 / 60);
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else {
+        format!("{}m {:02}s", minutes, seconds % 60)
+    }
+}
+
+/// Escape text for HTML element content and attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render the report for `runs` and write it to a file
+///
+/// Parent directories are created as needed. Returns the size of the file in
+/// bytes.
+pub fn write_html_report(runs: &[RunMetrics], out_path: &Path) -> std::io::Result<usize> {
+    let html = HtmlReport::from_runs(runs).render();
+    if let Some(parent) = out_path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    std::fs::write(out_path, &html)?;
+    Ok(html.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::demo::demo_runs;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Character;
+
+    const DEMO_REPORT: &str = include_str!("../../tests/fixtures/reports/demo.html");
+
+    #[test]
+    fn test_demo_report_snapshot() {
+        let html = HtmlReport::from_runs(&demo_runs()).render();
+        assert_eq!(html, DEMO_REPORT);
+    }
+
+    #[test]
+    fn test_report_is_self_contained() {
+        let html = HtmlReport::from_runs(&demo_runs()).render();
+        for external in ["http://", "https://", "<link", "<script", "src="] {
+            assert!(!html.contains(external), "{external}");
+        }
+    }
+
+    #[test]
+    fn test_matches_api_aggregates() {
+        let runs = demo_runs();
+        let report = HtmlReport::from_runs(&runs);
+        assert_eq!(
+            serde_json::to_value(&report.characters).unwrap(),
+            serde_json::to_value(calculate_character_stats(&runs)).unwrap()
+        );
+        assert_eq!(report.highlights, personal_bests(&runs));
+        let month_runs: i32 = report.months.iter().map(|m| m.runs).sum();
+        assert_eq!(month_runs as usize, runs.len());
+    }
+
+    #[test]
+    fn test_escapes_enemy_names() {
+        let mut run = sample_run("a", Character::Ironclad, false);
+        run.killed_by = Some("<Nob & Co>".to_string());
+        let html = HtmlReport::from_runs(&[run]).render();
+        assert!(html.contains("&lt;Nob &amp; Co&gt;"));
+        assert!(html.contains("No dated runs."));
+    }
+
+    #[test]
+    fn test_write_html_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out").join("report.html");
+        let written = write_html_report(&[], &path).unwrap();
+        let html = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, html.len());
+        assert!(html.contains("No runs yet."));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(754), "12m 34s");
+        assert_eq!(format_duration(3720), "1h 02m");
+    }
+}
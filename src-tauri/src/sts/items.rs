@@ -0,0 +1,761 @@
+//! Item name database
+//!
+//! Run files list purchases, rewards and removals by bare item id, with
+//! nothing saying whether an id is a card, a relic or a potion. Relic and
+//! potion ids from the base game are listed here; anything else is taken to
+//! be a card, which also covers upgraded (`Bash+1`) and modded cards.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use utoipa::ToSchema;
+
+/// Relic ids from the base game
+pub const RELICS: &[&str] = &[
+    // Starter
+    "Burning Blood",
+    "Ring of the Snake",
+    "Cracked Core",
+    "PureWater",
+    // Common
+    "Akabeko",
+    "Anchor",
+    "Ancient Tea Set",
+    "Art of War",
+    "Bag of Marbles",
+    "Bag of Preparation",
+    "Blood Vial",
+    "Boot",
+    "Bronze Scales",
+    "Centennial Puzzle",
+    "CeramicFish",
+    "Damaru",
+    "DataDisk",
+    "Dream Catcher",
+    "Happy Flower",
+    "Juzu Bracelet",
+    "Lantern",
+    "MawBank",
+    "MealTicket",
+    "Nunchaku",
+    "Oddly Smooth Stone",
+    "Omamori",
+    "Orichalcum",
+    "Pen Nib",
+    "Potion Belt",
+    "PreservedInsect",
+    "Red Skull",
+    "Regal Pillow",
+    "Smiling Mask",
+    "Snake Skull",
+    "Strawberry",
+    "Tiny Chest",
+    "Toy Ornithopter",
+    "Vajra",
+    "War Paint",
+    "Whetstone",
+    // Uncommon
+    "Blue Candle",
+    "Bottled Flame",
+    "Bottled Lightning",
+    "Bottled Tornado",
+    "Darkstone Periapt",
+    "Duality",
+    "Eternal Feather",
+    "Frozen Egg 2",
+    "Gold-Plated Cables",
+    "Gremlin Horn",
+    "HornCleat",
+    "InkBottle",
+    "Kunai",
+    "Letter Opener",
+    "Matryoshka",
+    "Meat on the Bone",
+    "Mercury Hourglass",
+    "Molten Egg 2",
+    "Mummified Hand",
+    "Ninja Scroll",
+    "Ornamental Fan",
+    "Pantograph",
+    "Paper Crane",
+    "Paper Frog",
+    "Pear",
+    "Question Card",
+    "Self Forming Clay",
+    "Shuriken",
+    "Singing Bowl",
+    "StrikeDummy",
+    "Sundial",
+    "Symbiotic Virus",
+    "TeardropLocket",
+    "The Courier",
+    "Toxic Egg 2",
+    "White Beast Statue",
+    // Rare
+    "Bird Faced Urn",
+    "Calipers",
+    "CaptainsWheel",
+    "Champion Belt",
+    "Charon's Ashes",
+    "CloakClasp",
+    "Dead Branch",
+    "Du-Vu Doll",
+    "Emotion Chip",
+    "FossilizedHelix",
+    "Gambling Chip",
+    "Ginger",
+    "Girya",
+    "GoldenEye",
+    "Ice Cream",
+    "Incense Burner",
+    "Lizard Tail",
+    "Magic Flower",
+    "Mango",
+    "Old Coin",
+    "Peace Pipe",
+    "Pocketwatch",
+    "Prayer Wheel",
+    "Shovel",
+    "StoneCalendar",
+    "The Specimen",
+    "Thread and Needle",
+    "Tingsha",
+    "Torii",
+    "Tough Bandages",
+    "TungstenRod",
+    "Turnip",
+    "Unceasing Top",
+    "WingedGreaves",
+    // Boss
+    "Astrolabe",
+    "Black Blood",
+    "Black Star",
+    "Busted Crown",
+    "Calling Bell",
+    "Coffee Dripper",
+    "Cursed Key",
+    "Ectoplasm",
+    "Empty Cage",
+    "FrozenCore",
+    "Fusion Hammer",
+    "HolyWater",
+    "HoveringKite",
+    "Inserter",
+    "Mark of Pain",
+    "Nuclear Battery",
+    "Pandora's Box",
+    "Philosopher's Stone",
+    "Ring of the Serpent",
+    "Runic Cube",
+    "Runic Dome",
+    "Runic Pyramid",
+    "SacredBark",
+    "SlaversCollar",
+    "Snecko Eye",
+    "Sozu",
+    "Tiny House",
+    "Velvet Choker",
+    "VioletLotus",
+    "WristBlade",
+    // Shop
+    "Brimstone",
+    "Cauldron",
+    "Chemical X",
+    "ClockworkSouvenir",
+    "DollysMirror",
+    "Frozen Eye",
+    "HandDrill",
+    "Lee's Waffle",
+    "Medical Kit",
+    "Melange",
+    "Membership Card",
+    "OrangePellets",
+    "Orrery",
+    "PrismaticShard",
+    "Runic Capacitor",
+    "Sling",
+    "Strange Spoon",
+    "TheAbacus",
+    "Toolbox",
+    "TwistedFunnel",
+    // Event and special
+    "Bloody Idol",
+    "Circlet",
+    "Cultist Mask",
+    "Enchiridion",
+    "FaceOfCleric",
+    "Golden Idol",
+    "GremlinMask",
+    "Mark of the Bloom",
+    "MutagenicStrength",
+    "Necronomicon",
+    "NeowsBlessing",
+    "Nilry's Codex",
+    "Nloth's Gift",
+    "NlothsMask",
+    "Odd Mushroom",
+    "Red Circlet",
+    "Red Mask",
+    "Spirit Poop",
+    "SsserpentHead",
+    "WarpedTongs",
+];
+
+/// Potion ids from the base game
+pub const POTIONS: &[&str] = &[
+    "Ambrosia",
+    "AncientPotion",
+    "AttackPotion",
+    "BlessingOfTheForge",
+    "Block Potion",
+    "BloodPotion",
+    "BottledMiracle",
+    "ColorlessPotion",
+    "CultistPotion",
+    "CunningPotion",
+    "Dexterity Potion",
+    "DistilledChaos",
+    "DuplicationPotion",
+    "ElixirPotion",
+    "Energy Potion",
+    "EntropicBrew",
+    "EssenceOfDarkness",
+    "EssenceOfSteel",
+    "Explosive Potion",
+    "FairyPotion",
+    "FearPotion",
+    "Fire Potion",
+    "FocusPotion",
+    "Fruit Juice",
+    "GamblersBrew",
+    "GhostInAJar",
+    "HeartOfIron",
+    "LiquidBronze",
+    "LiquidMemories",
+    "Poison Potion",
+    "PotionOfCapacity",
+    "PowerPotion",
+    "Regen Potion",
+    "SkillPotion",
+    "SmokeBomb",
+    "SneckoOil",
+    "SpeedPotion",
+    "StancePotion",
+    "SteroidPotion",
+    "Strength Potion",
+    "Swift Potion",
+    "Weak Potion",
+];
+
+/// Card ids from the base game
+///
+/// Cards whose id differs from the name the game shows (`Wraith Form v2` is
+/// Wraith Form) are listed under both, since older files and hand-written
+/// runs use the display name.
+pub const CARDS: &[&str] = &[
+    // Ironclad
+    "Strike_R",
+    "Defend_R",
+    "Bash",
+    "Anger",
+    "Armaments",
+    "Body Slam",
+    "Clash",
+    "Cleave",
+    "Clothesline",
+    "Flex",
+    "Havoc",
+    "Headbutt",
+    "Heavy Blade",
+    "Iron Wave",
+    "Perfected Strike",
+    "Pommel Strike",
+    "Shrug It Off",
+    "Sword Boomerang",
+    "Thunderclap",
+    "True Grit",
+    "Twin Strike",
+    "Warcry",
+    "Wild Strike",
+    "Battle Trance",
+    "Blood for Blood",
+    "Bloodletting",
+    "Burning Pact",
+    "Carnage",
+    "Combust",
+    "Dark Embrace",
+    "Disarm",
+    "Dropkick",
+    "Dual Wield",
+    "Entrench",
+    "Evolve",
+    "Feel No Pain",
+    "Fire Breathing",
+    "Flame Barrier",
+    "Ghostly Armor",
+    "Hemokinesis",
+    "Infernal Blade",
+    "Inflame",
+    "Intimidate",
+    "Metallicize",
+    "Power Through",
+    "Pummel",
+    "Rage",
+    "Rampage",
+    "Reckless Charge",
+    "Rupture",
+    "Searing Blow",
+    "Second Wind",
+    "Seeing Red",
+    "Sentinel",
+    "Sever Soul",
+    "Shockwave",
+    "Spot Weakness",
+    "Uppercut",
+    "Whirlwind",
+    "Barricade",
+    "Berserk",
+    "Bludgeon",
+    "Brutality",
+    "Corruption",
+    "Demon Form",
+    "Double Tap",
+    "Exhume",
+    "Feed",
+    "Fiend Fire",
+    "Immolate",
+    "Impervious",
+    "Juggernaut",
+    "Limit Break",
+    "Offering",
+    "Reaper",
+    // Silent
+    "Strike_G",
+    "Defend_G",
+    "Neutralize",
+    "Survivor",
+    "Acrobatics",
+    "Backflip",
+    "Bane",
+    "Blade Dance",
+    "Cloak And Dagger",
+    "Dagger Spray",
+    "Dagger Throw",
+    "Deadly Poison",
+    "Deflect",
+    "Dodge and Roll",
+    "Flying Knee",
+    "Outmaneuver",
+    "Piercing Wail",
+    "Poisoned Stab",
+    "Prepared",
+    "Quick Slash",
+    "Slice",
+    "Underhanded Strike",
+    "Sneaky Strike",
+    "Sucker Punch",
+    "Accuracy",
+    "All Out Attack",
+    "Backstab",
+    "Blur",
+    "Bouncing Flask",
+    "Calculated Gamble",
+    "Caltrops",
+    "Catalyst",
+    "Choke",
+    "Concentrate",
+    "Crippling Poison",
+    "Crippling Cloud",
+    "Dash",
+    "Distraction",
+    "Endless Agony",
+    "Escape Plan",
+    "Eviscerate",
+    "Expertise",
+    "Finisher",
+    "Flechettes",
+    "Footwork",
+    "Heel Hook",
+    "Infinite Blades",
+    "Leg Sweep",
+    "Masterful Stab",
+    "Noxious Fumes",
+    "Predator",
+    "Reflex",
+    "Riddle With Holes",
+    "Setup",
+    "Skewer",
+    "Tactician",
+    "Terror",
+    "Well Laid Plans",
+    "A Thousand Cuts",
+    "Adrenaline",
+    "After Image",
+    "Venomology",
+    "Alchemize",
+    "Bullet Time",
+    "Burst",
+    "Corpse Explosion",
+    "Die Die Die",
+    "Doppelganger",
+    "Envenom",
+    "Glass Knife",
+    "Grand Finale",
+    "Malaise",
+    "Night Terror",
+    "Nightmare",
+    "Phantasmal Killer",
+    "Storm of Steel",
+    "Tools of the Trade",
+    "Unload",
+    "Wraith Form v2",
+    "Wraith Form",
+    // Defect
+    "Strike_B",
+    "Defend_B",
+    "Zap",
+    "Dualcast",
+    "Ball Lightning",
+    "Barrage",
+    "Beam Cell",
+    "Cold Snap",
+    "Compile Driver",
+    "Conserve Battery",
+    "Charge Battery",
+    "Coolheaded",
+    "Go for the Eyes",
+    "Hologram",
+    "Leap",
+    "Rebound",
+    "Stack",
+    "Steam",
+    "Steam Barrier",
+    "Streamline",
+    "Sweeping Beam",
+    "Turbo",
+    "Gash",
+    "Claw",
+    "Redo",
+    "Recursion",
+    "Aggregate",
+    "Auto Shields",
+    "Blizzard",
+    "BootSequence",
+    "Capacitor",
+    "Chaos",
+    "Chill",
+    "Consume",
+    "Darkness",
+    "Defragment",
+    "Doom and Gloom",
+    "Double Energy",
+    "Undo",
+    "Equilibrium",
+    "FTL",
+    "Force Field",
+    "Fusion",
+    "Genetic Algorithm",
+    "Glacier",
+    "Heatsinks",
+    "Hello World",
+    "Lockon",
+    "Bullseye",
+    "Loop",
+    "Melter",
+    "Steam Power",
+    "Overclock",
+    "Recycle",
+    "Reinforced Body",
+    "Reprogram",
+    "Rip and Tear",
+    "Scrape",
+    "Self Repair",
+    "Skim",
+    "Static Discharge",
+    "Storm",
+    "Sunder",
+    "Tempest",
+    "White Noise",
+    "All For One",
+    "Amplify",
+    "Biased Cognition",
+    "Buffer",
+    "Core Surge",
+    "Creative AI",
+    "Echo Form",
+    "Electrodynamics",
+    "Fission",
+    "Hyperbeam",
+    "Machine Learning",
+    "Meteor Strike",
+    "Multi-Cast",
+    "Rainbow",
+    "Reboot",
+    "Seek",
+    "Thunder Strike",
+    // Watcher
+    "Strike_P",
+    "Defend_P",
+    "Eruption",
+    "Vigilance",
+    "BowlingBash",
+    "Consecrate",
+    "Crescendo",
+    "CrushJoints",
+    "CutThroughFate",
+    "EmptyBody",
+    "EmptyFist",
+    "Evaluate",
+    "FlurryOfBlows",
+    "FlyingSleeves",
+    "FollowUp",
+    "Halt",
+    "JustLucky",
+    "PathToVictory",
+    "Pressure Points",
+    "Prostrate",
+    "Protect",
+    "SashWhip",
+    "ClearTheMind",
+    "Tranquility",
+    "ThirdEye",
+    "BattleHymn",
+    "CarveReality",
+    "Collect",
+    "Conclude",
+    "DeceiveReality",
+    "EmptyMind",
+    "Fasting2",
+    "Fasting",
+    "FearNoEvil",
+    "ForeignInfluence",
+    "Wireheading",
+    "Foresight",
+    "Indignation",
+    "InnerPeace",
+    "LikeWater",
+    "Meditate",
+    "MentalFortress",
+    "Nirvana",
+    "Perseverance",
+    "Pray",
+    "ReachHeaven",
+    "Adaptation",
+    "Rushdown",
+    "Sanctity",
+    "SandsOfTime",
+    "SignatureMove",
+    "Vengeance",
+    "Simmering Fury",
+    "Study",
+    "Swivel",
+    "TalkToTheHand",
+    "Tantrum",
+    "Wallop",
+    "WaveOfTheHand",
+    "Weave",
+    "WheelKick",
+    "WindmillStrike",
+    "Worship",
+    "WreathOfFlame",
+    "Alpha",
+    "Blasphemy",
+    "Brilliance",
+    "ConjureBlade",
+    "DeusExMachina",
+    "DevaForm",
+    "Devotion",
+    "Establishment",
+    "Judgement",
+    "LessonLearned",
+    "MasterReality",
+    "Omniscience",
+    "Ragnarok",
+    "Scrawl",
+    "SpiritShield",
+    "Vault",
+    "Wish",
+    // Colorless
+    "Bandage Up",
+    "Blind",
+    "Dark Shackles",
+    "Deep Breath",
+    "Discovery",
+    "Dramatic Entrance",
+    "Enlightenment",
+    "Finesse",
+    "Flash of Steel",
+    "Forethought",
+    "Good Instincts",
+    "Impatience",
+    "Jack Of All Trades",
+    "Madness",
+    "Mind Blast",
+    "Panacea",
+    "PanicButton",
+    "Purity",
+    "Swift Strike",
+    "Trip",
+    "Apotheosis",
+    "Chrysalis",
+    "HandOfGreed",
+    "Magnetism",
+    "Master of Strategy",
+    "Mayhem",
+    "Metamorphosis",
+    "Panache",
+    "Sadistic Nature",
+    "Secret Technique",
+    "Secret Weapon",
+    "The Bomb",
+    "Thinking Ahead",
+    "Transmutation",
+    "Violence",
+    "Ghostly",
+    "Apparition",
+    "Bite",
+    "J.A.X.",
+    "RitualDagger",
+    "Shiv",
+    "Beta",
+    "Omega",
+    "Expunger",
+    "Insight",
+    "Miracle",
+    "Safety",
+    "Smite",
+    "ThroughViolence",
+    // Curses
+    "AscendersBane",
+    "Clumsy",
+    "CurseOfTheBell",
+    "Decay",
+    "Doubt",
+    "Injury",
+    "Necronomicurse",
+    "Normality",
+    "Pain",
+    "Parasite",
+    "Pride",
+    "Regret",
+    "Shame",
+    "Writhe",
+    // Status
+    "Burn",
+    "Dazed",
+    "Slimed",
+    "Void",
+    "Wound",
+];
+
+/// What kind of item an id refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Card,
+    Relic,
+    Potion,
+}
+
+/// Classify an item id from a run file
+pub fn classify(id: &str) -> ItemKind {
+    if RELICS.contains(&id) {
+        ItemKind::Relic
+    } else if POTIONS.contains(&id) {
+        ItemKind::Potion
+    } else {
+        ItemKind::Card
+    }
+}
+
+/// Card id without its upgrade suffix (`Bash+1` -> `Bash`)
+pub fn base_card_id(card: &str) -> &str {
+    card.split_once('+').map_or(card, |(base, _)| base)
+}
+
+/// Lookup key for a name: lowercase letters and digits only, so
+/// `Cut Through Fate` and `CutThroughFate` compare equal
+fn lookup_key(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+static CARD_KEYS: LazyLock<HashSet<String>> =
+    LazyLock::new(|| CARDS.iter().map(|c| lookup_key(c)).collect());
+
+static RELIC_KEYS: LazyLock<HashSet<String>> =
+    LazyLock::new(|| RELICS.iter().map(|r| lookup_key(r)).collect());
+
+/// Whether a deck entry is a base game card, upgraded or not
+pub fn is_base_game_card(card: &str) -> bool {
+    CARD_KEYS.contains(&lookup_key(base_card_id(card)))
+}
+
+/// Whether a relic id is from the base game
+pub fn is_base_game_relic(relic: &str) -> bool {
+    RELIC_KEYS.contains(&lookup_key(relic))
+}
+
+/// Count the items of one kind in a list of ids
+pub fn count_kind(ids: &[String], kind: ItemKind) -> i32 {
+    ids.iter().filter(|id| classify(id) == kind).count() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_mixed_purchases() {
+        let purchases: Vec<String> = [
+            "Membership Card",
+            "Inflame",
+            "Fire Potion",
+            "Shrug It Off+1",
+            "Orrery",
+            "SmokeBomb",
+            "Apotheosis",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        assert_eq!(count_kind(&purchases, ItemKind::Card), 3);
+        assert_eq!(count_kind(&purchases, ItemKind::Relic), 2);
+        assert_eq!(count_kind(&purchases, ItemKind::Potion), 2);
+    }
+
+    #[test]
+    fn test_lists_have_no_duplicates() {
+        for list in [CARDS, RELICS, POTIONS] {
+            let mut sorted = list.to_vec();
+            sorted.sort_unstable();
+            sorted.dedup();
+            assert_eq!(sorted.len(), list.len());
+        }
+        assert!(RELICS.iter().all(|r| !POTIONS.contains(r)));
+        assert!(CARDS
+            .iter()
+            .all(|c| !RELICS.contains(c) && !POTIONS.contains(c)));
+        assert_eq!(CARD_KEYS.len(), CARDS.len());
+    }
+
+    #[test]
+    fn test_base_game_lookups() {
+        for card in [
+            "Searing Blow+9",
+            "Bash+1",
+            "Wraith Form v2",
+            "Cut Through Fate",
+            "Follow-Up",
+        ] {
+            assert!(is_base_game_card(card), "{card}");
+        }
+        assert!(!is_base_game_card("Reap and Sow"));
+        assert!(is_base_game_relic("Burning Blood"));
+        assert!(is_base_game_relic("Mawbank"));
+        assert!(!is_base_game_relic("Inserter Mk II"));
+    }
+}
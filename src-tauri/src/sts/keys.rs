@@ -0,0 +1,167 @@
+//! Act 4 keys and heart attempts
+//!
+//! Run files record the three keys indirectly: the Ruby key is a `RECALL`
+//! campfire choice, the Sapphire key a `blue_key_relic_skipped_log` entry for
+//! the chest relic given up, and the Emerald key a `green_key_taken_log`
+//! entry. Files written before keys existed have none of these, so a missing
+//! signal only means "no key" when the file comes from a build with keys.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{completed_act, RunMetrics};
+
+/// First game build with Act 4 keys
+pub const KEYS_BUILD: &str = "2019-01-23";
+
+/// Floor of the Act 3 boss
+pub const ACT3_BOSS_FLOOR: i32 = 50;
+
+/// Floor of the Corrupt Heart
+pub const HEART_FLOOR: i32 = 55;
+
+/// Key signals found in a run file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeySignals {
+    /// A `RECALL` campfire choice
+    pub recall: bool,
+    /// A `blue_key_relic_skipped_log` entry
+    pub blue_key_log: bool,
+    /// A `green_key_taken_log` entry
+    pub green_key_log: bool,
+}
+
+impl KeySignals {
+    /// `(ruby, emerald, sapphire)`, or all `None` for files from before keys
+    ///
+    /// `build_version` is the file's `YYYY-MM-DD` build date. Files without
+    /// one are treated as key-era only if they record a key.
+    pub fn keys(&self, build_version: Option<&str>) -> (Option<bool>, Option<bool>, Option<bool>) {
+        let any_key = self.recall || self.blue_key_log || self.green_key_log;
+        let key_era = match build_version {
+            Some(build) => build >= KEYS_BUILD,
+            None => any_key,
+        };
+        if !key_era && !any_key {
+            return (None, None, None);
+        }
+        (
+            Some(self.recall),
+            Some(self.green_key_log),
+            Some(self.blue_key_log),
+        )
+    }
+}
+
+/// Whether a run held all three keys; `None` if the file predates keys
+pub fn has_all_keys(run: &RunMetrics) -> Option<bool> {
+    Some(run.has_ruby_key? && run.has_emerald_key? && run.has_sapphire_key?)
+}
+
+/// Whether a run beat the Act 3 boss
+pub fn completed_act3(run: &RunMetrics) -> bool {
+    completed_act(run.floor_reached, 3)
+}
+
+/// Whether a run killed the Corrupt Heart
+pub fn killed_heart(run: &RunMetrics) -> bool {
+    run.victory && run.floor_reached > HEART_FLOOR
+}
+
+/// How often Act 3 completions go on to the heart
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct KeyStats {
+    /// Runs that beat the Act 3 boss
+    pub act3_completions: i32,
+    /// Of those, runs from builds with keys
+    pub act3_completions_with_key_data: i32,
+    /// Act 3 completions holding all three keys (heart attempts)
+    pub heart_attempts: i32,
+    /// Runs that killed the heart
+    pub heart_kills: i32,
+    /// Share of key-era Act 3 completions that went for the heart
+    pub heart_attempt_rate: f64,
+    /// Share of heart attempts that killed the heart
+    pub heart_kill_rate: f64,
+}
+
+impl KeyStats {
+    /// Aggregate key stats over a set of runs
+    pub fn from_runs<'a>(runs: impl IntoIterator<Item = &'a RunMetrics>) -> Self {
+        let mut stats = Self::default();
+        for run in runs.into_iter().filter(|r| completed_act3(r)) {
+            stats.act3_completions += 1;
+            match has_all_keys(run) {
+                Some(true) => {
+                    stats.act3_completions_with_key_data += 1;
+                    stats.heart_attempts += 1;
+                }
+                Some(false) => stats.act3_completions_with_key_data += 1,
+                None => {}
+            }
+            stats.heart_kills += i32::from(killed_heart(run));
+        }
+
+        let rate = |n: i32, d: i32| if d > 0 { n as f64 / d as f64 } else { 0.0 };
+        stats.heart_attempt_rate = rate(stats.heart_attempts, stats.act3_completions_with_key_data);
+        stats.heart_kill_rate = rate(stats.heart_kills, stats.heart_attempts);
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Character;
+
+    fn run(floor: i32, victory: bool, keys: Option<bool>) -> RunMetrics {
+        let mut run = sample_run("r", Character::Defect, victory);
+        run.floor_reached = floor;
+        run.has_ruby_key = keys;
+        run.has_emerald_key = keys;
+        run.has_sapphire_key = keys;
+        run
+    }
+
+    #[test]
+    fn test_key_signals() {
+        let none = KeySignals::default();
+        let all = KeySignals {
+            recall: true,
+            blue_key_log: true,
+            green_key_log: true,
+        };
+        let ruby_only = KeySignals {
+            recall: true,
+            ..none
+        };
+
+        assert_eq!(none.keys(None), (None, None, None));
+        assert_eq!(none.keys(Some("2018-12-05")), (None, None, None));
+        assert_eq!(
+            none.keys(Some("2020-11-30")),
+            (Some(false), Some(false), Some(false))
+        );
+        assert_eq!(all.keys(None), (Some(true), Some(true), Some(true)));
+        assert_eq!(ruby_only.keys(None), (Some(true), Some(false), Some(false)));
+    }
+
+    #[test]
+    fn test_heart_attempt_rate() {
+        let runs = vec![
+            run(57, true, Some(true)),   // heart kill
+            run(53, false, Some(true)),  // died to the heart
+            run(51, true, Some(false)),  // stopped after Act 3
+            run(51, true, None),         // pre-keys file
+            run(30, false, Some(false)), // never reached Act 3's end
+        ];
+        let stats = KeyStats::from_runs(&runs);
+        assert_eq!(stats.act3_completions, 4);
+        assert_eq!(stats.act3_completions_with_key_data, 3);
+        assert_eq!(stats.heart_attempts, 2);
+        assert_eq!(stats.heart_kills, 1);
+        assert!((stats.heart_attempt_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert!((stats.heart_kill_rate - 0.5).abs() < 1e-9);
+    }
+}
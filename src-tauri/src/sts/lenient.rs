@@ -0,0 +1,556 @@
+//! Field-level tolerance for run files
+//!
+//! Run files edited by hand or written by other tools sometimes hold values
+//! the game never writes: numbers as strings, `null` inside lists, or a value
+//! of the wrong type. The deserializers here are used on every field of the
+//! raw run file so that one bad value only loses that value, not the file:
+//!
+//! - `null` is treated as a missing field or skipped as a list entry
+//! - numbers written as floats or as numeric strings (`"34"`, `" -2 "`,
+//!   `"+51"`, `"12.0"`) are read as integers, clamped to the `i32` range
+//! - any other value of the wrong type, including a string that isn't a
+//!   number, is ignored, as if it were missing
+//!
+//! A parse that ignored a value is recorded as
+//! [`Recovery::IgnoredInvalidValues`], and one that read a number from a
+//! string as [`Recovery::CoercedNumbers`] (see [`track`]), so the load report
+//! shows which files hold data the game wouldn't write. Nulls and floats are
+//! silent.
+//!
+//! [`Recovery::IgnoredInvalidValues`]: super::Recovery::IgnoredInvalidValues
+//! [`Recovery::CoercedNumbers`]: super::Recovery::CoercedNumbers
+
+use std::cell::Cell;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+use super::Recovery;
+
+/// What a tracked parse had to tolerate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tolerated {
+    /// Values of the wrong type were ignored
+    pub ignored_values: bool,
+    /// Numbers were read from strings
+    pub coerced_numbers: bool,
+}
+
+impl Tolerated {
+    /// The recoveries to record for the parse
+    pub fn recoveries(self) -> impl Iterator<Item = Recovery> {
+        [
+            self.ignored_values
+                .then_some(Recovery::IgnoredInvalidValues),
+            self.coerced_numbers.then_some(Recovery::CoercedNumbers),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+thread_local! {
+    static TOLERATED: Cell<Tolerated> = const {
+        Cell::new(Tolerated {
+            ignored_values: false,
+            coerced_numbers: false,
+        })
+    };
+}
+
+fn note(update: impl FnOnce(&mut Tolerated)) {
+    TOLERATED.with(|tolerated| {
+        let mut current = tolerated.get();
+        update(&mut current);
+        tolerated.set(current);
+    });
+}
+
+fn note_ignored() {
+    note(|t| t.ignored_values = true);
+}
+
+/// Run a parse and report what it tolerated
+pub fn track<T>(parse: impl FnOnce() -> T) -> (T, Tolerated) {
+    let previous = TOLERATED.with(|tolerated| tolerated.replace(Tolerated::default()));
+    let result = parse();
+    let tolerated = TOLERATED.with(|tolerated| tolerated.replace(previous));
+    (result, tolerated)
+}
+
+/// Convert a JSON value, noting it as ignored if it has the wrong type
+fn convert<T: DeserializeOwned>(value: Value) -> Option<T> {
+    if value.is_null() {
+        return None;
+    }
+    let converted = T::deserialize(value).ok();
+    if converted.is_none() {
+        note_ignored();
+    }
+    converted
+}
+
+/// Any optional field; a value of the wrong type reads as missing
+pub fn value<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    Ok(Option::<Value>::deserialize(deserializer)?.and_then(convert))
+}
+
+/// A 64-bit integer written as an integer, a float or a numeric string
+pub fn integer<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(
+        Option::<Value>::deserialize(deserializer)?.and_then(|value| {
+            let number = number_from(&value);
+            if number.is_none() && !value.is_null() {
+                note_ignored();
+            }
+            number
+        }),
+    )
+}
+
+/// An integer field, like [`integer`] but clamped to the `i32` range
+pub fn number<'de, D>(deserializer: D) -> Result<Option<i32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(integer(deserializer)?.map(clamp_i32))
+}
+
+/// A list whose `null` and invalid entries are dropped
+pub fn list<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    Ok(array(deserializer)?.map(|items| items.into_iter().filter_map(convert).collect()))
+}
+
+/// A list indexed by floor; `null` and invalid entries stay as `None` so
+/// later entries keep their floor
+pub fn floor_list<'de, D, T>(deserializer: D) -> Result<Option<Vec<Option<T>>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    Ok(array(deserializer)?.map(|items| items.into_iter().map(convert).collect()))
+}
+
+/// A list of integers indexed by floor, read like [`integer`]; `null` and
+/// invalid entries stay as `None` so later entries keep their floor
+pub fn numbers<'de, D>(deserializer: D) -> Result<Option<Vec<Option<i64>>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(array(deserializer)?.map(|items| {
+        items
+            .iter()
+            .map(|value| {
+                let number = number_from(value);
+                if number.is_none() && !value.is_null() {
+                    note_ignored();
+                }
+                number
+            })
+            .collect()
+    }))
+}
+
+fn array<'de, D>(deserializer: D) -> Result<Option<Vec<Value>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(
+        Option::<Value>::deserialize(deserializer)?.and_then(|value| match value {
+            Value::Array(items) => Some(items),
+            Value::Null => None,
+            _ => {
+                note_ignored();
+                None
+            }
+        }),
+    )
+}
+
+/// Read an integer from a JSON number or numeric string
+///
+/// Strings are trimmed and may carry a sign; a string that was read is noted
+/// for [`track`] as coerced, and one that isn't a number as ignored, so
+/// callers reading single values out of a JSON object report both.
+pub fn number_from(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f as i64)),
+        Value::String(s) => {
+            let s = s.trim();
+            let number = s.parse::<i64>().ok().or_else(|| {
+                s.parse::<f64>()
+                    .ok()
+                    .filter(|f| f.is_finite())
+                    .map(|f| f as i64)
+            });
+            if number.is_some() {
+                note(|t| t.coerced_numbers = true);
+            } else {
+                note_ignored();
+            }
+            number
+        }
+        _ => None,
+    }
+}
+
+/// Saturating conversion of a parsed number to `i32`
+pub fn clamp_i32(number: i64) -> i32 {
+    number.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::samples::SAMPLE_RUNS;
+    use crate::sts::{
+        parse_run_file, Character, Recovery, RunFileBuilder, RunMetrics, RunsDirFixture,
+    };
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize)]
+    struct Sample {
+        #[serde(deserialize_with = "number", default)]
+        floor: Option<i32>,
+        #[serde(deserialize_with = "value", default)]
+        name: Option<String>,
+        #[serde(deserialize_with = "list", default)]
+        deck: Option<Vec<String>>,
+        #[serde(deserialize_with = "floor_list", default)]
+        path: Option<Vec<Option<String>>>,
+    }
+
+    fn parse(json: &str) -> (Sample, bool) {
+        let (sample, tolerated) = track(|| serde_json::from_str::<Sample>(json));
+        (sample.unwrap(), tolerated.ignored_values)
+    }
+
+    #[test]
+    fn test_coercions_keep_values() {
+        let (sample, tolerated) = track(|| {
+            serde_json::from_str::<Sample>(
+                r#"{"floor": " 12 ", "name": null, "deck": ["Bash", null], "path": ["M", null, "?"]}"#,
+            )
+        });
+        let sample = sample.unwrap();
+        assert_eq!(
+            tolerated,
+            Tolerated {
+                ignored_values: false,
+                coerced_numbers: true,
+            }
+        );
+        assert_eq!(sample.floor, Some(12));
+        assert_eq!(sample.name, None);
+        assert_eq!(sample.deck.unwrap(), ["Bash"]);
+        assert_eq!(
+            sample.path.unwrap(),
+            [Some("M".into()), None, Some("?".into())]
+        );
+
+        let (sample, tolerated) = track(|| serde_json::from_str::<Sample>(r#"{"floor": 1e300}"#));
+        assert_eq!(sample.unwrap().floor, Some(i32::MAX));
+        assert_eq!(tolerated, Tolerated::default());
+    }
+
+    #[test]
+    fn test_numeric_strings() {
+        for (text, expected) in [
+            ("34", Some(34)),
+            (" -2 ", Some(-2)),
+            ("+51", Some(51)),
+            ("12.0", Some(12)),
+            ("-3.7", Some(-3)),
+            ("1e3", Some(1000)),
+            ("thirty", None),
+            ("", None),
+            ("NaN", None),
+            ("12 floors", None),
+        ] {
+            assert_eq!(number_from(&json!(text)), expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn test_wrong_types_are_ignored_and_tracked() {
+        let (sample, ignored) =
+            parse(r#"{"floor": [3], "name": 7, "deck": ["Bash", 4, {}], "path": ["M", 5]}"#);
+        assert!(ignored);
+        assert_eq!((sample.floor, sample.name), (None, None));
+        assert_eq!(sample.deck.unwrap(), ["Bash"]);
+        assert_eq!(sample.path.unwrap(), [Some("M".into()), None]);
+
+        let (numbers, tolerated) = track(|| {
+            let mut de = serde_json::Deserializer::from_str(r#"[1, "two", "3", null]"#);
+            numbers(&mut de).unwrap().unwrap()
+        });
+        assert_eq!(numbers, [Some(1), None, Some(3), None]);
+        assert_eq!(
+            tolerated,
+            Tolerated {
+                ignored_values: true,
+                coerced_numbers: true,
+            }
+        );
+
+        let (number, tolerated) = track(|| number_from(&json!("thirty")));
+        assert_eq!(number, None);
+        assert!(tolerated.ignored_values);
+
+        let (sample, ignored) = parse(r#"{"deck": "Bash"}"#);
+        assert!(ignored);
+        assert!(sample.deck.is_none());
+        assert!(!parse("{}").1);
+    }
+
+    /// A change to one value of a run file
+    #[derive(Debug, Clone)]
+    enum Mutation {
+        Delete,
+        Replace(Value),
+        TruncateArray(usize),
+    }
+
+    fn mutation() -> impl Strategy<Value = Mutation> {
+        let replacement = prop_oneof![
+            Just(Value::Null),
+            Just(json!("12")),
+            Just(json!("not a number")),
+            Just(json!(-1)),
+            Just(json!(i64::MIN)),
+            Just(json!(1e300)),
+            Just(json!(true)),
+            Just(json!([null, 1, "x", {}])),
+            Just(json!({ "floor": null, "key": 3 })),
+        ];
+        prop_oneof![
+            Just(Mutation::Delete),
+            replacement.prop_map(Mutation::Replace),
+            (0usize..4).prop_map(Mutation::TruncateArray),
+        ]
+    }
+
+    /// JSON pointers of every value below the root
+    fn pointers(value: &Value, prefix: String, out: &mut Vec<String>) {
+        let children: Vec<(String, &Value)> = match value {
+            Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), v))
+                .collect(),
+            _ => Vec::new(),
+        };
+        for (key, child) in children {
+            let pointer = format!("{}/{}", prefix, key);
+            out.push(pointer.clone());
+            pointers(child, pointer, out);
+        }
+    }
+
+    fn apply(root: &mut Value, pointer: &str, mutation: &Mutation) {
+        let Some((parent, key)) = pointer.rsplit_once('/') else {
+            return;
+        };
+        match mutation {
+            Mutation::Delete => match root.pointer_mut(parent) {
+                Some(Value::Object(map)) => {
+                    map.remove(key);
+                }
+                Some(Value::Array(items)) => {
+                    if let Ok(i) = key.parse::<usize>() {
+                        if i < items.len() {
+                            items.remove(i);
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Mutation::Replace(value) => {
+                if let Some(target) = root.pointer_mut(pointer) {
+                    *target = value.clone();
+                }
+            }
+            Mutation::TruncateArray(len) => {
+                if let Some(Value::Array(items)) = root.pointer_mut(pointer) {
+                    items.truncate(*len);
+                }
+            }
+        }
+    }
+
+    fn assert_counts_not_negative(run: &RunMetrics) {
+        let counts = [
+            run.deck_size,
+            run.attack_count,
+            run.skill_count,
+            run.power_count,
+            run.upgraded_cards,
+            run.cards_removed,
+            run.cards_added,
+            run.starters_remaining,
+            run.relic_count,
+            run.elites_killed,
+            run.bosses_killed,
+            run.campfires_rested,
+            run.campfires_upgraded,
+            run.shops_visited,
+            run.shops_skipped,
+            run.cards_purchased,
+            run.cards_from_shops,
+            run.relics_from_shops,
+            run.potions_from_shops,
+            run.shop_removals,
+            run.gold_spent_on_removals,
+            run.cards_from_rewards,
+            run.singing_bowl_picks,
+            run.potions_used,
+            run.question_rooms,
+            run.question_rooms_fought,
+        ];
+        assert!(counts.iter().all(|c| *c >= 0), "{counts:?}");
+        let optional = [
+            run.rewards_offered,
+            run.rewards_skipped,
+            run.potions_obtained_count,
+            run.potions_wasted,
+            run.relics_after_act1,
+            run.relics_after_act2,
+            run.relics_after_act3,
+            run.boss_relics_skipped,
+        ];
+        assert!(optional.iter().flatten().all(|c| *c >= 0), "{optional:?}");
+    }
+
+    /// Sample runs that are valid JSON, as mutation seeds
+    fn seeds() -> Vec<(Character, Value)> {
+        SAMPLE_RUNS
+            .iter()
+            .filter_map(|s| Some((s.character, serde_json::from_slice(s.contents).ok()?)))
+            .collect()
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(128))]
+
+        #[test]
+        fn test_mutated_runs_never_panic(
+            seed in any::<prop::sample::Index>(),
+            mutations in prop::collection::vec((any::<prop::sample::Index>(), mutation()), 1..6),
+            truncate_at in prop::option::weighted(0.2, any::<prop::sample::Index>()),
+        ) {
+            let seeds = seeds();
+            let (character, mut run) = seeds[seed.index(seeds.len())].clone();
+            for (target, mutation) in &mutations {
+                let mut targets = Vec::new();
+                pointers(&run, String::new(), &mut targets);
+                if targets.is_empty() {
+                    break;
+                }
+                apply(&mut run, &targets[target.index(targets.len())], mutation);
+            }
+            let mut contents = run.to_string().into_bytes();
+            if let Some(at) = truncate_at {
+                contents.truncate(at.index(contents.len()));
+            }
+
+            let fixture = RunsDirFixture::new();
+            let path = fixture.add_raw(character, "fuzz.run", &contents);
+            if let Ok((run, _)) = parse_run_file(&path, &character.into()) {
+                assert_counts_not_negative(&run);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hostile_values_keep_the_run() {
+        let fixture = RunsDirFixture::new();
+        let path = fixture.add(
+            &RunFileBuilder::new("hostile")
+                .set("floor_reached", "17")
+                .set("campfire_choices", json!([null, { "key": "REST" }, 3]))
+                .set(
+                    "damage_taken",
+                    json!([{ "floor": i64::MIN, "damage": 5, "enemies": "Cultist" }]),
+                )
+                .set("items_purged_floors", json!(["2", null]))
+                .set("current_hp_per_floor", json!([null, "70"]))
+                .set("max_hp_per_floor", json!([80, "not hp"]))
+                .set("card_choices", json!({ "picked": "Bash" }))
+                .deck(&["Strike_R", "Defend_Strike"]),
+        );
+        let (run, recoveries) = parse_run_file(&path, &Character::Ironclad.into()).unwrap();
+        assert_eq!(
+            recoveries,
+            [Recovery::IgnoredInvalidValues, Recovery::CoercedNumbers]
+        );
+        assert_eq!(run.floor_reached, 17);
+        assert_eq!(run.campfires_rested, 1);
+        assert_eq!(run.encounters.len(), 1);
+        assert_eq!(run.max_hp_at_end, 80);
+        assert!(run.rewards_offered.is_none());
+        assert_eq!(
+            (run.attack_count, run.skill_count, run.power_count),
+            (2, 0, 0)
+        );
+        assert_counts_not_negative(&run);
+    }
+
+    #[test]
+    fn test_non_numeric_potion_floor_is_reported() {
+        let fixture = RunsDirFixture::new();
+        let path = fixture.add(&RunFileBuilder::new("potion").set(
+            "potions_obtained",
+            json!([{ "key": "Fire Potion", "floor": "third" }]),
+        ));
+        let (run, recoveries) = parse_run_file(&path, &Character::Ironclad.into()).unwrap();
+        assert_eq!(recoveries, [Recovery::IgnoredInvalidValues]);
+        assert_eq!(run.potions_obtained[0].floor, None);
+    }
+
+    #[test]
+    fn test_string_numbers_are_read_and_reported() {
+        let fixture = RunsDirFixture::new();
+        let path = fixture.add_raw(
+            Character::Ironclad,
+            "string_numbers.run",
+            include_bytes!("../../tests/fixtures/runs/string_numbers.run"),
+        );
+        let (run, recoveries) = parse_run_file(&path, &Character::Ironclad.into()).unwrap();
+        assert_eq!(recoveries, [Recovery::CoercedNumbers]);
+        assert_eq!(
+            (
+                run.floor_reached,
+                run.score,
+                run.ascension_level,
+                run.playtime
+            ),
+            (34, 412, 15, 2431)
+        );
+        assert_eq!(run.timestamp, Some(1_700_000_000));
+        assert_eq!(run.max_hp_at_end, 85);
+        assert_eq!(run.shop_removals, 1);
+        assert_eq!(run.cards_purged[0].floor, Some(2));
+        assert_eq!(run.relic_acquisitions[1].floor, Some(7));
+        assert_eq!(run.encounters[0].floor, 3);
+        assert_eq!(run.encounters[0].hp_after, Some(61));
+        assert!(run.encounters[0].elite);
+
+        let clean = fixture.add(&RunFileBuilder::new("clean").floor_reached(34));
+        let (_, recoveries) = parse_run_file(&clean, &Character::Ironclad.into()).unwrap();
+        assert!(recoveries.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+//! Localized display names
+//!
+//! Only names shown to people are translated: character display names (and
+//! later card names). Identifiers such as `character` dir names and card keys
+//! stay as the game writes them, so filters and links work in every locale.
+//! The HTTP API picks a locale from the `Accept-Language` header; the desktop
+//! commands use the `locale` setting. Anything unknown falls back to English.
+
+use serde::{Deserialize, Serialize};
+
+use super::{Character, CharacterStats};
+
+/// A language display names are available in
+///
+/// Deserializes from any language tag, falling back to English, so a config
+/// file naming an unsupported locale still loads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", from = "String")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+    Zh,
+}
+
+impl Locale {
+    /// Every supported locale
+    pub fn all() -> &'static [Locale] {
+        &[Locale::En, Locale::De, Locale::Fr, Locale::Es, Locale::Zh]
+    }
+
+    /// Two-letter language code, e.g. `de`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+            Locale::Fr => "fr",
+            Locale::Es => "es",
+            Locale::Zh => "zh",
+        }
+    }
+
+    /// Resolve a language tag such as `de`, `de-AT` or `zh_Hans_CN` by its
+    /// primary language, ignoring case
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.trim().split(['-', '_']).next()?;
+        Locale::all()
+            .iter()
+            .copied()
+            .find(|locale| locale.code().eq_ignore_ascii_case(language))
+    }
+
+    /// Pick the best supported locale for an `Accept-Language` header value
+    ///
+    /// Languages are tried in order of their `q` weight (1 when left out),
+    /// earlier entries first among equal weights; `q=0` and malformed
+    /// weights rule an entry out. Without a supported language, including
+    /// for `*`, this is English.
+    pub fn negotiate(accept_language: &str) -> Locale {
+        let mut ranges: Vec<(&str, f32)> = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let mut quality = 1.0;
+                for param in parts {
+                    if let Some(q) = param.trim().strip_prefix("q=") {
+                        quality = q.trim().parse().ok()?;
+                    }
+                }
+                (quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // Stable, so equal weights keep the order they were listed in
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges
+            .into_iter()
+            .find_map(|(tag, _)| Locale::from_tag(tag))
+            .unwrap_or_default()
+    }
+}
+
+impl From<String> for Locale {
+    fn from(tag: String) -> Self {
+        Locale::from_tag(&tag).unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl Character {
+    /// Display name in the given locale
+    pub fn localized_name(&self, locale: Locale) -> &'static str {
+        match (locale, self) {
+            (Locale::En, _) => self.display_name(),
+            (Locale::De, Character::Ironclad) => "Der Eisenpanzer",
+            (Locale::De, Character::TheSilent) => "Die Stille",
+            (Locale::De, Character::Defect) => "Der Defekt",
+            (Locale::De, Character::Watcher) => "Die Wächterin",
+            (Locale::Fr, Character::Ironclad) => "Le Cuirassé",
+            (Locale::Fr, Character::TheSilent) => "La Silencieuse",
+            (Locale::Fr, Character::Defect) => "Le Défectueux",
+            (Locale::Fr, Character::Watcher) => "La Gardienne",
+            (Locale::Es, Character::Ironclad) => "El Acorazado",
+            (Locale::Es, Character::TheSilent) => "La Silenciosa",
+            (Locale::Es, Character::Defect) => "El Defecto",
+            (Locale::Es, Character::Watcher) => "La Vigía",
+            (Locale::Zh, Character::Ironclad) => "铁甲战士",
+            (Locale::Zh, Character::TheSilent) => "静默猎手",
+            (Locale::Zh, Character::Defect) => "故障机器人",
+            (Locale::Zh, Character::Watcher) => "观者",
+        }
+    }
+}
+
+impl CharacterStats {
+    /// Replace the display name with its translation; stats of a character
+    /// the app doesn't know keep theirs
+    pub fn localize(&mut self, locale: Locale) {
+        if let Ok(character) = self.character.parse::<Character>() {
+            self.display_name = character.localized_name(locale).to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(Locale::negotiate("de-DE,de;q=0.9,en;q=0.8"), Locale::De);
+        assert_eq!(Locale::negotiate("en;q=0.5, fr-CA"), Locale::Fr);
+        assert_eq!(Locale::negotiate("ja, zh-Hans-CN;q=0.7"), Locale::Zh);
+        assert_eq!(Locale::negotiate("es;q=0.8, de;q=0.8"), Locale::Es);
+        assert_eq!(Locale::negotiate("de;q=0, es;q=0.1"), Locale::Es);
+    }
+
+    #[test]
+    fn test_negotiate_falls_back_to_english() {
+        for header in ["", "*", "ja, ko;q=0.5", "de;q=abc", "de;q=0", ";;,"] {
+            assert_eq!(Locale::negotiate(header), Locale::En, "{header:?}");
+        }
+    }
+
+    #[test]
+    fn test_localized_names() {
+        assert_eq!(Character::TheSilent.localized_name(Locale::En), "Silent");
+        assert_eq!(
+            serde_json::from_str::<Locale>("\"fr-CA\"").unwrap(),
+            Locale::Fr
+        );
+        assert_eq!(
+            serde_json::from_str::<Locale>("\"ja\"").unwrap(),
+            Locale::En
+        );
+        assert_eq!(serde_json::to_string(&Locale::Zh).unwrap(), "\"zh\"");
+        assert_eq!(
+            Character::Ironclad.localized_name(Locale::De),
+            "Der Eisenpanzer"
+        );
+        for locale in Locale::all() {
+            assert_eq!(Locale::from_tag(locale.code()), Some(*locale));
+            for character in Character::all() {
+                assert!(!character.localized_name(*locale).is_empty());
+            }
+        }
+    }
+}
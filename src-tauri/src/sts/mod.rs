@@ -2,25 +2,254 @@
 //!
 //! This module handles parsing STS run files from the game's save directory.
 
+pub mod ascension;
+pub mod boss_relics;
+pub mod bosses;
+pub mod bucketing;
+pub mod cache;
+pub mod calendar;
+pub mod compare;
+pub mod csv;
+pub mod deck;
+pub mod deck_size;
+pub mod demo;
+pub mod elites;
+pub mod enemies;
+pub mod events;
+pub mod export;
+pub mod features;
+pub mod filter;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod fixtures;
+pub mod floors;
+pub mod hidden;
+pub mod html_report;
+pub mod items;
+pub mod keys;
+mod lenient;
+pub mod locale;
+pub mod modded;
+pub mod neow;
+pub mod paths;
+pub mod potions;
+pub mod profiles;
+pub mod progress;
+pub mod purges;
+pub mod records;
+pub mod recovery;
+pub mod relics;
+pub mod report;
+pub mod rewards;
+pub mod ruleset;
+#[cfg(any(test, feature = "sample-data"))]
+pub mod samples;
+pub mod schedule;
+pub mod score;
+pub mod shops;
+pub mod stats_math;
+pub mod upgrades;
+pub mod warmup;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
-use std::sync::RwLock;
 use utoipa::ToSchema;
 
+pub use ascension::{
+    ascension_normalized, ascension_stats, AscensionBand, AscensionNormalized, AscensionStats,
+    MIN_BAND_RUNS,
+};
+pub use boss_relics::{
+    boss_relic_stats, ActBossRelicStats, BossRelicChoice, BossRelicPicks, BossRelicStats,
+};
+pub use bosses::{act_boss_stats, ActBossStats, BossStats};
+pub use bucketing::{InvalidTzOffset, TimeBucketing, MAX_TZ_OFFSET_MINUTES};
+pub use cache::{
+    DeltaCursor, LoadProgress, ReparseError, RunCache, RunDelta, ScanOptions,
+    DEFAULT_MAX_FILE_BYTES,
+};
+pub use calendar::{calendar, calendar_years, CalendarDay};
+pub use compare::{PeriodComparison, PeriodDelta, PeriodStats};
+pub use deck_size::{
+    parse_deck_size_bounds, DeckSizeBucket, DeckSizeStats, DEFAULT_DECK_SIZE_BOUNDS,
+};
+pub use demo::{demo_mode, generate_runs, set_demo_mode};
+pub use elites::{ActEliteStats, ActElites, EliteRate, EliteStats};
+pub use enemies::{
+    classify_encounter, enemy_catalog, longest_fight, normalize_encounter, slow_fights, Encounter,
+    EncounterDamage, EncounterKind, EncounterTurns, EnemyCount,
+};
+pub use events::{ActQuestionRoomRate, ActQuestionRooms, EventStats, QuestionRoomRate};
+pub use export::{
+    import_export_data, verify_export_data, ExportChecksum, ExportFilter, ExportVerification,
+    ImportError, EXPORT_SCHEMA_VERSION,
+};
+pub use features::{feature_set, run_features, FeatureRow, FeatureSet, FEATURE_COLUMNS};
+pub use filter::{RunExclusion, RunFilter, RunSelection, TimeWindow};
+#[cfg(any(test, feature = "test-utils"))]
+pub use fixtures::{RunFileBuilder, RunsDirFixture};
+pub use floors::{floor_semantics, set_floor_semantics, FloorSemantics};
+pub use hidden::{hidden_runs, set_run_hidden};
+pub use html_report::{write_html_report, HtmlReport};
+pub use items::{classify, ItemKind};
+pub use keys::KeyStats;
+pub use locale::Locale;
+pub use neow::{boss_swap_stats, BossSwapExclusion, BossSwapRelic, BossSwapStats, WinRecord};
+pub use paths::{normalize_runs_path, PathInputError};
+pub use potions::{PotionNameCount, PotionNameStats, PotionPickup, PotionStats};
+pub use profiles::{
+    active_profile, current_profile, profile_exists, profile_names, set_active_profile,
+    set_profile_runs_paths, valid_profile_name, with_profile, DEFAULT_PROFILE,
+};
+pub use progress::{CharacterProgress, HeartMilestone, ProgressReport};
+pub use purges::{purged_card_stats, CardRemoval, PurgedCardStats};
+pub use records::{personal_bests, PersonalBests, PersonalRecord};
+pub use recovery::{DecodeError, Recovery};
+pub use relics::{
+    first_relic_stats, relic_source_stats, relic_timing, CharacterRelicSources, FirstRelic,
+    FirstRelicStats, RelicAcquisition, RelicCheckpoint, RelicSource, RelicSourceCounts,
+    RelicSourceStats, RelicTiming, RelicTimingBucket,
+};
+pub use report::{ReportFormat, StatsReport};
+pub use rewards::{ActRewards, ActSkipRate, SkipRate, SkipRateStats};
+pub use ruleset::Ruleset;
+#[cfg(any(test, feature = "sample-data"))]
+pub use samples::install_sample_runs;
+pub use schedule::{PlayTimeStats, TimeBucket, DEFAULT_MIN_BUCKET_RUNS};
+pub use score::{RunDetail, ScoreBreakdown, ScoreComponent};
+pub use shops::{ActShopEngagement, ActShops, CharacterShopEngagement, ShopEngagement, ShopStats};
+pub use stats_math::wilson_interval;
+pub use upgrades::{UpgradeBucket, UpgradeImpactStats, UpgradeSources};
+pub use warmup::{warm_up, warming_up, warmup_state, WarmupState, WARMUP_BATCH_FILES};
+
+/// Application identifier, used to locate the app data directory
+const APP_IDENTIFIER: &str = "com.sts.statviewer";
+
+/// Global override for the app data directory (used by tests and custom setups)
+static CUSTOM_DATA_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Set a custom directory for persisted viewer data
+pub fn set_data_dir(path: Option<PathBuf>) {
+    let mut data_dir = CUSTOM_DATA_DIR.write();
+    *data_dir = path;
+}
+
+/// Get the directory for persisted viewer data (hidden runs, ...)
+///
+/// Defaults to the same location Tauri uses for the app data directory.
+pub fn get_data_dir() -> Option<PathBuf> {
+    if let Some(custom) = CUSTOM_DATA_DIR.read().clone() {
+        return Some(custom);
+    }
+    dirs::data_dir().map(|d| d.join(APP_IDENTIFIER))
+}
+
+/// Report from the most recent call to [`load_all_runs`]
+static LAST_LOAD_REPORT: RwLock<Option<LoadReport>> = RwLock::new(None);
+
+/// Store the report of a completed load
+fn record_load_report(report: LoadReport) {
+    *LAST_LOAD_REPORT.write() = Some(report);
+}
+
+/// Get the report of the most recent load, if runs have been loaded yet
+pub fn last_load_report() -> Option<LoadReport> {
+    LAST_LOAD_REPORT.read().clone()
+}
+
+/// Parsed runs for the current runs directory
+static RUN_CACHE: RwLock<Option<RunCache>> = RwLock::new(None);
+
+/// Parsed runs of profiles other than the active one, least recently used
+/// first
+///
+/// Kept apart from [`RUN_CACHE`] so a request for another profile doesn't
+/// throw away the active profile's cache, its delta cursors or its load
+/// report.
+static SIDE_CACHES: parking_lot::Mutex<Vec<RunCache>> = parking_lot::Mutex::new(Vec::new());
+
+/// Side caches kept before the least recently used is dropped
+const MAX_SIDE_CACHES: usize = 4;
+
+/// How the runs directory is searched, set from the config
+static SCAN_OPTIONS: RwLock<ScanOptions> = RwLock::new(ScanOptions::DEFAULT);
+
+/// Set how the runs directory is searched
+pub fn set_scan_options(options: ScanOptions) {
+    *SCAN_OPTIONS.write() = options;
+}
+
+/// Get how the runs directory is searched
+pub fn scan_options() -> ScanOptions {
+    *SCAN_OPTIONS.read()
+}
+
 /// Global custom runs path that can be set by the user
 /// This takes precedence over auto-detection if set
 static CUSTOM_RUNS_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
 
 /// Set a custom path for loading runs
 pub fn set_custom_runs_path(path: Option<PathBuf>) {
-    let mut custom_path = CUSTOM_RUNS_PATH.write().unwrap();
+    let mut custom_path = CUSTOM_RUNS_PATH.write();
     *custom_path = path;
 }
 
 /// Get the currently set custom runs path
 pub fn get_custom_runs_path() -> Option<PathBuf> {
-    CUSTOM_RUNS_PATH.read().unwrap().clone()
+    CUSTOM_RUNS_PATH.read().clone()
+}
+
+/// Serializes tests that change the global runs path
+#[cfg(test)]
+static RUNS_PATH_TEST_LOCK: parking_lot::Mutex<()> = parking_lot::Mutex::new(());
+
+/// Points the custom runs path at an empty temporary directory for a test
+///
+/// Holds a lock so tests relying on the global path don't interfere, and
+/// clears the custom path again when dropped.
+#[cfg(test)]
+pub(crate) struct TestRunsDir {
+    fixture: RunsDirFixture,
+    _lock: parking_lot::MutexGuard<'static, ()>,
+}
+
+#[cfg(test)]
+impl TestRunsDir {
+    pub(crate) fn empty() -> Self {
+        let lock = RUNS_PATH_TEST_LOCK.lock();
+        let fixture = RunsDirFixture::new();
+        set_custom_runs_path(Some(fixture.path().to_path_buf()));
+        Self {
+            fixture,
+            _lock: lock,
+        }
+    }
+
+    /// The temporary runs directory
+    pub(crate) fn path(&self) -> &std::path::Path {
+        self.fixture.path()
+    }
+
+    /// Write a run file into the directory
+    pub(crate) fn add(&self, run: &RunFileBuilder) -> PathBuf {
+        self.fixture.add(run)
+    }
+
+    /// A runs directory holding the bundled sample runs
+    pub(crate) fn with_samples() -> Self {
+        let runs_dir = Self::empty();
+        install_sample_runs(runs_dir.path()).expect("failed to install sample runs");
+        runs_dir
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestRunsDir {
+    fn drop(&mut self) {
+        set_custom_runs_path(None);
+    }
 }
 
 /// Available characters in Slay the Spire
@@ -64,16 +293,188 @@ impl Character {
             Character::Watcher => "Watcher",
         }
     }
+
+    /// Extra names accepted by [`Character::parse_flexible`], lowercase
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Character::Ironclad => &["ic"],
+            Character::TheSilent => &["silent", "the silent"],
+            Character::Defect => &[],
+            Character::Watcher => &[],
+        }
+    }
+
+    /// Resolve a user-supplied character name
+    ///
+    /// Accepts dir names, display names and aliases, ignoring case,
+    /// surrounding whitespace, and whether words are separated by spaces,
+    /// underscores or hyphens.
+    pub fn parse_flexible(name: &str) -> Option<Character> {
+        let normalized = name
+            .split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        let matches = |candidate: &str| {
+            candidate
+                .replace('_', " ")
+                .eq_ignore_ascii_case(&normalized)
+        };
+        Character::all().iter().copied().find(|c| {
+            matches(c.dir_name())
+                || matches(c.display_name())
+                || c.aliases().iter().any(|alias| matches(alias))
+        })
+    }
+}
+
+/// Error for a character name that [`Character::parse_flexible`] doesn't accept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownCharacter(pub String);
+
+impl std::fmt::Display for UnknownCharacter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown character: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCharacter {}
+
+impl std::str::FromStr for Character {
+    type Err = UnknownCharacter;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Character::parse_flexible(s).ok_or_else(|| UnknownCharacter(s.to_string()))
+    }
+}
+
+impl TryFrom<&str> for Character {
+    type Error = UnknownCharacter;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Character a run belongs to, as named by its runs folder
+///
+/// Serializes as the folder name, so base game characters come out as their
+/// dir names (`IRONCLAD`, `THE_SILENT`, ...) and modded characters keep
+/// whatever id their mod uses.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CharacterId {
+    Known(Character),
+    Modded(String),
+}
+
+impl CharacterId {
+    /// Build from a folder name, recognizing base game dir names in any case
+    pub fn from_dir_name(name: &str) -> Self {
+        Character::all()
+            .iter()
+            .find(|c| c.dir_name().eq_ignore_ascii_case(name))
+            .map_or_else(|| CharacterId::Modded(name.to_string()), |c| (*c).into())
+    }
+
+    /// The base game character, if this is one
+    pub fn known(&self) -> Option<Character> {
+        match self {
+            CharacterId::Known(character) => Some(*character),
+            CharacterId::Modded(_) => None,
+        }
+    }
+
+    /// Folder name of the character
+    pub fn as_str(&self) -> &str {
+        match self {
+            CharacterId::Known(character) => character.dir_name(),
+            CharacterId::Modded(name) => name,
+        }
+    }
+}
+
+impl From<Character> for CharacterId {
+    fn from(character: Character) -> Self {
+        CharacterId::Known(character)
+    }
+}
+
+impl PartialEq<Character> for CharacterId {
+    fn eq(&self, other: &Character) -> bool {
+        self.known() == Some(*other)
+    }
+}
+
+impl std::fmt::Display for CharacterId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for CharacterId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for CharacterId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(CharacterId::from_dir_name(&name))
+    }
+}
+
+impl utoipa::PartialSchema for CharacterId {
+    fn schema() -> utoipa::openapi::RefOr<utoipa::openapi::schema::Schema> {
+        use utoipa::openapi::schema::{ObjectBuilder, Type};
+
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .description(Some(
+                "Character folder name: IRONCLAD, THE_SILENT, DEFECT or WATCHER, \
+                 or the id of a modded character",
+            ))
+            .examples(Character::all().iter().map(|c| c.dir_name()))
+            .into()
+    }
+}
+
+impl ToSchema for CharacterId {}
+
+/// Last floor of each of the first three acts (Act 3 includes the chest floor)
+pub const ACT_END_FLOORS: [i32; 3] = [17, 34, 51];
+
+/// Floor of the Act 1 boss; the act ends on the treasure floor after it
+pub const ACT1_BOSS_FLOOR: i32 = 16;
+
+/// Whether a run that reached `floor_reached` finished `act` (1-3)
+pub fn completed_act(floor_reached: i32, act: i32) -> bool {
+    usize::try_from(act - 1)
+        .ok()
+        .and_then(|i| ACT_END_FLOORS.get(i))
+        .is_some_and(|end| floor_reached >= *end)
+}
+
+/// Act a floor belongs to (1-3, or 4 for the ending)
+pub fn act_for_floor(floor: i32) -> i32 {
+    ACT_END_FLOORS
+        .iter()
+        .position(|end| floor <= *end)
+        .map_or(4, |i| i as i32 + 1)
 }
 
 /// Metrics extracted from a single run
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RunMetrics {
     pub play_id: String,
-    pub character: String,
+    pub character: CharacterId,
     pub floor_reached: i32,
     pub victory: bool,
     pub score: i32,
+    /// Score divided by floors reached, see [`score_per_floor`]
+    #[serde(default)]
+    pub score_per_floor: f64,
     pub ascension_level: i32,
 
     // Deck composition
@@ -83,25 +484,221 @@ pub struct RunMetrics {
     pub power_count: i32,
     pub upgraded_cards: i32,
     pub cards_removed: i32,
+    /// Cards removed, in the order they were removed
+    #[serde(default)]
+    pub cards_purged: Vec<CardRemoval>,
+    /// Cards in the final deck that weren't part of the starter deck
+    #[serde(default)]
+    pub cards_added: i32,
+    /// Starter cards (upgraded or not) still in the final deck
+    #[serde(default)]
+    pub starters_remaining: i32,
 
     // Progression
+    /// Neow bonus picked at the start of the run (e.g. `BOSS_RELIC`)
+    #[serde(default)]
+    pub neow_bonus: Option<String>,
     pub relic_count: i32,
     pub relics: Vec<String>,
+    /// Each relic in `relics` with the floor it was obtained on
+    #[serde(default)]
+    pub relic_acquisitions: Vec<RelicAcquisition>,
+    /// First relic picked up, not counting the starter relic and Neow
+    /// rewards (None if the run found none or the file has no
+    /// `relics_obtained`)
+    #[serde(default)]
+    pub first_relic: Option<RelicAcquisition>,
+    /// Relics in `relics_obtained` per source (`elite`, `chest`, `shop`,
+    /// `boss`, `event` or `uncertain`)
+    #[serde(default)]
+    pub relic_sources: HashMap<String, i32>,
+    /// Relics held at the end of Act 1 (None if the run died before)
+    #[serde(default)]
+    pub relics_after_act1: Option<i32>,
+    /// Relics held at the end of Act 2 (None if the run died before)
+    #[serde(default)]
+    pub relics_after_act2: Option<i32>,
+    /// Relics held at the end of Act 3 (None if the run died before)
+    #[serde(default)]
+    pub relics_after_act3: Option<i32>,
+    /// Boss relic chests opened, in act order (empty if the file has no
+    /// boss relic data)
+    #[serde(default)]
+    pub boss_relics: Vec<BossRelicChoice>,
+    /// Boss relic chests left without taking a relic (None if the file has
+    /// no boss relic data)
+    #[serde(default)]
+    pub boss_relics_skipped: Option<i32>,
     pub master_deck: Vec<String>,
     pub elites_killed: i32,
+    /// Elites fought and (heuristic) elite opportunities per act, from
+    /// `path_taken`; empty if the file has none
+    #[serde(default)]
+    pub elites_by_act: Vec<ActElites>,
     pub bosses_killed: i32,
     pub campfires_rested: i32,
     pub campfires_upgraded: i32,
     pub shops_visited: i32,
+    /// Shops left without buying anything or removing a card
+    #[serde(default)]
+    pub shops_skipped: i32,
+    /// Shops visited and skipped per act, from `path_per_floor`; empty if
+    /// the run visited none or the file has no purchase floors
+    #[serde(default)]
+    pub shops_by_act: Vec<ActShops>,
+    /// Items bought at shops (cards, relics and potions)
     pub cards_purchased: i32,
+    /// Cards bought at shops
+    #[serde(default)]
+    pub cards_from_shops: i32,
+    /// Relics bought at shops
+    #[serde(default)]
+    pub relics_from_shops: i32,
+    /// Potions bought at shops
+    #[serde(default)]
+    pub potions_from_shops: i32,
+    /// Card removals bought at shops
+    #[serde(default)]
+    pub shop_removals: i32,
+    /// Gold spent on shop removals, from the escalating removal price
+    #[serde(default)]
+    pub gold_spent_on_removals: i32,
+    /// Cards picked from card rewards
+    #[serde(default)]
+    pub cards_from_rewards: i32,
+    /// Card reward screens seen (None if the file has no reward data)
+    #[serde(default)]
+    pub rewards_offered: Option<i32>,
+    /// Card rewards skipped, including Singing Bowl picks
+    #[serde(default)]
+    pub rewards_skipped: Option<i32>,
+    /// Card rewards where the Singing Bowl bonus was taken
+    #[serde(default)]
+    pub singing_bowl_picks: i32,
+    /// Card rewards offered and skipped per act
+    #[serde(default)]
+    pub rewards_by_act: Vec<ActRewards>,
     pub potions_used: i32,
+    /// Potions picked up during the run (None if the file has no potion data)
+    #[serde(default)]
+    pub potions_obtained_count: Option<i32>,
+    /// Potions obtained but never used
+    #[serde(default)]
+    pub potions_wasted: Option<i32>,
+    /// Potions picked up, in order (empty if the file has no potion data)
+    #[serde(default)]
+    pub potions_obtained: Vec<PotionPickup>,
+    /// Fairy in a Bottle probably kept the run alive (heuristic, see
+    /// [`potions::fairy_saved_run`])
+    #[serde(default)]
+    pub fairy_saved_run: bool,
 
     // Combat stats
     pub total_damage_taken: i32,
+    /// Damage taken in hallway fights
+    #[serde(default)]
+    pub damage_from_normals: i32,
+    /// Damage taken in elite fights
+    #[serde(default)]
+    pub damage_from_elites: i32,
+    /// Damage taken in boss fights
+    #[serde(default)]
+    pub damage_from_bosses: i32,
+    /// Damage taken in event fights and fights against unrecognized
+    /// (e.g. modded) enemies
+    #[serde(default)]
+    pub damage_from_other: i32,
+    /// Damage taken in fights per act, Act 1 to 4 (empty in exports written
+    /// before it was recorded)
+    #[serde(default)]
+    pub damage_by_act: Vec<i32>,
     pub max_hp_at_end: i32,
+    /// Most damage taken in a single fight the player survived
+    #[serde(default)]
+    pub max_damage_survived: i32,
+
+    /// Fights in the order they happened
+    #[serde(default)]
+    pub encounters: Vec<Encounter>,
+
+    // Unknown rooms
+    /// `?` rooms entered
+    #[serde(default)]
+    pub question_rooms: i32,
+    /// `?` rooms that turned into a fight
+    #[serde(default)]
+    pub question_rooms_fought: i32,
+    /// `?` rooms entered and fought per act
+    #[serde(default)]
+    pub question_rooms_by_act: Vec<ActQuestionRooms>,
 
     // Death info
     pub killed_by: Option<String>,
+
+    // Act 4 keys (None for files from before keys existed)
+    #[serde(default)]
+    pub has_ruby_key: Option<bool>,
+    #[serde(default)]
+    pub has_emerald_key: Option<bool>,
+    #[serde(default)]
+    pub has_sapphire_key: Option<bool>,
+
+    // Timing
+    /// When the run ended (unix seconds), if the file records it
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+    /// When the run ended on the player's clock, if the file records it
+    #[serde(default)]
+    pub local_time: Option<NaiveDateTime>,
+    /// Time spent in the run, in seconds
+    #[serde(default)]
+    pub playtime: i32,
+
+    // Run mode
+    /// Daily Climb run
+    #[serde(default)]
+    pub is_daily: bool,
+    /// Run played on a seed the player chose
+    #[serde(default)]
+    pub seeded: bool,
+    /// Endless mode run
+    #[serde(default)]
+    pub is_endless: bool,
+    /// Custom run with modifiers the player picked (`is_trial` in run files)
+    #[serde(default)]
+    pub is_trial: bool,
+    /// Mode the run was played in, classified from the flags above (see
+    /// [`Ruleset`])
+    #[serde(default)]
+    pub ruleset: Ruleset,
+    /// Whether the run holds content that isn't from the base game (see
+    /// [`modded`])
+    #[serde(default)]
+    pub is_modded: bool,
+    /// Character, cards and relics that made the run count as modded
+    #[serde(default)]
+    pub modded_content: Vec<String>,
+
+    // Source file (left out of run lists, see [`RunMetrics::without_source`])
+    /// Path of the run file, possibly redacted in exports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_path: Option<String>,
+    /// When the run file was last written (unix seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_modified_at: Option<i64>,
+
+    /// Whether the run is hidden from statistics
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+impl RunMetrics {
+    /// The run without its source file details, as returned in run lists
+    pub fn without_source(mut self) -> Self {
+        self.source_path = None;
+        self.file_modified_at = None;
+        self
+    }
 }
 
 /// Aggregated statistics for a character
@@ -110,81 +707,495 @@ pub struct CharacterStats {
     pub character: String,
     pub display_name: String,
     pub total_runs: i32,
+    /// All victories, `heart_kills + act3_wins`
     pub wins: i32,
+    /// Victories over the Corrupt Heart
+    #[serde(default)]
+    pub heart_kills: i32,
+    /// Victories ending at the Act 3 boss
+    #[serde(default)]
+    pub act3_wins: i32,
     pub win_rate: f64,
+    /// Low end of the win rate's [`wilson_interval`]
+    pub win_rate_ci_low: Option<f64>,
+    /// High end of the win rate's [`wilson_interval`]
+    pub win_rate_ci_high: Option<f64>,
     pub avg_score: f64,
+    /// Average score per floor; Endless runs are left out, as their scoring
+    /// keeps going past the Heart
+    #[serde(default)]
+    pub avg_score_per_floor: f64,
+    /// Average score per floor of winning runs, without Endless runs
+    #[serde(default)]
+    pub avg_score_per_floor_wins: f64,
+    /// Average score per floor of lost runs, without Endless runs
+    #[serde(default)]
+    pub avg_score_per_floor_losses: f64,
+    /// Average floor reached; victories count as configured by
+    /// [`FloorSemantics`]
     pub avg_floor: f64,
+    /// Highest floor reached, counted like `avg_floor`
     pub max_floor: i32,
     pub avg_deck_size: f64,
     pub avg_relics: f64,
+    /// Average elites killed per run
+    #[serde(default)]
+    pub avg_elites_killed: f64,
+    /// Average campfires rested at per run
+    #[serde(default)]
+    pub avg_campfires_rested: f64,
+    /// Average campfires upgraded at per run
+    #[serde(default)]
+    pub avg_campfires_upgraded: f64,
+    /// Average shops entered per run
+    #[serde(default)]
+    pub avg_shops_visited: f64,
+    /// The same averages for won and lost runs, when requested with
+    /// `split_by_outcome`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by_outcome: Option<OutcomeBehavior>,
+    /// Average damage taken per winning run, by kind of fight
+    #[serde(default)]
+    pub avg_damage_wins: EncounterDamage,
+    /// Average damage taken per lost run, by kind of fight
+    #[serde(default)]
+    pub avg_damage_losses: EncounterDamage,
+    /// Average cards added beyond the starter deck in winning runs
+    #[serde(default)]
+    pub avg_cards_added_wins: f64,
+    /// Average cards added beyond the starter deck in lost runs
+    #[serde(default)]
+    pub avg_cards_added_losses: f64,
+    /// Share of acquired cards bought at shops, in winning runs
+    #[serde(default)]
+    pub shop_card_ratio_wins: f64,
+    /// Share of acquired cards bought at shops, in lost runs
+    #[serde(default)]
+    pub shop_card_ratio_losses: f64,
+    /// Act 3 completions, heart attempts and heart kills
+    #[serde(default)]
+    pub keys: KeyStats,
+    /// Playtime over all runs, in seconds (runs without playtime count as 0)
+    #[serde(default)]
+    pub total_playtime_seconds: i64,
+    /// Average playtime of runs that recorded one, in seconds
+    #[serde(default)]
+    pub avg_playtime_seconds: f64,
+    /// Average playtime of winning runs that recorded one, in seconds
+    #[serde(default)]
+    pub avg_victory_playtime_seconds: f64,
+    /// Wins per ascension level, listing only levels with at least one win
+    #[serde(default)]
+    pub wins_by_ascension: BTreeMap<i32, i32>,
+    /// Highest ascension level won
+    #[serde(default)]
+    pub highest_ascension_win: Option<i32>,
+    /// Average potions obtained but never used in winning runs (Sozu runs and
+    /// files without potion data are left out)
+    #[serde(default)]
+    pub avg_potions_wasted_wins: f64,
+    /// Average potions obtained but never used in lost runs
+    #[serde(default)]
+    pub avg_potions_wasted_losses: f64,
+    /// Average relics held at the end of each act, over runs that finished it
+    #[serde(default)]
+    pub relic_checkpoints: Vec<RelicCheckpoint>,
+    /// Share of runs that died in Act 1 (floor 17 or earlier), out of runs
+    /// that weren't abandoned
+    #[serde(default)]
+    pub act1_death_rate: f64,
+    /// Share of runs that died at the Act 1 boss (floor 16 or 17), out of
+    /// runs that weren't abandoned
+    #[serde(default)]
+    pub act1_boss_death_rate: f64,
+    /// Win rate with ascension bands weighted equally, when requested with
+    /// `normalize=ascension`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ascension_normalized: Option<AscensionNormalized>,
+}
+
+/// Average elites, campfire choices and shops per run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BehaviorAverages {
+    pub runs: i32,
+    pub avg_elites_killed: f64,
+    pub avg_campfires_rested: f64,
+    pub avg_campfires_upgraded: f64,
+    pub avg_shops_visited: f64,
+}
+
+impl BehaviorAverages {
+    pub fn from_runs<'a>(runs: impl IntoIterator<Item = &'a RunMetrics>) -> Self {
+        let runs: Vec<&RunMetrics> = runs.into_iter().collect();
+        let avg = |field: fn(&RunMetrics) -> i32| mean(runs.iter().map(|r| field(r)));
+        Self {
+            runs: runs.len() as i32,
+            avg_elites_killed: avg(|r| r.elites_killed),
+            avg_campfires_rested: avg(|r| r.campfires_rested),
+            avg_campfires_upgraded: avg(|r| r.campfires_upgraded),
+            avg_shops_visited: avg(|r| r.shops_visited),
+        }
+    }
+}
+
+/// Behavioral averages of a character's won and lost runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct OutcomeBehavior {
+    pub wins: BehaviorAverages,
+    pub losses: BehaviorAverages,
+}
+
+/// Act 1 death rate and Act 1 boss death rate, see
+/// [`CharacterStats::act1_death_rate`]
+fn act1_death_rates<'a>(runs: impl IntoIterator<Item = &'a RunMetrics>) -> (f64, f64) {
+    let (mut played, mut act1, mut boss) = (0, 0, 0);
+    for run in runs.into_iter().filter(|r| !filter::abandoned(r)) {
+        played += 1;
+        if !run.victory && run.floor_reached <= ACT_END_FLOORS[0] {
+            act1 += 1;
+            if run.floor_reached >= ACT1_BOSS_FLOOR {
+                boss += 1;
+            }
+        }
+    }
+    if played == 0 {
+        return (0.0, 0.0);
+    }
+    (act1 as f64 / played as f64, boss as f64 / played as f64)
+}
+
+/// Options controlling which runs feed into aggregated statistics
+///
+/// The HTTP stats endpoints build these from their query flags, and the
+/// desktop commands take them as is, so both count the same runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsOptions {
+    /// Include hidden runs in the aggregates
+    pub include_hidden: bool,
+    /// Characters and kinds of run to count
+    #[serde(flatten)]
+    pub selection: RunSelection,
+    /// When the counted runs ended
+    #[serde(flatten)]
+    pub window: TimeWindow,
+    /// Also give the behavioral averages of won and lost runs separately
+    pub split_by_outcome: bool,
+}
+
+impl StatsOptions {
+    /// Reject conflicting character filters and windows ending before they
+    /// start
+    pub fn validate(&self) -> Result<(), String> {
+        RunFilter::from(self).validate()
+    }
+}
+
+/// Field character stats can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsSortKey {
+    WinRate,
+    TotalRuns,
+    AvgFloor,
+    AvgScore,
+    AvgScorePerFloor,
+}
+
+impl StatsSortKey {
+    /// Accepted names, as used in query parameters
+    pub const NAMES: &[&str] = &[
+        "win_rate",
+        "total_runs",
+        "avg_floor",
+        "avg_score",
+        "avg_score_per_floor",
+    ];
+
+    /// Look up a key by its query parameter name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "win_rate" => Some(Self::WinRate),
+            "total_runs" => Some(Self::TotalRuns),
+            "avg_floor" => Some(Self::AvgFloor),
+            "avg_score" => Some(Self::AvgScore),
+            "avg_score_per_floor" => Some(Self::AvgScorePerFloor),
+            _ => None,
+        }
+    }
+
+    fn value(self, stats: &CharacterStats) -> f64 {
+        match self {
+            Self::WinRate => stats.win_rate,
+            Self::TotalRuns => f64::from(stats.total_runs),
+            Self::AvgFloor => stats.avg_floor,
+            Self::AvgScore => stats.avg_score,
+            Self::AvgScorePerFloor => stats.avg_score_per_floor,
+        }
+    }
+}
+
+/// Sort character stats by `key`
+///
+/// The sort is stable in both directions: characters that tie keep their
+/// current (character list) order.
+pub fn sort_character_stats(stats: &mut [CharacterStats], key: StatsSortKey, descending: bool) {
+    stats.sort_by(|a, b| {
+        let (a, b) = (key.value(a), key.value(b));
+        if descending {
+            b.total_cmp(&a)
+        } else {
+            a.total_cmp(&b)
+        }
+    });
+}
+
+/// Summary of the loaded dataset
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DatasetSummary {
+    /// Total number of runs found on disk
+    pub total_runs: i32,
+    /// Runs included in statistics
+    pub visible_runs: i32,
+    /// Runs hidden from statistics
+    pub hidden_runs: i32,
+    /// Victories among the visible runs, `heart_kills + act3_wins`
+    pub wins: i32,
+    /// Visible runs that killed the Corrupt Heart
+    #[serde(default)]
+    pub heart_kills: i32,
+    /// Visible runs won at the Act 3 boss
+    #[serde(default)]
+    pub act3_wins: i32,
+    /// Visible runs with content that isn't from the base game (see
+    /// [`modded`])
+    #[serde(default)]
+    pub modded_runs: i32,
+    /// The runs directory that was loaded
+    pub runs_path: Option<String>,
+    /// Whether the runs are synthetic demo data rather than real runs
+    #[serde(default)]
+    pub demo: bool,
+}
+
+/// A run file that could not be loaded
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LoadIssue {
+    /// Path of the run file
+    pub path: String,
+    /// Why the file was skipped
+    pub message: String,
+    /// Line of the JSON error, for files that failed to parse
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Column of the JSON error, for files that failed to parse
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+/// A run file that only loaded after being repaired
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RecoveredFile {
+    /// Path of the run file
+    pub path: String,
+    /// Repairs applied before the file parsed
+    pub recoveries: Vec<Recovery>,
+}
+
+/// A run file left out because another file has the same play_id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DuplicateRun {
+    pub play_id: String,
+    /// File the run was loaded from
+    pub kept_path: String,
+    /// File that was ignored
+    pub ignored_path: String,
+}
+
+/// Outcome of loading the runs directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct LoadReport {
+    /// The runs directory that was scanned (None if no directory was resolved)
+    pub runs_path: Option<String>,
+    /// Number of `.run` files found
+    pub files_scanned: i32,
+    /// Number of runs parsed successfully
+    pub runs_loaded: i32,
+    /// Number of files that failed to parse
+    pub parse_failures: i32,
+    /// Details for each file that failed to parse or link that was skipped
+    pub issues: Vec<LoadIssue>,
+    /// Run files found in subfolders of a character directory
+    #[serde(default)]
+    pub nested_files: i32,
+    /// Files that loaded after repairs (BOM, trailing bytes)
+    #[serde(default)]
+    pub recovered: Vec<RecoveredFile>,
+    /// Parsed files left out because a newer file has the same play_id
+    #[serde(default)]
+    pub duplicates: Vec<DuplicateRun>,
+    /// When the load finished (unix seconds)
+    pub loaded_at: i64,
 }
 
 /// Complete export data structure
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ExportData {
+    /// Version of the export layout, see [`EXPORT_SCHEMA_VERSION`]
+    pub schema_version: u32,
     pub runs: Vec<RunMetrics>,
     pub character_stats: Vec<CharacterStats>,
+    /// Results per character and ascension level
+    pub ascension_stats: Vec<AscensionStats>,
+    /// When the export was written (unix seconds), kept for older readers
     pub export_timestamp: i64,
+    /// When the export was written, as an RFC 3339 timestamp in UTC
+    pub export_datetime: DateTime<Utc>,
+    /// Offset of the exporting machine's local time from UTC, in minutes east
+    pub export_timezone_offset_minutes: i32,
+    /// Version of the app that wrote the export (None for older exports)
+    pub app_version: Option<String>,
+    /// Runs directory the export was read from, possibly redacted
+    pub runs_path: Option<String>,
+    /// Filter the runs (and so the stats) were selected with
+    #[serde(default)]
+    pub filters: ExportFilter,
+    /// Checksum over `runs`, checked on import (None for older exports)
+    #[serde(default)]
+    pub checksum: Option<ExportChecksum>,
+}
+
+/// Options for building an export
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Shorten the runs path and each run's `source_path` so they don't
+    /// reveal the user's home folder
+    pub redact_path: bool,
+    /// Which runs to export
+    pub filter: ExportFilter,
+    /// Which runs count towards the stats; runs they leave out aren't
+    /// exported either
+    pub stats: StatsOptions,
 }
 
+/// Layout of `local_time` in run files, e.g. `20190105213045`
+const LOCAL_TIME_FORMAT: &str = "%Y%m%d%H%M%S";
+
 /// Raw run file structure (partial, for parsing)
+///
+/// Every field is read through [`lenient`], so a value of the wrong type
+/// only loses that value.
 #[derive(Debug, Deserialize)]
 struct RawRunFile {
+    #[serde(deserialize_with = "lenient::value", default)]
     play_id: Option<String>,
-    #[serde(deserialize_with = "deserialize_number_option", default)]
+    #[serde(deserialize_with = "lenient::number", default)]
     floor_reached: Option<i32>,
+    #[serde(deserialize_with = "lenient::value", default)]
     victory: Option<bool>,
-    #[serde(deserialize_with = "deserialize_number_option", default)]
+    #[serde(deserialize_with = "lenient::number", default)]
     score: Option<i32>,
-    #[serde(deserialize_with = "deserialize_number_option", default)]
+    #[serde(deserialize_with = "lenient::number", default)]
     ascension_level: Option<i32>,
+    #[serde(deserialize_with = "lenient::list", default)]
     master_deck: Option<Vec<String>>,
+    #[serde(deserialize_with = "lenient::list", default)]
     relics: Option<Vec<String>>,
+    #[serde(deserialize_with = "lenient::list", default)]
+    relics_obtained: Option<Vec<RelicObtained>>,
+    #[serde(deserialize_with = "lenient::list", default)]
     campfire_choices: Option<Vec<CampfireChoice>>,
+    #[serde(deserialize_with = "lenient::floor_list", default)]
     path_per_floor: Option<Vec<Option<String>>>,
+    #[serde(deserialize_with = "lenient::floor_list", default)]
+    path_taken: Option<Vec<Option<String>>>,
+    #[serde(deserialize_with = "lenient::list", default)]
     items_purged: Option<Vec<String>>,
+    #[serde(deserialize_with = "lenient::numbers", default)]
+    items_purged_floors: Option<Vec<Option<i64>>>,
+    #[serde(deserialize_with = "lenient::list", default)]
     items_purchased: Option<Vec<String>>,
-    #[serde(default)]
+    #[serde(deserialize_with = "lenient::numbers", default)]
+    item_purchase_floors: Option<Vec<Option<i64>>>,
+    #[serde(deserialize_with = "lenient::list", default)]
+    card_choices: Option<Vec<CardChoice>>,
+    #[serde(deserialize_with = "lenient::list", default)]
+    boss_relics: Option<Vec<BossRelicChest>>,
+    #[serde(deserialize_with = "lenient::list", default)]
     potions_floor_usage: Option<Vec<serde_json::Value>>,
+    #[serde(deserialize_with = "lenient::list", default)]
+    potions_obtained: Option<Vec<serde_json::Value>>,
+    #[serde(deserialize_with = "lenient::list", default)]
     damage_taken: Option<Vec<DamageTaken>>,
-    #[serde(default)]
-    max_hp_per_floor: Option<Vec<serde_json::Value>>,
+    #[serde(deserialize_with = "lenient::numbers", default)]
+    max_hp_per_floor: Option<Vec<Option<i64>>>,
+    #[serde(deserialize_with = "lenient::numbers", default)]
+    current_hp_per_floor: Option<Vec<Option<i64>>>,
+    #[serde(deserialize_with = "lenient::value", default)]
     killed_by: Option<String>,
+    #[serde(deserialize_with = "lenient::value", default)]
+    neow_bonus: Option<String>,
+    #[serde(deserialize_with = "lenient::integer", default)]
+    timestamp: Option<i64>,
+    #[serde(deserialize_with = "lenient::value", default)]
+    local_time: Option<String>,
+    #[serde(deserialize_with = "lenient::value", default)]
+    build_version: Option<String>,
+    #[serde(deserialize_with = "lenient::value", default)]
+    green_key_taken_log: Option<serde_json::Value>,
+    #[serde(deserialize_with = "lenient::value", default)]
+    blue_key_relic_skipped_log: Option<serde_json::Value>,
+    #[serde(deserialize_with = "lenient::number", default)]
+    playtime: Option<i32>,
+    #[serde(deserialize_with = "lenient::value", default)]
+    is_daily: Option<bool>,
+    #[serde(deserialize_with = "lenient::value", default)]
+    chose_seed: Option<bool>,
+    #[serde(deserialize_with = "lenient::value", default)]
+    is_endless: Option<bool>,
+    #[serde(deserialize_with = "lenient::value", default)]
+    is_trial: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RelicObtained {
+    #[serde(deserialize_with = "lenient::value", default)]
+    key: Option<String>,
+    #[serde(deserialize_with = "lenient::number", default)]
+    floor: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CardChoice {
+    #[serde(deserialize_with = "lenient::value", default)]
+    picked: Option<String>,
+    #[serde(deserialize_with = "lenient::number", default)]
+    floor: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BossRelicChest {
+    #[serde(deserialize_with = "lenient::value", default)]
+    picked: Option<String>,
+    #[serde(deserialize_with = "lenient::list", default)]
+    not_picked: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
 struct CampfireChoice {
+    #[serde(deserialize_with = "lenient::value", default)]
     key: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct DamageTaken {
-    #[serde(deserialize_with = "deserialize_number_option", default)]
+    #[serde(deserialize_with = "lenient::number", default)]
     damage: Option<i32>,
-}
-
-/// Deserialize a number that could be either an integer or a float
-fn deserialize_number_option<'de, D>(deserializer: D) -> Result<Option<i32>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    use serde::de::Error;
-
-    let value: Option<serde_json::Value> = Option::deserialize(deserializer)?;
-
-    match value {
-        None => Ok(None),
-        Some(serde_json::Value::Number(n)) => {
-            if let Some(i) = n.as_i64() {
-                Ok(Some(i as i32))
-            } else if let Some(f) = n.as_f64() {
-                Ok(Some(f as i32))
-            } else {
-                Err(D::Error::custom("expected a number"))
-            }
-        }
-        Some(_) => Err(D::Error::custom("expected a number")),
-    }
+    #[serde(deserialize_with = "lenient::number", default)]
+    turns: Option<i32>,
+    #[serde(deserialize_with = "lenient::value", default)]
+    enemies: Option<String>,
+    #[serde(deserialize_with = "lenient::number", default)]
+    floor: Option<i32>,
 }
 
 /// Get the default STS runs directory (auto-detection only)
@@ -213,36 +1224,90 @@ fn get_default_runs_path() -> Option<PathBuf> {
     None
 }
 
-/// Get the STS runs directory, checking custom path first then falling back to auto-detection
-pub fn get_runs_path() -> Option<PathBuf> {
-    // First check for custom path
-    if let Some(custom) = get_custom_runs_path() {
-        if custom.exists() {
-            return Some(custom);
+/// How the runs directory was resolved
+///
+/// A custom path takes precedence. If it no longer exists, the auto-detected
+/// directory is used instead (when there is one) and the missing custom path
+/// is still reported so the UI can tell the user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum RunsPathResolution {
+    /// The custom path is set and exists
+    CustomValid {
+        /// Directory in use
+        path: String,
+    },
+    /// The custom path is set but doesn't exist
+    CustomMissing {
+        /// The configured directory that is missing
+        custom_path: String,
+        /// Auto-detected directory used instead, if any
+        fallback_path: Option<String>,
+    },
+    /// No custom path is set and the directory was auto-detected
+    AutoDetected {
+        /// Directory in use
+        path: String,
+    },
+    /// No custom path is set and auto-detection found nothing
+    Unresolved,
+}
+
+impl RunsPathResolution {
+    /// Resolve from a custom path and an auto-detected path
+    pub fn resolve(custom: Option<PathBuf>, auto_detected: Option<PathBuf>) -> Self {
+        let display = |p: PathBuf| p.to_string_lossy().to_string();
+        match (custom, auto_detected) {
+            (Some(custom), _) if custom.exists() => Self::CustomValid {
+                path: display(custom),
+            },
+            (Some(custom), fallback) => Self::CustomMissing {
+                custom_path: display(custom),
+                fallback_path: fallback.map(display),
+            },
+            (None, Some(auto)) => Self::AutoDetected {
+                path: display(auto),
+            },
+            (None, None) => Self::Unresolved,
         }
-        // Custom path set but doesn't exist - still return it so caller can report error
-        eprintln!("Custom runs path does not exist: {:?}", custom);
     }
 
-    // Fall back to auto-detection
-    get_default_runs_path()
+    /// The directory runs are loaded from, if any
+    pub fn active_path(&self) -> Option<PathBuf> {
+        match self {
+            Self::CustomValid { path } | Self::AutoDetected { path } => Some(PathBuf::from(path)),
+            Self::CustomMissing { fallback_path, .. } => fallback_path.as_ref().map(PathBuf::from),
+            Self::Unresolved => None,
+        }
+    }
+
+    /// Whether a custom path is configured (valid or not)
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Self::CustomValid { .. } | Self::CustomMissing { .. })
+    }
 }
 
-/// Get info about the current runs path configuration
-pub fn get_runs_path_info() -> (Option<PathBuf>, bool, Option<PathBuf>) {
-    let custom = get_custom_runs_path();
-    let auto_detected = get_default_runs_path();
-    let is_custom = custom.is_some();
-    let current = if let Some(ref c) = custom {
-        if c.exists() {
-            custom.clone()
-        } else {
-            None
-        }
-    } else {
-        auto_detected.clone()
-    };
-    (current, is_custom, auto_detected)
+/// Get the STS runs directory: the custom path if it exists, otherwise the
+/// auto-detected one (see [`RunsPathResolution`])
+pub fn get_runs_path() -> Option<PathBuf> {
+    let resolution = get_runs_path_info();
+    if let RunsPathResolution::CustomMissing { custom_path, .. } = &resolution {
+        tracing::warn!("Custom runs path does not exist: {}", custom_path);
+    }
+    resolution.active_path()
+}
+
+/// Resolve the runs directory of the current profile (see [`profiles`])
+pub fn get_runs_path_info() -> RunsPathResolution {
+    RunsPathResolution::resolve(
+        profiles::current_custom_runs_path(),
+        get_default_runs_path(),
+    )
+}
+
+/// The auto-detected runs directory, ignoring any custom path
+pub fn auto_detected_runs_path() -> Option<PathBuf> {
+    get_default_runs_path()
 }
 
 /// Keywords for categorizing attack cards
@@ -309,61 +1374,262 @@ const SKILL_KEYWORDS: &[&str] = &[
     "impervious",
 ];
 
+/// Count `(attacks, skills, powers)` in a deck by card name keywords
+///
+/// Attack keywords are checked first, so a card matching both lists counts
+/// once. Cards matching neither keyword list are counted as powers.
+fn card_type_counts(deck: &[String]) -> (i32, i32, i32) {
+    let (mut attacks, mut skills) = (0, 0);
+    for card in deck {
+        let lower = card.to_lowercase();
+        let matches = |keywords: &[&str]| keywords.iter().any(|k| lower.contains(k));
+        if matches(ATTACK_KEYWORDS) {
+            attacks += 1;
+        } else if matches(SKILL_KEYWORDS) {
+            skills += 1;
+        }
+    }
+    (attacks, skills, deck.len() as i32 - attacks - skills)
+}
+
+/// Largest damage taken in one fight, leaving out the fight that ended a loss
+fn max_damage_survived(damage_taken: &[DamageTaken], victory: bool) -> i32 {
+    let survived = if victory {
+        damage_taken
+    } else {
+        damage_taken.split_last().map_or(&[][..], |(_, rest)| rest)
+    };
+    survived.iter().filter_map(|d| d.damage).max().unwrap_or(0)
+}
+
+/// Damage taken in fights per act, Act 1 to 4
+fn damage_by_act(damage_taken: &[DamageTaken]) -> Vec<i32> {
+    let mut by_act = vec![0i32; ACT_END_FLOORS.len() + 1];
+    for fight in damage_taken {
+        if let (Some(floor), Some(damage)) = (fight.floor, fight.damage) {
+            let total = &mut by_act[act_for_floor(floor) as usize - 1];
+            *total = total.saturating_add(damage);
+        }
+    }
+    by_act
+}
+
+/// Why a run file could not be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ParseFailure {
+    pub message: String,
+    /// Line and column of a JSON error
+    pub position: Option<(usize, usize)>,
+}
+
+/// Read and deserialize a run file
+///
+/// The file is streamed through a buffered reader; only files that fail to
+/// parse that way are read into memory for [`recovery::decode_lenient`].
+fn read_run_json<T: serde::de::DeserializeOwned>(
+    path: &std::path::Path,
+) -> Result<(T, Vec<Recovery>), ParseFailure> {
+    let read_failure = |e: std::io::Error| ParseFailure {
+        message: format!("Failed to read: {}", e),
+        position: None,
+    };
+
+    let file = std::fs::File::open(path).map_err(read_failure)?;
+    if let Ok(value) = serde_json::from_reader(std::io::BufReader::new(file)) {
+        return Ok((value, Vec::new()));
+    }
+
+    let content = std::fs::read(path).map_err(read_failure)?;
+    recovery::decode_lenient(&content).map_err(|e| ParseFailure {
+        message: e.to_string(),
+        position: Some((e.line, e.column)),
+    })
+}
+
+/// Modification time of a file in unix seconds
+fn file_modified_at(path: &std::path::Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(DateTime::<Utc>::from(modified).timestamp())
+}
+
 /// Parse a single run file
-fn parse_run_file(path: &std::path::Path, character: &str) -> Option<RunMetrics> {
-    let content = std::fs::read_to_string(path).ok()?;
-    let raw: RawRunFile = serde_json::from_str(&content).ok()?;
+///
+/// Damaged files are repaired where possible; the repairs are returned with
+/// the run. Values of the wrong type are ignored (see [`lenient`]), so any
+/// file holding a JSON object parses, and no input panics.
+fn parse_run_file(
+    path: &std::path::Path,
+    character: &CharacterId,
+) -> Result<(RunMetrics, Vec<Recovery>), ParseFailure> {
+    let (parsed, tolerated) = lenient::track(|| parse_run_values(path, character));
+    let (run, mut recoveries) = parsed?;
+    recoveries.extend(tolerated.recoveries());
+    Ok((run, recoveries))
+}
+
+/// [`parse_run_file`] without the recoveries of [`lenient`] values
+fn parse_run_values(
+    path: &std::path::Path,
+    character: &CharacterId,
+) -> Result<(RunMetrics, Vec<Recovery>), ParseFailure> {
+    let (raw, mut recoveries) = read_run_json::<RawRunFile>(path)?;
+    if raw.play_id.is_none() {
+        recoveries.push(Recovery::PlayIdFromFileName);
+    }
 
     let master_deck = raw.master_deck.unwrap_or_default();
     let relics = raw.relics.unwrap_or_default();
     let campfire_choices = raw.campfire_choices.unwrap_or_default();
     let path_per_floor = raw.path_per_floor.unwrap_or_default();
     let damage_taken = raw.damage_taken.unwrap_or_default();
-
-    // Count card types
-    let attack_count = master_deck
+    let mut damage_split = enemies::DamageSplit::default();
+    for fight in &damage_taken {
+        damage_split.add(fight.enemies.as_deref(), fight.damage.unwrap_or(0));
+    }
+    let current_hp_per_floor = raw.current_hp_per_floor.unwrap_or_default();
+    let potions_used = raw.potions_floor_usage.map(|v| v.len()).unwrap_or(0) as i32;
+    let potions_obtained_count = raw.potions_obtained.as_ref().map(|v| v.len() as i32);
+    let potions_obtained: Vec<PotionPickup> = raw
+        .potions_obtained
+        .unwrap_or_default()
         .iter()
-        .filter(|c| {
-            let lower = c.to_lowercase();
-            ATTACK_KEYWORDS.iter().any(|k| lower.contains(k))
+        .filter_map(|p| {
+            Some(PotionPickup {
+                name: p.get("key")?.as_str()?.trim().to_string(),
+                floor: p
+                    .get("floor")
+                    .and_then(lenient::number_from)
+                    .and_then(|f| i32::try_from(f).ok()),
+            })
         })
-        .count() as i32;
-
-    let skill_count = master_deck
+        .collect();
+    let max_hp_per_floor = raw.max_hp_per_floor.unwrap_or_default();
+    let question_rooms = events::summarize_question_rooms(
+        &path_per_floor,
+        damage_taken.iter().filter_map(|d| d.floor),
+    );
+    let items_purchased = raw.items_purchased.unwrap_or_default();
+    let relics_obtained: Vec<(String, i32)> = raw
+        .relics_obtained
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|r| Some((r.key?, r.floor?)))
+        .collect();
+    let is_shop_floor = |floor: i64| {
+        usize::try_from(floor.saturating_sub(1))
+            .ok()
+            .and_then(|i| path_per_floor.get(i))
+            .is_some_and(|room| room.as_deref() == Some("$"))
+    };
+    let items_purged = raw.items_purged.unwrap_or_default();
+    let items_purged_floors = raw.items_purged_floors.unwrap_or_default();
+    let purge_floors: Vec<i64> = items_purged_floors.iter().flatten().copied().collect();
+    let cards_purged = items_purged
         .iter()
-        .filter(|c| {
-            let lower = c.to_lowercase();
-            SKILL_KEYWORDS.iter().any(|k| lower.contains(k))
+        .enumerate()
+        .map(|(i, card)| {
+            let floor = items_purged_floors.get(i).copied().flatten();
+            CardRemoval {
+                card: items::base_card_id(card.trim()).to_string(),
+                floor: floor.and_then(|f| i32::try_from(f).ok()),
+                shop: floor.is_some_and(is_shop_floor),
+            }
         })
-        .count() as i32;
+        .collect();
+    let shops_by_act = raw.item_purchase_floors.map(|floors| {
+        let purchase_floors: Vec<i64> = floors.into_iter().flatten().collect();
+        shops::summarize_shops(&path_per_floor, &purchase_floors, &purge_floors)
+    });
+    let shop_removals = purge_floors.iter().filter(|f| is_shop_floor(**f)).count() as i32;
+    let boss_relics = raw.boss_relics.map(|chests| {
+        boss_relics::summarize_chests(
+            chests.iter().map(|c| {
+                (
+                    c.picked.as_deref(),
+                    c.not_picked.clone().unwrap_or_default(),
+                )
+            }),
+            raw.neow_bonus.as_deref(),
+        )
+    });
+    let rewards = raw.card_choices.as_ref().map(|choices| {
+        rewards::summarize_choices(choices.iter().map(|c| (c.floor, c.picked.as_deref())))
+    });
 
-    let power_count = master_deck.len() as i32 - attack_count - skill_count;
+    let (attack_count, skill_count, power_count) = card_type_counts(&master_deck);
+    let modded_content = modded::modded_content(character, &master_deck, &relics);
+    let ascension_level = raw.ascension_level.unwrap_or(0);
+    let key_signals = keys::KeySignals {
+        recall: campfire_choices
+            .iter()
+            .any(|c| c.key.as_deref() == Some("RECALL")),
+        blue_key_log: raw.blue_key_relic_skipped_log.is_some(),
+        green_key_log: raw.green_key_taken_log.is_some(),
+    };
+    let (has_ruby_key, has_emerald_key, has_sapphire_key) =
+        key_signals.keys(raw.build_version.as_deref());
+    let floor_reached = raw.floor_reached.unwrap_or(0);
+    let relic_acquisitions = relics::relic_acquisitions(&relics, &relics_obtained);
+    let (cards_added, starters_remaining) =
+        deck::starter_breakdown(&master_deck, character.known(), ascension_level);
 
-    Some(RunMetrics {
+    let is_daily = raw.is_daily.unwrap_or(false);
+    let seeded = raw.chose_seed.unwrap_or(false);
+    let is_endless = raw.is_endless.unwrap_or(false);
+    let is_trial = raw.is_trial.unwrap_or(false);
+    let score = raw.score.unwrap_or(0);
+    let fairy_saved_run = potions::fairy_saved_run(
+        &potions_obtained,
+        &current_hp_per_floor,
+        &max_hp_per_floor,
+        floor_reached,
+    );
+    let run = RunMetrics {
         play_id: raw.play_id.unwrap_or_else(|| {
             path.file_stem()
                 .and_then(|s| s.to_str())
                 .unwrap_or("unknown")
                 .to_string()
         }),
-        character: character.to_string(),
-        floor_reached: raw.floor_reached.unwrap_or(0),
+        character: character.clone(),
+        floor_reached,
         victory: raw.victory.unwrap_or(false),
-        score: raw.score.unwrap_or(0),
-        ascension_level: raw.ascension_level.unwrap_or(0),
+        score,
+        score_per_floor: score_per_floor(score, floor_reached),
+        ascension_level,
         deck_size: master_deck.len() as i32,
         attack_count,
         skill_count,
         power_count,
         upgraded_cards: master_deck.iter().filter(|c| c.contains('+')).count() as i32,
-        cards_removed: raw.items_purged.map(|v| v.len()).unwrap_or(0) as i32,
+        cards_removed: items_purged.len() as i32,
+        cards_purged,
+        cards_added,
+        starters_remaining,
+        neow_bonus: raw.neow_bonus,
         relic_count: relics.len() as i32,
+        relics_after_act1: relics::relics_after_act(&relic_acquisitions, floor_reached, 1),
+        relics_after_act2: relics::relics_after_act(&relic_acquisitions, floor_reached, 2),
+        relics_after_act3: relics::relics_after_act(&relic_acquisitions, floor_reached, 3),
+        relic_sources: relics::relic_sources(
+            &relics_obtained,
+            boss_relics.as_deref().unwrap_or_default(),
+            &path_per_floor,
+            damage_taken.iter().filter_map(|d| d.floor),
+        ),
+        boss_relics_skipped: boss_relics
+            .as_ref()
+            .map(|chests| chests.iter().filter(|c| c.skipped).count() as i32),
+        boss_relics: boss_relics.unwrap_or_default(),
+        first_relic: relics::first_relic(&relics_obtained),
+        relic_acquisitions,
         relics,
         master_deck: master_deck.clone(),
         elites_killed: path_per_floor
             .iter()
             .filter(|p| p.as_deref() == Some("E"))
             .count() as i32,
+        elites_by_act: elites::summarize_elites(&raw.path_taken.unwrap_or_default()),
         bosses_killed: path_per_floor
             .iter()
             .filter(|p| p.as_deref() == Some("BOSS"))
@@ -380,89 +1646,428 @@ fn parse_run_file(path: &std::path::Path, character: &str) -> Option<RunMetrics>
             .iter()
             .filter(|p| p.as_deref() == Some("$"))
             .count() as i32,
-        cards_purchased: raw.items_purchased.map(|v| v.len()).unwrap_or(0) as i32,
-        potions_used: raw.potions_floor_usage.map(|v| v.len()).unwrap_or(0) as i32,
-        total_damage_taken: damage_taken.iter().filter_map(|d| d.damage).sum(),
-        max_hp_at_end: raw
-            .max_hp_per_floor
-            .and_then(|v| {
-                v.last()
-                    .and_then(|val| val.as_f64().or_else(|| val.as_i64().map(|i| i as f64)))
+        shops_skipped: shops_by_act
+            .iter()
+            .flatten()
+            .map(|act| act.shops_skipped)
+            .sum(),
+        shops_by_act: shops_by_act.unwrap_or_default(),
+        cards_purchased: items_purchased.len() as i32,
+        cards_from_shops: items::count_kind(&items_purchased, ItemKind::Card),
+        relics_from_shops: items::count_kind(&items_purchased, ItemKind::Relic),
+        potions_from_shops: items::count_kind(&items_purchased, ItemKind::Potion),
+        shop_removals,
+        gold_spent_on_removals: shops::removal_gold(shop_removals),
+        cards_from_rewards: rewards.as_ref().map_or(0, |r| r.picked),
+        rewards_offered: rewards.as_ref().map(|r| r.offered),
+        rewards_skipped: rewards.as_ref().map(|r| r.skipped),
+        singing_bowl_picks: rewards.as_ref().map_or(0, |r| r.singing_bowl),
+        rewards_by_act: rewards.map(|r| r.by_act).unwrap_or_default(),
+        potions_used,
+        potions_obtained_count,
+        potions_wasted: potions_obtained_count
+            .map(|obtained| potions::wasted(obtained, potions_used)),
+        potions_obtained,
+        fairy_saved_run,
+        total_damage_taken: damage_taken
+            .iter()
+            .filter_map(|d| d.damage)
+            .fold(0, i32::saturating_add),
+        damage_from_normals: damage_split.normals,
+        damage_from_elites: damage_split.elites,
+        damage_from_bosses: damage_split.bosses,
+        damage_from_other: damage_split.other,
+        damage_by_act: damage_by_act(&damage_taken),
+        max_damage_survived: max_damage_survived(&damage_taken, raw.victory.unwrap_or(false)),
+        encounters: damage_taken
+            .iter()
+            .filter_map(|d| {
+                let floor = d.floor?;
+                // `current_hp_per_floor[i]` is the HP after floor `i + 1`
+                let hp_after = usize::try_from(floor.saturating_sub(1))
+                    .ok()
+                    .and_then(|i| *current_hp_per_floor.get(i)?)
+                    .and_then(|hp| i32::try_from(hp).ok());
+                let elite = usize::try_from(floor.saturating_sub(1))
+                    .ok()
+                    .and_then(|i| path_per_floor.get(i))
+                    .is_some_and(|room| room.as_deref() == Some("E"));
+                Some(Encounter {
+                    enemies: d.enemies.clone()?,
+                    floor,
+                    hp_after,
+                    elite,
+                    turns: d.turns.filter(|turns| *turns > 0),
+                })
             })
-            .map(|f| f as i32)
+            .collect(),
+        question_rooms: question_rooms.rooms,
+        question_rooms_fought: question_rooms.fought,
+        question_rooms_by_act: question_rooms.by_act,
+        max_hp_at_end: max_hp_per_floor
+            .iter()
+            .rev()
+            .find_map(|hp| *hp)
+            .map(lenient::clamp_i32)
             .unwrap_or(72),
         killed_by: raw.killed_by,
-    })
+        has_ruby_key,
+        has_emerald_key,
+        has_sapphire_key,
+        timestamp: raw.timestamp,
+        local_time: raw
+            .local_time
+            .as_deref()
+            .and_then(|t| NaiveDateTime::parse_from_str(t, LOCAL_TIME_FORMAT).ok()),
+        playtime: raw.playtime.unwrap_or(0),
+        is_daily,
+        seeded,
+        is_endless,
+        is_trial,
+        ruleset: Ruleset::from_flags(is_daily, is_trial, is_endless, seeded),
+        is_modded: !modded_content.is_empty(),
+        modded_content,
+        source_path: Some(path.to_string_lossy().to_string()),
+        file_modified_at: file_modified_at(path),
+        hidden: false,
+    };
+    Ok((run, recoveries))
+}
+
+/// No runs directory is configured or could be auto-detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunsPathNotFound;
+
+impl std::fmt::Display for RunsPathNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "No Slay the Spire runs directory found; set one in the settings"
+        )
+    }
 }
 
+impl std::error::Error for RunsPathNotFound {}
+
 /// Load all runs from the STS directory
+///
+/// Fails when no runs directory is resolved, so callers can tell "not set
+/// up" apart from "no runs played yet" (an empty directory gives `Ok([])`).
+pub fn try_load_all_runs() -> Result<Vec<RunMetrics>, RunsPathNotFound> {
+    try_load_all_runs_with(scan_options())
+}
+
+/// Load all runs, searching the runs directory with explicit scan options
+///
+/// Options other than the configured [`scan_options`] (a one-off
+/// `recursive` override) load without a cache, so they don't throw away the
+/// shared one. In demo mode the synthetic dataset is returned instead (see
+/// [`demo`]).
+pub fn try_load_all_runs_with(options: ScanOptions) -> Result<Vec<RunMetrics>, RunsPathNotFound> {
+    if demo_mode() {
+        return Ok(mark_hidden(demo::demo_runs()));
+    }
+    load_runs_in(get_runs_path().as_deref(), options)
+}
+
+/// Load all runs from the STS directory, treating a missing directory as empty
 pub fn load_all_runs() -> Vec<RunMetrics> {
-    let Some(runs_path) = get_runs_path() else {
-        eprintln!("Could not find STS runs directory");
-        return Vec::new();
+    try_load_all_runs().unwrap_or_default()
+}
+
+/// Load runs through the shared cache for a resolved (or missing) runs directory
+fn load_runs_in(
+    runs_path: Option<&std::path::Path>,
+    options: ScanOptions,
+) -> Result<Vec<RunMetrics>, RunsPathNotFound> {
+    let Some(runs_path) = runs_path else {
+        tracing::warn!("Could not find STS runs directory");
+        record_load_report(LoadReport {
+            loaded_at: chrono::Utc::now().timestamp(),
+            ..Default::default()
+        });
+        return Err(RunsPathNotFound);
     };
 
-    let mut all_runs = Vec::new();
+    if options != scan_options() {
+        let mut cache = RunCache::with_options(runs_path, options);
+        cache.refresh();
+        return Ok(mark_hidden(cache.runs()));
+    }
+    Ok(with_run_cache(runs_path, options, |cache| {
+        mark_hidden(cache.runs())
+    }))
+}
+
+/// Whether loads for the current profile go through the shared cache
+fn uses_shared_cache() -> bool {
+    current_profile() == active_profile()
+}
 
-    for character in Character::all() {
-        let char_dir = runs_path.join(character.dir_name());
-        if !char_dir.exists() {
-            continue;
+/// Run a closure against the cache for a runs directory, without refreshing
+/// it
+///
+/// The active profile uses the shared cache, which is rebuilt when the runs
+/// directory or scan options change; other profiles get a side cache each.
+/// The closure is told which one it got.
+fn with_cache_entry<T>(
+    runs_path: &std::path::Path,
+    options: ScanOptions,
+    f: impl FnOnce(&mut RunCache, bool) -> T,
+) -> T {
+    if uses_shared_cache() {
+        let mut guard = RUN_CACHE.write();
+        if guard.as_ref().map(|c| (c.root(), c.options())) != Some((runs_path, options)) {
+            *guard = Some(RunCache::with_options(runs_path, options));
         }
+        return f(
+            guard.as_mut().expect("run cache was just initialized"),
+            true,
+        );
+    }
 
-        if let Ok(entries) = std::fs::read_dir(&char_dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().map(|e| e == "run").unwrap_or(false) {
-                    if let Some(metrics) = parse_run_file(&path, character.dir_name()) {
-                        all_runs.push(metrics);
-                    }
-                }
+    let mut caches = SIDE_CACHES.lock();
+    let cache = match caches
+        .iter()
+        .position(|c| c.root() == runs_path && c.options() == options)
+    {
+        Some(index) => caches.remove(index),
+        None => {
+            if caches.len() >= MAX_SIDE_CACHES {
+                caches.remove(0);
             }
+            RunCache::with_options(runs_path, options)
+        }
+    };
+    caches.push(cache);
+    f(
+        caches.last_mut().expect("side cache was just pushed"),
+        false,
+    )
+}
+
+/// Refresh the cache for a runs directory and run a closure against it
+///
+/// While the startup warm-up is loading the shared cache, the closure sees
+/// the runs parsed so far instead. Only loads through the shared cache are
+/// recorded as the last load report.
+fn with_run_cache<T>(
+    runs_path: &std::path::Path,
+    options: ScanOptions,
+    f: impl FnOnce(&RunCache) -> T,
+) -> T {
+    with_cache_entry(runs_path, options, |cache, shared| {
+        if !shared {
+            cache.refresh();
+        } else if !(warming_up() && cache.progress().is_some()) {
+            record_load_report(cache.refresh());
         }
+        f(cache)
+    })
+}
+
+/// Set the hidden flag on runs from the persisted hidden-run set
+fn mark_hidden(mut runs: Vec<RunMetrics>) -> Vec<RunMetrics> {
+    let hidden = hidden_runs();
+    for run in &mut runs {
+        run.hidden = hidden.contains(&run.play_id);
+    }
+    runs
+}
+
+/// Load all runs from a specific runs directory without touching the shared cache
+pub fn load_runs_from(runs_path: &std::path::Path) -> (Vec<RunMetrics>, LoadReport) {
+    let mut cache = RunCache::new(runs_path);
+    let report = cache.refresh();
+    (mark_hidden(cache.runs()), report)
+}
+
+/// Runs added, changed or removed since a cursor
+///
+/// Only files whose modification time or size changed are re-parsed.
+pub fn runs_delta(since: Option<&DeltaCursor>) -> Result<RunDelta, RunsPathNotFound> {
+    let runs_path = get_runs_path().ok_or(RunsPathNotFound)?;
+    let mut delta = with_run_cache(&runs_path, scan_options(), |cache| cache.delta(since));
+    delta.runs = mark_hidden(delta.runs);
+    Ok(delta)
+}
+
+/// [`runs_delta`] from what the cache already holds, without rescanning
+///
+/// For when a watcher or the auto-refresh poller keeps the cache current.
+/// The runs directory is only scanned if no refresh has finished yet.
+pub fn cached_runs_delta(since: Option<&DeltaCursor>) -> Result<RunDelta, RunsPathNotFound> {
+    let runs_path = get_runs_path().ok_or(RunsPathNotFound)?;
+    let delta = with_cache_entry(&runs_path, scan_options(), |cache, _| {
+        cache.has_scanned().then(|| cache.delta(since))
+    });
+    let Some(mut delta) = delta else {
+        return runs_delta(since);
+    };
+    delta.runs = mark_hidden(delta.runs);
+    Ok(delta)
+}
+
+/// Re-parse the file behind one run and update the shared cache
+///
+/// Meant for files edited or restored by hand: the file is read again even
+/// if its modification time is unchanged, and the rest of the runs directory
+/// is left alone. The runs directory is only scanned when the cache hasn't
+/// loaded it yet or doesn't know the play_id.
+pub fn reparse_run(play_id: &str) -> Result<RunMetrics, ReparseError> {
+    if demo_mode() {
+        return Err(ReparseError::NotFound);
     }
+    let runs_path = get_runs_path().ok_or(ReparseError::NoRunsPath)?;
+    let result = with_cache_entry(&runs_path, scan_options(), |cache, shared| {
+        let result = match cache.reparse(play_id) {
+            Err(ReparseError::NotFound) => {
+                cache.refresh();
+                cache.reparse(play_id)
+            }
+            result => result,
+        };
+        if shared {
+            record_load_report(cache.load_report());
+        }
+        result
+    });
+    let mut run = result?;
+    run.hidden = hidden_runs().contains(&run.play_id);
+    Ok(run)
+}
 
-    all_runs
+/// Find a single run by play_id, regardless of whether it is hidden
+pub fn find_run(play_id: &str) -> Result<Option<RunMetrics>, RunsPathNotFound> {
+    Ok(try_load_all_runs()?
+        .into_iter()
+        .find(|r| r.play_id == play_id))
 }
 
-/// Calculate aggregated stats for each character
+/// Calculate aggregated stats for each character, skipping hidden runs
 pub fn calculate_character_stats(runs: &[RunMetrics]) -> Vec<CharacterStats> {
-    let mut stats_map: HashMap<String, Vec<&RunMetrics>> = HashMap::new();
+    calculate_character_stats_with(runs, &StatsOptions::default())
+}
+
+/// Mean of a set of values, or 0 when there are none
+fn mean(values: impl Iterator<Item = i32>) -> f64 {
+    let (sum, count) = values.fold((0i64, 0usize), |(sum, n), v| (sum + i64::from(v), n + 1));
+    if count > 0 {
+        sum as f64 / count as f64
+    } else {
+        0.0
+    }
+}
+
+/// Score per floor reached, a pace measure that depends less on how far a
+/// run got than the raw score
+///
+/// Runs that ended before reaching a floor count as one floor.
+pub fn score_per_floor(score: i32, floor_reached: i32) -> f64 {
+    f64::from(score) / f64::from(floor_reached.max(1))
+}
+
+/// Average [`score_per_floor`] of runs, leaving out Endless runs
+fn avg_score_per_floor<'a>(runs: impl IntoIterator<Item = &'a RunMetrics>) -> f64 {
+    let (sum, count) = runs
+        .into_iter()
+        .filter(|r| r.ruleset != Ruleset::Endless)
+        .fold((0.0, 0usize), |(sum, n), r| {
+            (sum + score_per_floor(r.score, r.floor_reached), n + 1)
+        });
+    if count > 0 {
+        sum / count as f64
+    } else {
+        0.0
+    }
+}
+
+/// Calculate aggregated stats for each character with explicit options
+pub fn calculate_character_stats_with(
+    runs: &[RunMetrics],
+    options: &StatsOptions,
+) -> Vec<CharacterStats> {
+    let mut stats_map: HashMap<Character, Vec<&RunMetrics>> = HashMap::new();
 
-    for run in runs {
-        stats_map
-            .entry(run.character.clone())
-            .or_default()
-            .push(run);
+    for run in RunFilter::from(options).apply(runs) {
+        if let Some(character) = run.character.known() {
+            stats_map.entry(character).or_default().push(run);
+        }
     }
 
     let mut stats = Vec::new();
 
     for character in Character::all() {
         let char_name = character.dir_name();
-        if let Some(char_runs) = stats_map.get(char_name) {
+        if let Some(char_runs) = stats_map.get(character) {
             let total = char_runs.len() as i32;
             let wins = char_runs.iter().filter(|r| r.victory).count() as i32;
+            let heart_kills = char_runs.iter().filter(|r| keys::killed_heart(r)).count() as i32;
+            let mut wins_by_ascension: BTreeMap<i32, i32> = BTreeMap::new();
+            for run in char_runs.iter().filter(|r| r.victory) {
+                *wins_by_ascension.entry(run.ascension_level).or_default() += 1;
+            }
             let scores: Vec<i32> = char_runs.iter().map(|r| r.score).collect();
-            let floors: Vec<i32> = char_runs.iter().map(|r| r.floor_reached).collect();
+            let floors: Vec<i32> = char_runs.iter().map(|r| r.stats_floor()).collect();
             let deck_sizes: Vec<i32> = char_runs.iter().map(|r| r.deck_size).collect();
             let relics: Vec<i32> = char_runs.iter().map(|r| r.relic_count).collect();
+            let damage_split = |victory: bool| {
+                let runs: Vec<&RunMetrics> = char_runs
+                    .iter()
+                    .copied()
+                    .filter(|r| r.victory == victory)
+                    .collect();
+                EncounterDamage::average(&runs)
+            };
+            let cards_added = |victory: bool| {
+                mean(
+                    char_runs
+                        .iter()
+                        .filter(|r| r.victory == victory)
+                        .map(|r| r.cards_added),
+                )
+            };
+            // Runs with no recorded playtime report 0 and would drag the
+            // averages down
+            let avg_playtime = |victories_only: bool| {
+                mean(
+                    char_runs
+                        .iter()
+                        .filter(|r| r.playtime > 0 && (r.victory || !victories_only))
+                        .map(|r| r.playtime),
+                )
+            };
 
+            let (win_rate_ci_low, win_rate_ci_high) = wilson_interval(wins, total).unzip();
+            let behavior = BehaviorAverages::from_runs(char_runs.iter().copied());
+            let (act1_death_rate, act1_boss_death_rate) =
+                act1_death_rates(char_runs.iter().copied());
             stats.push(CharacterStats {
                 character: char_name.to_string(),
                 display_name: character.display_name().to_string(),
                 total_runs: total,
                 wins,
+                heart_kills,
+                act3_wins: wins - heart_kills,
                 win_rate: if total > 0 {
                     wins as f64 / total as f64
                 } else {
                     0.0
                 },
+                win_rate_ci_low,
+                win_rate_ci_high,
                 avg_score: if total > 0 {
                     scores.iter().sum::<i32>() as f64 / total as f64
                 } else {
                     0.0
                 },
+                avg_score_per_floor: avg_score_per_floor(char_runs.iter().copied()),
+                avg_score_per_floor_wins: avg_score_per_floor(
+                    char_runs.iter().copied().filter(|r| r.victory),
+                ),
+                avg_score_per_floor_losses: avg_score_per_floor(
+                    char_runs.iter().copied().filter(|r| !r.victory),
+                ),
                 avg_floor: if total > 0 {
                     floors.iter().sum::<i32>() as f64 / total as f64
                 } else {
@@ -479,6 +2084,47 @@ pub fn calculate_character_stats(runs: &[RunMetrics]) -> Vec<CharacterStats> {
                 } else {
                     0.0
                 },
+                avg_elites_killed: behavior.avg_elites_killed,
+                avg_campfires_rested: behavior.avg_campfires_rested,
+                avg_campfires_upgraded: behavior.avg_campfires_upgraded,
+                avg_shops_visited: behavior.avg_shops_visited,
+                by_outcome: options.split_by_outcome.then(|| OutcomeBehavior {
+                    wins: BehaviorAverages::from_runs(
+                        char_runs.iter().copied().filter(|r| r.victory),
+                    ),
+                    losses: BehaviorAverages::from_runs(
+                        char_runs.iter().copied().filter(|r| !r.victory),
+                    ),
+                }),
+                avg_cards_added_wins: cards_added(true),
+                avg_cards_added_losses: cards_added(false),
+                avg_damage_wins: damage_split(true),
+                avg_damage_losses: damage_split(false),
+                shop_card_ratio_wins: shops::shop_card_ratio(
+                    char_runs.iter().copied().filter(|r| r.victory),
+                ),
+                shop_card_ratio_losses: shops::shop_card_ratio(
+                    char_runs.iter().copied().filter(|r| !r.victory),
+                ),
+                keys: KeyStats::from_runs(char_runs.iter().copied()),
+                total_playtime_seconds: char_runs
+                    .iter()
+                    .map(|r| i64::from(r.playtime.max(0)))
+                    .sum(),
+                avg_playtime_seconds: avg_playtime(false),
+                avg_victory_playtime_seconds: avg_playtime(true),
+                avg_potions_wasted_wins: potions::avg_wasted(
+                    char_runs.iter().copied().filter(|r| r.victory),
+                ),
+                avg_potions_wasted_losses: potions::avg_wasted(
+                    char_runs.iter().copied().filter(|r| !r.victory),
+                ),
+                relic_checkpoints: relics::relic_checkpoints(char_runs.iter().copied()),
+                act1_death_rate,
+                act1_boss_death_rate,
+                ascension_normalized: None,
+                highest_ascension_win: wins_by_ascension.keys().next_back().copied(),
+                wins_by_ascension,
             });
         }
     }
@@ -486,16 +2132,77 @@ pub fn calculate_character_stats(runs: &[RunMetrics]) -> Vec<CharacterStats> {
     stats
 }
 
-/// Get complete export data
-pub fn get_export_data() -> ExportData {
-    let runs = load_all_runs();
-    let character_stats = calculate_character_stats(&runs);
+/// Summarize a set of loaded runs
+pub fn dataset_summary(runs: &[RunMetrics]) -> DatasetSummary {
+    let hidden_runs = runs.iter().filter(|r| r.hidden).count() as i32;
+    let wins = runs.iter().filter(|r| !r.hidden && r.victory).count() as i32;
+    let heart_kills = runs
+        .iter()
+        .filter(|r| !r.hidden && keys::killed_heart(r))
+        .count() as i32;
+
+    DatasetSummary {
+        total_runs: runs.len() as i32,
+        visible_runs: runs.len() as i32 - hidden_runs,
+        hidden_runs,
+        wins,
+        heart_kills,
+        act3_wins: wins - heart_kills,
+        modded_runs: runs.iter().filter(|r| !r.hidden && r.is_modded).count() as i32,
+        runs_path: get_runs_path().map(|p| p.to_string_lossy().to_string()),
+        demo: demo_mode(),
+    }
+}
+
+/// Get complete export data (hidden runs are left out)
+pub fn get_export_data() -> Result<ExportData, RunsPathNotFound> {
+    get_export_data_with(&ExportOptions::default())
+}
+
+/// Get all data for export with explicit options
+pub fn get_export_data_with(options: &ExportOptions) -> Result<ExportData, RunsPathNotFound> {
+    let mut runs = try_load_all_runs()?;
+    let (stats, filter) = (
+        RunFilter::from(&options.stats),
+        RunFilter::from(&options.filter),
+    );
+    runs.retain(|r| stats.matches(r) && filter.matches(r));
+    let character_stats = calculate_character_stats_with(&runs, &options.stats);
+    let ascension_stats = ascension_stats(&runs, &options.stats);
+    let now = chrono::Local::now();
+    let home = dirs::home_dir();
+    let runs_path = get_runs_path().map(|path| {
+        if options.redact_path {
+            export::redact_path(&path, home.as_deref())
+        } else {
+            path.display().to_string()
+        }
+    });
+    if options.redact_path {
+        for run in &mut runs {
+            run.source_path = run
+                .source_path
+                .as_deref()
+                .map(|path| export::redact_path(std::path::Path::new(path), home.as_deref()));
+        }
+    }
 
-    ExportData {
+    let checksum = serde_json::to_value(&runs)
+        .ok()
+        .map(|runs| ExportChecksum::of_runs(&runs));
+    Ok(ExportData {
+        schema_version: EXPORT_SCHEMA_VERSION,
         runs,
         character_stats,
-        export_timestamp: chrono::Utc::now().timestamp(),
-    }
+        ascension_stats,
+        export_timestamp: now.timestamp(),
+        export_datetime: now.with_timezone(&Utc),
+        export_timezone_offset_minutes: now.offset().local_minus_utc() / 60,
+        app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        runs_path,
+        filters: options.filter.clone(),
+        checksum,
+    })
 }
 
 #[cfg(test)]
@@ -510,13 +2217,679 @@ mod tests {
         assert_eq!(Character::Watcher.dir_name(), "WATCHER");
     }
 
+    #[test]
+    fn test_character_id_serializes_as_dir_name() {
+        let known: CharacterId = Character::TheSilent.into();
+        assert_eq!(serde_json::to_string(&known).unwrap(), "\"THE_SILENT\"");
+        let modded = CharacterId::Modded("HERMIT".to_string());
+        assert_eq!(serde_json::to_string(&modded).unwrap(), "\"HERMIT\"");
+
+        let parsed: CharacterId = serde_json::from_str("\"WATCHER\"").unwrap();
+        assert_eq!(parsed, Character::Watcher);
+        let parsed: CharacterId = serde_json::from_str("\"HERMIT\"").unwrap();
+        assert_eq!(parsed, modded);
+    }
+
+    #[test]
+    fn test_parse_flexible() {
+        let cases = [
+            ("IRONCLAD", Some(Character::Ironclad)),
+            ("ironclad", Some(Character::Ironclad)),
+            ("Ironclad", Some(Character::Ironclad)),
+            ("ic", Some(Character::Ironclad)),
+            ("IC", Some(Character::Ironclad)),
+            ("THE_SILENT", Some(Character::TheSilent)),
+            ("the_silent", Some(Character::TheSilent)),
+            ("The Silent", Some(Character::TheSilent)),
+            ("the-silent", Some(Character::TheSilent)),
+            ("  the   silent ", Some(Character::TheSilent)),
+            ("silent", Some(Character::TheSilent)),
+            ("Silent", Some(Character::TheSilent)),
+            ("DEFECT", Some(Character::Defect)),
+            ("defect", Some(Character::Defect)),
+            ("watcher", Some(Character::Watcher)),
+            ("Watcher", Some(Character::Watcher)),
+            ("", None),
+            ("the", None),
+            ("silentt", None),
+            ("HERMIT", None),
+            ("iron clad", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(Character::parse_flexible(input), expected, "{input:?}");
+            assert_eq!(input.parse::<Character>().ok(), expected, "{input:?}");
+        }
+        assert_eq!(
+            Character::try_from("hermit"),
+            Err(UnknownCharacter("hermit".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_other_profiles_leave_the_shared_cache_alone() {
+        let runs_dir = TestRunsDir::empty();
+        runs_dir.add(&RunFileBuilder::new("mine"));
+        let partner = RunsDirFixture::new();
+        partner.add(&RunFileBuilder::new("theirs"));
+        set_profile_runs_paths(BTreeMap::from([(
+            "partner".to_string(),
+            Some(partner.path().to_path_buf()),
+        )]));
+
+        let initial = runs_delta(None).unwrap();
+        let report = last_load_report();
+        let theirs = with_profile("partner".to_string(), async {
+            try_load_all_runs().unwrap()
+        })
+        .await;
+        let report_after = last_load_report();
+        let cursor = DeltaCursor::parse(&initial.cursor).unwrap();
+        let delta = runs_delta(Some(&cursor)).unwrap();
+        set_profile_runs_paths(BTreeMap::new());
+
+        assert_eq!(theirs.len(), 1);
+        assert_eq!(theirs[0].play_id, "theirs");
+        let runs_path = |report: Option<LoadReport>| report.and_then(|r| r.runs_path);
+        assert_eq!(runs_path(report_after), runs_path(report));
+        assert!(!delta.reset);
+        assert!(delta.runs.is_empty());
+    }
+
+    #[test]
+    fn test_cached_delta_does_not_rescan() {
+        let runs_dir = TestRunsDir::empty();
+        runs_dir.add(&RunFileBuilder::new("first"));
+        let initial = cached_runs_delta(None).unwrap();
+        assert_eq!(initial.runs.len(), 1);
+
+        runs_dir.add(&RunFileBuilder::new("second"));
+        let cursor = DeltaCursor::parse(&initial.cursor).unwrap();
+        assert!(cached_runs_delta(Some(&cursor)).unwrap().runs.is_empty());
+        let rescanned = runs_delta(Some(&cursor)).unwrap();
+        assert_eq!(rescanned.runs.len(), 1);
+        assert_eq!(rescanned.runs[0].play_id, "second");
+    }
+
+    #[test]
+    fn test_scan_overrides_leave_the_shared_cache_alone() {
+        let runs_dir = TestRunsDir::empty();
+        runs_dir.add(&RunFileBuilder::new("top"));
+        runs_dir.add(&RunFileBuilder::new("nested").file_name("archive/nested.run"));
+
+        let initial = runs_delta(None).unwrap();
+        let recursive = try_load_all_runs_with(ScanOptions {
+            recursive: true,
+            ..scan_options()
+        })
+        .unwrap();
+        let cursor = DeltaCursor::parse(&initial.cursor).unwrap();
+        let delta = runs_delta(Some(&cursor)).unwrap();
+
+        assert_eq!(initial.runs.len(), 1);
+        assert_eq!(recursive.len(), 2);
+        assert!(!delta.reset);
+        assert!(delta.runs.is_empty());
+    }
+
     #[test]
     fn test_load_runs() {
-        let runs = load_all_runs();
-        // Just verify we can load runs without panicking
-        // If runs exist, verify the stats can be calculated
+        let fixture = RunsDirFixture::new();
+        install_sample_runs(fixture.path()).unwrap();
+
+        let (mut runs, report) = fixture.load();
+        runs.sort_by_key(|r| r.timestamp);
+        assert_eq!(runs.len(), 4);
+        assert_eq!(report.parse_failures, 1);
+        assert!(report.issues[0].path.ends_with("corrupted.run"));
+
+        let stats = calculate_character_stats(&runs);
+        assert_eq!(stats.len(), 4);
+
+        // Old build: floats everywhere, no card rewards or keys recorded
+        let defect = &runs[0];
+        assert_eq!(defect.character, Character::Defect);
+        assert_eq!(defect.floor_reached, 27);
+        assert_eq!(defect.timestamp, Some(1_550_000_000));
+        assert_eq!(defect.total_damage_taken, 122);
+        assert_eq!(defect.rewards_offered, None);
+        assert_eq!(defect.has_ruby_key, None);
+
+        // Missing damage, campfire and relic arrays
+        let watcher = &runs[1];
+        assert_eq!(watcher.killed_by.as_deref(), Some("Gremlin Nob"));
+        assert_eq!(watcher.encounters, Vec::new());
+        assert_eq!(watcher.starters_remaining, 10);
+        assert_eq!(watcher.cards_added, 1);
+
+        let ironclad = &runs[2];
+        assert!(ironclad.victory);
+        assert_eq!(ironclad.bosses_killed, 3);
+        assert_eq!(ironclad.rewards_skipped, Some(1));
+
+        let silent = &runs[3];
+        assert!(keys::killed_heart(silent));
+        assert_eq!(keys::has_all_keys(silent), Some(true));
+    }
+
+    #[test]
+    fn test_runs_path_resolution() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().to_path_buf();
+        let missing = dir.path().join("renamed");
+
+        let resolution = RunsPathResolution::resolve(Some(existing.clone()), None);
+        assert!(matches!(resolution, RunsPathResolution::CustomValid { .. }));
+        assert_eq!(resolution.active_path(), Some(existing.clone()));
+
+        let resolution = RunsPathResolution::resolve(Some(missing.clone()), Some(existing.clone()));
+        assert_eq!(
+            resolution,
+            RunsPathResolution::CustomMissing {
+                custom_path: missing.to_string_lossy().to_string(),
+                fallback_path: Some(existing.to_string_lossy().to_string()),
+            }
+        );
+        assert_eq!(resolution.active_path(), Some(existing.clone()));
+        assert!(resolution.is_custom());
+
+        let resolution = RunsPathResolution::resolve(Some(missing), None);
+        assert_eq!(resolution.active_path(), None);
+
+        let resolution = RunsPathResolution::resolve(None, Some(existing));
+        assert!(matches!(
+            resolution,
+            RunsPathResolution::AutoDetected { .. }
+        ));
+        assert!(!resolution.is_custom());
+
+        assert_eq!(
+            RunsPathResolution::resolve(None, None),
+            RunsPathResolution::Unresolved
+        );
+        let json = serde_json::to_value(RunsPathResolution::Unresolved).unwrap();
+        assert_eq!(json["state"], "unresolved");
+    }
+
+    #[test]
+    fn test_missing_runs_path_is_an_error() {
+        let options = ScanOptions::default();
+        assert_eq!(load_runs_in(None, options).unwrap_err(), RunsPathNotFound);
+
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_runs_in(Some(dir.path()), options).unwrap().is_empty());
+    }
+
+    pub(crate) fn sample_run(play_id: &str, character: Character, victory: bool) -> RunMetrics {
+        RunMetrics {
+            play_id: play_id.to_string(),
+            character: character.into(),
+            floor_reached: if victory { 51 } else { 20 },
+            victory,
+            score: 100,
+            score_per_floor: score_per_floor(100, if victory { 51 } else { 20 }),
+            ascension_level: 0,
+            deck_size: 20,
+            attack_count: 5,
+            skill_count: 5,
+            power_count: 10,
+            upgraded_cards: 0,
+            cards_removed: 0,
+            cards_purged: Vec::new(),
+            cards_added: 0,
+            starters_remaining: 0,
+            neow_bonus: None,
+            relic_count: 1,
+            relics: vec!["Burning Blood".to_string()],
+            relic_acquisitions: Vec::new(),
+            first_relic: None,
+            relic_sources: HashMap::new(),
+            relics_after_act1: None,
+            relics_after_act2: None,
+            relics_after_act3: None,
+            boss_relics: Vec::new(),
+            boss_relics_skipped: None,
+            master_deck: Vec::new(),
+            elites_killed: 0,
+            elites_by_act: Vec::new(),
+            bosses_killed: 0,
+            campfires_rested: 0,
+            campfires_upgraded: 0,
+            shops_visited: 0,
+            shops_skipped: 0,
+            shops_by_act: Vec::new(),
+            cards_purchased: 0,
+            cards_from_shops: 0,
+            relics_from_shops: 0,
+            potions_from_shops: 0,
+            shop_removals: 0,
+            gold_spent_on_removals: 0,
+            cards_from_rewards: 0,
+            rewards_offered: None,
+            rewards_skipped: None,
+            singing_bowl_picks: 0,
+            rewards_by_act: Vec::new(),
+            potions_used: 0,
+            potions_obtained_count: None,
+            potions_obtained: Vec::new(),
+            fairy_saved_run: false,
+            potions_wasted: None,
+            total_damage_taken: 0,
+            damage_from_normals: 0,
+            damage_from_elites: 0,
+            damage_from_bosses: 0,
+            damage_from_other: 0,
+            damage_by_act: Vec::new(),
+            max_hp_at_end: 80,
+            max_damage_survived: 0,
+            encounters: Vec::new(),
+            question_rooms: 0,
+            question_rooms_fought: 0,
+            question_rooms_by_act: Vec::new(),
+            killed_by: None,
+            has_ruby_key: None,
+            has_emerald_key: None,
+            has_sapphire_key: None,
+            timestamp: None,
+            local_time: None,
+            playtime: 0,
+            is_daily: false,
+            seeded: false,
+            is_endless: false,
+            is_trial: false,
+            ruleset: Ruleset::Standard,
+            is_modded: false,
+            modded_content: Vec::new(),
+            source_path: None,
+            file_modified_at: None,
+            hidden: false,
+        }
+    }
+
+    #[test]
+    fn test_stats_skip_hidden_runs() {
+        let mut hidden = sample_run("b", Character::Ironclad, true);
+        hidden.hidden = true;
+        hidden.is_modded = true;
+        let runs = vec![sample_run("a", Character::Ironclad, false), hidden];
+
         let stats = calculate_character_stats(&runs);
-        assert!(stats.len() <= 4); // At most 4 characters
+        assert_eq!(stats[0].total_runs, 1);
+        assert_eq!(stats[0].wins, 0);
+
+        let options = StatsOptions {
+            include_hidden: true,
+            ..Default::default()
+        };
+        let stats = calculate_character_stats_with(&runs, &options);
+        assert_eq!(stats[0].total_runs, 2);
+        assert_eq!(stats[0].wins, 1);
+
+        let options: StatsOptions = serde_json::from_value(serde_json::json!({
+            "include_hidden": true,
+            "exclude": ["abandoned"]
+        }))
+        .unwrap();
+        let stats = calculate_character_stats_with(&runs, &options);
+        assert_eq!(stats[0].total_runs, 1);
+        assert_eq!(stats[0].wins, 1);
+
+        let summary = dataset_summary(&runs);
+        assert_eq!(summary.total_runs, 2);
+        assert_eq!(summary.hidden_runs, 1);
+        assert_eq!(summary.visible_runs, 1);
+        assert_eq!(summary.wins, 0);
+        assert_eq!(summary.modded_runs, 0);
+    }
+
+    #[test]
+    fn test_playtime_stats_skip_missing_playtime() {
+        let mut win = sample_run("a", Character::TheSilent, true);
+        win.playtime = 3_000;
+        let mut loss = sample_run("b", Character::TheSilent, false);
+        loss.playtime = 1_000;
+        let unrecorded = sample_run("c", Character::TheSilent, true);
+        let runs = vec![win, loss, unrecorded];
+
+        let stats = &calculate_character_stats(&runs)[0];
+        assert_eq!(stats.total_runs, 3);
+        assert_eq!(stats.total_playtime_seconds, 4_000);
+        assert_eq!(stats.avg_playtime_seconds, 2_000.0);
+        assert_eq!(stats.avg_victory_playtime_seconds, 3_000.0);
+    }
+
+    #[test]
+    fn test_wins_by_ascension() {
+        let run_at = |id: &str, character: Character, ascension: i32, victory: bool| {
+            let mut run = sample_run(id, character, victory);
+            run.ascension_level = ascension;
+            run
+        };
+        let runs = vec![
+            run_at("a", Character::Defect, 3, true),
+            run_at("b", Character::Defect, 17, true),
+            run_at("c", Character::Defect, 3, true),
+            run_at("d", Character::Defect, 20, false),
+            run_at("e", Character::Watcher, 20, false),
+        ];
+
+        let stats = calculate_character_stats(&runs);
+        let defect = &stats[0];
+        assert_eq!(defect.wins_by_ascension, BTreeMap::from([(3, 2), (17, 1)]));
+        assert_eq!(defect.highest_ascension_win, Some(17));
+
+        let watcher = &stats[1];
+        assert!(watcher.wins_by_ascension.is_empty());
+        assert_eq!(watcher.highest_ascension_win, None);
+
+        let json = serde_json::to_value(defect).unwrap();
+        assert_eq!(json["wins_by_ascension"]["17"], 1);
+    }
+
+    #[test]
+    fn test_act1_death_rates_at_boundary_floors() {
+        let died_on = |id: &str, floor: i32| {
+            let mut run = sample_run(id, Character::Ironclad, false);
+            run.floor_reached = floor;
+            run.killed_by = Some("Hexaghost".to_string());
+            run
+        };
+        let mut abandoned = died_on("abandoned", 5);
+        abandoned.killed_by = None;
+        let runs = vec![
+            died_on("15", 15),
+            died_on("16", 16),
+            died_on("17", 17),
+            died_on("18", 18),
+            sample_run("win", Character::Ironclad, true),
+            abandoned,
+        ];
+
+        // Floors 15-17 are in Act 1 and 16-17 at its boss; the abandoned
+        // run counts in neither rate
+        let stats = &calculate_character_stats(&runs)[0];
+        assert_eq!(stats.act1_death_rate, 3.0 / 5.0);
+        assert_eq!(stats.act1_boss_death_rate, 2.0 / 5.0);
+
+        let only_abandoned = calculate_character_stats(&runs[5..]);
+        assert_eq!(only_abandoned[0].act1_death_rate, 0.0);
+        assert_eq!(only_abandoned[0].act1_boss_death_rate, 0.0);
+    }
+
+    #[test]
+    fn test_wins_split_into_heart_and_act3() {
+        let runs = generate_runs(11, 400);
+        let stats = calculate_character_stats(&runs);
+        assert!(stats.iter().any(|s| s.heart_kills > 0 && s.act3_wins > 0));
+        for s in &stats {
+            assert_eq!(s.wins, s.heart_kills + s.act3_wins, "{}", s.character);
+            assert_eq!(s.heart_kills, s.keys.heart_kills, "{}", s.character);
+        }
+
+        let summary = dataset_summary(&runs);
+        assert_eq!(summary.wins, summary.heart_kills + summary.act3_wins);
+        assert_eq!(
+            summary.heart_kills,
+            stats.iter().map(|s| s.heart_kills).sum::<i32>()
+        );
+    }
+
+    #[test]
+    fn test_sort_character_stats_keeps_ties_in_order() {
+        let runs = vec![
+            sample_run("a", Character::Ironclad, true),
+            sample_run("b", Character::TheSilent, false),
+            sample_run("c", Character::Defect, true),
+            sample_run("d", Character::Watcher, false),
+            sample_run("e", Character::Watcher, true),
+        ];
+        let names = |stats: &[CharacterStats]| -> Vec<String> {
+            stats.iter().map(|s| s.character.clone()).collect()
+        };
+
+        let mut stats = calculate_character_stats(&runs);
+        sort_character_stats(&mut stats, StatsSortKey::WinRate, true);
+        assert_eq!(
+            names(&stats),
+            ["IRONCLAD", "DEFECT", "WATCHER", "THE_SILENT"]
+        );
+        sort_character_stats(&mut stats, StatsSortKey::WinRate, false);
+        assert_eq!(
+            names(&stats),
+            ["THE_SILENT", "WATCHER", "IRONCLAD", "DEFECT"]
+        );
+
+        let mut stats = calculate_character_stats(&runs);
+        sort_character_stats(&mut stats, StatsSortKey::TotalRuns, true);
+        assert_eq!(
+            names(&stats),
+            ["WATCHER", "IRONCLAD", "THE_SILENT", "DEFECT"]
+        );
+
+        assert_eq!(
+            StatsSortKey::from_name(" Avg_Score"),
+            Some(StatsSortKey::AvgScore)
+        );
+        assert_eq!(StatsSortKey::from_name("deck_size"), None);
+    }
+
+    #[test]
+    fn test_behavior_averages() {
+        let run = |id: &str, victory: bool, elites: i32, rested: i32, smithed: i32, shops: i32| {
+            RunMetrics {
+                elites_killed: elites,
+                campfires_rested: rested,
+                campfires_upgraded: smithed,
+                shops_visited: shops,
+                ..sample_run(id, Character::Defect, victory)
+            }
+        };
+        let runs = vec![
+            run("a", true, 6, 1, 5, 4),
+            run("b", true, 4, 3, 3, 2),
+            run("c", false, 2, 2, 0, 1),
+        ];
+
+        let stats = &calculate_character_stats(&runs)[0];
+        assert_eq!(stats.avg_elites_killed, 4.0);
+        assert_eq!(stats.avg_campfires_rested, 2.0);
+        assert!((stats.avg_campfires_upgraded - 8.0 / 3.0).abs() < 1e-9);
+        assert!((stats.avg_shops_visited - 7.0 / 3.0).abs() < 1e-9);
+        assert!(stats.by_outcome.is_none());
+
+        let options = StatsOptions {
+            split_by_outcome: true,
+            ..Default::default()
+        };
+        let split = calculate_character_stats_with(&runs, &options)[0]
+            .by_outcome
+            .clone()
+            .unwrap();
+        assert_eq!(
+            split.wins,
+            BehaviorAverages {
+                runs: 2,
+                avg_elites_killed: 5.0,
+                avg_campfires_rested: 2.0,
+                avg_campfires_upgraded: 4.0,
+                avg_shops_visited: 3.0,
+            }
+        );
+        assert_eq!(split.losses.runs, 1);
+        assert_eq!(split.losses.avg_elites_killed, 2.0);
+    }
+
+    #[test]
+    fn test_score_per_floor_averages() {
+        assert_eq!(score_per_floor(300, 0), 300.0);
+        assert_eq!(score_per_floor(500, 50), 10.0);
+
+        let run = |id: &str, victory: bool, score: i32, floor_reached: i32| RunMetrics {
+            score,
+            floor_reached,
+            score_per_floor: score_per_floor(score, floor_reached),
+            ..sample_run(id, Character::Ironclad, victory)
+        };
+        let endless = RunMetrics {
+            is_endless: true,
+            ruleset: Ruleset::Endless,
+            ..run("endless", true, 9000, 90)
+        };
+        let runs = vec![
+            run("a", true, 1000, 50),
+            run("b", false, 200, 20),
+            run("c", false, 400, 10),
+            endless,
+        ];
+        let stats = &calculate_character_stats(&runs)[0];
+        assert_eq!(stats.total_runs, 4);
+        assert!((stats.avg_score_per_floor - 70.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.avg_score_per_floor_wins, 20.0);
+        assert_eq!(stats.avg_score_per_floor_losses, 25.0);
+        assert_eq!(
+            StatsSortKey::from_name("avg_score_per_floor"),
+            Some(StatsSortKey::AvgScorePerFloor)
+        );
+    }
+
+    #[test]
+    fn test_filtered_export_stats() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let filter = ExportFilter {
+            since: Some(1_600_000_000),
+            ..Default::default()
+        };
+        let options = ExportOptions {
+            filter: filter.clone(),
+            ..Default::default()
+        };
+        let export = get_export_data_with(&options).unwrap();
+        assert_eq!(export.filters, filter);
+        assert_eq!(export.runs.len(), 3);
+        assert!(export.runs.iter().all(|r| r.character != Character::Defect));
+        let stats_runs: i32 = export.character_stats.iter().map(|s| s.total_runs).sum();
+        assert_eq!(stats_runs, 3);
+        assert!(export
+            .character_stats
+            .iter()
+            .all(|s| s.character != "DEFECT"));
+
+        let options = ExportOptions {
+            filter: ExportFilter {
+                min_ascension: Some(21),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let export = get_export_data_with(&options).unwrap();
+        assert!(export.runs.is_empty());
+        assert!(export.character_stats.is_empty());
+        let json = serde_json::to_value(&export).unwrap();
+        assert_eq!(json["filters"]["min_ascension"], 21);
+    }
+
+    #[test]
+    fn test_load_report_counts_parse_failures() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("good")
+                .set("floor_reached", 12.0)
+                .file_name("1.run"),
+        );
+        fixture.add_raw(Character::Ironclad, "2.run", "{ not json");
+        fixture.add_raw(Character::Ironclad, "notes.txt", "ignored");
+
+        let (runs, report) = fixture.load();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].floor_reached, 12);
+        assert_eq!(report.files_scanned, 2);
+        assert_eq!(report.runs_loaded, 1);
+        assert_eq!(report.parse_failures, 1);
+        assert!(report.issues[0].path.ends_with("2.run"));
+    }
+
+    #[test]
+    fn test_parse_shop_and_reward_sources() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("shops")
+                .character(Character::TheSilent)
+                .path(&["M", "$", "M", "?", "$"])
+                .relics(&["Ring of the Snake", "Kunai"])
+                .set(
+                    "items_purchased",
+                    vec!["Footwork", "Fire Potion", "Kunai", "Acrobatics+1"],
+                )
+                .set(
+                    "relics_obtained",
+                    serde_json::json!([{ "floor": 5, "key": "Kunai" }]),
+                )
+                .set("items_purged", vec!["Strike_G", "Defend_G", "Strike_G"])
+                .set("items_purged_floors", vec![2, 4, 5])
+                .set(
+                    "card_choices",
+                    serde_json::json!([
+                        { "picked": "Backflip", "floor": 1 },
+                        { "picked": "SKIP", "floor": 3 },
+                        { "picked": "Singing Bowl", "floor": 6 }
+                    ]),
+                ),
+        );
+
+        let (runs, _) = fixture.load();
+        let run = &runs[0];
+        assert_eq!(run.cards_purchased, 4);
+        assert_eq!(run.cards_from_shops, 2);
+        assert_eq!(run.relics_from_shops, 1);
+        assert_eq!(run.potions_from_shops, 1);
+        assert_eq!(run.cards_removed, 3);
+        // The Defend removed on floor 4 came from an event, so it was free
+        assert_eq!(run.shop_removals, 2);
+        assert_eq!(run.gold_spent_on_removals, 175);
+        assert_eq!(run.cards_from_rewards, 1);
+        let floors: Vec<Option<i32>> = run.relic_acquisitions.iter().map(|a| a.floor).collect();
+        assert_eq!(floors, vec![None, Some(5)]);
+    }
+
+    #[test]
+    fn test_parse_hp_after_fights() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("hp")
+                .character(Character::Defect)
+                .floor_reached(4)
+                .hp_per_floor(&[70, 64, 64, 51])
+                .fight(1, "Cultist", 5)
+                .fight(4, "Jaw Worm", 13)
+                .fight(5, "Looter", 2),
+        );
+
+        let (runs, _) = fixture.load();
+        let hp: Vec<Option<i32>> = runs[0].encounters.iter().map(|e| e.hp_after).collect();
+        assert_eq!(hp, vec![Some(70), Some(51), None]);
+    }
+
+    #[test]
+    fn test_act_for_floor() {
+        let cases = [
+            (1, 1),
+            (17, 1),
+            (18, 2),
+            (34, 2),
+            (35, 3),
+            (51, 3),
+            (52, 4),
+            (56, 4),
+        ];
+        for (floor, act) in cases {
+            assert_eq!(act_for_floor(floor), act, "floor {}", floor);
+            // A run that reached a floor finished every earlier act
+            for earlier in 1..act {
+                assert!(completed_act(floor, earlier), "floor {}", floor);
+            }
+        }
+        assert!(!completed_act(16, 1));
+        assert!(!completed_act(50, 3));
+        assert!(!completed_act(56, 4));
     }
 
     #[test]
@@ -0,0 +1,105 @@
+//! Runs played with content mods
+//!
+//! Run files don't record which mods were loaded, so a run counts as modded
+//! when it holds something the base game doesn't have: a card or relic
+//! missing from the [`items`](super::items) database, or a character folder
+//! that isn't one of the four base game characters. Deck entries are looked
+//! up without their upgrade suffix, so `Searing Blow+9` is still vanilla.
+
+use super::items::{is_base_game_card, is_base_game_relic};
+use super::CharacterId;
+
+/// Names in a run that aren't from the base game, in the order character,
+/// cards, relics, each listed once
+///
+/// Empty for a vanilla run.
+pub fn modded_content(character: &CharacterId, deck: &[String], relics: &[String]) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    if let CharacterId::Modded(name) = character {
+        found.push(name.clone());
+    }
+    let cards = deck.iter().filter(|c| !is_base_game_card(c));
+    let relics = relics.iter().filter(|r| !is_base_game_relic(r));
+    for name in cards.chain(relics) {
+        if !found.contains(name) {
+            found.push(name.clone());
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::{load_all_runs, Character, RunFileBuilder, TestRunsDir};
+
+    fn names(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_upgraded_vanilla_cards_are_not_modded() {
+        let deck = names(&["Strike_R", "Bash+1", "Searing Blow+9", "AscendersBane"]);
+        let relics = names(&["Burning Blood", "Vajra"]);
+        let found = modded_content(&Character::Ironclad.into(), &deck, &relics);
+        assert!(found.is_empty(), "{found:?}");
+    }
+
+    #[test]
+    fn test_unknown_cards_and_relics() {
+        let deck = names(&[
+            "Strike_R",
+            "Reap and Sow",
+            "Reap and Sow+1",
+            "Searing Blow+9",
+        ]);
+        let relics = names(&["Burning Blood", "Cursed Compass"]);
+        let found = modded_content(&Character::Ironclad.into(), &deck, &relics);
+        assert_eq!(found, ["Reap and Sow", "Reap and Sow+1", "Cursed Compass"]);
+    }
+
+    #[test]
+    fn test_bundled_runs_are_vanilla() {
+        let fixture = crate::sts::RunsDirFixture::new();
+        crate::sts::samples::install_sample_runs(fixture.path()).unwrap();
+        let (runs, _) = fixture.load();
+        assert!(!runs.is_empty());
+        for run in runs.iter().chain(&crate::sts::generate_runs(7, 50)) {
+            let character = run.character.clone();
+            let found = modded_content(&character, &run.master_deck, &run.relics);
+            assert!(found.is_empty(), "{}: {found:?}", run.play_id);
+        }
+    }
+
+    #[test]
+    fn test_runs_in_modded_character_folders() {
+        let runs_dir = TestRunsDir::empty();
+        runs_dir.add(&RunFileBuilder::new("vanilla"));
+        for (dir, play_id) in [
+            ("HERMIT", "hermit"),
+            ("DAILY", "daily"),
+            (".backup", "backup"),
+        ] {
+            let folder = runs_dir.path().join(dir);
+            std::fs::create_dir(&folder).unwrap();
+            let json = RunFileBuilder::new(play_id).to_json().to_string();
+            std::fs::write(folder.join(format!("{play_id}.run")), json).unwrap();
+        }
+
+        let mut runs = load_all_runs();
+        runs.sort_by(|a, b| a.play_id.cmp(&b.play_id));
+        let ids: Vec<&str> = runs.iter().map(|r| r.play_id.as_str()).collect();
+        assert_eq!(ids, ["hermit", "vanilla"]);
+        assert_eq!(runs[0].character, CharacterId::Modded("HERMIT".to_string()));
+        assert!(runs[0].is_modded);
+        assert_eq!(runs[0].modded_content, ["HERMIT"]);
+        assert!(!runs[1].is_modded);
+    }
+
+    #[test]
+    fn test_modded_character() {
+        let character = CharacterId::Modded("HERMIT".to_string());
+        let found = modded_content(&character, &names(&["Strike_R"]), &[]);
+        assert_eq!(found, ["HERMIT"]);
+    }
+}
@@ -0,0 +1,191 @@
+//! Neow bonus analysis
+//!
+//! The `BOSS_RELIC` Neow option trades the starter relic for a random boss
+//! relic, which lands in the first relic slot. Swap runs are grouped by that
+//! relic and compared against runs that took any other bonus.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use super::deck::starter_relic;
+use super::stats_math::wilson_interval;
+use super::RunMetrics;
+
+/// Neow bonus id for swapping the starter relic for a boss relic
+pub const BOSS_RELIC_BONUS: &str = "BOSS_RELIC";
+
+/// Runs and wins for a group of runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WinRecord {
+    /// Runs in the group
+    pub runs: i32,
+    /// Runs in the group that were won
+    pub wins: i32,
+    /// `wins / runs`, or 0 without runs
+    pub win_rate: f64,
+    /// Low end of the win rate's [`wilson_interval`]
+    pub win_rate_ci_low: Option<f64>,
+    /// High end of the win rate's [`wilson_interval`]
+    pub win_rate_ci_high: Option<f64>,
+}
+
+impl WinRecord {
+    pub(super) fn add(&mut self, run: &RunMetrics) {
+        self.runs += 1;
+        self.wins += i32::from(run.victory);
+        self.win_rate = self.wins as f64 / self.runs as f64;
+        (self.win_rate_ci_low, self.win_rate_ci_high) =
+            wilson_interval(self.wins, self.runs).unzip();
+    }
+}
+
+/// Record for one boss relic taken from Neow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BossSwapRelic {
+    /// Boss relic that replaced the starter relic
+    pub relic: String,
+    /// Record of the runs that received this relic
+    #[serde(flatten)]
+    pub record: WinRecord,
+}
+
+/// A swap run left out of the analysis
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct BossSwapExclusion {
+    /// Play ID of the excluded run
+    pub play_id: String,
+    /// Why the run was left out
+    pub reason: String,
+}
+
+/// Boss relic swap record versus the non-swap baseline
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BossSwapStats {
+    /// Runs that took the boss relic swap
+    pub swaps: WinRecord,
+    /// Runs that took any other Neow bonus (or none)
+    pub baseline: WinRecord,
+    /// Swap runs grouped by the boss relic received, most common first
+    pub relics: Vec<BossSwapRelic>,
+    /// Swap runs that were left out, e.g. because a mod kept the starter relic
+    pub excluded: Vec<BossSwapExclusion>,
+}
+
+/// Starter relic of the run's character, if the character is known
+fn run_starter_relic(run: &RunMetrics) -> Option<&'static str> {
+    run.character.known().map(starter_relic)
+}
+
+/// Compare boss relic swap runs against the rest, ignoring hidden runs
+pub fn boss_swap_stats(runs: &[RunMetrics]) -> BossSwapStats {
+    let mut stats = BossSwapStats::default();
+    let mut by_relic: BTreeMap<&str, WinRecord> = BTreeMap::new();
+
+    for run in runs.iter().filter(|r| !r.hidden) {
+        if run.neow_bonus.as_deref() != Some(BOSS_RELIC_BONUS) {
+            stats.baseline.add(run);
+            continue;
+        }
+
+        let kept_starter = run_starter_relic(run).filter(|s| run.relics.iter().any(|r| r == s));
+        let exclusion = if run.relics.is_empty() {
+            Some("No relics recorded".to_string())
+        } else {
+            kept_starter.map(|starter| {
+                format!(
+                    "Starter relic {} still present after the swap (modded run?)",
+                    starter
+                )
+            })
+        };
+        if let Some(reason) = exclusion {
+            tracing::debug!("Excluding {} from boss swap stats: {}", run.play_id, reason);
+            stats.excluded.push(BossSwapExclusion {
+                play_id: run.play_id.clone(),
+                reason,
+            });
+            continue;
+        }
+
+        stats.swaps.add(run);
+        by_relic.entry(&run.relics[0]).or_default().add(run);
+    }
+
+    stats.relics = by_relic
+        .into_iter()
+        .map(|(relic, record)| BossSwapRelic {
+            relic: relic.to_string(),
+            record,
+        })
+        .collect();
+    stats
+        .relics
+        .sort_by_key(|r| std::cmp::Reverse(r.record.runs));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{Character, RunsDirFixture};
+
+    fn load_fixtures() -> Vec<RunMetrics> {
+        let fixture = RunsDirFixture::new();
+        let fixtures: [(&str, &[u8]); 2] = [
+            (
+                "swap.run",
+                include_bytes!("../../tests/fixtures/runs/boss_swap.run"),
+            ),
+            (
+                "modded.run",
+                include_bytes!("../../tests/fixtures/runs/boss_swap_modded.run"),
+            ),
+        ];
+        for (name, bytes) in fixtures {
+            fixture.add_raw(Character::Ironclad, name, bytes);
+        }
+        fixture.load().0
+    }
+
+    #[test]
+    fn test_swap_fixtures() {
+        let mut runs = load_fixtures();
+        assert!(runs
+            .iter()
+            .all(|r| r.neow_bonus.as_deref() == Some("BOSS_RELIC")));
+        runs.push(sample_run("plain-win", Character::Ironclad, true));
+        runs.push(sample_run("plain-loss", Character::Ironclad, false));
+
+        let stats = boss_swap_stats(&runs);
+        assert_eq!((stats.swaps.runs, stats.swaps.wins), (1, 1));
+        assert_eq!((stats.baseline.runs, stats.baseline.wins), (2, 1));
+        assert_eq!(stats.relics.len(), 1);
+        assert_eq!(stats.relics[0].relic, "Black Star");
+        assert_eq!(stats.relics[0].record.win_rate, 1.0);
+
+        assert_eq!(stats.excluded.len(), 1);
+        assert_eq!(stats.excluded[0].play_id, "swap_modded");
+        assert!(stats.excluded[0].reason.contains("Burning Blood"));
+    }
+
+    #[test]
+    fn test_relics_sorted_by_count() {
+        let swap = |id: &str, relic: &str| {
+            let mut run = sample_run(id, Character::Watcher, false);
+            run.neow_bonus = Some(BOSS_RELIC_BONUS.to_string());
+            run.relics = vec![relic.to_string()];
+            run
+        };
+        let runs = vec![
+            swap("a", "Astrolabe"),
+            swap("b", "Snecko Eye"),
+            swap("c", "Snecko Eye"),
+        ];
+        let stats = boss_swap_stats(&runs);
+        let relics: Vec<&str> = stats.relics.iter().map(|r| r.relic.as_str()).collect();
+        assert_eq!(relics, vec!["Snecko Eye", "Astrolabe"]);
+        assert_eq!(stats.baseline, WinRecord::default());
+    }
+}
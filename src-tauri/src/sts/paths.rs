@@ -0,0 +1,330 @@
+//! User-entered path handling
+//!
+//! Paths pasted into the settings often come with surrounding quotes,
+//! trailing separators, mixed slashes or Windows verbatim (`\\?\`) and UNC
+//! prefixes. They are cleaned up and canonicalized before being validated so
+//! that error messages can name the path that was actually checked.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Longest path Windows APIs accept without the verbatim prefix
+const MAX_PATH: usize = 260;
+
+/// Most symlinks followed in a chain before giving up
+pub const MAX_LINK_DEPTH: usize = 16;
+
+/// Why a user-entered runs path was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathInputError {
+    /// Nothing left after trimming quotes and whitespace
+    Empty,
+    /// The normalized path doesn't exist
+    NotFound { input: String, checked: PathBuf },
+    /// The normalized path exists but is a file
+    NotADirectory { input: String, checked: PathBuf },
+}
+
+impl fmt::Display for PathInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "No path given"),
+            Self::NotFound { input, checked } => {
+                write!(f, "Path does not exist: {}", checked.display())?;
+                write_input_note(f, input, checked)
+            }
+            Self::NotADirectory { input, checked } => {
+                write!(f, "Path is not a directory: {}", checked.display())?;
+                write_input_note(f, input, checked)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PathInputError {}
+
+/// Mention the original input when normalization changed it
+fn write_input_note(f: &mut fmt::Formatter<'_>, input: &str, checked: &Path) -> fmt::Result {
+    if Path::new(input) != checked {
+        write!(f, " (normalized from {:?})", input)?;
+    }
+    Ok(())
+}
+
+/// Why a symlink could not be followed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// The link (or a link in its chain) points at nothing
+    Dangling { target: PathBuf },
+    /// The chain of links loops back on itself
+    Cycle,
+    /// The chain is longer than [`MAX_LINK_DEPTH`]
+    TooDeep,
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dangling { target } => write!(f, "Dangling link to {}", target.display()),
+            Self::Cycle => write!(f, "Symlink cycle"),
+            Self::TooDeep => write!(f, "More than {} chained symlinks", MAX_LINK_DEPTH),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+/// Follow a chain of symlinks to the final path
+///
+/// Returns the path itself if it isn't a link. Relative link targets are
+/// resolved against the link's directory.
+pub fn resolve_links(path: &Path) -> Result<PathBuf, LinkError> {
+    let mut current = path.to_path_buf();
+    let mut visited = vec![current.clone()];
+
+    for _ in 0..=MAX_LINK_DEPTH {
+        let Ok(metadata) = std::fs::symlink_metadata(&current) else {
+            return Err(LinkError::Dangling { target: current });
+        };
+        if !metadata.file_type().is_symlink() {
+            return Ok(current);
+        }
+
+        let target = std::fs::read_link(&current).map_err(|_| LinkError::Dangling {
+            target: current.clone(),
+        })?;
+        let next = match current.parent() {
+            Some(parent) if target.is_relative() => parent.join(target),
+            _ => target,
+        };
+        if visited.contains(&next) {
+            return Err(LinkError::Cycle);
+        }
+        visited.push(next.clone());
+        current = next;
+    }
+    Err(LinkError::TooDeep)
+}
+
+/// Strip whitespace and one pair of matching surrounding quotes
+fn trim_quotes(input: &str) -> &str {
+    let trimmed = input.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = trimmed
+            .strip_prefix(quote)
+            .and_then(|s| s.strip_suffix(quote))
+        {
+            return inner.trim();
+        }
+    }
+    trimmed
+}
+
+/// Expand a leading `~` to the home directory (Unix convention)
+fn expand_home(path: &str) -> PathBuf {
+    if cfg!(unix) {
+        if let Some(home) = dirs::home_dir() {
+            if path == "~" {
+                return home;
+            }
+            if let Some(rest) = path.strip_prefix("~/") {
+                return home.join(rest);
+            }
+        }
+    }
+    PathBuf::from(path)
+}
+
+/// Drop the verbatim prefix when the path is short enough not to need it
+///
+/// `\\?\C:\runs` becomes `C:\runs` and `\\?\UNC\server\share` becomes
+/// `\\server\share`; paths longer than `MAX_PATH` keep the verbatim form so
+/// they keep working.
+pub fn simplify_verbatim(path: &str) -> String {
+    let simplified = if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", rest)
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        let is_disk = rest.as_bytes().get(1) == Some(&b':');
+        if !is_disk {
+            return path.to_string();
+        }
+        rest.to_string()
+    } else {
+        return path.to_string();
+    };
+
+    if simplified.len() < MAX_PATH {
+        simplified
+    } else {
+        path.to_string()
+    }
+}
+
+/// Clean up a user-entered path without touching the filesystem
+///
+/// Trims whitespace and quotes, expands `~` on Unix and rebuilds the path from
+/// its components, which removes trailing and repeated separators (and turns
+/// forward slashes into backslashes on Windows).
+pub fn clean_path_input(input: &str) -> PathBuf {
+    let path = expand_home(trim_quotes(input));
+    path.components().collect()
+}
+
+/// Normalize a user-entered runs path and check that it is a directory
+///
+/// Canonicalizing resolves symlinks, so a link to the runs directory is stored
+/// as the directory it points at.
+pub fn normalize_runs_path(input: &str) -> Result<PathBuf, PathInputError> {
+    if trim_quotes(input).is_empty() {
+        return Err(PathInputError::Empty);
+    }
+
+    let cleaned = clean_path_input(input);
+    let canonical = match std::fs::canonicalize(&cleaned) {
+        Ok(canonical) => canonical,
+        Err(_) => {
+            return Err(PathInputError::NotFound {
+                input: input.to_string(),
+                checked: cleaned,
+            })
+        }
+    };
+    let canonical = PathBuf::from(simplify_verbatim(&canonical.to_string_lossy()));
+
+    if !canonical.is_dir() {
+        return Err(PathInputError::NotADirectory {
+            input: input.to_string(),
+            checked: canonical,
+        });
+    }
+    Ok(canonical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_quotes_and_whitespace() {
+        let cases = [
+            ("  /games/runs  ", "/games/runs"),
+            ("\"/games/runs\"", "/games/runs"),
+            ("'/games/runs'", "/games/runs"),
+            (" \" /games/runs \" ", "/games/runs"),
+            ("\"/games/runs", "\"/games/runs"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(trim_quotes(input), expected, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_simplify_verbatim() {
+        let long = format!(r"\\?\C:\{}", "a".repeat(MAX_PATH));
+        let cases = [
+            (r"\\?\C:\Games\runs", r"C:\Games\runs".to_string()),
+            (r"\\?\UNC\nas\share\runs", r"\\nas\share\runs".to_string()),
+            (r"\\?\Volume{abc}\runs", r"\\?\Volume{abc}\runs".to_string()),
+            (r"C:\Games\runs", r"C:\Games\runs".to_string()),
+            (long.as_str(), long.clone()),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(simplify_verbatim(input), expected, "input {:?}", input);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_clean_unix_paths() {
+        let home = dirs::home_dir().unwrap();
+        let cases = [
+            ("/games/runs/", PathBuf::from("/games/runs")),
+            ("\"/games//runs/\"", PathBuf::from("/games/runs")),
+            ("/games/./runs", PathBuf::from("/games/runs")),
+            ("/", PathBuf::from("/")),
+            ("~", home.clone()),
+            ("~/sts/runs/", home.join("sts/runs")),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(clean_path_input(input), expected, "input {:?}", input);
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_clean_windows_paths() {
+        let cases = [
+            (r#""C:\Users\me\runs\""#, r"C:\Users\me\runs"),
+            (r"C:/Users/me/runs/", r"C:\Users\me\runs"),
+            (r"C:\", r"C:\"),
+            (r"\\nas\share\runs\", r"\\nas\share\runs"),
+            (r"\\?\C:\Users\me\runs\", r"\\?\C:\Users\me\runs"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                clean_path_input(input),
+                PathBuf::from(expected),
+                "input {:?}",
+                input
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_links() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target.run");
+        std::fs::write(&target, "{}").unwrap();
+
+        // Plain paths and chains of relative links
+        assert_eq!(resolve_links(&target).unwrap(), target);
+        symlink("target.run", dir.path().join("one")).unwrap();
+        symlink("one", dir.path().join("two")).unwrap();
+        assert_eq!(
+            resolve_links(&dir.path().join("two")).unwrap(),
+            dir.path().join("target.run")
+        );
+
+        // Dangling links and cycles
+        symlink(dir.path().join("missing"), dir.path().join("dangling")).unwrap();
+        assert!(matches!(
+            resolve_links(&dir.path().join("dangling")),
+            Err(LinkError::Dangling { .. })
+        ));
+        symlink(dir.path().join("loop_b"), dir.path().join("loop_a")).unwrap();
+        symlink(dir.path().join("loop_a"), dir.path().join("loop_b")).unwrap();
+        assert_eq!(
+            resolve_links(&dir.path().join("loop_a")),
+            Err(LinkError::Cycle)
+        );
+    }
+
+    #[test]
+    fn test_normalize_runs_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let canonical = std::fs::canonicalize(dir.path()).unwrap();
+        let canonical = PathBuf::from(simplify_verbatim(&canonical.to_string_lossy()));
+
+        let quoted = format!("  \"{}\"  ", dir.path().display());
+        assert_eq!(normalize_runs_path(&quoted).unwrap(), canonical);
+
+        let file = dir.path().join("file.run");
+        std::fs::write(&file, "{}").unwrap();
+        assert!(matches!(
+            normalize_runs_path(&file.to_string_lossy()),
+            Err(PathInputError::NotADirectory { .. })
+        ));
+
+        let missing = format!("\"{}\"", dir.path().join("missing").display());
+        let error = normalize_runs_path(&missing).unwrap_err();
+        assert!(matches!(error, PathInputError::NotFound { .. }));
+        let message = error.to_string();
+        assert!(message.starts_with("Path does not exist"));
+        assert!(message.contains("normalized from"));
+
+        assert_eq!(normalize_runs_path(" \"\" "), Err(PathInputError::Empty));
+    }
+}
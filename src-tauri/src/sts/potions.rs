@@ -0,0 +1,417 @@
+//! Potion use
+//!
+//! `potions_obtained` lists potions picked up during the run and
+//! `potions_floor_usage` the floors potions were drunk on. Potions obtained
+//! but never used are counted as wasted. Files from before
+//! `potions_obtained` existed have no potion data, and Sozu runs can't obtain
+//! potions at all, so both are left out of the averages.
+//!
+//! `potions_floor_usage` doesn't say which potion was used, so uses can't be
+//! matched to potion names; per-potion statistics only count pickups.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use utoipa::ToSchema;
+
+use super::{mean, RunMetrics};
+
+/// Relic that stops the player from obtaining potions
+pub const SOZU: &str = "Sozu";
+
+/// Ids run files use for Fairy in a Bottle
+pub const FAIRY_POTION: &[&str] = &["FairyPotion", "Fairy in a Bottle"];
+
+/// Share of max HP Fairy in a Bottle revives the player with
+const FAIRY_HEAL_PERCENT: i64 = 30;
+
+/// A potion picked up during a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PotionPickup {
+    /// Potion id as written by the game (e.g. "Fire Potion", "FairyPotion")
+    pub name: String,
+    /// Floor it was obtained on, if the file records it
+    #[serde(default)]
+    pub floor: Option<i32>,
+}
+
+/// Whether a potion id is Fairy in a Bottle
+pub fn is_fairy_potion(name: &str) -> bool {
+    FAIRY_POTION.iter().any(|id| id.eq_ignore_ascii_case(name))
+}
+
+/// Whether Fairy in a Bottle probably saved the run from dying (heuristic)
+///
+/// The game doesn't record the Fairy reviving the player. This looks for a
+/// floor after a Fairy was obtained that the run ended at or below the HP the
+/// Fairy revives with (30% of max HP) and then carried on past. `hp` and
+/// `max_hp` hold the HP after each floor, starting at floor 1; floors
+/// outside them are never looked at, whatever the file claims.
+pub fn fairy_saved_run(
+    pickups: &[PotionPickup],
+    hp: &[Option<i64>],
+    max_hp: &[Option<i64>],
+    floor_reached: i32,
+) -> bool {
+    let Some(obtained) = pickups
+        .iter()
+        .filter(|p| is_fairy_potion(&p.name))
+        .filter_map(|p| p.floor)
+        .min()
+    else {
+        return false;
+    };
+    let first = (i64::from(obtained) + 1).max(1);
+    let last = (i64::from(floor_reached) - 1).min(hp.len() as i64);
+    (first..=last).any(|floor| {
+        let at = |values: &[Option<i64>]| *values.get(usize::try_from(floor - 1).ok()?)?;
+        at(hp).zip(at(max_hp)).is_some_and(|(hp, max_hp)| {
+            hp > 0 && hp.saturating_mul(100) <= max_hp.saturating_mul(FAIRY_HEAL_PERCENT)
+        })
+    })
+}
+
+/// Whether the run held Sozu
+pub fn has_sozu(run: &RunMetrics) -> bool {
+    run.relics.iter().any(|r| r == SOZU)
+}
+
+/// Potions obtained but not used, never below zero
+pub fn wasted(obtained: i32, used: i32) -> i32 {
+    (obtained - used).max(0)
+}
+
+/// Whether a run counts towards potion statistics
+fn has_potion_data(run: &RunMetrics) -> bool {
+    run.potions_wasted.is_some() && !has_sozu(run)
+}
+
+/// Average potions wasted over the runs with potion data
+pub fn avg_wasted<'a>(runs: impl IntoIterator<Item = &'a RunMetrics>) -> f64 {
+    mean(
+        runs.into_iter()
+            .filter(|r| has_potion_data(r))
+            .filter_map(|r| r.potions_wasted),
+    )
+}
+
+/// Potion use across runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PotionStats {
+    /// Runs with potion data, not counting Sozu runs
+    pub runs: i32,
+    /// Runs left out because the file has no potion data
+    pub runs_without_data: i32,
+    /// Runs left out because they held Sozu
+    pub sozu_runs: i32,
+    pub avg_obtained: f64,
+    pub avg_used: f64,
+    pub avg_wasted: f64,
+    pub avg_wasted_wins: f64,
+    pub avg_wasted_losses: f64,
+    /// Lost runs with potion data
+    pub losses: i32,
+    /// Lost runs that still had a potion they never used
+    pub losses_with_unused_potions: i32,
+    /// `losses_with_unused_potions / losses`, or 0 without losses
+    pub losses_with_unused_potions_rate: f64,
+}
+
+impl PotionStats {
+    /// Aggregate potion use, ignoring hidden runs
+    pub fn from_runs(runs: &[RunMetrics]) -> Self {
+        let mut stats = Self::default();
+        let mut counted: Vec<&RunMetrics> = Vec::new();
+        for run in runs.iter().filter(|r| !r.hidden) {
+            if run.potions_wasted.is_none() {
+                stats.runs_without_data += 1;
+            } else if has_sozu(run) {
+                stats.sozu_runs += 1;
+            } else {
+                counted.push(run);
+            }
+        }
+
+        stats.runs = counted.len() as i32;
+        stats.avg_obtained = mean(counted.iter().filter_map(|r| r.potions_obtained_count));
+        stats.avg_used = mean(counted.iter().map(|r| r.potions_used));
+        stats.avg_wasted = avg_wasted(counted.iter().copied());
+        stats.avg_wasted_wins = avg_wasted(counted.iter().copied().filter(|r| r.victory));
+        stats.avg_wasted_losses = avg_wasted(counted.iter().copied().filter(|r| !r.victory));
+
+        let losses: Vec<&RunMetrics> = counted.into_iter().filter(|r| !r.victory).collect();
+        stats.losses = losses.len() as i32;
+        stats.losses_with_unused_potions = losses
+            .iter()
+            .filter(|r| r.potions_wasted.is_some_and(|w| w > 0))
+            .count() as i32;
+        stats.losses_with_unused_potions_rate = if stats.losses > 0 {
+            stats.losses_with_unused_potions as f64 / stats.losses as f64
+        } else {
+            0.0
+        };
+        stats
+    }
+}
+
+/// Pickups of one potion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PotionNameCount {
+    /// Potion id as written by the game
+    pub name: String,
+    /// Times picked up across all runs
+    pub times_obtained: i32,
+    /// Runs that picked it up at least once
+    pub runs_obtained: i32,
+    /// Of those, runs that were won
+    pub wins: i32,
+    /// `wins / runs_obtained`
+    pub win_rate: f64,
+    /// For Fairy in a Bottle: runs it probably kept alive, see
+    /// [`fairy_saved_run`] (a heuristic)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deaths_prevented: Option<i32>,
+}
+
+/// Potions picked up across runs, by name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PotionNameStats {
+    /// Runs that record potion pickups by name
+    pub runs: i32,
+    /// Potions used in those runs; run files record the floor of each use
+    /// but not the potion, so uses aren't split by name
+    pub uses_unattributed: i32,
+    /// Every potion picked up, most obtained first
+    pub potions: Vec<PotionNameCount>,
+}
+
+impl PotionNameStats {
+    /// Count potion pickups by name, ignoring hidden runs
+    pub fn from_runs(runs: &[RunMetrics]) -> Self {
+        #[derive(Default)]
+        struct Tally {
+            obtained: i32,
+            runs: i32,
+            wins: i32,
+            saves: i32,
+        }
+
+        let mut stats = Self::default();
+        let mut tallies: BTreeMap<&str, Tally> = BTreeMap::new();
+        for run in runs.iter().filter(|r| !r.hidden) {
+            if run.potions_obtained.is_empty() {
+                continue;
+            }
+            stats.runs += 1;
+            stats.uses_unattributed += run.potions_used;
+            let mut seen = HashSet::new();
+            for pickup in &run.potions_obtained {
+                let tally = tallies.entry(&pickup.name).or_default();
+                tally.obtained += 1;
+                if seen.insert(pickup.name.as_str()) {
+                    tally.runs += 1;
+                    tally.wins += i32::from(run.victory);
+                    tally.saves += i32::from(run.fairy_saved_run);
+                }
+            }
+        }
+
+        stats.potions = tallies
+            .into_iter()
+            .map(|(name, tally)| PotionNameCount {
+                name: name.to_string(),
+                times_obtained: tally.obtained,
+                runs_obtained: tally.runs,
+                wins: tally.wins,
+                win_rate: tally.wins as f64 / tally.runs as f64,
+                deaths_prevented: is_fairy_potion(name).then_some(tally.saves),
+            })
+            .collect();
+        stats.potions.sort_by(|a, b| {
+            b.times_obtained
+                .cmp(&a.times_obtained)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{Character, RunFileBuilder, RunsDirFixture};
+
+    fn run(id: &str, victory: bool, obtained: Option<i32>, used: i32) -> RunMetrics {
+        let mut run = sample_run(id, Character::Defect, victory);
+        run.potions_obtained_count = obtained;
+        run.potions_used = used;
+        run.potions_wasted = obtained.map(|o| wasted(o, used));
+        run
+    }
+
+    #[test]
+    fn test_wasted_is_floored_at_zero() {
+        assert_eq!(wasted(5, 2), 3);
+        assert_eq!(wasted(1, 4), 0);
+    }
+
+    #[test]
+    fn test_parse_potion_counts() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("potions")
+                .character(Character::Defect)
+                .set(
+                    "potions_obtained",
+                    serde_json::json!([
+                        { "floor": 2, "key": "Fire Potion" },
+                        { "floor": 5, "key": "Block Potion" },
+                        { "floor": 9, "key": "Fairy in a Bottle" }
+                    ]),
+                )
+                .set("potions_floor_usage", vec![6]),
+        );
+        fixture.add(&RunFileBuilder::new("old").character(Character::Defect));
+
+        let runs = fixture.load_sorted();
+        let (old, potions) = (&runs[0], &runs[1]);
+        assert_eq!(potions.potions_obtained_count, Some(3));
+        assert_eq!(potions.potions_obtained[1].name, "Block Potion");
+        assert_eq!(potions.potions_obtained[1].floor, Some(5));
+        assert_eq!(potions.potions_used, 1);
+        assert_eq!(potions.potions_wasted, Some(2));
+        assert_eq!(old.potions_obtained_count, None);
+        assert_eq!(old.potions_wasted, None);
+    }
+
+    #[test]
+    fn test_potion_stats_exclude_sozu_and_missing_data() {
+        let mut sozu = run("sozu", false, Some(0), 0);
+        sozu.relics.push(SOZU.to_string());
+        let runs = vec![
+            run("a", true, Some(4), 4),
+            run("b", false, Some(5), 2),
+            run("c", false, Some(2), 3),
+            run("old", false, None, 1),
+            sozu,
+        ];
+
+        let stats = PotionStats::from_runs(&runs);
+        assert_eq!(stats.runs, 3);
+        assert_eq!(stats.runs_without_data, 1);
+        assert_eq!(stats.sozu_runs, 1);
+        assert_eq!(stats.avg_wasted_wins, 0.0);
+        assert_eq!(stats.avg_wasted_losses, 1.5);
+        assert_eq!(stats.losses, 2);
+        assert_eq!(stats.losses_with_unused_potions, 1);
+        assert_eq!(stats.losses_with_unused_potions_rate, 0.5);
+    }
+
+    fn pickup(name: &str, floor: i32) -> PotionPickup {
+        PotionPickup {
+            name: name.to_string(),
+            floor: Some(floor),
+        }
+    }
+
+    #[test]
+    fn test_fairy_save_heuristic() {
+        let fairy = [pickup("FairyPotion", 2)];
+        let max_hp = vec![Some(80); 6];
+        // Down to 20 HP (25% of max) on floor 4, then on to floor 6
+        let hp = vec![Some(70), Some(60), Some(50), Some(20), Some(40), Some(45)];
+        assert!(fairy_saved_run(&fairy, &hp, &max_hp, 6));
+        // The run ended on the low floor, so the Fairy didn't keep it going
+        assert!(!fairy_saved_run(&fairy, &hp, &max_hp, 4));
+        // Low HP before the Fairy was obtained doesn't count
+        assert!(!fairy_saved_run(
+            &[pickup("FairyPotion", 4)],
+            &hp,
+            &max_hp,
+            6
+        ));
+        assert!(!fairy_saved_run(
+            &[pickup("Fire Potion", 2)],
+            &hp,
+            &max_hp,
+            6
+        ));
+    }
+
+    #[test]
+    fn test_fairy_save_tolerates_bad_floors_and_hp() {
+        let max_hp = vec![Some(80); 6];
+        let hp = vec![Some(70), Some(60), Some(50), Some(20), Some(40), Some(45)];
+        for floor in [0, -3, i32::MIN] {
+            assert!(fairy_saved_run(
+                &[pickup("FairyPotion", floor)],
+                &hp,
+                &max_hp,
+                6
+            ));
+        }
+        assert!(!fairy_saved_run(
+            &[pickup("FairyPotion", i32::MAX)],
+            &hp,
+            &max_hp,
+            i32::MAX
+        ));
+        let fairy = [pickup("FairyPotion", 2)];
+        assert!(!fairy_saved_run(&fairy, &hp, &max_hp, 0));
+        assert!(!fairy_saved_run(&fairy, &hp, &max_hp, i32::MIN));
+        // Floors past the recorded HP are ignored
+        assert!(fairy_saved_run(&fairy, &hp, &max_hp, i32::MAX));
+
+        let huge = vec![Some(i64::MAX); 6];
+        assert!(!fairy_saved_run(&fairy, &huge, &max_hp, 6));
+        assert!(fairy_saved_run(&fairy, &hp, &huge, 6));
+    }
+
+    #[test]
+    fn test_potion_name_stats() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("fairy")
+                .victory(true)
+                .floor_reached(6)
+                .set(
+                    "potions_obtained",
+                    serde_json::json!([
+                        { "floor": 1, "key": "Fire Potion" },
+                        { "floor": 2, "key": "FairyPotion" },
+                        { "floor": 3, "key": "Fire Potion" }
+                    ]),
+                )
+                .set("potions_floor_usage", vec![3, 5])
+                .set("current_hp_per_floor", vec![70, 60, 50, 20, 40, 45])
+                .set("max_hp_per_floor", vec![80; 6]),
+        );
+        fixture.add(
+            &RunFileBuilder::new("fire")
+                .floor_reached(10)
+                .set(
+                    "potions_obtained",
+                    serde_json::json!([{ "floor": 4, "key": "Fire Potion" }]),
+                )
+                .set("potions_floor_usage", vec![6]),
+        );
+        fixture.add(&RunFileBuilder::new("old"));
+
+        let runs = fixture.load_sorted();
+        assert!(runs[0].fairy_saved_run);
+        let stats = PotionNameStats::from_runs(&runs);
+        assert_eq!(stats.runs, 2);
+        assert_eq!(stats.uses_unattributed, 3);
+
+        let fire = &stats.potions[0];
+        assert_eq!(fire.name, "Fire Potion");
+        assert_eq!((fire.times_obtained, fire.runs_obtained), (3, 2));
+        assert_eq!((fire.wins, fire.win_rate), (1, 0.5));
+        assert_eq!(fire.deaths_prevented, None);
+
+        let fairy = &stats.potions[1];
+        assert_eq!(fairy.name, "FairyPotion");
+        assert_eq!(fairy.win_rate, 1.0);
+        assert_eq!(fairy.deaths_prevented, Some(1));
+    }
+}
@@ -0,0 +1,147 @@
+//! Profiles
+//!
+//! People sharing a machine keep their runs apart with profiles. Each profile
+//! has its own runs directory and sidecar data (hidden runs). The `default`
+//! profile keeps the files the app used before profiles existed, so an
+//! existing setup becomes the `default` profile without a migration.
+//!
+//! The desktop app works on the active profile. An HTTP request can name
+//! another profile, which then applies to that request only (see
+//! [`with_profile`]). The run cache holds one runs directory at a time, so
+//! alternating between profiles re-reads run files.
+
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+use super::{get_custom_runs_path, get_data_dir};
+
+/// Profile holding the settings from before profiles existed
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Longest profile name accepted
+pub const MAX_PROFILE_NAME_LEN: usize = 32;
+
+/// Name of the active profile; `None` until set means the default profile
+static ACTIVE_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Custom runs directories of the profiles that aren't active
+static OTHER_RUNS_PATHS: RwLock<BTreeMap<String, Option<PathBuf>>> = RwLock::new(BTreeMap::new());
+
+tokio::task_local! {
+    /// Profile an HTTP request works on, when it names one
+    static REQUEST_PROFILE: String;
+}
+
+/// Whether `name` can name a profile: 1 to 32 ASCII letters, digits, `-` or
+/// `_`, since it also names the profile's data folder
+pub fn valid_profile_name(name: &str) -> bool {
+    (1..=MAX_PROFILE_NAME_LEN).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// Set the profile the app works on
+///
+/// Its runs directory is the custom runs path (see
+/// [`super::set_custom_runs_path`]).
+pub fn set_active_profile(name: &str) {
+    *ACTIVE_PROFILE.write() = Some(name.to_string());
+}
+
+/// Name of the profile the app works on
+pub fn active_profile() -> String {
+    ACTIVE_PROFILE
+        .read()
+        .clone()
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// Set the custom runs directories of the profiles that aren't active
+pub fn set_profile_runs_paths(paths: BTreeMap<String, Option<PathBuf>>) {
+    *OTHER_RUNS_PATHS.write() = paths;
+}
+
+/// Names of all profiles, sorted
+pub fn profile_names() -> Vec<String> {
+    let mut names: Vec<String> = OTHER_RUNS_PATHS.read().keys().cloned().collect();
+    names.push(active_profile());
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Whether a profile with this name exists
+pub fn profile_exists(name: &str) -> bool {
+    name == active_profile() || OTHER_RUNS_PATHS.read().contains_key(name)
+}
+
+/// Profile the current request works on, or the active profile outside
+/// requests naming one
+pub fn current_profile() -> String {
+    REQUEST_PROFILE
+        .try_with(String::clone)
+        .unwrap_or_else(|_| active_profile())
+}
+
+/// Run `f` against the dataset of the profile `name`
+///
+/// Code run synchronously while `f` is polled sees `name` as the
+/// [`current_profile`]; threads it spawns see the active profile.
+pub async fn with_profile<F: Future>(name: String, f: F) -> F::Output {
+    REQUEST_PROFILE.scope(name, f).await
+}
+
+/// Custom runs directory of the current profile
+pub(crate) fn current_custom_runs_path() -> Option<PathBuf> {
+    let profile = current_profile();
+    if profile == active_profile() {
+        return get_custom_runs_path();
+    }
+    OTHER_RUNS_PATHS.read().get(&profile).cloned().flatten()
+}
+
+/// Folder for a profile's sidecar data
+///
+/// The default profile uses the data directory itself, where sidecar files
+/// were kept before profiles existed; other profiles get a subfolder.
+pub fn profile_data_dir(name: &str) -> Option<PathBuf> {
+    get_data_dir().map(|data_dir| profile_dir_in(&data_dir, name))
+}
+
+/// Folder for a profile's sidecar data inside a given data directory
+pub fn profile_dir_in(data_dir: &Path, name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        return data_dir.to_path_buf();
+    }
+    data_dir.join("profiles").join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_names() {
+        for valid in ["default", "Kai", "player_2", "a-b"] {
+            assert!(valid_profile_name(valid), "{valid}");
+        }
+        for invalid in ["", "two words", "../up", "ü", &"x".repeat(33)] {
+            assert!(!valid_profile_name(invalid), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_default_profile_keeps_the_data_dir() {
+        let Some(data_dir) = get_data_dir() else {
+            return;
+        };
+        assert_eq!(profile_data_dir(DEFAULT_PROFILE), Some(data_dir.clone()));
+        assert_eq!(
+            profile_data_dir("partner"),
+            Some(data_dir.join("profiles").join("partner"))
+        );
+    }
+}
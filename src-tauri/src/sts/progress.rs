@@ -0,0 +1,169 @@
+//! Ascension ladder progress
+//!
+//! Each ascension level unlocks by winning at the level below, so the next
+//! level to play is one above the highest win. A win is any victory, with or
+//! without the heart; heart kills are tracked separately at a few milestone
+//! levels.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{keys, Character, RunMetrics};
+
+/// Highest ascension level in the game
+pub const MAX_ASCENSION: i32 = 20;
+
+/// Ascension levels reported in [`CharacterProgress::heart_milestones`]
+pub const MILESTONE_ASCENSIONS: &[i32] = &[0, 10, 20];
+
+/// Whether the heart was killed at a milestone ascension level
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct HeartMilestone {
+    pub ascension_level: i32,
+    pub heart_killed: bool,
+}
+
+/// Ladder progress for one character
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CharacterProgress {
+    pub character: String,
+    pub display_name: String,
+    /// Highest ascension level won, if any
+    pub highest_ascension_won: Option<i32>,
+    /// Level to play next: one above the highest win, at most 20
+    pub target_ascension: i32,
+    /// Runs played at the target level
+    pub attempts_at_target: i32,
+    /// Wins at the target level (only above 0 once A20 is beaten)
+    pub wins_at_target: i32,
+    pub heart_milestones: Vec<HeartMilestone>,
+}
+
+/// Ladder progress for every character
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ProgressReport {
+    /// Characters in [`Character::all`] order, including ones never played
+    pub characters: Vec<CharacterProgress>,
+}
+
+impl ProgressReport {
+    /// Build the report in one pass over the runs, ignoring hidden runs
+    pub fn from_runs(runs: &[RunMetrics]) -> Self {
+        #[derive(Default)]
+        struct Tally {
+            highest_won: Option<i32>,
+            // Runs and wins per ascension level
+            runs: [i32; MAX_ASCENSION as usize + 1],
+            wins: [i32; MAX_ASCENSION as usize + 1],
+            hearts: [bool; MAX_ASCENSION as usize + 1],
+        }
+        let mut tallies: Vec<Tally> = Character::all().iter().map(|_| Tally::default()).collect();
+
+        for run in runs.iter().filter(|r| !r.hidden) {
+            let Some(order) = run
+                .character
+                .known()
+                .and_then(|c| Character::all().iter().position(|k| *k == c))
+            else {
+                continue;
+            };
+            let level = run.ascension_level.clamp(0, MAX_ASCENSION) as usize;
+            let tally = &mut tallies[order];
+            tally.runs[level] += 1;
+            if run.victory {
+                tally.wins[level] += 1;
+                tally.highest_won = tally.highest_won.max(Some(level as i32));
+            }
+            tally.hearts[level] |= keys::killed_heart(run);
+        }
+
+        let characters = Character::all()
+            .iter()
+            .zip(tallies)
+            .map(|(character, tally)| {
+                let target = tally
+                    .highest_won
+                    .map_or(0, |level| (level + 1).min(MAX_ASCENSION));
+                CharacterProgress {
+                    character: character.dir_name().to_string(),
+                    display_name: character.display_name().to_string(),
+                    highest_ascension_won: tally.highest_won,
+                    target_ascension: target,
+                    attempts_at_target: tally.runs[target as usize],
+                    wins_at_target: tally.wins[target as usize],
+                    heart_milestones: MILESTONE_ASCENSIONS
+                        .iter()
+                        .map(|&level| HeartMilestone {
+                            ascension_level: level,
+                            heart_killed: tally.hearts[level as usize],
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        Self { characters }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+
+    fn run_at(id: &str, character: Character, ascension: i32, victory: bool) -> RunMetrics {
+        let mut run = sample_run(id, character, victory);
+        run.ascension_level = ascension;
+        run
+    }
+
+    #[test]
+    fn test_progress_report() {
+        let mut heart = run_at("h", Character::Ironclad, 0, true);
+        heart.floor_reached = 56;
+        let mut hidden = run_at("x", Character::TheSilent, 14, true);
+        hidden.hidden = true;
+        let runs = vec![
+            heart,
+            run_at("a", Character::TheSilent, 12, true),
+            run_at("b", Character::TheSilent, 13, false),
+            run_at("c", Character::TheSilent, 13, false),
+            run_at("d", Character::TheSilent, 5, true),
+            run_at("e", Character::Watcher, 20, true),
+            run_at("f", Character::Watcher, 20, false),
+            hidden,
+        ];
+
+        let report = ProgressReport::from_runs(&runs);
+        assert_eq!(report.characters.len(), Character::all().len());
+        let get = |name: &str| {
+            report
+                .characters
+                .iter()
+                .find(|c| c.character == name)
+                .unwrap()
+        };
+
+        let ironclad = get("IRONCLAD");
+        assert_eq!(ironclad.highest_ascension_won, Some(0));
+        assert_eq!(ironclad.target_ascension, 1);
+        assert_eq!(ironclad.attempts_at_target, 0);
+        assert!(ironclad.heart_milestones[0].heart_killed);
+
+        let silent = get("THE_SILENT");
+        assert_eq!(silent.highest_ascension_won, Some(12));
+        assert_eq!(
+            (silent.target_ascension, silent.attempts_at_target),
+            (13, 2)
+        );
+        assert!(silent.heart_milestones.iter().all(|m| !m.heart_killed));
+
+        // A20 is the top of the ladder
+        let watcher = get("WATCHER");
+        assert_eq!(watcher.target_ascension, 20);
+        assert_eq!((watcher.attempts_at_target, watcher.wins_at_target), (2, 1));
+
+        let defect = get("DEFECT");
+        assert_eq!(defect.highest_ascension_won, None);
+        assert_eq!((defect.target_ascension, defect.attempts_at_target), (0, 0));
+    }
+}
@@ -0,0 +1,219 @@
+//! Cards removed from the deck
+//!
+//! `items_purged` lists every card removed during a run, with the floor of
+//! each removal in the matching position of `items_purged_floors`. Removals
+//! on shop floors were bought; the others came from events (and Neow), where
+//! the removal is often part of a larger choice or forced outright, so only
+//! shop removals feed the win rate comparison.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use utoipa::ToSchema;
+
+use super::items::base_card_id;
+use super::RunMetrics;
+
+/// A card removed from the deck
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CardRemoval {
+    /// Card id without its upgrade suffix
+    pub card: String,
+    /// Floor of the removal, if the file records it
+    #[serde(default)]
+    pub floor: Option<i32>,
+    /// Bought at a shop rather than removed by an event
+    #[serde(default)]
+    pub shop: bool,
+}
+
+/// How often a card was removed and how runs that removed it went
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PurgedCardStats {
+    /// Card id without its upgrade suffix
+    pub card: String,
+    /// Copies removed across all runs
+    pub removals: i32,
+    /// Of those, copies removed at a shop
+    pub shop_removals: i32,
+    /// Average floor of the removals that record one
+    pub avg_floor: Option<f64>,
+    /// Runs that removed the card at a shop at least once
+    pub runs_removed: i32,
+    /// `wins / runs_removed`, or 0 without such runs
+    pub win_rate_removed: f64,
+    /// Runs that never removed the card and still had it at the end
+    pub runs_kept: i32,
+    /// `wins / runs_kept`, or 0 without such runs
+    pub win_rate_kept: f64,
+}
+
+fn win_rate(wins: i32, runs: i32) -> f64 {
+    if runs > 0 {
+        wins as f64 / runs as f64
+    } else {
+        0.0
+    }
+}
+
+/// Removed cards across runs, most removed first
+///
+/// Runs that only removed a card through events count towards its removals
+/// but are left out of the removed versus kept comparison. Hidden runs are
+/// ignored.
+pub fn purged_card_stats(runs: &[RunMetrics]) -> Vec<PurgedCardStats> {
+    #[derive(Default)]
+    struct Tally {
+        removals: i32,
+        shop_removals: i32,
+        floors: Vec<i32>,
+        /// Runs (and their wins) that bought a removal of the card
+        removed: (i32, i32),
+    }
+
+    let visible: Vec<&RunMetrics> = runs.iter().filter(|r| !r.hidden).collect();
+    let mut tallies: BTreeMap<&str, Tally> = BTreeMap::new();
+    for run in &visible {
+        let mut bought = HashSet::new();
+        for removal in &run.cards_purged {
+            let tally = tallies.entry(&removal.card).or_default();
+            tally.removals += 1;
+            tally.floors.extend(removal.floor);
+            if removal.shop {
+                tally.shop_removals += 1;
+                if bought.insert(removal.card.as_str()) {
+                    tally.removed.0 += 1;
+                    tally.removed.1 += i32::from(run.victory);
+                }
+            }
+        }
+    }
+
+    // Runs (and their wins) that ended with a removed card and never removed
+    // it
+    let mut kept: BTreeMap<&str, (i32, i32)> = BTreeMap::new();
+    for run in &visible {
+        let held: HashSet<&str> = run.master_deck.iter().map(|c| base_card_id(c)).collect();
+        for card in held {
+            let Some((&card, _)) = tallies.get_key_value(card) else {
+                continue;
+            };
+            if run.cards_purged.iter().all(|r| r.card != card) {
+                let entry = kept.entry(card).or_default();
+                entry.0 += 1;
+                entry.1 += i32::from(run.victory);
+            }
+        }
+    }
+
+    let mut stats: Vec<PurgedCardStats> = tallies
+        .into_iter()
+        .map(|(card, tally)| {
+            let (runs_kept, kept_wins) = kept.get(card).copied().unwrap_or_default();
+            PurgedCardStats {
+                card: card.to_string(),
+                removals: tally.removals,
+                shop_removals: tally.shop_removals,
+                avg_floor: (!tally.floors.is_empty()).then(|| {
+                    tally.floors.iter().map(|f| *f as f64).sum::<f64>() / tally.floors.len() as f64
+                }),
+                runs_removed: tally.removed.0,
+                win_rate_removed: win_rate(tally.removed.1, tally.removed.0),
+                runs_kept,
+                win_rate_kept: win_rate(kept_wins, runs_kept),
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| {
+        b.removals
+            .cmp(&a.removals)
+            .then_with(|| a.card.cmp(&b.card))
+    });
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{Character, RunFileBuilder, RunsDirFixture};
+
+    fn removal(card: &str, floor: i32, shop: bool) -> CardRemoval {
+        CardRemoval {
+            card: card.to_string(),
+            floor: Some(floor),
+            shop,
+        }
+    }
+
+    fn run_with(id: &str, victory: bool, deck: &[&str], purged: Vec<CardRemoval>) -> RunMetrics {
+        let mut run = sample_run(id, Character::Ironclad, victory);
+        run.master_deck = deck.iter().map(|c| c.to_string()).collect();
+        run.cards_purged = purged;
+        run
+    }
+
+    #[test]
+    fn test_purged_card_stats() {
+        let runs = vec![
+            run_with(
+                "a",
+                true,
+                &["Bash"],
+                vec![removal("Strike_R", 5, true), removal("Strike_R", 20, true)],
+            ),
+            run_with(
+                "b",
+                false,
+                &["Strike_R"],
+                vec![removal("Defend_R", 8, true)],
+            ),
+            // Removed by an event only: counted, but not compared
+            run_with(
+                "c",
+                true,
+                &["Strike_R"],
+                vec![removal("Strike_R", 11, false)],
+            ),
+            run_with("d", true, &["Strike_R+1", "Defend_R"], vec![]),
+        ];
+
+        let stats = purged_card_stats(&runs);
+        let cards: Vec<&str> = stats.iter().map(|s| s.card.as_str()).collect();
+        assert_eq!(cards, ["Strike_R", "Defend_R"]);
+
+        let strike = &stats[0];
+        assert_eq!((strike.removals, strike.shop_removals), (3, 2));
+        assert_eq!(strike.avg_floor, Some(12.0));
+        assert_eq!((strike.runs_removed, strike.win_rate_removed), (1, 1.0));
+        assert_eq!((strike.runs_kept, strike.win_rate_kept), (2, 0.5));
+
+        let defend = &stats[1];
+        assert_eq!((defend.runs_removed, defend.win_rate_removed), (1, 0.0));
+        assert_eq!((defend.runs_kept, defend.win_rate_kept), (1, 1.0));
+    }
+
+    #[test]
+    fn test_removals_from_run_file() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("purges")
+                .path(&["M", "$", "?", "$"])
+                .set("items_purged", vec!["Strike_G", "Defend_G+1", "Strike_G"])
+                .set("items_purged_floors", serde_json::json!([2, "3", null])),
+        );
+
+        let runs = fixture.load_sorted();
+        assert_eq!(
+            runs[0].cards_purged,
+            [
+                removal("Strike_G", 2, true),
+                removal("Defend_G", 3, false),
+                CardRemoval {
+                    card: "Strike_G".to_string(),
+                    floor: None,
+                    shop: false,
+                },
+            ]
+        );
+    }
+}
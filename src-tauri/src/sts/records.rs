@@ -0,0 +1,226 @@
+//! Personal records across runs
+//!
+//! Each record names the run that set it. Ties go to the run that reached the
+//! value first, so a record only moves when it is strictly beaten.
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::enemies::longest_fight;
+use super::{CharacterId, RunMetrics};
+
+/// A record value and the run that set it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PersonalRecord {
+    /// The record value (score, floor, seconds, ...)
+    pub value: i64,
+    /// Run that set the record
+    pub play_id: String,
+    /// Character played in that run
+    pub character: CharacterId,
+    /// When the run ended (RFC 3339, UTC), if the run file records it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    /// What the record was set against, for records about one fight
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl PersonalRecord {
+    fn of(run: &RunMetrics, value: i64) -> Self {
+        Self {
+            value,
+            play_id: run.play_id.clone(),
+            character: run.character.clone(),
+            date: run
+                .timestamp
+                .and_then(|t| DateTime::from_timestamp(t, 0))
+                .map(|d| d.to_rfc3339()),
+            detail: None,
+        }
+    }
+}
+
+/// Best results across a set of runs
+///
+/// Categories without a qualifying run are left out of the JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PersonalBests {
+    /// Highest score
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highest_score: Option<PersonalRecord>,
+    /// Highest ascension level won
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highest_winning_ascension: Option<PersonalRecord>,
+    /// Deepest floor reached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deepest_floor: Option<PersonalRecord>,
+    /// Shortest victory, in seconds of playtime
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fastest_victory: Option<PersonalRecord>,
+    /// Most elites killed in one run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub most_elites_killed: Option<PersonalRecord>,
+    /// Most damage taken in a single fight that was survived
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub largest_damage_survived: Option<PersonalRecord>,
+    /// Most turns a single fight lasted, with the encounter as `detail`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longest_fight: Option<PersonalRecord>,
+}
+
+/// Keep `record` unless `value` strictly beats it, returning the new record
+/// when it was replaced
+fn consider<'a>(
+    record: &'a mut Option<PersonalRecord>,
+    run: &RunMetrics,
+    value: Option<i64>,
+    beats: fn(i64, i64) -> bool,
+) -> Option<&'a mut PersonalRecord> {
+    let value = value?;
+    if record.as_ref().is_none_or(|r| beats(value, r.value)) {
+        return Some(record.insert(PersonalRecord::of(run, value)));
+    }
+    None
+}
+
+/// Compute personal bests, ignoring hidden runs
+///
+/// Runs are considered oldest first; runs without a timestamp come last, in
+/// the order given.
+pub fn personal_bests(runs: &[RunMetrics]) -> PersonalBests {
+    let mut ordered: Vec<&RunMetrics> = runs.iter().filter(|r| !r.hidden).collect();
+    ordered.sort_by_key(|r| r.timestamp.unwrap_or(i64::MAX));
+
+    let higher = |a: i64, b: i64| a > b;
+    let lower = |a: i64, b: i64| a < b;
+    let positive = |v: i32| (v > 0).then_some(i64::from(v));
+
+    let mut bests = PersonalBests::default();
+    for run in ordered {
+        consider(
+            &mut bests.highest_score,
+            run,
+            Some(run.score.into()),
+            higher,
+        );
+        consider(
+            &mut bests.highest_winning_ascension,
+            run,
+            run.victory.then_some(run.ascension_level.into()),
+            higher,
+        );
+        consider(
+            &mut bests.deepest_floor,
+            run,
+            Some(run.stats_floor().into()),
+            higher,
+        );
+        consider(
+            &mut bests.fastest_victory,
+            run,
+            positive(run.playtime).filter(|_| run.victory),
+            lower,
+        );
+        consider(
+            &mut bests.most_elites_killed,
+            run,
+            positive(run.elites_killed),
+            higher,
+        );
+        consider(
+            &mut bests.largest_damage_survived,
+            run,
+            positive(run.max_damage_survived),
+            higher,
+        );
+        if let Some(fight) = longest_fight(run) {
+            let turns = fight.turns.map(i64::from);
+            if let Some(record) = consider(&mut bests.longest_fight, run, turns, higher) {
+                record.detail = Some(fight.enemies.clone());
+            }
+        }
+    }
+    bests
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{Character, Encounter};
+
+    fn run(play_id: &str, timestamp: i64, victory: bool, score: i32) -> RunMetrics {
+        let mut run = sample_run(play_id, Character::Ironclad, victory);
+        run.timestamp = Some(timestamp);
+        run.score = score;
+        run
+    }
+
+    #[test]
+    fn test_ties_go_to_the_earlier_run() {
+        // Given newest first, the older run still holds the tied record
+        let runs = vec![
+            run("later", 2_000, false, 500),
+            run("earlier", 1_000, false, 500),
+        ];
+        let bests = personal_bests(&runs);
+        let score = bests.highest_score.unwrap();
+        assert_eq!(score.play_id, "earlier");
+        assert_eq!(score.value, 500);
+        assert_eq!(score.date.as_deref(), Some("1970-01-01T00:16:40+00:00"));
+    }
+
+    #[test]
+    fn test_records_pick_the_best_run() {
+        let mut fast = run("fast", 1_000, true, 800);
+        fast.playtime = 1_500;
+        fast.ascension_level = 5;
+        let mut slow = run("slow", 2_000, true, 900);
+        slow.playtime = 3_000;
+        slow.ascension_level = 12;
+        slow.elites_killed = 4;
+        let mut loss = run("loss", 3_000, false, 300);
+        loss.ascension_level = 20;
+        loss.max_damage_survived = 45;
+
+        let bests = personal_bests(&[fast, slow, loss]);
+        assert_eq!(bests.highest_score.unwrap().play_id, "slow");
+        let ascension = bests.highest_winning_ascension.unwrap();
+        assert_eq!((ascension.play_id.as_str(), ascension.value), ("slow", 12));
+        assert_eq!(bests.fastest_victory.unwrap().play_id, "fast");
+        assert_eq!(bests.most_elites_killed.unwrap().value, 4);
+        assert_eq!(bests.largest_damage_survived.unwrap().play_id, "loss");
+    }
+
+    #[test]
+    fn test_longest_fight_names_the_encounter() {
+        let mut short = run("short", 1_000, false, 100);
+        let mut long = run("long", 2_000, false, 100);
+        for (run, turns) in [(&mut short, 6), (&mut long, 11)] {
+            run.encounters = vec![Encounter {
+                floor: 3,
+                enemies: format!("Fight of {turns}"),
+                hp_after: None,
+                elite: false,
+                turns: Some(turns),
+            }];
+        }
+        let record = personal_bests(&[short, long]).longest_fight.unwrap();
+        assert_eq!((record.play_id.as_str(), record.value), ("long", 11));
+        assert_eq!(record.detail.as_deref(), Some("Fight of 11"));
+    }
+
+    #[test]
+    fn test_categories_without_a_qualifying_run_are_omitted() {
+        let bests = personal_bests(&[run("loss", 1_000, false, 100)]);
+        assert!(bests.highest_winning_ascension.is_none());
+        assert!(bests.fastest_victory.is_none());
+
+        let json = serde_json::to_value(&bests).unwrap();
+        assert!(json.get("fastest_victory").is_none());
+        assert!(json.get("highest_score").is_some());
+        assert_eq!(personal_bests(&[]), PersonalBests::default());
+    }
+}
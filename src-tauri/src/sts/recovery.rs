@@ -0,0 +1,156 @@
+//! Lenient decoding of run files
+//!
+//! Run files occasionally pick up damage outside the game: a UTF-8 byte order
+//! mark when copied through Windows editors, trailing NUL bytes when the game
+//! crashes mid-write, or stray bytes after the closing brace. These are
+//! repaired before parsing and each repair is recorded so the load report can
+//! say which files needed it. Values of the wrong type inside an otherwise
+//! valid file, and numbers written as strings, are handled by the parser and
+//! recorded the same way.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::ToSchema;
+
+/// UTF-8 byte order mark
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// A repair applied to a run file before it parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Recovery {
+    /// A leading UTF-8 byte order mark was removed
+    StrippedBom,
+    /// Trailing NUL bytes were removed
+    TrimmedTrailingNul,
+    /// Content after the last closing brace was dropped
+    TruncatedAtLastBrace,
+    /// Values of the wrong type were ignored, as if the fields were missing
+    ///
+    /// Nulls and numbers written as floats are read without a record, since
+    /// no data is lost.
+    IgnoredInvalidValues,
+    /// Numbers written as strings (e.g. `"floor_reached": "34"`) were read as
+    /// numbers; nothing was lost, but the game doesn't write them this way
+    CoercedNumbers,
+    /// The file has no `play_id`, so the run is named after the file
+    ///
+    /// Such runs are never treated as copies of each other, as files in
+    /// different folders can share a name.
+    PlayIdFromFileName,
+}
+
+/// A run file that could not be parsed even after recovery
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    /// The parser error for the file as found on disk (minus BOM and NULs)
+    pub message: String,
+    /// Line of the error (1-based)
+    pub line: usize,
+    /// Column of the error (1-based)
+    pub column: usize,
+}
+
+impl From<serde_json::Error> for DecodeError {
+    fn from(e: serde_json::Error) -> Self {
+        Self {
+            message: e.to_string(),
+            line: e.line(),
+            column: e.column(),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid run file: {}", self.message)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Parse JSON, repairing common file damage first
+///
+/// Returns the value along with the repairs that were needed. When the
+/// repaired content still doesn't parse, the content is cut after its last
+/// `}` and parsed again; if that fails too the original parse error is
+/// returned.
+pub fn decode_lenient<T: DeserializeOwned>(
+    bytes: &[u8],
+) -> Result<(T, Vec<Recovery>), DecodeError> {
+    let mut recoveries = Vec::new();
+
+    let mut content = bytes;
+    if let Some(rest) = content.strip_prefix(BOM) {
+        content = rest;
+        recoveries.push(Recovery::StrippedBom);
+    }
+
+    let trimmed_len = content
+        .iter()
+        .rposition(|b| *b != 0 && !b.is_ascii_whitespace())
+        .map_or(0, |i| i + 1);
+    if content[trimmed_len..].contains(&0) {
+        recoveries.push(Recovery::TrimmedTrailingNul);
+    }
+    content = &content[..trimmed_len];
+
+    let error = match serde_json::from_slice(content) {
+        Ok(value) => return Ok((value, recoveries)),
+        Err(e) => e,
+    };
+
+    if let Some(end) = content.iter().rposition(|b| *b == b'}') {
+        if end + 1 < content.len() {
+            if let Ok(value) = serde_json::from_slice(&content[..=end]) {
+                recoveries.push(Recovery::TruncatedAtLastBrace);
+                return Ok((value, recoveries));
+            }
+        }
+    }
+    Err(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOM_RUN: &[u8] = include_bytes!("../../tests/fixtures/runs/bom.run");
+    const NUL_RUN: &[u8] = include_bytes!("../../tests/fixtures/runs/trailing_nul.run");
+    const GARBAGE_RUN: &[u8] = include_bytes!("../../tests/fixtures/runs/trailing_garbage.run");
+    const TRUNCATED_RUN: &[u8] = include_bytes!("../../tests/fixtures/runs/truncated.run");
+
+    fn decode(bytes: &[u8]) -> Result<(serde_json::Value, Vec<Recovery>), DecodeError> {
+        decode_lenient(bytes)
+    }
+
+    #[test]
+    fn test_clean_json_needs_no_recovery() {
+        let (value, recoveries) = decode(b"{\"floor_reached\": 3}\n").unwrap();
+        assert_eq!(value["floor_reached"], 3);
+        assert!(recoveries.is_empty());
+    }
+
+    #[test]
+    fn test_recovers_damaged_fixtures() {
+        let cases: [(&[u8], &[Recovery]); 3] = [
+            (BOM_RUN, &[Recovery::StrippedBom]),
+            (NUL_RUN, &[Recovery::TrimmedTrailingNul]),
+            (GARBAGE_RUN, &[Recovery::TruncatedAtLastBrace]),
+        ];
+        for (bytes, expected) in cases {
+            let (value, recoveries) = decode(bytes).unwrap();
+            assert_eq!(value["play_id"], "fixture", "recoveries {:?}", recoveries);
+            assert_eq!(recoveries, expected);
+        }
+    }
+
+    #[test]
+    fn test_unrecoverable_file_reports_position() {
+        let error = decode(TRUNCATED_RUN).unwrap_err();
+        assert!(error.line >= 1);
+        assert!(error.column > 0);
+        assert!(error.to_string().starts_with("Invalid run file"));
+    }
+}
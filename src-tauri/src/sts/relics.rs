@@ -0,0 +1,691 @@
+//! Relic acquisition timing and sources
+//!
+//! `relics_obtained` in a run file records the floor each relic was picked
+//! up on. Starter relics and Neow rewards aren't listed there, so they end up
+//! without a floor and are grouped as "start of run".
+//!
+//! The source of a relic is read from the room on its floor in
+//! `path_per_floor`. Boss relics aren't in `relics_obtained`, so each relic
+//! picked from a boss chest (`boss_relics`) is counted as a boss relic. Unknown rooms are only counted as events when the run
+//! didn't fight there, as a `?` room that turned into a fight may have
+//! dropped the relic as a combat reward. Those, and relics on floors that
+//! don't normally give one, are [`RelicSource::Uncertain`] rather than
+//! guessed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use utoipa::ToSchema;
+
+use super::elites::{BOSS_ROOM, ELITE_ROOM};
+use super::events::QUESTION_ROOM;
+use super::shops::SHOP_ROOM;
+use super::stats_math::wilson_interval;
+use super::{
+    act_for_floor, completed_act, mean, BossRelicChoice, CharacterId, RunMetrics, ACT_END_FLOORS,
+};
+
+/// `path_per_floor` symbol of a treasure room
+pub const TREASURE_ROOM: &str = "T";
+
+/// A relic in the final relic list and when it was obtained
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RelicAcquisition {
+    pub relic: String,
+    /// Floor the relic was obtained on; `None` for starter and Neow relics
+    pub floor: Option<i32>,
+}
+
+/// Join the relic list with `relics_obtained` entries
+///
+/// Entries are matched in order, so a relic obtained twice (e.g. via a mod)
+/// gets each floor once.
+pub fn relic_acquisitions(relics: &[String], obtained: &[(String, i32)]) -> Vec<RelicAcquisition> {
+    let mut used = vec![false; obtained.len()];
+    relics
+        .iter()
+        .map(|relic| {
+            let floor = obtained
+                .iter()
+                .enumerate()
+                .find(|(i, (name, _))| !used[*i] && name == relic)
+                .map(|(i, (_, floor))| {
+                    used[i] = true;
+                    *floor
+                });
+            RelicAcquisition {
+                relic: relic.clone(),
+                floor,
+            }
+        })
+        .collect()
+}
+
+/// Relics held at the end of `act` (1-3), or `None` if the run died before
+///
+/// Counts the starter and Neow relics plus those obtained up to the act's
+/// last floor. Only relics still held at the end of the run are known, so
+/// relics given away or swapped (e.g. the starter relic for a boss relic)
+/// aren't counted.
+pub fn relics_after_act(
+    acquisitions: &[RelicAcquisition],
+    floor_reached: i32,
+    act: i32,
+) -> Option<i32> {
+    if !completed_act(floor_reached, act) {
+        return None;
+    }
+    let end = ACT_END_FLOORS[act as usize - 1];
+    Some(
+        acquisitions
+            .iter()
+            .filter(|a| a.floor.is_none_or(|floor| floor <= end))
+            .count() as i32,
+    )
+}
+
+/// Where a relic was picked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RelicSource {
+    Elite,
+    Chest,
+    Shop,
+    Boss,
+    Event,
+    /// The room doesn't tell where the relic came from
+    Uncertain,
+}
+
+impl RelicSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RelicSource::Elite => "elite",
+            RelicSource::Chest => "chest",
+            RelicSource::Shop => "shop",
+            RelicSource::Boss => "boss",
+            RelicSource::Event => "event",
+            RelicSource::Uncertain => "uncertain",
+        }
+    }
+}
+
+/// Source of a relic obtained on `floor`, from the room on that floor
+pub fn relic_source(
+    floor: i32,
+    path_per_floor: &[Option<String>],
+    fight_floors: &HashSet<i32>,
+) -> RelicSource {
+    let room = usize::try_from(floor.saturating_sub(1))
+        .ok()
+        .and_then(|i| path_per_floor.get(i));
+    match room.map(Option::as_deref) {
+        Some(Some(ELITE_ROOM)) => RelicSource::Elite,
+        Some(Some(TREASURE_ROOM)) => RelicSource::Chest,
+        Some(Some(SHOP_ROOM)) => RelicSource::Shop,
+        Some(Some(BOSS_ROOM)) => RelicSource::Boss,
+        Some(Some(QUESTION_ROOM)) if !fight_floors.contains(&floor) => RelicSource::Event,
+        _ => RelicSource::Uncertain,
+    }
+}
+
+/// Relics in `relics_obtained` and boss chests per source
+///
+/// `fight_floors` are the floors of the run's `damage_taken` entries.
+pub fn relic_sources(
+    obtained: &[(String, i32)],
+    boss_relics: &[BossRelicChoice],
+    path_per_floor: &[Option<String>],
+    fight_floors: impl IntoIterator<Item = i32>,
+) -> HashMap<String, i32> {
+    let fight_floors: HashSet<i32> = fight_floors.into_iter().collect();
+    let mut sources = HashMap::new();
+    let picked = boss_relics.iter().filter(|c| c.picked.is_some());
+    for source in obtained
+        .iter()
+        .map(|(_, floor)| relic_source(*floor, path_per_floor, &fight_floors))
+        .chain(picked.map(|_| RelicSource::Boss))
+    {
+        *sources.entry(source.as_str().to_string()).or_default() += 1;
+    }
+    sources
+}
+
+/// Relics picked up per source across a group of runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RelicSourceCounts {
+    pub runs: i32,
+    /// Relics in `relics_obtained`
+    pub relics: i32,
+    /// Relics per source
+    pub by_source: BTreeMap<String, i32>,
+    /// Share of `relics` per source
+    pub share_by_source: BTreeMap<String, f64>,
+}
+
+impl RelicSourceCounts {
+    fn add(&mut self, run: &RunMetrics) {
+        self.runs += 1;
+        for (source, count) in &run.relic_sources {
+            self.relics += count;
+            *self.by_source.entry(source.clone()).or_default() += count;
+        }
+    }
+
+    fn finish(&mut self) {
+        self.share_by_source = self
+            .by_source
+            .iter()
+            .map(|(source, count)| (source.clone(), *count as f64 / self.relics as f64))
+            .collect();
+    }
+}
+
+/// Relic sources of one character's runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CharacterRelicSources {
+    pub character: CharacterId,
+    pub wins: RelicSourceCounts,
+    pub losses: RelicSourceCounts,
+}
+
+/// Where relics came from in wins versus losses
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RelicSourceStats {
+    pub wins: RelicSourceCounts,
+    pub losses: RelicSourceCounts,
+    /// One entry per character, by character id
+    pub by_character: Vec<CharacterRelicSources>,
+}
+
+/// Relic source distribution for wins and losses, ignoring hidden runs
+pub fn relic_source_stats(runs: &[RunMetrics]) -> RelicSourceStats {
+    let mut stats = RelicSourceStats::default();
+    let mut by_character: BTreeMap<&str, CharacterRelicSources> = BTreeMap::new();
+    for run in runs.iter().filter(|r| !r.hidden) {
+        let character = by_character
+            .entry(run.character.as_str())
+            .or_insert_with(|| CharacterRelicSources {
+                character: run.character.clone(),
+                wins: RelicSourceCounts::default(),
+                losses: RelicSourceCounts::default(),
+            });
+        if run.victory {
+            stats.wins.add(run);
+            character.wins.add(run);
+        } else {
+            stats.losses.add(run);
+            character.losses.add(run);
+        }
+    }
+
+    stats.wins.finish();
+    stats.losses.finish();
+    stats.by_character = by_character
+        .into_values()
+        .map(|mut character| {
+            character.wins.finish();
+            character.losses.finish();
+            character
+        })
+        .collect();
+    stats
+}
+
+/// Average relics held at the end of an act
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RelicCheckpoint {
+    pub act: i32,
+    /// Runs that finished the act
+    pub runs: i32,
+    pub avg_relics: f64,
+    pub avg_relics_wins: f64,
+    pub avg_relics_losses: f64,
+}
+
+/// A run's relics held at the end of `act`
+fn checkpoint(run: &RunMetrics, act: i32) -> Option<i32> {
+    match act {
+        1 => run.relics_after_act1,
+        2 => run.relics_after_act2,
+        3 => run.relics_after_act3,
+        _ => None,
+    }
+}
+
+/// Relics held at the end of acts 1 to 3, averaged over the runs that got
+/// that far
+pub fn relic_checkpoints<'a>(
+    runs: impl IntoIterator<Item = &'a RunMetrics>,
+) -> Vec<RelicCheckpoint> {
+    let runs: Vec<&RunMetrics> = runs.into_iter().collect();
+    (1..=ACT_END_FLOORS.len() as i32)
+        .map(|act| {
+            let counts: Vec<(bool, i32)> = runs
+                .iter()
+                .filter_map(|r| Some((r.victory, checkpoint(r, act)?)))
+                .collect();
+            let avg = |victory: Option<bool>| {
+                mean(
+                    counts
+                        .iter()
+                        .filter(|(won, _)| victory.is_none_or(|v| *won == v))
+                        .map(|(_, relics)| *relics),
+                )
+            };
+            RelicCheckpoint {
+                act,
+                runs: counts.len() as i32,
+                avg_relics: avg(None),
+                avg_relics_wins: avg(Some(true)),
+                avg_relics_losses: avg(Some(false)),
+            }
+        })
+        .collect()
+}
+
+/// Runs holding a relic, grouped by the act it was obtained in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RelicTimingBucket {
+    /// Act the relic was obtained in (0 = start of run, e.g. Neow)
+    pub act: i32,
+    pub runs: i32,
+    pub wins: i32,
+    pub win_rate: f64,
+    /// Low end of the win rate's [`wilson_interval`]
+    pub win_rate_ci_low: Option<f64>,
+    /// High end of the win rate's [`wilson_interval`]
+    pub win_rate_ci_high: Option<f64>,
+}
+
+/// Win rate for a relic by when it was obtained
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RelicTiming {
+    pub relic: String,
+    /// Runs that ended with the relic
+    pub runs: i32,
+    /// One entry per act the relic was obtained in, earliest first
+    pub buckets: Vec<RelicTimingBucket>,
+}
+
+/// Win rate for runs ending with `relic` (case-insensitive), by acquisition act
+///
+/// Hidden runs are ignored.
+pub fn relic_timing(runs: &[RunMetrics], relic: &str) -> RelicTiming {
+    let mut by_act: BTreeMap<i32, (i32, i32)> = BTreeMap::new();
+    for run in runs.iter().filter(|r| !r.hidden) {
+        let Some(acquisition) = run
+            .relic_acquisitions
+            .iter()
+            .find(|a| a.relic.eq_ignore_ascii_case(relic))
+        else {
+            continue;
+        };
+        let act = acquisition.floor.map_or(0, act_for_floor);
+        let bucket = by_act.entry(act).or_default();
+        bucket.0 += 1;
+        bucket.1 += i32::from(run.victory);
+    }
+
+    let buckets: Vec<RelicTimingBucket> = by_act
+        .into_iter()
+        .map(|(act, (runs, wins))| {
+            let (win_rate_ci_low, win_rate_ci_high) = wilson_interval(wins, runs).unzip();
+            RelicTimingBucket {
+                act,
+                runs,
+                wins,
+                win_rate: wins as f64 / runs as f64,
+                win_rate_ci_low,
+                win_rate_ci_high,
+            }
+        })
+        .collect();
+    RelicTiming {
+        relic: relic.to_string(),
+        runs: buckets.iter().map(|b| b.runs).sum(),
+        buckets,
+    }
+}
+
+/// Runs whose first relic pickup was one relic
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FirstRelic {
+    pub relic: String,
+    /// Runs that picked this relic up first
+    pub runs: i32,
+    /// Share of runs with a first relic that picked up this one
+    pub share: f64,
+    pub wins: i32,
+    /// `wins / runs`
+    pub win_rate: f64,
+    /// Low end of the win rate's [`wilson_interval`]
+    pub win_rate_ci_low: Option<f64>,
+    /// High end of the win rate's [`wilson_interval`]
+    pub win_rate_ci_high: Option<f64>,
+    /// Win rate minus the baseline win rate of all runs with a first relic
+    pub win_rate_vs_baseline: f64,
+    /// Fewer runs than the minimum, so the win rate says little
+    pub low_confidence: bool,
+}
+
+/// Win rates by the first relic picked up in a run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FirstRelicStats {
+    /// Runs that picked up a relic; runs that died before are left out
+    pub runs: i32,
+    pub wins: i32,
+    /// `wins / runs`, or 0 without runs
+    pub baseline_win_rate: f64,
+    /// Runs a relic needs to not be flagged low-confidence
+    pub min_runs: i32,
+    /// Every first relic, most frequent first
+    pub relics: Vec<FirstRelic>,
+}
+
+/// The first relic in a run's `relics_obtained`
+///
+/// The list is in pickup order and leaves out the starter relic and Neow
+/// rewards, so its first entry is the first relic the run found.
+pub fn first_relic(obtained: &[(String, i32)]) -> Option<RelicAcquisition> {
+    obtained
+        .iter()
+        .find(|(_, floor)| *floor > 0)
+        .map(|(relic, floor)| RelicAcquisition {
+            relic: relic.clone(),
+            floor: Some(*floor),
+        })
+}
+
+/// Win rate by first relic, ignoring hidden runs
+pub fn first_relic_stats(runs: &[RunMetrics], min_runs: i32) -> FirstRelicStats {
+    let mut by_relic: BTreeMap<&str, (i32, i32)> = BTreeMap::new();
+    let mut stats = FirstRelicStats {
+        min_runs,
+        ..FirstRelicStats::default()
+    };
+    for run in runs.iter().filter(|r| !r.hidden) {
+        let Some(first) = &run.first_relic else {
+            continue;
+        };
+        stats.runs += 1;
+        stats.wins += i32::from(run.victory);
+        let entry = by_relic.entry(&first.relic).or_default();
+        entry.0 += 1;
+        entry.1 += i32::from(run.victory);
+    }
+    if stats.runs > 0 {
+        stats.baseline_win_rate = stats.wins as f64 / stats.runs as f64;
+    }
+
+    stats.relics = by_relic
+        .into_iter()
+        .map(|(relic, (runs, wins))| {
+            let (win_rate_ci_low, win_rate_ci_high) = wilson_interval(wins, runs).unzip();
+            let win_rate = wins as f64 / runs as f64;
+            FirstRelic {
+                relic: relic.to_string(),
+                runs,
+                share: runs as f64 / stats.runs as f64,
+                wins,
+                win_rate,
+                win_rate_ci_low,
+                win_rate_ci_high,
+                win_rate_vs_baseline: win_rate - stats.baseline_win_rate,
+                low_confidence: runs < min_runs,
+            }
+        })
+        .collect();
+    stats
+        .relics
+        .sort_by(|a, b| b.runs.cmp(&a.runs).then_with(|| a.relic.cmp(&b.relic)));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{Character, RunFileBuilder, RunsDirFixture};
+
+    fn names(relics: &[&str]) -> Vec<String> {
+        relics.iter().map(|r| r.to_string()).collect()
+    }
+
+    #[test]
+    fn test_join_tolerates_unlisted_relics() {
+        let relics = names(&["Burning Blood", "Neow's Lament", "Vajra", "Dead Branch"]);
+        let obtained = vec![("Dead Branch".to_string(), 40), ("Vajra".to_string(), 6)];
+
+        let floors: Vec<Option<i32>> = relic_acquisitions(&relics, &obtained)
+            .into_iter()
+            .map(|a| a.floor)
+            .collect();
+        assert_eq!(floors, vec![None, None, Some(6), Some(40)]);
+    }
+
+    #[test]
+    fn test_relics_after_act() {
+        let relics = names(&["Burning Blood", "Vajra", "Orichalcum", "Dead Branch"]);
+        let obtained = vec![
+            ("Vajra".to_string(), 17),
+            ("Orichalcum".to_string(), 18),
+            ("Dead Branch".to_string(), 40),
+        ];
+        let acquisitions = relic_acquisitions(&relics, &obtained);
+
+        // The Act 1 chest on floor 17 counts towards Act 1
+        assert_eq!(relics_after_act(&acquisitions, 51, 1), Some(2));
+        assert_eq!(relics_after_act(&acquisitions, 51, 2), Some(3));
+        assert_eq!(relics_after_act(&acquisitions, 51, 3), Some(4));
+        // Dying to the Act 2 boss leaves only the Act 1 checkpoint
+        assert_eq!(relics_after_act(&acquisitions, 33, 1), Some(2));
+        assert_eq!(relics_after_act(&acquisitions, 33, 2), None);
+    }
+
+    #[test]
+    fn test_first_relic_from_run_file() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("vajra")
+                .relics(&["Burning Blood", "Neow's Lament", "Anchor", "Vajra"])
+                .set(
+                    "relics_obtained",
+                    serde_json::json!([
+                        { "floor": 4, "key": "Vajra" },
+                        { "floor": 9, "key": "Anchor" }
+                    ]),
+                ),
+        );
+        fixture.add(&RunFileBuilder::new("none").relics(&["Burning Blood"]));
+
+        let runs = fixture.load_sorted();
+        assert_eq!(runs[0].first_relic, None);
+        assert_eq!(
+            runs[1].first_relic,
+            Some(RelicAcquisition {
+                relic: "Vajra".to_string(),
+                floor: Some(4),
+            })
+        );
+    }
+
+    #[test]
+    fn test_relic_sources_from_path() {
+        let mut path: Vec<Option<&str>> = vec![Some("M"); 17];
+        path[1] = Some("?");
+        path[2] = Some("?");
+        path[3] = Some("$");
+        path[4] = Some("E");
+        path[5] = Some("T");
+        path[6] = Some("R");
+        path[15] = Some("BOSS");
+        // Treasure floor after the boss
+        path[16] = None;
+        let relic = |floor: i32| serde_json::json!({ "floor": floor, "key": "Relic" });
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("sources")
+                .set("path_per_floor", path)
+                .fight(3, "Cultist", 5)
+                .set(
+                    "boss_relics",
+                    serde_json::json!([
+                        { "picked": "Runic Dome", "not_picked": ["Astrolabe", "Sozu"] },
+                        { "not_picked": ["Ectoplasm", "Snecko Eye", "Pandora's Box"] },
+                    ]),
+                )
+                .set(
+                    "relics_obtained",
+                    // Event, event turned fight, shop, elite, chest, rest,
+                    // the floor after the boss and a floor past the path
+                    [2, 3, 4, 5, 6, 7, 17, 30].map(relic).to_vec(),
+                ),
+        );
+
+        let run = &fixture.load().0[0];
+        let sources: BTreeMap<&str, i32> = run
+            .relic_sources
+            .iter()
+            .map(|(source, count)| (source.as_str(), *count))
+            .collect();
+        assert_eq!(
+            sources,
+            BTreeMap::from([
+                ("boss", 1),
+                ("chest", 1),
+                ("elite", 1),
+                ("event", 1),
+                ("shop", 1),
+                ("uncertain", 4),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_relic_source_stats_by_outcome() {
+        let run = |id: &str, character: Character, victory: bool, sources: &[(&str, i32)]| {
+            let mut run = sample_run(id, character, victory);
+            run.relic_sources = sources.iter().map(|(s, n)| (s.to_string(), *n)).collect();
+            run
+        };
+        let mut hidden = run("hidden", Character::Ironclad, true, &[("shop", 9)]);
+        hidden.hidden = true;
+        let runs = [
+            run("a", Character::Ironclad, true, &[("elite", 3), ("boss", 1)]),
+            run(
+                "b",
+                Character::Ironclad,
+                false,
+                &[("elite", 1), ("event", 1)],
+            ),
+            run("c", Character::Defect, true, &[("shop", 2), ("boss", 2)]),
+            hidden,
+        ];
+
+        let stats = relic_source_stats(&runs);
+        assert_eq!((stats.wins.runs, stats.wins.relics), (2, 8));
+        assert_eq!(stats.wins.by_source["boss"], 3);
+        assert_eq!(stats.wins.share_by_source["elite"], 0.375);
+        assert_eq!(stats.losses.share_by_source["event"], 0.5);
+
+        let characters: Vec<&str> = stats
+            .by_character
+            .iter()
+            .map(|c| c.character.as_str())
+            .collect();
+        assert_eq!(characters, ["DEFECT", "IRONCLAD"]);
+        let ironclad = &stats.by_character[1];
+        assert_eq!(ironclad.wins.share_by_source["elite"], 0.75);
+        assert_eq!(ironclad.losses.relics, 2);
+        assert_eq!(stats.by_character[0].losses, RelicSourceCounts::default());
+    }
+
+    #[test]
+    fn test_first_relic_stats() {
+        let run = |id: &str, victory: bool, relic: Option<&str>| {
+            let mut run = sample_run(id, Character::TheSilent, victory);
+            run.first_relic = relic.map(|relic| RelicAcquisition {
+                relic: relic.to_string(),
+                floor: Some(5),
+            });
+            run
+        };
+        let runs = [
+            run("a", true, Some("Vajra")),
+            run("b", true, Some("Vajra")),
+            run("c", false, Some("Vajra")),
+            run("d", false, Some("Anchor")),
+            // Died before finding a relic: not in the baseline
+            run("e", false, None),
+        ];
+
+        let stats = first_relic_stats(&runs, 3);
+        assert_eq!((stats.runs, stats.wins), (4, 2));
+        assert_eq!(stats.baseline_win_rate, 0.5);
+        let vajra = &stats.relics[0];
+        assert_eq!((vajra.relic.as_str(), vajra.runs), ("Vajra", 3));
+        assert_eq!(vajra.share, 0.75);
+        assert!((vajra.win_rate_vs_baseline - (2.0 / 3.0 - 0.5)).abs() < 1e-9);
+        assert!(!vajra.low_confidence);
+        let anchor = &stats.relics[1];
+        assert_eq!(anchor.win_rate_vs_baseline, -0.5);
+        assert!(anchor.low_confidence);
+    }
+
+    #[test]
+    fn test_relic_checkpoints_skip_runs_that_died_earlier() {
+        let run = |id: &str, victory: bool, floor_reached: i32, after: [Option<i32>; 3]| {
+            let mut run = sample_run(id, Character::Defect, victory);
+            run.floor_reached = floor_reached;
+            [
+                run.relics_after_act1,
+                run.relics_after_act2,
+                run.relics_after_act3,
+            ] = after;
+            run
+        };
+        let runs = [
+            run("win", true, 51, [Some(3), Some(6), Some(9)]),
+            run("loss", false, 40, [Some(2), Some(4), None]),
+            run("early", false, 10, [None, None, None]),
+        ];
+
+        let checkpoints = relic_checkpoints(&runs);
+        assert_eq!(checkpoints.len(), 3);
+        assert_eq!((checkpoints[0].runs, checkpoints[0].avg_relics), (2, 2.5));
+        assert_eq!(checkpoints[1].avg_relics_wins, 6.0);
+        assert_eq!(checkpoints[1].avg_relics_losses, 4.0);
+        assert_eq!(
+            (checkpoints[2].runs, checkpoints[2].avg_relics_losses),
+            (1, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_relic_timing_by_act() {
+        let run = |id: &str, victory: bool, floor: Option<i32>| {
+            let mut run = sample_run(id, Character::Ironclad, victory);
+            run.relic_acquisitions = vec![RelicAcquisition {
+                relic: "Dead Branch".to_string(),
+                floor,
+            }];
+            run
+        };
+        let runs = vec![
+            run("early-win", true, Some(9)),
+            run("early-loss", false, Some(15)),
+            run("late-loss", false, Some(48)),
+            run("neow-win", true, None),
+            sample_run("without", Character::Ironclad, true),
+        ];
+
+        let timing = relic_timing(&runs, "dead branch");
+        assert_eq!(timing.runs, 4);
+        let acts: Vec<(i32, i32, i32)> = timing
+            .buckets
+            .iter()
+            .map(|b| (b.act, b.runs, b.wins))
+            .collect();
+        assert_eq!(acts, vec![(0, 1, 1), (1, 2, 1), (3, 1, 0)]);
+        assert_eq!(timing.buckets[1].win_rate, 0.5);
+    }
+}
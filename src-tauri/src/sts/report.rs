@@ -0,0 +1,278 @@
+//! Stats summaries for sharing
+//!
+//! A [`StatsReport`] condenses the visible runs into overall and
+//! per-character results, rendered as aligned plain text for chat, a markdown
+//! table for forums and issues, or JSON. Nothing identifying the player (such
+//! as the runs path) is included.
+
+use serde::{Deserialize, Serialize};
+
+use super::{calculate_character_stats, keys, RunMetrics};
+
+/// Layout of a rendered report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Text,
+    Markdown,
+    Json,
+}
+
+impl ReportFormat {
+    /// Every format, in documentation order
+    pub const ALL: [ReportFormat; 3] = [
+        ReportFormat::Text,
+        ReportFormat::Markdown,
+        ReportFormat::Json,
+    ];
+
+    /// Name used to pick the format
+    pub fn name(self) -> &'static str {
+        match self {
+            ReportFormat::Text => "text",
+            ReportFormat::Markdown => "markdown",
+            ReportFormat::Json => "json",
+        }
+    }
+
+    /// Parse a format name, ignoring case and surrounding whitespace
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Self::ALL
+            .into_iter()
+            .find(|f| f.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Results for one character
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CharacterLine {
+    pub display_name: String,
+    pub runs: i32,
+    pub wins: i32,
+    pub win_rate: f64,
+    pub heart_kills: i32,
+    pub highest_ascension_win: Option<i32>,
+}
+
+/// Overall and per-character results over the visible runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsReport {
+    pub runs: i32,
+    pub wins: i32,
+    /// `wins / runs`, or 0 without runs
+    pub win_rate: f64,
+    pub heart_kills: i32,
+    /// Characters with at least one run, in game order
+    pub characters: Vec<CharacterLine>,
+}
+
+impl StatsReport {
+    /// Summarize runs, ignoring hidden ones
+    pub fn from_runs(runs: &[RunMetrics]) -> Self {
+        let visible: Vec<&RunMetrics> = runs.iter().filter(|r| !r.hidden).collect();
+        let wins = visible.iter().filter(|r| r.victory).count() as i32;
+        Self {
+            runs: visible.len() as i32,
+            wins,
+            win_rate: rate(wins, visible.len() as i32),
+            heart_kills: visible.iter().filter(|r| keys::killed_heart(r)).count() as i32,
+            characters: calculate_character_stats(runs)
+                .into_iter()
+                .map(|s| CharacterLine {
+                    display_name: s.display_name,
+                    runs: s.total_runs,
+                    wins: s.wins,
+                    win_rate: s.win_rate,
+                    heart_kills: s.heart_kills,
+                    highest_ascension_win: s.highest_ascension_win,
+                })
+                .collect(),
+        }
+    }
+
+    /// Render the report in a format
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.to_text(),
+            ReportFormat::Markdown => self.to_markdown(),
+            ReportFormat::Json => {
+                serde_json::to_string_pretty(self).expect("report serializes to JSON")
+            }
+        }
+    }
+
+    /// One line of totals
+    fn headline(&self) -> String {
+        format!(
+            "{} runs, {} wins ({}), {} heart kills",
+            self.runs,
+            self.wins,
+            percent(self.win_rate),
+            self.heart_kills
+        )
+    }
+
+    /// Rows of the character table, as text cells
+    fn rows(&self) -> Vec<[String; 6]> {
+        self.characters
+            .iter()
+            .map(|c| {
+                [
+                    c.display_name.clone(),
+                    c.runs.to_string(),
+                    c.wins.to_string(),
+                    percent(c.win_rate),
+                    c.heart_kills.to_string(),
+                    c.highest_ascension_win
+                        .map_or_else(|| "-".to_string(), |a| format!("A{}", a)),
+                ]
+            })
+            .collect()
+    }
+
+    /// Compact summary with aligned columns
+    fn to_text(&self) -> String {
+        let header = HEADERS.map(str::to_string);
+        let rows = self.rows();
+        let mut widths = HEADERS.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let mut out = format!("Slay the Spire stats: {}\n", self.headline());
+        if rows.is_empty() {
+            return out;
+        }
+        out.push('\n');
+        for row in std::iter::once(&header).chain(&rows) {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .enumerate()
+                .map(|(i, (cell, width))| {
+                    // Names are left-aligned, numbers right-aligned
+                    if i == 0 {
+                        format!("{:<width$}", cell)
+                    } else {
+                        format!("{:>width$}", cell)
+                    }
+                })
+                .collect();
+            out.push_str(cells.join("  ").trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Heading, totals and a table of characters
+    fn to_markdown(&self) -> String {
+        let mut out = format!("## Slay the Spire stats\n\n{}\n", self.headline());
+        if self.characters.is_empty() {
+            return out;
+        }
+        out.push_str(&format!("\n| {} |\n", HEADERS.join(" | ")));
+        out.push_str("|---|---:|---:|---:|---:|---:|\n");
+        for row in self.rows() {
+            out.push_str(&format!("| {} |\n", row.join(" | ")));
+        }
+        out
+    }
+}
+
+/// Column headings of the character table
+const HEADERS: [&str; 6] = [
+    "Character",
+    "Runs",
+    "Wins",
+    "Win rate",
+    "Heart kills",
+    "Best ascension",
+];
+
+fn rate(wins: i32, runs: i32) -> f64 {
+    if runs > 0 {
+        wins as f64 / runs as f64
+    } else {
+        0.0
+    }
+}
+
+/// A ratio as a percentage with one decimal, e.g. `37.5%`
+pub(super) fn percent(ratio: f64) -> String {
+    format!("{:.1}%", ratio * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Character;
+
+    const TEXT: &str = include_str!("../../tests/fixtures/reports/stats.txt");
+    const MARKDOWN: &str = include_str!("../../tests/fixtures/reports/stats.md");
+
+    fn runs() -> Vec<RunMetrics> {
+        let mut runs = Vec::new();
+        for i in 0..8 {
+            let mut run = sample_run(&format!("ic{i}"), Character::Ironclad, i < 3);
+            run.ascension_level = 15 + i;
+            runs.push(run);
+        }
+        let mut heart = sample_run("heart", Character::Ironclad, true);
+        heart.floor_reached = 57;
+        heart.ascension_level = 20;
+        runs.push(heart);
+        for i in 0..3 {
+            runs.push(sample_run(&format!("w{i}"), Character::Watcher, false));
+        }
+        let mut hidden = sample_run("hidden", Character::Watcher, true);
+        hidden.hidden = true;
+        runs.push(hidden);
+        runs
+    }
+
+    #[test]
+    fn test_text_snapshot() {
+        let report = StatsReport::from_runs(&runs());
+        assert_eq!(report.render(ReportFormat::Text), TEXT);
+    }
+
+    #[test]
+    fn test_markdown_snapshot() {
+        let report = StatsReport::from_runs(&runs());
+        assert_eq!(report.render(ReportFormat::Markdown), MARKDOWN);
+    }
+
+    #[test]
+    fn test_json_report() {
+        let report = StatsReport::from_runs(&runs());
+        let json = report.render(ReportFormat::Json);
+        let parsed: StatsReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+        assert_eq!((parsed.runs, parsed.wins, parsed.heart_kills), (12, 4, 1));
+    }
+
+    #[test]
+    fn test_empty_report() {
+        let report = StatsReport::from_runs(&[]);
+        assert_eq!(
+            report.render(ReportFormat::Text),
+            "Slay the Spire stats: 0 runs, 0 wins (0.0%), 0 heart kills\n"
+        );
+    }
+
+    #[test]
+    fn test_format_names() {
+        for format in ReportFormat::ALL {
+            assert_eq!(ReportFormat::from_name(format.name()), Some(format));
+        }
+        assert_eq!(
+            ReportFormat::from_name(" Markdown "),
+            Some(ReportFormat::Markdown)
+        );
+        assert_eq!(ReportFormat::from_name("csv"), None);
+    }
+}
@@ -0,0 +1,202 @@
+//! Card reward choices and skip rate
+//!
+//! Each `card_choices` entry is one card reward screen. Taking nothing is
+//! recorded as `SKIP`; taking the Singing Bowl's max HP instead is recorded
+//! as `Singing Bowl` and counts as a skip too, though it is also tallied on
+//! its own. Files from before `card_choices` existed have no reward data and
+//! are left out of skip rates entirely.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use super::{act_for_floor, RunMetrics};
+
+/// `picked` value for a skipped card reward
+pub const SKIP: &str = "SKIP";
+
+/// `picked` value for taking the Singing Bowl bonus instead of a card
+pub const SINGING_BOWL: &str = "Singing Bowl";
+
+/// Card rewards offered and skipped in one act of a run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ActRewards {
+    pub act: i32,
+    pub offered: i32,
+    /// Skips, including Singing Bowl picks
+    pub skipped: i32,
+}
+
+/// Card reward counts for one run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RewardSummary {
+    pub offered: i32,
+    pub skipped: i32,
+    pub singing_bowl: i32,
+    pub picked: i32,
+    pub by_act: Vec<ActRewards>,
+}
+
+/// Summarize card reward choices given as `(floor, picked)` pairs
+pub fn summarize_choices<'a>(
+    choices: impl IntoIterator<Item = (Option<i32>, Option<&'a str>)>,
+) -> RewardSummary {
+    let mut summary = RewardSummary::default();
+    let mut by_act: BTreeMap<i32, ActRewards> = BTreeMap::new();
+    for (floor, picked) in choices {
+        let skipped = matches!(picked, Some(SKIP) | Some(SINGING_BOWL));
+        summary.offered += 1;
+        summary.skipped += i32::from(skipped);
+        summary.singing_bowl += i32::from(picked == Some(SINGING_BOWL));
+        summary.picked += i32::from(picked.is_some() && !skipped);
+
+        if let Some(floor) = floor {
+            let act = act_for_floor(floor);
+            let entry = by_act.entry(act).or_insert_with(|| ActRewards {
+                act,
+                ..ActRewards::default()
+            });
+            entry.offered += 1;
+            entry.skipped += i32::from(skipped);
+        }
+    }
+    summary.by_act = by_act.into_values().collect();
+    summary
+}
+
+/// Skip rate for a group of card rewards
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SkipRate {
+    pub offered: i32,
+    pub skipped: i32,
+    pub skip_rate: f64,
+}
+
+impl SkipRate {
+    fn add(&mut self, offered: i32, skipped: i32) {
+        self.offered += offered;
+        self.skipped += skipped;
+        self.skip_rate = if self.offered > 0 {
+            self.skipped as f64 / self.offered as f64
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Skip rate for one act
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ActSkipRate {
+    pub act: i32,
+    #[serde(flatten)]
+    pub rate: SkipRate,
+}
+
+/// Card reward skip rates across runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SkipRateStats {
+    /// Runs with card reward data
+    pub runs: i32,
+    /// Runs left out because the file has no card reward data
+    pub runs_without_data: i32,
+    /// All card rewards
+    pub overall: SkipRate,
+    /// Card rewards in winning runs
+    pub wins: SkipRate,
+    /// Card rewards in lost runs
+    pub losses: SkipRate,
+    /// Card rewards by act, earliest first
+    pub by_act: Vec<ActSkipRate>,
+    /// Rewards where the Singing Bowl bonus was taken (included in skips)
+    pub singing_bowl_picks: i32,
+}
+
+impl SkipRateStats {
+    /// Aggregate skip rates, ignoring hidden runs
+    pub fn from_runs(runs: &[RunMetrics]) -> Self {
+        let mut stats = Self::default();
+        let mut by_act: BTreeMap<i32, SkipRate> = BTreeMap::new();
+        for run in runs.iter().filter(|r| !r.hidden) {
+            let (Some(offered), Some(skipped)) = (run.rewards_offered, run.rewards_skipped) else {
+                stats.runs_without_data += 1;
+                continue;
+            };
+            stats.runs += 1;
+            stats.overall.add(offered, skipped);
+            if run.victory {
+                stats.wins.add(offered, skipped);
+            } else {
+                stats.losses.add(offered, skipped);
+            }
+            stats.singing_bowl_picks += run.singing_bowl_picks;
+            for act in &run.rewards_by_act {
+                by_act
+                    .entry(act.act)
+                    .or_default()
+                    .add(act.offered, act.skipped);
+            }
+        }
+        stats.by_act = by_act
+            .into_iter()
+            .map(|(act, rate)| ActSkipRate { act, rate })
+            .collect();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::{Character, RunFileBuilder, RunsDirFixture};
+
+    fn load_fixture() -> RunMetrics {
+        let fixture = RunsDirFixture::new();
+        fixture.add_raw(
+            Character::Defect,
+            "choices.run",
+            include_bytes!("../../tests/fixtures/runs/card_choices.run"),
+        );
+        fixture.add(&RunFileBuilder::new("old").character(Character::Defect));
+
+        let mut runs = fixture.load_sorted();
+        let old = runs.pop().unwrap();
+        assert_eq!(old.play_id, "old");
+        assert_eq!(old.rewards_offered, None);
+        runs.pop().unwrap()
+    }
+
+    #[test]
+    fn test_fixture_reward_counts() {
+        let run = load_fixture();
+        assert_eq!(run.rewards_offered, Some(6));
+        assert_eq!(run.rewards_skipped, Some(3));
+        assert_eq!(run.singing_bowl_picks, 1);
+        assert_eq!(run.cards_from_rewards, 3);
+        let acts: Vec<(i32, i32, i32)> = run
+            .rewards_by_act
+            .iter()
+            .map(|a| (a.act, a.offered, a.skipped))
+            .collect();
+        assert_eq!(acts, vec![(1, 3, 1), (2, 2, 1), (3, 1, 1)]);
+    }
+
+    #[test]
+    fn test_runs_without_data_are_excluded() {
+        let with_data = load_fixture();
+        let mut old = with_data.clone();
+        old.play_id = "old".to_string();
+        old.victory = false;
+        old.rewards_offered = None;
+        old.rewards_skipped = None;
+
+        let stats = SkipRateStats::from_runs(&[with_data, old]);
+        assert_eq!(stats.runs, 1);
+        assert_eq!(stats.runs_without_data, 1);
+        assert_eq!(stats.overall.skip_rate, 0.5);
+        assert_eq!(stats.wins.offered, 6);
+        assert_eq!(stats.losses, SkipRate::default());
+        assert_eq!(stats.by_act.len(), 3);
+        assert_eq!(stats.by_act[2].rate.skip_rate, 1.0);
+        assert_eq!(stats.singing_bowl_picks, 1);
+    }
+}
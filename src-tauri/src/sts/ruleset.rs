@@ -0,0 +1,150 @@
+//! Rules a run was played under
+//!
+//! Run files record the game mode as separate flags (`is_daily`,
+//! `chose_seed`, `is_endless`, `is_trial`) that can be combined, e.g. a
+//! custom run on a chosen seed. Each run is classified into one [`Ruleset`]
+//! so filters and exclusions make a single check. When several flags are set
+//! the mode that changes the game the most wins:
+//!
+//! 1. `Daily`: the Daily Climb fixes the seed, character and modifiers
+//! 2. `Custom`: custom runs (`is_trial`) pick their own modifiers
+//! 3. `Endless`: Endless mode keeps going after the Heart
+//! 4. `Seeded`: a chosen seed only fixes the random numbers
+//! 5. `Standard`: none of the above
+//!
+//! The raw flags stay on [`RunMetrics`] for the detail view.
+//!
+//! [`RunMetrics`]: super::RunMetrics
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Game mode a run was played in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Ruleset {
+    /// An ordinary run on a random seed
+    #[default]
+    Standard,
+    /// A run on a seed the player chose
+    Seeded,
+    /// A Daily Climb run
+    Daily,
+    /// An Endless mode run
+    Endless,
+    /// A custom run with modifiers the player picked
+    Custom,
+}
+
+impl Ruleset {
+    /// Every ruleset, in documentation order
+    pub const ALL: [Ruleset; 5] = [
+        Ruleset::Standard,
+        Ruleset::Seeded,
+        Ruleset::Daily,
+        Ruleset::Endless,
+        Ruleset::Custom,
+    ];
+
+    /// Name used in query parameters and JSON
+    pub fn name(self) -> &'static str {
+        match self {
+            Ruleset::Standard => "standard",
+            Ruleset::Seeded => "seeded",
+            Ruleset::Daily => "daily",
+            Ruleset::Endless => "endless",
+            Ruleset::Custom => "custom",
+        }
+    }
+
+    /// Parse a ruleset name, ignoring case and surrounding whitespace
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim();
+        Self::ALL
+            .into_iter()
+            .find(|r| r.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Classify a run from its mode flags, see the module docs for the
+    /// precedence
+    pub fn from_flags(is_daily: bool, is_trial: bool, is_endless: bool, seeded: bool) -> Self {
+        if is_daily {
+            Ruleset::Daily
+        } else if is_trial {
+            Ruleset::Custom
+        } else if is_endless {
+            Ruleset::Endless
+        } else if seeded {
+            Ruleset::Seeded
+        } else {
+            Ruleset::Standard
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ruleset_names() {
+        for ruleset in Ruleset::ALL {
+            assert_eq!(Ruleset::from_name(ruleset.name()), Some(ruleset));
+            assert_eq!(serde_json::to_value(ruleset).unwrap(), ruleset.name());
+        }
+        assert_eq!(Ruleset::from_name(" Daily "), Some(Ruleset::Daily));
+        assert_eq!(Ruleset::from_name("dailies"), None);
+    }
+
+    #[test]
+    fn test_single_flags() {
+        assert_eq!(
+            Ruleset::from_flags(false, false, false, false),
+            Ruleset::Standard
+        );
+        assert_eq!(
+            Ruleset::from_flags(true, false, false, false),
+            Ruleset::Daily
+        );
+        assert_eq!(
+            Ruleset::from_flags(false, true, false, false),
+            Ruleset::Custom
+        );
+        assert_eq!(
+            Ruleset::from_flags(false, false, true, false),
+            Ruleset::Endless
+        );
+        assert_eq!(
+            Ruleset::from_flags(false, false, false, true),
+            Ruleset::Seeded
+        );
+    }
+
+    #[test]
+    fn test_precedence_of_combined_flags() {
+        let flags = |bits: u8| [4, 2, 1].map(|bit| bits & bit != 0);
+        // Daily wins over every other flag
+        for bits in 0..8 {
+            let [trial, endless, seeded] = flags(bits);
+            assert_eq!(
+                Ruleset::from_flags(true, trial, endless, seeded),
+                Ruleset::Daily
+            );
+        }
+        // Then custom, over endless and seeded
+        for bits in 0..4 {
+            let [_, endless, seeded] = flags(bits);
+            assert_eq!(
+                Ruleset::from_flags(false, true, endless, seeded),
+                Ruleset::Custom
+            );
+        }
+        // Then endless, over seeded
+        for seeded in [false, true] {
+            assert_eq!(
+                Ruleset::from_flags(false, false, true, seeded),
+                Ruleset::Endless
+            );
+        }
+    }
+}
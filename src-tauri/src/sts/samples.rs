@@ -0,0 +1,62 @@
+//! Sample run files bundled with the crate
+//!
+//! Real-shaped `.run` files for tests and demos: one per character, covering
+//! a victory, a heart kill, an old build that writes every number as a float
+//! and lacks `card_choices`, a file missing most arrays, and one corrupted
+//! file that can't be repaired. Available with the `sample-data` feature (and
+//! always in tests).
+
+use std::io;
+use std::path::Path;
+
+use super::Character;
+
+/// A bundled run file
+#[derive(Debug, Clone, Copy)]
+pub struct SampleRun {
+    /// Character folder the file belongs in
+    pub character: Character,
+    pub file_name: &'static str,
+    pub contents: &'static [u8],
+}
+
+/// The bundled run files
+pub const SAMPLE_RUNS: &[SampleRun] = &[
+    SampleRun {
+        character: Character::Ironclad,
+        file_name: "1700000000.run",
+        contents: include_bytes!("../../tests/fixtures/sample_runs/IRONCLAD/1700000000.run"),
+    },
+    SampleRun {
+        character: Character::Ironclad,
+        file_name: "corrupted.run",
+        contents: include_bytes!("../../tests/fixtures/sample_runs/IRONCLAD/corrupted.run"),
+    },
+    SampleRun {
+        character: Character::TheSilent,
+        file_name: "1700500000.run",
+        contents: include_bytes!("../../tests/fixtures/sample_runs/THE_SILENT/1700500000.run"),
+    },
+    SampleRun {
+        character: Character::Defect,
+        file_name: "1550000000.run",
+        contents: include_bytes!("../../tests/fixtures/sample_runs/DEFECT/1550000000.run"),
+    },
+    SampleRun {
+        character: Character::Watcher,
+        file_name: "1650000000.run",
+        contents: include_bytes!("../../tests/fixtures/sample_runs/WATCHER/1650000000.run"),
+    },
+];
+
+/// Write the sample runs into `dir`, laid out like a game runs folder
+///
+/// Returns the number of files written.
+pub fn install_sample_runs(dir: &Path) -> io::Result<usize> {
+    for sample in SAMPLE_RUNS {
+        let char_dir = dir.join(sample.character.dir_name());
+        std::fs::create_dir_all(&char_dir)?;
+        std::fs::write(char_dir.join(sample.file_name), sample.contents)?;
+    }
+    Ok(SAMPLE_RUNS.len())
+}
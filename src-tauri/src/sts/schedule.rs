@@ -0,0 +1,216 @@
+//! Results by weekday and time of day
+//!
+//! Runs are placed by their local end time, see [`TimeBucketing`].
+
+use chrono::{Datelike, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{RunMetrics, TimeBucketing};
+
+/// Hours in each time-of-day block
+pub const HOURS_PER_BLOCK: u32 = 4;
+
+/// Buckets with fewer runs than this are flagged by default
+pub const DEFAULT_MIN_BUCKET_RUNS: i32 = 10;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Results for one weekday or time-of-day block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TimeBucket {
+    /// Weekday (e.g. "Monday") or block (e.g. "04:00-08:00")
+    pub label: String,
+    pub runs: i32,
+    pub wins: i32,
+    /// `wins / runs`, or 0 without runs
+    pub win_rate: f64,
+    /// Fewer runs than the minimum, so the win rate says little
+    pub low_confidence: bool,
+}
+
+/// Results by when the runs were played
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PlayTimeStats {
+    /// Runs placed in a bucket
+    pub runs: i32,
+    /// Runs without `local_time` or a timestamp
+    pub runs_without_time: i32,
+    /// Runs a bucket needs to not be flagged low-confidence
+    pub min_runs: i32,
+    /// Monday first
+    pub by_weekday: Vec<TimeBucket>,
+    /// Four-hour blocks starting at midnight
+    pub by_time_of_day: Vec<TimeBucket>,
+}
+
+fn bucket(label: String, runs: i32, wins: i32, min_runs: i32) -> TimeBucket {
+    TimeBucket {
+        label,
+        runs,
+        wins,
+        win_rate: if runs > 0 {
+            wins as f64 / runs as f64
+        } else {
+            0.0
+        },
+        low_confidence: runs < min_runs,
+    }
+}
+
+impl PlayTimeStats {
+    /// Bucket runs by local weekday and time of day, ignoring hidden runs
+    pub fn from_runs(runs: &[RunMetrics], bucketing: TimeBucketing, min_runs: i32) -> Self {
+        let blocks = (24 / HOURS_PER_BLOCK) as usize;
+        let mut weekdays = [(0, 0); 7];
+        let mut times = vec![(0, 0); blocks];
+        let mut counted = 0;
+        let mut runs_without_time = 0;
+        for run in runs.iter().filter(|r| !r.hidden) {
+            let Some(time) = bucketing.local_time(run) else {
+                runs_without_time += 1;
+                continue;
+            };
+            counted += 1;
+            let day = &mut weekdays[time.weekday().num_days_from_monday() as usize];
+            day.0 += 1;
+            day.1 += i32::from(run.victory);
+            let block = &mut times[(time.hour() / HOURS_PER_BLOCK) as usize];
+            block.0 += 1;
+            block.1 += i32::from(run.victory);
+        }
+
+        Self {
+            runs: counted,
+            runs_without_time,
+            min_runs,
+            by_weekday: WEEKDAYS
+                .iter()
+                .zip(weekdays)
+                .map(|(day, (runs, wins))| {
+                    bucket(weekday_name(*day).to_string(), runs, wins, min_runs)
+                })
+                .collect(),
+            by_time_of_day: times
+                .into_iter()
+                .enumerate()
+                .map(|(i, (runs, wins))| {
+                    let start = i as u32 * HOURS_PER_BLOCK;
+                    let label = format!("{:02}:00-{:02}:00", start, start + HOURS_PER_BLOCK);
+                    bucket(label, runs, wins, min_runs)
+                })
+                .collect(),
+        }
+    }
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{Character, RunFileBuilder, RunsDirFixture};
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    // 2024-01-01 was a Monday
+    const MONDAY_2330_UTC: i64 = 1_704_151_800;
+    const MONDAY_0100_UTC: i64 = 1_704_070_800;
+
+    fn run_at(id: &str, timestamp: i64, victory: bool) -> RunMetrics {
+        let mut run = sample_run(id, Character::Ironclad, victory);
+        run.timestamp = Some(timestamp);
+        run
+    }
+
+    fn tz(offset: i32) -> TimeBucketing {
+        TimeBucketing::new(offset).unwrap()
+    }
+
+    fn find<'a>(buckets: &'a [TimeBucket], label: &str) -> &'a TimeBucket {
+        buckets.iter().find(|b| b.label == label).unwrap()
+    }
+
+    #[test]
+    fn test_positive_offset_crosses_midnight() {
+        // 23:30 UTC on Monday is 00:30 on Tuesday at UTC+1
+        let runs = [run_at("a", MONDAY_2330_UTC, true)];
+        let stats = PlayTimeStats::from_runs(&runs, tz(60), 1);
+        assert_eq!(find(&stats.by_weekday, "Tuesday").runs, 1);
+        assert_eq!(find(&stats.by_time_of_day, "00:00-04:00").wins, 1);
+
+        let utc = PlayTimeStats::from_runs(&runs, tz(0), 1);
+        assert_eq!(find(&utc.by_weekday, "Monday").runs, 1);
+        assert_eq!(find(&utc.by_time_of_day, "20:00-24:00").runs, 1);
+    }
+
+    #[test]
+    fn test_negative_offset_crosses_midnight() {
+        // 01:00 UTC on Monday is 23:00 on Sunday at UTC-2
+        let runs = [run_at("a", MONDAY_0100_UTC, false)];
+        let stats = PlayTimeStats::from_runs(&runs, tz(-120), 1);
+        assert_eq!(find(&stats.by_weekday, "Sunday").runs, 1);
+        assert_eq!(find(&stats.by_time_of_day, "20:00-24:00").runs, 1);
+        assert_eq!(find(&stats.by_time_of_day, "20:00-24:00").win_rate, 0.0);
+    }
+
+    #[test]
+    fn test_local_time_wins_over_timestamp() {
+        let mut run = run_at("a", MONDAY_0100_UTC, true);
+        run.local_time = NaiveDateTime::parse_from_str("20240106093000", "%Y%m%d%H%M%S").ok();
+        let mut untimed = sample_run("b", Character::Ironclad, false);
+        untimed.timestamp = None;
+
+        let stats = PlayTimeStats::from_runs(&[run, untimed], tz(-120), 1);
+        assert_eq!((stats.runs, stats.runs_without_time), (1, 1));
+        assert_eq!(find(&stats.by_weekday, "Saturday").runs, 1);
+        assert_eq!(find(&stats.by_time_of_day, "08:00-12:00").runs, 1);
+    }
+
+    #[test]
+    fn test_parse_local_time() {
+        let expected = NaiveDate::from_ymd_opt(2024, 1, 6).and_then(|d| d.and_hms_opt(9, 30, 0));
+        let fixture = RunsDirFixture::new();
+        fixture.add(&RunFileBuilder::new("a").local_time(expected.unwrap()));
+        fixture.add(&RunFileBuilder::new("b").set("local_time", "yesterday"));
+
+        let runs = fixture.load_sorted();
+        assert_eq!(runs[0].local_time, expected);
+        assert_eq!(runs[1].local_time, None);
+    }
+
+    #[test]
+    fn test_low_confidence_buckets_are_kept() {
+        let runs: Vec<RunMetrics> = (0..3)
+            .map(|i| run_at(&i.to_string(), MONDAY_0100_UTC, i == 0))
+            .collect();
+        let stats = PlayTimeStats::from_runs(&runs, tz(0), 3);
+        assert_eq!(stats.by_weekday.len(), 7);
+        assert_eq!(stats.by_time_of_day.len(), 6);
+
+        let monday = find(&stats.by_weekday, "Monday");
+        assert!(!monday.low_confidence);
+        assert!((monday.win_rate - 1.0 / 3.0).abs() < 1e-9);
+        let tuesday = find(&stats.by_weekday, "Tuesday");
+        assert_eq!(tuesday.runs, 0);
+        assert!(tuesday.low_confidence);
+    }
+}
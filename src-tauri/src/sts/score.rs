@@ -0,0 +1,352 @@
+//! Score breakdown
+//!
+//! Run files only store the final score, so the breakdown is rebuilt from the
+//! parsed run using the game's scoring rules. Some inputs aren't in the file
+//! at all: the game counts every enemy slain, but `damage_taken` lists fights,
+//! so a fight against three Louses counts as one kill here. Components built
+//! from incomplete data are marked as estimates, and bonuses that can't be
+//! derived (perfect fights, gold, card rarity) are listed in
+//! [`ScoreBreakdown::not_counted`].
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::bosses::{fatal, find_boss};
+use super::{act_for_floor, keys, Character, RunMetrics};
+
+/// Points per floor climbed
+pub const FLOOR_POINTS: i32 = 5;
+
+/// Points per enemy slain
+pub const ENEMY_POINTS: i32 = 2;
+
+/// Points per elite slain in acts 1, 2 and 3 (Act 4 elites count as Act 3)
+pub const ELITE_POINTS: [i32; 3] = [10, 20, 30];
+
+/// Points for the first boss slain; each further boss is worth this much more
+pub const BOSS_POINTS: i32 = 50;
+
+/// Points for killing the Corrupt Heart
+pub const HEARTBREAKER_POINTS: i32 = 250;
+
+/// Score bonus per ascension level, in percent
+pub const ASCENSION_BONUS_PERCENT: i32 = 5;
+
+/// Bonuses the run file has no data for
+pub const NOT_COUNTED: &[&str] = &[
+    "Champion and Perfect (fights without taking damage)",
+    "Gold collected",
+    "Card rarity and duplicate bonuses",
+    "Combo, Overkill and speed bonuses",
+];
+
+/// Threshold bonuses: (name, points, minimum)
+const MYSTERY_MACHINE: (&str, i32, i32) = ("Mystery Machine", 25, 15);
+const LIBRARIAN: (&str, i32, i32) = ("Librarian", 25, 35);
+const ENCYCLOPEDIAN: (&str, i32, i32) = ("Encyclopedian", 50, 50);
+const SHINY: (&str, i32, i32) = ("Shiny", 50, 25);
+const WELL_FED: (&str, i32, i32) = ("Well Fed", 25, 15);
+const STUFFED: (&str, i32, i32) = ("Stuffed", 50, 30);
+
+/// Ascension level from which characters start with less max HP
+const LOWER_MAX_HP_ASCENSION: i32 = 14;
+
+/// Max HP a character starts a run with
+pub fn starting_max_hp(character: Character, ascension_level: i32) -> i32 {
+    let (base, ascension_loss) = match character {
+        Character::Ironclad => (80, 5),
+        Character::TheSilent => (70, 4),
+        Character::Defect => (75, 4),
+        Character::Watcher => (72, 4),
+    };
+    if ascension_level >= LOWER_MAX_HP_ASCENSION {
+        base - ascension_loss
+    } else {
+        base
+    }
+}
+
+/// One line of a score breakdown
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ScoreComponent {
+    pub name: String,
+    pub points: i32,
+    /// Whether the points are a guess from incomplete data
+    pub estimated: bool,
+}
+
+impl ScoreComponent {
+    fn new(name: impl Into<String>, points: i32, estimated: bool) -> Self {
+        Self {
+            name: name.into(),
+            points,
+            estimated,
+        }
+    }
+}
+
+/// Where a run's score came from, as far as the run file tells
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ScoreBreakdown {
+    /// Points before the ascension bonus; bonuses the run didn't earn are left out
+    pub components: Vec<ScoreComponent>,
+    /// Ascension bonus on top of the components
+    pub ascension_bonus: i32,
+    /// Components plus the ascension bonus
+    pub estimated_score: i32,
+    /// Score the game recorded
+    pub recorded_score: i32,
+    /// Whether any component is an estimate
+    pub estimated: bool,
+    /// Bonuses that may be part of the recorded score but can't be derived
+    pub not_counted: Vec<String>,
+}
+
+impl ScoreBreakdown {
+    /// Rebuild the score of a run
+    pub fn estimate(run: &RunMetrics) -> Self {
+        // Without fights the combat components can't be rebuilt
+        let no_fights = run.encounters.is_empty() && run.floor_reached > 1;
+        let won: Vec<_> = run.encounters.iter().filter(|e| !fatal(run, e)).collect();
+
+        let mut components = vec![
+            ScoreComponent::new(
+                "Floors climbed",
+                run.floor_reached.saturating_mul(FLOOR_POINTS),
+                false,
+            ),
+            ScoreComponent::new(
+                "Enemies slain",
+                (won.len() as i32).saturating_mul(ENEMY_POINTS),
+                true,
+            ),
+        ];
+
+        let mut elites = [0i32; 3];
+        for encounter in won.iter().filter(|e| e.elite) {
+            let act = act_for_floor(encounter.floor).min(3);
+            elites[act as usize - 1] = elites[act as usize - 1].saturating_add(1);
+        }
+        for (act, (kills, points)) in elites.iter().zip(ELITE_POINTS).enumerate() {
+            components.push(ScoreComponent::new(
+                format!("Act {} elites", act + 1),
+                kills.saturating_mul(points),
+                no_fights,
+            ));
+        }
+
+        let bosses = won
+            .iter()
+            .filter(|e| find_boss(&e.enemies).is_some())
+            .count() as i32;
+        // 50 for the first boss, 100 for the second, and so on
+        let boss_points = (1..=bosses)
+            .map(|n| n.saturating_mul(BOSS_POINTS))
+            .fold(0, i32::saturating_add);
+        components.push(ScoreComponent::new("Bosses slain", boss_points, no_fights));
+
+        if keys::killed_heart(run) {
+            components.push(ScoreComponent::new(
+                "Heartbreaker",
+                HEARTBREAKER_POINTS,
+                false,
+            ));
+        }
+
+        let mut threshold = |(name, points, minimum): (&str, i32, i32), value: i32, estimated| {
+            if value >= minimum {
+                components.push(ScoreComponent::new(name, points, estimated));
+                true
+            } else {
+                false
+            }
+        };
+        threshold(MYSTERY_MACHINE, run.question_rooms, false);
+        if !threshold(ENCYCLOPEDIAN, run.deck_size, false) {
+            threshold(LIBRARIAN, run.deck_size, false);
+        }
+        threshold(SHINY, run.relic_count, false);
+        if let Some(character) = run.character.known() {
+            // Only the final max HP is known, so gains can't be told from Neow
+            // swaps that lowered it first
+            let gained = run
+                .max_hp_at_end
+                .saturating_sub(starting_max_hp(character, run.ascension_level));
+            if !threshold(STUFFED, gained, true) {
+                threshold(WELL_FED, gained, true);
+            }
+        }
+
+        let subtotal = components
+            .iter()
+            .map(|c| c.points)
+            .fold(0, i32::saturating_add);
+        let ascension_bonus =
+            (subtotal as f64 * run.ascension_level as f64 * ASCENSION_BONUS_PERCENT as f64 / 100.0)
+                .round() as i32;
+        Self {
+            estimated: components.iter().any(|c| c.estimated),
+            estimated_score: subtotal.saturating_add(ascension_bonus),
+            ascension_bonus,
+            recorded_score: run.score,
+            components,
+            not_counted: NOT_COUNTED.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// A run with its score breakdown, as returned by the single-run endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RunDetail {
+    #[serde(flatten)]
+    pub run: RunMetrics,
+    pub score_breakdown: ScoreBreakdown,
+}
+
+impl From<RunMetrics> for RunDetail {
+    fn from(run: RunMetrics) -> Self {
+        Self {
+            score_breakdown: ScoreBreakdown::estimate(&run),
+            run,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Encounter;
+
+    fn fight(enemies: &str, floor: i32, elite: bool) -> Encounter {
+        Encounter {
+            enemies: enemies.to_string(),
+            floor,
+            hp_after: None,
+            elite,
+            turns: None,
+        }
+    }
+
+    fn points(breakdown: &ScoreBreakdown, name: &str) -> Option<i32> {
+        breakdown
+            .components
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.points)
+    }
+
+    #[test]
+    fn test_starting_max_hp() {
+        assert_eq!(starting_max_hp(Character::Ironclad, 0), 80);
+        assert_eq!(starting_max_hp(Character::Ironclad, 14), 75);
+        assert_eq!(starting_max_hp(Character::TheSilent, 20), 66);
+    }
+
+    #[test]
+    fn test_act1_death() {
+        // Died to the Act 1 boss on floor 16 at A0, with one elite kill:
+        // 16 floors (80) + 4 fights won (8) + 1 Act 1 elite (10)
+        let mut run = sample_run("a", Character::Ironclad, false);
+        run.floor_reached = 16;
+        run.ascension_level = 0;
+        run.score = 98;
+        run.max_hp_at_end = 80;
+        run.encounters = vec![
+            fight("Jaw Worm", 1, false),
+            fight("Cultist", 2, false),
+            fight("Gremlin Nob", 6, true),
+            fight("2 Louse", 9, false),
+            fight("Hexaghost", 16, false),
+        ];
+
+        let breakdown = ScoreBreakdown::estimate(&run);
+        assert_eq!(points(&breakdown, "Enemies slain"), Some(8));
+        assert_eq!(points(&breakdown, "Act 1 elites"), Some(10));
+        assert_eq!(points(&breakdown, "Bosses slain"), Some(0));
+        assert_eq!(breakdown.ascension_bonus, 0);
+        assert_eq!(breakdown.estimated_score, breakdown.recorded_score);
+        assert!(breakdown.estimated);
+    }
+
+    #[test]
+    fn test_heart_kill_at_a20() {
+        // 56 floors (280) + 8 fights won (16) + elites 10 + 20 + 30 + 30
+        // + bosses 50 + 100 + 150 + 200 + Heartbreaker (250) + Stuffed (50)
+        // = 1186, doubled at A20
+        let mut run = sample_run("b", Character::TheSilent, true);
+        run.floor_reached = 56;
+        run.ascension_level = 20;
+        run.score = 2372;
+        run.max_hp_at_end = 66 + 31;
+        run.encounters = vec![
+            fight("Lagavulin", 8, true),
+            fight("The Guardian", 16, false),
+            fight("Book of Stabbing", 25, true),
+            fight("Champ", 33, false),
+            fight("Nemesis", 40, true),
+            fight("Awakened One", 50, false),
+            fight("Shield and Spear", 54, true),
+            fight("The Heart", 55, false),
+        ];
+
+        let breakdown = ScoreBreakdown::estimate(&run);
+        // Act 4 elites score like Act 3 ones
+        assert_eq!(points(&breakdown, "Act 3 elites"), Some(60));
+        assert_eq!(points(&breakdown, "Bosses slain"), Some(500));
+        assert_eq!(points(&breakdown, "Heartbreaker"), Some(250));
+        assert_eq!(points(&breakdown, "Stuffed"), Some(50));
+        assert_eq!(points(&breakdown, "Well Fed"), None);
+        assert_eq!(breakdown.ascension_bonus, 1186);
+        assert_eq!(breakdown.estimated_score, breakdown.recorded_score);
+    }
+
+    #[test]
+    fn test_ascension_bonus_rounding() {
+        // 51 floors (255) + 3 fights won (6) + 3 bosses (300) + Librarian (25)
+        // = 586, and 35% of that at A7 is 205.1
+        let mut run = sample_run("c", Character::Defect, true);
+        run.floor_reached = 51;
+        run.ascension_level = 7;
+        run.score = 791;
+        run.max_hp_at_end = 75;
+        run.deck_size = 40;
+        run.encounters = vec![
+            fight("Slime Boss", 16, false),
+            fight("Automaton", 33, false),
+            fight("Time Eater", 50, false),
+        ];
+
+        let breakdown = ScoreBreakdown::estimate(&run);
+        assert_eq!(points(&breakdown, "Librarian"), Some(25));
+        assert_eq!(points(&breakdown, "Encyclopedian"), None);
+        assert_eq!(breakdown.ascension_bonus, 205);
+        assert!((breakdown.estimated_score - breakdown.recorded_score).abs() <= 1);
+    }
+
+    #[test]
+    fn test_missing_fights_are_estimated() {
+        let mut run = sample_run("d", Character::Watcher, false);
+        run.floor_reached = 20;
+        run.encounters.clear();
+        let breakdown = ScoreBreakdown::estimate(&run);
+        let bosses = breakdown
+            .components
+            .iter()
+            .find(|c| c.name == "Bosses slain")
+            .unwrap();
+        assert!(bosses.estimated);
+        assert!(!breakdown.not_counted.is_empty());
+    }
+
+    #[test]
+    fn test_extreme_values_saturate() {
+        let mut run = sample_run("e", Character::Ironclad, true);
+        run.floor_reached = i32::MAX;
+        run.ascension_level = 20;
+        run.max_hp_at_end = i32::MIN;
+        let breakdown = ScoreBreakdown::estimate(&run);
+        assert_eq!(points(&breakdown, "Floors climbed"), Some(i32::MAX));
+        assert_eq!(breakdown.estimated_score, i32::MAX);
+    }
+}
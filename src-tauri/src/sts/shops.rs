@@ -0,0 +1,385 @@
+//! Shop usage
+//!
+//! How much of a deck comes from shops versus card rewards, what the shops
+//! were spent on, and how often a shop was left empty-handed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use super::{act_for_floor, mean, CharacterId, RunMetrics};
+
+/// `path_per_floor` symbol of a shop room
+pub const SHOP_ROOM: &str = "$";
+
+/// Price of the first card removal at a shop
+pub const FIRST_REMOVAL_COST: i32 = 75;
+
+/// Price increase after each card removal bought
+pub const REMOVAL_COST_STEP: i32 = 25;
+
+/// Gold spent on a run's shop removals: 75, then 25 more for each one after
+///
+/// Discounts (Smiling Mask, Membership Card, The Courier) aren't known from
+/// the run file, so runs with them are overestimated.
+pub fn removal_gold(removals: i32) -> i32 {
+    (0..removals.max(0))
+        .map(|i| FIRST_REMOVAL_COST.saturating_add(i.saturating_mul(REMOVAL_COST_STEP)))
+        .fold(0, i32::saturating_add)
+}
+
+/// Share of acquired cards that were bought, or 0 when no cards were acquired
+pub fn shop_card_ratio<'a>(runs: impl IntoIterator<Item = &'a RunMetrics>) -> f64 {
+    let (bought, rewarded) = runs.into_iter().fold((0, 0), |(b, r), run| {
+        (b + run.cards_from_shops, r + run.cards_from_rewards)
+    });
+    if bought + rewarded > 0 {
+        bought as f64 / (bought + rewarded) as f64
+    } else {
+        0.0
+    }
+}
+
+/// Shops entered and left empty-handed in one act of a run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ActShops {
+    pub act: i32,
+    pub shops_visited: i32,
+    /// Shops where nothing was bought and no card was removed
+    pub shops_skipped: i32,
+}
+
+/// Shops visited and skipped per act from a run's `path_per_floor`
+///
+/// A shop counts as engaged when its floor is among `purchase_floors`
+/// (`item_purchase_floors`) or `purge_floors` (`items_purged_floors`), so a
+/// visit that only bought a card removal isn't skipped. Acts without a shop
+/// are left out.
+pub fn summarize_shops(
+    path_per_floor: &[Option<String>],
+    purchase_floors: &[i64],
+    purge_floors: &[i64],
+) -> Vec<ActShops> {
+    let mut acts: Vec<ActShops> = Vec::new();
+    for (i, room) in path_per_floor.iter().enumerate() {
+        if room.as_deref() != Some(SHOP_ROOM) {
+            continue;
+        }
+        let floor = i as i64 + 1;
+        let act = act_for_floor(floor as i32);
+        if acts.last().is_none_or(|a| a.act != act) {
+            acts.push(ActShops {
+                act,
+                ..ActShops::default()
+            });
+        }
+        let entry = acts.last_mut().expect("pushed for this act");
+        entry.shops_visited += 1;
+        let engaged = purchase_floors.contains(&floor) || purge_floors.contains(&floor);
+        entry.shops_skipped += i32::from(!engaged);
+    }
+    acts
+}
+
+/// How often shops were left empty-handed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ShopEngagement {
+    pub shops_visited: i32,
+    /// Shops where nothing was bought and no card was removed
+    pub shops_skipped: i32,
+    /// `shops_skipped / shops_visited`, or 0 without visits
+    pub empty_handed_rate: f64,
+}
+
+impl ShopEngagement {
+    fn add(&mut self, shops: &ActShops) {
+        self.shops_visited += shops.shops_visited;
+        self.shops_skipped += shops.shops_skipped;
+        self.empty_handed_rate = if self.shops_visited > 0 {
+            self.shops_skipped as f64 / self.shops_visited as f64
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Empty-handed shop rate in one act, for won and lost runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ActShopEngagement {
+    pub act: i32,
+    pub wins: ShopEngagement,
+    pub losses: ShopEngagement,
+}
+
+/// Empty-handed shop rate of one character, for won and lost runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CharacterShopEngagement {
+    pub character: CharacterId,
+    pub wins: ShopEngagement,
+    pub losses: ShopEngagement,
+}
+
+/// Aggregate shop usage across runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ShopStats {
+    /// Runs included
+    pub runs: i32,
+    /// Shops entered
+    pub shops_visited: i32,
+    /// Cards bought
+    pub cards_purchased: i32,
+    /// Relics bought
+    pub relics_purchased: i32,
+    /// Potions bought
+    pub potions_purchased: i32,
+    /// Card removals bought (the shop's purge service)
+    pub removals_purchased: i32,
+    /// Cards taken from card rewards
+    pub cards_from_rewards: i32,
+    /// Share of acquired cards that were bought rather than picked
+    pub shop_card_ratio: f64,
+    /// Shop card ratio in winning runs
+    pub shop_card_ratio_wins: f64,
+    /// Shop card ratio in lost runs
+    pub shop_card_ratio_losses: f64,
+    /// Average gold spent on card removals per run
+    pub avg_gold_on_removals: f64,
+    /// Average gold spent on card removals in winning runs
+    pub avg_gold_on_removals_wins: f64,
+    /// Average gold spent on card removals in lost runs
+    pub avg_gold_on_removals_losses: f64,
+    /// Shops left without buying anything or removing a card
+    #[serde(default)]
+    pub shops_skipped: i32,
+    /// Share of shops left empty-handed, counting only runs whose files
+    /// record purchase floors
+    #[serde(default)]
+    pub empty_handed_rate: f64,
+    /// Empty-handed shop rate in winning runs
+    #[serde(default)]
+    pub empty_handed_rate_wins: f64,
+    /// Empty-handed shop rate in lost runs
+    #[serde(default)]
+    pub empty_handed_rate_losses: f64,
+    /// Empty-handed shop rates by act, earliest first
+    #[serde(default)]
+    pub by_act: Vec<ActShopEngagement>,
+    /// Empty-handed shop rates by character
+    #[serde(default)]
+    pub by_character: Vec<CharacterShopEngagement>,
+}
+
+impl ShopStats {
+    /// Aggregate shop usage, ignoring hidden runs
+    pub fn from_runs(runs: &[RunMetrics]) -> Self {
+        let visible: Vec<&RunMetrics> = runs.iter().filter(|r| !r.hidden).collect();
+        let mut stats = Self {
+            shop_card_ratio: shop_card_ratio(visible.iter().copied()),
+            shop_card_ratio_wins: shop_card_ratio(visible.iter().copied().filter(|r| r.victory)),
+            shop_card_ratio_losses: shop_card_ratio(visible.iter().copied().filter(|r| !r.victory)),
+            avg_gold_on_removals: mean(visible.iter().map(|r| r.gold_spent_on_removals)),
+            avg_gold_on_removals_wins: mean(
+                visible
+                    .iter()
+                    .filter(|r| r.victory)
+                    .map(|r| r.gold_spent_on_removals),
+            ),
+            avg_gold_on_removals_losses: mean(
+                visible
+                    .iter()
+                    .filter(|r| !r.victory)
+                    .map(|r| r.gold_spent_on_removals),
+            ),
+            ..Self::default()
+        };
+        let mut overall = ShopEngagement::default();
+        let mut wins = ShopEngagement::default();
+        let mut losses = ShopEngagement::default();
+        let mut by_act: BTreeMap<i32, ActShopEngagement> = BTreeMap::new();
+        let mut by_character: BTreeMap<String, CharacterShopEngagement> = BTreeMap::new();
+        for run in visible {
+            for shops in &run.shops_by_act {
+                let act = by_act
+                    .entry(shops.act)
+                    .or_insert_with(|| ActShopEngagement {
+                        act: shops.act,
+                        ..ActShopEngagement::default()
+                    });
+                let character = by_character
+                    .entry(run.character.as_str().to_string())
+                    .or_insert_with(|| CharacterShopEngagement {
+                        character: run.character.clone(),
+                        wins: ShopEngagement::default(),
+                        losses: ShopEngagement::default(),
+                    });
+                overall.add(shops);
+                if run.victory {
+                    wins.add(shops);
+                    act.wins.add(shops);
+                    character.wins.add(shops);
+                } else {
+                    losses.add(shops);
+                    act.losses.add(shops);
+                    character.losses.add(shops);
+                }
+            }
+            stats.runs += 1;
+            stats.shops_visited += run.shops_visited;
+            stats.cards_purchased += run.cards_from_shops;
+            stats.relics_purchased += run.relics_from_shops;
+            stats.potions_purchased += run.potions_from_shops;
+            stats.removals_purchased += run.shop_removals;
+            stats.cards_from_rewards += run.cards_from_rewards;
+        }
+        stats.shops_skipped = overall.shops_skipped;
+        stats.empty_handed_rate = overall.empty_handed_rate;
+        stats.empty_handed_rate_wins = wins.empty_handed_rate;
+        stats.empty_handed_rate_losses = losses.empty_handed_rate;
+        stats.by_act = by_act.into_values().collect();
+        stats.by_character = by_character.into_values().collect();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::{Character, RunFileBuilder, RunsDirFixture};
+
+    #[test]
+    fn test_shop_stats() {
+        let mut win = sample_run("win", Character::Ironclad, true);
+        win.cards_from_shops = 3;
+        win.cards_from_rewards = 9;
+        win.relics_from_shops = 1;
+        win.shop_removals = 2;
+        win.gold_spent_on_removals = removal_gold(2);
+        let mut loss = sample_run("loss", Character::Ironclad, false);
+        loss.cards_from_shops = 1;
+        loss.cards_from_rewards = 1;
+        loss.potions_from_shops = 2;
+        let mut hidden = sample_run("hidden", Character::Ironclad, false);
+        hidden.cards_from_shops = 50;
+        hidden.hidden = true;
+
+        let stats = ShopStats::from_runs(&[win, loss, hidden]);
+        assert_eq!(stats.runs, 2);
+        assert_eq!(stats.cards_purchased, 4);
+        assert_eq!(stats.relics_purchased, 1);
+        assert_eq!(stats.potions_purchased, 2);
+        assert_eq!(stats.removals_purchased, 2);
+        assert_eq!(stats.cards_from_rewards, 10);
+        assert!((stats.shop_card_ratio - 4.0 / 14.0).abs() < 1e-9);
+        assert!((stats.shop_card_ratio_wins - 0.25).abs() < 1e-9);
+        assert!((stats.shop_card_ratio_losses - 0.5).abs() < 1e-9);
+        assert_eq!(stats.avg_gold_on_removals, 87.5);
+        assert_eq!(stats.avg_gold_on_removals_wins, 175.0);
+        assert_eq!(stats.avg_gold_on_removals_losses, 0.0);
+    }
+
+    #[test]
+    fn test_summarize_shops_counts_removals_as_engaged() {
+        // Shops on floors 3 (bought a card), 5 (only removed a card) and 8
+        // (nothing), then one in Act 2 on floor 20 (nothing)
+        let mut path: Vec<Option<String>> = vec![Some("M".to_string()); 20];
+        for floor in [3, 5, 8, 20] {
+            path[floor - 1] = Some(SHOP_ROOM.to_string());
+        }
+        let acts = summarize_shops(&path, &[3, 12], &[5, 9]);
+        assert_eq!(
+            acts,
+            vec![
+                ActShops {
+                    act: 1,
+                    shops_visited: 3,
+                    shops_skipped: 1,
+                },
+                ActShops {
+                    act: 2,
+                    shops_visited: 1,
+                    shops_skipped: 1,
+                },
+            ]
+        );
+        assert!(summarize_shops(&path[..2], &[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_shops_skipped_from_run_file() {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new("shops")
+                .character(Character::Watcher)
+                .path(&["M", "$", "M", "$", "?", "$"])
+                .set("item_purchase_floors", vec!["2"])
+                .set("items_purged_floors", vec![4]),
+        );
+        fixture.add(&RunFileBuilder::new("old").path(&["$"]));
+
+        let runs = fixture.load_sorted();
+        let (old, shops) = (&runs[0], &runs[1]);
+        assert_eq!(shops.shops_visited, 3);
+        assert_eq!(shops.shops_skipped, 1);
+        assert_eq!(shops.shops_by_act.len(), 1);
+        // Without purchase floors it's unknown which shops were skipped
+        assert_eq!(old.shops_visited, 1);
+        assert_eq!(old.shops_skipped, 0);
+        assert!(old.shops_by_act.is_empty());
+    }
+
+    #[test]
+    fn test_empty_handed_rates() {
+        let shops = |act: i32, shops_visited: i32, shops_skipped: i32| ActShops {
+            act,
+            shops_visited,
+            shops_skipped,
+        };
+        let mut win = sample_run("win", Character::Ironclad, true);
+        win.shops_by_act = vec![shops(1, 2, 0), shops(2, 2, 1)];
+        let mut loss = sample_run("loss", Character::Ironclad, false);
+        loss.shops_by_act = vec![shops(1, 1, 1)];
+        let mut silent = sample_run("silent", Character::TheSilent, false);
+        silent.shops_by_act = vec![shops(1, 3, 1)];
+        let mut hidden = sample_run("hidden", Character::Ironclad, true);
+        hidden.shops_by_act = vec![shops(1, 5, 5)];
+        hidden.hidden = true;
+
+        let stats = ShopStats::from_runs(&[win, loss, silent, hidden]);
+        assert_eq!(stats.shops_skipped, 3);
+        assert!((stats.empty_handed_rate - 3.0 / 8.0).abs() < 1e-9);
+        assert_eq!(stats.empty_handed_rate_wins, 0.25);
+        assert_eq!(stats.empty_handed_rate_losses, 0.5);
+
+        let acts: Vec<i32> = stats.by_act.iter().map(|a| a.act).collect();
+        assert_eq!(acts, [1, 2]);
+        assert_eq!(stats.by_act[0].wins.empty_handed_rate, 0.0);
+        assert_eq!(stats.by_act[0].losses.shops_visited, 4);
+        assert_eq!(stats.by_act[0].losses.empty_handed_rate, 0.5);
+        assert_eq!(stats.by_act[1].wins.empty_handed_rate, 0.5);
+
+        let ironclad = stats
+            .by_character
+            .iter()
+            .find(|c| c.character == Character::Ironclad)
+            .unwrap();
+        assert_eq!(ironclad.wins.shops_skipped, 1);
+        assert_eq!(ironclad.losses.empty_handed_rate, 1.0);
+        assert_eq!(stats.by_character.len(), 2);
+    }
+
+    #[test]
+    fn test_removal_gold_escalates() {
+        assert_eq!(removal_gold(0), 0);
+        assert_eq!(removal_gold(1), 75);
+        assert_eq!(removal_gold(2), 75 + 100);
+        assert_eq!(removal_gold(4), 75 + 100 + 125 + 150);
+        assert_eq!(removal_gold(100_000), i32::MAX);
+    }
+
+    #[test]
+    fn test_ratio_without_cards_is_zero() {
+        let runs = [sample_run("a", Character::Defect, false)];
+        assert_eq!(shop_card_ratio(&runs), 0.0);
+    }
+}
@@ -0,0 +1,59 @@
+//! Statistics helpers shared by the aggregates
+
+/// z value for a 95% confidence level
+pub const Z_95: f64 = 1.959_964;
+
+/// 95% Wilson score interval for `wins` out of `runs`, as `(low, high)`
+///
+/// Unlike the plain `p ± z·se` interval it stays inside [0, 1] and doesn't
+/// collapse to a point at 0% or 100%, which matters for the small samples a
+/// single character or ascension level often has. `None` without runs.
+pub fn wilson_interval(wins: i32, runs: i32) -> Option<(f64, f64)> {
+    if runs <= 0 {
+        return None;
+    }
+    let n = runs as f64;
+    let p = (wins.clamp(0, runs)) as f64 / n;
+    let z2 = Z_95 * Z_95;
+    let denominator = 1.0 + z2 / n;
+    let center = (p + z2 / (2.0 * n)) / denominator;
+    let margin = Z_95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / denominator;
+    Some(((center - margin).max(0.0), (center + margin).min(1.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_interval(wins: i32, runs: i32, low: f64, high: f64) {
+        let (l, h) = wilson_interval(wins, runs).unwrap();
+        assert!((l - low).abs() < 1e-4, "{}/{}: low {}", wins, runs, l);
+        assert!((h - high).abs() < 1e-4, "{}/{}: high {}", wins, runs, h);
+    }
+
+    #[test]
+    fn test_no_runs_has_no_interval() {
+        assert_eq!(wilson_interval(0, 0), None);
+    }
+
+    #[test]
+    fn test_known_intervals() {
+        assert_interval(1, 1, 0.2065, 1.0);
+        assert_interval(0, 1, 0.0, 0.7935);
+        assert_interval(50, 100, 0.4038, 0.5962);
+        assert_interval(9, 14, 0.3876, 0.8366);
+        assert_interval(0, 10, 0.0, 0.2775);
+    }
+
+    #[test]
+    fn test_interval_stays_in_bounds() {
+        for runs in 1..=50 {
+            for wins in 0..=runs {
+                let (low, high) = wilson_interval(wins, runs).unwrap();
+                assert!((0.0..=1.0).contains(&low) && (0.0..=1.0).contains(&high));
+                assert!(low <= wins as f64 / runs as f64 + 1e-12);
+                assert!(high >= wins as f64 / runs as f64 - 1e-12);
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Upgraded cards versus outcome
+//!
+//! Runs are grouped by how many cards in the final deck were upgraded, and by
+//! the share of the deck that was upgraded. Upgrades are also split into
+//! those smithed at campfires and those from elsewhere (events, relics,
+//! cards such as Armaments), for runs that record campfire choices.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{mean, RunMetrics};
+
+/// Ranges of upgraded card counts, as `(min, max)`; the last is open-ended
+const UPGRADE_COUNT_RANGES: [(i32, Option<i32>); 4] =
+    [(0, Some(2)), (3, Some(5)), (6, Some(9)), (10, None)];
+
+/// Ranges of upgraded share of the deck in percent, as `[min, max)`
+const UPGRADE_RATIO_RANGES: [(i32, Option<i32>); 4] =
+    [(0, Some(10)), (10, Some(20)), (20, Some(30)), (30, None)];
+
+/// Results for runs in one upgrade range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UpgradeBucket {
+    /// Range label, e.g. `3-5` or `10+` upgrades, or `10-20%` of the deck
+    pub label: String,
+    pub runs: i32,
+    pub wins: i32,
+    /// `wins / runs`, or 0 without runs
+    pub win_rate: f64,
+    /// Average floor reached (0 without runs)
+    pub avg_floor: f64,
+    /// Fewer runs than the minimum, so the win rate says little
+    pub low_confidence: bool,
+}
+
+/// Where upgrades came from, in winning and lost runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UpgradeSources {
+    /// Runs that record campfire choices
+    pub runs: i32,
+    /// Runs without campfire choices, left out of the averages
+    pub runs_without_data: i32,
+    /// Average upgrades smithed at campfires in winning runs
+    pub avg_smithed_wins: f64,
+    /// Average upgrades smithed at campfires in lost runs
+    pub avg_smithed_losses: f64,
+    /// Average upgrades from events, relics and cards in winning runs
+    pub avg_other_wins: f64,
+    /// Average upgrades from events, relics and cards in lost runs
+    pub avg_other_losses: f64,
+}
+
+/// Results by upgraded cards in the final deck
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UpgradeImpactStats {
+    /// Runs included
+    pub runs: i32,
+    /// Runs a bucket needs to not be flagged low-confidence
+    pub min_runs: i32,
+    /// By number of upgraded cards: 0-2, 3-5, 6-9 and 10+
+    pub by_upgrades: Vec<UpgradeBucket>,
+    /// By upgraded share of the deck: under 10%, 10-20%, 20-30% and 30%+
+    /// (runs with an empty deck are left out)
+    pub by_upgrade_ratio: Vec<UpgradeBucket>,
+    /// Smithed versus other upgrades
+    pub sources: UpgradeSources,
+}
+
+/// Upgrades smithed at campfires and from elsewhere, if the run records its
+/// campfire choices
+///
+/// Smithed cards can be removed or transformed later, so smithing is capped
+/// at the upgrades left in the final deck.
+pub fn upgrade_sources(run: &RunMetrics) -> Option<(i32, i32)> {
+    if run.campfires_rested.saturating_add(run.campfires_upgraded) == 0 {
+        return None;
+    }
+    let smithed = run.campfires_upgraded.min(run.upgraded_cards);
+    Some((smithed, run.upgraded_cards.saturating_sub(smithed)))
+}
+
+fn bucket<'a>(
+    label: String,
+    runs: impl IntoIterator<Item = &'a RunMetrics>,
+    min_runs: i32,
+) -> UpgradeBucket {
+    let runs: Vec<&RunMetrics> = runs.into_iter().collect();
+    let total = runs.len() as i32;
+    let wins = runs.iter().filter(|r| r.victory).count() as i32;
+    UpgradeBucket {
+        label,
+        runs: total,
+        wins,
+        win_rate: if total > 0 {
+            wins as f64 / total as f64
+        } else {
+            0.0
+        },
+        avg_floor: mean(runs.iter().map(|r| r.stats_floor())),
+        low_confidence: total < min_runs,
+    }
+}
+
+fn in_range(value: i32, (min, max): (i32, Option<i32>)) -> bool {
+    value >= min && max.is_none_or(|max| value <= max)
+}
+
+impl UpgradeImpactStats {
+    /// Bucket runs by upgraded cards, ignoring hidden runs
+    pub fn from_runs(runs: &[RunMetrics], min_runs: i32) -> Self {
+        let visible: Vec<&RunMetrics> = runs.iter().filter(|r| !r.hidden).collect();
+
+        let by_upgrades = UPGRADE_COUNT_RANGES
+            .into_iter()
+            .map(|range| {
+                let label = match range {
+                    (min, Some(max)) => format!("{}-{}", min, max),
+                    (min, None) => format!("{}+", min),
+                };
+                let runs = visible
+                    .iter()
+                    .copied()
+                    .filter(|r| in_range(r.upgraded_cards, range));
+                bucket(label, runs, min_runs)
+            })
+            .collect();
+
+        let by_upgrade_ratio = UPGRADE_RATIO_RANGES
+            .into_iter()
+            .map(|(min, max)| {
+                let label = match max {
+                    Some(max) if min == 0 => format!("<{}%", max),
+                    Some(max) => format!("{}-{}%", min, max),
+                    None => format!("{}%+", min),
+                };
+                let runs = visible.iter().copied().filter(|r| {
+                    // Compare `100 * upgraded` with `percent * deck_size` to
+                    // avoid rounding at the edges
+                    let upgraded = 100 * i64::from(r.upgraded_cards);
+                    let deck = i64::from(r.deck_size);
+                    r.deck_size > 0
+                        && upgraded >= i64::from(min) * deck
+                        && max.is_none_or(|max| upgraded < i64::from(max) * deck)
+                });
+                bucket(label, runs, min_runs)
+            })
+            .collect();
+
+        let with_sources: Vec<(bool, (i32, i32))> = visible
+            .iter()
+            .filter_map(|r| upgrade_sources(r).map(|s| (r.victory, s)))
+            .collect();
+        let average = |victory: bool, pick: fn((i32, i32)) -> i32| {
+            mean(
+                with_sources
+                    .iter()
+                    .filter(|(v, _)| *v == victory)
+                    .map(|(_, s)| pick(*s)),
+            )
+        };
+        let sources = UpgradeSources {
+            runs: with_sources.len() as i32,
+            runs_without_data: (visible.len() - with_sources.len()) as i32,
+            avg_smithed_wins: average(true, |s| s.0),
+            avg_smithed_losses: average(false, |s| s.0),
+            avg_other_wins: average(true, |s| s.1),
+            avg_other_losses: average(false, |s| s.1),
+        };
+
+        Self {
+            runs: visible.len() as i32,
+            min_runs,
+            by_upgrades,
+            by_upgrade_ratio,
+            sources,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::tests::sample_run;
+    use crate::sts::Character;
+
+    fn run_with(id: &str, upgraded: i32, smithed: i32, victory: bool) -> RunMetrics {
+        let mut run = sample_run(id, Character::Defect, victory);
+        run.deck_size = 20;
+        run.upgraded_cards = upgraded;
+        run.campfires_upgraded = smithed;
+        run.campfires_rested = 1;
+        run
+    }
+
+    #[test]
+    fn test_buckets_by_upgrades_and_ratio() {
+        let mut hidden = run_with("hidden", 12, 0, true);
+        hidden.hidden = true;
+        let runs = vec![
+            run_with("a", 0, 0, false),
+            run_with("b", 2, 1, false),
+            run_with("c", 4, 4, true),
+            run_with("d", 10, 6, true),
+            run_with("e", 15, 5, true),
+            hidden,
+        ];
+
+        let stats = UpgradeImpactStats::from_runs(&runs, 2);
+        let counts: Vec<(&str, i32, i32, bool)> = stats
+            .by_upgrades
+            .iter()
+            .map(|b| (b.label.as_str(), b.runs, b.wins, b.low_confidence))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("0-2", 2, 0, false),
+                ("3-5", 1, 1, true),
+                ("6-9", 0, 0, true),
+                ("10+", 2, 2, false)
+            ]
+        );
+        assert_eq!(stats.by_upgrades[0].avg_floor, 20.0);
+
+        // 0%, 10%, 20%, 50% and 75% of a 20 card deck
+        let ratios: Vec<(&str, i32)> = stats
+            .by_upgrade_ratio
+            .iter()
+            .map(|b| (b.label.as_str(), b.runs))
+            .collect();
+        assert_eq!(
+            ratios,
+            [("<10%", 1), ("10-20%", 1), ("20-30%", 1), ("30%+", 2)]
+        );
+        assert_eq!(stats.runs, 5);
+        assert_eq!(stats.min_runs, 2);
+    }
+
+    #[test]
+    fn test_smithed_and_other_upgrades() {
+        let mut no_campfires = run_with("x", 3, 0, false);
+        no_campfires.campfires_rested = 0;
+        // Smithed cards later removed don't count beyond the final upgrades
+        let removed = run_with("y", 1, 3, false);
+        let runs = vec![run_with("a", 10, 6, true), removed, no_campfires];
+
+        let sources = UpgradeImpactStats::from_runs(&runs, 1).sources;
+        assert_eq!((sources.runs, sources.runs_without_data), (2, 1));
+        assert_eq!(
+            (sources.avg_smithed_wins, sources.avg_other_wins),
+            (6.0, 4.0)
+        );
+        assert_eq!(
+            (sources.avg_smithed_losses, sources.avg_other_losses),
+            (1.0, 0.0)
+        );
+
+        // Counts from damaged files saturate instead of overflowing
+        let mut extreme = run_with("z", i32::MAX, i32::MAX, false);
+        extreme.campfires_rested = i32::MAX;
+        assert_eq!(upgrade_sources(&extreme), Some((i32::MAX, 0)));
+        extreme.campfires_upgraded = i32::MIN;
+        assert_eq!(upgrade_sources(&extreme), Some((i32::MIN, i32::MAX)));
+    }
+}
@@ -0,0 +1,174 @@
+//! First load of the runs directory at startup
+//!
+//! A large runs directory takes a few seconds to parse. [`warm_up`] does the
+//! first load on a background thread before any request asks for runs,
+//! reporting progress after each batch of files. The shared cache is only
+//! locked for one batch at a time, so requests in the meantime get the runs
+//! parsed so far instead of waiting for the whole load.
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use utoipa::ToSchema;
+
+use super::{
+    dataset_summary, demo_mode, get_runs_path, record_load_report, scan_options, try_load_all_runs,
+    DatasetSummary, LoadProgress, RunCache, RunsPathNotFound, ScanOptions, RUN_CACHE,
+};
+
+/// Run files checked per batch
+pub const WARMUP_BATCH_FILES: usize = 50;
+
+/// Progress of the startup load
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum WarmupState {
+    /// No warm-up was started; the first request loads the runs
+    NotStarted,
+    /// The runs directory is being loaded
+    Loading {
+        /// Run files checked so far
+        files_processed: usize,
+        /// Run files found in the runs directory
+        files_total: usize,
+    },
+    /// The first load finished
+    Complete,
+}
+
+impl From<LoadProgress> for WarmupState {
+    fn from(progress: LoadProgress) -> Self {
+        WarmupState::Loading {
+            files_processed: progress.files_processed,
+            files_total: progress.files_total,
+        }
+    }
+}
+
+static WARMUP_STATE: RwLock<WarmupState> = RwLock::new(WarmupState::NotStarted);
+
+/// Get the progress of the startup load
+pub fn warmup_state() -> WarmupState {
+    *WARMUP_STATE.read()
+}
+
+/// Whether the startup load is still in progress
+pub fn warming_up() -> bool {
+    matches!(warmup_state(), WarmupState::Loading { .. })
+}
+
+fn set_warmup_state(state: WarmupState) {
+    *WARMUP_STATE.write() = state;
+}
+
+/// Load the runs directory in batches, calling `on_progress` after each
+///
+/// The last progress report has every file processed. Returns the summary of
+/// the loaded runs; demo mode skips straight to it.
+pub fn warm_up(
+    batch_files: usize,
+    mut on_progress: impl FnMut(LoadProgress),
+) -> Result<DatasetSummary, RunsPathNotFound> {
+    if !demo_mode() {
+        if let Some(runs_path) = get_runs_path() {
+            set_warmup_state(WarmupState::Loading {
+                files_processed: 0,
+                files_total: 0,
+            });
+            load_in_batches(
+                &runs_path,
+                scan_options(),
+                batch_files.max(1),
+                &mut on_progress,
+            );
+        }
+    }
+    set_warmup_state(WarmupState::Complete);
+    try_load_all_runs().map(|runs| dataset_summary(&runs))
+}
+
+fn load_in_batches(
+    runs_path: &Path,
+    options: ScanOptions,
+    batch_files: usize,
+    on_progress: &mut impl FnMut(LoadProgress),
+) {
+    let mut progress = {
+        let mut guard = RUN_CACHE.write();
+        if guard.as_ref().map(|c| (c.root(), c.options())) != Some((runs_path, options)) {
+            *guard = Some(RunCache::with_options(runs_path, options));
+        }
+        guard
+            .as_mut()
+            .expect("run cache was just initialized")
+            .begin_refresh()
+    };
+    set_warmup_state(progress.into());
+    on_progress(progress);
+
+    loop {
+        let (report, next) = {
+            let mut guard = RUN_CACHE.write();
+            // A request for another directory or scan options replaces the
+            // cache; the warm-up has nothing left to do then
+            let Some(cache) = guard
+                .as_mut()
+                .filter(|c| (c.root(), c.options()) == (runs_path, options))
+            else {
+                return;
+            };
+            let report = cache.continue_refresh(batch_files);
+            (report, cache.progress())
+        };
+        if let Some(report) = report {
+            record_load_report(report);
+            on_progress(LoadProgress {
+                files_processed: progress.files_total,
+                ..progress
+            });
+            return;
+        }
+        if let Some(next) = next {
+            progress = next;
+            set_warmup_state(progress.into());
+            on_progress(progress);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::{load_all_runs, Character, RunFileBuilder, TestRunsDir};
+
+    #[test]
+    fn test_warm_up_reports_monotonic_progress() {
+        let runs_dir = TestRunsDir::empty();
+        let characters = Character::all();
+        for i in 0..300 {
+            runs_dir.add(
+                &RunFileBuilder::new(&format!("run-{i}"))
+                    .character(characters[i % characters.len()])
+                    .victory(i % 3 == 0),
+            );
+        }
+
+        let mut events = Vec::new();
+        let summary = warm_up(25, |progress| {
+            // Runs parsed so far are served while the load goes on
+            assert!(load_all_runs().len() <= progress.files_processed);
+            events.push(progress);
+        })
+        .unwrap();
+
+        assert!(events.len() >= 300 / 25);
+        assert!(events.iter().all(|p| p.files_total == 300));
+        assert!(events
+            .windows(2)
+            .all(|w| w[0].files_processed <= w[1].files_processed));
+        assert_eq!(events.last().unwrap().files_processed, 300);
+        assert_eq!(summary.total_runs, 300);
+        assert_eq!(summary.wins, 100);
+        assert_eq!(warmup_state(), WarmupState::Complete);
+    }
+}
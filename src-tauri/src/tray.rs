@@ -0,0 +1,173 @@
+//! Tray icon summary and controls
+//!
+//! While the window is minimized, the tray icon's tooltip and menu show the
+//! overall win rate and how the latest run ended. This module decides what
+//! the tray shows and what its menu items do; `lib.rs` only builds the Tauri
+//! menu from it. Nothing here assumes runs are loaded: without a runs
+//! directory or any runs, the tray says so.
+
+use crate::{config, refresh, sts};
+
+/// Menu item ids
+pub const OPEN_DASHBOARD: &str = "open-dashboard";
+pub const RELOAD_RUNS: &str = "reload-runs";
+pub const PAUSE_WATCHING: &str = "pause-watching";
+pub const QUIT: &str = "quit";
+
+/// Menu lines and tooltip shown until the runs are loaded
+pub const LOADING_WIN_RATE: &str = "Win rate: loading runs…";
+pub const LOADING_LAST_RUN: &str = "Last run: loading runs…";
+pub const LOADING_TOOLTIP: &str = "STS Stat Viewer\nLoading runs…";
+
+/// How the most recent run ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastRun {
+    /// Display name of the character
+    pub character: String,
+    pub victory: bool,
+    pub floor_reached: i32,
+    pub ascension_level: i32,
+}
+
+/// What the tray shows, from the runs counting towards statistics
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraySummary {
+    pub runs: usize,
+    pub wins: usize,
+    /// The run that ended last; runs without a timestamp count as oldest
+    pub last_run: Option<LastRun>,
+}
+
+impl TraySummary {
+    /// Summarize `runs`, ignoring hidden ones
+    pub fn from_runs(runs: &[sts::RunMetrics]) -> Self {
+        let visible: Vec<&sts::RunMetrics> = runs.iter().filter(|r| !r.hidden).collect();
+        let last_run = visible
+            .iter()
+            .rev()
+            .max_by_key(|r| r.timestamp)
+            .map(|run| LastRun {
+                character: run
+                    .character
+                    .known()
+                    .map_or(run.character.as_str(), |c| c.display_name())
+                    .to_string(),
+                victory: run.victory,
+                floor_reached: run.floor_reached,
+                ascension_level: run.ascension_level,
+            });
+        Self {
+            runs: visible.len(),
+            wins: visible.iter().filter(|r| r.victory).count(),
+            last_run,
+        }
+    }
+
+    /// Summarize the runs directory; empty when it can't be found
+    pub fn current() -> Self {
+        sts::try_load_all_runs()
+            .map(|runs| Self::from_runs(&runs))
+            .unwrap_or_default()
+    }
+
+    /// First menu line, e.g. "Win rate: 42.5% of 40 runs"
+    pub fn win_rate_label(&self) -> String {
+        if self.runs == 0 {
+            return "Win rate: no runs yet".to_string();
+        }
+        let rate = self.wins as f64 / self.runs as f64 * 100.0;
+        let plural = if self.runs == 1 { "" } else { "s" };
+        format!("Win rate: {:.1}% of {} run{}", rate, self.runs, plural)
+    }
+
+    /// Second menu line, e.g. "Last run: Silent died on floor 23 (A5)"
+    pub fn last_run_label(&self) -> String {
+        let Some(run) = &self.last_run else {
+            return "Last run: none yet".to_string();
+        };
+        let outcome = if run.victory {
+            format!("{} won", run.character)
+        } else {
+            format!("{} died on floor {}", run.character, run.floor_reached)
+        };
+        if run.ascension_level > 0 {
+            format!("Last run: {} (A{})", outcome, run.ascension_level)
+        } else {
+            format!("Last run: {}", outcome)
+        }
+    }
+
+    /// Tooltip of the tray icon
+    pub fn tooltip(&self) -> String {
+        format!(
+            "STS Stat Viewer\n{}\n{}",
+            self.win_rate_label(),
+            self.last_run_label()
+        )
+    }
+}
+
+/// Whether there is a watcher to pause: auto-refresh is configured
+pub fn can_pause_watching() -> bool {
+    config::current().auto_refresh_interval().is_some()
+}
+
+/// Stop auto-refresh until resumed, or resume it at the configured interval
+///
+/// Pausing doesn't change the saved setting, so the next start watches again.
+pub fn set_watching_paused(paused: bool) {
+    let interval = config::current().auto_refresh_interval();
+    refresh::set_auto_refresh(interval.filter(|_| !paused));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::{Character, RunFileBuilder, RunsDirFixture};
+
+    fn run(play_id: &str, timestamp: Option<i64>, victory: bool) -> sts::RunMetrics {
+        let fixture = RunsDirFixture::new();
+        fixture.add(
+            &RunFileBuilder::new(play_id)
+                .character(Character::TheSilent)
+                .victory(victory)
+                .floor_reached(23)
+                .ascension(5),
+        );
+        let mut run = fixture.load_sorted().remove(0);
+        run.timestamp = timestamp;
+        run
+    }
+
+    #[test]
+    fn test_summary_without_runs() {
+        let summary = TraySummary::from_runs(&[]);
+        assert_eq!(summary, TraySummary::default());
+        assert_eq!(summary.win_rate_label(), "Win rate: no runs yet");
+        assert_eq!(summary.last_run_label(), "Last run: none yet");
+        assert!(summary.tooltip().starts_with("STS Stat Viewer\n"));
+    }
+
+    #[test]
+    fn test_summary_of_runs() {
+        let mut hidden = run("hidden", Some(3_000), true);
+        hidden.hidden = true;
+        let runs = vec![
+            run("undated", None, true),
+            run("latest", Some(2_000), false),
+            run("older", Some(1_000), true),
+            hidden,
+        ];
+        let summary = TraySummary::from_runs(&runs);
+        assert_eq!((summary.runs, summary.wins), (3, 2));
+        assert_eq!(summary.win_rate_label(), "Win rate: 66.7% of 3 runs");
+        assert_eq!(
+            summary.last_run_label(),
+            "Last run: Silent died on floor 23 (A5)"
+        );
+
+        let single = TraySummary::from_runs(&runs[..1]);
+        assert_eq!(single.win_rate_label(), "Win rate: 100.0% of 1 run");
+        assert_eq!(single.last_run_label(), "Last run: Silent won (A5)");
+    }
+}
@@ -0,0 +1,253 @@
+//! Fail-fast startup checks for headless deployments
+//!
+//! With `serve --validate` or `STS_VIEWER_REQUIRE_RUNS=1`, the server loads
+//! the runs directory before it starts listening and exits with
+//! [`VALIDATION_EXIT_CODE`] when there is nothing sensible to serve: no runs
+//! directory, a configured directory that doesn't exist, no run files, or
+//! more failed files than `max_failed_file_fraction` allows. The reason is
+//! written to stderr as one line of JSON so container tooling can read it.
+//! A `max_failed_file_fraction` outside 0.0 to 1.0 fails validation before
+//! anything is loaded.
+//!
+//! The desktop app never validates; it shows a setup prompt instead.
+
+use serde::Serialize;
+
+use crate::sts::{self, LoadIssue, LoadReport, RunsPathResolution};
+
+/// Environment variable that turns validation on, like `serve --validate`
+pub const REQUIRE_RUNS_ENV_VAR: &str = "STS_VIEWER_REQUIRE_RUNS";
+
+/// Exit code when validation fails (2 is taken by invalid arguments)
+pub const VALIDATION_EXIT_CODE: i32 = 3;
+
+/// Default for the `max_failed_file_fraction` setting
+pub const DEFAULT_MAX_FAILED_FILE_FRACTION: f64 = 0.25;
+
+/// Failed files listed in a validation error; the rest are only counted
+const MAX_REPORTED_ISSUES: usize = 10;
+
+/// Whether [`REQUIRE_RUNS_ENV_VAR`] asks for validation
+pub fn require_runs_env_enabled() -> bool {
+    std::env::var(REQUIRE_RUNS_ENV_VAR)
+        .is_ok_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
+/// Why the runs directory can't be served, as written to stderr
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationFailure {
+    /// Machine-readable reason: `INVALID_MAX_FAILED_FILE_FRACTION`,
+    /// `RUNS_PATH_NOT_FOUND`, `RUNS_PATH_MISSING`, `NO_RUN_FILES` or
+    /// `TOO_MANY_PARSE_FAILURES`
+    pub code: &'static str,
+    /// Human-readable description
+    pub message: String,
+    /// The runs directory that was checked, if one was resolved
+    pub runs_path: Option<String>,
+    /// Run files found
+    pub files_scanned: i32,
+    /// Run files that failed to parse
+    pub parse_failures: i32,
+    /// The first files that failed, with their errors
+    pub issues: Vec<LoadIssue>,
+}
+
+impl ValidationFailure {
+    fn new(code: &'static str, message: String, runs_path: Option<String>) -> Self {
+        Self {
+            code,
+            message,
+            runs_path,
+            files_scanned: 0,
+            parse_failures: 0,
+            issues: Vec::new(),
+        }
+    }
+}
+
+/// Reject a `max_failed_file_fraction` that is NaN or outside 0.0 to 1.0
+pub fn check_max_failed_file_fraction(fraction: f64) -> Result<(), ValidationFailure> {
+    if (0.0..=1.0).contains(&fraction) {
+        return Ok(());
+    }
+    Err(ValidationFailure::new(
+        "INVALID_MAX_FAILED_FILE_FRACTION",
+        format!(
+            "max_failed_file_fraction must be between 0.0 and 1.0, got {}",
+            fraction
+        ),
+        None,
+    ))
+}
+
+/// Reject a runs path that is unresolved, or configured but missing
+///
+/// A missing custom path fails even when auto-detection found another
+/// directory, since serving that one would hide the misconfiguration.
+pub fn check_runs_path(resolution: &RunsPathResolution) -> Result<String, ValidationFailure> {
+    match resolution {
+        RunsPathResolution::CustomValid { path } | RunsPathResolution::AutoDetected { path } => {
+            Ok(path.clone())
+        }
+        RunsPathResolution::CustomMissing { custom_path, .. } => Err(ValidationFailure::new(
+            "RUNS_PATH_MISSING",
+            format!("Configured runs directory does not exist: {}", custom_path),
+            Some(custom_path.clone()),
+        )),
+        RunsPathResolution::Unresolved => Err(ValidationFailure::new(
+            "RUNS_PATH_NOT_FOUND",
+            "No runs directory configured or detected".to_string(),
+            None,
+        )),
+    }
+}
+
+/// Reject a load that found no run files or too many broken ones
+pub fn check_load_report(
+    report: &LoadReport,
+    max_failed_file_fraction: f64,
+) -> Result<(), ValidationFailure> {
+    let failure = |code, message| ValidationFailure {
+        code,
+        message,
+        runs_path: report.runs_path.clone(),
+        files_scanned: report.files_scanned,
+        parse_failures: report.parse_failures,
+        issues: report
+            .issues
+            .iter()
+            .take(MAX_REPORTED_ISSUES)
+            .cloned()
+            .collect(),
+    };
+    if report.files_scanned == 0 {
+        return Err(failure(
+            "NO_RUN_FILES",
+            "The runs directory holds no run files".to_string(),
+        ));
+    }
+    let failed_fraction = report.parse_failures as f64 / report.files_scanned as f64;
+    if failed_fraction > max_failed_file_fraction {
+        return Err(failure(
+            "TOO_MANY_PARSE_FAILURES",
+            format!(
+                "{} of {} run files failed to parse, more than the allowed {:.0}%",
+                report.parse_failures,
+                report.files_scanned,
+                max_failed_file_fraction * 100.0
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Resolve and load the runs directory, failing if it can't be served
+///
+/// The runs are loaded into the shared cache, so the server starts warm.
+/// Demo mode has no runs directory to check and always passes.
+pub fn validate_runs(max_failed_file_fraction: f64) -> Result<LoadReport, ValidationFailure> {
+    check_max_failed_file_fraction(max_failed_file_fraction)?;
+    if sts::demo_mode() {
+        return Ok(LoadReport::default());
+    }
+    let runs_path = check_runs_path(&sts::get_runs_path_info())?;
+    let _ = sts::warm_up(sts::WARMUP_BATCH_FILES, |_| {});
+    let report = sts::last_load_report().ok_or_else(|| {
+        ValidationFailure::new(
+            "RUNS_PATH_NOT_FOUND",
+            "The runs directory could not be loaded".to_string(),
+            Some(runs_path),
+        )
+    })?;
+    check_load_report(&report, max_failed_file_fraction)?;
+    Ok(report)
+}
+
+/// Validate the runs directory, writing a failure to stderr as JSON
+///
+/// Returns the exit code: 0 when the runs can be served, otherwise
+/// [`VALIDATION_EXIT_CODE`].
+pub fn run_validation(max_failed_file_fraction: f64) -> i32 {
+    match validate_runs(max_failed_file_fraction) {
+        Ok(report) => {
+            tracing::info!(
+                "Validated {} runs from {} files ({} failed)",
+                report.runs_loaded,
+                report.files_scanned,
+                report.parse_failures
+            );
+            0
+        }
+        Err(failure) => {
+            let json = serde_json::to_string(&failure).unwrap_or_else(|_| failure.message.clone());
+            eprintln!("{}", json);
+            VALIDATION_EXIT_CODE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sts::TestRunsDir;
+
+    #[test]
+    fn test_valid_runs_dir() {
+        let _runs_dir = TestRunsDir::with_samples();
+        let report = validate_runs(DEFAULT_MAX_FAILED_FILE_FRACTION).unwrap();
+        assert!(report.runs_loaded > 0);
+        assert_eq!(run_validation(DEFAULT_MAX_FAILED_FILE_FRACTION), 0);
+    }
+
+    #[test]
+    fn test_empty_runs_dir() {
+        let _runs_dir = TestRunsDir::empty();
+        let failure = validate_runs(DEFAULT_MAX_FAILED_FILE_FRACTION).unwrap_err();
+        assert_eq!(failure.code, "NO_RUN_FILES");
+        assert!(failure.runs_path.is_some());
+        assert_eq!(
+            run_validation(DEFAULT_MAX_FAILED_FILE_FRACTION),
+            VALIDATION_EXIT_CODE
+        );
+    }
+
+    #[test]
+    fn test_too_many_parse_failures() {
+        let _runs_dir = TestRunsDir::with_samples();
+        // The samples include one corrupted file
+        let failure = validate_runs(0.0).unwrap_err();
+        assert_eq!(failure.code, "TOO_MANY_PARSE_FAILURES");
+        assert_eq!(failure.parse_failures, 1);
+        assert_eq!(failure.issues.len(), 1);
+
+        let json = serde_json::to_value(&failure).unwrap();
+        assert_eq!(json["code"], "TOO_MANY_PARSE_FAILURES");
+        assert!(json["files_scanned"].as_i64().unwrap() > 1);
+    }
+
+    #[test]
+    fn test_invalid_fraction() {
+        let _runs_dir = TestRunsDir::with_samples();
+        for fraction in [-0.1, 1.5, f64::NAN, f64::INFINITY] {
+            let failure = validate_runs(fraction).unwrap_err();
+            assert_eq!(
+                failure.code, "INVALID_MAX_FAILED_FILE_FRACTION",
+                "{fraction}"
+            );
+        }
+        assert!(check_max_failed_file_fraction(0.0).is_ok());
+        assert!(check_max_failed_file_fraction(1.0).is_ok());
+    }
+
+    #[test]
+    fn test_missing_or_unresolved_path() {
+        let runs_dir = TestRunsDir::empty();
+        sts::set_custom_runs_path(Some(runs_dir.path().join("not-mounted")));
+        let failure = validate_runs(DEFAULT_MAX_FAILED_FILE_FRACTION).unwrap_err();
+        assert_eq!(failure.code, "RUNS_PATH_MISSING");
+        assert!(failure.runs_path.unwrap().ends_with("not-mounted"));
+
+        let failure = check_runs_path(&RunsPathResolution::Unresolved).unwrap_err();
+        assert_eq!(failure.code, "RUNS_PATH_NOT_FOUND");
+    }
+}
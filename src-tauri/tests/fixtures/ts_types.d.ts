@@ -0,0 +1,38 @@
+// Generated from the STS Stat Viewer OpenAPI schemas. Do not edit by hand.
+
+/** API error response */
+export interface ApiError {
+  /** Error code */
+  code: string;
+  /** Additional error details */
+  details?: string | null;
+  /** Error message */
+  error: string;
+}
+
+/**
+ * Nested objects, maps and nullable references
+ * across several lines
+ */
+export interface Bundle {
+  counts: Record<string, number>;
+  items: (string | null)[];
+  latest?: null | HealthResponse;
+  nested: { a: boolean; "b-c"?: number };
+  wrapped?: HealthStatus;
+}
+
+/** Response from the health check endpoint */
+export interface HealthResponse {
+  /** Current health status */
+  status: HealthStatus;
+  /** Timestamp of the health check */
+  timestamp: string;
+  /** API version */
+  version?: string | null;
+}
+
+/** Health status of the API */
+export type HealthStatus = "healthy" | "unhealthy";
+
+export type Scores = number[];
@@ -5,6 +5,7 @@ import { browser } from '$app/environment';
 export const PLOT_FIELDS = [
   { key: 'floor_reached', label: 'Floor Reached' },
   { key: 'score', label: 'Score' },
+  { key: 'score_per_floor', label: 'Score per Floor' },
   { key: 'ascension_level', label: 'Ascension Level' },
   { key: 'deck_size', label: 'Deck Size' },
   { key: 'attack_count', label: 'Attack Cards' },
@@ -21,6 +22,9 @@ export const PLOT_FIELDS = [
   { key: 'cards_purchased', label: 'Cards Purchased' },
   { key: 'potions_used', label: 'Potions Used' },
   { key: 'total_damage_taken', label: 'Total Damage Taken' },
+  { key: 'damage_from_normals', label: 'Damage from Hallway Fights' },
+  { key: 'damage_from_elites', label: 'Damage from Elites' },
+  { key: 'damage_from_bosses', label: 'Damage from Bosses' },
   { key: 'max_hp_at_end', label: 'Max HP at End' },
 ] as const;
 
@@ -3,7 +3,16 @@ import { browser } from '$app/environment';
 
 const STORAGE_KEY = 'sts-settings';
 
+/** How the runs directory was resolved */
+export type RunsPathResolution =
+  | { state: 'custom_valid'; path: string }
+  | { state: 'custom_missing'; custom_path: string; fallback_path: string | null }
+  | { state: 'auto_detected'; path: string }
+  | { state: 'unresolved' };
+
 export interface RunsPathInfo {
+  /** Resolution state, including a missing custom path and its fallback */
+  resolution: RunsPathResolution;
   /** Currently active path (custom if set and valid, otherwise auto-detected) */
   current_path: string | null;
   /** Whether a custom path is currently set */